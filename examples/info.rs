@@ -1,6 +1,6 @@
 use anyhow::{Context, Result};
 use argh::FromArgs;
-use darknet_config::{DarknetConfig, DarknetModel, LayerBase, ModelBase};
+use darknet_config::{DarknetConfig, DarknetModel, ModelBase};
 use prettytable::{cell, row, Table};
 use std::path::PathBuf;
 
@@ -31,7 +31,7 @@ fn main() -> Result<()> {
         let mut table = Table::new();
         table.add_row(row![
             "index",
-            "kind",
+            "layer",
             "from indexes",
             "input shape",
             "output shape"
@@ -41,20 +41,9 @@ fn main() -> Result<()> {
         (0..num_layers).for_each(|index| {
             let layer = &model.layers[&index];
 
-            let kind = match layer {
-                LayerBase::Convolutional(_) => "conv",
-                LayerBase::Connected(_) => "connected",
-                LayerBase::BatchNorm(_) => "batch_norm",
-                LayerBase::Shortcut(_) => "shortcut",
-                LayerBase::MaxPool(_) => "max_pool",
-                LayerBase::Route(_) => "route",
-                LayerBase::UpSample(_) => "up_sample",
-                LayerBase::Yolo(_) => "yolo",
-            };
-
             table.add_row(row![
                 index,
-                kind,
+                layer.config(),
                 layer.from_indexes(),
                 layer.input_shape(),
                 layer.output_shape()
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use argh::FromArgs;
+use darknet_config::{dataset, serve::ModelMetadata, ModelBase};
+use std::{
+    io::{prelude::*, BufReader},
+    net::TcpListener,
+    path::PathBuf,
+};
+
+#[derive(Debug, Clone, FromArgs)]
+/// Serve model metadata of a darknet config over HTTP.
+struct Args {
+    #[argh(positional)]
+    /// configuration file
+    config_file: PathBuf,
+    #[argh(option)]
+    /// class names file
+    names_file: Option<PathBuf>,
+    #[argh(option, default = "String::from(\"127.0.0.1:8080\")")]
+    /// listening address
+    addr: String,
+}
+
+fn main() -> Result<()> {
+    pretty_env_logger::init();
+
+    let Args {
+        config_file,
+        names_file,
+        addr,
+    } = argh::from_env();
+
+    let model = ModelBase::from_config_file(config_file)?;
+    let class_names = names_file.map(dataset::load_names).transpose()?;
+    let body = ModelMetadata::new(&model, class_names)?.to_json()?;
+
+    let listener = TcpListener::bind(&addr)?;
+    println!("serving model metadata on http://{}/metadata", addr);
+
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+
+        let response = match request_line
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            ["GET", "/metadata", ..] => format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+            _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_owned(),
+        };
+        stream.write_all(response.as_bytes())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use crate::{
+    common::*,
+    config::{CompoundNetConfig, DarknetConfig, LayerConfig, Policy, Shape},
+    dataset::DatasetStats,
+    validate::{self, Severity},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Advice {
+    pub severity: Severity,
+    pub layer_index: Option<usize>,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl Advice {
+    fn new(
+        severity: Severity,
+        layer_index: Option<usize>,
+        message: impl Display,
+        suggestion: Option<String>,
+    ) -> Self {
+        Self {
+            severity,
+            layer_index,
+            message: message.to_string(),
+            suggestion,
+        }
+    }
+}
+
+impl Display for Advice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.layer_index {
+            Some(index) => write!(f, "{}: layer {}: {}", self.severity, index, self.message)?,
+            None => write!(f, "{}: {}", self.severity, self.message)?,
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (suggestion: {})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn advise(config: &DarknetConfig, stats: Option<&DatasetStats>) -> Vec<Advice> {
+    let mut advices: Vec<_> = validate::validate(config)
+        .into_iter()
+        .map(|diagnostic| {
+            Advice::new(
+                diagnostic.severity,
+                diagnostic.layer_index,
+                diagnostic.message,
+                None,
+            )
+        })
+        .collect();
+
+    advise_net(&config.net, &mut advices);
+    advise_layers(config, &mut advices);
+    if let Some(stats) = stats {
+        advise_dataset(config, stats, &mut advices);
+    }
+
+    advices
+}
+
+fn recommended_steps(max_batches: u64) -> String {
+    format!("steps={},{}", max_batches * 8 / 10, max_batches * 9 / 10)
+}
+
+fn advise_net(net: &CompoundNetConfig, advices: &mut Vec<Advice>) {
+    let CompoundNetConfig {
+        max_batches,
+        batch,
+        subdivisions,
+        burn_in,
+        ref policy,
+        ..
+    } = *net;
+
+    if subdivisions == 0 || batch % subdivisions != 0 {
+        let suggestion = (1..=batch)
+            .rev()
+            .find(|&divisor| batch % divisor == 0 && divisor <= subdivisions.max(1))
+            .map(|divisor| format!("subdivisions={}", divisor));
+        advices.push(Advice::new(
+            Severity::Error,
+            None,
+            format!(
+                "batch ({}) is not divisible by subdivisions ({})",
+                batch, subdivisions
+            ),
+            suggestion,
+        ));
+    }
+
+    if burn_in > max_batches {
+        advices.push(Advice::new(
+            Severity::Warning,
+            None,
+            format!(
+                "burn_in ({}) is longer than max_batches ({}), the learning rate never leaves warmup",
+                burn_in, max_batches
+            ),
+            Some(format!("burn_in={}", (max_batches / 10).min(1000))),
+        ));
+    }
+
+    let steps = match policy {
+        Policy::Steps { steps, .. } | Policy::SgdrCustom { steps, .. } => Some(steps),
+        _ => None,
+    };
+    if let Some(steps) = steps {
+        if steps.iter().any(|&step| step > max_batches) {
+            advices.push(Advice::new(
+                Severity::Warning,
+                None,
+                format!(
+                    "some steps {:?} exceed max_batches ({}) and will never be reached",
+                    steps, max_batches
+                ),
+                Some(recommended_steps(max_batches)),
+            ));
+        }
+
+        if steps.windows(2).any(|pair| pair[0] > pair[1]) {
+            advices.push(Advice::new(
+                Severity::Warning,
+                None,
+                format!("steps {:?} are not in increasing order", steps),
+                Some(recommended_steps(max_batches)),
+            ));
+        }
+    }
+}
+
+fn advise_layers(config: &DarknetConfig, advices: &mut Vec<Advice>) {
+    let is_square = match config.net.input_size {
+        Shape::Hwc([h, w, _c]) => h == w,
+        Shape::Flat(_) => true,
+    };
+
+    config
+        .layers
+        .iter()
+        .enumerate()
+        .for_each(|(layer_index, layer)| {
+            if let LayerConfig::Yolo(yolo) = layer {
+                if yolo.random.raw() != 0.0 && !is_square {
+                    advices.push(Advice::new(
+                        Severity::Warning,
+                        Some(layer_index),
+                        "random resizing is enabled with non-square network input",
+                        Some("random=0".into()),
+                    ));
+                }
+            }
+        });
+}
+
+fn advise_dataset(config: &DarknetConfig, stats: &DatasetStats, advices: &mut Vec<Advice>) {
+    let CompoundNetConfig {
+        max_batches,
+        classes,
+        ..
+    } = config.net;
+
+    if stats.num_classes != classes {
+        advices.push(Advice::new(
+            Severity::Error,
+            None,
+            format!(
+                "the dataset has {} classes but the config has {} classes",
+                stats.num_classes, classes
+            ),
+            Some(format!("classes={}", stats.num_classes)),
+        ));
+    }
+
+    if !stats.missing_labels.is_empty() {
+        advices.push(Advice::new(
+            Severity::Warning,
+            None,
+            format!(
+                "{} training images have no label files",
+                stats.missing_labels.len()
+            ),
+            None,
+        ));
+    }
+
+    // darknet recommends classes * 2000 iterations, but not less than the
+    // number of training images and not less than 6000
+    let recommended_max_batches = (stats.num_classes * 2000)
+        .max(stats.num_images as u64)
+        .max(6000);
+    if max_batches < recommended_max_batches {
+        advices.push(Advice::new(
+            Severity::Warning,
+            None,
+            format!(
+                "max_batches ({}) is less than the recommended {}",
+                max_batches, recommended_max_batches
+            ),
+            Some(format!(
+                "max_batches={}, {}",
+                recommended_max_batches,
+                recommended_steps(recommended_max_batches)
+            )),
+        ));
+    }
+
+    // suggest counters_per_class on imbalanced datasets
+    let max_count = stats.class_frequencies.iter().cloned().max().unwrap_or(0);
+    let min_count = stats.class_frequencies.iter().cloned().min().unwrap_or(0);
+    let is_imbalanced = min_count == 0 || max_count / min_count >= 10;
+
+    if max_count > 0 && is_imbalanced {
+        let counters = stats
+            .suggest_counters_per_class()
+            .iter()
+            .map(|count| count.to_string())
+            .join(",");
+
+        config
+            .layers
+            .iter()
+            .enumerate()
+            .filter_map(|(layer_index, layer)| match layer {
+                LayerConfig::Yolo(yolo) if yolo.counters_per_class.is_none() => Some(layer_index),
+                _ => None,
+            })
+            .for_each(|layer_index| {
+                advices.push(Advice::new(
+                    Severity::Warning,
+                    Some(layer_index),
+                    format!(
+                        "the dataset is imbalanced (max {} vs min {} objects per class)",
+                        max_count, min_count
+                    ),
+                    Some(format!("counters_per_class={}", counters)),
+                ));
+            });
+    }
+}
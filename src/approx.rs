@@ -0,0 +1,154 @@
+use crate::{common::*, darknet::DarknetModel, parity::Tolerance, weights_layout::named_buffers};
+
+// the differences of two models with the same layout, e.g. the weights before
+// and after a fp16 or ONNX round trip
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightsComparison {
+    pub tolerance: Tolerance,
+    // layers without buffers are omitted
+    pub layers: Vec<LayerWeightsDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerWeightsDiff {
+    pub layer_index: usize,
+    pub buffers: Vec<BufferDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BufferDiff {
+    pub name: String,
+    pub len: usize,
+    // infinite if either side has a NaN
+    pub max_error: f32,
+    // the number of values out of tolerance
+    pub mismatches: usize,
+}
+
+impl BufferDiff {
+    fn new(name: &str, lhs: &[f32], rhs: &[f32], tolerance: Tolerance) -> Self {
+        let (max_error, mismatches) =
+            izip!(lhs, rhs).fold((0f32, 0), |(max_error, mismatches), (&lhs, &rhs)| {
+                let error = (lhs - rhs).abs();
+                let error = if error.is_nan() { f32::INFINITY } else { error };
+                let mismatch = !tolerance.accepts(lhs, rhs) as usize;
+                (max_error.max(error), mismatches + mismatch)
+            });
+        Self {
+            name: name.to_owned(),
+            len: lhs.len(),
+            max_error,
+            mismatches,
+        }
+    }
+}
+
+impl LayerWeightsDiff {
+    pub fn passed(&self) -> bool {
+        self.buffers.iter().all(|buffer| buffer.mismatches == 0)
+    }
+
+    pub fn max_error(&self) -> f32 {
+        self.buffers
+            .iter()
+            .map(|buffer| buffer.max_error)
+            .fold(0.0, f32::max)
+    }
+}
+
+impl WeightsComparison {
+    pub fn passed(&self) -> bool {
+        self.layers.iter().all(|layer| layer.passed())
+    }
+
+    pub fn max_error(&self) -> f32 {
+        self.layers
+            .iter()
+            .map(|layer| layer.max_error())
+            .fold(0.0, f32::max)
+    }
+
+    // the layers with values out of tolerance
+    pub fn failed_layers(&self) -> impl Iterator<Item = &LayerWeightsDiff> {
+        self.layers.iter().filter(|layer| !layer.passed())
+    }
+
+    pub fn ensure_passed(&self) -> Result<()> {
+        let failures: Vec<_> = self
+            .failed_layers()
+            .flat_map(|layer| {
+                layer
+                    .buffers
+                    .iter()
+                    .filter(|buffer| buffer.mismatches > 0)
+                    .map(move |buffer| {
+                        format!(
+                            "layer {} {}: {} of {} values, max error {:e}",
+                            layer.layer_index,
+                            buffer.name,
+                            buffer.mismatches,
+                            buffer.len,
+                            buffer.max_error
+                        )
+                    })
+            })
+            .collect();
+        ensure!(
+            failures.is_empty(),
+            "weights differ beyond tolerance:\n{}",
+            failures.join("\n")
+        );
+        Ok(())
+    }
+}
+
+impl DarknetModel {
+    // compare the weights value by value, where lhs is taken as the expected side of
+    // the relative tolerance. fails if the models differ in their buffer layout.
+    pub fn approx_eq(&self, other: &Self, tolerance: Tolerance) -> Result<WeightsComparison> {
+        ensure!(
+            self.layers.len() == other.layers.len(),
+            "the models have {} and {} layers",
+            self.layers.len(),
+            other.layers.len()
+        );
+
+        let layers: Vec<LayerWeightsDiff> = self
+            .layers
+            .iter()
+            .map(|(&layer_index, lhs)| -> Result<_> {
+                let lhs = named_buffers(lhs);
+                let rhs = named_buffers(&other.layers[&layer_index]);
+                let lhs_layout: Vec<_> = lhs
+                    .iter()
+                    .map(|(name, values)| (*name, values.len()))
+                    .collect();
+                let rhs_layout: Vec<_> = rhs
+                    .iter()
+                    .map(|(name, values)| (*name, values.len()))
+                    .collect();
+                ensure!(
+                    lhs_layout == rhs_layout,
+                    "layer {}: the buffers differ, {:?} and {:?}",
+                    layer_index,
+                    lhs_layout,
+                    rhs_layout
+                );
+
+                let buffers: Vec<_> = izip!(lhs, rhs)
+                    .map(|((name, lhs), (_, rhs))| BufferDiff::new(name, lhs, rhs, tolerance))
+                    .collect();
+                Ok(LayerWeightsDiff {
+                    layer_index,
+                    buffers,
+                })
+            })
+            .try_collect()?;
+        let layers = layers
+            .into_iter()
+            .filter(|layer| !layer.buffers.is_empty())
+            .collect();
+
+        Ok(WeightsComparison { tolerance, layers })
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{common::*, config::CompoundNetConfig, preprocess::ChwImage};
+use image::{DynamicImage, Rgb, RgbImage};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// the photometric distortion of one training image
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Photometric {
+    // added to the hue in [0, 1), wrapping around
+    pub hue: f32,
+    // multipliers of the saturation and value channels
+    pub saturation: f32,
+    pub exposure: f32,
+}
+
+impl Photometric {
+    pub const IDENTITY: Self = Self {
+        hue: 0.0,
+        saturation: 1.0,
+        exposure: 1.0,
+    };
+
+    // apply the distortion like distort_image() in darknet. the image is
+    // converted to RGB and the values are truncated back to bytes.
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let mut image = ChwImage::from_dynamic_image(image, 3).unwrap();
+        let plane = image.width * image.height;
+
+        for index in 0..plane {
+            let rgb = [
+                image.data[index],
+                image.data[plane + index],
+                image.data[2 * plane + index],
+            ];
+            let [h, s, v] = rgb_to_hsv(rgb);
+            let s = s * self.saturation;
+            let v = v * self.exposure;
+            let mut h = h + self.hue;
+            if h > 1.0 {
+                h -= 1.0;
+            }
+            if h < 0.0 {
+                h += 1.0;
+            }
+
+            let [r, g, b] = hsv_to_rgb([h, s, v]);
+            image.data[index] = r.clamp(0.0, 1.0);
+            image.data[plane + index] = g.clamp(0.0, 1.0);
+            image.data[2 * plane + index] = b.clamp(0.0, 1.0);
+        }
+
+        let ChwImage {
+            width,
+            height,
+            ref data,
+            ..
+        } = image;
+        let pixel = |x: u32, y: u32, c: usize| {
+            (data[c * plane + y as usize * width + x as usize] * 255.0) as u8
+        };
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width as u32, height as u32, |x, y| {
+            Rgb([pixel(x, y, 0), pixel(x, y, 1), pixel(x, y, 2)])
+        }))
+    }
+}
+
+impl CompoundNetConfig {
+    // draw the distortion from the hue, saturation and exposure options like
+    // random_distort_image() in darknet
+    pub fn sample_photometric<R>(&self, rng: &mut R) -> Photometric
+    where
+        R: Rng,
+    {
+        let hue = self.hue.raw() as f32;
+        Photometric {
+            hue: rand_uniform(rng, -hue, hue),
+            saturation: rand_scale(rng, self.saturation.raw() as f32),
+            exposure: rand_scale(rng, self.exposure.raw() as f32),
+        }
+    }
+
+    // sample augmented copies of the image to preview the options, the same seed
+    // always yields the same images
+    pub fn augment_preview(
+        &self,
+        image: &DynamicImage,
+        count: usize,
+        seed: u64,
+    ) -> Vec<(Photometric, DynamicImage)> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        (0..count)
+            .map(|_| {
+                let photometric = self.sample_photometric(&mut rng);
+                (photometric, photometric.apply(image))
+            })
+            .collect()
+    }
+}
+
+// the range is swapped if reversed, see rand_uniform() in darknet
+fn rand_uniform<R: Rng>(rng: &mut R, min: f32, max: f32) -> f32 {
+    let (min, max) = if max < min { (max, min) } else { (min, max) };
+    rng.gen::<f32>() * (max - min) + min
+}
+
+// a scale in [1, s] or its reciprocal with equal chance
+fn rand_scale<R: Rng>(rng: &mut R, s: f32) -> f32 {
+    let scale = rand_uniform(rng, 1.0, s);
+    if rng.gen::<bool>() {
+        scale
+    } else {
+        1.0 / scale
+    }
+}
+
+// hue in [0, 1), see rgb_to_hsv() in darknet. gray pixels get zero hue, which
+// is ignored on the way back as their saturation stays zero.
+fn rgb_to_hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    if max == 0.0 || delta == 0.0 {
+        return [0.0, 0.0, max];
+    }
+    let s = delta / max;
+    let mut h = if r == max {
+        (g - b) / delta
+    } else if g == max {
+        2.0 + (b - r) / delta
+    } else {
+        4.0 + (r - g) / delta
+    };
+    if h < 0.0 {
+        h += 6.0;
+    }
+    [h / 6.0, s, max]
+}
+
+fn hsv_to_rgb([h, s, v]: [f32; 3]) -> [f32; 3] {
+    if s == 0.0 {
+        return [v, v, v];
+    }
+    let h = h * 6.0;
+    let index = h.floor();
+    let f = h - index;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - s * f);
+    let t = v * (1.0 - s * (1.0 - f));
+
+    match index as i64 {
+        0 => [v, t, p],
+        1 => [q, v, p],
+        2 => [p, v, t],
+        3 => [p, q, v],
+        4 => [t, p, v],
+        _ => [v, p, q],
+    }
+}
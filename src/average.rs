@@ -0,0 +1,85 @@
+use crate::{
+    common::*,
+    darknet::{DarknetModel, Layer},
+};
+
+impl DarknetModel {
+    // element-wise mean of the weights of checkpoints sharing the same architecture.
+    // the rolling statistics of batch normalization are averaged as well, consider
+    // recomputing them on training data for stochastic weight averaging.
+    pub fn average(models: &[Self]) -> Result<Self> {
+        let (first, rest) = models
+            .split_first()
+            .ok_or_else(|| format_err!("at least one checkpoint is required"))?;
+
+        // the checkpoints must agree on every layer and buffer size
+        let buffer_lens = |layer: &Layer| -> Vec<usize> {
+            layer
+                .buffers()
+                .iter()
+                .map(|(_, values)| values.len())
+                .collect()
+        };
+        rest.iter()
+            .enumerate()
+            .try_for_each(|(index, model)| -> Result<_> {
+                let checkpoint_index = index + 1;
+                ensure!(
+                    model.base.net.input_size == first.base.net.input_size,
+                    "the input shape of checkpoint {} differs from checkpoint 0",
+                    checkpoint_index
+                );
+                ensure!(
+                    model.layers.len() == first.layers.len(),
+                    "checkpoint {} has {} layers, but checkpoint 0 has {} layers",
+                    checkpoint_index,
+                    model.layers.len(),
+                    first.layers.len()
+                );
+
+                first
+                    .layers
+                    .iter()
+                    .try_for_each(|(layer_index, layer)| -> Result<_> {
+                        let other = &model.layers[layer_index];
+                        ensure!(
+                            model.base.layers[layer_index].config()
+                                == first.base.layers[layer_index].config()
+                                && buffer_lens(other) == buffer_lens(layer),
+                            "layer {} of checkpoint {} differs from checkpoint 0",
+                            layer_index,
+                            checkpoint_index
+                        );
+                        Ok(())
+                    })
+            })?;
+
+        let num_models = models.len() as f64;
+        let mut averaged = first.clone();
+        averaged.base.seen = models.iter().map(|model| model.base.seen).max().unwrap();
+        averaged.base.cur_iteration = averaged.base.net.iteration(averaged.base.seen);
+
+        for (layer_index, layer) in averaged.layers.iter_mut() {
+            let others: Vec<_> = rest
+                .iter()
+                .map(|model| model.layers[layer_index].buffers())
+                .collect();
+
+            for (buffer_index, (_, values)) in layer.buffers_mut().into_iter().enumerate() {
+                values
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(value_index, value)| {
+                        // accumulate in f64 to keep the precision over many checkpoints
+                        let sum: f64 = others
+                            .iter()
+                            .map(|buffers| buffers[buffer_index].1[value_index] as f64)
+                            .sum();
+                        *value = ((*value as f64 + sum) / num_models) as f32;
+                    });
+            }
+        }
+
+        Ok(averaged)
+    }
+}
@@ -0,0 +1,289 @@
+use crate::{
+    common::*,
+    config::Shape,
+    export::{blob_name, layer_name},
+    model::{LayerBase, LayerPosition, ModelBase},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TensorLayout {
+    #[serde(rename = "nchw")]
+    Nchw,
+    // inputs of models starting with a connected layer
+    #[serde(rename = "nc")]
+    Nc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DataType {
+    #[serde(rename = "float32")]
+    Float32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ColorFormat {
+    #[serde(rename = "gray")]
+    Gray,
+    #[serde(rename = "rgb")]
+    Rgb,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum ResizeMode {
+    // bilinear resize to the input size, ignoring the aspect ratio
+    #[serde(rename = "stretch")]
+    Stretch,
+    // keep the aspect ratio and center the image on a constant background
+    #[serde(rename = "letterbox")]
+    Letterbox { pad_value: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputBinding {
+    pub name: String,
+    pub layout: TensorLayout,
+    pub dtype: DataType,
+    // dims are in CHW order without the batch dimension
+    pub dims: Vec<u64>,
+    // pixel values are scaled to this range
+    pub value_range: [f32; 2],
+    // None if the channels are not image colors
+    pub color_format: Option<ColorFormat>,
+    pub resize: ResizeMode,
+}
+
+// the anchors and strides of yolo heads are given like in HeadInfo
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum DecodeParams {
+    // the tensor holds raw head outputs in [anchor, 4 + 1 + classes, h, w] order,
+    // the logistic activation and box decoding are left to the consumer
+    #[serde(rename = "yolo")]
+    Yolo {
+        classes: u64,
+        anchors: Vec<(u64, u64)>,
+        stride: [u64; 2],
+        scale_x_y: f64,
+        new_coords: bool,
+    },
+    // the tensor holds class probabilities of a classifier, normalized within
+    // each of the groups of the flattened output
+    #[serde(rename = "softmax")]
+    Softmax { groups: u64, spatial: bool },
+    // the tensor holds raw YOLOv2 outputs in [anchor, coords + 1 + classes, h, w]
+    // order, decoded like yolo heads except that the classes are normalized by
+    // softmax if enabled
+    #[serde(rename = "region")]
+    Region {
+        classes: u64,
+        coords: u64,
+        // anchors in grid cells
+        anchors: Vec<(f64, f64)>,
+        softmax: bool,
+    },
+    // the tensor holds raw Gaussian YOLOv3 outputs in [anchor, 8 + 1 + classes,
+    // h, w] order, where each box coordinate is followed by its sigma
+    #[serde(rename = "gaussian_yolo")]
+    GaussianYolo {
+        classes: u64,
+        anchors: Vec<(u64, u64)>,
+        stride: [u64; 2],
+        scale_x_y: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputBinding {
+    pub name: String,
+    pub layer_index: usize,
+    // dims are in CHW order without the batch dimension
+    pub dims: Vec<u64>,
+    pub decode: DecodeParams,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HeadKind {
+    #[serde(rename = "yolo")]
+    Yolo,
+    #[serde(rename = "gaussian_yolo")]
+    GaussianYolo,
+    #[serde(rename = "region")]
+    Region,
+}
+
+// the decoding parameters of one detection head at the configured input size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeadInfo {
+    pub layer_index: usize,
+    pub kind: HeadKind,
+    // input pixels per grid cell in [y, x] order
+    pub stride: [u64; 2],
+    // grid cells in [height, width] order
+    pub grid_size: [u64; 2],
+    // anchors of this head in input pixels, region anchors are given in grid
+    // cells and rounded to pixels here
+    pub anchors: Vec<(u64, u64)>,
+    pub classes: u64,
+    pub scale_x_y: f64,
+}
+
+impl ModelBase {
+    // the detection heads in layer order
+    pub fn heads(&self) -> Vec<HeadInfo> {
+        self.layers
+            .iter()
+            .sorted_by_key(|(layer_index, _)| **layer_index)
+            .filter_map(|(&layer_index, layer)| {
+                let (kind, yolo, inout_shape) = match layer {
+                    LayerBase::Yolo(yolo) => (HeadKind::Yolo, &yolo.config, yolo.inout_shape),
+                    LayerBase::GaussianYolo(yolo) => {
+                        (HeadKind::GaussianYolo, &yolo.config.yolo, yolo.inout_shape)
+                    }
+                    LayerBase::Region(region) => {
+                        let [out_h, out_w, _out_c] = region.inout_shape;
+                        let stride = self.head_stride(region.inout_shape);
+                        let anchors = region
+                            .config
+                            .anchors
+                            .iter()
+                            .flatten()
+                            .map(|(w, h)| {
+                                let w = (w.raw() * stride[1] as f64).round() as u64;
+                                let h = (h.raw() * stride[0] as f64).round() as u64;
+                                (w, h)
+                            })
+                            .collect();
+
+                        return Some(HeadInfo {
+                            layer_index,
+                            kind: HeadKind::Region,
+                            stride,
+                            grid_size: [out_h, out_w],
+                            anchors,
+                            classes: region.config.classes,
+                            scale_x_y: 1.0,
+                        });
+                    }
+                    _ => return None,
+                };
+
+                let [out_h, out_w, _out_c] = inout_shape;
+                Some(HeadInfo {
+                    layer_index,
+                    kind,
+                    stride: self.head_stride(inout_shape),
+                    grid_size: [out_h, out_w],
+                    anchors: yolo.anchors.clone(),
+                    classes: self.net.classes,
+                    scale_x_y: yolo.scale_x_y.raw(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn head_table_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.heads())?)
+    }
+
+    fn head_stride(&self, [out_h, out_w, _out_c]: [u64; 3]) -> [u64; 2] {
+        let [in_h, in_w] = match self.net.input_size {
+            Shape::Hwc([h, w, _c]) => [h, w],
+            Shape::Flat(_) => [1, 1],
+        };
+        [in_h / out_h.max(1), in_w / out_w.max(1)]
+    }
+
+    // the input tensors as fed by darknet, see preprocess() for the reference
+    // implementation of the normalization
+    pub fn inputs(&self) -> Vec<InputBinding> {
+        let input_size = self.net.input_size;
+        let (layout, color_format) = match input_size {
+            Shape::Hwc([_h, _w, 1]) => (TensorLayout::Nchw, Some(ColorFormat::Gray)),
+            Shape::Hwc([_h, _w, 3]) => (TensorLayout::Nchw, Some(ColorFormat::Rgb)),
+            Shape::Hwc(_) => (TensorLayout::Nchw, None),
+            Shape::Flat(_) => (TensorLayout::Nc, None),
+        };
+        let resize = if self.net.letter_box {
+            ResizeMode::Letterbox { pad_value: 0.5 }
+        } else {
+            ResizeMode::Stretch
+        };
+
+        vec![InputBinding {
+            name: blob_name(self, LayerPosition::Input),
+            layout,
+            dtype: DataType::Float32,
+            dims: chw_dims(input_size),
+            value_range: [0.0, 1.0],
+            color_format,
+            resize,
+        }]
+    }
+
+    // the output layers in layer order, named like the exported tensors. softmax
+    // layers are outputs unless another layer reads them.
+    pub fn outputs(&self) -> Vec<OutputBinding> {
+        let consumed: HashSet<_> = self
+            .layers
+            .values()
+            .flat_map(|layer| layer.from_indexes().iter())
+            .collect();
+
+        self.layers
+            .iter()
+            .filter_map(|(&layer_index, layer)| {
+                let decode = match layer {
+                    LayerBase::Yolo(yolo) => DecodeParams::Yolo {
+                        classes: self.net.classes,
+                        anchors: yolo.config.anchors.clone(),
+                        stride: self.head_stride(yolo.inout_shape),
+                        scale_x_y: yolo.config.scale_x_y.raw(),
+                        new_coords: yolo.config.new_coords,
+                    },
+                    LayerBase::GaussianYolo(yolo) => DecodeParams::GaussianYolo {
+                        classes: self.net.classes,
+                        anchors: yolo.config.yolo.anchors.clone(),
+                        stride: self.head_stride(yolo.inout_shape),
+                        scale_x_y: yolo.config.yolo.scale_x_y.raw(),
+                    },
+                    LayerBase::Region(region) => DecodeParams::Region {
+                        classes: region.config.classes,
+                        coords: region.config.coords,
+                        anchors: region
+                            .config
+                            .anchors
+                            .iter()
+                            .flatten()
+                            .map(|(w, h)| (w.raw(), h.raw()))
+                            .collect(),
+                        softmax: region.config.softmax,
+                    },
+                    LayerBase::Softmax(softmax)
+                        if !consumed.contains(&LayerPosition::Absolute(layer_index)) =>
+                    {
+                        DecodeParams::Softmax {
+                            groups: softmax.config.groups,
+                            spatial: softmax.config.spatial,
+                        }
+                    }
+                    _ => return None,
+                };
+
+                Some(OutputBinding {
+                    name: layer_name(layer_index, layer),
+                    layer_index,
+                    dims: chw_dims(layer.output_shape()),
+                    decode,
+                })
+            })
+            .collect()
+    }
+}
+
+fn chw_dims(shape: Shape) -> Vec<u64> {
+    match shape {
+        Shape::Hwc([h, w, c]) => vec![c, h, w],
+        Shape::Flat(size) => vec![size],
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{common::*, darknet::DarknetModel, weights_layout::named_buffers};
+
+const FLOAT_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BufferOptions {
+    // the start address is a multiple of this many bytes, a power of two of at
+    // least 4
+    pub alignment: usize,
+    // the length is padded with zeros to a multiple of this many values
+    pub pad_to: usize,
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        Self {
+            alignment: FLOAT_SIZE,
+            pad_to: 1,
+        }
+    }
+}
+
+impl BufferOptions {
+    // 64-byte aligned buffers padded to whole cache lines
+    pub fn cache_line() -> Self {
+        Self {
+            alignment: 64,
+            pad_to: 64 / FLOAT_SIZE,
+        }
+    }
+}
+
+// a copy of a layer buffer whose storage satisfies the requested alignment, so
+// it can be handed to SIMD or GPU code as is
+#[derive(Debug)]
+pub struct Buffer {
+    pub layer_index: usize,
+    pub name: &'static str,
+    options: BufferOptions,
+    len: usize,
+    padded_len: usize,
+    // over-allocated so that the aligned range fits, the storage is never
+    // reallocated after construction so the address stays put
+    storage: Vec<f32>,
+    start: usize,
+}
+
+impl Buffer {
+    pub fn new(
+        layer_index: usize,
+        name: &'static str,
+        values: &[f32],
+        options: BufferOptions,
+    ) -> Result<Self> {
+        let BufferOptions { alignment, pad_to } = options;
+        ensure!(
+            alignment >= FLOAT_SIZE && alignment.is_power_of_two(),
+            "the alignment {} must be a power of two of at least {}",
+            alignment,
+            FLOAT_SIZE
+        );
+        ensure!(pad_to > 0, "pad_to must be positive");
+
+        let len = values.len();
+        let padded_len = len.div_ceil(pad_to) * pad_to;
+        let slack = alignment / FLOAT_SIZE - 1;
+        let mut storage = vec![0.0; padded_len + slack];
+        let start = storage.as_ptr().align_offset(alignment);
+        ensure!(
+            start <= slack,
+            "please report bug: cannot align the buffer to {} bytes",
+            alignment
+        );
+        storage[start..(start + len)].copy_from_slice(values);
+
+        Ok(Self {
+            layer_index,
+            name,
+            options,
+            len,
+            padded_len,
+            storage,
+            start,
+        })
+    }
+
+    pub fn alignment(&self) -> usize {
+        self.options.alignment
+    }
+
+    pub fn options(&self) -> BufferOptions {
+        self.options
+    }
+
+    // the number of values without the padding
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn padded_len(&self) -> usize {
+        self.padded_len
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.storage[self.start..(self.start + self.len)]
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [f32] {
+        &mut self.storage[self.start..(self.start + self.len)]
+    }
+
+    // the values followed by the zero padding
+    pub fn padded_slice(&self) -> &[f32] {
+        &self.storage[self.start..(self.start + self.padded_len)]
+    }
+}
+
+// the storage of a derived clone would lose the alignment
+impl Clone for Buffer {
+    fn clone(&self) -> Self {
+        Self::new(self.layer_index, self.name, self.as_slice(), self.options).unwrap()
+    }
+}
+
+impl PartialEq for Buffer {
+    fn eq(&self, other: &Self) -> bool {
+        self.layer_index == other.layer_index
+            && self.name == other.name
+            && self.options == other.options
+            && self.as_slice() == other.as_slice()
+    }
+}
+
+impl DarknetModel {
+    // aligned copies of all layer buffers in file order
+    pub fn aligned_buffers(&self, options: BufferOptions) -> Result<Vec<Buffer>> {
+        self.layers
+            .iter()
+            .sorted_by_key(|(layer_index, _)| **layer_index)
+            .flat_map(|(&layer_index, layer)| {
+                named_buffers(layer)
+                    .into_iter()
+                    .map(move |(name, values)| Buffer::new(layer_index, name, values, options))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{common::*, config::DarknetConfig, darknet::DarknetModel};
+use chacha20poly1305::{
+    aead::{Aead, NewAead, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use std::io;
+
+// an encrypted container of the config and the weights of a model. the bundle
+// starts with a plain header:
+//
+//   magic "DNBUNDLE", format version (u32), chunk size (u32), nonce prefix (7 bytes)
+//
+// followed by the plaintext encrypted chunk by chunk with ChaCha20-Poly1305 in
+// the STREAM construction. the nonce of a chunk is the random prefix, the chunk
+// counter (u32, big endian) and a byte flagging the last chunk, and each chunk
+// authenticates the header. a wrong key, an altered or reordered chunk or a
+// truncated bundle fails to decrypt. the plaintext is the length of the config
+// text (u64), the config text and the weights file.
+//
+// the key is supplied by the caller, who is responsible for deriving and
+// storing it.
+
+const MAGIC: &[u8; 8] = b"DNBUNDLE";
+const VERSION: u32 = 1;
+const NONCE_PREFIX_LEN: usize = 7;
+const HEADER_LEN: usize = MAGIC.len() + 8 + NONCE_PREFIX_LEN;
+const TAG_LEN: usize = 16;
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+pub const KEY_LEN: usize = 32;
+
+impl DarknetModel {
+    pub fn write_bundle<W>(&self, writer: W, key: &[u8; KEY_LEN]) -> Result<()>
+    where
+        W: Write,
+    {
+        let text = self.base.to_config().to_cfg_string()?;
+        let mut writer = EncryptWriter::new(writer, key)?;
+        writer.write_all(&(text.len() as u64).to_le_bytes())?;
+        writer.write_all(text.as_bytes())?;
+        self.write_weights(&mut writer)?;
+        writer.finish()?.flush()?;
+        Ok(())
+    }
+
+    pub fn save_bundle<P>(&self, bundle_file: P, key: &[u8; KEY_LEN]) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let writer = BufWriter::new(File::create(bundle_file)?);
+        self.write_bundle(writer, key)
+    }
+
+    // decrypt the bundle while parsing it, the plaintext is never held as a whole
+    pub fn read_bundle<R>(reader: R, key: &[u8; KEY_LEN]) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut reader = DecryptReader::new(reader, key)?;
+        let config_len = reader.read_u64::<LittleEndian>()?;
+        let mut text = String::new();
+        (&mut reader).take(config_len).read_to_string(&mut text)?;
+        ensure!(
+            text.len() as u64 == config_len,
+            "the config in the bundle is truncated"
+        );
+
+        let config: DarknetConfig = text.parse()?;
+        let mut model = Self::from_config(&config)?;
+        model.read_weights(reader)?;
+        Ok(model)
+    }
+
+    pub fn load_bundle<P>(bundle_file: P, key: &[u8; KEY_LEN]) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::read_bundle(BufReader::new(File::open(bundle_file)?), key)
+    }
+}
+
+// encrypts the written bytes into a bundle stream. finish() must be called to
+// write the last chunk, otherwise the stream is seen as truncated.
+pub struct EncryptWriter<W>
+where
+    W: Write,
+{
+    writer: W,
+    cipher: ChaCha20Poly1305,
+    header: [u8; HEADER_LEN],
+    counter: u32,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+}
+
+impl<W> EncryptWriter<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W, key: &[u8; KEY_LEN]) -> Result<Self> {
+        Self::with_chunk_size(writer, key, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(mut writer: W, key: &[u8; KEY_LEN], chunk_size: usize) -> Result<Self> {
+        ensure!(
+            chunk_size > 0 && chunk_size <= MAX_CHUNK_SIZE,
+            "the chunk size must be in range 1..={}",
+            MAX_CHUNK_SIZE
+        );
+
+        let prefix: [u8; NONCE_PREFIX_LEN] = rand::random();
+        let mut header = [0u8; HEADER_LEN];
+        header[..8].copy_from_slice(MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&(chunk_size as u32).to_le_bytes());
+        header[16..].copy_from_slice(&prefix);
+        writer.write_all(&header)?;
+
+        Ok(Self {
+            writer,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+            header,
+            counter: 0,
+            chunk_size,
+            buffer: Vec::with_capacity(chunk_size),
+        })
+    }
+
+    // encrypt the remaining bytes as the last chunk, which is always shorter
+    // than a full chunk, and return the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let buffer = mem::take(&mut self.buffer);
+        self.write_chunk(&buffer, true)?;
+        Ok(self.writer)
+    }
+
+    fn write_chunk(&mut self, chunk: &[u8], last: bool) -> io::Result<()> {
+        let nonce = chunk_nonce(&self.header, self.counter, last);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: chunk,
+                    aad: &self.header,
+                },
+            )
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "failed to encrypt the chunk"))?;
+        self.writer.write_all(&ciphertext)?;
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "too many chunks"))?;
+        Ok(())
+    }
+}
+
+impl<W> Write for EncryptWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.chunk_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+
+        if self.buffer.len() == self.chunk_size {
+            let buffer = mem::take(&mut self.buffer);
+            self.write_chunk(&buffer, false)?;
+            self.buffer = buffer;
+            self.buffer.clear();
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // buffered bytes are kept until the chunk is full
+        self.writer.flush()
+    }
+}
+
+// decrypts a bundle stream chunk by chunk. the end of the stream is reported
+// only after the last chunk is authenticated.
+pub struct DecryptReader<R>
+where
+    R: Read,
+{
+    reader: R,
+    cipher: ChaCha20Poly1305,
+    header: [u8; HEADER_LEN],
+    counter: u32,
+    chunk_size: usize,
+    plaintext: Vec<u8>,
+    consumed: usize,
+    finished: bool,
+}
+
+impl<R> DecryptReader<R>
+where
+    R: Read,
+{
+    pub fn new(mut reader: R, key: &[u8; KEY_LEN]) -> Result<Self> {
+        let mut header = [0u8; HEADER_LEN];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| format_err!("the bundle header is truncated"))?;
+        ensure!(&header[..8] == MAGIC, "not a model bundle");
+
+        let version = LittleEndian::read_u32(&header[8..12]);
+        ensure!(version == VERSION, "unsupported bundle version {}", version);
+        let chunk_size = LittleEndian::read_u32(&header[12..16]) as usize;
+        ensure!(
+            chunk_size > 0 && chunk_size <= MAX_CHUNK_SIZE,
+            "invalid chunk size {} in the bundle header",
+            chunk_size
+        );
+
+        Ok(Self {
+            reader,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+            header,
+            counter: 0,
+            chunk_size,
+            plaintext: vec![],
+            consumed: 0,
+            finished: false,
+        })
+    }
+
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        // a chunk shorter than a full one is the last
+        let mut ciphertext = vec![0u8; self.chunk_size + TAG_LEN];
+        let len = read_full(&mut self.reader, &mut ciphertext)?;
+        if len < TAG_LEN {
+            return Err(invalid("the bundle is truncated".into()));
+        }
+        let last = len < ciphertext.len();
+
+        let nonce = chunk_nonce(&self.header, self.counter, last);
+        self.plaintext = self
+            .cipher
+            .decrypt(
+                &nonce,
+                Payload {
+                    msg: &ciphertext[..len],
+                    aad: &self.header,
+                },
+            )
+            .map_err(|_| {
+                invalid(format!(
+                    "failed to decrypt chunk {}, the key is wrong or the bundle is corrupted",
+                    self.counter
+                ))
+            })?;
+        self.consumed = 0;
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| invalid("too many chunks".into()))?;
+
+        if last {
+            if read_full(&mut self.reader, &mut [0u8])? != 0 {
+                return Err(invalid("trailing bytes after the last chunk".into()));
+            }
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+impl<R> Read for DecryptReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.consumed == self.plaintext.len() {
+            if self.finished || buf.is_empty() {
+                return Ok(0);
+            }
+            self.read_chunk()?;
+        }
+
+        let remaining = &self.plaintext[self.consumed..];
+        let len = buf.len().min(remaining.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.consumed += len;
+        Ok(len)
+    }
+}
+
+fn chunk_nonce(header: &[u8; HEADER_LEN], counter: u32, last: bool) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(&header[(HEADER_LEN - NONCE_PREFIX_LEN)..]);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    Nonce::clone_from_slice(&nonce)
+}
+
+// read until the buffer is full or the end of the stream
+fn read_full(mut reader: impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(len)
+}
@@ -0,0 +1,135 @@
+use crate::{
+    common::*,
+    export::layer_name,
+    model::{LayerBase, ModelBase},
+};
+
+// the largest finite half precision value
+const FP16_MAX: f32 = 65504.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ActivationRange {
+    pub min: f32,
+    pub max: f32,
+    pub num_batches: u64,
+}
+
+impl ActivationRange {
+    pub fn new(min: f32, max: f32) -> Self {
+        Self {
+            min,
+            max,
+            num_batches: 1,
+        }
+    }
+
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            num_batches: self.num_batches + other.num_batches,
+        }
+    }
+
+    pub fn abs_max(&self) -> f32 {
+        self.min.abs().max(self.max.abs())
+    }
+}
+
+// receives the activation ranges observed by an external inference backend,
+// typically called once per layer and calibration batch
+pub trait CalibrationSink {
+    fn record(&mut self, layer_index: usize, range: ActivationRange);
+}
+
+// provides the activation ranges to the quantization and fp16 safety analyses
+pub trait ActivationStatsProvider {
+    fn activation_range(&self, layer_index: usize) -> Option<ActivationRange>;
+}
+
+// no calibration data
+impl ActivationStatsProvider for () {
+    fn activation_range(&self, _layer_index: usize) -> Option<ActivationRange> {
+        None
+    }
+}
+
+// collects ranges in memory and merges repeated records of the same layer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationTable {
+    pub ranges: IndexMap<usize, ActivationRange>,
+}
+
+impl CalibrationTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load<P>(file: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(serde_json::from_str(&fs::read_to_string(file)?)?)
+    }
+
+    pub fn save<P>(&self, file: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(file, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl CalibrationSink for CalibrationTable {
+    fn record(&mut self, layer_index: usize, range: ActivationRange) {
+        self.ranges
+            .entry(layer_index)
+            .and_modify(|merged| *merged = merged.merge(&range))
+            .or_insert(range);
+    }
+}
+
+impl ActivationStatsProvider for CalibrationTable {
+    fn activation_range(&self, layer_index: usize) -> Option<ActivationRange> {
+        self.ranges.get(&layer_index).cloned()
+    }
+}
+
+// layers whose observed activations do not fit in fp16
+pub fn fp16_overflow_layers(model: &ModelBase, stats: &dyn ActivationStatsProvider) -> Vec<usize> {
+    model
+        .layers
+        .keys()
+        .cloned()
+        .filter(|&layer_index| {
+            stats.activation_range(layer_index).is_some_and(|range| {
+                let abs_max = range.abs_max();
+                abs_max.is_nan() || abs_max > FP16_MAX
+            })
+        })
+        .collect()
+}
+
+// symmetric int8 scales of layer outputs, keyed by layer name. layers without
+// calibration data and yolo layers, which stay in fp32, are left out.
+pub fn int8_scales(
+    model: &ModelBase,
+    stats: &dyn ActivationStatsProvider,
+) -> IndexMap<String, f32> {
+    model
+        .layers
+        .iter()
+        .filter(|(_, layer)| !matches!(layer, LayerBase::Yolo(_)))
+        .filter_map(|(&layer_index, layer)| {
+            let range = stats.activation_range(layer_index)?;
+            let abs_max = range.abs_max();
+            let scale = if abs_max > 0.0 && abs_max.is_finite() {
+                abs_max / 127.0
+            } else {
+                1.0 / 127.0
+            };
+            Some((layer_name(layer_index, layer), scale))
+        })
+        .collect()
+}
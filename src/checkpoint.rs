@@ -0,0 +1,166 @@
+use crate::{common::*, darknet::DarknetModel};
+
+// which numbered checkpoints survive a save, the _last, _best and _final files
+// are always kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    // keep the most recent checkpoints, or all of them if None
+    pub keep_last: Option<usize>,
+    // also keep the checkpoints at multiples of the iteration regardless of age
+    pub keep_every: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn keep_all() -> Self {
+        Self::default()
+    }
+
+    pub fn keep_last(count: usize) -> Self {
+        Self {
+            keep_last: Some(count),
+            keep_every: None,
+        }
+    }
+
+    fn is_pinned(&self, iteration: u64) -> bool {
+        matches!(self.keep_every, Some(every) if every > 0 && iteration.is_multiple_of(every))
+    }
+}
+
+// the checkpoints of one training run named like darknet does, that is
+// {prefix}_last.weights, {prefix}_best.weights, {prefix}_final.weights and
+// {prefix}_{iteration}.weights in the backup directory
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CheckpointManager {
+    pub dir: PathBuf,
+    pub prefix: String,
+    pub retention: RetentionPolicy,
+}
+
+impl CheckpointManager {
+    pub fn new<P>(dir: P, prefix: &str, retention: RetentionPolicy) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        ensure!(
+            !prefix.is_empty(),
+            "the checkpoint prefix must not be empty"
+        );
+        Ok(Self {
+            dir: dir.as_ref().to_owned(),
+            prefix: prefix.to_owned(),
+            retention,
+        })
+    }
+
+    pub fn last_path(&self) -> PathBuf {
+        self.dir.join(format!("{}_last.weights", self.prefix))
+    }
+
+    pub fn best_path(&self) -> PathBuf {
+        self.dir.join(format!("{}_best.weights", self.prefix))
+    }
+
+    pub fn final_path(&self) -> PathBuf {
+        self.dir.join(format!("{}_final.weights", self.prefix))
+    }
+
+    pub fn iteration_path(&self, iteration: u64) -> PathBuf {
+        self.dir
+            .join(format!("{}_{}.weights", self.prefix, iteration))
+    }
+
+    // save the numbered checkpoint and _last, then drop the numbered checkpoints
+    // the retention policy does not keep. returns the removed files.
+    pub fn save_iteration(&self, model: &DarknetModel, iteration: u64) -> Result<Vec<PathBuf>> {
+        save_atomic(model, &self.iteration_path(iteration))?;
+        save_atomic(model, &self.last_path())?;
+        self.apply_retention()
+    }
+
+    pub fn save_last(&self, model: &DarknetModel) -> Result<()> {
+        save_atomic(model, &self.last_path())
+    }
+
+    pub fn save_best(&self, model: &DarknetModel) -> Result<()> {
+        save_atomic(model, &self.best_path())
+    }
+
+    pub fn save_final(&self, model: &DarknetModel) -> Result<()> {
+        save_atomic(model, &self.final_path())
+    }
+
+    // the numbered checkpoints in the directory sorted by iteration
+    pub fn iterations(&self) -> Result<Vec<(u64, PathBuf)>> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let checkpoints = fs::read_dir(&self.dir)?
+            .map(|entry| -> Result<_> {
+                let path = entry?.path();
+                let iteration = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_suffix(".weights"))
+                    .and_then(|stem| stem.strip_prefix(self.prefix.as_str()))
+                    .and_then(|rest| rest.strip_prefix('_'))
+                    .and_then(|number| number.parse::<u64>().ok());
+                Ok(iteration.map(|iteration| (iteration, path)))
+            })
+            .filter_map(|result| result.transpose())
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .sorted_by_key(|(iteration, _)| *iteration)
+            .collect();
+        Ok(checkpoints)
+    }
+
+    // the file to resume training from, _last if present, otherwise the
+    // numbered checkpoint with the largest iteration
+    pub fn latest(&self) -> Result<Option<PathBuf>> {
+        let last_path = self.last_path();
+        if last_path.is_file() {
+            return Ok(Some(last_path));
+        }
+        Ok(self.iterations()?.pop().map(|(_, path)| path))
+    }
+
+    fn apply_retention(&self) -> Result<Vec<PathBuf>> {
+        let keep_last = match self.retention.keep_last {
+            Some(keep_last) => keep_last,
+            None => return Ok(vec![]),
+        };
+
+        // pinned checkpoints do not count towards keep_last
+        let checkpoints: Vec<_> = self
+            .iterations()?
+            .into_iter()
+            .filter(|(iteration, _)| !self.retention.is_pinned(*iteration))
+            .collect();
+        let num_expired = checkpoints.len().saturating_sub(keep_last);
+        let removed: Vec<_> = checkpoints
+            .into_iter()
+            .take(num_expired)
+            .map(|(_, path)| path)
+            .collect();
+        removed.iter().try_for_each(fs::remove_file)?;
+        Ok(removed)
+    }
+}
+
+// write to a temporary file in the same directory first so that an interrupted
+// save never leaves a truncated checkpoint behind
+fn save_atomic(model: &DarknetModel, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("weights.tmp");
+    model
+        .save_weights(&tmp_path)
+        .and_then(|()| Ok(fs::rename(&tmp_path, path)?))
+        .map_err(|err| {
+            let _ = fs::remove_file(&tmp_path);
+            format_err!("failed to save {}: {}", path.display(), err)
+        })
+}
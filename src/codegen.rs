@@ -0,0 +1,5 @@
+pub mod ir;
+pub mod python;
+pub mod rust;
+
+pub use rust::RustBackend;
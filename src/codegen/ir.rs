@@ -0,0 +1,343 @@
+use crate::{
+    common::*,
+    config::{
+        ConnectedConfig, ConvolutionalConfig, DropoutConfig, ImplicitConfig, MaxPoolConfig,
+        ScaleChannelsConfig, Shape, ShortcutConfig, UpSampleConfig, WeightsNormalization,
+        WeightsType,
+    },
+    export::{blob_name, layer_name},
+    model::{
+        ConnectedLayerBase, ConvLstmLayerBase, ConvolutionalLayerBase, CrnnLayerBase,
+        CustomLayerBase, DropoutLayerBase, GaussianYoloLayerBase, ImplicitLayerBase, LayerBase,
+        LayerPosition, LocalAvgPoolLayerBase, LocalLayerBase, LstmLayerBase, MaxPoolLayerBase,
+        ModelBase, RegionLayerBase, ReorgLayerBase, RouteLayerBase, SamLayerBase,
+        ScaleChannelsLayerBase, ShortcutLayerBase, SoftmaxLayerBase, UpSampleLayerBase,
+        YoloLayerBase,
+    },
+};
+use std::fmt::Write as _;
+
+// bump the version whenever the textual format changes
+const IR_VERSION: u64 = 1;
+
+impl ModelBase {
+    pub fn ir_text(&self) -> String {
+        ir_text(self)
+    }
+}
+
+// one op per line in the form
+// %conv_0: f32[416, 416, 32] = conv(%input: f32[416, 416, 3]) filters=32 size=3 ...
+pub fn ir_text(model: &ModelBase) -> String {
+    let typed = |position: LayerPosition| {
+        let shape = match position {
+            LayerPosition::Input => model.net.input_size,
+            LayerPosition::Absolute(index) => model.layers[&index].output_shape(),
+        };
+        format!("%{}: {}", blob_name(model, position), tensor_type(shape))
+    };
+
+    let mut text = String::new();
+    writeln!(text, "# darknet ir v{}", IR_VERSION).unwrap();
+    writeln!(text, "{} = input", typed(LayerPosition::Input)).unwrap();
+
+    let mut outputs = vec![];
+
+    for (&layer_index, layer) in &model.layers {
+        let operands = layer.from_indexes().iter().map(typed).join(", ");
+
+        let attributes: Vec<String> = match layer {
+            LayerBase::Convolutional(ConvolutionalLayerBase {
+                config:
+                    ConvolutionalConfig {
+                        filters,
+                        groups,
+                        size,
+                        stride_x,
+                        stride_y,
+                        dilation,
+                        padding,
+                        batch_normalize,
+                        activation,
+                        share_index,
+                        ..
+                    },
+                ..
+            }) => {
+                let mut attributes = vec![
+                    format!("filters={}", filters),
+                    format!("size={}", size),
+                    format!("stride=[{}, {}]", stride_y, stride_x),
+                    format!("padding={}", padding),
+                    format!("dilation={}", dilation),
+                    format!("groups={}", groups),
+                    format!("batch_normalize={}", batch_normalize),
+                    format!("activation={}", activation),
+                ];
+                if let Some(share_index) = share_index {
+                    let share = share_index
+                        .to_absolute(layer_index)
+                        .and_then(|index| Some(layer_name(index, model.layers.get(&index)?)))
+                        .unwrap_or_else(|| "?".into());
+                    attributes.push(format!("share=%{}", share));
+                }
+                attributes
+            }
+            LayerBase::Connected(ConnectedLayerBase {
+                config:
+                    ConnectedConfig {
+                        output,
+                        activation,
+                        batch_normalize,
+                        ..
+                    },
+                ..
+            }) => vec![
+                format!("output={}", output),
+                format!("batch_normalize={}", batch_normalize),
+                format!("activation={}", activation),
+            ],
+            LayerBase::BatchNorm(_) => vec![],
+            LayerBase::Shortcut(ShortcutLayerBase {
+                config:
+                    ShortcutConfig {
+                        activation,
+                        weights_type,
+                        weights_normalization,
+                        ..
+                    },
+                ..
+            }) => vec![
+                format!("activation={}", activation),
+                format!(
+                    "weights_type={}",
+                    match weights_type {
+                        WeightsType::None => "none",
+                        WeightsType::PerFeature => "per_feature",
+                        WeightsType::PerChannel => "per_channel",
+                    }
+                ),
+                format!(
+                    "weights_normalization={}",
+                    match weights_normalization {
+                        WeightsNormalization::None => "none",
+                        WeightsNormalization::ReLU => "relu",
+                        WeightsNormalization::Softmax => "softmax",
+                    }
+                ),
+            ],
+            LayerBase::MaxPool(MaxPoolLayerBase {
+                config:
+                    MaxPoolConfig {
+                        stride_x,
+                        stride_y,
+                        size,
+                        padding,
+                        maxpool_depth,
+                        ..
+                    },
+                ..
+            }) => vec![
+                format!("size={}", size),
+                format!("stride=[{}, {}]", stride_y, stride_x),
+                format!("padding={}", padding),
+                format!("maxpool_depth={}", maxpool_depth),
+            ],
+            LayerBase::Route(RouteLayerBase { config, .. }) => vec![format!(
+                "group={}/{}",
+                config.group.group_id(),
+                config.group.num_groups()
+            )],
+            LayerBase::UpSample(UpSampleLayerBase {
+                config: UpSampleConfig {
+                    stride, reverse, ..
+                },
+                ..
+            }) => vec![format!("stride={}", stride), format!("reverse={}", reverse)],
+            LayerBase::Implicit(ImplicitLayerBase {
+                config:
+                    ImplicitConfig {
+                        filters,
+                        mean,
+                        std,
+                        atoms,
+                        mode,
+                        ..
+                    },
+                ..
+            }) => vec![
+                format!("filters={}", filters),
+                format!("mean={}", mean),
+                format!("std={}", std),
+                format!("atoms={}", atoms),
+                format!("mode={:?}", mode),
+            ],
+            LayerBase::AvgPool(_) => vec![],
+            LayerBase::ScaleChannels(ScaleChannelsLayerBase {
+                config:
+                    ScaleChannelsConfig {
+                        scale_wh,
+                        activation,
+                        ..
+                    },
+                ..
+            }) => vec![
+                format!("scale_wh={}", scale_wh),
+                format!("activation={}", activation),
+            ],
+            LayerBase::Dropout(DropoutLayerBase {
+                config:
+                    DropoutConfig {
+                        probability,
+                        dropblock,
+                        ..
+                    },
+                ..
+            }) => vec![
+                format!("probability={}", probability),
+                format!("dropblock={}", dropblock),
+            ],
+            LayerBase::Softmax(SoftmaxLayerBase { config, .. }) => {
+                let mut attributes = vec![
+                    format!("groups={}", config.groups),
+                    format!("temperature={}", config.temperature),
+                    format!("spatial={}", config.spatial),
+                ];
+                if let Some(tree) = &config.tree {
+                    attributes.push(format!("tree={:?}", tree));
+                }
+                attributes
+            }
+            LayerBase::Reorg(ReorgLayerBase { config, .. }) => vec![
+                format!("stride={}", config.stride),
+                format!("reverse={}", config.reverse),
+                format!("mode={:?}", config.mode),
+            ],
+            LayerBase::Local(LocalLayerBase { config, .. }) => vec![
+                format!("filters={}", config.filters),
+                format!("size={}", config.size),
+                format!("stride={}", config.stride),
+                format!("pad={}", config.pad),
+                format!("activation={:?}", config.activation),
+            ],
+            LayerBase::LocalAvgPool(LocalAvgPoolLayerBase { config, .. }) => vec![
+                format!("size={}", config.size),
+                format!("stride_x={}", config.stride_x),
+                format!("stride_y={}", config.stride_y),
+                format!("padding={}", config.padding),
+            ],
+            LayerBase::Sam(SamLayerBase { config, .. }) => {
+                vec![format!("activation={}", config.activation)]
+            }
+            LayerBase::Lstm(LstmLayerBase { config, .. }) => vec![
+                format!("output={}", config.output),
+                format!("batch_normalize={}", config.batch_normalize),
+            ],
+            LayerBase::Crnn(CrnnLayerBase { config, .. }) => vec![
+                format!("output={}", config.output),
+                format!("hidden={}", config.hidden),
+                format!("size={}", config.size),
+                format!("stride={}", config.stride),
+                format!("pad={}", config.pad),
+                format!("activation={:?}", config.activation),
+                format!("batch_normalize={}", config.batch_normalize),
+            ],
+            LayerBase::ConvLstm(ConvLstmLayerBase { config, .. }) => vec![
+                format!("output={}", config.output),
+                format!("size={}", config.size),
+                format!("stride={}", config.stride),
+                format!("pad={}", config.pad),
+                format!("groups={}", config.groups),
+                format!("activation={:?}", config.activation),
+                format!("lstm_activation={:?}", config.lstm_activation),
+                format!("batch_normalize={}", config.batch_normalize),
+                format!("peephole={}", config.peephole),
+                format!("bottleneck={}", config.bottleneck),
+            ],
+            LayerBase::Custom(CustomLayerBase { config, .. }) => {
+                iter::once(format!("section={}", config.section))
+                    .chain(
+                        config
+                            .options
+                            .iter()
+                            .map(|(key, value)| format!("{}={}", key, value)),
+                    )
+                    .collect()
+            }
+            LayerBase::Region(RegionLayerBase { config, .. }) => {
+                outputs.push(format!("%{}", layer_name(layer_index, layer)));
+                let mut attributes = vec![
+                    format!("classes={}", config.classes),
+                    format!("coords={}", config.coords),
+                    format!("num={}", config.num),
+                    format!("softmax={}", config.softmax),
+                ];
+                if let Some(anchors) = &config.anchors {
+                    attributes.push(format!(
+                        "anchors=[{}]",
+                        anchors
+                            .iter()
+                            .map(|(w, h)| format!("({}, {})", w, h))
+                            .join(", ")
+                    ));
+                }
+                attributes
+            }
+            LayerBase::Yolo(YoloLayerBase { config, .. }) => {
+                outputs.push(format!("%{}", layer_name(layer_index, layer)));
+                vec![
+                    format!("classes={}", model.net.classes),
+                    format!("new_coords={}", config.new_coords),
+                    format!(
+                        "anchors=[{}]",
+                        config
+                            .anchors
+                            .iter()
+                            .map(|(w, h)| format!("({}, {})", w, h))
+                            .join(", ")
+                    ),
+                ]
+            }
+            LayerBase::GaussianYolo(GaussianYoloLayerBase { config, .. }) => {
+                outputs.push(format!("%{}", layer_name(layer_index, layer)));
+                vec![
+                    format!("classes={}", model.net.classes),
+                    format!("uc_normalizer={}", config.uc_normalizer),
+                    format!(
+                        "anchors=[{}]",
+                        config
+                            .yolo
+                            .anchors
+                            .iter()
+                            .map(|(w, h)| format!("({}, {})", w, h))
+                            .join(", ")
+                    ),
+                ]
+            }
+        };
+
+        write!(
+            text,
+            "%{}: {} = {}({})",
+            layer_name(layer_index, layer),
+            tensor_type(layer.output_shape()),
+            layer.kind(),
+            operands
+        )
+        .unwrap();
+        attributes
+            .iter()
+            .for_each(|attribute| write!(text, " {}", attribute).unwrap());
+        text.push('\n');
+    }
+
+    writeln!(text, "return {}", outputs.join(", ")).unwrap();
+    text
+}
+
+fn tensor_type(shape: Shape) -> String {
+    match shape {
+        Shape::Hwc([h, w, c]) => format!("f32[{}, {}, {}]", h, w, c),
+        Shape::Flat(size) => format!("f32[{}]", size),
+    }
+}
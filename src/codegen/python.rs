@@ -0,0 +1,376 @@
+use crate::{
+    common::*,
+    config::{Activation, ConnectedConfig, ConvolutionalConfig, MaxPoolConfig, ShortcutConfig},
+    export::{
+        blob_name, find_unsupported_layers, layer_name, maxpool_padding, yolo_output,
+        BATCH_NORM_EPSILON,
+    },
+    model::{
+        ConnectedLayerBase, ConvolutionalLayerBase, LayerBase, LayerPosition, MaxPoolLayerBase,
+        ModelBase, RouteLayerBase, ShortcutLayerBase, UpSampleLayerBase,
+    },
+};
+use std::fmt::Write as _;
+
+// module attributes follow the tensor names of the safetensors export, so that the
+// exported file loads into the generated module with load_state_dict()
+const PRELUDE: &str = r#"# generated by darknet-config, do not edit
+import torch
+import torch.nn as nn
+import torch.nn.functional as F
+
+
+class ConvBlock(nn.Module):
+    def __init__(self, in_channels, out_channels, kernel_size, stride, padding, dilation, groups, batch_normalize):
+        super().__init__()
+        self.conv = nn.Conv2d(
+            in_channels,
+            out_channels,
+            kernel_size,
+            stride=stride,
+            padding=padding,
+            dilation=dilation,
+            groups=groups,
+            bias=not batch_normalize,
+        )
+        self.bn = nn.BatchNorm2d(out_channels, eps={eps}) if batch_normalize else None
+
+    def forward(self, x):
+        x = self.conv(x)
+        if self.bn is not None:
+            x = self.bn(x)
+        return x
+
+
+class FcBlock(nn.Module):
+    def __init__(self, in_features, out_features, batch_normalize):
+        super().__init__()
+        self.fc = nn.Linear(in_features, out_features, bias=not batch_normalize)
+        self.bn = nn.BatchNorm1d(out_features, eps={eps}) if batch_normalize else None
+
+    def forward(self, x):
+        x = self.fc(x)
+        if self.bn is not None:
+            x = self.bn(x)
+        return x
+
+
+class NormBlock(nn.Module):
+    def __init__(self, channels):
+        super().__init__()
+        self.bn = nn.BatchNorm2d(channels, eps={eps})
+
+    def forward(self, x):
+        return self.bn(x)
+"#;
+
+const LOADER: &str = r#"
+
+def load_model(safetensors_file, device="cpu"):
+    from safetensors.torch import load_file
+
+    model = Model()
+    model.load_state_dict(load_file(safetensors_file, device=device))
+    return model.to(device)
+"#;
+
+fn activation_expr(var: &str, activation: Activation) -> Option<String> {
+    let expr = match activation {
+        Activation::Linear => var.to_owned(),
+        Activation::Relu => format!("F.relu({})", var),
+        Activation::Leaky => format!("F.leaky_relu({}, 0.1)", var),
+        Activation::Logistic => format!("torch.sigmoid({})", var),
+        Activation::Tanh => format!("torch.tanh({})", var),
+        Activation::Mish => format!("F.mish({})", var),
+        Activation::Swish => format!("F.silu({})", var),
+        Activation::Gelu => format!("F.gelu({})", var),
+        Activation::Elu => format!("F.elu({})", var),
+        Activation::Selu => format!("F.selu({})", var),
+        Activation::Hardtan => format!("F.hardtanh({})", var),
+        _ => return None,
+    };
+    Some(expr)
+}
+
+impl ModelBase {
+    pub fn codegen_python(&self) -> Result<String> {
+        codegen_python(self)
+    }
+}
+
+pub fn codegen_python(model: &ModelBase) -> Result<String> {
+    ensure!(
+        model.net.input_size.hwc().is_some(),
+        "flat network input is not supported by the code generator"
+    );
+
+    let unsupported = find_unsupported_layers(model, |activation| {
+        activation_expr("x", activation).is_some()
+    });
+    ensure!(
+        unsupported.is_empty(),
+        "the model cannot be generated for PyTorch:\n{}",
+        unsupported.iter().join("\n")
+    );
+
+    let var = |position| blob_name(model, position);
+
+    let mut inits = String::new();
+    let mut forward = String::new();
+    let mut outputs = vec![];
+
+    for (&layer_index, layer) in &model.layers {
+        let name = layer_name(layer_index, layer);
+
+        match layer {
+            LayerBase::Convolutional(ConvolutionalLayerBase {
+                config:
+                    ConvolutionalConfig {
+                        filters,
+                        groups,
+                        size,
+                        stride_x,
+                        stride_y,
+                        dilation,
+                        padding,
+                        batch_normalize,
+                        activation,
+                        share_index,
+                        ..
+                    },
+                from_indexes,
+                input_shape: [_h, _w, in_c],
+                ..
+            }) => {
+                // layers sharing weights reuse the module of the owning layer
+                let module = match share_index {
+                    Some(share_index) => {
+                        let share_index = share_index
+                            .to_absolute(layer_index)
+                            .ok_or_else(|| format_err!("invalid layer index"))?;
+                        layer_name(share_index, &model.layers[&share_index])
+                    }
+                    None => {
+                        writeln!(
+                            inits,
+                            "        self.{} = ConvBlock({}, {}, {}, ({}, {}), {}, {}, {}, {})",
+                            name,
+                            in_c,
+                            filters,
+                            size,
+                            stride_y,
+                            stride_x,
+                            padding,
+                            dilation,
+                            groups,
+                            if *batch_normalize { "True" } else { "False" }
+                        )?;
+                        name.clone()
+                    }
+                };
+
+                writeln!(
+                    forward,
+                    "        {} = self.{}({})",
+                    name,
+                    module,
+                    var(*from_indexes)
+                )?;
+                if *activation != Activation::Linear {
+                    writeln!(
+                        forward,
+                        "        {} = {}",
+                        name,
+                        activation_expr(&name, *activation).unwrap()
+                    )?;
+                }
+            }
+            LayerBase::Connected(ConnectedLayerBase {
+                config:
+                    ConnectedConfig {
+                        activation,
+                        batch_normalize,
+                        ..
+                    },
+                from_indexes,
+                input_shape,
+                output_shape,
+            }) => {
+                writeln!(
+                    inits,
+                    "        self.{} = FcBlock({}, {}, {})",
+                    name,
+                    input_shape,
+                    output_shape,
+                    if *batch_normalize { "True" } else { "False" }
+                )?;
+
+                // darknet flattens feature maps in CHW order, which matches torch.flatten()
+                let is_flat = match from_indexes {
+                    LayerPosition::Absolute(index) => {
+                        model.layers[index].output_shape().flat().is_some()
+                    }
+                    LayerPosition::Input => false,
+                };
+                let input = if is_flat {
+                    var(*from_indexes)
+                } else {
+                    format!("torch.flatten({}, 1)", var(*from_indexes))
+                };
+                writeln!(forward, "        {} = self.{}({})", name, name, input)?;
+                if *activation != Activation::Linear {
+                    writeln!(
+                        forward,
+                        "        {} = {}",
+                        name,
+                        activation_expr(&name, *activation).unwrap()
+                    )?;
+                }
+            }
+            LayerBase::BatchNorm(layer) => {
+                let [_h, _w, channels] = layer.inout_shape;
+                writeln!(inits, "        self.{} = NormBlock({})", name, channels)?;
+                writeln!(
+                    forward,
+                    "        {} = self.{}({})",
+                    name,
+                    name,
+                    var(layer.from_indexes)
+                )?;
+            }
+            LayerBase::Shortcut(ShortcutLayerBase {
+                config: ShortcutConfig { activation, .. },
+                from_indexes,
+                ..
+            }) => {
+                let expr = from_indexes
+                    .iter()
+                    .map(|&position| var(position))
+                    .join(" + ");
+                writeln!(forward, "        {} = {}", name, expr)?;
+                if *activation != Activation::Linear {
+                    writeln!(
+                        forward,
+                        "        {} = {}",
+                        name,
+                        activation_expr(&name, *activation).unwrap()
+                    )?;
+                }
+            }
+            LayerBase::MaxPool(MaxPoolLayerBase {
+                config:
+                    MaxPoolConfig {
+                        stride_x,
+                        stride_y,
+                        size,
+                        padding,
+                        ..
+                    },
+                from_indexes,
+                ..
+            }) => {
+                let [pad_begin, pad_end] = maxpool_padding(*padding);
+                let input = if *padding > 0 {
+                    format!(
+                        "F.pad({}, ({}, {}, {}, {}), mode=\"replicate\")",
+                        var(*from_indexes),
+                        pad_begin,
+                        pad_end,
+                        pad_begin,
+                        pad_end
+                    )
+                } else {
+                    var(*from_indexes)
+                };
+                writeln!(
+                    forward,
+                    "        {} = F.max_pool2d({}, ({}, {}), ({}, {}))",
+                    name, input, size, size, stride_y, stride_x
+                )?;
+            }
+            LayerBase::Route(RouteLayerBase {
+                config,
+                from_indexes,
+                input_shape,
+                ..
+            }) => {
+                let group_id = config.group.group_id();
+                let num_groups = config.group.num_groups();
+
+                let inputs: Vec<_> = izip!(from_indexes, input_shape)
+                    .map(|(&position, &[_h, _w, in_c])| {
+                        if num_groups == 1 {
+                            var(position)
+                        } else {
+                            let group_size = in_c / num_groups;
+                            let begin = group_size * group_id;
+                            format!("{}[:, {}:{}]", var(position), begin, begin + group_size)
+                        }
+                    })
+                    .collect();
+
+                let expr = if inputs.len() == 1 {
+                    inputs[0].clone()
+                } else {
+                    format!("torch.cat([{}], 1)", inputs.join(", "))
+                };
+                writeln!(forward, "        {} = {}", name, expr)?;
+            }
+            LayerBase::UpSample(UpSampleLayerBase {
+                from_indexes,
+                output_shape: [out_h, out_w, _c],
+                ..
+            }) => {
+                writeln!(
+                    forward,
+                    "        {} = F.interpolate({}, size=({}, {}), mode=\"nearest\")",
+                    name,
+                    var(*from_indexes),
+                    out_h,
+                    out_w
+                )?;
+            }
+            LayerBase::Yolo(yolo) => {
+                outputs.push(var(yolo_output(yolo)));
+            }
+            LayerBase::Implicit(_)
+            | LayerBase::AvgPool(_)
+            | LayerBase::ScaleChannels(_)
+            | LayerBase::Dropout(_)
+            | LayerBase::Softmax(_)
+            | LayerBase::Region(_)
+            | LayerBase::ConvLstm(_)
+            | LayerBase::Crnn(_)
+            | LayerBase::Lstm(_)
+            | LayerBase::Sam(_)
+            | LayerBase::LocalAvgPool(_)
+            | LayerBase::Local(_)
+            | LayerBase::Reorg(_)
+            | LayerBase::GaussianYolo(_)
+            | LayerBase::Custom(_) => unreachable!("please report bug"),
+        }
+    }
+
+    if inits.is_empty() {
+        inits.push_str("        pass\n");
+    }
+
+    let source = format!(
+        "{}
+
+class Model(nn.Module):
+    def __init__(self):
+        super().__init__()
+{}
+    def forward(self, input):
+{}        return [{}]
+{}",
+        PRELUDE.replace("{eps}", &format!("{:e}", BATCH_NORM_EPSILON)),
+        inits,
+        forward,
+        outputs.join(", "),
+        LOADER
+    );
+
+    Ok(source)
+}
@@ -0,0 +1,603 @@
+use crate::{
+    common::*,
+    config::{
+        Activation, ConnectedConfig, ConvolutionalConfig, MaxPoolConfig, ShortcutConfig,
+        UpSampleConfig,
+    },
+    export::{
+        blob_name, find_unsupported_layers, layer_name, maxpool_padding, yolo_output,
+        UnsupportedLayer,
+    },
+    model::{
+        ConnectedLayerBase, ConvolutionalLayerBase, LayerBase, LayerPosition, MaxPoolLayerBase,
+        ModelBase, RouteLayerBase, ShortcutLayerBase, UpSampleLayerBase,
+    },
+};
+use std::fmt::Write as _;
+
+// the generated code targets tch 0.3 and candle 0.4
+const TCH_PRELUDE: &str = r#"// generated by darknet-config, do not edit
+use tch::{nn, Tensor};
+
+#[derive(Debug)]
+pub struct ConvBlock {
+    pub conv: nn::Conv<[i64; 2]>,
+    pub bn: Option<nn::BatchNorm>,
+}
+
+impl ConvBlock {
+    pub fn forward_t(&self, xs: &Tensor, train: bool) -> Tensor {
+        let xs = xs.apply(&self.conv);
+        match &self.bn {
+            Some(bn) => xs.apply_t(bn, train),
+            None => xs,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FcBlock {
+    pub fc: nn::Linear,
+    pub bn: Option<nn::BatchNorm>,
+}
+
+impl FcBlock {
+    pub fn forward_t(&self, xs: &Tensor, train: bool) -> Tensor {
+        let xs = xs.apply(&self.fc);
+        match &self.bn {
+            Some(bn) => xs.apply_t(bn, train),
+            None => xs,
+        }
+    }
+}
+"#;
+
+const CANDLE_PRELUDE: &str = r#"// generated by darknet-config, do not edit
+use candle_core::{Result, Tensor};
+use candle_nn::{
+    batch_norm, conv2d, conv2d_no_bias, linear, linear_no_bias, BatchNorm, BatchNormConfig,
+    Conv2d, Conv2dConfig, Linear, Module, ModuleT, VarBuilder,
+};
+
+#[derive(Debug)]
+pub struct ConvBlock {
+    pub conv: Conv2d,
+    pub bn: Option<BatchNorm>,
+}
+
+impl ConvBlock {
+    pub fn forward_t(&self, xs: &Tensor, train: bool) -> Result<Tensor> {
+        let xs = self.conv.forward(xs)?;
+        match &self.bn {
+            Some(bn) => bn.forward_t(&xs, train),
+            None => Ok(xs),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FcBlock {
+    pub fc: Linear,
+    pub bn: Option<BatchNorm>,
+}
+
+impl FcBlock {
+    pub fn forward_t(&self, xs: &Tensor, train: bool) -> Result<Tensor> {
+        let xs = self.fc.forward(xs)?;
+        match &self.bn {
+            Some(bn) => bn.forward_t(&xs, train),
+            None => Ok(xs),
+        }
+    }
+}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RustBackend {
+    #[serde(rename = "tch")]
+    Tch,
+    #[serde(rename = "candle")]
+    Candle,
+}
+
+impl RustBackend {
+    fn activation_expr(&self, var: &str, activation: Activation) -> Option<String> {
+        let expr = match (self, activation) {
+            (_, Activation::Linear) => return Some(var.to_owned()),
+            (Self::Tch, Activation::Relu) => format!("{}.relu()", var),
+            (Self::Tch, Activation::Leaky) => {
+                format!("{0}.clamp_min(0.0) + {0}.clamp_max(0.0) * 0.1", var)
+            }
+            (Self::Tch, Activation::Logistic) => format!("{}.sigmoid()", var),
+            (Self::Tch, Activation::Tanh) => format!("{}.tanh()", var),
+            (Self::Tch, Activation::Mish) => format!("&{0} * {0}.softplus().tanh()", var),
+            (Self::Tch, Activation::Swish) => format!("&{0} * {0}.sigmoid()", var),
+            (Self::Tch, Activation::Gelu) => format!("{}.gelu()", var),
+            (Self::Tch, Activation::Elu) => format!("{}.elu()", var),
+            (Self::Candle, Activation::Relu) => format!("{}.relu()?", var),
+            (Self::Candle, Activation::Leaky) => {
+                format!("candle_nn::ops::leaky_relu(&{}, 0.1)?", var)
+            }
+            (Self::Candle, Activation::Logistic) => format!("candle_nn::ops::sigmoid(&{})?", var),
+            (Self::Candle, Activation::Tanh) => format!("{}.tanh()?", var),
+            (Self::Candle, Activation::Mish) => {
+                format!("(&{0} * {0}.exp()?.affine(1.0, 1.0)?.log()?.tanh()?)?", var)
+            }
+            (Self::Candle, Activation::Swish) => format!("{}.silu()?", var),
+            (Self::Candle, Activation::Gelu) => format!("{}.gelu()?", var),
+            (Self::Candle, Activation::Elu) => format!("{}.elu(1.0)?", var),
+            _ => return None,
+        };
+        Some(expr)
+    }
+
+    // the expression that yields the variable path of a parameter group, e.g. conv_0.bn
+    fn path(&self, name: &str, child: &str) -> String {
+        match self {
+            Self::Tch => format!("vs / \"{}\" / \"{}\"", name, child),
+            Self::Candle => format!("vb.pp(\"{}\").pp(\"{}\")", name, child),
+        }
+    }
+
+    fn try_op(&self) -> &'static str {
+        match self {
+            Self::Tch => "",
+            Self::Candle => "?",
+        }
+    }
+}
+
+impl ModelBase {
+    pub fn codegen_rust(&self, backend: RustBackend) -> Result<String> {
+        codegen_rust(self, backend)
+    }
+}
+
+pub fn codegen_rust(model: &ModelBase, backend: RustBackend) -> Result<String> {
+    ensure!(
+        model.net.input_size.hwc().is_some(),
+        "flat network input is not supported by the code generator"
+    );
+
+    let mut unsupported = find_unsupported_layers(model, |activation| {
+        backend.activation_expr("x", activation).is_some()
+    });
+    if backend == RustBackend::Candle {
+        model
+            .layers
+            .iter()
+            .for_each(|(&layer_index, layer)| match layer {
+                LayerBase::Convolutional(ConvolutionalLayerBase { config, .. })
+                    if config.stride_x != config.stride_y =>
+                {
+                    unsupported.push(UnsupportedLayer {
+                        layer_index,
+                        kind: layer.kind().into(),
+                        reason: "non-square strides are not supported by candle".into(),
+                    });
+                }
+                _ => (),
+            });
+    }
+    ensure!(
+        unsupported.is_empty(),
+        "the model cannot be generated for {:?}:\n{}",
+        backend,
+        unsupported.iter().join("\n")
+    );
+
+    let try_op = backend.try_op();
+    let var = |position| blob_name(model, position);
+
+    let mut fields = String::new();
+    let mut inits = String::new();
+    let mut forward = String::new();
+    let mut outputs = vec![];
+
+    for (&layer_index, layer) in &model.layers {
+        let name = layer_name(layer_index, layer);
+
+        match layer {
+            LayerBase::Convolutional(ConvolutionalLayerBase {
+                config:
+                    ConvolutionalConfig {
+                        filters,
+                        groups,
+                        size,
+                        stride_x,
+                        stride_y,
+                        dilation,
+                        padding,
+                        batch_normalize,
+                        activation,
+                        share_index,
+                        ..
+                    },
+                from_indexes,
+                input_shape: [_h, _w, in_c],
+                ..
+            }) => {
+                // layers sharing weights reuse the module of the owning layer
+                let module = match share_index {
+                    Some(share_index) => {
+                        let share_index = share_index
+                            .to_absolute(layer_index)
+                            .ok_or_else(|| format_err!("invalid layer index"))?;
+                        layer_name(share_index, &model.layers[&share_index])
+                    }
+                    None => {
+                        writeln!(fields, "    pub {}: ConvBlock,", name)?;
+                        let conv_path = backend.path(&name, "conv");
+                        let bn_path = backend.path(&name, "bn");
+                        let (conv, bn) = match backend {
+                            RustBackend::Tch => (
+                                format!(
+                                    "nn::conv({}, {}, {}, [{}, {}], nn::ConvConfigND {{ stride: [{}, {}], padding: [{}, {}], dilation: [{}, {}], groups: {}, bias: {}, ..Default::default() }})",
+                                    conv_path, in_c, filters, size, size, stride_y, stride_x,
+                                    padding, padding, dilation, dilation, groups, !batch_normalize
+                                ),
+                                format!(
+                                    "nn::batch_norm2d({}, {}, nn::BatchNormConfig {{ eps: 1e-6, ..Default::default() }})",
+                                    bn_path, filters
+                                ),
+                            ),
+                            RustBackend::Candle => (
+                                format!(
+                                    "{}({}, {}, {}, Conv2dConfig {{ padding: {}, stride: {}, dilation: {}, groups: {}, ..Default::default() }}, {})?",
+                                    if *batch_normalize { "conv2d_no_bias" } else { "conv2d" },
+                                    in_c, filters, size, padding, stride_x, dilation, groups,
+                                    conv_path
+                                ),
+                                format!(
+                                    "batch_norm({}, BatchNormConfig {{ eps: 1e-6, ..Default::default() }}, {})?",
+                                    filters, bn_path
+                                ),
+                            ),
+                        };
+                        let bn = if *batch_normalize {
+                            format!("Some({})", bn)
+                        } else {
+                            "None".into()
+                        };
+                        writeln!(
+                            inits,
+                            "            {}: ConvBlock {{\n                conv: {},\n                bn: {},\n            }},",
+                            name, conv, bn
+                        )?;
+                        name.clone()
+                    }
+                };
+
+                writeln!(
+                    forward,
+                    "        let {} = self.{}.forward_t(&{}, train){};",
+                    name,
+                    module,
+                    var(*from_indexes),
+                    try_op
+                )?;
+                if *activation != Activation::Linear {
+                    writeln!(
+                        forward,
+                        "        let {} = {};",
+                        name,
+                        backend.activation_expr(&name, *activation).unwrap()
+                    )?;
+                }
+            }
+            LayerBase::Connected(ConnectedLayerBase {
+                config:
+                    ConnectedConfig {
+                        activation,
+                        batch_normalize,
+                        ..
+                    },
+                from_indexes,
+                input_shape,
+                output_shape,
+            }) => {
+                writeln!(fields, "    pub {}: FcBlock,", name)?;
+                let fc_path = backend.path(&name, "fc");
+                let bn_path = backend.path(&name, "bn");
+                let (fc, bn) = match backend {
+                    RustBackend::Tch => (
+                        format!(
+                            "nn::linear({}, {}, {}, nn::LinearConfig {{ bias: {}, ..Default::default() }})",
+                            fc_path, input_shape, output_shape, !batch_normalize
+                        ),
+                        format!(
+                            "nn::batch_norm1d({}, {}, nn::BatchNormConfig {{ eps: 1e-6, ..Default::default() }})",
+                            bn_path, output_shape
+                        ),
+                    ),
+                    RustBackend::Candle => (
+                        format!(
+                            "{}({}, {}, {})?",
+                            if *batch_normalize {
+                                "linear_no_bias"
+                            } else {
+                                "linear"
+                            },
+                            input_shape,
+                            output_shape,
+                            fc_path
+                        ),
+                        format!(
+                            "batch_norm({}, BatchNormConfig {{ eps: 1e-6, ..Default::default() }}, {})?",
+                            output_shape, bn_path
+                        ),
+                    ),
+                };
+                let bn = if *batch_normalize {
+                    format!("Some({})", bn)
+                } else {
+                    "None".into()
+                };
+                writeln!(
+                    inits,
+                    "            {}: FcBlock {{\n                fc: {},\n                bn: {},\n            }},",
+                    name, fc, bn
+                )?;
+
+                // darknet flattens feature maps in CHW order
+                let is_flat = match from_indexes {
+                    LayerPosition::Absolute(index) => {
+                        model.layers[index].output_shape().flat().is_some()
+                    }
+                    LayerPosition::Input => false,
+                };
+                let input = match (is_flat, backend) {
+                    (true, _) => format!("&{}", var(*from_indexes)),
+                    (false, RustBackend::Tch) => format!("&{}.flatten(1, -1)", var(*from_indexes)),
+                    (false, RustBackend::Candle) => {
+                        format!("&{}.flatten_from(1)?", var(*from_indexes))
+                    }
+                };
+                writeln!(
+                    forward,
+                    "        let {} = self.{}.forward_t({}, train){};",
+                    name, name, input, try_op
+                )?;
+                if *activation != Activation::Linear {
+                    writeln!(
+                        forward,
+                        "        let {} = {};",
+                        name,
+                        backend.activation_expr(&name, *activation).unwrap()
+                    )?;
+                }
+            }
+            LayerBase::BatchNorm(layer) => {
+                let [_h, _w, channels] = layer.inout_shape;
+                let (field_type, init) = match backend {
+                    RustBackend::Tch => (
+                        "nn::BatchNorm",
+                        format!(
+                            "nn::batch_norm2d({}, {}, nn::BatchNormConfig {{ eps: 1e-6, ..Default::default() }})",
+                            backend.path(&name, "bn"),
+                            channels
+                        ),
+                    ),
+                    RustBackend::Candle => (
+                        "BatchNorm",
+                        format!(
+                            "batch_norm({}, BatchNormConfig {{ eps: 1e-6, ..Default::default() }}, {})?",
+                            channels,
+                            backend.path(&name, "bn")
+                        ),
+                    ),
+                };
+                writeln!(fields, "    pub {}: {},", name, field_type)?;
+                writeln!(inits, "            {}: {},", name, init)?;
+
+                let expr = match backend {
+                    RustBackend::Tch => {
+                        format!("{}.apply_t(&self.{}, train)", var(layer.from_indexes), name)
+                    }
+                    RustBackend::Candle => format!(
+                        "self.{}.forward_t(&{}, train)?",
+                        name,
+                        var(layer.from_indexes)
+                    ),
+                };
+                writeln!(forward, "        let {} = {};", name, expr)?;
+            }
+            LayerBase::Shortcut(ShortcutLayerBase {
+                config: ShortcutConfig { activation, .. },
+                from_indexes,
+                ..
+            }) => {
+                let mut inputs = from_indexes.iter().map(|&position| var(position));
+                let first = inputs.next().unwrap();
+                let expr = inputs.fold(first, |sum, input| match backend {
+                    RustBackend::Tch => format!("&({}) + &{}", sum, input),
+                    RustBackend::Candle => format!("(&({}) + &{})?", sum, input),
+                });
+                writeln!(forward, "        let {} = {};", name, expr)?;
+                if *activation != Activation::Linear {
+                    writeln!(
+                        forward,
+                        "        let {} = {};",
+                        name,
+                        backend.activation_expr(&name, *activation).unwrap()
+                    )?;
+                }
+            }
+            LayerBase::MaxPool(MaxPoolLayerBase {
+                config:
+                    MaxPoolConfig {
+                        stride_x,
+                        stride_y,
+                        size,
+                        padding,
+                        ..
+                    },
+                from_indexes,
+                ..
+            }) => {
+                let [pad_begin, pad_end] = maxpool_padding(*padding);
+                let input = var(*from_indexes);
+                let expr = match backend {
+                    RustBackend::Tch => {
+                        let input = if *padding > 0 {
+                            format!(
+                                "{}.replication_pad2d(&[{}, {}, {}, {}])",
+                                input, pad_begin, pad_end, pad_begin, pad_end
+                            )
+                        } else {
+                            input
+                        };
+                        format!(
+                            "{}.max_pool2d(&[{}, {}], &[{}, {}], &[0, 0], &[1, 1], false)",
+                            input, size, size, stride_y, stride_x
+                        )
+                    }
+                    RustBackend::Candle => {
+                        let input = if *padding > 0 {
+                            format!(
+                                "{}.pad_with_same(2, {}, {})?.pad_with_same(3, {}, {})?",
+                                input, pad_begin, pad_end, pad_begin, pad_end
+                            )
+                        } else {
+                            input
+                        };
+                        format!(
+                            "{}.max_pool2d_with_stride(({}, {}), ({}, {}))?",
+                            input, size, size, stride_y, stride_x
+                        )
+                    }
+                };
+                writeln!(forward, "        let {} = {};", name, expr)?;
+            }
+            LayerBase::Route(RouteLayerBase {
+                config,
+                from_indexes,
+                input_shape,
+                ..
+            }) => {
+                let group_id = config.group.group_id();
+                let num_groups = config.group.num_groups();
+
+                let inputs: Vec<_> = izip!(from_indexes, input_shape)
+                    .map(|(&position, &[_h, _w, in_c])| {
+                        if num_groups == 1 {
+                            var(position)
+                        } else {
+                            let group_size = in_c / num_groups;
+                            format!(
+                                "{}.narrow(1, {}, {}){}",
+                                var(position),
+                                group_size * group_id,
+                                group_size,
+                                try_op
+                            )
+                        }
+                    })
+                    .collect();
+
+                let expr = match (inputs.len(), num_groups, backend) {
+                    (1, 1, RustBackend::Tch) => format!("{}.shallow_clone()", inputs[0]),
+                    (1, 1, RustBackend::Candle) => format!("{}.clone()", inputs[0]),
+                    (1, _, _) => inputs[0].clone(),
+                    _ => format!(
+                        "Tensor::cat(&[{}], 1){}",
+                        inputs.iter().map(|input| format!("&{}", input)).join(", "),
+                        try_op
+                    ),
+                };
+                writeln!(forward, "        let {} = {};", name, expr)?;
+            }
+            LayerBase::UpSample(UpSampleLayerBase {
+                config: UpSampleConfig { .. },
+                from_indexes,
+                output_shape: [out_h, out_w, _c],
+                ..
+            }) => {
+                let expr = match backend {
+                    RustBackend::Tch => format!(
+                        "{}.upsample_nearest2d(&[{}, {}], None, None)",
+                        var(*from_indexes),
+                        out_h,
+                        out_w
+                    ),
+                    RustBackend::Candle => format!(
+                        "{}.upsample_nearest2d({}, {})?",
+                        var(*from_indexes),
+                        out_h,
+                        out_w
+                    ),
+                };
+                writeln!(forward, "        let {} = {};", name, expr)?;
+            }
+            LayerBase::Yolo(yolo) => {
+                outputs.push(var(yolo_output(yolo)));
+            }
+            LayerBase::Implicit(_)
+            | LayerBase::AvgPool(_)
+            | LayerBase::ScaleChannels(_)
+            | LayerBase::Dropout(_)
+            | LayerBase::Softmax(_)
+            | LayerBase::Region(_)
+            | LayerBase::ConvLstm(_)
+            | LayerBase::Crnn(_)
+            | LayerBase::Lstm(_)
+            | LayerBase::Sam(_)
+            | LayerBase::LocalAvgPool(_)
+            | LayerBase::Local(_)
+            | LayerBase::Reorg(_)
+            | LayerBase::GaussianYolo(_)
+            | LayerBase::Custom(_) => unreachable!("please report bug"),
+        }
+    }
+
+    let source = match backend {
+        RustBackend::Tch => format!(
+            "{}
+#[derive(Debug)]
+pub struct Model {{
+{}}}
+
+impl Model {{
+    pub fn new(vs: &nn::Path) -> Self {{
+        Self {{
+{}        }}
+    }}
+
+    pub fn forward_t(&self, input: &Tensor, train: bool) -> Vec<Tensor> {{
+{}        vec![{}]
+    }}
+}}
+",
+            TCH_PRELUDE,
+            fields,
+            inits,
+            forward,
+            outputs.join(", ")
+        ),
+        RustBackend::Candle => format!(
+            "{}
+#[derive(Debug)]
+pub struct Model {{
+{}}}
+
+impl Model {{
+    pub fn new(vb: VarBuilder) -> Result<Self> {{
+        Ok(Self {{
+{}        }})
+    }}
+
+    pub fn forward_t(&self, input: &Tensor, train: bool) -> Result<Vec<Tensor>> {{
+{}        Ok(vec![{}])
+    }}
+}}
+",
+            CANDLE_PRELUDE,
+            fields,
+            inits,
+            forward,
+            outputs.join(", ")
+        ),
+    };
+
+    Ok(source)
+}
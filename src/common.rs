@@ -1,6 +1,5 @@
 pub use anyhow::{bail, ensure, format_err, Error, Result};
-pub use binread::{prelude::*, BinReaderExt};
-pub use byteorder::{LittleEndian, ReadBytesExt};
+pub use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
 pub use derivative::Derivative;
 pub use indexmap::{IndexMap, IndexSet};
 pub use itertools::{izip, Itertools};
@@ -26,9 +25,10 @@ pub use std::{
     fs::{self, File},
     hash::Hash,
     hash::Hasher,
-    io::{prelude::*, BufReader},
+    io::{prelude::*, BufReader, BufWriter},
     iter, mem,
     num::{NonZeroU64, NonZeroUsize},
+    ops::Range,
     path::{Path, PathBuf},
     slice,
     str::FromStr,
@@ -0,0 +1,63 @@
+use crate::common::*;
+
+// the magic number of zstd frames. weights files start with the major version,
+// which is never this large, so compressed files are told apart by content.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+pub(crate) fn is_zstd_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "zst")
+}
+
+// decompress the stream if it starts with a zstd frame, otherwise pass it through
+pub(crate) fn maybe_decompress<'a, R>(mut reader: R) -> Result<Box<dyn BufRead + 'a>>
+where
+    R: BufRead + 'a,
+{
+    if !reader.fill_buf()?.starts_with(&ZSTD_MAGIC) {
+        return Ok(Box::new(reader));
+    }
+
+    #[cfg(feature = "zstd")]
+    {
+        let decoder = zstd::stream::read::Decoder::with_buffer(reader)?;
+        Ok(Box::new(BufReader::new(decoder)))
+    }
+    #[cfg(not(feature = "zstd"))]
+    bail!("the weights are compressed by zstd, which requires the zstd feature")
+}
+
+pub(crate) fn open_file(path: &Path) -> Result<Box<dyn BufRead>> {
+    maybe_decompress(BufReader::new(File::open(path)?))
+}
+
+pub(crate) fn read_file(path: &Path) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    open_file(path)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+// create the file and write the contents by write_fn, which are compressed if
+// the path ends with .zst
+pub(crate) fn write_file<F>(path: &Path, write_fn: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    if !is_zstd_path(path) {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_fn(&mut writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+
+    #[cfg(feature = "zstd")]
+    {
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder =
+            zstd::stream::write::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        write_fn(&mut encoder)?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+    #[cfg(not(feature = "zstd"))]
+    bail!("saving {} requires the zstd feature", path.display())
+}
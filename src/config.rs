@@ -1,4 +1,10 @@
-use crate::{common::*, utils::Unzip2};
+use crate::{
+    common::*,
+    progress::{ProgressObserver, Stage},
+    section::expect_section_handler,
+    utils::Unzip2,
+};
+use std::sync::OnceLock;
 
 pub use items::*;
 
@@ -7,7 +13,7 @@ pub trait LayerConfigEx {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(try_from = "Vec<Item>", into = "Vec<Item>")]
+#[serde(try_from = "Sections", into = "Sections")]
 pub struct DarknetConfig {
     pub net: CompoundNetConfig,
     pub layers: Vec<LayerConfig>,
@@ -21,8 +27,638 @@ impl DarknetConfig {
         Ok(Self::from_str(&fs::read_to_string(config_file)?)?)
     }
 
-    pub fn to_string(&self) -> Result<String> {
-        Ok(serde_ini::to_string(self)?)
+    pub fn load_with_progress<P>(
+        config_file: P,
+        observer: &mut dyn ProgressObserver,
+    ) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::parse_with_progress(&fs::read_to_string(config_file)?, observer)
+    }
+
+    pub fn parse_with_progress(text: &str, observer: &mut dyn ProgressObserver) -> Result<Self> {
+        // the ini parser works on the whole text, so there are no per-layer events
+        observer.stage_started(Stage::Parse, None);
+        let config = Self::from_str(text)?;
+        observer.stage_finished(Stage::Parse, text.len() as u64);
+        Ok(config)
+    }
+
+    // parse every .cfg file in the directory in file name order, the failure of
+    // one file does not stop the others
+    pub fn load_dir<P>(dir: P) -> Result<impl Iterator<Item = (PathBuf, Result<Self>)>>
+    where
+        P: AsRef<Path>,
+    {
+        let paths = list_cfg_files(dir.as_ref())?;
+        Ok(paths.into_iter().map(|path| {
+            let result = Self::load(&path);
+            (path, result)
+        }))
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn par_load_dir<P>(
+        dir: P,
+    ) -> Result<impl rayon::iter::IndexedParallelIterator<Item = (PathBuf, Result<Self>)>>
+    where
+        P: AsRef<Path>,
+    {
+        use rayon::prelude::*;
+
+        let paths = list_cfg_files(dir.as_ref())?;
+        Ok(paths.into_par_iter().map(|path| {
+            let result = Self::load(&path);
+            (path, result)
+        }))
+    }
+
+    pub fn to_cfg_string(&self) -> Result<String> {
+        self.to_cfg_string_with(FloatFormat::default())
+    }
+
+    pub fn to_cfg_string_with(&self, format: FloatFormat) -> Result<String> {
+        let sections = FormattedSections {
+            sections: Sections(self.clone().into()),
+            format,
+        };
+        Ok(serde_ini::to_string(&sections)?)
+    }
+
+    // check that DarknetConfig -> Vec<Item> -> String -> DarknetConfig keeps the
+    // layer order and the section counts and restores an equal config
+    pub fn roundtrip_check(&self) -> Result<()> {
+        let items: Vec<Item> = self.clone().into();
+        let item_sections: Vec<_> = items.iter().map(|item| item.section_name()).collect();
+        ensure!(
+            items.len() == self.layers.len() + 1,
+            "expect {} items, but get {}",
+            self.layers.len() + 1,
+            items.len()
+        );
+
+        let text = serde_ini::to_string(&Sections(items.clone()))?;
+        let text_sections: Vec<_> = text
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix('[')?.strip_suffix(']'))
+            .collect();
+        if let Some(index) = (0..item_sections.len().max(text_sections.len()))
+            .find(|&index| item_sections.get(index) != text_sections.get(index))
+        {
+            bail!(
+                "section {} is serialized as {:?} instead of {:?}",
+                index,
+                text_sections.get(index),
+                item_sections.get(index)
+            );
+        }
+
+        let restored: DarknetConfig = text.parse()?;
+        ensure!(
+            restored.layers.len() == self.layers.len(),
+            "expect {} layers after the round trip, but get {}",
+            self.layers.len(),
+            restored.layers.len()
+        );
+        if let Some((layer_index, (orig, restored))) = self
+            .layers
+            .iter()
+            .zip(&restored.layers)
+            .enumerate()
+            .find(|(_, (orig, restored))| orig != restored)
+        {
+            bail!(
+                "layer {} changes from {:?} to {:?} after the round trip",
+                layer_index,
+                orig,
+                restored
+            );
+        }
+        ensure!(
+            restored.net == self.net,
+            "the net section changes after the round trip"
+        );
+        Ok(())
+    }
+
+    // rewrite layer references to absolute indexes, so that configs differing only
+    // in the indexing style compare and hash equally
+    pub fn canonicalize(&self) -> Self {
+        let absolute = |index: LayerIndex, layer_index: usize| {
+            // out of range references are kept and left to the model builder to reject
+            index
+                .to_absolute(layer_index)
+                .map(LayerIndex::Absolute)
+                .unwrap_or(index)
+        };
+        let absolute_set = |indexes: &IndexSet<LayerIndex>, layer_index: usize| {
+            indexes
+                .iter()
+                .map(|&index| absolute(index, layer_index))
+                .collect::<IndexSet<_>>()
+        };
+
+        let mut config = self.clone();
+        config
+            .layers
+            .iter_mut()
+            .enumerate()
+            .for_each(|(layer_index, layer)| match layer {
+                LayerConfig::Convolutional(conf) => {
+                    conf.share_index = conf.share_index.map(|index| absolute(index, layer_index));
+                }
+                LayerConfig::Route(conf) => {
+                    conf.layers = absolute_set(&conf.layers, layer_index);
+                }
+                LayerConfig::Shortcut(conf) => {
+                    conf.from = absolute_set(&conf.from, layer_index);
+                }
+                LayerConfig::ScaleChannels(conf) => {
+                    conf.from = absolute(conf.from, layer_index);
+                }
+                LayerConfig::Sam(conf) => {
+                    conf.from = absolute(conf.from, layer_index);
+                }
+                LayerConfig::Yolo(conf) => {
+                    conf.embedding_layer = conf
+                        .embedding_layer
+                        .map(|index| absolute(index, layer_index));
+                }
+                LayerConfig::GaussianYolo(conf) => {
+                    conf.yolo.embedding_layer = conf
+                        .yolo
+                        .embedding_layer
+                        .map(|index| absolute(index, layer_index));
+                }
+                LayerConfig::Custom(conf) => {
+                    conf.from = absolute_set(&conf.from, layer_index);
+                }
+                LayerConfig::Connected(_)
+                | LayerConfig::MaxPool(_)
+                | LayerConfig::UpSample(_)
+                | LayerConfig::BatchNorm(_)
+                | LayerConfig::Implicit(_)
+                | LayerConfig::AvgPool(_)
+                | LayerConfig::Dropout(_)
+                | LayerConfig::Softmax(_)
+                | LayerConfig::Region(_)
+                | LayerConfig::Reorg(_)
+                | LayerConfig::Local(_)
+                | LayerConfig::LocalAvgPool(_)
+                | LayerConfig::Lstm(_)
+                | LayerConfig::Crnn(_)
+                | LayerConfig::ConvLstm(_) => (),
+            });
+        config
+    }
+}
+
+// compare configs modulo cosmetics, defaults and key order are already resolved
+// by the parser and layer references are compared by their absolute indexes
+pub fn semantic_eq(lhs: &DarknetConfig, rhs: &DarknetConfig) -> bool {
+    // IndexSet equality ignores the order, while the order of route inputs
+    // decides the channel order
+    let ordered_references = |layer: &LayerConfig| -> Option<Vec<LayerIndex>> {
+        match layer {
+            LayerConfig::Route(conf) => Some(conf.layers.iter().cloned().collect()),
+            LayerConfig::Shortcut(conf) => Some(conf.from.iter().cloned().collect()),
+            LayerConfig::Custom(conf) => Some(conf.from.iter().cloned().collect()),
+            _ => None,
+        }
+    };
+
+    let lhs = lhs.canonicalize();
+    let rhs = rhs.canonicalize();
+    lhs == rhs
+        && lhs
+            .layers
+            .iter()
+            .zip(&rhs.layers)
+            .all(|(lhs, rhs)| ordered_references(lhs) == ordered_references(rhs))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum FloatFormat {
+    // the shortest text that parses back to the same f32, which is the
+    // precision darknet reads the values in
+    Shortest,
+    // the shortest text that parses back to the same f64
+    #[default]
+    Exact,
+    // at most the given number of fractional digits, trailing zeros are trimmed
+    Fixed(usize),
+}
+
+impl FloatFormat {
+    pub fn format(&self, value: f64) -> String {
+        let text = match *self {
+            Self::Shortest if (value as f32).is_finite() => (value as f32).to_string(),
+            Self::Shortest | Self::Exact => value.to_string(),
+            Self::Fixed(precision) => {
+                let text = format!("{:.*}", precision, value);
+                if text.contains('.') {
+                    text.trim_end_matches('0').to_owned()
+                } else {
+                    text
+                }
+            }
+        };
+
+        // keep the fractional part so that the value reads as a float
+        if !value.is_finite() {
+            text
+        } else if text.ends_with('.') {
+            format!("{}0", text)
+        } else if !text.contains('.') {
+            format!("{}.0", text)
+        } else {
+            text
+        }
+    }
+}
+
+// how keys that no section option recognizes are treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum UnknownKeys {
+    #[default]
+    Ignore,
+    // log a warning and continue
+    Warn,
+    Deny,
+}
+
+// keys of darknet forks that are not modeled by this crate and carry no
+// meaning for the architecture, in "section.key" form
+pub const FORK_KEYS: &[&str] = &[
+    "net.ema_alpha",
+    "net.equidistant_point",
+    "net.badlabels_rejection_percentage",
+    "net.num_sigmas_reject_badlabels",
+    "net.use_cuda_graph",
+];
+
+// the section names parsed by this crate, other names need a SectionHandler
+pub const BUILTIN_SECTIONS: &[&str] = &[
+    "net",
+    "connected",
+    "convolutional",
+    "route",
+    "shortcut",
+    "maxpool",
+    "upsample",
+    "yolo",
+    "batchnorm",
+    "implicit",
+    "implicit_add",
+    "implicit_mul",
+    "avgpool",
+    "scale_channels",
+    "dropout",
+    "softmax",
+    "region",
+    "Gaussian_yolo",
+    "conv_lstm",
+    "crnn",
+    "lstm",
+    "sam",
+    "local_avgpool",
+    "local",
+    "reorg",
+    "reorg_old",
+    "reorg3d",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ParseOptions {
+    pub unknown_keys: UnknownKeys,
+    // overrides by section name, e.g. "yolo"
+    pub section_unknown_keys: IndexMap<String, UnknownKeys>,
+    // keys that are ignored silently regardless of the policy, either "key" for
+    // any section or "section.key"
+    pub allowed_keys: IndexSet<String>,
+}
+
+impl ParseOptions {
+    // reject unknown keys except the known fork keys
+    pub fn strict() -> Self {
+        Self {
+            unknown_keys: UnknownKeys::Deny,
+            section_unknown_keys: IndexMap::new(),
+            allowed_keys: FORK_KEYS.iter().map(|key| key.to_string()).collect(),
+        }
+    }
+
+    fn policy(&self, section: &str, key: &str) -> UnknownKeys {
+        // extension keys are kept, see CommonLayerOptions
+        if key.starts_with("x_")
+            || self.allowed_keys.contains(key)
+            || self.allowed_keys.contains(&format!("{}.{}", section, key))
+        {
+            return UnknownKeys::Ignore;
+        }
+        self.section_unknown_keys
+            .get(section)
+            .cloned()
+            .unwrap_or(self.unknown_keys)
+    }
+}
+
+// keys read under another name, which are written back under the canonical name
+const KEY_ALIASES: &[(&str, &str, &str)] = &[
+    ("net", "cutmux", "cutmix"),
+    ("route", "groupd_id", "group_id"),
+    ("softmax", "hierarchy", "tree"),
+];
+
+// the sections that fall back to 20 classes if the key is missing
+const DEFAULT_CLASSES_SECTIONS: &[&str] = &["yolo", "Gaussian_yolo", "region"];
+
+// the compatibility fixes applied while parsing
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ParseReport {
+    pub normalizations: Vec<Normalization>,
+}
+
+impl ParseReport {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Normalization {
+    // index of the section in the file, [net] is 0
+    pub section_index: usize,
+    pub section: String,
+    #[serde(flatten)]
+    pub kind: NormalizationKind,
+}
+
+impl Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "section {} [{}]: ", self.section_index, self.section)?;
+        match &self.kind {
+            NormalizationKind::KeyAlias { key, canonical } => {
+                write!(f, "{} is read as {}", key, canonical)
+            }
+            NormalizationKind::IgnoredKey { key, reason } => {
+                write!(f, "{} is ignored, {}", key, reason)
+            }
+            NormalizationKind::DefaultedKey { key, value } => {
+                write!(f, "{} is not specified, use default {}", key, value)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum NormalizationKind {
+    #[serde(rename = "key_alias")]
+    KeyAlias { key: String, canonical: String },
+    // the key is overridden by another key
+    #[serde(rename = "ignored_key")]
+    IgnoredKey { key: String, reason: String },
+    // a missing key that darknet falls back on with a warning
+    #[serde(rename = "defaulted_key")]
+    DefaultedKey { key: String, value: String },
+}
+
+impl DarknetConfig {
+    pub fn load_with_report<P>(
+        config_file: P,
+        options: &ParseOptions,
+    ) -> Result<(Self, ParseReport)>
+    where
+        P: AsRef<Path>,
+    {
+        Self::parse_with_report(&fs::read_to_string(config_file)?, options)
+    }
+
+    pub fn parse_with_report(text: &str, options: &ParseOptions) -> Result<(Self, ParseReport)> {
+        let config = Self::parse_with_options(text, options)?;
+        let report = ParseReport {
+            normalizations: scan_normalizations(text),
+        };
+        Ok((config, report))
+    }
+
+    pub fn load_with_options<P>(config_file: P, options: &ParseOptions) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::parse_with_options(&fs::read_to_string(config_file)?, options)
+    }
+
+    pub fn parse_with_options(text: &str, options: &ParseOptions) -> Result<Self> {
+        let (config, dropped_keys) =
+            serde_extensions::collect_dropped_keys(|| Self::from_str(text));
+        let config = config?;
+
+        // the layer sections report their dropped keys during deserialization,
+        // while the keys of [net] are checked against its fields
+        let sections = scan_sections(text);
+        ensure!(
+            sections.len() == config.layers.len() + 1 && dropped_keys.len() == config.layers.len(),
+            "unexpected section count, please report bug"
+        );
+        let net_fields = net_fields();
+        let (_, net_keys) = &sections[0];
+        let net_unknown_keys: Vec<_> = net_keys
+            .iter()
+            .filter(|key| !net_fields.contains(&key.as_str()))
+            .cloned()
+            .collect();
+
+        let mut errors = vec![];
+        let unknown_keys = iter::once(net_unknown_keys).chain(dropped_keys);
+        for (section_index, ((name, _), keys)) in sections.iter().zip(unknown_keys).enumerate() {
+            let (denied, warned): (Vec<_>, Vec<_>) = keys
+                .iter()
+                .map(|key| (key, options.policy(name, key)))
+                .filter(|(_, policy)| *policy != UnknownKeys::Ignore)
+                .partition(|(_, policy)| *policy == UnknownKeys::Deny);
+            let describe = |keys: &[(&String, UnknownKeys)]| {
+                format!(
+                    "section {} [{}]: unknown keys {}",
+                    section_index,
+                    name,
+                    keys.iter().map(|(key, _)| key).join(", ")
+                )
+            };
+            if !warned.is_empty() {
+                warn!("{}", describe(&warned));
+            }
+            if !denied.is_empty() {
+                errors.push(describe(&denied));
+            }
+        }
+
+        ensure!(errors.is_empty(), "{}", errors.join("; "));
+        Ok(config)
+    }
+}
+
+// the section names and option keys in file order
+fn scan_sections(text: &str) -> Vec<(String, Vec<String>)> {
+    scan_entries(text)
+        .into_iter()
+        .map(|(name, entries)| (name, entries.into_iter().map(|(key, _)| key).collect()))
+        .collect()
+}
+
+// the section names and key-value pairs in file order
+pub(crate) fn scan_entries(text: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = vec![];
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            sections.push((line[1..(line.len() - 1)].trim().to_owned(), vec![]));
+        } else if let (Some((_, entries)), Some(pos)) = (sections.last_mut(), line.find('=')) {
+            entries.push((
+                line[..pos].trim().to_owned(),
+                line[(pos + 1)..].trim().to_owned(),
+            ));
+        }
+    }
+    sections
+}
+
+// the fixes that the parser applies silently or with a warning only
+fn scan_normalizations(text: &str) -> Vec<Normalization> {
+    let mut normalizations = vec![];
+    for (section_index, (section, entries)) in scan_entries(text).into_iter().enumerate() {
+        let value = |key: &str| {
+            entries
+                .iter()
+                .find(|(other, _)| other == key)
+                .map(|(_, value)| value.as_str())
+        };
+        let mut push = |kind| {
+            normalizations.push(Normalization {
+                section_index,
+                section: section.clone(),
+                kind,
+            })
+        };
+
+        for (key, _) in &entries {
+            let canonical = KEY_ALIASES
+                .iter()
+                .find(|(name, alias, _)| *name == section && alias == key);
+            if let Some((_, _, canonical)) = canonical {
+                push(NormalizationKind::KeyAlias {
+                    key: key.clone(),
+                    canonical: canonical.to_string(),
+                });
+            }
+        }
+
+        let pad = value("pad").is_some_and(|pad| pad != "0");
+        if section == "convolutional" && pad && value("padding").is_some() {
+            push(NormalizationKind::IgnoredKey {
+                key: "padding".into(),
+                reason: "pad=1 sets the padding to size / 2".into(),
+            });
+        }
+
+        if DEFAULT_CLASSES_SECTIONS.contains(&section.as_str()) && value("classes").is_none() {
+            push(NormalizationKind::DefaultedKey {
+                key: "classes".into(),
+                value: "20".into(),
+            });
+        }
+    }
+    normalizations
+}
+
+// the keys of the [net] section, captured from the derived deserializer
+fn net_fields() -> &'static [&'static str] {
+    struct FieldsCapture<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de, 'a> Deserializer<'de> for FieldsCapture<'a> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            Err(de::value::Error::custom("expect a struct"))
+        }
+
+        fn deserialize_struct<V>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            *self.0 = fields;
+            Err(de::value::Error::custom("fields captured"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = RawNetConfig::deserialize(FieldsCapture(&mut fields));
+    fields
+}
+
+fn list_cfg_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| -> Result<_> { Ok(entry?.path()) })
+        .try_collect()?;
+    paths.retain(|path| {
+        path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some("cfg")
+    });
+    paths.sort();
+    Ok(paths)
+}
+
+// a header with the section counts followed by one indexed line per layer
+impl Display for DarknetConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { net, layers } = self;
+
+        let counts = layers.iter().map(|layer| layer.kind()).fold(
+            IndexMap::<_, usize>::new(),
+            |mut counts, kind| {
+                *counts.entry(kind).or_default() += 1;
+                counts
+            },
+        );
+        let input = match net.input_size {
+            Shape::Hwc([h, w, c]) => format!("{}x{}x{}", h, w, c),
+            Shape::Flat(size) => format!("{}", size),
+        };
+        writeln!(
+            f,
+            "net {}, {} classes, {} layers ({})",
+            input,
+            net.classes,
+            layers.len(),
+            counts
+                .iter()
+                .map(|(kind, count)| format!("{} {}", kind, count))
+                .join(", ")
+        )?;
+
+        let width = layers.len().saturating_sub(1).to_string().len();
+        for (index, layer) in layers.iter().enumerate() {
+            writeln!(f, "{:>width$} {}", index, layer, width = width)?;
+        }
+        Ok(())
     }
 }
 
@@ -30,7 +666,17 @@ impl FromStr for DarknetConfig {
     type Err = Error;
 
     fn from_str(text: &str) -> Result<Self, Self::Err> {
-        Ok(serde_ini::from_str(text)?)
+        let mut config: Self = serde_ini::from_str(text)?;
+
+        // the first section is [net], which is checked on conversion
+        let net_fields = net_fields();
+        if let Some((_, keys)) = scan_sections(text).into_iter().next() {
+            config.net.provenance = Provenance::new(
+                keys.into_iter()
+                    .filter(|key| net_fields.contains(&key.as_str())),
+            );
+        }
+        Ok(config)
     }
 }
 
@@ -53,10 +699,11 @@ impl TryFrom<Vec<Item>> for DarknetConfig {
 
         // extract global options from yolo item
         let classes = {
-            let (classes_vec, anchors_vec) = items
+            let (mut classes_vec, anchors_vec) = items
                 .iter()
                 .filter_map(|item| match item {
                     Item::Yolo(yolo) => Some(yolo),
+                    Item::GaussianYolo(conf) => Some(&conf.yolo),
                     _ => None,
                 })
                 .map(|yolo| {
@@ -69,20 +716,26 @@ impl TryFrom<Vec<Item>> for DarknetConfig {
                     (classes, anchors)
                 })
                 .unzip_n_vec();
+            classes_vec.extend(items.iter().filter_map(|item| match item {
+                Item::Region(region) => Some(region.classes),
+                _ => None,
+            }));
 
+            // classifiers have no yolo layers, their classes are given by the
+            // data file instead
             let classes = {
                 let classes_set: HashSet<_> = classes_vec.iter().cloned().collect();
                 ensure!(
-                    classes_set.len() == 1,
-                    "the classes of every yolo layer must be equal"
+                    classes_set.len() <= 1,
+                    "the classes of every yolo and region layer must be equal"
                 );
-                classes_vec[0]
+                classes_vec.first().cloned().unwrap_or(0)
             };
 
             {
                 let anchors_set: HashSet<_> = anchors_vec.iter().collect();
                 ensure!(
-                    anchors_set.len() == 1,
+                    anchors_set.len() <= 1,
                     "the anchors of every yolo layer must be equal"
                 );
             }
@@ -126,7 +779,7 @@ impl TryFrom<Vec<Item>> for DarknetConfig {
                 blur,
                 gaussian_noise,
                 mixup,
-                cutmux,
+                cutmix,
                 mosaic,
                 letter_box,
                 mosaic_bound,
@@ -147,6 +800,7 @@ impl TryFrom<Vec<Item>> for DarknetConfig {
                 power,
                 policy,
                 burn_in,
+                provenance,
             } = net;
 
             CompoundNetConfig {
@@ -175,9 +829,7 @@ impl TryFrom<Vec<Item>> for DarknetConfig {
                 flip,
                 blur,
                 gaussian_noise,
-                mixup,
-                cutmux,
-                mosaic,
+                augmentation_mix: AugmentationMix::from_keys(mixup, cutmix, mosaic),
                 letter_box,
                 mosaic_bound,
                 contrastive,
@@ -198,6 +850,91 @@ impl TryFrom<Vec<Item>> for DarknetConfig {
                 policy,
                 burn_in,
                 classes,
+                provenance,
+            }
+        };
+
+        // the anchors of yolo layers are selected by their masks
+        let compound_yolo = |layer: YoloConfig| {
+            let YoloConfig {
+                mask,
+                max_boxes,
+                max_delta,
+                counters_per_class,
+                label_smooth_eps,
+                scale_x_y,
+                new_coords,
+                objectness_smooth,
+                iou_normalizer,
+                obj_normalizer,
+                cls_normalizer,
+                delta_normalizer,
+                iou_loss,
+                iou_thresh_kind,
+                beta_nms,
+                nms_kind,
+                yolo_point,
+                jitter,
+                resize,
+                focal_loss,
+                ignore_thresh,
+                truth_thresh,
+                iou_thresh,
+                random,
+                track_history_size,
+                sim_thresh,
+                dets_for_track,
+                dets_for_show,
+                track_ciou_norm,
+                embedding_layer,
+                map,
+                anchors,
+                common,
+                ..
+            } = layer;
+
+            // darknet takes all anchors if the mask is omitted
+            let anchors: Vec<_> = match mask {
+                Some(mask) => mask
+                    .into_iter()
+                    .map(|index| anchors[index as usize].clone())
+                    .collect(),
+                None => anchors,
+            };
+
+            CompoundYoloConfig {
+                max_boxes,
+                max_delta,
+                counters_per_class,
+                label_smooth_eps,
+                scale_x_y,
+                new_coords,
+                objectness_smooth,
+                iou_normalizer,
+                obj_normalizer,
+                cls_normalizer,
+                delta_normalizer,
+                iou_loss,
+                iou_thresh_kind,
+                beta_nms,
+                nms_kind,
+                yolo_point,
+                jitter,
+                resize,
+                focal_loss,
+                ignore_thresh,
+                truth_thresh,
+                iou_thresh,
+                random,
+                track_history_size,
+                sim_thresh,
+                dets_for_track,
+                dets_for_show,
+                track_ciou_norm,
+                embedding_layer,
+                map,
+                anchors,
+                common,
             }
         };
 
@@ -211,83 +948,45 @@ impl TryFrom<Vec<Item>> for DarknetConfig {
                     Item::Shortcut(layer) => LayerConfig::Shortcut(layer),
                     Item::MaxPool(layer) => LayerConfig::MaxPool(layer),
                     Item::UpSample(layer) => LayerConfig::UpSample(layer),
-                    Item::Yolo(layer) => {
-                        let YoloConfig {
-                            mask,
-                            max_boxes,
-                            max_delta,
-                            counters_per_class,
-                            label_smooth_eps,
-                            scale_x_y,
-                            objectness_smooth,
-                            iou_normalizer,
-                            obj_normalizer,
-                            cls_normalizer,
-                            delta_normalizer,
-                            iou_loss,
-                            iou_thresh_kind,
-                            beta_nms,
-                            nms_kind,
-                            yolo_point,
-                            jitter,
-                            resize,
-                            focal_loss,
-                            ignore_thresh,
-                            truth_thresh,
-                            iou_thresh,
-                            random,
-                            track_history_size,
-                            sim_thresh,
-                            dets_for_track,
-                            dets_for_show,
-                            track_ciou_norm,
-                            embedding_layer,
-                            map,
-                            anchors,
-                            common,
-                            ..
-                        } = layer;
-
-                        let anchors: Vec<_> = mask
-                            .into_iter()
-                            .map(|index| anchors[index as usize].clone())
-                            .collect();
-
-                        LayerConfig::Yolo(CompoundYoloConfig {
-                            max_boxes,
-                            max_delta,
-                            counters_per_class,
-                            label_smooth_eps,
-                            scale_x_y,
-                            objectness_smooth,
-                            iou_normalizer,
-                            obj_normalizer,
-                            cls_normalizer,
-                            delta_normalizer,
-                            iou_loss,
-                            iou_thresh_kind,
-                            beta_nms,
-                            nms_kind,
-                            yolo_point,
-                            jitter,
-                            resize,
-                            focal_loss,
-                            ignore_thresh,
-                            truth_thresh,
-                            iou_thresh,
-                            random,
-                            track_history_size,
-                            sim_thresh,
-                            dets_for_track,
-                            dets_for_show,
-                            track_ciou_norm,
-                            embedding_layer,
-                            map,
-                            anchors,
-                            common,
-                        })
-                    }
+                    Item::Yolo(layer) => LayerConfig::Yolo(compound_yolo(layer)),
+                    Item::GaussianYolo(GaussianYoloConfig {
+                        yolo,
+                        uc_normalizer,
+                    }) => LayerConfig::GaussianYolo(CompoundGaussianYoloConfig {
+                        yolo: compound_yolo(yolo),
+                        uc_normalizer,
+                    }),
                     Item::BatchNorm(layer) => LayerConfig::BatchNorm(layer),
+                    Item::Implicit(layer) => LayerConfig::Implicit(layer),
+                    Item::ImplicitAdd(layer) => LayerConfig::Implicit(ImplicitConfig {
+                        mode: ImplicitMode::Add,
+                        ..layer
+                    }),
+                    Item::ImplicitMul(layer) => LayerConfig::Implicit(ImplicitConfig {
+                        mode: ImplicitMode::Mul,
+                        ..layer
+                    }),
+                    Item::AvgPool(layer) => LayerConfig::AvgPool(layer),
+                    Item::ScaleChannels(layer) => LayerConfig::ScaleChannels(layer),
+                    Item::Dropout(layer) => LayerConfig::Dropout(layer),
+                    Item::Softmax(layer) => LayerConfig::Softmax(layer),
+                    Item::Region(layer) => LayerConfig::Region(layer),
+                    Item::ConvLstm(layer) => LayerConfig::ConvLstm(layer),
+                    Item::Crnn(layer) => LayerConfig::Crnn(layer),
+                    Item::Lstm(layer) => LayerConfig::Lstm(layer),
+                    Item::Sam(layer) => LayerConfig::Sam(layer),
+                    Item::LocalAvgPool(layer) => LayerConfig::LocalAvgPool(layer),
+                    Item::Local(layer) => LayerConfig::Local(layer),
+                    Item::Reorg(layer) => LayerConfig::Reorg(layer),
+                    Item::ReorgOld(layer) => LayerConfig::Reorg(ReorgConfig {
+                        mode: ReorgMode::Old,
+                        ..layer
+                    }),
+                    Item::Reorg3d(layer) => LayerConfig::Reorg(ReorgConfig {
+                        mode: ReorgMode::Reorg3d,
+                        ..layer
+                    }),
+                    Item::Custom(layer) => LayerConfig::Custom(layer),
                     Item::Net(_layer) => bail!("the 'net' layer must appear in the first section"),
                 };
                 Ok(layer)
@@ -316,6 +1015,309 @@ pub enum LayerConfig {
     Yolo(CompoundYoloConfig),
     #[serde(rename = "batchnorm")]
     BatchNorm(BatchNormConfig),
+    #[serde(rename = "implicit")]
+    Implicit(ImplicitConfig),
+    #[serde(rename = "avgpool")]
+    AvgPool(AvgPoolConfig),
+    #[serde(rename = "scale_channels")]
+    ScaleChannels(ScaleChannelsConfig),
+    #[serde(rename = "dropout")]
+    Dropout(DropoutConfig),
+    #[serde(rename = "softmax")]
+    Softmax(SoftmaxConfig),
+    #[serde(rename = "region")]
+    Region(RegionConfig),
+    #[serde(rename = "gaussian_yolo")]
+    GaussianYolo(CompoundGaussianYoloConfig),
+    #[serde(rename = "conv_lstm")]
+    ConvLstm(ConvLstmConfig),
+    #[serde(rename = "crnn")]
+    Crnn(CrnnConfig),
+    #[serde(rename = "lstm")]
+    Lstm(LstmConfig),
+    #[serde(rename = "sam")]
+    Sam(SamConfig),
+    #[serde(rename = "local_avgpool")]
+    LocalAvgPool(LocalAvgPoolConfig),
+    #[serde(rename = "local")]
+    Local(LocalConfig),
+    #[serde(rename = "reorg")]
+    Reorg(ReorgConfig),
+    #[serde(rename = "custom")]
+    Custom(CustomConfig),
+}
+
+impl LayerConfig {
+    pub fn kind(&self) -> &'static str {
+        LayerConfigRef::from(self).kind()
+    }
+}
+
+// a borrowed layer config, which model layers hand out without cloning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerConfigRef<'a> {
+    Connected(&'a ConnectedConfig),
+    Convolutional(&'a ConvolutionalConfig),
+    Route(&'a RouteConfig),
+    Shortcut(&'a ShortcutConfig),
+    MaxPool(&'a MaxPoolConfig),
+    UpSample(&'a UpSampleConfig),
+    Yolo(&'a CompoundYoloConfig),
+    BatchNorm(&'a BatchNormConfig),
+    Implicit(&'a ImplicitConfig),
+    AvgPool(&'a AvgPoolConfig),
+    ScaleChannels(&'a ScaleChannelsConfig),
+    Dropout(&'a DropoutConfig),
+    Softmax(&'a SoftmaxConfig),
+    Region(&'a RegionConfig),
+    GaussianYolo(&'a CompoundGaussianYoloConfig),
+    ConvLstm(&'a ConvLstmConfig),
+    Crnn(&'a CrnnConfig),
+    Lstm(&'a LstmConfig),
+    Sam(&'a SamConfig),
+    LocalAvgPool(&'a LocalAvgPoolConfig),
+    Local(&'a LocalConfig),
+    Reorg(&'a ReorgConfig),
+    Custom(&'a CustomConfig),
+}
+
+impl LayerConfigRef<'_> {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Connected(_) => "connected",
+            Self::Convolutional(_) => "conv",
+            Self::Route(_) => "route",
+            Self::Shortcut(_) => "shortcut",
+            Self::MaxPool(_) => "maxpool",
+            Self::UpSample(_) => "upsample",
+            Self::Yolo(_) => "yolo",
+            Self::BatchNorm(_) => "batchnorm",
+            Self::Implicit(_) => "implicit",
+            Self::AvgPool(_) => "avgpool",
+            Self::ScaleChannels(_) => "scale_channels",
+            Self::Dropout(_) => "dropout",
+            Self::Softmax(_) => "softmax",
+            Self::Region(_) => "region",
+            Self::GaussianYolo(_) => "gaussian_yolo",
+            Self::ConvLstm(_) => "conv_lstm",
+            Self::Crnn(_) => "crnn",
+            Self::Lstm(_) => "lstm",
+            Self::Sam(_) => "sam",
+            Self::LocalAvgPool(_) => "local_avgpool",
+            Self::Local(_) => "local",
+            Self::Reorg(_) => "reorg",
+            Self::Custom(_) => "custom",
+        }
+    }
+
+    pub fn cloned(&self) -> LayerConfig {
+        match *self {
+            Self::Connected(layer) => LayerConfig::Connected(layer.clone()),
+            Self::Convolutional(layer) => LayerConfig::Convolutional(layer.clone()),
+            Self::Route(layer) => LayerConfig::Route(layer.clone()),
+            Self::Shortcut(layer) => LayerConfig::Shortcut(layer.clone()),
+            Self::MaxPool(layer) => LayerConfig::MaxPool(layer.clone()),
+            Self::UpSample(layer) => LayerConfig::UpSample(layer.clone()),
+            Self::Yolo(layer) => LayerConfig::Yolo(layer.clone()),
+            Self::BatchNorm(layer) => LayerConfig::BatchNorm(layer.clone()),
+            Self::Implicit(layer) => LayerConfig::Implicit(layer.clone()),
+            Self::AvgPool(layer) => LayerConfig::AvgPool(layer.clone()),
+            Self::ScaleChannels(layer) => LayerConfig::ScaleChannels(layer.clone()),
+            Self::Dropout(layer) => LayerConfig::Dropout(layer.clone()),
+            Self::Softmax(layer) => LayerConfig::Softmax(layer.clone()),
+            Self::Region(layer) => LayerConfig::Region(layer.clone()),
+            Self::GaussianYolo(layer) => LayerConfig::GaussianYolo(layer.clone()),
+            Self::ConvLstm(layer) => LayerConfig::ConvLstm(layer.clone()),
+            Self::Crnn(layer) => LayerConfig::Crnn(layer.clone()),
+            Self::Lstm(layer) => LayerConfig::Lstm(layer.clone()),
+            Self::Sam(layer) => LayerConfig::Sam(layer.clone()),
+            Self::LocalAvgPool(layer) => LayerConfig::LocalAvgPool(layer.clone()),
+            Self::Local(layer) => LayerConfig::Local(layer.clone()),
+            Self::Reorg(layer) => LayerConfig::Reorg(layer.clone()),
+            Self::Custom(layer) => LayerConfig::Custom(layer.clone()),
+        }
+    }
+}
+
+impl<'a> From<&'a LayerConfig> for LayerConfigRef<'a> {
+    fn from(from: &'a LayerConfig) -> Self {
+        match from {
+            LayerConfig::Connected(layer) => Self::Connected(layer),
+            LayerConfig::Convolutional(layer) => Self::Convolutional(layer),
+            LayerConfig::Route(layer) => Self::Route(layer),
+            LayerConfig::Shortcut(layer) => Self::Shortcut(layer),
+            LayerConfig::MaxPool(layer) => Self::MaxPool(layer),
+            LayerConfig::UpSample(layer) => Self::UpSample(layer),
+            LayerConfig::Yolo(layer) => Self::Yolo(layer),
+            LayerConfig::BatchNorm(layer) => Self::BatchNorm(layer),
+            LayerConfig::Implicit(layer) => Self::Implicit(layer),
+            LayerConfig::AvgPool(layer) => Self::AvgPool(layer),
+            LayerConfig::ScaleChannels(layer) => Self::ScaleChannels(layer),
+            LayerConfig::Dropout(layer) => Self::Dropout(layer),
+            LayerConfig::Softmax(layer) => Self::Softmax(layer),
+            LayerConfig::Region(layer) => Self::Region(layer),
+            LayerConfig::GaussianYolo(layer) => Self::GaussianYolo(layer),
+            LayerConfig::ConvLstm(layer) => Self::ConvLstm(layer),
+            LayerConfig::Crnn(layer) => Self::Crnn(layer),
+            LayerConfig::Lstm(layer) => Self::Lstm(layer),
+            LayerConfig::Sam(layer) => Self::Sam(layer),
+            LayerConfig::LocalAvgPool(layer) => Self::LocalAvgPool(layer),
+            LayerConfig::Local(layer) => Self::Local(layer),
+            LayerConfig::Reorg(layer) => Self::Reorg(layer),
+            LayerConfig::Custom(layer) => Self::Custom(layer),
+        }
+    }
+}
+
+impl Display for LayerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&LayerConfigRef::from(self), f)
+    }
+}
+
+// one-line descriptions in the spirit of the darknet layer table, e.g. "conv 3x3/2 256"
+impl Display for LayerConfigRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indexes = |indexes: &IndexSet<LayerIndex>| {
+            indexes
+                .iter()
+                .map(|&index| isize::from(index).to_string())
+                .join(",")
+        };
+        let window = |size: u64, stride_x: u64, stride_y: u64| {
+            if stride_x == stride_y {
+                format!("{}x{}/{}", size, size, stride_x)
+            } else {
+                format!("{}x{}/{}x{}", size, size, stride_y, stride_x)
+            }
+        };
+
+        write!(f, "{}", self.kind())?;
+        match self {
+            Self::Connected(conf) => write!(f, " {}", conf.output)?,
+            Self::Convolutional(conf) => {
+                write!(
+                    f,
+                    " {} {}",
+                    window(conf.size, conf.stride_x, conf.stride_y),
+                    conf.filters
+                )?;
+                if conf.groups > 1 {
+                    write!(f, " g{}", conf.groups)?;
+                }
+                if conf.dilation > 1 {
+                    write!(f, " d{}", conf.dilation)?;
+                }
+            }
+            Self::Route(conf) => {
+                write!(f, " {}", indexes(&conf.layers))?;
+                if conf.group.num_groups() > 1 {
+                    write!(
+                        f,
+                        " group {}/{}",
+                        conf.group.group_id(),
+                        conf.group.num_groups()
+                    )?;
+                }
+            }
+            Self::Shortcut(conf) => write!(f, " {}", indexes(&conf.from))?,
+            Self::MaxPool(conf) => {
+                write!(f, " {}", window(conf.size, conf.stride_x, conf.stride_y))?
+            }
+            Self::UpSample(conf) => {
+                if conf.reverse {
+                    write!(f, " /{}", conf.stride)?
+                } else {
+                    write!(f, " x{}", conf.stride)?
+                }
+            }
+            Self::Yolo(conf) => write!(f, " {} anchors", conf.anchors.len())?,
+            Self::Implicit(conf) => {
+                write!(f, " {}", conf.filters)?;
+                match conf.mode {
+                    ImplicitMode::Standard => (),
+                    ImplicitMode::Add => write!(f, " add")?,
+                    ImplicitMode::Mul => write!(f, " mul")?,
+                }
+            }
+            Self::ScaleChannels(conf) => write!(f, " {}", isize::from(conf.from))?,
+            Self::Dropout(conf) => write!(f, " {}", conf.probability)?,
+            Self::Softmax(conf) => {
+                if conf.tree.is_some() {
+                    write!(f, " tree")?;
+                } else if conf.groups > 1 {
+                    write!(f, " g{}", conf.groups)?;
+                }
+            }
+            Self::Region(conf) => write!(f, " {} anchors", conf.num)?,
+            Self::GaussianYolo(conf) => write!(f, " {} anchors", conf.yolo.anchors.len())?,
+            Self::Reorg(conf) => {
+                write!(f, " /{}", conf.stride)?;
+                match conf.mode {
+                    ReorgMode::Standard => (),
+                    ReorgMode::Old => write!(f, " old")?,
+                    ReorgMode::Reorg3d => write!(f, " 3d")?,
+                }
+            }
+            Self::Local(conf) => write!(
+                f,
+                " {} {}",
+                window(conf.size, conf.stride, conf.stride),
+                conf.filters
+            )?,
+            Self::LocalAvgPool(conf) => {
+                write!(f, " {}", window(conf.size, conf.stride_x, conf.stride_y))?
+            }
+            Self::Sam(conf) => write!(f, " {}", isize::from(conf.from))?,
+            Self::Lstm(conf) => write!(f, " {}", conf.output)?,
+            Self::Crnn(conf) => write!(
+                f,
+                " {} {}/{}",
+                window(conf.size, conf.stride, conf.stride),
+                conf.hidden,
+                conf.output
+            )?,
+            Self::ConvLstm(conf) => write!(
+                f,
+                " {} {}",
+                window(conf.size, conf.stride, conf.stride),
+                conf.output
+            )?,
+            Self::Custom(conf) => write!(f, " [{}]", conf.section)?,
+            Self::BatchNorm(_) | Self::AvgPool(_) => (),
+        }
+        Ok(())
+    }
+}
+
+impl LayerConfigEx for LayerConfigRef<'_> {
+    fn common(&self) -> &CommonLayerOptions {
+        match *self {
+            Self::Connected(layer) => layer.common(),
+            Self::Convolutional(layer) => layer.common(),
+            Self::Route(layer) => layer.common(),
+            Self::Shortcut(layer) => layer.common(),
+            Self::MaxPool(layer) => layer.common(),
+            Self::UpSample(layer) => layer.common(),
+            Self::Yolo(layer) => layer.common(),
+            Self::BatchNorm(layer) => layer.common(),
+            Self::Implicit(layer) => layer.common(),
+            Self::AvgPool(layer) => layer.common(),
+            Self::ScaleChannels(layer) => layer.common(),
+            Self::Dropout(layer) => layer.common(),
+            Self::Softmax(layer) => layer.common(),
+            Self::Region(layer) => layer.common(),
+            Self::GaussianYolo(layer) => layer.common(),
+            Self::ConvLstm(layer) => layer.common(),
+            Self::Crnn(layer) => layer.common(),
+            Self::Lstm(layer) => layer.common(),
+            Self::Sam(layer) => layer.common(),
+            Self::LocalAvgPool(layer) => layer.common(),
+            Self::Local(layer) => layer.common(),
+            Self::Reorg(layer) => layer.common(),
+            Self::Custom(layer) => layer.common(),
+        }
+    }
 }
 
 impl LayerConfigEx for LayerConfig {
@@ -329,6 +1331,21 @@ impl LayerConfigEx for LayerConfig {
             LayerConfig::UpSample(layer) => layer.common(),
             LayerConfig::Yolo(layer) => layer.common(),
             LayerConfig::BatchNorm(layer) => layer.common(),
+            LayerConfig::Implicit(layer) => layer.common(),
+            LayerConfig::AvgPool(layer) => layer.common(),
+            LayerConfig::ScaleChannels(layer) => layer.common(),
+            LayerConfig::Dropout(layer) => layer.common(),
+            LayerConfig::Softmax(layer) => layer.common(),
+            LayerConfig::Region(layer) => layer.common(),
+            LayerConfig::GaussianYolo(layer) => layer.common(),
+            LayerConfig::ConvLstm(layer) => layer.common(),
+            LayerConfig::Crnn(layer) => layer.common(),
+            LayerConfig::Lstm(layer) => layer.common(),
+            LayerConfig::Sam(layer) => layer.common(),
+            LayerConfig::LocalAvgPool(layer) => layer.common(),
+            LayerConfig::Local(layer) => layer.common(),
+            LayerConfig::Reorg(layer) => layer.common(),
+            LayerConfig::Custom(layer) => layer.common(),
         }
     }
 }
@@ -356,6 +1373,80 @@ mod items {
         Yolo(YoloConfig),
         #[serde(rename = "batchnorm")]
         BatchNorm(BatchNormConfig),
+        #[serde(rename = "implicit")]
+        Implicit(ImplicitConfig),
+        #[serde(rename = "implicit_add")]
+        ImplicitAdd(ImplicitConfig),
+        #[serde(rename = "implicit_mul")]
+        ImplicitMul(ImplicitConfig),
+        #[serde(rename = "avgpool")]
+        AvgPool(AvgPoolConfig),
+        #[serde(rename = "scale_channels")]
+        ScaleChannels(ScaleChannelsConfig),
+        #[serde(rename = "dropout")]
+        Dropout(DropoutConfig),
+        #[serde(rename = "softmax")]
+        Softmax(SoftmaxConfig),
+        #[serde(rename = "region")]
+        Region(RegionConfig),
+        #[serde(rename = "Gaussian_yolo")]
+        GaussianYolo(GaussianYoloConfig),
+        #[serde(rename = "conv_lstm")]
+        ConvLstm(ConvLstmConfig),
+        #[serde(rename = "crnn")]
+        Crnn(CrnnConfig),
+        #[serde(rename = "lstm")]
+        Lstm(LstmConfig),
+        #[serde(rename = "sam")]
+        Sam(SamConfig),
+        #[serde(rename = "local_avgpool")]
+        LocalAvgPool(LocalAvgPoolConfig),
+        #[serde(rename = "local")]
+        Local(LocalConfig),
+        #[serde(rename = "reorg")]
+        Reorg(ReorgConfig),
+        #[serde(rename = "reorg_old")]
+        ReorgOld(ReorgConfig),
+        #[serde(rename = "reorg3d")]
+        Reorg3d(ReorgConfig),
+        // parsed and serialized by Sections
+        #[serde(skip)]
+        Custom(CustomConfig),
+    }
+
+    impl Item {
+        pub fn section_name(&self) -> &str {
+            match self {
+                Self::Net(_) => "net",
+                Self::Connected(_) => "connected",
+                Self::Convolutional(_) => "convolutional",
+                Self::Route(_) => "route",
+                Self::Shortcut(_) => "shortcut",
+                Self::MaxPool(_) => "maxpool",
+                Self::UpSample(_) => "upsample",
+                Self::Yolo(_) => "yolo",
+                Self::BatchNorm(_) => "batchnorm",
+                Self::Implicit(_) => "implicit",
+                Self::ImplicitAdd(_) => "implicit_add",
+                Self::ImplicitMul(_) => "implicit_mul",
+                Self::AvgPool(_) => "avgpool",
+                Self::ScaleChannels(_) => "scale_channels",
+                Self::Dropout(_) => "dropout",
+                Self::Softmax(_) => "softmax",
+                Self::Region(_) => "region",
+                Self::GaussianYolo(_) => "Gaussian_yolo",
+                Self::ConvLstm(_) => "conv_lstm",
+                Self::Crnn(_) => "crnn",
+                Self::Lstm(_) => "lstm",
+                Self::Sam(_) => "sam",
+                Self::LocalAvgPool(_) => "local_avgpool",
+                Self::Local(_) => "local",
+                Self::Reorg(_) => "reorg",
+                Self::ReorgOld(_) => "reorg_old",
+                Self::Reorg3d(_) => "reorg3d",
+                Self::Custom(conf) => &conf.section,
+            }
+        }
     }
 
     impl From<DarknetConfig> for Vec<Item> {
@@ -393,9 +1484,7 @@ mod items {
                     flip,
                     blur,
                     gaussian_noise,
-                    mixup,
-                    cutmux,
-                    mosaic,
+                    augmentation_mix,
                     letter_box,
                     mosaic_bound,
                     contrastive,
@@ -416,7 +1505,9 @@ mod items {
                     policy,
                     burn_in,
                     classes,
+                    provenance,
                 } = orig_net;
+                let (mixup, cutmix, mosaic) = augmentation_mix.to_keys();
                 let net = NetConfig {
                     max_batches,
                     batch,
@@ -444,7 +1535,7 @@ mod items {
                     blur,
                     gaussian_noise,
                     mixup,
-                    cutmux,
+                    cutmix,
                     mosaic,
                     letter_box,
                     mosaic_bound,
@@ -465,6 +1556,7 @@ mod items {
                     power,
                     policy,
                     burn_in,
+                    provenance,
                 };
 
                 (net, classes)
@@ -474,6 +1566,7 @@ mod items {
                 .iter()
                 .filter_map(|layer| match layer {
                     LayerConfig::Yolo(yolo) => Some(yolo),
+                    LayerConfig::GaussianYolo(conf) => Some(&conf.yolo),
                     _ => None,
                 })
                 .flat_map(|yolo| {
@@ -482,6 +1575,93 @@ mod items {
                 })
                 .collect();
 
+            // the masks index the anchors of all yolo layers in order
+            let yolo_item = |orig_layer: CompoundYoloConfig, mask_count: &mut usize| {
+                let CompoundYoloConfig {
+                    max_boxes,
+                    max_delta,
+                    counters_per_class,
+                    label_smooth_eps,
+                    scale_x_y,
+                    new_coords,
+                    objectness_smooth,
+                    iou_normalizer,
+                    obj_normalizer,
+                    cls_normalizer,
+                    delta_normalizer,
+                    iou_loss,
+                    iou_thresh_kind,
+                    beta_nms,
+                    nms_kind,
+                    yolo_point,
+                    jitter,
+                    resize,
+                    focal_loss,
+                    ignore_thresh,
+                    truth_thresh,
+                    iou_thresh,
+                    random,
+                    track_history_size,
+                    sim_thresh,
+                    dets_for_track,
+                    dets_for_show,
+                    track_ciou_norm,
+                    embedding_layer,
+                    map,
+                    anchors: local_anchors,
+                    common,
+                } = orig_layer;
+
+                // build mask list
+                let mask: Option<IndexSet<_>> = {
+                    let num_anchors = local_anchors.len();
+                    let mask_begin = *mask_count;
+                    let mask_end = mask_begin + num_anchors;
+
+                    // update counter
+                    *mask_count += num_anchors;
+
+                    Some((mask_begin..mask_end).map(|index| index as u64).collect())
+                };
+
+                YoloConfig {
+                    classes,
+                    max_boxes,
+                    max_delta,
+                    counters_per_class,
+                    label_smooth_eps,
+                    scale_x_y,
+                    new_coords,
+                    objectness_smooth,
+                    iou_normalizer,
+                    obj_normalizer,
+                    cls_normalizer,
+                    delta_normalizer,
+                    iou_loss,
+                    iou_thresh_kind,
+                    beta_nms,
+                    nms_kind,
+                    yolo_point,
+                    jitter,
+                    resize,
+                    focal_loss,
+                    ignore_thresh,
+                    truth_thresh,
+                    iou_thresh,
+                    random,
+                    track_history_size,
+                    sim_thresh,
+                    dets_for_track,
+                    dets_for_show,
+                    track_ciou_norm,
+                    embedding_layer,
+                    map,
+                    mask,
+                    anchors: global_anchors.clone(),
+                    common,
+                }
+            };
+
             let items: Vec<_> = iter::once(Item::Net(net))
                 .chain(orig_layers.into_iter().scan(0, |mask_count, layer| {
                     let item = match layer {
@@ -491,90 +1671,37 @@ mod items {
                         LayerConfig::Shortcut(layer) => Item::Shortcut(layer),
                         LayerConfig::MaxPool(layer) => Item::MaxPool(layer),
                         LayerConfig::UpSample(layer) => Item::UpSample(layer),
-                        LayerConfig::Yolo(orig_layer) => {
-                            let CompoundYoloConfig {
-                                max_boxes,
-                                max_delta,
-                                counters_per_class,
-                                label_smooth_eps,
-                                scale_x_y,
-                                objectness_smooth,
-                                iou_normalizer,
-                                obj_normalizer,
-                                cls_normalizer,
-                                delta_normalizer,
-                                iou_loss,
-                                iou_thresh_kind,
-                                beta_nms,
-                                nms_kind,
-                                yolo_point,
-                                jitter,
-                                resize,
-                                focal_loss,
-                                ignore_thresh,
-                                truth_thresh,
-                                iou_thresh,
-                                random,
-                                track_history_size,
-                                sim_thresh,
-                                dets_for_track,
-                                dets_for_show,
-                                track_ciou_norm,
-                                embedding_layer,
-                                map,
-                                anchors: local_anchors,
-                                common,
-                            } = orig_layer;
-
-                            // build mask list
-                            let mask: IndexSet<_> = {
-                                let num_anchors = local_anchors.len();
-                                let mask_begin = *mask_count;
-                                let mask_end = mask_begin + num_anchors;
-
-                                // update counter
-                                *mask_count += num_anchors;
-
-                                (mask_begin..mask_end).map(|index| index as u64).collect()
-                            };
-
-                            Item::Yolo(YoloConfig {
-                                classes,
-                                max_boxes,
-                                max_delta,
-                                counters_per_class,
-                                label_smooth_eps,
-                                scale_x_y,
-                                objectness_smooth,
-                                iou_normalizer,
-                                obj_normalizer,
-                                cls_normalizer,
-                                delta_normalizer,
-                                iou_loss,
-                                iou_thresh_kind,
-                                beta_nms,
-                                nms_kind,
-                                yolo_point,
-                                jitter,
-                                resize,
-                                focal_loss,
-                                ignore_thresh,
-                                truth_thresh,
-                                iou_thresh,
-                                random,
-                                track_history_size,
-                                sim_thresh,
-                                dets_for_track,
-                                dets_for_show,
-                                track_ciou_norm,
-                                embedding_layer,
-                                map,
-                                mask,
-                                anchors: global_anchors.clone(),
-                                common,
-                            })
-                        }
+                        LayerConfig::Yolo(layer) => Item::Yolo(yolo_item(layer, mask_count)),
+                        LayerConfig::GaussianYolo(CompoundGaussianYoloConfig {
+                            yolo,
+                            uc_normalizer,
+                        }) => Item::GaussianYolo(GaussianYoloConfig {
+                            yolo: yolo_item(yolo, mask_count),
+                            uc_normalizer,
+                        }),
                         LayerConfig::BatchNorm(layer) => Item::BatchNorm(layer),
+                        LayerConfig::Implicit(layer) => match layer.mode {
+                            ImplicitMode::Standard => Item::Implicit(layer),
+                            ImplicitMode::Add => Item::ImplicitAdd(layer),
+                            ImplicitMode::Mul => Item::ImplicitMul(layer),
+                        },
+                        LayerConfig::AvgPool(layer) => Item::AvgPool(layer),
+                        LayerConfig::ScaleChannels(layer) => Item::ScaleChannels(layer),
+                        LayerConfig::Dropout(layer) => Item::Dropout(layer),
+                        LayerConfig::Softmax(layer) => Item::Softmax(layer),
+                        LayerConfig::Region(layer) => Item::Region(layer),
+                        LayerConfig::ConvLstm(layer) => Item::ConvLstm(layer),
+                        LayerConfig::Crnn(layer) => Item::Crnn(layer),
+                        LayerConfig::Lstm(layer) => Item::Lstm(layer),
+                        LayerConfig::Sam(layer) => Item::Sam(layer),
+                        LayerConfig::LocalAvgPool(layer) => Item::LocalAvgPool(layer),
+                        LayerConfig::Local(layer) => Item::Local(layer),
+                        LayerConfig::Reorg(layer) => match layer.mode {
+                            ReorgMode::Standard => Item::Reorg(layer),
+                            ReorgMode::Old => Item::ReorgOld(layer),
+                            ReorgMode::Reorg3d => Item::Reorg3d(layer),
+                        },
+                        LayerConfig::Custom(layer) => Item::Custom(layer),
                     };
                     Some(item)
                 }))
@@ -610,9 +1737,7 @@ mod items {
         pub flip: bool,
         pub blur: bool,
         pub gaussian_noise: bool,
-        pub mixup: MixUp,
-        pub cutmux: bool,
-        pub mosaic: bool,
+        pub augmentation_mix: AugmentationMix,
         pub letter_box: bool,
         pub mosaic_bound: bool,
         pub contrastive: bool,
@@ -633,12 +1758,141 @@ mod items {
         pub policy: Policy,
         pub burn_in: u64,
         pub classes: u64,
+        #[serde(skip)]
+        pub provenance: Provenance,
     }
 
     impl CompoundNetConfig {
         pub fn iteration(&self, seen: u64) -> u64 {
             seen / (self.batch * self.subdivisions)
         }
+
+        pub fn provenance(&self) -> &Provenance {
+            &self.provenance
+        }
+
+        pub fn default_step_schedule(&self) -> Policy {
+            Policy::default_steps(self.max_batches)
+        }
+
+        // change max_batches and move the steps along, see Policy::rescale_steps()
+        pub fn set_max_batches(&mut self, max_batches: u64) {
+            self.policy = self.policy.rescale_steps(self.max_batches, max_batches);
+            self.max_batches = max_batches;
+        }
+
+        // the learning rate of the network at the iteration, including the burn-in
+        // ramp, see get_current_rate() in darknet
+        pub fn learning_rate_at(&self, iteration: u64) -> Result<R64> {
+            let lr = self.learning_rate.raw();
+            let batch_num = iteration as f64;
+
+            if iteration < self.burn_in {
+                let ratio = batch_num / self.burn_in as f64;
+                return Ok(R64::new(lr * ratio.powf(self.power.raw())));
+            }
+
+            let sgdr = || {
+                let mut cycle_start = 0;
+                let mut cycle_size = self.sgdr_cycle.max(1);
+                while cycle_start + cycle_size < iteration {
+                    cycle_start += cycle_size;
+                    cycle_size *= self.sgdr_mult.max(1);
+                }
+                let min = self.learning_rate_min.raw();
+                let phase =
+                    (iteration - cycle_start) as f64 * std::f64::consts::PI / cycle_size as f64;
+                min + 0.5 * (lr - min) * (1.0 + phase.cos())
+            };
+
+            let rate = match &self.policy {
+                Policy::Constant => lr,
+                Policy::Step { step, scale } => {
+                    lr * scale.raw().powi((iteration / (*step).max(1)) as i32)
+                }
+                Policy::Steps { steps, scales, .. } => steps
+                    .iter()
+                    .zip(scales)
+                    .take_while(|(&step, _)| step <= iteration)
+                    .fold(lr, |rate, (_, scale)| rate * scale.raw()),
+                Policy::Exp { gamma } => lr * gamma.raw().powf(batch_num),
+                Policy::Poly => {
+                    let progress = 1.0 - batch_num / self.max_batches.max(1) as f64;
+                    lr * progress.max(0.0).powf(self.power.raw())
+                }
+                Policy::Sigmoid { gamma, step } => {
+                    lr / (1.0 + (gamma.raw() * (batch_num - *step as f64)).exp())
+                }
+                // the custom steps of sgdr only scale the sequence lengths
+                Policy::Sgdr | Policy::SgdrCustom { .. } => sgdr(),
+                Policy::Random => bail!("the random policy has no deterministic learning rate"),
+            };
+            Ok(R64::new(rate))
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub enum KeySource {
+        File,
+        Default,
+    }
+
+    // the [net] keys given in the file, the other options take their defaults.
+    // it records how the config was parsed and does not take part in equality,
+    // so a parsed config equals the config built from the same values.
+    #[derive(Debug, Clone, Default)]
+    pub struct Provenance {
+        keys: IndexSet<String>,
+    }
+
+    impl Provenance {
+        pub fn new<I, S>(keys: I) -> Self
+        where
+            I: IntoIterator<Item = S>,
+            S: Into<String>,
+        {
+            Self {
+                keys: keys.into_iter().map(Into::into).collect(),
+            }
+        }
+
+        pub fn source(&self, key: &str) -> KeySource {
+            if self.keys.contains(key) {
+                KeySource::File
+            } else {
+                KeySource::Default
+            }
+        }
+
+        pub fn is_specified(&self, key: &str) -> bool {
+            self.keys.contains(key)
+        }
+
+        // the keys in file order
+        pub fn specified_keys(&self) -> impl Iterator<Item = &str> {
+            self.keys.iter().map(|key| key.as_str())
+        }
+
+        // the known [net] keys that are absent in the file
+        pub fn defaulted_keys(&self) -> Vec<&'static str> {
+            net_fields()
+                .iter()
+                .cloned()
+                .filter(|key| !self.keys.contains(*key))
+                .collect()
+        }
+    }
+
+    impl PartialEq for Provenance {
+        fn eq(&self, _other: &Self) -> bool {
+            true
+        }
+    }
+
+    impl Eq for Provenance {}
+
+    impl Hash for Provenance {
+        fn hash<H: Hasher>(&self, _state: &mut H) {}
     }
 
     #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -670,7 +1924,7 @@ mod items {
         pub blur: bool,
         pub gaussian_noise: bool,
         pub mixup: MixUp,
-        pub cutmux: bool,
+        pub cutmix: bool,
         pub mosaic: bool,
         pub letter_box: bool,
         pub mosaic_bound: bool,
@@ -691,12 +1945,27 @@ mod items {
         pub power: R64,
         pub policy: Policy,
         pub burn_in: u64,
+        pub provenance: Provenance,
     }
 
     impl NetConfig {
         pub fn iteration(&self, seen: u64) -> u64 {
             seen / (self.batch * self.subdivisions)
         }
+
+        pub fn provenance(&self) -> &Provenance {
+            &self.provenance
+        }
+
+        pub fn default_step_schedule(&self) -> Policy {
+            Policy::default_steps(self.max_batches)
+        }
+
+        // change max_batches and move the steps along, see Policy::rescale_steps()
+        pub fn set_max_batches(&mut self, max_batches: u64) {
+            self.policy = self.policy.rescale_steps(self.max_batches, max_batches);
+            self.max_batches = max_batches;
+        }
     }
 
     impl TryFrom<RawNetConfig> for NetConfig {
@@ -736,7 +2005,7 @@ mod items {
                 blur,
                 gaussian_noise,
                 mixup,
-                cutmux,
+                cutmix,
                 mosaic,
                 letter_box,
                 mosaic_bound,
@@ -869,7 +2138,7 @@ mod items {
                 blur,
                 gaussian_noise,
                 mixup,
-                cutmux,
+                cutmix,
                 mosaic,
                 letter_box,
                 mosaic_bound,
@@ -890,6 +2159,7 @@ mod items {
                 power,
                 policy,
                 burn_in,
+                provenance: Provenance::default(),
             })
         }
     }
@@ -955,8 +2225,13 @@ mod items {
         pub gaussian_noise: bool,
         #[serde(default = "defaults::mixup")]
         pub mixup: MixUp,
-        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
-        pub cutmux: bool,
+        // cutmux is the misspelling written by earlier versions
+        #[serde(
+            alias = "cutmux",
+            with = "serde_zero_one_bool",
+            default = "defaults::bool_false"
+        )]
+        pub cutmix: bool,
         #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
         pub mosaic: bool,
         #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
@@ -1040,7 +2315,7 @@ mod items {
                 blur,
                 gaussian_noise,
                 mixup,
-                cutmux,
+                cutmix,
                 mosaic,
                 letter_box,
                 mosaic_bound,
@@ -1061,6 +2336,7 @@ mod items {
                 power,
                 policy,
                 burn_in,
+                provenance: _,
             } = net;
 
             let (adam, b1, b2, eps) = match adam {
@@ -1199,7 +2475,7 @@ mod items {
                 blur,
                 gaussian_noise,
                 mixup,
-                cutmux,
+                cutmix,
                 mosaic,
                 letter_box,
                 mosaic_bound,
@@ -1279,7 +2555,7 @@ mod items {
     }
 
     impl ConvolutionalConfig {
-        pub fn output_shape(&self, [h, w, _c]: [u64; 3]) -> [u64; 3] {
+        pub fn output_shape(&self, [h, w, _c]: [u64; 3]) -> Result<[u64; 3]> {
             let Self {
                 filters,
                 padding,
@@ -1288,12 +2564,60 @@ mod items {
                 stride_y,
                 ..
             } = *self;
-            let out_h = (h + 2 * padding - size) / stride_y + 1;
-            let out_w = (w + 2 * padding - size) / stride_x + 1;
-            [out_h, out_w, filters]
+            let padding = padding
+                .checked_mul(2)
+                .ok_or_else(|| format_err!("the padding {} is too large", padding))?;
+            let out_h = sliding_window_len("height", h, padding, size, stride_y)?;
+            let out_w = sliding_window_len("width", w, padding, size, stride_x)?;
+            Ok([out_h, out_w, filters])
         }
     }
 
+    // computes (len + padding - size) / stride + 1 without wrapping around
+    // darknet sizes the convolutions of recurrent layers by the input, so the
+    // hidden state must keep the height and width to be fed to the next step
+    fn ensure_recurrent_state([h, w]: [u64; 2], size: u64, stride: u64, pad: bool) -> Result<()> {
+        let padding = if pad { size / 2 * 2 } else { 0 };
+        let state_h = sliding_window_len("height", h, padding, size, stride)?;
+        let state_w = sliding_window_len("width", w, padding, size, stride)?;
+        ensure!(
+            [state_h, state_w] == [h, w],
+            "the hidden state of size {}x{} does not match the input size {}x{}, \
+             so it cannot be carried over time steps",
+            state_h,
+            state_w,
+            h,
+            w
+        );
+        Ok(())
+    }
+
+    fn sliding_window_len(
+        dim: &str,
+        len: u64,
+        padding: u64,
+        size: u64,
+        stride: u64,
+    ) -> Result<u64> {
+        ensure!(size > 0, "the kernel size must be positive");
+        ensure!(stride > 0, "the stride along {} must be positive", dim);
+        let padded_len = len
+            .checked_add(padding)
+            .ok_or_else(|| format_err!("the padded {} overflows", dim))?;
+        let span = padded_len.checked_sub(size).ok_or_else(|| {
+            format_err!(
+                "the kernel size {} exceeds the padded {} {} (input {} {}, padding {})",
+                size,
+                dim,
+                padded_len,
+                dim,
+                len,
+                padding
+            )
+        })?;
+        Ok(span / stride + 1)
+    }
+
     impl LayerConfigEx for ConvolutionalConfig {
         fn common(&self) -> &CommonLayerOptions {
             &self.common
@@ -1577,7 +2901,8 @@ mod items {
         pub layers: IndexSet<LayerIndex>,
         #[serde(default = "defaults::route_groups")]
         pub groups: NonZeroU64,
-        #[serde(default = "defaults::route_group_id")]
+        // groupd_id is a misspelling found in the wild, written back as group_id
+        #[serde(alias = "groupd_id", default = "defaults::route_group_id")]
         pub group_id: u64,
         #[serde(flatten)]
         pub common: CommonLayerOptions,
@@ -1636,7 +2961,7 @@ mod items {
     }
 
     impl MaxPoolConfig {
-        pub fn output_shape(&self, input_shape: [u64; 3]) -> [u64; 3] {
+        pub fn output_shape(&self, input_shape: [u64; 3]) -> Result<[u64; 3]> {
             let Self {
                 padding,
                 size,
@@ -1644,16 +2969,23 @@ mod items {
                 stride_y,
                 ..
             } = *self;
-            let [in_h, in_w, in_c] = input_shape;
-
-            let out_h = (in_h + padding - size) / stride_y + 1;
-            let out_w = (in_w + padding - size) / stride_x + 1;
-            let out_c = in_c;
-
-            [out_h, out_w, out_c]
+            pooling_output_shape(input_shape, size, stride_x, stride_y, padding)
         }
     }
 
+    // darknet pools over (len + padding - size) / stride + 1 windows
+    fn pooling_output_shape(
+        [in_h, in_w, in_c]: [u64; 3],
+        size: u64,
+        stride_x: u64,
+        stride_y: u64,
+        padding: u64,
+    ) -> Result<[u64; 3]> {
+        let out_h = sliding_window_len("height", in_h, padding, size, stride_y)?;
+        let out_w = sliding_window_len("width", in_w, padding, size, stride_x)?;
+        Ok([out_h, out_w, in_c])
+    }
+
     impl LayerConfigEx for MaxPoolConfig {
         fn common(&self) -> &CommonLayerOptions {
             &self.common
@@ -1677,7 +3009,7 @@ mod items {
             let stride_x = stride_x.unwrap_or(stride);
             let stride_y = stride_y.unwrap_or(stride);
             let size = size.unwrap_or(stride);
-            let padding = padding.unwrap_or(size - 1);
+            let padding = padding.unwrap_or_else(|| size.saturating_sub(1));
 
             Self {
                 stride_x,
@@ -1748,18 +3080,31 @@ mod items {
     }
 
     impl UpSampleConfig {
-        pub fn output_shape(&self, input_shape: [u64; 3]) -> [u64; 3] {
+        pub fn output_shape(&self, input_shape: [u64; 3]) -> Result<[u64; 3]> {
             let Self {
                 stride, reverse, ..
             } = *self;
             let [in_h, in_w, in_c] = input_shape;
+            ensure!(stride > 0, "the stride must be positive");
             let (out_h, out_w) = if reverse {
                 (in_h / stride, in_w / stride)
             } else {
-                (in_h * stride, in_w * stride)
+                let scale = |dim: &str, len: u64| {
+                    len.checked_mul(stride).ok_or_else(|| {
+                        format_err!("the upsampled {} {} x {} overflows", dim, len, stride)
+                    })
+                };
+                (scale("height", in_h)?, scale("width", in_w)?)
             };
+            ensure!(
+                out_h > 0 && out_w > 0,
+                "the reverse upsampling of {}x{} by {} yields an empty output",
+                in_h,
+                in_w,
+                stride
+            );
             let out_c = in_c;
-            [out_h, out_w, out_c]
+            Ok([out_h, out_w, out_c])
         }
     }
 
@@ -1777,6 +3122,7 @@ mod items {
         pub counters_per_class: Option<Vec<u64>>,
         pub label_smooth_eps: R64,
         pub scale_x_y: R64,
+        pub new_coords: bool,
         pub objectness_smooth: bool,
         pub iou_normalizer: R64,
         pub obj_normalizer: R64,
@@ -1811,18 +3157,39 @@ mod items {
         }
     }
 
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct CompoundGaussianYoloConfig {
+        pub yolo: CompoundYoloConfig,
+        pub uc_normalizer: R64,
+    }
+
+    impl CompoundGaussianYoloConfig {
+        // every anchor predicts 4 coordinates, 4 sigmas, objectness and classes
+        pub fn num_entries(classes: u64) -> u64 {
+            classes + 8 + 1
+        }
+    }
+
+    impl LayerConfigEx for CompoundGaussianYoloConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.yolo.common
+        }
+    }
+
     #[derive(Debug, Clone, PartialEq, Eq, Derivative, Serialize, Deserialize)]
     #[serde(try_from = "RawYoloConfig", into = "RawYoloConfig")]
     #[derivative(Hash)]
     pub struct YoloConfig {
         pub classes: u64,
-        #[derivative(Hash(hash_with = "hash_vec_indexset::<u64, _>"))]
-        pub mask: IndexSet<u64>,
+        // the indexes of the anchors of this layer, all anchors if omitted
+        #[derivative(Hash(hash_with = "hash_option_vec_indexset::<u64, _>"))]
+        pub mask: Option<IndexSet<u64>>,
         pub max_boxes: u64,
         pub max_delta: Option<R64>,
         pub counters_per_class: Option<Vec<u64>>,
         pub label_smooth_eps: R64,
         pub scale_x_y: R64,
+        pub new_coords: bool,
         pub objectness_smooth: bool,
         pub iou_normalizer: R64,
         pub obj_normalizer: R64,
@@ -1864,6 +3231,7 @@ mod items {
                 counters_per_class,
                 label_smooth_eps,
                 scale_x_y,
+                new_coords,
                 objectness_smooth,
                 iou_normalizer,
                 obj_normalizer,
@@ -1889,9 +3257,15 @@ mod items {
                 embedding_layer,
                 map,
                 anchors,
+                uc_normalizer,
                 common,
             } = from;
 
+            ensure!(
+                uc_normalizer.is_none(),
+                "uc_normalizer is only valid in [Gaussian_yolo] sections"
+            );
+
             let anchors = match (num, anchors) {
                 (0, None) => vec![],
                 (_, None) => bail!("num and length of anchors mismatch"),
@@ -1904,11 +3278,10 @@ mod items {
                 }
             };
 
-            let mask = mask.unwrap_or_else(|| IndexSet::new());
             ensure!(
                 mask.iter()
-                    .cloned()
-                    .all(|index| (index as usize) < anchors.len()),
+                    .flatten()
+                    .all(|&index| (index as usize) < anchors.len()),
                 "mask index exceeds total number of anchors"
             );
 
@@ -1920,6 +3293,7 @@ mod items {
                 counters_per_class,
                 label_smooth_eps,
                 scale_x_y,
+                new_coords,
                 objectness_smooth,
                 iou_normalizer,
                 obj_normalizer,
@@ -1976,6 +3350,8 @@ mod items {
         #[serde(default = "defaults::scale_x_y")]
         pub scale_x_y: R64,
         #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub new_coords: bool,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
         pub objectness_smooth: bool,
         #[serde(default = "defaults::iou_normalizer")]
         pub iou_normalizer: R64,
@@ -2023,6 +3399,8 @@ mod items {
         pub map: Option<PathBuf>,
         #[serde(with = "serde_anchors", default)]
         pub anchors: Option<Vec<(u64, u64)>>,
+        // only read by [Gaussian_yolo]
+        pub uc_normalizer: Option<R64>,
         #[serde(flatten)]
         pub common: CommonLayerOptions,
     }
@@ -2037,6 +3415,7 @@ mod items {
                 counters_per_class,
                 label_smooth_eps,
                 scale_x_y,
+                new_coords,
                 objectness_smooth,
                 iou_normalizer,
                 obj_normalizer,
@@ -2068,13 +3447,12 @@ mod items {
             // make sure mask indexes are valid
             assert!(
                 mask.iter()
-                    .cloned()
-                    .all(|index| (index as usize) < anchors.len()),
+                    .flatten()
+                    .all(|&index| (index as usize) < anchors.len()),
                 "mask indexes must not exceed total number of anchors"
             );
 
             let num = anchors.len() as u64;
-            let mask = if mask.is_empty() { None } else { Some(mask) };
             let anchors = if anchors.is_empty() {
                 None
             } else {
@@ -2090,6 +3468,7 @@ mod items {
                 counters_per_class,
                 label_smooth_eps,
                 scale_x_y,
+                new_coords,
                 objectness_smooth,
                 iou_normalizer,
                 obj_normalizer,
@@ -2115,24 +3494,677 @@ mod items {
                 embedding_layer,
                 map,
                 anchors,
+                uc_normalizer: None,
+                common,
+            }
+        }
+    }
+
+    // the detection head of Gaussian YOLOv3, which predicts a mean and a sigma
+    // for each of the 4 box coordinates. the other options are parsed as [yolo].
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(try_from = "RawYoloConfig", into = "RawYoloConfig")]
+    pub struct GaussianYoloConfig {
+        pub yolo: YoloConfig,
+        // the weight of the coordinate uncertainty in the loss
+        pub uc_normalizer: R64,
+    }
+
+    impl TryFrom<RawYoloConfig> for GaussianYoloConfig {
+        type Error = Error;
+
+        fn try_from(mut from: RawYoloConfig) -> Result<Self, Self::Error> {
+            let uc_normalizer = from
+                .uc_normalizer
+                .take()
+                .unwrap_or_else(defaults::uc_normalizer);
+            Ok(Self {
+                yolo: YoloConfig::try_from(from)?,
+                uc_normalizer,
+            })
+        }
+    }
+
+    impl From<GaussianYoloConfig> for RawYoloConfig {
+        fn from(from: GaussianYoloConfig) -> Self {
+            let GaussianYoloConfig {
+                yolo,
+                uc_normalizer,
+            } = from;
+            Self {
+                uc_normalizer: Some(uc_normalizer),
+                ..yolo.into()
+            }
+        }
+    }
+
+    impl LayerConfigEx for GaussianYoloConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.yolo.common
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct BatchNormConfig {
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl LayerConfigEx for BatchNormConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct ImplicitConfig {
+        #[serde(default = "defaults::implicit_filters")]
+        pub filters: u64,
+        #[serde(default = "defaults::implicit_mean")]
+        pub mean: R64,
+        #[serde(default = "defaults::implicit_std")]
+        pub std: R64,
+        #[serde(default = "defaults::implicit_atoms")]
+        pub atoms: u64,
+        // given by the section name rather than a key
+        #[serde(skip)]
+        pub mode: ImplicitMode,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl ImplicitConfig {
+        // implicit layers carry a learned [1, 1, filters] tensor and ignore their input
+        pub fn output_shape(&self) -> [u64; 3] {
+            [1, 1, self.filters]
+        }
+    }
+
+    impl LayerConfigEx for ImplicitConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    // AlexeyAB's darknet spells the YOLOR implicit knowledge as [implicit_add]
+    // and [implicit_mul], which parse like [implicit]. the section only tells
+    // whether the following layer adds or multiplies the learned tensor.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+    pub enum ImplicitMode {
+        #[default]
+        Standard,
+        Add,
+        Mul,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct AvgPoolConfig {
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl AvgPoolConfig {
+        // global average pooling
+        pub fn output_shape(&self, [_h, _w, c]: [u64; 3]) -> [u64; 3] {
+            [1, 1, c]
+        }
+    }
+
+    impl LayerConfigEx for AvgPoolConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct ScaleChannelsConfig {
+        pub from: LayerIndex,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub scale_wh: bool,
+        #[serde(default = "defaults::scale_channels_activation")]
+        pub activation: Activation,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl LayerConfigEx for ScaleChannelsConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct DropoutConfig {
+        #[serde(default = "defaults::dropout_probability")]
+        pub probability: R64,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub dropblock: bool,
+        #[serde(default = "defaults::dropblock_size_rel")]
+        pub dropblock_size_rel: R64,
+        #[serde(default = "defaults::dropblock_size_abs")]
+        pub dropblock_size_abs: u64,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl LayerConfigEx for DropoutConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct SoftmaxConfig {
+        #[serde(default = "defaults::groups")]
+        pub groups: u64,
+        #[serde(default = "defaults::temperature")]
+        pub temperature: R64,
+        // the class hierarchy of YOLO9000, e.g. data/9k.tree. the groups are
+        // given by the tree instead.
+        #[serde(alias = "hierarchy")]
+        pub tree: Option<PathBuf>,
+        // normalize over the channels at every location
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub spatial: bool,
+        #[serde(
+            rename = "noloss",
+            with = "serde_zero_one_bool",
+            default = "defaults::bool_false"
+        )]
+        pub no_loss: bool,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl SoftmaxConfig {
+        // the output has the shape of the input
+        pub fn output_shape(&self, input_shape: Shape) -> Result<Shape> {
+            ensure!(
+                self.temperature > 0.0,
+                "the temperature {} must be positive",
+                self.temperature
+            );
+            if self.tree.is_none() {
+                let inputs = match input_shape {
+                    Shape::Hwc([h, w, c]) => h * w * c,
+                    Shape::Flat(size) => size,
+                };
+                ensure!(
+                    self.groups > 0 && inputs % self.groups == 0,
+                    "the input size {} is not a multiple of the groups {}",
+                    inputs,
+                    self.groups
+                );
+            }
+            Ok(input_shape)
+        }
+    }
+
+    impl LayerConfigEx for SoftmaxConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    // the detection head of YOLOv2. unlike [yolo], the anchors are in the units
+    // of grid cells and every anchor predicts on the input.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct RegionConfig {
+        #[serde(default = "defaults::classes")]
+        pub classes: u64,
+        #[serde(default = "defaults::coords")]
+        pub coords: u64,
+        #[serde(default = "defaults::num")]
+        pub num: u64,
+        #[serde(with = "serde_anchors", default)]
+        pub anchors: Option<Vec<(R64, R64)>>,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub bias_match: bool,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub softmax: bool,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub rescore: bool,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub background: bool,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub absolute: bool,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub random: bool,
+        #[serde(default = "defaults::region_thresh")]
+        pub thresh: R64,
+        #[serde(rename = "max", default = "defaults::region_max_boxes")]
+        pub max_boxes: u64,
+        #[serde(default = "defaults::jitter")]
+        pub jitter: R64,
+        #[serde(default = "defaults::region_loss_scale")]
+        pub coord_scale: R64,
+        #[serde(default = "defaults::region_loss_scale")]
+        pub object_scale: R64,
+        #[serde(default = "defaults::region_loss_scale")]
+        pub noobject_scale: R64,
+        #[serde(default = "defaults::region_loss_scale")]
+        pub class_scale: R64,
+        // the class hierarchy of YOLO9000, e.g. data/9k.tree
+        pub tree: Option<PathBuf>,
+        pub map: Option<PathBuf>,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl RegionConfig {
+        // every anchor predicts coords, objectness and classes at each location
+        pub fn output_shape(&self, [h, w, c]: [u64; 3]) -> Result<[u64; 3]> {
+            if let Some(anchors) = &self.anchors {
+                ensure!(
+                    anchors.len() as u64 == self.num,
+                    "num {} and the {} anchors mismatch",
+                    self.num,
+                    anchors.len()
+                );
+            }
+            let expect = self.num * (self.coords + self.classes + 1);
+            ensure!(
+                c == expect,
+                "the region layer expects {} input channels, but {} are given",
+                expect,
+                c
+            );
+            Ok([h, w, c])
+        }
+    }
+
+    impl LayerConfigEx for RegionConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    // the space-to-depth layer of YOLOv2, which moves each stride x stride block
+    // into the channels. reverse=1 moves the channels back into blocks.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct ReorgConfig {
+        #[serde(default = "defaults::stride")]
+        pub stride: u64,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub reverse: bool,
+        // given by the section name rather than a key
+        #[serde(skip)]
+        pub mode: ReorgMode,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl ReorgConfig {
+        pub fn output_shape(&self, [in_h, in_w, in_c]: [u64; 3]) -> Result<[u64; 3]> {
+            let Self {
+                stride, reverse, ..
+            } = *self;
+            ensure!(stride > 0, "the stride must be positive");
+            let area = stride * stride;
+            if reverse {
+                ensure!(
+                    in_c % area == 0,
+                    "the channels {} are not divisible by the stride {} squared",
+                    in_c,
+                    stride
+                );
+                Ok([in_h * stride, in_w * stride, in_c / area])
+            } else {
+                ensure!(
+                    in_h % stride == 0 && in_w % stride == 0,
+                    "the input size {}x{} is not divisible by the stride {}",
+                    in_h,
+                    in_w,
+                    stride
+                );
+                Ok([in_h / stride, in_w / stride, in_c * area])
+            }
+        }
+    }
+
+    impl LayerConfigEx for ReorgConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    // the legacy [reorg_old] and [reorg3d] sections produce the same shapes as
+    // [reorg], but place the moved values in different channel orders, so the
+    // weights of the following layers are only valid for the same variant
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+    pub enum ReorgMode {
+        #[default]
+        Standard,
+        Old,
+        Reorg3d,
+    }
+
+    // the locally connected layer of YOLOv1, a convolution with separate weights
+    // at each output location
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct LocalConfig {
+        #[serde(default = "defaults::local_filters")]
+        pub filters: u64,
+        #[serde(default = "defaults::local_size")]
+        pub size: u64,
+        #[serde(default = "defaults::stride")]
+        pub stride: u64,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub pad: bool,
+        #[serde(default = "defaults::local_activation")]
+        pub activation: Activation,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl LocalConfig {
+        pub fn output_shape(&self, [h, w, _c]: [u64; 3]) -> Result<[u64; 3]> {
+            let Self {
+                filters,
+                size,
+                stride,
+                pad,
+                ..
+            } = *self;
+            // darknet pads to (len - 1) / stride + 1 outputs regardless of the size
+            let padding = if pad { size.saturating_sub(1) } else { 0 };
+            let out_h = sliding_window_len("height", h, padding, size, stride)?;
+            let out_w = sliding_window_len("width", w, padding, size, stride)?;
+            Ok([out_h, out_w, filters])
+        }
+    }
+
+    impl LayerConfigEx for LocalConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    // the average over a sliding window, which takes the same keys as [maxpool]
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(from = "RawLocalAvgPoolConfig", into = "RawLocalAvgPoolConfig")]
+    pub struct LocalAvgPoolConfig {
+        pub stride_x: u64,
+        pub stride_y: u64,
+        pub size: u64,
+        pub padding: u64,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl LocalAvgPoolConfig {
+        pub fn output_shape(&self, input_shape: [u64; 3]) -> Result<[u64; 3]> {
+            let Self {
+                padding,
+                size,
+                stride_x,
+                stride_y,
+                ..
+            } = *self;
+            pooling_output_shape(input_shape, size, stride_x, stride_y, padding)
+        }
+    }
+
+    impl LayerConfigEx for LocalAvgPoolConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    impl From<RawLocalAvgPoolConfig> for LocalAvgPoolConfig {
+        fn from(raw: RawLocalAvgPoolConfig) -> Self {
+            let RawLocalAvgPoolConfig {
+                stride,
+                stride_x,
+                stride_y,
+                size,
+                padding,
+                common,
+            } = raw;
+
+            let stride_x = stride_x.unwrap_or(stride);
+            let stride_y = stride_y.unwrap_or(stride);
+            let size = size.unwrap_or(stride);
+            let padding = padding.unwrap_or_else(|| size.saturating_sub(1));
+
+            Self {
+                stride_x,
+                stride_y,
+                size,
+                padding,
                 common,
             }
         }
     }
 
     #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-    pub struct BatchNormConfig {
+    pub struct RawLocalAvgPoolConfig {
+        #[serde(default = "defaults::maxpool_stride")]
+        pub stride: u64,
+        pub stride_x: Option<u64>,
+        pub stride_y: Option<u64>,
+        pub size: Option<u64>,
+        pub padding: Option<u64>,
         #[serde(flatten)]
         pub common: CommonLayerOptions,
     }
 
-    impl LayerConfigEx for BatchNormConfig {
+    impl From<LocalAvgPoolConfig> for RawLocalAvgPoolConfig {
+        fn from(avgpool: LocalAvgPoolConfig) -> Self {
+            let LocalAvgPoolConfig {
+                stride_x,
+                stride_y,
+                size,
+                padding,
+                common,
+            } = avgpool;
+
+            Self {
+                stride: defaults::maxpool_stride(),
+                stride_x: Some(stride_x),
+                stride_y: Some(stride_y),
+                size: Some(size),
+                padding: Some(padding),
+                common,
+            }
+        }
+    }
+
+    // the spatial attention module, which multiplies the features of the from
+    // layer by the attention of the previous layer element-wise
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct SamConfig {
+        pub from: LayerIndex,
+        #[serde(default = "defaults::sam_activation")]
+        pub activation: Activation,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl LayerConfigEx for SamConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    // a long short-term memory layer. darknet multiplies the batch by time_steps
+    // in [net] and runs the steps of each sequence through the layer.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct LstmConfig {
+        #[serde(default = "defaults::connected_output")]
+        pub output: u64,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub batch_normalize: bool,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl LayerConfigEx for LstmConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    // a convolutional recurrent layer. the input, self and output convolutions
+    // run once per time step, and the hidden state is carried between the steps.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct CrnnConfig {
+        #[serde(default = "defaults::connected_output")]
+        pub output: u64,
+        #[serde(default = "defaults::crnn_hidden")]
+        pub hidden: u64,
+        #[serde(default = "defaults::crnn_size")]
+        pub size: u64,
+        #[serde(default = "defaults::stride")]
+        pub stride: u64,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub pad: bool,
+        #[serde(default = "defaults::connected_activation")]
+        pub activation: Activation,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub batch_normalize: bool,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl CrnnConfig {
+        pub fn output_shape(&self, [h, w, _c]: [u64; 3]) -> Result<[u64; 3]> {
+            let Self {
+                output,
+                hidden,
+                size,
+                stride,
+                pad,
+                ..
+            } = *self;
+            ensure!(hidden > 0, "the hidden filters must be positive");
+            ensure_recurrent_state([h, w], size, stride, pad)?;
+            Ok([h, w, output])
+        }
+    }
+
+    impl LayerConfigEx for CrnnConfig {
         fn common(&self) -> &CommonLayerOptions {
             &self.common
         }
     }
 
+    // a convolutional lstm layer. the gates are convolutions, where the optional
+    // peephole gates see the cell state and the bottleneck mode replaces the w
+    // gates by a single convolution over the input concatenated to the output.
     #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct ConvLstmConfig {
+        #[serde(default = "defaults::connected_output")]
+        pub output: u64,
+        #[serde(default = "defaults::conv_lstm_size")]
+        pub size: u64,
+        #[serde(default = "defaults::stride")]
+        pub stride: u64,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub pad: bool,
+        #[serde(default = "defaults::groups")]
+        pub groups: u64,
+        #[serde(default = "defaults::conv_lstm_activation")]
+        pub activation: Activation,
+        #[serde(default = "defaults::conv_lstm_lstm_activation")]
+        pub lstm_activation: Activation,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub batch_normalize: bool,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub peephole: bool,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub bottleneck: bool,
+        // darknet clips the cell state to this value, 32 * time_steps by default
+        pub state_constrain: Option<u64>,
+        #[serde(with = "serde_zero_one_bool", default = "defaults::bool_false")]
+        pub shortcut: bool,
+        #[serde(default = "defaults::conv_lstm_time_normalizer")]
+        pub time_normalizer: R64,
+        #[serde(flatten)]
+        pub common: CommonLayerOptions,
+    }
+
+    impl ConvLstmConfig {
+        pub fn output_shape(&self, [h, w, in_c]: [u64; 3]) -> Result<[u64; 3]> {
+            let Self {
+                output,
+                size,
+                stride,
+                pad,
+                groups,
+                bottleneck,
+                ..
+            } = *self;
+            ensure!(groups > 0, "groups must be positive");
+            ensure!(
+                in_c % groups == 0 && output % groups == 0,
+                "the input channels {} and the output {} must be multiples of groups {}",
+                in_c,
+                output,
+                groups
+            );
+            // the bottleneck copies the input next to the output of the same size
+            ensure!(
+                !bottleneck || in_c == output,
+                "the bottleneck expects {} input channels to match the output, but {} are given",
+                output,
+                in_c
+            );
+            ensure_recurrent_state([h, w], size, stride, pad)?;
+            Ok([h, w, output])
+        }
+    }
+
+    impl LayerConfigEx for ConvLstmConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            &self.common
+        }
+    }
+
+    // a section parsed by a registered SectionHandler
+    #[derive(Debug, Clone, PartialEq, Eq, Derivative, Serialize, Deserialize)]
+    #[derivative(Hash)]
+    pub struct CustomConfig {
+        pub section: String,
+        // the input layers, the previous layer unless the handler has a from key
+        #[derivative(Hash(hash_with = "hash_vec_layers"))]
+        pub from: IndexSet<LayerIndex>,
+        // the remaining options as written in the section
+        #[derivative(Hash(hash_with = "hash_index_map"))]
+        pub options: IndexMap<String, String>,
+    }
+
+    impl CustomConfig {
+        pub fn new(section: impl Into<String>) -> Self {
+            Self {
+                section: section.into(),
+                from: iter::once(LayerIndex::Relative(NonZeroUsize::new(1).unwrap())).collect(),
+                options: IndexMap::new(),
+            }
+        }
+    }
+
+    impl LayerConfigEx for CustomConfig {
+        fn common(&self) -> &CommonLayerOptions {
+            // the options of custom sections are left to their handlers
+            static COMMON: OnceLock<CommonLayerOptions> = OnceLock::new();
+            COMMON.get_or_init(|| CommonLayerOptions {
+                clip: None,
+                only_forward: false,
+                dont_update: false,
+                burnin_update: false,
+                stop_backward: false,
+                train_only_bn: false,
+                dont_load: false,
+                dont_load_scales: false,
+                learning_scale_scale: defaults::learning_scale_scale(),
+                extensions: IndexMap::new(),
+            })
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Derivative, Serialize, Deserialize)]
+    #[derivative(Hash)]
     pub struct CommonLayerOptions {
         pub clip: Option<R64>,
         #[serde(
@@ -2167,6 +4199,27 @@ mod items {
         pub dont_load_scales: bool,
         #[serde(rename = "learning_rate", default = "defaults::learning_scale_scale")]
         pub learning_scale_scale: R64,
+        // custom options of darknet forks, keys starting with "x_" are kept here
+        // and written back on save, other unknown keys are dropped, see ParseOptions
+        #[derivative(Hash(hash_with = "hash_index_map"))]
+        #[serde(flatten, with = "serde_extensions")]
+        pub extensions: IndexMap<String, String>,
+    }
+
+    impl CommonLayerOptions {
+        // the learning rate the optimizer applies to the layer at the iteration,
+        // before darknet divides it by the batch size. layers with burnin_update
+        // are frozen until the burn-in ends, see update_network() in darknet.
+        pub fn effective_learning_rate(
+            &self,
+            net: &CompoundNetConfig,
+            iteration: u64,
+        ) -> Result<R64> {
+            if self.burnin_update && iteration < net.burn_in {
+                return Ok(R64::new(0.0));
+            }
+            Ok(net.learning_rate_at(iteration)? * self.learning_scale_scale)
+        }
     }
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -2224,6 +4277,35 @@ mod items {
         Lhtan,
     }
 
+    impl Display for Activation {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let name = match self {
+                Self::Mish => "mish",
+                Self::HardMish => "hard_mish",
+                Self::Swish => "swish",
+                Self::NormalizeChannels => "normalize_channels",
+                Self::NormalizeChannelsSoftmax => "normalize_channels_softmax",
+                Self::NormalizeChannelsSoftmaxMaxval => "normalize_channels_softmax_maxval",
+                Self::Logistic => "logistic",
+                Self::Loggy => "loggy",
+                Self::Relu => "relu",
+                Self::Elu => "elu",
+                Self::Selu => "selu",
+                Self::Gelu => "gelu",
+                Self::Relie => "relie",
+                Self::Ramp => "ramp",
+                Self::Linear => "linear",
+                Self::Tanh => "tanh",
+                Self::Plse => "plse",
+                Self::Leaky => "leaky",
+                Self::Stair => "stair",
+                Self::Hardtan => "hardtan",
+                Self::Lhtan => "lhtan",
+            };
+            write!(f, "{}", name)
+        }
+    }
+
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
     pub enum IouLoss {
         #[serde(rename = "mse")]
@@ -2319,15 +4401,117 @@ mod items {
         },
     }
 
+    impl Policy {
+        // the schedule recommended by darknet, which decays the learning rate
+        // by 10x at 80% and 90% of max_batches
+        pub fn default_steps(max_batches: u64) -> Self {
+            Self::Steps {
+                steps: vec![max_batches * 8 / 10, max_batches * 9 / 10],
+                scales: vec![R64::new(0.1); 2],
+                seq_scales: vec![R64::new(1.0); 2],
+            }
+        }
+
+        // the schedule for a new max_batches. the default schedule is regenerated,
+        // while custom steps keep their relative positions. other policies are
+        // returned as is.
+        pub fn rescale_steps(&self, old_max_batches: u64, new_max_batches: u64) -> Self {
+            if *self == Self::default_steps(old_max_batches) {
+                return Self::default_steps(new_max_batches);
+            }
+            let rescale = |steps: &[u64]| -> Vec<u64> {
+                steps
+                    .iter()
+                    .map(|&step| {
+                        (step as f64 * new_max_batches as f64 / old_max_batches.max(1) as f64)
+                            .round() as u64
+                    })
+                    .collect()
+            };
+
+            match self {
+                Self::Steps {
+                    steps,
+                    scales,
+                    seq_scales,
+                } => Self::Steps {
+                    steps: rescale(steps),
+                    scales: scales.clone(),
+                    seq_scales: seq_scales.clone(),
+                },
+                Self::SgdrCustom {
+                    steps,
+                    scales,
+                    seq_scales,
+                } => Self::SgdrCustom {
+                    steps: rescale(steps),
+                    scales: scales.clone(),
+                    seq_scales: seq_scales.clone(),
+                },
+                _ => self.clone(),
+            }
+        }
+    }
+
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize_repr, Deserialize_repr)]
     #[repr(u64)]
     pub enum MixUp {
+        None = 0,
         MixUp = 1,
         CutMix = 2,
         Mosaic = 3,
         Random = 4,
     }
 
+    // the image mixing of training samples. darknet folds the mixup, cutmix and
+    // mosaic keys into one selector, where cutmix and mosaic take precedence over
+    // mixup, see parse_net_options().
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub enum AugmentationMix {
+        None,
+        MixUp,
+        CutMix,
+        Mosaic,
+        // either cutmix or mosaic per sample, also selected by mixup=4
+        CutMixMosaic,
+    }
+
+    impl AugmentationMix {
+        pub fn from_keys(mixup: MixUp, cutmix: bool, mosaic: bool) -> Self {
+            match (cutmix, mosaic) {
+                (true, true) => Self::CutMixMosaic,
+                (true, false) => Self::CutMix,
+                (false, true) => Self::Mosaic,
+                (false, false) => match mixup {
+                    MixUp::None => Self::None,
+                    MixUp::MixUp => Self::MixUp,
+                    MixUp::CutMix => Self::CutMix,
+                    MixUp::Mosaic => Self::Mosaic,
+                    MixUp::Random => Self::CutMixMosaic,
+                },
+            }
+        }
+
+        // the mixup, cutmix and mosaic values that select it
+        pub fn to_keys(self) -> (MixUp, bool, bool) {
+            match self {
+                Self::None => (MixUp::None, false, false),
+                Self::MixUp => (MixUp::MixUp, false, false),
+                Self::CutMix => (MixUp::None, true, false),
+                Self::Mosaic => (MixUp::None, false, true),
+                Self::CutMixMosaic => (MixUp::None, true, true),
+            }
+        }
+
+        pub fn uses_cutmix(self) -> bool {
+            matches!(self, Self::CutMix | Self::CutMixMosaic)
+        }
+
+        pub fn uses_mosaic(self) -> bool {
+            matches!(self, Self::Mosaic | Self::CutMixMosaic)
+        }
+    }
+
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub enum LayerIndex {
         Relative(NonZeroUsize),
@@ -2582,7 +4766,7 @@ mod defaults {
     }
 
     pub fn mixup() -> MixUp {
-        MixUp::Random
+        MixUp::None
     }
 
     pub fn label_smooth_eps() -> R64 {
@@ -2641,45 +4825,137 @@ mod defaults {
         0
     }
 
-    pub fn route_groups() -> NonZeroU64 {
-        NonZeroU64::new(1).unwrap()
+    pub fn route_groups() -> NonZeroU64 {
+        NonZeroU64::new(1).unwrap()
+    }
+
+    pub fn route_group_id() -> u64 {
+        0
+    }
+
+    pub fn weights_type() -> WeightsType {
+        WeightsType::None
+    }
+
+    pub fn weights_normalization() -> WeightsNormalization {
+        WeightsNormalization::None
+    }
+
+    pub fn maxpool_stride() -> u64 {
+        1
+    }
+
+    pub fn out_channels() -> u64 {
+        1
+    }
+
+    pub fn upsample_stride() -> u64 {
+        2
+    }
+
+    pub fn implicit_filters() -> u64 {
+        128
+    }
+
+    pub fn implicit_mean() -> R64 {
+        R64::new(0.0)
+    }
+
+    pub fn implicit_std() -> R64 {
+        R64::new(0.2)
+    }
+
+    pub fn implicit_atoms() -> u64 {
+        1
+    }
+
+    pub fn scale_channels_activation() -> Activation {
+        Activation::Linear
+    }
+
+    pub fn temperature() -> R64 {
+        R64::new(1.0)
+    }
+
+    pub fn dropout_probability() -> R64 {
+        R64::new(0.2)
+    }
+
+    pub fn dropblock_size_rel() -> R64 {
+        R64::new(0.0)
+    }
+
+    pub fn dropblock_size_abs() -> u64 {
+        7
+    }
+
+    pub fn classes() -> u64 {
+        warn!("classes option is not specified, use default 20");
+        20
+    }
+
+    pub fn num() -> u64 {
+        1
+    }
+
+    pub fn max_boxes() -> u64 {
+        200
+    }
+
+    pub fn coords() -> u64 {
+        4
+    }
+
+    pub fn region_max_boxes() -> u64 {
+        30
+    }
+
+    pub fn region_thresh() -> R64 {
+        R64::new(0.5)
+    }
+
+    pub fn region_loss_scale() -> R64 {
+        R64::new(1.0)
+    }
+
+    pub fn local_filters() -> u64 {
+        1
     }
 
-    pub fn route_group_id() -> u64 {
-        0
+    pub fn local_size() -> u64 {
+        1
     }
 
-    pub fn weights_type() -> WeightsType {
-        WeightsType::None
+    pub fn local_activation() -> Activation {
+        Activation::Logistic
     }
 
-    pub fn weights_normalization() -> WeightsNormalization {
-        WeightsNormalization::None
+    pub fn sam_activation() -> Activation {
+        Activation::Linear
     }
 
-    pub fn maxpool_stride() -> u64 {
+    pub fn crnn_hidden() -> u64 {
         1
     }
 
-    pub fn out_channels() -> u64 {
-        1
+    pub fn crnn_size() -> u64 {
+        3
     }
 
-    pub fn upsample_stride() -> u64 {
-        2
+    pub fn conv_lstm_size() -> u64 {
+        3
     }
 
-    pub fn classes() -> u64 {
-        warn!("classes option is not specified, use default 20");
-        20
+    pub fn conv_lstm_activation() -> Activation {
+        Activation::Linear
     }
 
-    pub fn num() -> u64 {
-        1
+    pub fn conv_lstm_lstm_activation() -> Activation {
+        Activation::Tanh
     }
 
-    pub fn max_boxes() -> u64 {
-        200
+    pub fn conv_lstm_time_normalizer() -> R64 {
+        R64::new(1.0)
     }
 
     pub fn yolo_label_smooth_eps() -> R64 {
@@ -2690,6 +4966,10 @@ mod defaults {
         R64::new(1.0)
     }
 
+    pub fn uc_normalizer() -> R64 {
+        R64::new(1.0)
+    }
+
     pub fn iou_normalizer() -> R64 {
         R64::new(0.75)
     }
@@ -2791,22 +5071,426 @@ where
     layers.hash(state);
 }
 
-fn hash_vec_indexset<T, H>(set: &IndexSet<T>, state: &mut H)
+fn hash_option_vec_indexset<T, H>(opt: &Option<IndexSet<T>>, state: &mut H)
 where
     T: Hash,
     H: Hasher,
 {
-    let set: Vec<_> = set.iter().collect();
-    set.hash(state);
+    let opt: Option<Vec<_>> = opt.as_ref().map(|set| set.iter().collect());
+    opt.hash(state);
 }
 
-fn hash_option_vec_indexset<T, H>(opt: &Option<IndexSet<T>>, state: &mut H)
+fn hash_index_map<K, V, H>(map: &IndexMap<K, V>, state: &mut H)
 where
-    T: Hash,
+    K: Hash + Ord,
+    V: Hash,
     H: Hasher,
 {
-    let opt: Option<Vec<_>> = opt.as_ref().map(|set| set.iter().collect());
-    opt.hash(state);
+    // IndexMap equality ignores the order, so does the hash
+    let entries: Vec<_> = map
+        .iter()
+        .sorted_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs))
+        .collect();
+    entries.hash(state);
+}
+
+mod serde_extensions {
+    use super::*;
+    use std::cell::RefCell;
+
+    pub const PREFIX: &str = "x_";
+
+    thread_local! {
+        // the dropped keys of every deserialized layer section while collecting
+        static DROPPED_KEYS: RefCell<Option<Vec<Vec<String>>>> = const { RefCell::new(None) };
+    }
+
+    pub fn collect_dropped_keys<T>(f: impl FnOnce() -> T) -> (T, Vec<Vec<String>>) {
+        let saved = DROPPED_KEYS.with(|keys| keys.replace(Some(vec![])));
+        let output = f();
+        let dropped = DROPPED_KEYS
+            .with(|keys| keys.replace(saved))
+            .unwrap_or_default();
+        (output, dropped)
+    }
+
+    pub fn serialize<S>(
+        extensions: &IndexMap<String, String>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if let Some(key) = extensions.keys().find(|key| !key.starts_with(PREFIX)) {
+            return Err(serde::ser::Error::custom(format!(
+                "extension key '{}' must start with '{}'",
+                key, PREFIX
+            )));
+        }
+        serializer.collect_map(extensions)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<IndexMap<String, String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ExtensionsVisitor)
+    }
+
+    struct ExtensionsVisitor;
+
+    impl<'de> de::Visitor<'de> for ExtensionsVisitor {
+        type Value = IndexMap<String, String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("layer options")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: de::MapAccess<'de>,
+        {
+            let mut extensions = IndexMap::new();
+            let mut dropped = vec![];
+            while let Some(key) = map.next_key::<String>()? {
+                if key.starts_with(PREFIX) {
+                    let ExtensionValue(value) = map.next_value()?;
+                    extensions.insert(key, value);
+                } else {
+                    map.next_value::<de::IgnoredAny>()?;
+                    dropped.push(key);
+                }
+            }
+            record_dropped_keys(dropped);
+            Ok(extensions)
+        }
+    }
+
+    pub fn record_dropped_keys(dropped: Vec<String>) {
+        DROPPED_KEYS.with(|keys| {
+            if let Some(keys) = &mut *keys.borrow_mut() {
+                keys.push(dropped);
+            }
+        });
+    }
+
+    // the value is kept as text regardless of how the deserializer types it
+    pub struct ExtensionValue(pub String);
+
+    impl<'de> Deserialize<'de> for ExtensionValue {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct ValueVisitor;
+
+            impl<'de> de::Visitor<'de> for ValueVisitor {
+                type Value = String;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a string or number")
+                }
+
+                fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+                    Ok(value.to_owned())
+                }
+
+                fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+                    Ok(value)
+                }
+
+                fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+                    Ok(value.to_string())
+                }
+
+                fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+                    Ok(value.to_string())
+                }
+
+                fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+                    Ok(value.to_string())
+                }
+
+                fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+                    Ok(value.to_string())
+                }
+            }
+
+            Ok(Self(deserializer.deserialize_any(ValueVisitor)?))
+        }
+    }
+}
+
+use serde_float_format::FormattedSections;
+pub(crate) use serde_sections::Sections;
+
+mod serde_sections {
+    use super::*;
+    use crate::section::section_handler;
+    use serde::{
+        de::IntoDeserializer,
+        ser::{Error as _, SerializeSeq},
+    };
+    use serde_extensions::ExtensionValue;
+
+    // the sections of a config file. sections named by a registered
+    // SectionHandler become Item::Custom, the others go through the derived
+    // impls of Item.
+    pub struct Sections(pub Vec<Item>);
+
+    impl From<DarknetConfig> for Sections {
+        fn from(config: DarknetConfig) -> Self {
+            Self(config.into())
+        }
+    }
+
+    impl TryFrom<Sections> for DarknetConfig {
+        type Error = Error;
+
+        fn try_from(sections: Sections) -> Result<Self, Self::Error> {
+            Self::try_from(sections.0)
+        }
+    }
+
+    impl Serialize for Sections {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+            for item in &self.0 {
+                match item {
+                    Item::Custom(conf) => seq.serialize_element(&CustomSection(conf))?,
+                    item => seq.serialize_element(item)?,
+                }
+            }
+            seq.end()
+        }
+    }
+
+    struct CustomSection<'a>(&'a CustomConfig);
+
+    impl Serialize for CustomSection<'_> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let CustomConfig {
+                section,
+                from,
+                options,
+            } = self.0;
+            let handler = expect_section_handler(section).map_err(S::Error::custom)?;
+            let mut options = handler.serialize(options).map_err(S::Error::custom)?;
+
+            if *from != CustomConfig::new(section.as_str()).from {
+                let key = handler.input_key().ok_or_else(|| {
+                    S::Error::custom(format!("[{}] only reads the previous layer", section))
+                })?;
+                let text = from
+                    .iter()
+                    .map(|&index| isize::from(index).to_string())
+                    .join(",");
+                options.insert(key.to_owned(), text);
+            }
+
+            serializer.serialize_newtype_variant(
+                "Item",
+                BUILTIN_SECTIONS.len() as u32,
+                handler.section_name(),
+                &options,
+            )
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Sections {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct SectionsVisitor;
+
+            impl<'de> de::Visitor<'de> for SectionsVisitor {
+                type Value = Sections;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("config sections")
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: de::SeqAccess<'de>,
+                {
+                    let mut items = vec![];
+                    while let Some(item) = seq.next_element_seed(SectionSeed)? {
+                        items.push(item);
+                    }
+                    Ok(Sections(items))
+                }
+            }
+
+            deserializer.deserialize_seq(SectionsVisitor)
+        }
+    }
+
+    struct SectionSeed;
+
+    impl<'de> de::DeserializeSeed<'de> for SectionSeed {
+        type Value = Item;
+
+        fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_enum("Item", BUILTIN_SECTIONS, SectionVisitor)
+        }
+    }
+
+    struct SectionVisitor;
+
+    impl<'de> de::Visitor<'de> for SectionVisitor {
+        type Value = Item;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a config section")
+        }
+
+        fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+        where
+            A: de::EnumAccess<'de>,
+        {
+            use de::VariantAccess;
+
+            let (SectionName(name), variant) = data.variant()?;
+            let handler = match section_handler(&name) {
+                Some(handler) => handler,
+                None => return Item::deserialize(BuiltinSection { name, variant }),
+            };
+
+            let CustomOptions(mut options) = variant.newtype_variant()?;
+            // custom sections keep all of their keys
+            serde_extensions::record_dropped_keys(vec![]);
+
+            let mut conf = CustomConfig::new(name);
+            if let Some(text) = handler
+                .input_key()
+                .and_then(|key| options.shift_remove(key))
+            {
+                conf.from = serde_vec_layers::deserialize(text.as_str().into_deserializer())
+                    .map_err(|err: de::value::Error| {
+                        A::Error::custom(format!("[{}]: {}", conf.section, err))
+                    })?;
+            }
+            conf.options = handler
+                .parse(options)
+                .map_err(|err| A::Error::custom(format!("[{}]: {:#}", conf.section, err)))?;
+            Ok(Item::Custom(conf))
+        }
+    }
+
+    struct SectionName(String);
+
+    impl<'de> Deserialize<'de> for SectionName {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct NameVisitor;
+
+            impl<'de> de::Visitor<'de> for NameVisitor {
+                type Value = String;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a section name")
+                }
+
+                fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+                    Ok(value.to_owned())
+                }
+
+                fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+                    Ok(value)
+                }
+            }
+
+            Ok(Self(deserializer.deserialize_identifier(NameVisitor)?))
+        }
+    }
+
+    struct CustomOptions(IndexMap<String, String>);
+
+    impl<'de> Deserialize<'de> for CustomOptions {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct OptionsVisitor;
+
+            impl<'de> de::Visitor<'de> for OptionsVisitor {
+                type Value = IndexMap<String, String>;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("section options")
+                }
+
+                fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                where
+                    A: de::MapAccess<'de>,
+                {
+                    let mut options = IndexMap::new();
+                    while let Some(key) = map.next_key::<String>()? {
+                        let ExtensionValue(value) = map.next_value()?;
+                        options.insert(key, value);
+                    }
+                    Ok(options)
+                }
+            }
+
+            Ok(Self(deserializer.deserialize_map(OptionsVisitor)?))
+        }
+    }
+
+    // hands a section whose name is already read to the derived impl of Item
+    struct BuiltinSection<A> {
+        name: String,
+        variant: A,
+    }
+
+    impl<'de, A> Deserializer<'de> for BuiltinSection<A>
+    where
+        A: de::VariantAccess<'de>,
+    {
+        type Error = A::Error;
+
+        fn deserialize_any<W>(self, visitor: W) -> Result<W::Value, Self::Error>
+        where
+            W: de::Visitor<'de>,
+        {
+            visitor.visit_enum(self)
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    impl<'de, A> de::EnumAccess<'de> for BuiltinSection<A>
+    where
+        A: de::VariantAccess<'de>,
+    {
+        type Error = A::Error;
+        type Variant = A;
+
+        fn variant_seed<S>(self, seed: S) -> Result<(S::Value, Self::Variant), Self::Error>
+        where
+            S: de::DeserializeSeed<'de>,
+        {
+            let name = seed.deserialize(self.name.into_deserializer())?;
+            Ok((name, self.variant))
+        }
+    }
 }
 
 mod serde_zero_one_bool {
@@ -2945,6 +5629,84 @@ mod serde_opt_vec_u64 {
     }
 }
 
+mod serde_float_format {
+    use super::*;
+    use serde::ser::{Error as _, SerializeSeq};
+    use serde_json::Value;
+
+    // the sections with floats written in the given format. the sections go
+    // through JSON first, which tells floats apart from integers, and are then
+    // written as text options like custom sections. float lists such as scales
+    // are serialized as text and are kept exact.
+    pub struct FormattedSections {
+        pub sections: Sections,
+        pub format: FloatFormat,
+    }
+
+    impl Serialize for FormattedSections {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let json = serde_json::to_string(&self.sections).map_err(S::Error::custom)?;
+            // IndexMap keeps the key order of the JSON text
+            let sections: Vec<IndexMap<String, IndexMap<String, Value>>> =
+                serde_json::from_str(&json).map_err(S::Error::custom)?;
+
+            let mut seq = serializer.serialize_seq(Some(sections.len()))?;
+            for (name, options) in sections.into_iter().flatten() {
+                let options: IndexMap<_, _> = options
+                    .into_iter()
+                    .filter_map(|(key, value)| {
+                        let text = match value {
+                            Value::Null => return None,
+                            Value::Bool(yes) => yes.to_string(),
+                            Value::Number(number) => match number.as_f64() {
+                                Some(value) if number.is_f64() => self.format.format(value),
+                                _ => number.to_string(),
+                            },
+                            Value::String(text) => text,
+                            Value::Array(_) | Value::Object(_) => {
+                                return Some(Err(S::Error::custom(format!(
+                                    "[{}]: the value of '{}' is not a scalar",
+                                    name, key
+                                ))))
+                            }
+                        };
+                        Some(Ok((key, text)))
+                    })
+                    .try_collect()?;
+                seq.serialize_element(&FormattedSection { name, options })?;
+            }
+            seq.end()
+        }
+    }
+
+    struct FormattedSection {
+        name: String,
+        options: IndexMap<String, String>,
+    }
+
+    impl Serialize for FormattedSection {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let (index, name) = match BUILTIN_SECTIONS
+                .iter()
+                .position(|&section| section == self.name)
+            {
+                Some(index) => (index, BUILTIN_SECTIONS[index]),
+                None => {
+                    let handler = expect_section_handler(&self.name).map_err(S::Error::custom)?;
+                    (BUILTIN_SECTIONS.len(), handler.section_name())
+                }
+            };
+            serializer.serialize_newtype_variant("Item", index as u32, name, &self.options)
+        }
+    }
+}
+
 mod serde_opt_vec_r64 {
     use super::*;
 
@@ -2988,9 +5750,27 @@ mod serde_opt_vec_r64 {
 mod serde_anchors {
     use super::*;
 
-    pub fn serialize<S>(steps: &Option<Vec<(u64, u64)>>, serializer: S) -> Result<S::Ok, S::Error>
+    // yolo anchors are in pixels and region anchors in grid cells
+    pub trait AnchorValue: Sized + Display {
+        fn parse_value(token: &str) -> Option<Self>;
+    }
+
+    impl AnchorValue for u64 {
+        fn parse_value(token: &str) -> Option<Self> {
+            token.parse().ok()
+        }
+    }
+
+    impl AnchorValue for R64 {
+        fn parse_value(token: &str) -> Option<Self> {
+            R64::try_new(token.parse().ok()?)
+        }
+    }
+
+    pub fn serialize<S, T>(steps: &Option<Vec<(T, T)>>, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
+        T: AnchorValue,
     {
         steps
             .as_ref()
@@ -3004,22 +5784,29 @@ mod serde_anchors {
             .serialize(serializer)
     }
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Vec<(u64, u64)>>, D::Error>
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<Vec<(T, T)>>, D::Error>
     where
         D: Deserializer<'de>,
+        T: AnchorValue,
     {
         let text = match Option::<String>::deserialize(deserializer)? {
             Some(text) => text,
             None => return Ok(None),
         };
-        let values: Vec<u64> = text
+        let values: Vec<T> = text
             .chars()
             .filter(|c| !c.is_whitespace())
             .collect::<String>()
             .split(",")
-            .map(|token| token.parse())
-            .try_collect()
-            .map_err(|err| D::Error::custom(format!("failed to parse anchors: {:?}", err)))?;
+            .map(|token| {
+                T::parse_value(token).ok_or_else(|| {
+                    D::Error::custom(format!(
+                        "failed to parse anchors: invalid value '{}'",
+                        token
+                    ))
+                })
+            })
+            .try_collect()?;
 
         if values.len() % 2 != 0 {
             return Err(D::Error::custom("expect even number of values"));
@@ -1,15 +1,25 @@
+#[cfg(feature = "manifest")]
+use crate::provenance::{sidecar_path, WeightsProvenance};
 use crate::{
     common::*,
+    compress,
     config::{
-        BatchNormConfig, CommonLayerOptions, ConnectedConfig, ConvolutionalConfig, DarknetConfig,
+        BatchNormConfig, CommonLayerOptions, ConnectedConfig, ConvLstmConfig, ConvolutionalConfig,
+        CrnnConfig, DarknetConfig, ImplicitConfig, LayerConfigEx, LocalConfig, LstmConfig,
         ShortcutConfig, WeightsType,
     },
     model::{
-        BatchNormLayerBase, ConnectedLayerBase, ConvolutionalLayerBase, LayerBase,
-        MaxPoolLayerBase, ModelBase, RouteLayerBase, ShortcutLayerBase, UpSampleLayerBase,
-        YoloLayerBase,
+        AvgPoolLayerBase, BatchNormLayerBase, ConnectedLayerBase, ConvLstmLayerBase,
+        ConvolutionalLayerBase, CrnnLayerBase, CustomLayerBase, DropoutLayerBase,
+        GaussianYoloLayerBase, ImplicitLayerBase, LayerBase, LocalAvgPoolLayerBase, LocalLayerBase,
+        LstmLayerBase, MaxPoolLayerBase, ModelBase, RegionLayerBase, ReorgLayerBase,
+        RouteLayerBase, SamLayerBase, ScaleChannelsLayerBase, ShortcutLayerBase, SoftmaxLayerBase,
+        UpSampleLayerBase, YoloLayerBase,
     },
+    progress::{ProgressObserver, Stage},
+    weights_layout::stored_buffers,
 };
+use std::io;
 
 pub use layer::*;
 pub use model::*;
@@ -24,6 +34,26 @@ mod model {
         pub layers: IndexMap<usize, Layer>,
     }
 
+    // darknet dumps the weights in the byte order of the host, so files saved on
+    // big-endian hosts like s390x are big endian. the published weights are
+    // little endian.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub enum WeightsByteOrder {
+        Little,
+        Big,
+    }
+
+    impl WeightsByteOrder {
+        // the order of files dumped by darknet on this host
+        pub fn native() -> Self {
+            if cfg!(target_endian = "big") {
+                Self::Big
+            } else {
+                Self::Little
+            }
+        }
+    }
+
     impl DarknetModel {
         pub fn new(model_base: &ModelBase) -> Result<Self> {
             // aggregate all computed features
@@ -53,6 +83,39 @@ mod model {
                                 Layer::BatchNorm(BatchNormLayer::new(base))
                             }
                             LayerBase::Yolo(base) => Layer::Yolo(YoloLayer { base: base.clone() }),
+                            LayerBase::Implicit(base) => Layer::Implicit(ImplicitLayer::new(base)),
+                            LayerBase::AvgPool(base) => {
+                                Layer::AvgPool(AvgPoolLayer { base: base.clone() })
+                            }
+                            LayerBase::ScaleChannels(base) => {
+                                Layer::ScaleChannels(ScaleChannelsLayer { base: base.clone() })
+                            }
+                            LayerBase::Dropout(base) => {
+                                Layer::Dropout(DropoutLayer { base: base.clone() })
+                            }
+                            LayerBase::Softmax(base) => {
+                                Layer::Softmax(SoftmaxLayer { base: base.clone() })
+                            }
+                            LayerBase::Region(base) => {
+                                Layer::Region(RegionLayer { base: base.clone() })
+                            }
+                            LayerBase::GaussianYolo(base) => {
+                                Layer::GaussianYolo(GaussianYoloLayer { base: base.clone() })
+                            }
+                            LayerBase::ConvLstm(base) => Layer::ConvLstm(ConvLstmLayer::new(base)),
+                            LayerBase::Crnn(base) => Layer::Crnn(CrnnLayer::new(base)),
+                            LayerBase::Lstm(base) => Layer::Lstm(LstmLayer::new(base)),
+                            LayerBase::Sam(base) => Layer::Sam(SamLayer { base: base.clone() }),
+                            LayerBase::LocalAvgPool(base) => {
+                                Layer::LocalAvgPool(LocalAvgPoolLayer { base: base.clone() })
+                            }
+                            LayerBase::Local(base) => Layer::Local(LocalLayer::new(base)),
+                            LayerBase::Reorg(base) => {
+                                Layer::Reorg(ReorgLayer { base: base.clone() })
+                            }
+                            LayerBase::Custom(base) => {
+                                Layer::Custom(CustomLayer { base: base.clone() })
+                            }
                         };
 
                         Ok((layer_index, layer))
@@ -84,30 +147,167 @@ mod model {
         where
             P: AsRef<Path>,
         {
-            #[derive(Debug, Clone, PartialEq, Eq, Hash, BinRead)]
-            pub struct Version {
-                pub major: u32,
-                pub minor: u32,
-                pub revision: u32,
+            self.load_weights_with_offsets(weights_file)?;
+            Ok(())
+        }
+
+        pub fn load_weights_with_progress<P>(
+            &mut self,
+            weights_file: P,
+            observer: &mut dyn ProgressObserver,
+        ) -> Result<()>
+        where
+            P: AsRef<Path>,
+        {
+            self.load_weights_impl(weights_file.as_ref(), WeightsByteOrder::Little, observer)?;
+            Ok(())
+        }
+
+        // load a file dumped in the given byte order, see WeightsByteOrder
+        pub fn load_weights_with_byte_order<P>(
+            &mut self,
+            weights_file: P,
+            byte_order: WeightsByteOrder,
+        ) -> Result<()>
+        where
+            P: AsRef<Path>,
+        {
+            self.load_weights_impl(weights_file.as_ref(), byte_order, &mut ())?;
+            Ok(())
+        }
+
+        // loads the weights and reports the byte range that each layer occupies in the file.
+        // the ranges of a zstd-compressed file are in the decompressed stream.
+        pub fn load_weights_with_offsets<P>(
+            &mut self,
+            weights_file: P,
+        ) -> Result<IndexMap<usize, Range<u64>>>
+        where
+            P: AsRef<Path>,
+        {
+            self.load_weights_impl(weights_file.as_ref(), WeightsByteOrder::Little, &mut ())
+        }
+
+        // write the weights in the darknet 0.2.0 format, the inverse of load_weights()
+        pub fn write_weights<W>(&self, writer: W) -> Result<()>
+        where
+            W: Write,
+        {
+            self.write_weights_upto(writer, self.base.seen, self.layers.len())
+        }
+
+        // write the header with the given seen and the first num_layers layers
+        pub(crate) fn write_weights_upto<W>(
+            &self,
+            mut writer: W,
+            seen: u64,
+            num_layers: usize,
+        ) -> Result<()>
+        where
+            W: Write,
+        {
+            [0u32, 2, 0]
+                .iter()
+                .try_for_each(|value| writer.write_all(&value.to_le_bytes()))?;
+            writer.write_all(&seen.to_le_bytes())?;
+
+            for layer_index in 0..num_layers {
+                let layer = &self.layers[&layer_index];
+                let config = self.base.layers[&layer_index].config();
+                for (_, values) in stored_buffers(layer, config.common()) {
+                    let bytes: Vec<u8> = values
+                        .iter()
+                        .flat_map(|value| value.to_le_bytes().to_vec())
+                        .collect();
+                    writer.write_all(&bytes)?;
+                }
             }
 
-            let mut reader = BufReader::new(File::open(weights_file)?);
+            Ok(())
+        }
 
-            // load weights file
-            let (seen, transpose, mut reader) = move || -> Result<_, binread::Error> {
-                let version: Version = reader.read_le()?;
-                let Version { major, minor, .. } = version;
+        #[cfg(feature = "zstd")]
+        pub fn write_weights_zstd<W>(&self, writer: W, level: i32) -> Result<()>
+        where
+            W: Write,
+        {
+            let mut encoder = zstd::stream::write::Encoder::new(writer, level)?;
+            self.write_weights(&mut encoder)?;
+            encoder.finish()?;
+            Ok(())
+        }
+
+        // the weights are compressed by zstd if the file name ends with .zst
+        pub fn save_weights<P>(&self, weights_file: P) -> Result<()>
+        where
+            P: AsRef<Path>,
+        {
+            compress::write_file(weights_file.as_ref(), |writer| self.write_weights(writer))
+        }
+
+        // load the weights from a stream, such as a decompressor or a decryptor,
+        // which does not have to seek. zstd-compressed streams are decompressed.
+        pub fn read_weights<R>(&mut self, reader: R) -> Result<()>
+        where
+            R: Read,
+        {
+            let reader = compress::maybe_decompress(BufReader::new(reader))?;
+            self.read_weights_impl(reader, WeightsByteOrder::Little, &mut ())?;
+            Ok(())
+        }
+
+        fn load_weights_impl(
+            &mut self,
+            weights_file: &Path,
+            byte_order: WeightsByteOrder,
+            observer: &mut dyn ProgressObserver,
+        ) -> Result<IndexMap<usize, Range<u64>>> {
+            // a provenance sidecar, if any, must match the model and the file
+            #[cfg(feature = "manifest")]
+            if let Some(provenance) = WeightsProvenance::find(weights_file)? {
+                provenance
+                    .verify(&self.base.to_config(), weights_file)
+                    .map_err(|err| {
+                        format_err!("{:#}, see {}", err, sidecar_path(weights_file).display())
+                    })?;
+            }
+
+            let reader = compress::open_file(weights_file)?;
+            self.read_weights_impl(reader, byte_order, observer)
+        }
+
+        fn read_weights_impl(
+            &mut self,
+            reader: impl BufRead,
+            byte_order: WeightsByteOrder,
+            observer: &mut dyn ProgressObserver,
+        ) -> Result<IndexMap<usize, Range<u64>>> {
+            // the endianness is passed on reading instead of fixed by the header
+            fn read_header<B>(mut reader: impl Read) -> io::Result<(u64, bool)>
+            where
+                B: ByteOrder,
+            {
+                let major = reader.read_u32::<B>()?;
+                let minor = reader.read_u32::<B>()?;
+                let _revision = reader.read_u32::<B>()?;
 
                 let seen: u64 = if major * 10 + minor >= 2 {
-                    reader.read_le()?
+                    reader.read_u64::<B>()?
                 } else {
-                    let seen: u32 = reader.read_le()?;
-                    seen as u64
+                    reader.read_u32::<B>()? as u64
                 };
                 let transpose = (major > 1000) || (minor > 1000);
+                Ok((seen, transpose))
+            }
+
+            let mut reader = CountingReader::new(reader);
+            observer.stage_started(Stage::LoadWeights, Some(self.layers.len()));
 
-                Ok((seen, transpose, reader))
-            }()
+            // load weights file
+            let (seen, transpose) = match byte_order {
+                WeightsByteOrder::Little => read_header::<LittleEndian>(&mut reader),
+                WeightsByteOrder::Big => read_header::<BigEndian>(&mut reader),
+            }
             .map_err(|err| format_err!("failed to parse weight file: {:?}", err))?;
 
             // update network parameters
@@ -115,22 +315,75 @@ mod model {
             self.base.cur_iteration = self.base.net.iteration(seen);
 
             // load weights
-            {
+            let offsets = {
                 let num_layers = self.layers.len();
 
-                (0..num_layers).try_for_each(|layer_index| -> Result<_> {
-                    let layer = &mut self.layers[&layer_index];
-                    layer.load_weights(&mut reader, transpose)?;
-                    Ok(())
-                })?;
+                let offsets: IndexMap<_, _> = (0..num_layers)
+                    .map(|layer_index| -> Result<_> {
+                        let layer = &mut self.layers[&layer_index];
+                        observer.layer_started(Stage::LoadWeights, layer_index);
+                        let begin = reader.position;
+                        match byte_order {
+                            WeightsByteOrder::Little => {
+                                layer.load_weights_as::<LittleEndian>(&mut reader, transpose)?
+                            }
+                            WeightsByteOrder::Big => {
+                                layer.load_weights_as::<BigEndian>(&mut reader, transpose)?
+                            }
+                        }
+                        let end = reader.position;
+                        observer.layer_finished(Stage::LoadWeights, layer_index, end - begin);
+                        Ok((layer_index, begin..end))
+                    })
+                    .try_collect()?;
 
                 ensure!(
                     matches!(reader.fill_buf()?, &[]),
                     "the weights file is not totally consumed"
                 );
-            }
 
-            Ok(())
+                observer.stage_finished(Stage::LoadWeights, reader.position);
+                offsets
+            };
+
+            Ok(offsets)
+        }
+    }
+
+    // counts the consumed bytes in place of the position of a seekable reader
+    struct CountingReader<R> {
+        inner: R,
+        position: u64,
+    }
+
+    impl<R> CountingReader<R> {
+        fn new(inner: R) -> Self {
+            Self { inner, position: 0 }
+        }
+    }
+
+    impl<R> Read for CountingReader<R>
+    where
+        R: BufRead,
+    {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.inner.read(buf)?;
+            self.position += len as u64;
+            Ok(len)
+        }
+    }
+
+    impl<R> BufRead for CountingReader<R>
+    where
+        R: BufRead,
+    {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            self.inner.fill_buf()
+        }
+
+        fn consume(&mut self, amt: usize) {
+            self.inner.consume(amt);
+            self.position += amt as u64;
         }
     }
 }
@@ -164,19 +417,333 @@ mod layer {
         UpSample(UpSampleLayer),
         Yolo(YoloLayer),
         BatchNorm(BatchNormLayer),
+        Implicit(ImplicitLayer),
+        AvgPool(AvgPoolLayer),
+        ScaleChannels(ScaleChannelsLayer),
+        Dropout(DropoutLayer),
+        Softmax(SoftmaxLayer),
+        Region(RegionLayer),
+        GaussianYolo(GaussianYoloLayer),
+        ConvLstm(ConvLstmLayer),
+        Crnn(CrnnLayer),
+        Lstm(LstmLayer),
+        Sam(SamLayer),
+        LocalAvgPool(LocalAvgPoolLayer),
+        Local(LocalLayer),
+        Reorg(ReorgLayer),
+        Custom(CustomLayer),
     }
 
     impl Layer {
         pub fn load_weights(&mut self, reader: impl ReadBytesExt, transpose: bool) -> Result<()> {
+            self.load_weights_as::<LittleEndian>(reader, transpose)
+        }
+
+        // read the values in the byte order B regardless of the host
+        pub fn load_weights_as<B>(
+            &mut self,
+            reader: impl ReadBytesExt,
+            transpose: bool,
+        ) -> Result<()>
+        where
+            B: ByteOrder,
+        {
             match self {
-                Self::Connected(layer) => layer.load_weights(reader, transpose),
-                Self::Convolutional(layer) => layer.load_weights(reader),
+                Self::Connected(layer) => layer.load_weights::<B>(reader, transpose),
+                Self::Convolutional(layer) => layer.load_weights::<B>(reader),
                 Self::Route(_layer) => Ok(()),
-                Self::Shortcut(layer) => layer.load_weights(reader),
+                Self::Shortcut(layer) => layer.load_weights::<B>(reader),
                 Self::MaxPool(_layer) => Ok(()),
                 Self::UpSample(_layer) => Ok(()),
                 Self::Yolo(_layer) => Ok(()),
-                Self::BatchNorm(layer) => layer.load_weights(reader),
+                Self::BatchNorm(layer) => layer.load_weights::<B>(reader),
+                Self::Implicit(layer) => layer.load_weights::<B>(reader),
+                Self::AvgPool(_layer) => Ok(()),
+                Self::ScaleChannels(_layer) => Ok(()),
+                Self::Dropout(_layer) => Ok(()),
+                Self::Softmax(_layer) => Ok(()),
+                Self::Region(_layer) => Ok(()),
+                Self::GaussianYolo(_layer) => Ok(()),
+                Self::ConvLstm(layer) => layer.load_weights::<B>(reader),
+                Self::Crnn(layer) => layer.load_weights::<B>(reader),
+                Self::Lstm(layer) => layer.load_weights::<B>(reader, transpose),
+                Self::Sam(_layer) => Ok(()),
+                Self::LocalAvgPool(_layer) => Ok(()),
+                Self::Local(layer) => layer.load_weights::<B>(reader),
+                Self::Reorg(_layer) => Ok(()),
+                Self::Custom(_layer) => Ok(()),
+            }
+        }
+
+        // the parameter buffers in file order, paired with a flag marking the rolling
+        // statistics of batch normalization. shared weights belong to the owning layer.
+        pub fn buffers(&self) -> Vec<(bool, &[f32])> {
+            fn scale_buffers(scales: Option<&ScaleWeights>) -> Vec<(bool, &[f32])> {
+                match scales {
+                    Some(scales) => vec![
+                        (false, scales.scales.as_slice().unwrap()),
+                        (true, scales.rolling_mean.as_slice().unwrap()),
+                        (true, scales.rolling_variance.as_slice().unwrap()),
+                    ],
+                    None => vec![],
+                }
+            }
+
+            fn conv_buffers(conv: &RecurrentConvWeights) -> Vec<(bool, &[f32])> {
+                let RecurrentConvWeights {
+                    biases,
+                    weights,
+                    scales,
+                } = conv;
+                let mut buffers = vec![(false, biases.as_slice().unwrap())];
+                buffers.extend(scale_buffers(scales.as_ref()));
+                buffers.push((false, weights.as_slice().unwrap()));
+                buffers
+            }
+
+            match self {
+                Self::Connected(ConnectedLayer {
+                    weights:
+                        ConnectedWeights {
+                            biases,
+                            weights,
+                            scales,
+                        },
+                    ..
+                }) => {
+                    let mut buffers = vec![
+                        (false, biases.as_slice().unwrap()),
+                        (false, weights.as_slice().unwrap()),
+                    ];
+                    buffers.extend(scale_buffers(scales.as_ref()));
+                    buffers
+                }
+                Self::Convolutional(ConvolutionalLayer { weights, .. }) => match weights {
+                    ConvolutionalWeights::Owned {
+                        biases,
+                        weights,
+                        scales,
+                    } => {
+                        let mut buffers = vec![(false, biases.as_slice().unwrap())];
+                        buffers.extend(scale_buffers(scales.as_ref()));
+                        buffers.push((false, weights.as_slice().unwrap()));
+                        buffers
+                    }
+                    ConvolutionalWeights::Ref { .. } => vec![],
+                },
+                Self::BatchNorm(BatchNormLayer {
+                    weights:
+                        BatchNormWeights {
+                            biases,
+                            scales,
+                            rolling_mean,
+                            rolling_variance,
+                        },
+                    ..
+                }) => vec![
+                    (false, biases.as_slice().unwrap()),
+                    (false, scales.as_slice().unwrap()),
+                    (true, rolling_mean.as_slice().unwrap()),
+                    (true, rolling_variance.as_slice().unwrap()),
+                ],
+                Self::Shortcut(ShortcutLayer { weights, .. }) => match weights {
+                    ShortcutWeights::None => vec![],
+                    ShortcutWeights::PerFeature(weights) => {
+                        vec![(false, weights.as_slice().unwrap())]
+                    }
+                    ShortcutWeights::PerChannel(weights) => {
+                        vec![(false, weights.as_slice().unwrap())]
+                    }
+                },
+                Self::Implicit(ImplicitLayer {
+                    weights: ImplicitWeights { weights },
+                    ..
+                }) => vec![(false, weights.as_slice().unwrap())],
+                Self::Local(LocalLayer {
+                    weights: LocalWeights { biases, weights },
+                    ..
+                }) => vec![
+                    (false, biases.as_slice().unwrap()),
+                    (false, weights.as_slice().unwrap()),
+                ],
+                Self::Lstm(LstmLayer {
+                    weights: LstmWeights { gates },
+                    ..
+                }) => gates
+                    .iter()
+                    .flat_map(|gate| {
+                        let ConnectedWeights {
+                            biases,
+                            weights,
+                            scales,
+                        } = gate;
+                        let mut buffers = vec![
+                            (false, biases.as_slice().unwrap()),
+                            (false, weights.as_slice().unwrap()),
+                        ];
+                        buffers.extend(scale_buffers(scales.as_ref()));
+                        buffers
+                    })
+                    .collect(),
+                Self::Crnn(CrnnLayer {
+                    weights: CrnnWeights { convs },
+                    ..
+                }) => convs.iter().flat_map(conv_buffers).collect(),
+                Self::ConvLstm(ConvLstmLayer {
+                    weights: ConvLstmWeights { gates },
+                    ..
+                }) => gates.iter().flat_map(conv_buffers).collect(),
+                Self::Route(_)
+                | Self::MaxPool(_)
+                | Self::UpSample(_)
+                | Self::Yolo(_)
+                | Self::AvgPool(_)
+                | Self::ScaleChannels(_)
+                | Self::Dropout(_)
+                | Self::Softmax(_)
+                | Self::Region(_)
+                | Self::GaussianYolo(_)
+                | Self::Sam(_)
+                | Self::LocalAvgPool(_)
+                | Self::Reorg(_)
+                | Self::Custom(_) => vec![],
+            }
+        }
+
+        pub fn buffers_mut(&mut self) -> Vec<(bool, &mut [f32])> {
+            fn scale_buffers(scales: Option<&mut ScaleWeights>) -> Vec<(bool, &mut [f32])> {
+                match scales {
+                    Some(ScaleWeights {
+                        scales,
+                        rolling_mean,
+                        rolling_variance,
+                    }) => vec![
+                        (false, scales.as_slice_mut().unwrap()),
+                        (true, rolling_mean.as_slice_mut().unwrap()),
+                        (true, rolling_variance.as_slice_mut().unwrap()),
+                    ],
+                    None => vec![],
+                }
+            }
+
+            fn conv_buffers(conv: &mut RecurrentConvWeights) -> Vec<(bool, &mut [f32])> {
+                let RecurrentConvWeights {
+                    biases,
+                    weights,
+                    scales,
+                } = conv;
+                let mut buffers = vec![(false, biases.as_slice_mut().unwrap())];
+                buffers.extend(scale_buffers(scales.as_mut()));
+                buffers.push((false, weights.as_slice_mut().unwrap()));
+                buffers
+            }
+
+            match self {
+                Self::Connected(ConnectedLayer {
+                    weights:
+                        ConnectedWeights {
+                            biases,
+                            weights,
+                            scales,
+                        },
+                    ..
+                }) => {
+                    let mut buffers = vec![
+                        (false, biases.as_slice_mut().unwrap()),
+                        (false, weights.as_slice_mut().unwrap()),
+                    ];
+                    buffers.extend(scale_buffers(scales.as_mut()));
+                    buffers
+                }
+                Self::Convolutional(ConvolutionalLayer { weights, .. }) => match weights {
+                    ConvolutionalWeights::Owned {
+                        biases,
+                        weights,
+                        scales,
+                    } => {
+                        let mut buffers = vec![(false, biases.as_slice_mut().unwrap())];
+                        buffers.extend(scale_buffers(scales.as_mut()));
+                        buffers.push((false, weights.as_slice_mut().unwrap()));
+                        buffers
+                    }
+                    ConvolutionalWeights::Ref { .. } => vec![],
+                },
+                Self::BatchNorm(BatchNormLayer {
+                    weights:
+                        BatchNormWeights {
+                            biases,
+                            scales,
+                            rolling_mean,
+                            rolling_variance,
+                        },
+                    ..
+                }) => vec![
+                    (false, biases.as_slice_mut().unwrap()),
+                    (false, scales.as_slice_mut().unwrap()),
+                    (true, rolling_mean.as_slice_mut().unwrap()),
+                    (true, rolling_variance.as_slice_mut().unwrap()),
+                ],
+                Self::Shortcut(ShortcutLayer { weights, .. }) => match weights {
+                    ShortcutWeights::None => vec![],
+                    ShortcutWeights::PerFeature(weights) => {
+                        vec![(false, weights.as_slice_mut().unwrap())]
+                    }
+                    ShortcutWeights::PerChannel(weights) => {
+                        vec![(false, weights.as_slice_mut().unwrap())]
+                    }
+                },
+                Self::Implicit(ImplicitLayer {
+                    weights: ImplicitWeights { weights },
+                    ..
+                }) => vec![(false, weights.as_slice_mut().unwrap())],
+                Self::Local(LocalLayer {
+                    weights: LocalWeights { biases, weights },
+                    ..
+                }) => vec![
+                    (false, biases.as_slice_mut().unwrap()),
+                    (false, weights.as_slice_mut().unwrap()),
+                ],
+                Self::Lstm(LstmLayer {
+                    weights: LstmWeights { gates },
+                    ..
+                }) => gates
+                    .iter_mut()
+                    .flat_map(|gate| {
+                        let ConnectedWeights {
+                            biases,
+                            weights,
+                            scales,
+                        } = gate;
+                        let mut buffers = vec![
+                            (false, biases.as_slice_mut().unwrap()),
+                            (false, weights.as_slice_mut().unwrap()),
+                        ];
+                        buffers.extend(scale_buffers(scales.as_mut()));
+                        buffers
+                    })
+                    .collect(),
+                Self::Crnn(CrnnLayer {
+                    weights: CrnnWeights { convs },
+                    ..
+                }) => convs.iter_mut().flat_map(conv_buffers).collect(),
+                Self::ConvLstm(ConvLstmLayer {
+                    weights: ConvLstmWeights { gates },
+                    ..
+                }) => gates.iter_mut().flat_map(conv_buffers).collect(),
+                Self::Route(_)
+                | Self::MaxPool(_)
+                | Self::UpSample(_)
+                | Self::Yolo(_)
+                | Self::AvgPool(_)
+                | Self::ScaleChannels(_)
+                | Self::Dropout(_)
+                | Self::Softmax(_)
+                | Self::Region(_)
+                | Self::GaussianYolo(_)
+                | Self::Sam(_)
+                | Self::LocalAvgPool(_)
+                | Self::Reorg(_)
+                | Self::Custom(_) => vec![],
             }
         }
     }
@@ -193,6 +760,21 @@ mod layer {
     declare_darknet_layer!(MaxPoolLayer, MaxPoolLayerBase);
     declare_darknet_layer!(UpSampleLayer, UpSampleLayerBase);
     declare_darknet_layer!(YoloLayer, YoloLayerBase);
+    declare_darknet_layer!(ImplicitLayer, ImplicitLayerBase, ImplicitWeights);
+    declare_darknet_layer!(AvgPoolLayer, AvgPoolLayerBase);
+    declare_darknet_layer!(ScaleChannelsLayer, ScaleChannelsLayerBase);
+    declare_darknet_layer!(DropoutLayer, DropoutLayerBase);
+    declare_darknet_layer!(SoftmaxLayer, SoftmaxLayerBase);
+    declare_darknet_layer!(RegionLayer, RegionLayerBase);
+    declare_darknet_layer!(GaussianYoloLayer, GaussianYoloLayerBase);
+    declare_darknet_layer!(SamLayer, SamLayerBase);
+    declare_darknet_layer!(LocalAvgPoolLayer, LocalAvgPoolLayerBase);
+    declare_darknet_layer!(LocalLayer, LocalLayerBase, LocalWeights);
+    declare_darknet_layer!(LstmLayer, LstmLayerBase, LstmWeights);
+    declare_darknet_layer!(CrnnLayer, CrnnLayerBase, CrnnWeights);
+    declare_darknet_layer!(ConvLstmLayer, ConvLstmLayerBase, ConvLstmWeights);
+    declare_darknet_layer!(ReorgLayer, ReorgLayerBase);
+    declare_darknet_layer!(CustomLayer, CustomLayerBase);
 
     impl ConnectedLayer {
         pub fn new(base: &ConnectedLayerBase) -> Self {
@@ -227,11 +809,14 @@ mod layer {
             }
         }
 
-        pub fn load_weights(
+        pub fn load_weights<B>(
             &mut self,
             mut reader: impl ReadBytesExt,
             transpose: bool,
-        ) -> Result<()> {
+        ) -> Result<()>
+        where
+            B: ByteOrder,
+        {
             let Self {
                 base:
                     ConnectedLayerBase {
@@ -262,8 +847,8 @@ mod layer {
                 return Ok(());
             }
 
-            reader.read_f32_into::<LittleEndian>(biases.as_slice_mut().unwrap())?;
-            reader.read_f32_into::<LittleEndian>(weights.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(biases.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(weights.as_slice_mut().unwrap())?;
 
             if transpose {
                 crate::utils::transpose_matrix(
@@ -274,7 +859,7 @@ mod layer {
             }
 
             if let (Some(scales), false) = (scales, dont_load_scales) {
-                scales.load_weights(reader)?;
+                scales.load_weights::<B>(reader)?;
             }
 
             Ok(())
@@ -338,7 +923,10 @@ mod layer {
             })
         }
 
-        pub fn load_weights(&mut self, mut reader: impl ReadBytesExt) -> Result<()> {
+        pub fn load_weights<B>(&mut self, mut reader: impl ReadBytesExt) -> Result<()>
+        where
+            B: ByteOrder,
+        {
             let Self {
                 base:
                     ConvolutionalLayerBase {
@@ -374,13 +962,13 @@ mod layer {
                     scales,
                     weights,
                 } => {
-                    reader.read_f32_into::<LittleEndian>(biases.as_slice_mut().unwrap())?;
+                    reader.read_f32_into::<B>(biases.as_slice_mut().unwrap())?;
 
                     if let (Some(scales), false) = (scales, dont_load_scales) {
-                        scales.load_weights(&mut reader)?;
+                        scales.load_weights::<B>(&mut reader)?;
                     }
 
-                    reader.read_f32_into::<LittleEndian>(weights.as_slice_mut().unwrap())?;
+                    reader.read_f32_into::<B>(weights.as_slice_mut().unwrap())?;
 
                     if flipped {
                         crate::utils::transpose_matrix(
@@ -419,7 +1007,10 @@ mod layer {
             }
         }
 
-        pub fn load_weights(&mut self, mut reader: impl ReadBytesExt) -> Result<()> {
+        pub fn load_weights<B>(&mut self, mut reader: impl ReadBytesExt) -> Result<()>
+        where
+            B: ByteOrder,
+        {
             let Self {
                 base:
                     BatchNormLayerBase {
@@ -444,10 +1035,10 @@ mod layer {
                 return Ok(());
             }
 
-            reader.read_f32_into::<LittleEndian>(biases.as_slice_mut().unwrap())?;
-            reader.read_f32_into::<LittleEndian>(scales.as_slice_mut().unwrap())?;
-            reader.read_f32_into::<LittleEndian>(rolling_mean.as_slice_mut().unwrap())?;
-            reader.read_f32_into::<LittleEndian>(rolling_variance.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(biases.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(scales.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(rolling_mean.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(rolling_variance.as_slice_mut().unwrap())?;
 
             Ok(())
         }
@@ -489,7 +1080,10 @@ mod layer {
             }
         }
 
-        pub fn load_weights(&mut self, mut reader: impl ReadBytesExt) -> Result<()> {
+        pub fn load_weights<B>(&mut self, mut reader: impl ReadBytesExt) -> Result<()>
+        where
+            B: ByteOrder,
+        {
             let Self {
                 base:
                     ShortcutLayerBase {
@@ -511,16 +1105,293 @@ mod layer {
             match weights {
                 ShortcutWeights::None => (),
                 ShortcutWeights::PerFeature(weights) => {
-                    reader.read_f32_into::<LittleEndian>(weights.as_slice_mut().unwrap())?;
+                    reader.read_f32_into::<B>(weights.as_slice_mut().unwrap())?;
                 }
                 ShortcutWeights::PerChannel(weights) => {
-                    reader.read_f32_into::<LittleEndian>(weights.as_slice_mut().unwrap())?;
+                    reader.read_f32_into::<B>(weights.as_slice_mut().unwrap())?;
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    impl ImplicitLayer {
+        pub fn new(base: &ImplicitLayerBase) -> Self {
+            let [_h, _w, channels] = base.output_shape;
+            let channels = channels as usize;
+
+            let weights = ImplicitWeights {
+                weights: Array1::from_shape_vec(channels, vec![0.0; channels]).unwrap(),
+            };
+
+            Self {
+                base: base.clone(),
+                weights,
+            }
+        }
+
+        pub fn load_weights<B>(&mut self, mut reader: impl ReadBytesExt) -> Result<()>
+        where
+            B: ByteOrder,
+        {
+            let Self {
+                base:
+                    ImplicitLayerBase {
+                        config:
+                            ImplicitConfig {
+                                common: CommonLayerOptions { dont_load, .. },
+                                ..
+                            },
+                        ..
+                    },
+                weights: ImplicitWeights { ref mut weights },
+            } = *self;
+
+            if dont_load {
+                return Ok(());
+            }
+
+            reader.read_f32_into::<B>(weights.as_slice_mut().unwrap())?;
+
+            Ok(())
+        }
+    }
+
+    impl LocalLayer {
+        pub fn new(base: &LocalLayerBase) -> Self {
+            let [s1, s2, s3] = base.weights_shape();
+            let [s1, s2, s3] = [s1 as usize, s2 as usize, s3 as usize];
+            let [out_h, out_w, filters] = base.output_shape;
+            let [out_h, out_w, filters] = [out_h as usize, out_w as usize, filters as usize];
+
+            let weights = LocalWeights {
+                biases: Array3::from_shape_vec(
+                    [filters, out_h, out_w],
+                    vec![0.0; filters * out_h * out_w],
+                )
+                .unwrap(),
+                weights: Array3::from_shape_vec([s1, s2, s3], vec![0.0; s1 * s2 * s3]).unwrap(),
+            };
+
+            Self {
+                base: base.clone(),
+                weights,
+            }
+        }
+
+        pub fn load_weights<B>(&mut self, mut reader: impl ReadBytesExt) -> Result<()>
+        where
+            B: ByteOrder,
+        {
+            let Self {
+                base:
+                    LocalLayerBase {
+                        config:
+                            LocalConfig {
+                                common: CommonLayerOptions { dont_load, .. },
+                                ..
+                            },
+                        ..
+                    },
+                weights:
+                    LocalWeights {
+                        ref mut biases,
+                        ref mut weights,
+                    },
+            } = *self;
+
+            if dont_load {
+                return Ok(());
+            }
+
+            reader.read_f32_into::<B>(biases.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(weights.as_slice_mut().unwrap())?;
+
+            Ok(())
+        }
+    }
+
+    impl LstmLayer {
+        pub fn new(base: &LstmLayerBase) -> Self {
+            let batch_normalize = base.config.batch_normalize;
+            let gates = base
+                .gate_shapes()
+                .iter()
+                .map(|&(inputs, outputs)| {
+                    ConnectedWeights::new(inputs as usize, outputs as usize, batch_normalize)
+                })
+                .collect();
+
+            Self {
+                base: base.clone(),
+                weights: LstmWeights { gates },
+            }
+        }
+
+        pub fn load_weights<B>(
+            &mut self,
+            mut reader: impl ReadBytesExt,
+            transpose: bool,
+        ) -> Result<()>
+        where
+            B: ByteOrder,
+        {
+            let Self {
+                ref base,
+                weights: LstmWeights { ref mut gates },
+            } = *self;
+            let LstmLayerBase {
+                config:
+                    LstmConfig {
+                        common: CommonLayerOptions { dont_load, .. },
+                        ..
+                    },
+                ..
+            } = *base;
+
+            if dont_load {
+                return Ok(());
+            }
+
+            // darknet loads the scales of the gates regardless of dontloadscales
+            for (gate, (inputs, outputs)) in gates.iter_mut().zip(base.gate_shapes().iter()) {
+                let ConnectedWeights {
+                    biases,
+                    weights,
+                    scales,
+                } = gate;
+
+                reader.read_f32_into::<B>(biases.as_slice_mut().unwrap())?;
+                reader.read_f32_into::<B>(weights.as_slice_mut().unwrap())?;
+
+                if transpose {
+                    crate::utils::transpose_matrix(
+                        weights.as_slice_mut().unwrap(),
+                        *inputs as usize,
+                        *outputs as usize,
+                    )?;
+                }
+
+                if let Some(scales) = scales {
+                    scales.load_weights::<B>(&mut reader)?;
                 }
             }
 
             Ok(())
         }
     }
+
+    impl CrnnLayer {
+        pub fn new(base: &CrnnLayerBase) -> Self {
+            let CrnnConfig {
+                size,
+                batch_normalize,
+                ..
+            } = base.config;
+            let convs = base
+                .conv_shapes()
+                .iter()
+                .map(|&(_, in_c, filters)| {
+                    RecurrentConvWeights::new(
+                        in_c as usize,
+                        filters as usize,
+                        size as usize,
+                        batch_normalize,
+                    )
+                })
+                .collect();
+
+            Self {
+                base: base.clone(),
+                weights: CrnnWeights { convs },
+            }
+        }
+
+        pub fn load_weights<B>(&mut self, mut reader: impl ReadBytesExt) -> Result<()>
+        where
+            B: ByteOrder,
+        {
+            let Self {
+                base:
+                    CrnnLayerBase {
+                        config:
+                            CrnnConfig {
+                                common: CommonLayerOptions { dont_load, .. },
+                                ..
+                            },
+                        ..
+                    },
+                weights: CrnnWeights { ref mut convs },
+            } = *self;
+
+            if dont_load {
+                return Ok(());
+            }
+
+            for conv in convs {
+                conv.load_weights::<B>(&mut reader)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl ConvLstmLayer {
+        pub fn new(base: &ConvLstmLayerBase) -> Self {
+            let ConvLstmConfig {
+                size,
+                groups,
+                batch_normalize,
+                ..
+            } = base.config;
+            let gates = base
+                .gate_shapes()
+                .iter()
+                .map(|&(_, in_c, filters)| {
+                    RecurrentConvWeights::new(
+                        (in_c / groups) as usize,
+                        filters as usize,
+                        size as usize,
+                        batch_normalize,
+                    )
+                })
+                .collect();
+
+            Self {
+                base: base.clone(),
+                weights: ConvLstmWeights { gates },
+            }
+        }
+
+        pub fn load_weights<B>(&mut self, mut reader: impl ReadBytesExt) -> Result<()>
+        where
+            B: ByteOrder,
+        {
+            let Self {
+                base:
+                    ConvLstmLayerBase {
+                        config:
+                            ConvLstmConfig {
+                                common: CommonLayerOptions { dont_load, .. },
+                                ..
+                            },
+                        ..
+                    },
+                weights: ConvLstmWeights { ref mut gates },
+            } = *self;
+
+            if dont_load {
+                return Ok(());
+            }
+
+            for gate in gates {
+                gate.load_weights::<B>(&mut reader)?;
+            }
+
+            Ok(())
+        }
+    }
 }
 
 mod weights {
@@ -542,16 +1413,19 @@ mod weights {
             }
         }
 
-        pub fn load_weights(&mut self, mut reader: impl ReadBytesExt) -> Result<()> {
+        pub fn load_weights<B>(&mut self, mut reader: impl ReadBytesExt) -> Result<()>
+        where
+            B: ByteOrder,
+        {
             let Self {
                 scales,
                 rolling_mean,
                 rolling_variance,
             } = self;
 
-            reader.read_f32_into::<LittleEndian>(scales.as_slice_mut().unwrap())?;
-            reader.read_f32_into::<LittleEndian>(rolling_mean.as_slice_mut().unwrap())?;
-            reader.read_f32_into::<LittleEndian>(rolling_variance.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(scales.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(rolling_mean.as_slice_mut().unwrap())?;
+            reader.read_f32_into::<B>(rolling_variance.as_slice_mut().unwrap())?;
             Ok(())
         }
     }
@@ -563,6 +1437,21 @@ mod weights {
         pub scales: Option<ScaleWeights>,
     }
 
+    impl ConnectedWeights {
+        pub fn new(inputs: usize, outputs: usize, batch_normalize: bool) -> Self {
+            Self {
+                biases: Array1::from_shape_vec(outputs, vec![0.0; outputs]).unwrap(),
+                weights: Array2::from_shape_vec([inputs, outputs], vec![0.0; inputs * outputs])
+                    .unwrap(),
+                scales: if batch_normalize {
+                    Some(ScaleWeights::new(outputs))
+                } else {
+                    None
+                },
+            }
+        }
+    }
+
     #[derive(Debug, Clone)]
     pub enum ConvolutionalWeights {
         Owned {
@@ -589,4 +1478,81 @@ mod weights {
         PerFeature(Array1<f32>),
         PerChannel(Array2<f32>),
     }
+
+    #[derive(Debug, Clone)]
+    pub struct ImplicitWeights {
+        pub weights: Array1<f32>,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct LocalWeights {
+        // one bias per output value
+        pub biases: Array3<f32>,
+        // the kernels of each output location
+        pub weights: Array3<f32>,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct LstmWeights {
+        // in the order of LSTM_GATES
+        pub gates: Vec<ConnectedWeights>,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct RecurrentConvWeights {
+        pub biases: Array1<f32>,
+        pub weights: Array4<f32>,
+        pub scales: Option<ScaleWeights>,
+    }
+
+    impl RecurrentConvWeights {
+        pub fn new(in_c: usize, filters: usize, size: usize, batch_normalize: bool) -> Self {
+            let weights_shape = [in_c, filters, size, size];
+            Self {
+                biases: Array1::from_shape_vec(filters, vec![0.0; filters]).unwrap(),
+                weights: Array4::from_shape_vec(
+                    weights_shape,
+                    vec![0.0; weights_shape.iter().product()],
+                )
+                .unwrap(),
+                scales: if batch_normalize {
+                    Some(ScaleWeights::new(filters))
+                } else {
+                    None
+                },
+            }
+        }
+
+        // loaded like a [convolutional] layer, except that darknet loads the scales
+        // regardless of dontloadscales
+        pub fn load_weights<B>(&mut self, mut reader: impl ReadBytesExt) -> Result<()>
+        where
+            B: ByteOrder,
+        {
+            let Self {
+                biases,
+                weights,
+                scales,
+            } = self;
+
+            reader.read_f32_into::<B>(biases.as_slice_mut().unwrap())?;
+            if let Some(scales) = scales {
+                scales.load_weights::<B>(&mut reader)?;
+            }
+            reader.read_f32_into::<B>(weights.as_slice_mut().unwrap())?;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct CrnnWeights {
+        // in the order of CRNN_CONVS
+        pub convs: Vec<RecurrentConvWeights>,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct ConvLstmWeights {
+        // in the order of ConvLstmLayerBase::gate_shapes()
+        pub gates: Vec<RecurrentConvWeights>,
+    }
 }
@@ -0,0 +1,1016 @@
+use crate::common::*;
+
+pub use convert::*;
+pub use data::*;
+pub use label::*;
+pub use merge::*;
+pub use stats::*;
+
+mod data {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct DataConfig {
+        pub classes: u64,
+        pub train: Option<PathBuf>,
+        pub valid: Option<PathBuf>,
+        pub names: Option<PathBuf>,
+        pub backup: Option<PathBuf>,
+        pub eval: Option<String>,
+        pub extra: IndexMap<String, String>,
+    }
+
+    impl DataConfig {
+        pub fn load<P>(data_file: P) -> Result<Self>
+        where
+            P: AsRef<Path>,
+        {
+            Self::from_str(&fs::read_to_string(data_file)?)
+        }
+
+        pub fn load_names(&self) -> Result<Vec<String>> {
+            let names_file = self
+                .names
+                .as_ref()
+                .ok_or_else(|| format_err!("names option is not specified"))?;
+            let names = load_names(names_file)?;
+            ensure!(
+                names.len() as u64 == self.classes,
+                "the number of names ({}) does not match the number of classes ({})",
+                names.len(),
+                self.classes
+            );
+            Ok(names)
+        }
+
+        pub fn load_train_list(&self) -> Result<Vec<PathBuf>> {
+            let train_file = self
+                .train
+                .as_ref()
+                .ok_or_else(|| format_err!("train option is not specified"))?;
+            load_image_list(train_file)
+        }
+
+        pub fn load_valid_list(&self) -> Result<Vec<PathBuf>> {
+            let valid_file = self
+                .valid
+                .as_ref()
+                .ok_or_else(|| format_err!("valid option is not specified"))?;
+            load_image_list(valid_file)
+        }
+    }
+
+    impl Display for DataConfig {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let Self {
+                classes,
+                ref train,
+                ref valid,
+                ref names,
+                ref backup,
+                ref eval,
+                ref extra,
+            } = *self;
+
+            writeln!(f, "classes = {}", classes)?;
+            let paths = vec![
+                ("train", train),
+                ("valid", valid),
+                ("names", names),
+                ("backup", backup),
+            ];
+            for (key, path) in paths {
+                if let Some(path) = path {
+                    writeln!(f, "{} = {}", key, path.display())?;
+                }
+            }
+            if let Some(eval) = eval {
+                writeln!(f, "eval = {}", eval)?;
+            }
+            for (key, value) in extra {
+                writeln!(f, "{} = {}", key, value)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl FromStr for DataConfig {
+        type Err = Error;
+
+        fn from_str(text: &str) -> Result<Self, Self::Err> {
+            let mut options: IndexMap<String, String> = text
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+                .map(|line| {
+                    let mut tokens = line.splitn(2, '=');
+                    let key = tokens.next().unwrap().trim();
+                    let value = tokens
+                        .next()
+                        .ok_or_else(|| format_err!("invalid line '{}' in data file", line))?
+                        .trim();
+                    Ok((key.to_owned(), value.to_owned()))
+                })
+                .collect::<Result<_>>()?;
+
+            let classes: u64 = options
+                .remove("classes")
+                .ok_or_else(|| format_err!("classes option is not specified"))?
+                .parse()?;
+            let train = options.remove("train").map(PathBuf::from);
+            let valid = options.remove("valid").map(PathBuf::from);
+            let names = options
+                .remove("names")
+                .or_else(|| options.remove("labels"))
+                .map(PathBuf::from);
+            let backup = options.remove("backup").map(PathBuf::from);
+            let eval = options.remove("eval");
+
+            Ok(Self {
+                classes,
+                train,
+                valid,
+                names,
+                backup,
+                eval,
+                extra: options,
+            })
+        }
+    }
+
+    pub fn load_names<P>(names_file: P) -> Result<Vec<String>>
+    where
+        P: AsRef<Path>,
+    {
+        let names: Vec<_> = fs::read_to_string(names_file)?
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_owned())
+            .collect();
+        Ok(names)
+    }
+
+    #[cfg(feature = "image")]
+    pub fn image_size<P>(image_path: P) -> Result<(u32, u32)>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(image::image_dimensions(image_path)?)
+    }
+
+    pub fn load_image_list<P>(list_file: P) -> Result<Vec<PathBuf>>
+    where
+        P: AsRef<Path>,
+    {
+        let paths: Vec<_> = fs::read_to_string(list_file)?
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect();
+        Ok(paths)
+    }
+}
+
+mod convert {
+    use super::*;
+    use serde_json::json;
+    #[cfg(feature = "voc")]
+    use xml::reader::{EventReader, XmlEvent};
+
+    // images and their labels in darknet format
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct LabeledDataset {
+        pub names: Vec<String>,
+        pub images: Vec<(PathBuf, Vec<LabelBox>)>,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct CocoFile {
+        images: Vec<CocoImage>,
+        annotations: Vec<CocoAnnotation>,
+        categories: Vec<CocoCategory>,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct CocoImage {
+        id: u64,
+        file_name: String,
+        width: f64,
+        height: f64,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct CocoAnnotation {
+        image_id: u64,
+        category_id: u64,
+        bbox: [f64; 4],
+        #[serde(default)]
+        iscrowd: u8,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct CocoCategory {
+        id: u64,
+        name: String,
+    }
+
+    #[cfg(feature = "voc")]
+    #[derive(Debug, Clone, Default)]
+    struct VocObject {
+        name: String,
+        difficult: bool,
+        bndbox: [f64; 4],
+    }
+
+    impl LabeledDataset {
+        // categories are ordered by their ids and crowd annotations are skipped
+        pub fn from_coco_json<P, Q>(annotation_file: P, image_dir: Q) -> Result<Self>
+        where
+            P: AsRef<Path>,
+            Q: AsRef<Path>,
+        {
+            let image_dir = image_dir.as_ref();
+            let CocoFile {
+                images,
+                annotations,
+                mut categories,
+            } = serde_json::from_str(&fs::read_to_string(annotation_file)?)?;
+
+            categories.sort_by_key(|category| category.id);
+            let classes: HashMap<u64, u64> = categories
+                .iter()
+                .enumerate()
+                .map(|(class, category)| (category.id, class as u64))
+                .collect();
+            let names = categories
+                .into_iter()
+                .map(|category| category.name)
+                .collect();
+
+            let mut labels: IndexMap<u64, (PathBuf, [f64; 2], Vec<LabelBox>)> = images
+                .into_iter()
+                .map(|image| {
+                    let path = image_dir.join(&image.file_name);
+                    (image.id, (path, [image.width, image.height], vec![]))
+                })
+                .collect();
+
+            for annotation in annotations {
+                if annotation.iscrowd != 0 {
+                    continue;
+                }
+                let class = *classes
+                    .get(&annotation.category_id)
+                    .ok_or_else(|| format_err!("unknown category id {}", annotation.category_id))?;
+                let (_, [width, height], boxes) = labels
+                    .get_mut(&annotation.image_id)
+                    .ok_or_else(|| format_err!("unknown image id {}", annotation.image_id))?;
+                ensure!(
+                    *width > 0.0 && *height > 0.0,
+                    "image {} has an empty size",
+                    annotation.image_id
+                );
+
+                let [x, y, w, h] = annotation.bbox;
+                boxes.push(LabelBox {
+                    class,
+                    x: (x + w / 2.0) / *width,
+                    y: (y + h / 2.0) / *height,
+                    w: w / *width,
+                    h: h / *height,
+                });
+            }
+
+            Ok(Self {
+                names,
+                images: labels
+                    .into_iter()
+                    .map(|(_, (path, _, boxes))| (path, boxes))
+                    .collect(),
+            })
+        }
+
+        // follow darknet's scripts/voc_label.py, difficult objects and objects not
+        // in the names are skipped. the names are collected in sorted order if not given.
+        #[cfg(feature = "voc")]
+        pub fn from_voc_xml<P, Q>(
+            xml_files: &[P],
+            image_dir: Q,
+            names: Option<&[String]>,
+        ) -> Result<Self>
+        where
+            P: AsRef<Path>,
+            Q: AsRef<Path>,
+        {
+            let image_dir = image_dir.as_ref();
+            let annotations: Vec<_> = xml_files
+                .iter()
+                .map(|xml_file| -> Result<_> {
+                    let xml_file = xml_file.as_ref();
+                    parse_voc_xml(&fs::read_to_string(xml_file)?)
+                        .map_err(|err| format_err!("{}: {}", xml_file.display(), err))
+                })
+                .try_collect()?;
+
+            let names: Vec<String> = match names {
+                Some(names) => names.to_vec(),
+                None => annotations
+                    .iter()
+                    .flat_map(|(_, _, objects)| objects)
+                    .filter(|object| !object.difficult)
+                    .map(|object| object.name.clone())
+                    .sorted()
+                    .dedup()
+                    .collect(),
+            };
+
+            let images = annotations
+                .into_iter()
+                .map(|(file_name, [width, height], objects)| -> Result<_> {
+                    ensure!(
+                        width > 0.0 && height > 0.0,
+                        "image {} has an empty size",
+                        file_name
+                    );
+
+                    let boxes = objects
+                        .into_iter()
+                        .filter(|object| !object.difficult)
+                        .filter_map(|object| {
+                            let class = names.iter().position(|name| *name == object.name)?;
+                            let [xmin, ymin, xmax, ymax] = object.bndbox;
+                            // voc coordinates are 1-based
+                            Some(LabelBox {
+                                class: class as u64,
+                                x: ((xmin + xmax) / 2.0 - 1.0) / width,
+                                y: ((ymin + ymax) / 2.0 - 1.0) / height,
+                                w: (xmax - xmin) / width,
+                                h: (ymax - ymin) / height,
+                            })
+                        })
+                        .collect();
+
+                    Ok((image_dir.join(file_name), boxes))
+                })
+                .try_collect()?;
+
+            Ok(Self { names, images })
+        }
+
+        // load the labels of the images, images without label files have no objects
+        pub fn from_image_list<P>(names: Vec<String>, image_paths: &[P]) -> Result<Self>
+        where
+            P: AsRef<Path>,
+        {
+            let images = image_paths
+                .iter()
+                .map(|image_path| -> Result<_> {
+                    let image_path = image_path.as_ref();
+                    let label_file = label_path(image_path);
+                    let boxes = if label_file.is_file() {
+                        load_labels(&label_file)?
+                    } else {
+                        vec![]
+                    };
+                    Ok((image_path.to_owned(), boxes))
+                })
+                .try_collect()?;
+            Ok(Self { names, images })
+        }
+
+        pub fn from_data_config(data: &DataConfig, valid: bool) -> Result<Self> {
+            let image_paths = if valid {
+                data.load_valid_list()?
+            } else {
+                data.load_train_list()?
+            };
+            Self::from_image_list(data.load_names()?, &image_paths)
+        }
+
+        // the ground truth in COCO format. category ids are class ids plus one, and
+        // image ids follow darknet's get_coco_image_id() if all file names are numeric.
+        // the image size is queried by the closure in (width, height).
+        pub fn to_coco_json<F>(&self, mut image_size: F) -> Result<String>
+        where
+            F: FnMut(&Path) -> Result<(u32, u32)>,
+        {
+            let file_names: Vec<String> = self
+                .images
+                .iter()
+                .map(|(image_path, _)| -> Result<_> {
+                    let file_name = image_path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .ok_or_else(|| {
+                            format_err!("invalid image path '{}'", image_path.display())
+                        })?;
+                    Ok(file_name.to_owned())
+                })
+                .try_collect()?;
+            let numeric_ids: Option<Vec<u64>> = file_names
+                .iter()
+                .map(|file_name| {
+                    let stem = file_name
+                        .rsplit_once('_')
+                        .map_or(file_name.as_str(), |(_, id)| id);
+                    Path::new(stem).file_stem()?.to_str()?.parse().ok()
+                })
+                .collect();
+            let image_ids = match numeric_ids {
+                Some(ids) if ids.iter().collect::<HashSet<_>>().len() == ids.len() => ids,
+                _ => (1..=self.images.len() as u64).collect(),
+            };
+
+            let mut images = vec![];
+            let mut annotations = vec![];
+
+            for (((image_path, boxes), file_name), &image_id) in
+                self.images.iter().zip(&file_names).zip(&image_ids)
+            {
+                let (width, height) = image_size(image_path)?;
+                let [width, height] = [width as f64, height as f64];
+                images.push(json!({
+                    "id": image_id,
+                    "file_name": file_name,
+                    "width": width,
+                    "height": height,
+                }));
+
+                for label in boxes {
+                    let LabelBox { class, x, y, w, h } = *label;
+                    ensure!(
+                        (class as usize) < self.names.len(),
+                        "class id {} exceeds the number of classes in '{}'",
+                        class,
+                        image_path.display()
+                    );
+                    let [w, h] = [w * width, h * height];
+                    annotations.push(json!({
+                        "id": annotations.len() + 1,
+                        "image_id": image_id,
+                        "category_id": class + 1,
+                        "bbox": [x * width - w / 2.0, y * height - h / 2.0, w, h],
+                        "area": w * h,
+                        "iscrowd": 0,
+                    }));
+                }
+            }
+
+            let categories: Vec<_> = self
+                .names
+                .iter()
+                .enumerate()
+                .map(|(class, name)| json!({ "id": class + 1, "name": name }))
+                .collect();
+
+            Ok(serde_json::to_string(&json!({
+                "images": images,
+                "annotations": annotations,
+                "categories": categories,
+            }))?)
+        }
+
+        // write the label files next to the images, and the names file, the image
+        // list and the .data file to the output directory
+        pub fn save<P>(&self, output_dir: P, name: &str) -> Result<DataConfig>
+        where
+            P: AsRef<Path>,
+        {
+            let output_dir = output_dir.as_ref();
+            fs::create_dir_all(output_dir)?;
+
+            for (image_path, boxes) in &self.images {
+                let label_file = label_path(image_path);
+                ensure!(
+                    label_file != *image_path,
+                    "cannot derive the label file path of '{}'",
+                    image_path.display()
+                );
+                if let Some(dir) = label_file.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                save_labels(&label_file, boxes)?;
+            }
+
+            let names_file = output_dir.join(format!("{}.names", name));
+            let names_text: String = self
+                .names
+                .iter()
+                .map(|name| format!("{}\n", name))
+                .collect();
+            fs::write(&names_file, names_text)?;
+
+            let list_file = output_dir.join(format!("{}.txt", name));
+            let list_text: String = self
+                .images
+                .iter()
+                .map(|(image_path, _)| format!("{}\n", image_path.display()))
+                .collect();
+            fs::write(&list_file, list_text)?;
+
+            let data = DataConfig {
+                classes: self.names.len() as u64,
+                train: Some(list_file),
+                valid: None,
+                names: Some(names_file),
+                backup: None,
+                eval: None,
+                extra: IndexMap::new(),
+            };
+            fs::write(output_dir.join(format!("{}.data", name)), data.to_string())?;
+
+            Ok(data)
+        }
+    }
+
+    #[cfg(feature = "voc")]
+    fn parse_voc_xml(text: &str) -> Result<(String, [f64; 2], Vec<VocObject>)> {
+        let mut path: Vec<String> = vec![];
+        let mut file_name = None;
+        let mut size = [0.0; 2];
+        let mut objects = vec![];
+        let mut object: Option<VocObject> = None;
+
+        for event in EventReader::new(text.as_bytes()) {
+            match event? {
+                XmlEvent::StartElement { name, .. } => {
+                    if path.len() == 1 && name.local_name == "object" {
+                        object = Some(VocObject::default());
+                    }
+                    path.push(name.local_name);
+                }
+                XmlEvent::EndElement { .. } => {
+                    if path.len() == 2 && path[1] == "object" {
+                        objects.extend(object.take());
+                    }
+                    path.pop();
+                }
+                XmlEvent::Characters(text) => {
+                    let text = text.trim();
+                    let keys: Vec<_> = path.iter().skip(1).map(|key| key.as_str()).collect();
+                    let parse = || -> Result<f64> {
+                        text.parse().map_err(|_| {
+                            format_err!("invalid number '{}' in {}", text, keys.join("/"))
+                        })
+                    };
+
+                    match (keys.as_slice(), object.as_mut()) {
+                        (["filename"], _) => file_name = Some(text.to_owned()),
+                        (["size", "width"], _) => size[0] = parse()?,
+                        (["size", "height"], _) => size[1] = parse()?,
+                        (["object", "name"], Some(object)) => object.name = text.to_owned(),
+                        (["object", "difficult"], Some(object)) => object.difficult = text == "1",
+                        (["object", "bndbox", key], Some(object)) => {
+                            let index = match *key {
+                                "xmin" => 0,
+                                "ymin" => 1,
+                                "xmax" => 2,
+                                "ymax" => 3,
+                                _ => continue,
+                            };
+                            object.bndbox[index] = parse()?;
+                        }
+                        _ => (),
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let file_name = file_name.ok_or_else(|| format_err!("filename is not specified"))?;
+        Ok((file_name, size, objects))
+    }
+}
+
+mod label {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct LabelBox {
+        pub class: u64,
+        pub x: f64,
+        pub y: f64,
+        pub w: f64,
+        pub h: f64,
+    }
+
+    impl Display for LabelBox {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "{} {:.6} {:.6} {:.6} {:.6}",
+                self.class, self.x, self.y, self.w, self.h
+            )
+        }
+    }
+
+    // follow darknet's replace_image_to_label()
+    pub fn label_path<P>(image_path: P) -> PathBuf
+    where
+        P: AsRef<Path>,
+    {
+        let text = image_path
+            .as_ref()
+            .to_string_lossy()
+            .replace("/JPEGImages/", "/labels/")
+            .replace("/images/", "/labels/");
+
+        let path = PathBuf::from(text);
+        let is_image = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| {
+                matches!(
+                    ext.to_lowercase().as_str(),
+                    "jpg" | "jpeg" | "png" | "bmp" | "tif" | "tiff"
+                )
+            })
+            .unwrap_or(false);
+
+        if is_image {
+            path.with_extension("txt")
+        } else {
+            path
+        }
+    }
+
+    pub fn load_labels<P>(label_file: P) -> Result<Vec<LabelBox>>
+    where
+        P: AsRef<Path>,
+    {
+        parse_labels(&fs::read_to_string(label_file)?)
+    }
+
+    pub fn parse_labels(text: &str) -> Result<Vec<LabelBox>> {
+        text.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| -> Result<_> {
+                let tokens: Vec<_> = line.split_whitespace().collect();
+                ensure!(tokens.len() == 5, "invalid label line '{}'", line);
+                let class: u64 = tokens[0].parse()?;
+                let values: Vec<f64> = tokens[1..]
+                    .iter()
+                    .map(|token| token.parse())
+                    .try_collect()?;
+                Ok(LabelBox {
+                    class,
+                    x: values[0],
+                    y: values[1],
+                    w: values[2],
+                    h: values[3],
+                })
+            })
+            .try_collect()
+    }
+
+    pub fn save_labels<P>(label_file: P, labels: &[LabelBox]) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let text: String = labels.iter().map(|label| format!("{}\n", label)).collect();
+        fs::write(label_file, text)?;
+        Ok(())
+    }
+}
+
+mod merge {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct MergedNames {
+        pub names: Vec<String>,
+        // translations[dataset_index][old class id] is the class id in the merged names
+        pub translations: Vec<Vec<u64>>,
+    }
+
+    // merge class names of multiple datasets, classes of the same name are unified
+    // and the merged names are ordered by first appearance
+    pub fn merge_names<S>(name_lists: &[S]) -> MergedNames
+    where
+        S: AsRef<[String]>,
+    {
+        let mut names: IndexSet<String> = IndexSet::new();
+        let translations = name_lists
+            .iter()
+            .map(|list| {
+                list.as_ref()
+                    .iter()
+                    .map(|name| names.insert_full(name.trim().to_owned()).0 as u64)
+                    .collect()
+            })
+            .collect();
+
+        MergedNames {
+            names: names.into_iter().collect(),
+            translations,
+        }
+    }
+
+    pub fn merge_names_files<P>(names_files: &[P]) -> Result<MergedNames>
+    where
+        P: AsRef<Path>,
+    {
+        let name_lists: Vec<_> = names_files.iter().map(load_names).try_collect()?;
+        Ok(merge_names(&name_lists))
+    }
+
+    impl MergedNames {
+        pub fn save_names<P>(&self, names_file: P) -> Result<()>
+        where
+            P: AsRef<Path>,
+        {
+            let text: String = self
+                .names
+                .iter()
+                .map(|name| format!("{}\n", name))
+                .collect();
+            fs::write(names_file, text)?;
+            Ok(())
+        }
+
+        pub fn remap_labels(
+            &self,
+            dataset_index: usize,
+            labels: &[LabelBox],
+        ) -> Result<Vec<LabelBox>> {
+            let translation = self
+                .translations
+                .get(dataset_index)
+                .ok_or_else(|| format_err!("dataset {} does not exist", dataset_index))?;
+
+            labels
+                .iter()
+                .map(|label| -> Result<_> {
+                    let class = *translation.get(label.class as usize).ok_or_else(|| {
+                        format_err!(
+                            "class id {} exceeds the {} classes of dataset {}",
+                            label.class,
+                            translation.len(),
+                            dataset_index
+                        )
+                    })?;
+                    Ok(LabelBox {
+                        class,
+                        ..label.clone()
+                    })
+                })
+                .try_collect()
+        }
+
+        pub fn remap_label_file<P, Q>(
+            &self,
+            dataset_index: usize,
+            label_file: P,
+            output_file: Q,
+        ) -> Result<()>
+        where
+            P: AsRef<Path>,
+            Q: AsRef<Path>,
+        {
+            let label_file = label_file.as_ref();
+            let labels = self
+                .remap_labels(dataset_index, &load_labels(label_file)?)
+                .map_err(|err| format_err!("{}: {}", label_file.display(), err))?;
+            save_labels(output_file, &labels)
+        }
+
+        // rewrite the label files of the images in place and returns the number of
+        // rewritten files. images without label files are skipped, as darknet treats
+        // them as images without objects. the rewriting is not idempotent, run it
+        // once per dataset.
+        pub fn remap_image_list<P>(&self, dataset_index: usize, image_paths: &[P]) -> Result<usize>
+        where
+            P: AsRef<Path>,
+        {
+            // remap all files before writing, so that a bad label leaves the dataset untouched
+            let remapped: Vec<_> = image_paths
+                .iter()
+                .map(label_path)
+                .filter(|label_file| label_file.is_file())
+                .map(|label_file| -> Result<_> {
+                    let labels = self
+                        .remap_labels(dataset_index, &load_labels(&label_file)?)
+                        .map_err(|err| format_err!("{}: {}", label_file.display(), err))?;
+                    Ok((label_file, labels))
+                })
+                .try_collect()?;
+
+            for (label_file, labels) in &remapped {
+                save_labels(label_file, labels)?;
+            }
+            Ok(remapped.len())
+        }
+    }
+}
+
+mod stats {
+    use super::*;
+
+    const NUM_HISTOGRAM_BINS: usize = 10;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Histogram {
+        pub min: f64,
+        pub max: f64,
+        pub counts: Vec<u64>,
+    }
+
+    impl Histogram {
+        pub fn new(values: impl IntoIterator<Item = f64>, min: f64, max: f64, bins: usize) -> Self {
+            let mut counts = vec![0; bins];
+            values.into_iter().for_each(|value| {
+                let ratio = (value - min) / (max - min);
+                let index = ((ratio * bins as f64) as usize).min(bins - 1);
+                counts[index] += 1;
+            });
+            Self { min, max, counts }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct DatasetStats {
+        pub num_classes: u64,
+        pub num_images: usize,
+        pub num_boxes: usize,
+        pub missing_labels: Vec<PathBuf>,
+        pub class_frequencies: Vec<u64>,
+        pub images_per_class: Vec<u64>,
+        pub box_sizes: Vec<[f64; 2]>,
+        pub width_histogram: Histogram,
+        pub height_histogram: Histogram,
+        pub aspect_ratio_histogram: Histogram,
+    }
+
+    impl DatasetStats {
+        pub fn analyze(data: &DataConfig) -> Result<Self> {
+            let image_paths = data.load_train_list()?;
+            Self::from_image_list(data.classes, &image_paths)
+        }
+
+        pub fn from_image_list<P>(num_classes: u64, image_paths: &[P]) -> Result<Self>
+        where
+            P: AsRef<Path>,
+        {
+            let mut missing_labels = vec![];
+            let mut class_frequencies = vec![0; num_classes as usize];
+            let mut images_per_class = vec![0; num_classes as usize];
+            let mut box_sizes = vec![];
+
+            for image_path in image_paths {
+                let label_file = label_path(image_path);
+                if !label_file.is_file() {
+                    missing_labels.push(image_path.as_ref().to_owned());
+                    continue;
+                }
+
+                let labels = load_labels(&label_file)?;
+                let mut classes_in_image = HashSet::new();
+
+                for label in labels {
+                    let LabelBox { class, w, h, .. } = label;
+                    ensure!(
+                        class < num_classes,
+                        "class id {} exceeds the number of classes in '{}'",
+                        class,
+                        label_file.display()
+                    );
+                    class_frequencies[class as usize] += 1;
+                    classes_in_image.insert(class);
+                    box_sizes.push([w, h]);
+                }
+
+                classes_in_image.into_iter().for_each(|class| {
+                    images_per_class[class as usize] += 1;
+                });
+            }
+
+            let width_histogram = Histogram::new(
+                box_sizes.iter().map(|&[w, _h]| w),
+                0.0,
+                1.0,
+                NUM_HISTOGRAM_BINS,
+            );
+            let height_histogram = Histogram::new(
+                box_sizes.iter().map(|&[_w, h]| h),
+                0.0,
+                1.0,
+                NUM_HISTOGRAM_BINS,
+            );
+            // log2 of w/h ratio within [1/8, 8]
+            let aspect_ratio_histogram = Histogram::new(
+                box_sizes
+                    .iter()
+                    .filter(|&&[_w, h]| h > 0.0)
+                    .map(|&[w, h]| (w / h).log2().clamp(-3.0, 3.0)),
+                -3.0,
+                3.0,
+                NUM_HISTOGRAM_BINS,
+            );
+
+            Ok(Self {
+                num_classes,
+                num_images: image_paths.len(),
+                num_boxes: box_sizes.len(),
+                missing_labels,
+                class_frequencies,
+                images_per_class,
+                box_sizes,
+                width_histogram,
+                height_histogram,
+                aspect_ratio_histogram,
+            })
+        }
+
+        pub fn suggest_counters_per_class(&self) -> Vec<u64> {
+            self.class_frequencies.clone()
+        }
+
+        // k-means over box sizes with 1 - IoU distance, as darknet's calc_anchors
+        pub fn suggest_anchors(
+            &self,
+            num_anchors: usize,
+            width: u64,
+            height: u64,
+        ) -> Result<Vec<(u64, u64)>> {
+            ensure!(num_anchors > 0, "the number of anchors must be positive");
+
+            // zero-area boxes have no defined IoU, so they are left out
+            let boxes: Vec<[f64; 2]> = self
+                .box_sizes
+                .iter()
+                .map(|&[w, h]| [w * width as f64, h * height as f64])
+                .filter(|&[w, h]| w > 0.0 && h > 0.0)
+                .collect();
+            ensure!(
+                boxes.len() >= num_anchors,
+                "the number of non-empty boxes is less than the number of anchors"
+            );
+
+            // initialize centroids by evenly picking from boxes sorted by area
+            let mut centroids: Vec<[f64; 2]> = {
+                let mut sorted = boxes.clone();
+                sorted.sort_by(|[lw, lh], [rw, rh]| (lw * lh).total_cmp(&(rw * rh)));
+                (0..num_anchors)
+                    .map(|index| sorted[(2 * index + 1) * sorted.len() / (2 * num_anchors)])
+                    .collect()
+            };
+
+            let iou = |[lw, lh]: [f64; 2], [rw, rh]: [f64; 2]| {
+                let inter = lw.min(rw) * lh.min(rh);
+                inter / (lw * lh + rw * rh - inter)
+            };
+
+            let mut assignments = vec![usize::MAX; boxes.len()];
+
+            for _ in 0..1000 {
+                let new_assignments: Vec<usize> = boxes
+                    .iter()
+                    .map(|&bbox| {
+                        centroids
+                            .iter()
+                            .enumerate()
+                            .max_by(|(_, &lhs), (_, &rhs)| {
+                                iou(bbox, lhs).total_cmp(&iou(bbox, rhs))
+                            })
+                            .unwrap()
+                            .0
+                    })
+                    .collect();
+
+                if new_assignments == assignments {
+                    break;
+                }
+                assignments = new_assignments;
+
+                centroids = (0..num_anchors)
+                    .map(|cluster| {
+                        let (sum_w, sum_h, count) = boxes
+                            .iter()
+                            .zip(assignments.iter())
+                            .filter(|(_, &assignment)| assignment == cluster)
+                            .fold((0.0, 0.0, 0), |(sum_w, sum_h, count), (&[w, h], _)| {
+                                (sum_w + w, sum_h + h, count + 1)
+                            });
+                        if count == 0 {
+                            centroids[cluster]
+                        } else {
+                            [sum_w / count as f64, sum_h / count as f64]
+                        }
+                    })
+                    .collect();
+            }
+
+            let mut anchors: Vec<(u64, u64)> = centroids
+                .into_iter()
+                .map(|[w, h]| ((w.round() as u64).max(1), (h.round() as u64).max(1)))
+                .collect();
+            anchors.sort_by_key(|&(w, h)| w * h);
+
+            Ok(anchors)
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    common::*,
+    config::Shape,
+    model::{LayerBase, ModelBase},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Alignment {
+    // the feature maps can be compared directly
+    #[serde(rename = "exact")]
+    Exact,
+    // the heights and widths agree, a 1x1 adapter is needed to match the channels
+    #[serde(rename = "channels")]
+    Channels,
+    // the heights and widths differ, usually due to different input sizes
+    #[serde(rename = "spatial")]
+    Spatial,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FeaturePair {
+    pub teacher_index: usize,
+    pub student_index: usize,
+    pub stride: u64,
+    pub teacher_shape: [u64; 3],
+    pub student_shape: [u64; 3],
+    pub same_kind: bool,
+    pub alignment: Alignment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DistillationMap {
+    pub pairs: Vec<FeaturePair>,
+    pub unpaired_teacher: Vec<usize>,
+    pub unpaired_student: Vec<usize>,
+}
+
+impl DistillationMap {
+    pub fn is_aligned(&self) -> bool {
+        self.pairs
+            .iter()
+            .all(|pair| pair.alignment == Alignment::Exact)
+    }
+}
+
+// pair the feature layers of a teacher and a student model for feature distillation
+//
+// the candidates are the stage outputs, that is, the last layer before the stride
+// changes in layer order. the stage outputs of each stride are paired in the order
+// of appearance, so that backbone stages pair with backbone stages and neck stages
+// pair with neck stages.
+pub fn pair_feature_layers(teacher: &ModelBase, student: &ModelBase) -> DistillationMap {
+    let teacher_stages = stage_outputs(teacher);
+    let student_stages = stage_outputs(student);
+
+    let strides: IndexSet<u64> = teacher_stages
+        .iter()
+        .chain(&student_stages)
+        .map(|&(_, stride, _)| stride)
+        .collect();
+
+    let mut pairs = vec![];
+    let mut unpaired_teacher = vec![];
+    let mut unpaired_student = vec![];
+
+    for stride in strides {
+        let with_stride = |stages: &[(usize, u64, [u64; 3])]| -> Vec<(usize, [u64; 3])> {
+            stages
+                .iter()
+                .filter(|&&(_, other, _)| other == stride)
+                .map(|&(layer_index, _, shape)| (layer_index, shape))
+                .collect()
+        };
+        let teacher_layers = with_stride(&teacher_stages);
+        let student_layers = with_stride(&student_stages);
+
+        teacher_layers
+            .iter()
+            .zip_longest(&student_layers)
+            .for_each(|pair| {
+                use itertools::EitherOrBoth::*;
+
+                match pair {
+                    Both(&(teacher_index, teacher_shape), &(student_index, student_shape)) => {
+                        let [teacher_h, teacher_w, teacher_c] = teacher_shape;
+                        let [student_h, student_w, student_c] = student_shape;
+                        let alignment = if [teacher_h, teacher_w] != [student_h, student_w] {
+                            Alignment::Spatial
+                        } else if teacher_c != student_c {
+                            Alignment::Channels
+                        } else {
+                            Alignment::Exact
+                        };
+
+                        pairs.push(FeaturePair {
+                            teacher_index,
+                            student_index,
+                            stride,
+                            teacher_shape,
+                            student_shape,
+                            same_kind: teacher.layers[&teacher_index].kind()
+                                == student.layers[&student_index].kind(),
+                            alignment,
+                        });
+                    }
+                    Left(&(teacher_index, _)) => unpaired_teacher.push(teacher_index),
+                    Right(&(student_index, _)) => unpaired_student.push(student_index),
+                }
+            });
+    }
+
+    pairs.sort_by_key(|pair| (pair.teacher_index, pair.student_index));
+    unpaired_teacher.sort_unstable();
+    unpaired_student.sort_unstable();
+
+    DistillationMap {
+        pairs,
+        unpaired_teacher,
+        unpaired_student,
+    }
+}
+
+// (layer index, stride, output shape) of the last layer of each stage
+fn stage_outputs(model: &ModelBase) -> Vec<(usize, u64, [u64; 3])> {
+    let [in_h, in_w] = match model.net.input_size {
+        Shape::Hwc([h, w, _c]) => [h, w],
+        Shape::Flat(_) => return vec![],
+    };
+
+    // yolo layers only decode the previous layer, and implicit layers have no
+    // spatial extent
+    let features: Vec<_> = model
+        .layers
+        .iter()
+        .filter(|(_, layer)| !matches!(layer, LayerBase::Yolo(_) | LayerBase::Implicit(_)))
+        .filter_map(|(&layer_index, layer)| {
+            let [out_h, out_w, out_c] = layer.output_shape().hwc()?;
+            let valid = out_h > 0
+                && out_w > 0
+                && in_h % out_h == 0
+                && in_w % out_w == 0
+                && in_h / out_h == in_w / out_w;
+            valid.then(|| (layer_index, in_h / out_h, [out_h, out_w, out_c]))
+        })
+        .collect();
+
+    features
+        .iter()
+        .enumerate()
+        .filter(
+            |&(position, &(_, stride, _))| match features.get(position + 1) {
+                Some(&(_, next_stride, _)) => next_stride != stride,
+                None => true,
+            },
+        )
+        .map(|(_, &feature)| feature)
+        .collect()
+}
+
+impl ModelBase {
+    pub fn pair_feature_layers(&self, student: &ModelBase) -> DistillationMap {
+        pair_feature_layers(self, student)
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{
+    common::*,
+    config::{Activation, WeightsType},
+    darknet::{ConvolutionalLayer, ConvolutionalWeights, DarknetModel, Layer, ScaleWeights},
+    model::{
+        ConnectedLayerBase, ConvolutionalLayerBase, CustomLayerBase, LayerBase, LayerPosition,
+        MaxPoolLayerBase, ModelBase, ShortcutLayerBase, UpSampleLayerBase, YoloLayerBase,
+    },
+};
+
+pub mod burn;
+#[cfg(feature = "coreml")]
+pub mod coreml;
+#[cfg(feature = "gguf")]
+pub mod gguf;
+pub mod onnx;
+pub mod openvino;
+pub mod registry;
+pub mod safetensors;
+pub mod triton;
+
+pub use onnx::{coverage, coverage_with_options};
+
+// darknet adds this constant to the variance in normalize_cpu()
+pub(crate) const BATCH_NORM_EPSILON: f32 = 0.000001;
+
+// the domain of custom ops, which is the opset name in OpenVINO IR
+pub const CUSTOM_OP_DOMAIN: &str = "darknet";
+
+// how mish, hard_mish and swish are exported, as they are not native ops in
+// every target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ActivationLowering {
+    // the native op of the target if any, otherwise primitive ops
+    #[default]
+    #[serde(rename = "native")]
+    Native,
+    // mish(x) = x * tanh(softplus(x)), hard_mish(x) = x * hard_sigmoid(x) and
+    // swish(x) = x * sigmoid(x)
+    #[serde(rename = "primitives")]
+    Primitives,
+    // one op in CUSTOM_OP_DOMAIN for the runtime to implement
+    #[serde(rename = "custom")]
+    Custom,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExportOptions {
+    pub activation_lowering: ActivationLowering,
+}
+
+// the name of the custom op of the activations that ActivationLowering applies to
+pub fn custom_op_type(activation: Activation) -> Option<&'static str> {
+    match activation {
+        Activation::Mish => Some("Mish"),
+        Activation::HardMish => Some("HardMish"),
+        Activation::Swish => Some("Swish"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct UnsupportedLayer {
+    pub layer_index: usize,
+    pub kind: String,
+    pub reason: String,
+}
+
+impl Display for UnsupportedLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "layer {} ({}): {}",
+            self.layer_index, self.kind, self.reason
+        )
+    }
+}
+
+pub fn layer_name(layer_index: usize, layer: &LayerBase) -> String {
+    format!("{}_{}", layer.kind(), layer_index)
+}
+
+// yolo layers are exported as the raw head tensor they read, the logistic
+// activation and the box decoding are left to the decoder. see
+// binding::DecodeParams::Yolo for the layout.
+pub(crate) fn yolo_output(layer: &YoloLayerBase) -> LayerPosition {
+    layer.from_indexes
+}
+
+// the max pooling padding as [leading, trailing] pixels of each spatial axis.
+// darknet pads padding / 2 pixels on the leading edges and the rest on the
+// trailing edges. the padded pixels never win, so replication padding yields
+// the same maxima as padding with -inf, since every padded window still covers
+// an edge pixel.
+pub(crate) fn maxpool_padding(padding: u64) -> [u64; 2] {
+    let leading = padding / 2;
+    [leading, padding - leading]
+}
+
+// the name of the tensor produced at the position
+pub fn blob_name(model: &ModelBase, position: LayerPosition) -> String {
+    match position {
+        LayerPosition::Input => "input".into(),
+        LayerPosition::Absolute(layer_index) => {
+            layer_name(layer_index, &model.layers[&layer_index])
+        }
+    }
+}
+
+// resolve shared weights to the weights of the owning layer
+pub(crate) fn conv_weights<'a>(
+    model: &'a DarknetModel,
+    weights: &'a ConvolutionalWeights,
+) -> Option<(&'a Array1<f32>, &'a Array4<f32>, &'a Option<ScaleWeights>)> {
+    match weights {
+        ConvolutionalWeights::Owned {
+            biases,
+            weights,
+            scales,
+        } => Some((biases, weights, scales)),
+        ConvolutionalWeights::Ref { share_index } => match model.layers.get(share_index)? {
+            Layer::Convolutional(ConvolutionalLayer {
+                weights:
+                    ConvolutionalWeights::Owned {
+                        biases,
+                        weights,
+                        scales,
+                    },
+                ..
+            }) => Some((biases, weights, scales)),
+            _ => None,
+        },
+    }
+}
+
+// report layer options that none of the exporters can lower
+pub(crate) fn find_unsupported_layers(
+    model: &ModelBase,
+    supports_activation: impl Fn(Activation) -> bool,
+) -> Vec<UnsupportedLayer> {
+    model
+        .layers
+        .iter()
+        .flat_map(|(&layer_index, layer)| {
+            let mut reasons = vec![];
+            let mut check_activation = |activation: Activation| {
+                if !supports_activation(activation) {
+                    reasons.push(format!("activation {:?} is not supported", activation));
+                }
+            };
+
+            match layer {
+                LayerBase::Convolutional(ConvolutionalLayerBase { config, .. }) => {
+                    check_activation(config.activation);
+                    if config.antialiasing {
+                        reasons.push("antialiasing is not supported".into());
+                    }
+                }
+                LayerBase::Connected(ConnectedLayerBase { config, .. }) => {
+                    check_activation(config.activation);
+                }
+                LayerBase::Shortcut(ShortcutLayerBase {
+                    config,
+                    input_shape,
+                    ..
+                }) => {
+                    check_activation(config.activation);
+                    if config.weights_type != WeightsType::None {
+                        reasons.push("weighted shortcut is not supported".into());
+                    }
+                    if !input_shape.iter().all_equal() {
+                        reasons.push("shortcut of different shapes is not supported".into());
+                    }
+                }
+                LayerBase::MaxPool(MaxPoolLayerBase { config, .. }) => {
+                    if config.maxpool_depth {
+                        reasons.push("maxpool_depth is not supported".into());
+                    }
+                    if config.antialiasing {
+                        reasons.push("antialiasing is not supported".into());
+                    }
+                }
+                LayerBase::UpSample(UpSampleLayerBase { config, .. }) => {
+                    if config.reverse {
+                        reasons.push("reverse upsampling is not supported".into());
+                    }
+                }
+                LayerBase::Implicit(_) => {
+                    reasons.push("implicit layers are not supported".into());
+                }
+                LayerBase::AvgPool(_) => {
+                    reasons.push("avgpool layers are not supported".into());
+                }
+                LayerBase::ScaleChannels(_) => {
+                    reasons.push("scale_channels layers are not supported".into());
+                }
+                LayerBase::Dropout(_) => {
+                    reasons.push("dropout layers are not supported".into());
+                }
+                LayerBase::Softmax(_) => {
+                    reasons.push("softmax layers are not supported".into());
+                }
+                LayerBase::Region(_) => {
+                    reasons.push("region layers are not supported".into());
+                }
+                LayerBase::GaussianYolo(_) => {
+                    reasons.push("Gaussian_yolo layers are not supported".into());
+                }
+                LayerBase::Reorg(_) => {
+                    reasons.push("reorg layers are not supported".into());
+                }
+                LayerBase::Local(_) => {
+                    reasons.push("local layers are not supported".into());
+                }
+                LayerBase::LocalAvgPool(_) => {
+                    reasons.push("local_avgpool layers are not supported".into());
+                }
+                LayerBase::Sam(_) => {
+                    reasons.push("sam layers are not supported".into());
+                }
+                LayerBase::Lstm(_) => {
+                    reasons.push("lstm layers are not supported".into());
+                }
+                LayerBase::Crnn(_) => {
+                    reasons.push("crnn layers are not supported".into());
+                }
+                LayerBase::ConvLstm(_) => {
+                    reasons.push("conv_lstm layers are not supported".into());
+                }
+                LayerBase::Custom(CustomLayerBase { config, .. }) => {
+                    reasons.push(format!(
+                        "custom [{}] layers are not supported",
+                        config.section
+                    ));
+                }
+                LayerBase::Route(_) | LayerBase::Yolo(_) | LayerBase::BatchNorm(_) => (),
+            }
+
+            reasons.into_iter().map(move |reason| UnsupportedLayer {
+                layer_index,
+                kind: layer.kind().into(),
+                reason,
+            })
+        })
+        .collect()
+}
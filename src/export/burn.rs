@@ -0,0 +1,445 @@
+use super::{
+    blob_name, find_unsupported_layers, layer_name, maxpool_padding, safetensors::to_safetensors,
+    yolo_output, UnsupportedLayer,
+};
+use crate::{
+    common::*,
+    config::{
+        Activation, ConnectedConfig, ConvolutionalConfig, MaxPoolConfig, ShortcutConfig,
+        UpSampleConfig,
+    },
+    darknet::DarknetModel,
+    model::{
+        ConnectedLayerBase, ConvolutionalLayerBase, LayerBase, LayerPosition, MaxPoolLayerBase,
+        ModelBase, RouteLayerBase, ShortcutLayerBase, UpSampleLayerBase,
+    },
+};
+use std::fmt::Write as _;
+
+// the generated code targets burn 0.13 with the burn-import crate
+const PRELUDE: &str = r#"// generated by darknet-config, do not edit
+use burn::{
+    module::Module,
+    nn::{
+        conv::{Conv2d, Conv2dConfig},
+        BatchNorm, BatchNormConfig, Linear, LinearConfig, PaddingConfig2d,
+    },
+    record::{FullPrecisionSettings, Recorder, RecorderError},
+    tensor::{
+        activation,
+        backend::Backend,
+        module::{interpolate, max_pool2d},
+        ops::{InterpolateMode, InterpolateOptions},
+        Tensor,
+    },
+};
+use burn_import::safetensors::{AdapterType, LoadArgs, SafetensorsFileRecorder};
+
+#[derive(Module, Debug)]
+pub struct ConvBlock<B: Backend> {
+    pub conv: Conv2d<B>,
+    pub bn: Option<BatchNorm<B, 2>>,
+}
+
+impl<B: Backend> ConvBlock<B> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> Tensor<B, 4> {
+        let x = self.conv.forward(x);
+        match &self.bn {
+            Some(bn) => bn.forward(x),
+            None => x,
+        }
+    }
+}
+
+#[derive(Module, Debug)]
+pub struct FcBlock<B: Backend> {
+    pub fc: Linear<B>,
+    pub bn: Option<BatchNorm<B, 0>>,
+}
+
+impl<B: Backend> FcBlock<B> {
+    pub fn forward(&self, x: Tensor<B, 2>) -> Tensor<B, 2> {
+        let x = self.fc.forward(x);
+        match &self.bn {
+            Some(bn) => bn.forward(x),
+            None => x,
+        }
+    }
+}
+
+#[derive(Module, Debug)]
+pub struct NormBlock<B: Backend> {
+    pub bn: BatchNorm<B, 2>,
+}
+"#;
+
+const LOADER: &str = r#"
+impl<B: Backend> Model<B> {
+    // load the weights exported by darknet-config in safetensors format
+    pub fn load(weights_file: &str, device: &B::Device) -> Result<Self, RecorderError> {
+        let args = LoadArgs::new(weights_file.into()).with_adapter_type(AdapterType::PyTorch);
+        let record = SafetensorsFileRecorder::<FullPrecisionSettings>::default().load(args, device)?;
+        Ok(Self::new(device).load_record(record))
+    }
+}
+"#;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BurnModule {
+    pub source: String,
+    pub record: Vec<u8>,
+}
+
+impl BurnModule {
+    // save model.rs and model.safetensors to the directory
+    pub fn save<P>(&self, dir: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("model.rs"), &self.source)?;
+        fs::write(dir.join("model.safetensors"), &self.record)?;
+        Ok(())
+    }
+}
+
+pub fn to_burn(model: &DarknetModel) -> Result<BurnModule> {
+    Ok(BurnModule {
+        source: burn_source(&model.base)?,
+        record: to_safetensors(model)?,
+    })
+}
+
+pub fn unsupported_layers(model: &ModelBase) -> Vec<UnsupportedLayer> {
+    find_unsupported_layers(model, |activation| {
+        activation_expr("x", activation).is_some()
+    })
+}
+
+pub fn burn_source(model: &ModelBase) -> Result<String> {
+    ensure!(
+        model.net.input_size.hwc().is_some(),
+        "flat network input is not supported by the burn exporter"
+    );
+    let unsupported = unsupported_layers(model);
+    ensure!(
+        unsupported.is_empty(),
+        "the model cannot be exported to burn:\n{}",
+        unsupported.iter().join("\n")
+    );
+
+    let mut fields = String::new();
+    let mut inits = String::new();
+    let mut forward = String::new();
+    let mut outputs = vec![];
+
+    let var = |position: LayerPosition| blob_name(model, position);
+
+    for (&layer_index, layer) in &model.layers {
+        let name = layer_name(layer_index, layer);
+
+        match layer {
+            LayerBase::Convolutional(ConvolutionalLayerBase {
+                config:
+                    ConvolutionalConfig {
+                        filters,
+                        groups,
+                        size,
+                        stride_x,
+                        stride_y,
+                        dilation,
+                        padding,
+                        batch_normalize,
+                        activation,
+                        share_index,
+                        ..
+                    },
+                from_indexes,
+                input_shape: [_h, _w, in_c],
+                ..
+            }) => {
+                // layers sharing weights reuse the module of the owning layer
+                let module = match share_index {
+                    Some(share_index) => {
+                        let share_index = share_index
+                            .to_absolute(layer_index)
+                            .ok_or_else(|| format_err!("invalid layer index"))?;
+                        layer_name(share_index, &model.layers[&share_index])
+                    }
+                    None => {
+                        writeln!(fields, "    pub {}: ConvBlock<B>,", name)?;
+                        let bn = if *batch_normalize {
+                            format!(
+                                "Some(BatchNormConfig::new({}).with_epsilon(1e-6).init(device))",
+                                filters
+                            )
+                        } else {
+                            "None".into()
+                        };
+                        writeln!(
+                            inits,
+                            "            {}: ConvBlock {{
+                conv: Conv2dConfig::new([{}, {}], [{}, {}])
+                    .with_stride([{}, {}])
+                    .with_padding(PaddingConfig2d::Explicit({}, {}))
+                    .with_dilation([{}, {}])
+                    .with_groups({})
+                    .with_bias({})
+                    .init(device),
+                bn: {},
+            }},",
+                            name,
+                            in_c,
+                            filters,
+                            size,
+                            size,
+                            stride_y,
+                            stride_x,
+                            padding,
+                            padding,
+                            dilation,
+                            dilation,
+                            groups,
+                            !*batch_normalize,
+                            bn
+                        )?;
+                        name.clone()
+                    }
+                };
+                let expr = format!("self.{}.forward({}.clone())", module, var(*from_indexes));
+                writeln!(
+                    forward,
+                    "        let {} = {};",
+                    name,
+                    activation_expr(&expr, *activation).unwrap()
+                )?;
+            }
+            LayerBase::Connected(ConnectedLayerBase {
+                config:
+                    ConnectedConfig {
+                        activation,
+                        batch_normalize,
+                        ..
+                    },
+                from_indexes,
+                input_shape,
+                output_shape,
+            }) => {
+                writeln!(fields, "    pub {}: FcBlock<B>,", name)?;
+                let bn = if *batch_normalize {
+                    format!(
+                        "Some(BatchNormConfig::new({}).with_epsilon(1e-6).init(device))",
+                        output_shape
+                    )
+                } else {
+                    "None".into()
+                };
+                writeln!(
+                    inits,
+                    "            {}: FcBlock {{
+                fc: LinearConfig::new({}, {}).with_bias({}).init(device),
+                bn: {},
+            }},",
+                    name, input_shape, output_shape, !*batch_normalize, bn
+                )?;
+
+                // darknet flattens feature maps in CHW order
+                let input = match from_indexes {
+                    LayerPosition::Absolute(index)
+                        if model.layers[index].output_shape().flat().is_some() =>
+                    {
+                        format!("{}.clone()", var(*from_indexes))
+                    }
+                    _ => format!("{}.clone().flatten::<2>(1, 3)", var(*from_indexes)),
+                };
+                let expr = format!("self.{}.forward({})", name, input);
+                writeln!(
+                    forward,
+                    "        let {} = {};",
+                    name,
+                    activation_expr(&expr, *activation).unwrap()
+                )?;
+            }
+            LayerBase::BatchNorm(layer) => {
+                let [_h, _w, channels] = layer.inout_shape;
+                writeln!(fields, "    pub {}: NormBlock<B>,", name)?;
+                writeln!(
+                    inits,
+                    "            {}: NormBlock {{
+                bn: BatchNormConfig::new({}).with_epsilon(1e-6).init(device),
+            }},",
+                    name, channels
+                )?;
+                writeln!(
+                    forward,
+                    "        let {} = self.{}.bn.forward({}.clone());",
+                    name,
+                    name,
+                    var(layer.from_indexes)
+                )?;
+            }
+            LayerBase::Shortcut(ShortcutLayerBase {
+                config: ShortcutConfig { activation, .. },
+                from_indexes,
+                ..
+            }) => {
+                let expr = from_indexes
+                    .iter()
+                    .map(|&position| format!("{}.clone()", var(position)))
+                    .join(" + ");
+                writeln!(
+                    forward,
+                    "        let {} = {};",
+                    name,
+                    activation_expr(&expr, *activation).unwrap()
+                )?;
+            }
+            LayerBase::MaxPool(MaxPoolLayerBase {
+                config:
+                    MaxPoolConfig {
+                        stride_x,
+                        stride_y,
+                        size,
+                        padding,
+                        ..
+                    },
+                from_indexes,
+                ..
+            }) => {
+                let input = if *padding > 0 {
+                    let [pad_begin, pad_end] = maxpool_padding(*padding);
+                    format!(
+                        "{}.clone().pad(({}, {}, {}, {}), f32::NEG_INFINITY)",
+                        var(*from_indexes),
+                        pad_begin,
+                        pad_end,
+                        pad_begin,
+                        pad_end
+                    )
+                } else {
+                    format!("{}.clone()", var(*from_indexes))
+                };
+                writeln!(
+                    forward,
+                    "        let {} = max_pool2d({}, [{}, {}], [{}, {}], [0, 0], [1, 1]);",
+                    name, input, size, size, stride_y, stride_x
+                )?;
+            }
+            LayerBase::Route(RouteLayerBase {
+                config,
+                from_indexes,
+                input_shape,
+                ..
+            }) => {
+                let group_id = config.group.group_id();
+                let num_groups = config.group.num_groups();
+
+                let inputs: Vec<_> = izip!(from_indexes, input_shape)
+                    .map(|(&position, &[_h, _w, in_c])| {
+                        if num_groups == 1 {
+                            format!("{}.clone()", var(position))
+                        } else {
+                            let group_size = in_c / num_groups;
+                            format!(
+                                "{}.clone().narrow(1, {}, {})",
+                                var(position),
+                                group_size * group_id,
+                                group_size
+                            )
+                        }
+                    })
+                    .collect();
+                let expr = if inputs.len() == 1 {
+                    inputs.into_iter().next().unwrap()
+                } else {
+                    format!("Tensor::cat(vec![{}], 1)", inputs.join(", "))
+                };
+                writeln!(forward, "        let {} = {};", name, expr)?;
+            }
+            LayerBase::UpSample(UpSampleLayerBase {
+                config: UpSampleConfig { .. },
+                from_indexes,
+                output_shape: [out_h, out_w, _c],
+                ..
+            }) => {
+                writeln!(
+                    forward,
+                    "        let {} = interpolate({}.clone(), [{}, {}], InterpolateOptions::new(InterpolateMode::Nearest));",
+                    name,
+                    var(*from_indexes),
+                    out_h,
+                    out_w
+                )?;
+            }
+            LayerBase::Yolo(yolo) => {
+                writeln!(
+                    forward,
+                    "        let {} = {}.clone();",
+                    name,
+                    var(yolo_output(yolo))
+                )?;
+                outputs.push(name.clone());
+            }
+            LayerBase::Implicit(_)
+            | LayerBase::AvgPool(_)
+            | LayerBase::ScaleChannels(_)
+            | LayerBase::Dropout(_)
+            | LayerBase::Softmax(_)
+            | LayerBase::Region(_)
+            | LayerBase::ConvLstm(_)
+            | LayerBase::Crnn(_)
+            | LayerBase::Lstm(_)
+            | LayerBase::Sam(_)
+            | LayerBase::LocalAvgPool(_)
+            | LayerBase::Local(_)
+            | LayerBase::Reorg(_)
+            | LayerBase::GaussianYolo(_)
+            | LayerBase::Custom(_) => unreachable!("please report bug"),
+        }
+    }
+
+    let mut source = PRELUDE.to_owned();
+    write!(
+        source,
+        "
+#[derive(Module, Debug)]
+pub struct Model<B: Backend> {{
+{}}}
+
+impl<B: Backend> Model<B> {{
+    pub fn new(device: &B::Device) -> Self {{
+        Self {{
+{}        }}
+    }}
+
+    #[allow(clippy::redundant_clone)]
+    pub fn forward(&self, input: Tensor<B, 4>) -> Vec<Tensor<B, 4>> {{
+{}        vec![{}]
+    }}
+}}
+{}",
+        fields,
+        inits,
+        forward,
+        outputs.join(", "),
+        LOADER
+    )?;
+
+    Ok(source)
+}
+
+fn activation_expr(expr: &str, activation: Activation) -> Option<String> {
+    let expr = match activation {
+        Activation::Linear => expr.to_owned(),
+        Activation::Relu => format!("activation::relu({})", expr),
+        Activation::Leaky => format!("activation::leaky_relu({}, 0.1)", expr),
+        Activation::Logistic => format!("activation::sigmoid({})", expr),
+        Activation::Tanh => format!("activation::tanh({})", expr),
+        Activation::Mish => format!("activation::mish({})", expr),
+        Activation::Swish => format!("activation::silu({})", expr),
+        Activation::Gelu => format!("activation::gelu({})", expr),
+        _ => return None,
+    };
+    Some(expr)
+}
@@ -0,0 +1,1192 @@
+use super::{
+    blob_name, conv_weights, custom_op_type, layer_name, maxpool_padding, yolo_output,
+    ActivationLowering, ExportOptions, BATCH_NORM_EPSILON,
+};
+use crate::{
+    common::*,
+    config::{
+        Activation, CompoundYoloConfig, ConvolutionalConfig, MaxPoolConfig, Shape, ShortcutConfig,
+        UpSampleConfig, WeightsType,
+    },
+    darknet::{
+        BatchNormLayer, BatchNormWeights, ConnectedLayer, ConnectedWeights, ConvolutionalLayer,
+        DarknetModel, Layer, MaxPoolLayer, RouteLayer, ScaleWeights, ShortcutLayer, UpSampleLayer,
+        YoloLayer,
+    },
+    model::{
+        ConnectedLayerBase, ConvolutionalLayerBase, LayerBase, LayerPosition, MaxPoolLayerBase,
+        RouteLayerBase, ShortcutLayerBase, UpSampleLayerBase, YoloLayerBase,
+    },
+};
+use prost::Message;
+use proto::*;
+
+pub fn to_coreml(model: &DarknetModel) -> Result<Vec<u8>> {
+    to_coreml_with_options(model, &ExportOptions::default())
+}
+
+pub fn to_coreml_with_options(model: &DarknetModel, options: &ExportOptions) -> Result<Vec<u8>> {
+    let mut builder = NetworkBuilder {
+        options: options.clone(),
+        layers: vec![],
+    };
+
+    model
+        .layers
+        .iter()
+        .try_for_each(|(&layer_index, layer)| builder.push_layer(model, layer_index, layer))?;
+
+    let input_shape = model.base.net.input_size;
+    let input = feature(&blob_name(&model.base, LayerPosition::Input), input_shape);
+    let output: Vec<_> = model
+        .base
+        .layers
+        .iter()
+        .filter(|(_, layer)| matches!(layer, LayerBase::Yolo(_)))
+        .map(|(&layer_index, layer)| feature(&layer_name(layer_index, layer), layer.output_shape()))
+        .collect();
+    ensure!(!output.is_empty(), "the model has no output layers");
+
+    let user_defined: HashMap<String, String> = {
+        let classes = ("classes".to_owned(), model.base.net.classes.to_string());
+        let anchors = model
+            .base
+            .layers
+            .iter()
+            .filter_map(|(&layer_index, layer)| match layer {
+                LayerBase::Yolo(YoloLayerBase {
+                    config: CompoundYoloConfig { anchors, .. },
+                    ..
+                }) => {
+                    let anchors = anchors
+                        .iter()
+                        .map(|(w, h)| format!("{},{}", w, h))
+                        .join(",");
+                    Some((
+                        format!("{}.anchors", layer_name(layer_index, layer)),
+                        anchors,
+                    ))
+                }
+                _ => None,
+            });
+        // the heads are exported like yolo_output() describes
+        let activation = ("yolo_activation".to_owned(), "raw".to_owned());
+        iter::once(classes)
+            .chain(anchors)
+            .chain(iter::once(activation))
+            .collect()
+    };
+
+    let spec = Model {
+        specification_version: 4,
+        description: Some(ModelDescription {
+            input: vec![input],
+            output,
+            metadata: Some(Metadata {
+                short_description: "converted from darknet".into(),
+                version_string: env!("CARGO_PKG_VERSION").into(),
+                user_defined,
+            }),
+        }),
+        r#type: Some(model::Type::NeuralNetwork(NeuralNetwork {
+            layers: builder.layers,
+        })),
+    };
+
+    let mut bytes = vec![];
+    spec.encode(&mut bytes)?;
+    Ok(bytes)
+}
+
+impl DarknetModel {
+    pub fn save_coreml<P>(&self, coreml_file: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.save_coreml_with_options(coreml_file, &ExportOptions::default())
+    }
+
+    pub fn save_coreml_with_options<P>(&self, coreml_file: P, options: &ExportOptions) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(coreml_file, to_coreml_with_options(self, options)?)?;
+        Ok(())
+    }
+}
+
+fn feature(name: &str, shape: Shape) -> FeatureDescription {
+    let shape = match shape {
+        Shape::Hwc([h, w, c]) => vec![c as i64, h as i64, w as i64],
+        Shape::Flat(size) => vec![size as i64],
+    };
+
+    FeatureDescription {
+        name: name.into(),
+        r#type: Some(FeatureType {
+            r#type: Some(feature_type::Type::MultiArrayType(ArrayFeatureType {
+                shape,
+                data_type: ArrayDataType::Float32 as i32,
+            })),
+        }),
+    }
+}
+
+fn weight_params(values: &[f32]) -> WeightParams {
+    WeightParams {
+        float_value: values.to_vec(),
+    }
+}
+
+struct NetworkBuilder {
+    options: ExportOptions,
+    layers: Vec<NeuralNetworkLayer>,
+}
+
+impl NetworkBuilder {
+    fn push(
+        &mut self,
+        name: impl Into<String>,
+        inputs: Vec<String>,
+        output: impl Into<String>,
+        layer: neural_network_layer::Layer,
+    ) {
+        self.layers.push(NeuralNetworkLayer {
+            name: name.into(),
+            input: inputs,
+            output: vec![output.into()],
+            layer: Some(layer),
+        });
+    }
+
+    fn push_layer(
+        &mut self,
+        model: &DarknetModel,
+        layer_index: usize,
+        layer: &Layer,
+    ) -> Result<()> {
+        let name = layer_name(layer_index, &model.base.layers[&layer_index]);
+        match layer {
+            Layer::Convolutional(layer) => self.push_convolutional(model, name, layer),
+            Layer::Connected(layer) => self.push_connected(model, name, layer),
+            Layer::BatchNorm(layer) => self.push_batch_norm(model, name, layer),
+            Layer::Shortcut(layer) => self.push_shortcut(model, name, layer),
+            Layer::MaxPool(layer) => self.push_max_pool(model, name, layer),
+            Layer::Route(layer) => self.push_route(model, name, layer),
+            Layer::UpSample(layer) => self.push_up_sample(model, name, layer),
+            Layer::Yolo(layer) => self.push_yolo(model, name, layer),
+            Layer::Implicit(_) => bail!(
+                "{}: implicit layers are not supported by the CoreML exporter",
+                name
+            ),
+            Layer::AvgPool(_)
+            | Layer::ScaleChannels(_)
+            | Layer::Dropout(_)
+            | Layer::Softmax(_)
+            | Layer::Region(_)
+            | Layer::ConvLstm(_)
+            | Layer::Crnn(_)
+            | Layer::Lstm(_)
+            | Layer::Sam(_)
+            | Layer::LocalAvgPool(_)
+            | Layer::Local(_)
+            | Layer::Reorg(_)
+            | Layer::GaussianYolo(_)
+            | Layer::Custom(_) => {
+                bail!(
+                    "{}: {} layers are not supported by the CoreML exporter",
+                    name,
+                    model.base.layers[&layer_index].kind()
+                )
+            }
+        }
+    }
+
+    fn push_convolutional(
+        &mut self,
+        model: &DarknetModel,
+        name: String,
+        layer: &ConvolutionalLayer,
+    ) -> Result<()> {
+        let ConvolutionalLayer {
+            base:
+                ConvolutionalLayerBase {
+                    config:
+                        ConvolutionalConfig {
+                            filters,
+                            groups,
+                            size,
+                            stride_x,
+                            stride_y,
+                            dilation,
+                            padding,
+                            activation,
+                            antialiasing,
+                            ..
+                        },
+                    from_indexes,
+                    input_shape: [_h, _w, in_c],
+                    ..
+                },
+            ref weights,
+        } = *layer;
+
+        ensure!(
+            !antialiasing,
+            "{}: antialiasing is not supported by the CoreML exporter",
+            name
+        );
+
+        let (biases, weights, scales) = conv_weights(model, weights)
+            .ok_or_else(|| format_err!("{}: invalid shared weights layer", name))?;
+
+        let pre_activation = pre_activation_name(&name, activation);
+        let conv_output = if scales.is_some() {
+            format!("{}_conv", name)
+        } else {
+            pre_activation.clone()
+        };
+
+        let padding_amounts = BorderAmounts {
+            border_amounts: vec![
+                EdgeSizes {
+                    start_edge_size: padding,
+                    end_edge_size: padding,
+                };
+                2
+            ],
+        };
+
+        self.push(
+            format!("{}_conv", name),
+            vec![blob_name(&model.base, from_indexes)],
+            conv_output.clone(),
+            neural_network_layer::Layer::Convolution(ConvolutionLayerParams {
+                output_channels: filters,
+                kernel_channels: in_c / groups,
+                n_groups: groups,
+                kernel_size: vec![size, size],
+                stride: vec![stride_y, stride_x],
+                dilation_factor: vec![dilation, dilation],
+                has_bias: scales.is_none(),
+                weights: Some(weight_params(weights.as_slice().unwrap())),
+                bias: if scales.is_none() {
+                    Some(weight_params(biases.as_slice().unwrap()))
+                } else {
+                    None
+                },
+                convolution_padding_type: Some(
+                    convolution_layer_params::ConvolutionPaddingType::Valid(ValidPadding {
+                        padding_amounts: Some(padding_amounts),
+                    }),
+                ),
+            }),
+        );
+
+        if let Some(scales) = scales {
+            self.push_scale_weights(
+                format!("{}_bn", name),
+                conv_output,
+                pre_activation.clone(),
+                biases,
+                scales,
+            );
+        }
+
+        self.push_activation(&name, pre_activation, activation)
+    }
+
+    fn push_connected(
+        &mut self,
+        model: &DarknetModel,
+        name: String,
+        layer: &ConnectedLayer,
+    ) -> Result<()> {
+        let ConnectedLayer {
+            base:
+                ConnectedLayerBase {
+                    ref config,
+                    from_indexes,
+                    input_shape,
+                    output_shape,
+                },
+            weights:
+                ConnectedWeights {
+                    ref biases,
+                    ref weights,
+                    ref scales,
+                },
+        } = *layer;
+
+        ensure!(
+            biases.len() as u64 == output_shape,
+            "{}: the bias size does not match the output size",
+            name
+        );
+
+        // darknet flattens feature maps in CHW order
+        let input = match from_indexes {
+            LayerPosition::Absolute(index)
+                if model.base.layers[&index].output_shape().hwc().is_some() =>
+            {
+                let flatten_output = format!("{}_flatten", name);
+                self.push(
+                    flatten_output.clone(),
+                    vec![blob_name(&model.base, from_indexes)],
+                    flatten_output.clone(),
+                    neural_network_layer::Layer::Flatten(FlattenLayerParams {
+                        mode: FlattenOrder::ChannelFirst as i32,
+                    }),
+                );
+                flatten_output
+            }
+            _ => blob_name(&model.base, from_indexes),
+        };
+
+        let pre_activation = pre_activation_name(&name, config.activation);
+        let fc_output = if scales.is_some() {
+            format!("{}_fc", name)
+        } else {
+            pre_activation.clone()
+        };
+
+        self.push(
+            format!("{}_fc", name),
+            vec![input],
+            fc_output.clone(),
+            neural_network_layer::Layer::InnerProduct(InnerProductLayerParams {
+                input_channels: input_shape,
+                output_channels: output_shape,
+                has_bias: scales.is_none(),
+                weights: Some(weight_params(weights.as_slice().unwrap())),
+                bias: if scales.is_none() {
+                    Some(weight_params(biases.as_slice().unwrap()))
+                } else {
+                    None
+                },
+            }),
+        );
+
+        if let Some(scales) = scales {
+            self.push_scale_weights(
+                format!("{}_bn", name),
+                fc_output,
+                pre_activation.clone(),
+                biases,
+                scales,
+            );
+        }
+
+        self.push_activation(&name, pre_activation, config.activation)
+    }
+
+    fn push_batch_norm(
+        &mut self,
+        model: &DarknetModel,
+        name: String,
+        layer: &BatchNormLayer,
+    ) -> Result<()> {
+        let BatchNormLayer {
+            ref base,
+            weights:
+                BatchNormWeights {
+                    ref biases,
+                    ref scales,
+                    ref rolling_mean,
+                    ref rolling_variance,
+                },
+        } = *layer;
+
+        self.push(
+            name.clone(),
+            vec![blob_name(&model.base, base.from_indexes)],
+            name,
+            neural_network_layer::Layer::Batchnorm(BatchnormLayerParams {
+                channels: scales.len() as u64,
+                epsilon: BATCH_NORM_EPSILON,
+                gamma: Some(weight_params(scales.as_slice().unwrap())),
+                beta: Some(weight_params(biases.as_slice().unwrap())),
+                mean: Some(weight_params(rolling_mean.as_slice().unwrap())),
+                variance: Some(weight_params(rolling_variance.as_slice().unwrap())),
+            }),
+        );
+        Ok(())
+    }
+
+    fn push_shortcut(
+        &mut self,
+        model: &DarknetModel,
+        name: String,
+        layer: &ShortcutLayer,
+    ) -> Result<()> {
+        let ShortcutLayer {
+            base:
+                ShortcutLayerBase {
+                    config:
+                        ShortcutConfig {
+                            activation,
+                            weights_type,
+                            ..
+                        },
+                    ref from_indexes,
+                    ref input_shape,
+                    ..
+                },
+            ..
+        } = *layer;
+
+        ensure!(
+            weights_type == WeightsType::None,
+            "{}: weighted shortcut is not supported by the CoreML exporter",
+            name
+        );
+        ensure!(
+            input_shape.iter().all_equal(),
+            "{}: shortcut of different shapes is not supported by the CoreML exporter",
+            name
+        );
+
+        let pre_activation = pre_activation_name(&name, activation);
+        self.push(
+            format!("{}_add", name),
+            from_indexes
+                .iter()
+                .map(|&position| blob_name(&model.base, position))
+                .collect(),
+            pre_activation.clone(),
+            neural_network_layer::Layer::Add(AddLayerParams { alpha: 0.0 }),
+        );
+        self.push_activation(&name, pre_activation, activation)
+    }
+
+    fn push_max_pool(
+        &mut self,
+        model: &DarknetModel,
+        name: String,
+        layer: &MaxPoolLayer,
+    ) -> Result<()> {
+        let MaxPoolLayer {
+            base:
+                MaxPoolLayerBase {
+                    config:
+                        MaxPoolConfig {
+                            stride_x,
+                            stride_y,
+                            size,
+                            padding,
+                            maxpool_depth,
+                            antialiasing,
+                            ..
+                        },
+                    from_indexes,
+                    ..
+                },
+        } = *layer;
+
+        ensure!(
+            !maxpool_depth && !antialiasing,
+            "{}: maxpool_depth and antialiasing are not supported by the CoreML exporter",
+            name
+        );
+
+        let [pad_begin, pad_end] = maxpool_padding(padding);
+        let padding_amounts = BorderAmounts {
+            border_amounts: vec![
+                EdgeSizes {
+                    start_edge_size: pad_begin,
+                    end_edge_size: pad_end,
+                };
+                2
+            ],
+        };
+
+        self.push(
+            name.clone(),
+            vec![blob_name(&model.base, from_indexes)],
+            name,
+            neural_network_layer::Layer::Pooling(PoolingLayerParams {
+                r#type: PoolingType::Max as i32,
+                kernel_size: vec![size, size],
+                stride: vec![stride_y, stride_x],
+                pooling_padding_type: Some(pooling_layer_params::PoolingPaddingType::Valid(
+                    ValidPadding {
+                        padding_amounts: Some(padding_amounts),
+                    },
+                )),
+            }),
+        );
+        Ok(())
+    }
+
+    fn push_route(&mut self, model: &DarknetModel, name: String, layer: &RouteLayer) -> Result<()> {
+        let RouteLayer {
+            base:
+                RouteLayerBase {
+                    ref config,
+                    ref from_indexes,
+                    ref input_shape,
+                    ..
+                },
+        } = *layer;
+        let group_id = config.group.group_id();
+        let num_groups = config.group.num_groups();
+
+        // take the channel group of each input, then concatenate them
+        let inputs: Vec<_> = izip!(from_indexes.iter(), input_shape.iter())
+            .enumerate()
+            .map(|(nth, (&position, &[_h, _w, in_c]))| {
+                let input = blob_name(&model.base, position);
+                if num_groups == 1 {
+                    return input;
+                }
+
+                let group_size = in_c / num_groups;
+                let slice_output = if from_indexes.len() == 1 {
+                    name.clone()
+                } else {
+                    format!("{}_slice_{}", name, nth)
+                };
+                self.push(
+                    format!("{}_slice_{}", name, nth),
+                    vec![input],
+                    slice_output.clone(),
+                    neural_network_layer::Layer::Slice(SliceLayerParams {
+                        start_index: (group_size * group_id) as i64,
+                        end_index: (group_size * (group_id + 1)) as i64,
+                        stride: 1,
+                        axis: SliceAxis::ChannelAxis as i32,
+                    }),
+                );
+                slice_output
+            })
+            .collect();
+
+        match (inputs.len(), num_groups) {
+            (1, 1) => self.push_identity(name, inputs.into_iter().next().unwrap()),
+            (1, _) => (),
+            _ => self.push(
+                name.clone(),
+                inputs,
+                name,
+                neural_network_layer::Layer::Concat(ConcatLayerParams {
+                    sequence_concat: false,
+                }),
+            ),
+        }
+        Ok(())
+    }
+
+    fn push_up_sample(
+        &mut self,
+        model: &DarknetModel,
+        name: String,
+        layer: &UpSampleLayer,
+    ) -> Result<()> {
+        let UpSampleLayer {
+            base:
+                UpSampleLayerBase {
+                    config:
+                        UpSampleConfig {
+                            stride, reverse, ..
+                        },
+                    from_indexes,
+                    ..
+                },
+        } = *layer;
+
+        ensure!(
+            !reverse,
+            "{}: reverse upsampling is not supported by the CoreML exporter",
+            name
+        );
+
+        self.push(
+            name.clone(),
+            vec![blob_name(&model.base, from_indexes)],
+            name,
+            neural_network_layer::Layer::Upsample(UpsampleLayerParams {
+                scaling_factor: vec![stride, stride],
+                mode: InterpolationMode::Nn as i32,
+            }),
+        );
+        Ok(())
+    }
+
+    fn push_yolo(&mut self, model: &DarknetModel, name: String, layer: &YoloLayer) -> Result<()> {
+        let input = blob_name(&model.base, yolo_output(&layer.base));
+        self.push_identity(name, input);
+        Ok(())
+    }
+
+    fn push_scale_weights(
+        &mut self,
+        name: String,
+        input: String,
+        output: String,
+        biases: &Array1<f32>,
+        scales: &ScaleWeights,
+    ) {
+        let ScaleWeights {
+            scales,
+            rolling_mean,
+            rolling_variance,
+        } = scales;
+
+        self.push(
+            name,
+            vec![input],
+            output,
+            neural_network_layer::Layer::Batchnorm(BatchnormLayerParams {
+                channels: scales.len() as u64,
+                epsilon: BATCH_NORM_EPSILON,
+                gamma: Some(weight_params(scales.as_slice().unwrap())),
+                beta: Some(weight_params(biases.as_slice().unwrap())),
+                mean: Some(weight_params(rolling_mean.as_slice().unwrap())),
+                variance: Some(weight_params(rolling_variance.as_slice().unwrap())),
+            }),
+        );
+    }
+
+    fn push_identity(&mut self, name: String, input: String) {
+        self.push(
+            name.clone(),
+            vec![input],
+            name,
+            neural_network_layer::Layer::Activation(ActivationParams {
+                nonlinearity_type: Some(activation_params::NonlinearityType::Linear(
+                    ActivationLinear {
+                        alpha: 1.0,
+                        beta: 0.0,
+                    },
+                )),
+            }),
+        );
+    }
+
+    fn push_activation(&mut self, name: &str, input: String, activation: Activation) -> Result<()> {
+        use activation_params::NonlinearityType as N;
+
+        let lowering = self.options.activation_lowering;
+        let mut push_unary = |suffix: &str, input: String, output: String, nonlinearity: N| {
+            self.push(
+                format!("{}_{}", name, suffix),
+                vec![input],
+                output,
+                neural_network_layer::Layer::Activation(ActivationParams {
+                    nonlinearity_type: Some(nonlinearity),
+                }),
+            );
+        };
+
+        match activation {
+            Activation::Linear => (),
+            Activation::Relu => push_unary("relu", input, name.into(), N::ReLu(ActivationReLu {})),
+            Activation::Leaky => push_unary(
+                "leaky",
+                input,
+                name.into(),
+                N::LeakyReLu(ActivationLeakyReLu { alpha: 0.1 }),
+            ),
+            Activation::Logistic => push_unary(
+                "logistic",
+                input,
+                name.into(),
+                N::Sigmoid(ActivationSigmoid {}),
+            ),
+            Activation::Tanh => push_unary("tanh", input, name.into(), N::Tanh(ActivationTanh {})),
+            Activation::Elu => push_unary(
+                "elu",
+                input,
+                name.into(),
+                N::Elu(ActivationElu { alpha: 1.0 }),
+            ),
+            // custom layers are implemented by the app as classes named after the op
+            Activation::Mish | Activation::HardMish | Activation::Swish
+                if lowering == ActivationLowering::Custom =>
+            {
+                let op_type = custom_op_type(activation).unwrap();
+                self.push(
+                    format!("{}_{}", name, op_type.to_lowercase()),
+                    vec![input],
+                    name,
+                    neural_network_layer::Layer::Custom(CustomLayerParams {
+                        class_name: format!("Darknet{}", op_type),
+                        weights: vec![],
+                        description: format!("darknet {} activation", activation),
+                    }),
+                );
+            }
+            // CoreML has no native op for these, so they are always lowered
+            // mish(x) = x * tanh(softplus(x))
+            Activation::Mish => {
+                let softplus = format!("{}_softplus", name);
+                let tanh = format!("{}_tanh", name);
+                push_unary(
+                    "softplus",
+                    input.clone(),
+                    softplus.clone(),
+                    N::Softplus(ActivationSoftplus {}),
+                );
+                push_unary("tanh", softplus, tanh.clone(), N::Tanh(ActivationTanh {}));
+                self.push_multiply(name, input, tanh);
+            }
+            // swish(x) = x * sigmoid(x)
+            Activation::Swish => {
+                let sigmoid = format!("{}_sigmoid", name);
+                push_unary(
+                    "sigmoid",
+                    input.clone(),
+                    sigmoid.clone(),
+                    N::Sigmoid(ActivationSigmoid {}),
+                );
+                self.push_multiply(name, input, sigmoid);
+            }
+            // hard_mish(x) = x * min(max(x / 2 + 1, 0), 1), see activate() in darknet
+            Activation::HardMish => {
+                let hard_sigmoid = format!("{}_hard_sigmoid", name);
+                push_unary(
+                    "hard_sigmoid",
+                    input.clone(),
+                    hard_sigmoid.clone(),
+                    N::SigmoidHard(ActivationSigmoidHard {
+                        alpha: 0.5,
+                        beta: 1.0,
+                    }),
+                );
+                self.push_multiply(name, input, hard_sigmoid);
+            }
+            _ => bail!(
+                "{}: activation {:?} is not supported by the CoreML exporter",
+                name,
+                activation
+            ),
+        }
+        Ok(())
+    }
+
+    fn push_multiply(&mut self, name: &str, lhs: String, rhs: String) {
+        self.push(
+            format!("{}_mul", name),
+            vec![lhs, rhs],
+            name,
+            neural_network_layer::Layer::Multiply(MultiplyLayerParams { alpha: 0.0 }),
+        );
+    }
+}
+
+fn pre_activation_name(name: &str, activation: Activation) -> String {
+    match activation {
+        Activation::Linear => name.into(),
+        _ => format!("{}_pre", name),
+    }
+}
+
+// a subset of the CoreML specification (Model.proto, FeatureTypes.proto and NeuralNetwork.proto)
+mod proto {
+    use prost::{Enumeration, Message, Oneof};
+    use std::collections::HashMap;
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Model {
+        #[prost(int32, tag = "1")]
+        pub specification_version: i32,
+        #[prost(message, optional, tag = "2")]
+        pub description: Option<ModelDescription>,
+        #[prost(oneof = "model::Type", tags = "500")]
+        pub r#type: Option<model::Type>,
+    }
+
+    pub mod model {
+        use super::*;
+
+        #[derive(Clone, PartialEq, Oneof)]
+        pub enum Type {
+            #[prost(message, tag = "500")]
+            NeuralNetwork(NeuralNetwork),
+        }
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ModelDescription {
+        #[prost(message, repeated, tag = "1")]
+        pub input: Vec<FeatureDescription>,
+        #[prost(message, repeated, tag = "10")]
+        pub output: Vec<FeatureDescription>,
+        #[prost(message, optional, tag = "100")]
+        pub metadata: Option<Metadata>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Metadata {
+        #[prost(string, tag = "1")]
+        pub short_description: String,
+        #[prost(string, tag = "2")]
+        pub version_string: String,
+        #[prost(map = "string, string", tag = "100")]
+        pub user_defined: HashMap<String, String>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct FeatureDescription {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(message, optional, tag = "3")]
+        pub r#type: Option<FeatureType>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct FeatureType {
+        #[prost(oneof = "feature_type::Type", tags = "5")]
+        pub r#type: Option<feature_type::Type>,
+    }
+
+    pub mod feature_type {
+        use super::*;
+
+        #[derive(Clone, PartialEq, Oneof)]
+        pub enum Type {
+            #[prost(message, tag = "5")]
+            MultiArrayType(ArrayFeatureType),
+        }
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ArrayFeatureType {
+        #[prost(int64, repeated, tag = "1")]
+        pub shape: Vec<i64>,
+        #[prost(enumeration = "ArrayDataType", tag = "2")]
+        pub data_type: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+    #[repr(i32)]
+    pub enum ArrayDataType {
+        InvalidArrayDataType = 0,
+        Float32 = 65568,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct NeuralNetwork {
+        #[prost(message, repeated, tag = "1")]
+        pub layers: Vec<NeuralNetworkLayer>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct NeuralNetworkLayer {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, repeated, tag = "2")]
+        pub input: Vec<String>,
+        #[prost(string, repeated, tag = "3")]
+        pub output: Vec<String>,
+        #[prost(
+            oneof = "neural_network_layer::Layer",
+            tags = "100, 120, 130, 140, 160, 210, 230, 231, 301, 320, 350, 500"
+        )]
+        pub layer: Option<neural_network_layer::Layer>,
+    }
+
+    pub mod neural_network_layer {
+        use super::*;
+
+        #[derive(Clone, PartialEq, Oneof)]
+        pub enum Layer {
+            #[prost(message, tag = "100")]
+            Convolution(ConvolutionLayerParams),
+            #[prost(message, tag = "120")]
+            Pooling(PoolingLayerParams),
+            #[prost(message, tag = "130")]
+            Activation(ActivationParams),
+            #[prost(message, tag = "140")]
+            InnerProduct(InnerProductLayerParams),
+            #[prost(message, tag = "160")]
+            Batchnorm(BatchnormLayerParams),
+            #[prost(message, tag = "210")]
+            Upsample(UpsampleLayerParams),
+            #[prost(message, tag = "230")]
+            Add(AddLayerParams),
+            #[prost(message, tag = "231")]
+            Multiply(MultiplyLayerParams),
+            #[prost(message, tag = "301")]
+            Flatten(FlattenLayerParams),
+            #[prost(message, tag = "320")]
+            Concat(ConcatLayerParams),
+            #[prost(message, tag = "350")]
+            Slice(SliceLayerParams),
+            #[prost(message, tag = "500")]
+            Custom(CustomLayerParams),
+        }
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct WeightParams {
+        #[prost(float, repeated, tag = "1")]
+        pub float_value: Vec<f32>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct EdgeSizes {
+        #[prost(uint64, tag = "1")]
+        pub start_edge_size: u64,
+        #[prost(uint64, tag = "2")]
+        pub end_edge_size: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct BorderAmounts {
+        #[prost(message, repeated, tag = "10")]
+        pub border_amounts: Vec<EdgeSizes>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ValidPadding {
+        #[prost(message, optional, tag = "1")]
+        pub padding_amounts: Option<BorderAmounts>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ConvolutionLayerParams {
+        #[prost(uint64, tag = "1")]
+        pub output_channels: u64,
+        #[prost(uint64, tag = "2")]
+        pub kernel_channels: u64,
+        #[prost(uint64, tag = "10")]
+        pub n_groups: u64,
+        #[prost(uint64, repeated, tag = "20")]
+        pub kernel_size: Vec<u64>,
+        #[prost(uint64, repeated, tag = "30")]
+        pub stride: Vec<u64>,
+        #[prost(uint64, repeated, tag = "40")]
+        pub dilation_factor: Vec<u64>,
+        #[prost(
+            oneof = "convolution_layer_params::ConvolutionPaddingType",
+            tags = "50"
+        )]
+        pub convolution_padding_type: Option<convolution_layer_params::ConvolutionPaddingType>,
+        #[prost(bool, tag = "70")]
+        pub has_bias: bool,
+        #[prost(message, optional, tag = "90")]
+        pub weights: Option<WeightParams>,
+        #[prost(message, optional, tag = "91")]
+        pub bias: Option<WeightParams>,
+    }
+
+    pub mod convolution_layer_params {
+        use super::*;
+
+        #[derive(Clone, PartialEq, Oneof)]
+        pub enum ConvolutionPaddingType {
+            #[prost(message, tag = "50")]
+            Valid(ValidPadding),
+        }
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct PoolingLayerParams {
+        #[prost(enumeration = "PoolingType", tag = "1")]
+        pub r#type: i32,
+        #[prost(uint64, repeated, tag = "10")]
+        pub kernel_size: Vec<u64>,
+        #[prost(uint64, repeated, tag = "20")]
+        pub stride: Vec<u64>,
+        #[prost(oneof = "pooling_layer_params::PoolingPaddingType", tags = "30")]
+        pub pooling_padding_type: Option<pooling_layer_params::PoolingPaddingType>,
+    }
+
+    pub mod pooling_layer_params {
+        use super::*;
+
+        #[derive(Clone, PartialEq, Oneof)]
+        pub enum PoolingPaddingType {
+            #[prost(message, tag = "30")]
+            Valid(ValidPadding),
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+    #[repr(i32)]
+    pub enum PoolingType {
+        Max = 0,
+        Average = 1,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ActivationParams {
+        #[prost(
+            oneof = "activation_params::NonlinearityType",
+            tags = "5, 10, 15, 30, 40, 41, 50, 70"
+        )]
+        pub nonlinearity_type: Option<activation_params::NonlinearityType>,
+    }
+
+    pub mod activation_params {
+        use super::*;
+
+        #[derive(Clone, PartialEq, Oneof)]
+        pub enum NonlinearityType {
+            #[prost(message, tag = "5")]
+            Linear(ActivationLinear),
+            #[prost(message, tag = "10")]
+            ReLu(ActivationReLu),
+            #[prost(message, tag = "15")]
+            LeakyReLu(ActivationLeakyReLu),
+            #[prost(message, tag = "30")]
+            Tanh(ActivationTanh),
+            #[prost(message, tag = "40")]
+            Sigmoid(ActivationSigmoid),
+            #[prost(message, tag = "41")]
+            SigmoidHard(ActivationSigmoidHard),
+            #[prost(message, tag = "50")]
+            Elu(ActivationElu),
+            #[prost(message, tag = "70")]
+            Softplus(ActivationSoftplus),
+        }
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ActivationLinear {
+        #[prost(float, tag = "1")]
+        pub alpha: f32,
+        #[prost(float, tag = "2")]
+        pub beta: f32,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ActivationReLu {}
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ActivationLeakyReLu {
+        #[prost(float, tag = "1")]
+        pub alpha: f32,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ActivationTanh {}
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ActivationSigmoid {}
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ActivationSigmoidHard {
+        #[prost(float, tag = "1")]
+        pub alpha: f32,
+        #[prost(float, tag = "2")]
+        pub beta: f32,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ActivationElu {
+        #[prost(float, tag = "1")]
+        pub alpha: f32,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ActivationSoftplus {}
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct InnerProductLayerParams {
+        #[prost(uint64, tag = "1")]
+        pub input_channels: u64,
+        #[prost(uint64, tag = "2")]
+        pub output_channels: u64,
+        #[prost(bool, tag = "10")]
+        pub has_bias: bool,
+        #[prost(message, optional, tag = "20")]
+        pub weights: Option<WeightParams>,
+        #[prost(message, optional, tag = "21")]
+        pub bias: Option<WeightParams>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct BatchnormLayerParams {
+        #[prost(uint64, tag = "1")]
+        pub channels: u64,
+        #[prost(float, tag = "10")]
+        pub epsilon: f32,
+        #[prost(message, optional, tag = "15")]
+        pub gamma: Option<WeightParams>,
+        #[prost(message, optional, tag = "16")]
+        pub beta: Option<WeightParams>,
+        #[prost(message, optional, tag = "17")]
+        pub mean: Option<WeightParams>,
+        #[prost(message, optional, tag = "18")]
+        pub variance: Option<WeightParams>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct UpsampleLayerParams {
+        #[prost(uint64, repeated, tag = "1")]
+        pub scaling_factor: Vec<u64>,
+        #[prost(enumeration = "InterpolationMode", tag = "5")]
+        pub mode: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+    #[repr(i32)]
+    pub enum InterpolationMode {
+        Nn = 0,
+        Bilinear = 1,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct AddLayerParams {
+        #[prost(float, tag = "1")]
+        pub alpha: f32,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct MultiplyLayerParams {
+        #[prost(float, tag = "1")]
+        pub alpha: f32,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct FlattenLayerParams {
+        #[prost(enumeration = "FlattenOrder", tag = "1")]
+        pub mode: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+    #[repr(i32)]
+    pub enum FlattenOrder {
+        ChannelFirst = 0,
+        ChannelLast = 1,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ConcatLayerParams {
+        #[prost(bool, tag = "100")]
+        pub sequence_concat: bool,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct CustomLayerParams {
+        #[prost(string, tag = "10")]
+        pub class_name: String,
+        #[prost(message, repeated, tag = "20")]
+        pub weights: Vec<WeightParams>,
+        #[prost(string, tag = "40")]
+        pub description: String,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct SliceLayerParams {
+        #[prost(int64, tag = "1")]
+        pub start_index: i64,
+        #[prost(int64, tag = "2")]
+        pub end_index: i64,
+        #[prost(uint64, tag = "3")]
+        pub stride: u64,
+        #[prost(enumeration = "SliceAxis", tag = "4")]
+        pub axis: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+    #[repr(i32)]
+    #[allow(clippy::enum_variant_names)]
+    pub enum SliceAxis {
+        ChannelAxis = 0,
+        HeightAxis = 1,
+        WidthAxis = 2,
+    }
+}
@@ -0,0 +1,519 @@
+use super::layer_name;
+use crate::{
+    common::*,
+    config::{DarknetConfig, Shape},
+    darknet::{
+        BatchNormLayer, BatchNormWeights, ConnectedLayer, ConnectedWeights, ConvLstmLayer,
+        ConvLstmWeights, ConvolutionalLayer, ConvolutionalWeights, CrnnLayer, CrnnWeights,
+        DarknetModel, ImplicitLayer, ImplicitWeights, Layer, LocalLayer, LocalWeights, LstmLayer,
+        LstmWeights, RecurrentConvWeights, ScaleWeights, ShortcutLayer, ShortcutWeights,
+    },
+    model::LSTM_GATES,
+};
+use byteorder::WriteBytesExt;
+use half::f16;
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+const GGUF_VERSION: u32 = 3;
+const GGUF_ALIGNMENT: usize = 32;
+const Q8_0_BLOCK_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TensorType {
+    F32,
+    F16,
+    Q8_0,
+}
+
+impl TensorType {
+    // the ggml_type id
+    fn id(&self) -> u32 {
+        match self {
+            Self::F32 => 0,
+            Self::F16 => 1,
+            Self::Q8_0 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GgufOptions {
+    pub name: String,
+    pub tensor_type: TensorType,
+    pub config: Option<DarknetConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum MetadataValue {
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    String(String),
+    U64Array(Vec<u64>),
+    StringArray(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Tensor {
+    name: String,
+    // in ggml order, the innermost dimension goes first
+    dims: Vec<u64>,
+    tensor_type: TensorType,
+    data: Vec<u8>,
+}
+
+impl Tensor {
+    fn new(name: String, dims: Vec<u64>, values: &[f32], tensor_type: TensorType) -> Self {
+        // Q8_0 quantizes rows in blocks of 32 values, fall back to F16 otherwise
+        let tensor_type = match tensor_type {
+            TensorType::Q8_0 if !(dims[0] as usize).is_multiple_of(Q8_0_BLOCK_SIZE) => {
+                TensorType::F16
+            }
+            tensor_type => tensor_type,
+        };
+
+        let data = match tensor_type {
+            TensorType::F32 => values
+                .iter()
+                .flat_map(|value| value.to_le_bytes().to_vec())
+                .collect(),
+            TensorType::F16 => values
+                .iter()
+                .flat_map(|&value| f16::from_f32(value).to_bits().to_le_bytes().to_vec())
+                .collect(),
+            TensorType::Q8_0 => quantize_q8_0(values),
+        };
+
+        Self {
+            name,
+            dims,
+            tensor_type,
+            data,
+        }
+    }
+}
+
+pub fn to_gguf(model: &DarknetModel, options: &GgufOptions) -> Result<Vec<u8>> {
+    let GgufOptions {
+        ref name,
+        tensor_type,
+        ref config,
+    } = *options;
+    let net = &model.base.net;
+
+    let input_shape = match net.input_size {
+        Shape::Hwc([h, w, c]) => vec![h, w, c],
+        Shape::Flat(size) => vec![size],
+    };
+    let kinds: Vec<String> = model
+        .base
+        .layers
+        .values()
+        .map(|layer| layer.kind().to_owned())
+        .collect();
+
+    let mut metadata = vec![
+        (
+            "general.architecture",
+            MetadataValue::String("darknet".into()),
+        ),
+        ("general.name", MetadataValue::String(name.clone())),
+        (
+            "general.alignment",
+            MetadataValue::U32(GGUF_ALIGNMENT as u32),
+        ),
+        ("darknet.input_shape", MetadataValue::U64Array(input_shape)),
+        ("darknet.classes", MetadataValue::U64(net.classes)),
+        ("darknet.seen", MetadataValue::U64(model.base.seen)),
+        (
+            "darknet.learning_rate",
+            MetadataValue::F32(net.learning_rate.raw() as f32),
+        ),
+        (
+            "darknet.block_count",
+            MetadataValue::U64(kinds.len() as u64),
+        ),
+        ("darknet.layer_kinds", MetadataValue::StringArray(kinds)),
+    ];
+    if let Some(config) = config {
+        metadata.push((
+            "darknet.config",
+            MetadataValue::String(config.to_cfg_string()?),
+        ));
+    }
+
+    let tensors: Vec<_> = model
+        .layers
+        .iter()
+        .flat_map(|(&layer_index, layer)| {
+            let name = layer_name(layer_index, &model.base.layers[&layer_index]);
+            layer_tensors(&name, layer, tensor_type)
+        })
+        .collect();
+
+    // header
+    let mut bytes = vec![];
+    bytes.extend_from_slice(GGUF_MAGIC);
+    bytes.write_u32::<LittleEndian>(GGUF_VERSION)?;
+    bytes.write_u64::<LittleEndian>(tensors.len() as u64)?;
+    bytes.write_u64::<LittleEndian>(metadata.len() as u64)?;
+
+    for (key, value) in &metadata {
+        write_string(&mut bytes, key)?;
+        write_value(&mut bytes, value)?;
+    }
+
+    // tensor infos, offsets are relative to the start of the data section
+    let mut offset = 0;
+    for tensor in &tensors {
+        write_string(&mut bytes, &tensor.name)?;
+        bytes.write_u32::<LittleEndian>(tensor.dims.len() as u32)?;
+        for &dim in &tensor.dims {
+            bytes.write_u64::<LittleEndian>(dim)?;
+        }
+        bytes.write_u32::<LittleEndian>(tensor.tensor_type.id())?;
+        bytes.write_u64::<LittleEndian>(offset as u64)?;
+        offset = align(offset + tensor.data.len());
+    }
+
+    // tensor data
+    bytes.resize(align(bytes.len()), 0);
+    for tensor in &tensors {
+        bytes.extend_from_slice(&tensor.data);
+        bytes.resize(align(bytes.len()), 0);
+    }
+
+    Ok(bytes)
+}
+
+impl DarknetModel {
+    pub fn save_gguf<P>(&self, gguf_file: P, options: &GgufOptions) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(gguf_file, to_gguf(self, options)?)?;
+        Ok(())
+    }
+}
+
+fn layer_tensors(name: &str, layer: &Layer, tensor_type: TensorType) -> Vec<Tensor> {
+    let f32_tensor = |suffix: &str, values: &Array1<f32>| {
+        Tensor::new(
+            format!("{}.{}", name, suffix),
+            vec![values.len() as u64],
+            values.as_slice().unwrap(),
+            TensorType::F32,
+        )
+    };
+    let scale_tensors = |scales: &ScaleWeights| {
+        let ScaleWeights {
+            scales,
+            rolling_mean,
+            rolling_variance,
+        } = scales;
+        vec![
+            f32_tensor("bn.scale", scales),
+            f32_tensor("bn.mean", rolling_mean),
+            f32_tensor("bn.variance", rolling_variance),
+        ]
+    };
+
+    match layer {
+        Layer::Convolutional(ConvolutionalLayer { base, weights }) => match weights {
+            ConvolutionalWeights::Owned {
+                biases,
+                weights,
+                scales,
+            } => {
+                // kernels are stored as [filters, in_c / groups * size * size] matrices
+                let [in_c, filters, size, _size] = base.weights_shape();
+                let weights = Tensor::new(
+                    format!("{}.weight", name),
+                    vec![in_c * size * size, filters],
+                    weights.as_slice().unwrap(),
+                    tensor_type,
+                );
+                iter::once(weights)
+                    .chain(iter::once(f32_tensor("bias", biases)))
+                    .chain(scales.iter().flat_map(scale_tensors))
+                    .collect()
+            }
+            // shared weights are resolved from the config by loaders
+            ConvolutionalWeights::Ref { .. } => vec![],
+        },
+        Layer::Connected(ConnectedLayer {
+            base,
+            weights:
+                ConnectedWeights {
+                    biases,
+                    weights,
+                    scales,
+                },
+        }) => {
+            let weights = Tensor::new(
+                format!("{}.weight", name),
+                vec![base.input_shape, base.output_shape],
+                weights.as_slice().unwrap(),
+                tensor_type,
+            );
+            iter::once(weights)
+                .chain(iter::once(f32_tensor("bias", biases)))
+                .chain(scales.iter().flat_map(scale_tensors))
+                .collect()
+        }
+        Layer::BatchNorm(BatchNormLayer {
+            weights:
+                BatchNormWeights {
+                    biases,
+                    scales,
+                    rolling_mean,
+                    rolling_variance,
+                },
+            ..
+        }) => vec![
+            f32_tensor("bias", biases),
+            f32_tensor("bn.scale", scales),
+            f32_tensor("bn.mean", rolling_mean),
+            f32_tensor("bn.variance", rolling_variance),
+        ],
+        Layer::Shortcut(ShortcutLayer { weights, .. }) => match weights {
+            ShortcutWeights::None => vec![],
+            ShortcutWeights::PerFeature(weights) => vec![f32_tensor("weight", weights)],
+            ShortcutWeights::PerChannel(weights) => {
+                let (num_inputs, channels) = weights.dim();
+                vec![Tensor::new(
+                    format!("{}.weight", name),
+                    vec![channels as u64, num_inputs as u64],
+                    weights.as_slice().unwrap(),
+                    TensorType::F32,
+                )]
+            }
+        },
+        Layer::Implicit(ImplicitLayer {
+            weights: ImplicitWeights { weights },
+            ..
+        }) => vec![f32_tensor("weight", weights)],
+        Layer::Local(LocalLayer {
+            base,
+            weights: LocalWeights { biases, weights },
+        }) => {
+            // one [filters, in_c * size * size] matrix per output location
+            let [locations, filters, kernel_len] = base.weights_shape();
+            let [out_h, out_w, _filters] = base.output_shape;
+            vec![
+                Tensor::new(
+                    format!("{}.weight", name),
+                    vec![kernel_len, filters, locations],
+                    weights.as_slice().unwrap(),
+                    tensor_type,
+                ),
+                Tensor::new(
+                    format!("{}.bias", name),
+                    vec![out_w, out_h, filters],
+                    biases.as_slice().unwrap(),
+                    TensorType::F32,
+                ),
+            ]
+        }
+        Layer::Lstm(LstmLayer {
+            base,
+            weights: LstmWeights { gates },
+        }) => izip!(LSTM_GATES.iter(), base.gate_shapes().iter(), gates)
+            .flat_map(|(gate, &(inputs, outputs), weights)| {
+                let ConnectedWeights {
+                    biases,
+                    weights,
+                    scales,
+                } = weights;
+                let gate_tensor = |suffix: &str, values: &Array1<f32>| {
+                    Tensor::new(
+                        format!("{}.{}.{}", name, gate, suffix),
+                        vec![values.len() as u64],
+                        values.as_slice().unwrap(),
+                        TensorType::F32,
+                    )
+                };
+                let mut tensors = vec![
+                    Tensor::new(
+                        format!("{}.{}.weight", name, gate),
+                        vec![inputs, outputs],
+                        weights.as_slice().unwrap(),
+                        tensor_type,
+                    ),
+                    gate_tensor("bias", biases),
+                ];
+                if let Some(ScaleWeights {
+                    scales,
+                    rolling_mean,
+                    rolling_variance,
+                }) = scales
+                {
+                    tensors.extend(vec![
+                        gate_tensor("bn.scale", scales),
+                        gate_tensor("bn.mean", rolling_mean),
+                        gate_tensor("bn.variance", rolling_variance),
+                    ]);
+                }
+                tensors
+            })
+            .collect(),
+        Layer::Crnn(CrnnLayer {
+            base,
+            weights: CrnnWeights { convs },
+        }) => recurrent_conv_tensors(
+            name,
+            base.conv_shapes().iter().map(|(conv, ..)| *conv),
+            convs,
+            tensor_type,
+        ),
+        Layer::ConvLstm(ConvLstmLayer {
+            base,
+            weights: ConvLstmWeights { gates },
+        }) => recurrent_conv_tensors(
+            name,
+            base.gate_shapes().iter().map(|(gate, ..)| *gate),
+            gates,
+            tensor_type,
+        ),
+        Layer::Route(_)
+        | Layer::MaxPool(_)
+        | Layer::UpSample(_)
+        | Layer::Yolo(_)
+        | Layer::AvgPool(_)
+        | Layer::ScaleChannels(_)
+        | Layer::Dropout(_)
+        | Layer::Softmax(_)
+        | Layer::Region(_)
+        | Layer::Sam(_)
+        | Layer::LocalAvgPool(_)
+        | Layer::Reorg(_)
+        | Layer::GaussianYolo(_)
+        | Layer::Custom(_) => vec![],
+    }
+}
+
+// the convolutions are named after the gates, e.g. conv_lstm_3.wf.weight
+fn recurrent_conv_tensors(
+    name: &str,
+    convs: impl Iterator<Item = &'static str>,
+    weights: &[RecurrentConvWeights],
+    tensor_type: TensorType,
+) -> Vec<Tensor> {
+    convs
+        .zip(weights)
+        .flat_map(|(conv, weights)| {
+            let RecurrentConvWeights {
+                biases,
+                weights,
+                scales,
+            } = weights;
+            let conv_tensor = |suffix: &str, values: &Array1<f32>| {
+                Tensor::new(
+                    format!("{}.{}.{}", name, conv, suffix),
+                    vec![values.len() as u64],
+                    values.as_slice().unwrap(),
+                    TensorType::F32,
+                )
+            };
+            // kernels are stored as [filters, in_c / groups * size * size] matrices
+            let (in_c, filters, size, _size) = weights.dim();
+            let mut tensors = vec![
+                Tensor::new(
+                    format!("{}.{}.weight", name, conv),
+                    vec![(in_c * size * size) as u64, filters as u64],
+                    weights.as_slice().unwrap(),
+                    tensor_type,
+                ),
+                conv_tensor("bias", biases),
+            ];
+            if let Some(ScaleWeights {
+                scales,
+                rolling_mean,
+                rolling_variance,
+            }) = scales
+            {
+                tensors.extend(vec![
+                    conv_tensor("bn.scale", scales),
+                    conv_tensor("bn.mean", rolling_mean),
+                    conv_tensor("bn.variance", rolling_variance),
+                ]);
+            }
+            tensors
+        })
+        .collect()
+}
+
+fn quantize_q8_0(values: &[f32]) -> Vec<u8> {
+    values
+        .chunks(Q8_0_BLOCK_SIZE)
+        .flat_map(|block| {
+            let amax = block
+                .iter()
+                .map(|value| value.abs())
+                .fold(0.0f32, |lhs, rhs| lhs.max(rhs));
+            let scale = amax / 127.0;
+            let inv_scale = if scale != 0.0 { 1.0 / scale } else { 0.0 };
+
+            let scale_bytes = f16::from_f32(scale).to_bits().to_le_bytes();
+            let quants = block
+                .iter()
+                .map(move |&value| (value * inv_scale).round() as i8 as u8);
+            IntoIterator::into_iter(scale_bytes).chain(quants)
+        })
+        .collect()
+}
+
+fn align(offset: usize) -> usize {
+    offset.div_ceil(GGUF_ALIGNMENT) * GGUF_ALIGNMENT
+}
+
+fn write_string(writer: &mut Vec<u8>, text: &str) -> Result<()> {
+    writer.write_u64::<LittleEndian>(text.len() as u64)?;
+    writer.extend_from_slice(text.as_bytes());
+    Ok(())
+}
+
+fn write_value(writer: &mut Vec<u8>, value: &MetadataValue) -> Result<()> {
+    // gguf_type ids
+    const UINT32: u32 = 4;
+    const FLOAT32: u32 = 6;
+    const STRING: u32 = 8;
+    const ARRAY: u32 = 9;
+    const UINT64: u32 = 10;
+
+    match value {
+        MetadataValue::U32(value) => {
+            writer.write_u32::<LittleEndian>(UINT32)?;
+            writer.write_u32::<LittleEndian>(*value)?;
+        }
+        MetadataValue::U64(value) => {
+            writer.write_u32::<LittleEndian>(UINT64)?;
+            writer.write_u64::<LittleEndian>(*value)?;
+        }
+        MetadataValue::F32(value) => {
+            writer.write_u32::<LittleEndian>(FLOAT32)?;
+            writer.write_f32::<LittleEndian>(*value)?;
+        }
+        MetadataValue::String(value) => {
+            writer.write_u32::<LittleEndian>(STRING)?;
+            write_string(writer, value)?;
+        }
+        MetadataValue::U64Array(values) => {
+            writer.write_u32::<LittleEndian>(ARRAY)?;
+            writer.write_u32::<LittleEndian>(UINT64)?;
+            writer.write_u64::<LittleEndian>(values.len() as u64)?;
+            for &value in values {
+                writer.write_u64::<LittleEndian>(value)?;
+            }
+        }
+        MetadataValue::StringArray(values) => {
+            writer.write_u32::<LittleEndian>(ARRAY)?;
+            writer.write_u32::<LittleEndian>(STRING)?;
+            writer.write_u64::<LittleEndian>(values.len() as u64)?;
+            for value in values {
+                write_string(writer, value)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,337 @@
+use super::{
+    custom_op_type, ActivationLowering, ExportOptions, UnsupportedLayer, CUSTOM_OP_DOMAIN,
+};
+use crate::{
+    common::*,
+    config::{Activation, Deform, WeightsType},
+    model::{
+        ConnectedLayerBase, ConvolutionalLayerBase, CustomLayerBase, LayerBase, MaxPoolLayerBase,
+        ModelBase, RouteLayerBase, ScaleChannelsLayerBase, ShortcutLayerBase, SoftmaxLayerBase,
+        UpSampleLayerBase,
+    },
+};
+
+// an ONNX operator and the first opset version with the semantics the layer
+// needs
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OnnxOp {
+    // None for the default ai.onnx domain
+    pub domain: Option<String>,
+    pub op_type: String,
+    pub since_version: u64,
+}
+
+impl OnnxOp {
+    fn new(op_type: &str, since_version: u64) -> Self {
+        Self {
+            domain: None,
+            op_type: op_type.into(),
+            since_version,
+        }
+    }
+
+    fn custom(op_type: &str) -> Self {
+        Self {
+            domain: Some(CUSTOM_OP_DOMAIN.into()),
+            op_type: op_type.into(),
+            since_version: 1,
+        }
+    }
+}
+
+impl Display for OnnxOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(domain) = &self.domain {
+            write!(f, "{}.", domain)?;
+        }
+        write!(f, "{}-{}", self.op_type, self.since_version)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LayerCoverage {
+    pub layer_index: usize,
+    pub kind: String,
+    // the operators the layer lowers to, empty for yolo layers, which are
+    // exported as raw outputs for the post-processing
+    pub ops: Vec<OnnxOp>,
+    // the reasons the layer cannot be lowered
+    pub unsupported: Vec<String>,
+}
+
+impl LayerCoverage {
+    pub fn is_supported(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OnnxCoverage {
+    pub layers: Vec<LayerCoverage>,
+}
+
+impl OnnxCoverage {
+    pub fn is_supported(&self) -> bool {
+        self.layers.iter().all(|layer| layer.is_supported())
+    }
+
+    // the lowest ai.onnx opset that provides all operators of the supported layers
+    pub fn min_opset(&self) -> u64 {
+        self.layers
+            .iter()
+            .flat_map(|layer| &layer.ops)
+            .filter(|op| op.domain.is_none())
+            .map(|op| op.since_version)
+            .max()
+            .unwrap_or(1)
+    }
+
+    pub fn unsupported_layers(&self) -> Vec<UnsupportedLayer> {
+        self.layers
+            .iter()
+            .flat_map(|layer| {
+                layer
+                    .unsupported
+                    .iter()
+                    .map(move |reason| UnsupportedLayer {
+                        layer_index: layer.layer_index,
+                        kind: layer.kind.clone(),
+                        reason: reason.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+impl Display for OnnxCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for layer in &self.layers {
+            write!(f, "layer {} ({}):", layer.layer_index, layer.kind)?;
+            if layer.is_supported() {
+                writeln!(f, " {}", layer.ops.iter().join(", "))?;
+            } else {
+                writeln!(f, " unsupported, {}", layer.unsupported.join(", "))?;
+            }
+        }
+        write!(f, "minimum opset: {}", self.min_opset())
+    }
+}
+
+// list the ONNX operators each layer maps to, so that the config can be fixed
+// before attempting the export
+pub fn coverage(model: &ModelBase) -> OnnxCoverage {
+    coverage_with_options(model, &ExportOptions::default())
+}
+
+pub fn coverage_with_options(model: &ModelBase, options: &ExportOptions) -> OnnxCoverage {
+    let lowering = options.activation_lowering;
+    let layers = model
+        .layers
+        .iter()
+        .sorted_by_key(|(layer_index, _)| **layer_index)
+        .map(|(&layer_index, layer)| {
+            let mut ops = vec![];
+            let mut unsupported = vec![];
+
+            match layer {
+                LayerBase::Convolutional(ConvolutionalLayerBase { config, .. }) => {
+                    ops.push(OnnxOp::new("Conv", 1));
+                    if config.batch_normalize {
+                        ops.push(OnnxOp::new("BatchNormalization", 9));
+                    }
+                    push_activation(&mut ops, &mut unsupported, lowering, config.activation);
+                    if config.antialiasing {
+                        unsupported.push("antialiasing is not supported".into());
+                    }
+                    if config.deform != Deform::None {
+                        unsupported.push("deformable kernels are not supported".into());
+                    }
+                    if config.binary || config.xnor {
+                        unsupported.push("binary weights are not supported".into());
+                    }
+                }
+                LayerBase::Connected(ConnectedLayerBase { config, .. }) => {
+                    ops.push(OnnxOp::new("Flatten", 1));
+                    ops.push(OnnxOp::new("Gemm", 7));
+                    if config.batch_normalize {
+                        ops.push(OnnxOp::new("BatchNormalization", 9));
+                    }
+                    push_activation(&mut ops, &mut unsupported, lowering, config.activation);
+                }
+                LayerBase::Route(RouteLayerBase { config, .. }) => {
+                    if config.layers.len() > 1 {
+                        ops.push(OnnxOp::new("Concat", 4));
+                    }
+                    if config.group.num_groups() > 1 {
+                        ops.push(OnnxOp::new("Slice", 10));
+                    } else if config.layers.len() == 1 {
+                        ops.push(OnnxOp::new("Identity", 1));
+                    }
+                }
+                LayerBase::Shortcut(ShortcutLayerBase {
+                    config,
+                    input_shape,
+                    ..
+                }) => {
+                    ops.push(OnnxOp::new("Add", 7));
+                    push_activation(&mut ops, &mut unsupported, lowering, config.activation);
+                    if config.weights_type != WeightsType::None {
+                        unsupported.push("weighted shortcut is not supported".into());
+                    }
+                    if !input_shape.iter().all_equal() {
+                        unsupported.push("shortcut of different shapes is not supported".into());
+                    }
+                }
+                LayerBase::MaxPool(MaxPoolLayerBase { config, .. }) => {
+                    ops.push(OnnxOp::new("MaxPool", 8));
+                    if config.maxpool_depth {
+                        unsupported.push("maxpool_depth is not supported".into());
+                    }
+                    if config.antialiasing {
+                        unsupported.push("antialiasing is not supported".into());
+                    }
+                }
+                LayerBase::UpSample(UpSampleLayerBase { config, .. }) => {
+                    ops.push(OnnxOp::new("Resize", 11));
+                    if config.reverse {
+                        unsupported.push("reverse upsampling is not supported".into());
+                    }
+                }
+                LayerBase::BatchNorm(_) => {
+                    ops.push(OnnxOp::new("BatchNormalization", 9));
+                }
+                // the learned tensor becomes an initializer
+                LayerBase::Implicit(_) => {
+                    ops.push(OnnxOp::new("Constant", 1));
+                }
+                LayerBase::AvgPool(_) => {
+                    ops.push(OnnxOp::new("GlobalAveragePool", 1));
+                }
+                LayerBase::ScaleChannels(ScaleChannelsLayerBase { config, .. }) => {
+                    ops.push(OnnxOp::new("Mul", 7));
+                    push_activation(&mut ops, &mut unsupported, lowering, config.activation);
+                    if config.scale_wh {
+                        unsupported.push("scale_wh is not supported".into());
+                    }
+                }
+                // dropout is a no-op in inference
+                LayerBase::Dropout(_) => {
+                    ops.push(OnnxOp::new("Identity", 1));
+                }
+                LayerBase::Softmax(SoftmaxLayerBase { config, .. }) => {
+                    if config.tree.is_some() {
+                        unsupported.push("softmax over a class tree is not supported".into());
+                    }
+                    if config.temperature != 1.0 {
+                        ops.push(OnnxOp::new("Div", 7));
+                    }
+                    // before opset 13 the input is coerced to 2D at the axis, which
+                    // is the flattened softmax of darknet
+                    if config.groups > 1 {
+                        ops.push(OnnxOp::new("Reshape", 5));
+                        ops.push(OnnxOp::new("Softmax", 13));
+                        ops.push(OnnxOp::new("Reshape", 5));
+                    } else if config.spatial {
+                        ops.push(OnnxOp::new("Softmax", 13));
+                    } else {
+                        ops.push(OnnxOp::new("Softmax", 1));
+                    }
+                }
+                LayerBase::Region(_) => {
+                    unsupported.push("region layers are not supported".into());
+                }
+                LayerBase::GaussianYolo(_) => {
+                    unsupported.push("Gaussian_yolo layers are not supported".into());
+                }
+                LayerBase::Reorg(_) => {
+                    unsupported.push("reorg layers are not supported".into());
+                }
+                LayerBase::Local(_) => {
+                    unsupported.push("local layers are not supported".into());
+                }
+                LayerBase::LocalAvgPool(_) => {
+                    unsupported.push("local_avgpool layers are not supported".into());
+                }
+                LayerBase::Sam(_) => {
+                    unsupported.push("sam layers are not supported".into());
+                }
+                LayerBase::Lstm(_) => {
+                    unsupported.push("lstm layers are not supported".into());
+                }
+                LayerBase::Crnn(_) => {
+                    unsupported.push("crnn layers are not supported".into());
+                }
+                LayerBase::ConvLstm(_) => {
+                    unsupported.push("conv_lstm layers are not supported".into());
+                }
+                LayerBase::Custom(CustomLayerBase { config, .. }) => {
+                    unsupported.push(format!(
+                        "custom [{}] layers are not supported",
+                        config.section
+                    ));
+                }
+                LayerBase::Yolo(_) => (),
+            }
+
+            LayerCoverage {
+                layer_index,
+                kind: layer.kind().into(),
+                ops,
+                unsupported,
+            }
+        })
+        .collect();
+
+    OnnxCoverage { layers }
+}
+
+fn push_activation(
+    ops: &mut Vec<OnnxOp>,
+    unsupported: &mut Vec<String>,
+    lowering: ActivationLowering,
+    activation: Activation,
+) {
+    match activation_ops(lowering, activation) {
+        Some(activation_ops) => ops.extend(activation_ops),
+        None => unsupported.push(format!("activation {} is not supported", activation)),
+    }
+}
+
+// the operators computing the activation, None if there is no lowering
+fn activation_ops(lowering: ActivationLowering, activation: Activation) -> Option<Vec<OnnxOp>> {
+    if let (ActivationLowering::Custom, Some(op_type)) = (lowering, custom_op_type(activation)) {
+        return Some(vec![OnnxOp::custom(op_type)]);
+    }
+
+    let ops = match activation {
+        Activation::Linear => vec![],
+        Activation::Relu => vec![OnnxOp::new("Relu", 6)],
+        Activation::Leaky => vec![OnnxOp::new("LeakyRelu", 6)],
+        Activation::Logistic => vec![OnnxOp::new("Sigmoid", 6)],
+        Activation::Tanh => vec![OnnxOp::new("Tanh", 6)],
+        Activation::Elu => vec![OnnxOp::new("Elu", 6)],
+        Activation::Selu => vec![OnnxOp::new("Selu", 6)],
+        Activation::Gelu => vec![OnnxOp::new("Gelu", 20)],
+        Activation::Mish => match lowering {
+            ActivationLowering::Native => vec![OnnxOp::new("Mish", 18)],
+            _ => vec![
+                OnnxOp::new("Softplus", 1),
+                OnnxOp::new("Tanh", 6),
+                OnnxOp::new("Mul", 7),
+            ],
+        },
+        Activation::HardMish => vec![OnnxOp::new("HardSigmoid", 6), OnnxOp::new("Mul", 7)],
+        Activation::Swish => vec![OnnxOp::new("Sigmoid", 6), OnnxOp::new("Mul", 7)],
+        Activation::Hardtan => vec![OnnxOp::new("Clip", 11)],
+        Activation::NormalizeChannels
+        | Activation::NormalizeChannelsSoftmax
+        | Activation::NormalizeChannelsSoftmaxMaxval
+        | Activation::Loggy
+        | Activation::Relie
+        | Activation::Ramp
+        | Activation::Plse
+        | Activation::Stair
+        | Activation::Lhtan => return None,
+    };
+    Some(ops)
+}
@@ -0,0 +1,819 @@
+use super::{
+    blob_name, conv_weights, custom_op_type, find_unsupported_layers, layer_name, maxpool_padding,
+    yolo_output, ActivationLowering, ExportOptions, UnsupportedLayer, BATCH_NORM_EPSILON,
+    CUSTOM_OP_DOMAIN,
+};
+use crate::{
+    common::*,
+    config::{
+        Activation, ConnectedConfig, ConvolutionalConfig, MaxPoolConfig, Shape, ShortcutConfig,
+        UpSampleConfig,
+    },
+    darknet::{
+        BatchNormLayer, BatchNormWeights, ConnectedLayer, ConnectedWeights, ConvolutionalLayer,
+        DarknetModel, Layer, MaxPoolLayer, RouteLayer, ScaleWeights, ShortcutLayer, UpSampleLayer,
+        YoloLayer,
+    },
+    model::{
+        ConnectedLayerBase, ConvolutionalLayerBase, LayerPosition, MaxPoolLayerBase, ModelBase,
+        RouteLayerBase, ShortcutLayerBase, UpSampleLayerBase,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenVinoIr {
+    pub xml: String,
+    pub bin: Vec<u8>,
+}
+
+impl OpenVinoIr {
+    // the weights are saved to the .bin file next to the .xml file
+    pub fn save<P>(&self, xml_file: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let xml_file = xml_file.as_ref();
+        fs::write(xml_file, &self.xml)?;
+        fs::write(xml_file.with_extension("bin"), &self.bin)?;
+        Ok(())
+    }
+}
+
+impl DarknetModel {
+    pub fn save_openvino<P>(&self, xml_file: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.save_openvino_with_options(xml_file, &ExportOptions::default())
+    }
+
+    pub fn save_openvino_with_options<P>(&self, xml_file: P, options: &ExportOptions) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let xml_file = xml_file.as_ref();
+        let name = xml_file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("darknet");
+        to_openvino_with_options(self, name, options)?.save(xml_file)
+    }
+}
+
+pub fn unsupported_layers(model: &ModelBase) -> Vec<UnsupportedLayer> {
+    find_unsupported_layers(model, is_supported_activation)
+}
+
+pub fn to_openvino(model: &DarknetModel, name: &str) -> Result<OpenVinoIr> {
+    to_openvino_with_options(model, name, &ExportOptions::default())
+}
+
+pub fn to_openvino_with_options(
+    model: &DarknetModel,
+    name: &str,
+    options: &ExportOptions,
+) -> Result<OpenVinoIr> {
+    let unsupported = unsupported_layers(&model.base);
+    ensure!(
+        unsupported.is_empty(),
+        "the model cannot be exported to OpenVINO IR:\n{}",
+        unsupported.iter().join("\n")
+    );
+
+    let mut builder = IrBuilder {
+        options: options.clone(),
+        ..Default::default()
+    };
+    let input_dims = nchw_dims(model.base.net.input_size);
+    let input_port = builder
+        .layer(
+            &blob_name(&model.base, LayerPosition::Input),
+            "Parameter",
+            vec![
+                ("shape", input_dims.iter().join(",")),
+                ("element_type", "f32".into()),
+            ],
+            &[],
+            "FP32",
+            vec![input_dims.clone()],
+        )
+        .remove(0);
+
+    let mut ports: IndexMap<usize, Port> = IndexMap::new();
+    model.layers.iter().try_for_each(|(&layer_index, layer)| {
+        let port = {
+            let get_port = |position: LayerPosition| match position {
+                LayerPosition::Input => input_port.clone(),
+                LayerPosition::Absolute(index) => ports[&index].clone(),
+            };
+            builder.push_layer(model, layer_index, layer, get_port)?
+        };
+        ports.insert(layer_index, port);
+        Ok::<_, Error>(())
+    })?;
+
+    let IrBuilder {
+        layers, edges, bin, ..
+    } = builder;
+    let edges = edges
+        .into_iter()
+        .map(|(from_layer, from_port, to_layer, to_port)| {
+            format!(
+                "    <edge from-layer=\"{}\" from-port=\"{}\" to-layer=\"{}\" to-port=\"{}\"/>\n",
+                from_layer, from_port, to_layer, to_port
+            )
+        })
+        .join("");
+    let xml = format!(
+        "<?xml version=\"1.0\"?>\n<net name=\"{}\" version=\"10\">\n  <layers>\n{}  </layers>\n  <edges>\n{}  </edges>\n</net>\n",
+        name,
+        layers.join(""),
+        edges
+    );
+
+    Ok(OpenVinoIr { xml, bin })
+}
+
+fn is_supported_activation(activation: Activation) -> bool {
+    matches!(
+        activation,
+        Activation::Linear
+            | Activation::Relu
+            | Activation::Leaky
+            | Activation::Logistic
+            | Activation::Tanh
+            | Activation::Elu
+            | Activation::Mish
+            | Activation::HardMish
+            | Activation::Swish
+    )
+}
+
+fn nchw_dims(shape: Shape) -> Vec<u64> {
+    match shape {
+        Shape::Hwc([h, w, c]) => vec![1, c, h, w],
+        Shape::Flat(size) => vec![1, size],
+    }
+}
+
+// fold the batch normalization into per-channel scales and offsets
+fn fold_batch_norm(
+    biases: &Array1<f32>,
+    scales: &Array1<f32>,
+    rolling_mean: &Array1<f32>,
+    rolling_variance: &Array1<f32>,
+) -> (Vec<f32>, Vec<f32>) {
+    izip!(biases, scales, rolling_mean, rolling_variance)
+        .map(|(&bias, &scale, &mean, &variance)| {
+            let factor = scale / (variance + BATCH_NORM_EPSILON).sqrt();
+            (factor, bias - mean * factor)
+        })
+        .unzip()
+}
+
+// fold optional batch normalization into weights and biases, where
+// weights are laid out as [out_channels, ...]
+fn fold_weights(
+    biases: &Array1<f32>,
+    weights: &[f32],
+    scales: &Option<ScaleWeights>,
+) -> (Vec<f32>, Vec<f32>) {
+    match scales {
+        Some(ScaleWeights {
+            scales,
+            rolling_mean,
+            rolling_variance,
+        }) => {
+            let (factors, offsets) =
+                fold_batch_norm(biases, scales, rolling_mean, rolling_variance);
+            let chunk_size = weights.len() / factors.len();
+            let weights = weights
+                .chunks(chunk_size)
+                .zip(factors.iter())
+                .flat_map(|(chunk, &factor)| chunk.iter().map(move |&value| value * factor))
+                .collect();
+            (weights, offsets)
+        }
+        None => (weights.to_vec(), biases.to_vec()),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Port {
+    layer_id: usize,
+    port_id: usize,
+    dims: Vec<u64>,
+}
+
+#[derive(Debug, Default)]
+struct IrBuilder {
+    options: ExportOptions,
+    layers: Vec<String>,
+    edges: Vec<(usize, usize, usize, usize)>,
+    bin: Vec<u8>,
+}
+
+impl IrBuilder {
+    fn layer(
+        &mut self,
+        name: &str,
+        kind: &str,
+        data: Vec<(&str, String)>,
+        inputs: &[&Port],
+        precision: &str,
+        outputs: Vec<Vec<u64>>,
+    ) -> Vec<Port> {
+        let version = match kind {
+            "Mish" | "Swish" | "SoftPlus" => "opset4",
+            _ => "opset1",
+        };
+        self.layer_with_version(name, kind, version, data, inputs, precision, outputs)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn layer_with_version(
+        &mut self,
+        name: &str,
+        kind: &str,
+        version: &str,
+        data: Vec<(&str, String)>,
+        inputs: &[&Port],
+        precision: &str,
+        outputs: Vec<Vec<u64>>,
+    ) -> Vec<Port> {
+        let layer_id = self.layers.len();
+        let dims_xml = |dims: &[u64]| {
+            dims.iter()
+                .map(|dim| format!("<dim>{}</dim>", dim))
+                .join("")
+        };
+
+        let mut xml = format!(
+            "    <layer id=\"{}\" name=\"{}\" type=\"{}\" version=\"{}\">\n",
+            layer_id, name, kind, version
+        );
+        if !data.is_empty() {
+            let attributes = data
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, value))
+                .join(" ");
+            xml += &format!("      <data {}/>\n", attributes);
+        }
+        if !inputs.is_empty() {
+            xml += "      <input>\n";
+            inputs.iter().enumerate().for_each(|(port_id, port)| {
+                xml += &format!(
+                    "        <port id=\"{}\">{}</port>\n",
+                    port_id,
+                    dims_xml(&port.dims)
+                );
+                self.edges
+                    .push((port.layer_id, port.port_id, layer_id, port_id));
+            });
+            xml += "      </input>\n";
+        }
+
+        let ports: Vec<_> = outputs
+            .into_iter()
+            .enumerate()
+            .map(|(nth, dims)| Port {
+                layer_id,
+                port_id: inputs.len() + nth,
+                dims,
+            })
+            .collect();
+        if !ports.is_empty() {
+            xml += "      <output>\n";
+            ports.iter().for_each(|port| {
+                xml += &format!(
+                    "        <port id=\"{}\" precision=\"{}\">{}</port>\n",
+                    port.port_id,
+                    precision,
+                    dims_xml(&port.dims)
+                );
+            });
+            xml += "      </output>\n";
+        }
+        xml += "    </layer>\n";
+
+        self.layers.push(xml);
+        ports
+    }
+
+    fn op(
+        &mut self,
+        name: &str,
+        kind: &str,
+        data: Vec<(&str, String)>,
+        inputs: &[&Port],
+        output: Vec<u64>,
+    ) -> Port {
+        self.layer(name, kind, data, inputs, "FP32", vec![output])
+            .remove(0)
+    }
+
+    fn constant(&mut self, name: &str, element_type: &str, bytes: Vec<u8>, dims: Vec<u64>) -> Port {
+        let offset = self.bin.len();
+        let size = bytes.len();
+        self.bin.extend(bytes);
+        let precision = match element_type {
+            "i64" => "I64",
+            _ => "FP32",
+        };
+        self.layer(
+            name,
+            "Const",
+            vec![
+                ("element_type", element_type.into()),
+                ("shape", dims.iter().join(",")),
+                ("offset", offset.to_string()),
+                ("size", size.to_string()),
+            ],
+            &[],
+            precision,
+            vec![dims],
+        )
+        .remove(0)
+    }
+
+    fn const_f32(&mut self, name: &str, values: &[f32], dims: Vec<u64>) -> Port {
+        let bytes = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect();
+        self.constant(name, "f32", bytes, dims)
+    }
+
+    fn const_i64(&mut self, name: &str, values: &[i64], dims: Vec<u64>) -> Port {
+        let bytes = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect();
+        self.constant(name, "i64", bytes, dims)
+    }
+
+    fn push_layer(
+        &mut self,
+        model: &DarknetModel,
+        layer_index: usize,
+        layer: &Layer,
+        get_port: impl Fn(LayerPosition) -> Port,
+    ) -> Result<Port> {
+        let layer_base = &model.base.layers[&layer_index];
+        let name = layer_name(layer_index, layer_base);
+        let output_dims = nchw_dims(layer_base.output_shape());
+
+        let port = match layer {
+            Layer::Convolutional(layer) => {
+                let ConvolutionalLayer {
+                    base:
+                        ConvolutionalLayerBase {
+                            config:
+                                ConvolutionalConfig {
+                                    filters,
+                                    groups,
+                                    size,
+                                    stride_x,
+                                    stride_y,
+                                    dilation,
+                                    padding,
+                                    activation,
+                                    ..
+                                },
+                            from_indexes,
+                            input_shape: [_h, _w, in_c],
+                            ..
+                        },
+                    ref weights,
+                } = *layer;
+
+                let (biases, weights, scales) = conv_weights(model, weights)
+                    .ok_or_else(|| format_err!("{}: invalid shared weights layer", name))?;
+                let (weights, biases) = fold_weights(biases, weights.as_slice().unwrap(), scales);
+
+                let weights_dims = if groups == 1 {
+                    vec![filters, in_c, size, size]
+                } else {
+                    vec![groups, filters / groups, in_c / groups, size, size]
+                };
+                let weights = self.const_f32(&format!("{}/weights", name), &weights, weights_dims);
+                let biases =
+                    self.const_f32(&format!("{}/biases", name), &biases, vec![1, filters, 1, 1]);
+
+                let input = get_port(from_indexes);
+                let conv = self.op(
+                    &format!("{}/conv", name),
+                    if groups == 1 {
+                        "Convolution"
+                    } else {
+                        "GroupConvolution"
+                    },
+                    vec![
+                        ("strides", format!("{},{}", stride_y, stride_x)),
+                        ("dilations", format!("{},{}", dilation, dilation)),
+                        ("pads_begin", format!("{},{}", padding, padding)),
+                        ("pads_end", format!("{},{}", padding, padding)),
+                        ("auto_pad", "explicit".into()),
+                    ],
+                    &[&input, &weights],
+                    output_dims.clone(),
+                );
+                let add = self.op(
+                    &pre_activation_name(&name, activation),
+                    "Add",
+                    vec![("auto_broadcast", "numpy".into())],
+                    &[&conv, &biases],
+                    output_dims,
+                );
+                self.push_activation(&name, add, activation)
+            }
+            Layer::Connected(layer) => {
+                let ConnectedLayer {
+                    base:
+                        ConnectedLayerBase {
+                            config: ConnectedConfig { activation, .. },
+                            from_indexes,
+                            input_shape,
+                            output_shape,
+                        },
+                    weights:
+                        ConnectedWeights {
+                            ref biases,
+                            ref weights,
+                            ref scales,
+                        },
+                } = *layer;
+
+                ensure!(
+                    biases.len() as u64 == output_shape,
+                    "{}: the bias size does not match the output size",
+                    name
+                );
+
+                // darknet flattens feature maps in CHW order
+                let input = get_port(from_indexes);
+                let input = if input.dims.len() > 2 {
+                    let shape = self.const_i64(
+                        &format!("{}/shape", name),
+                        &[1, input_shape as i64],
+                        vec![2],
+                    );
+                    self.op(
+                        &format!("{}/flatten", name),
+                        "Reshape",
+                        vec![("special_zero", "false".into())],
+                        &[&input, &shape],
+                        vec![1, input_shape],
+                    )
+                } else {
+                    input
+                };
+
+                let (weights, biases) = fold_weights(biases, weights.as_slice().unwrap(), scales);
+                let weights = self.const_f32(
+                    &format!("{}/weights", name),
+                    &weights,
+                    vec![output_shape, input_shape],
+                );
+                let biases =
+                    self.const_f32(&format!("{}/biases", name), &biases, vec![1, output_shape]);
+
+                let matmul = self.op(
+                    &format!("{}/matmul", name),
+                    "MatMul",
+                    vec![
+                        ("transpose_a", "false".into()),
+                        ("transpose_b", "true".into()),
+                    ],
+                    &[&input, &weights],
+                    output_dims.clone(),
+                );
+                let add = self.op(
+                    &pre_activation_name(&name, activation),
+                    "Add",
+                    vec![("auto_broadcast", "numpy".into())],
+                    &[&matmul, &biases],
+                    output_dims,
+                );
+                self.push_activation(&name, add, activation)
+            }
+            Layer::BatchNorm(layer) => {
+                let BatchNormLayer {
+                    ref base,
+                    weights:
+                        BatchNormWeights {
+                            ref biases,
+                            ref scales,
+                            ref rolling_mean,
+                            ref rolling_variance,
+                        },
+                } = *layer;
+                let channels = scales.len() as u64;
+
+                let (factors, offsets) =
+                    fold_batch_norm(biases, scales, rolling_mean, rolling_variance);
+                let factors = self.const_f32(
+                    &format!("{}/scales", name),
+                    &factors,
+                    vec![1, channels, 1, 1],
+                );
+                let offsets = self.const_f32(
+                    &format!("{}/offsets", name),
+                    &offsets,
+                    vec![1, channels, 1, 1],
+                );
+
+                let input = get_port(base.from_indexes);
+                let mul = self.op(
+                    &format!("{}/mul", name),
+                    "Multiply",
+                    vec![("auto_broadcast", "numpy".into())],
+                    &[&input, &factors],
+                    output_dims.clone(),
+                );
+                self.op(
+                    &name,
+                    "Add",
+                    vec![("auto_broadcast", "numpy".into())],
+                    &[&mul, &offsets],
+                    output_dims,
+                )
+            }
+            Layer::Shortcut(layer) => {
+                let ShortcutLayer {
+                    base:
+                        ShortcutLayerBase {
+                            config: ShortcutConfig { activation, .. },
+                            ref from_indexes,
+                            ..
+                        },
+                    ..
+                } = *layer;
+
+                let mut inputs = from_indexes.iter().map(|&position| get_port(position));
+                let first = inputs
+                    .next()
+                    .ok_or_else(|| format_err!("{}: the shortcut has no inputs", name))?;
+                let num_adds = from_indexes.len() - 1;
+                let sum = inputs.enumerate().fold(first, |sum, (nth, input)| {
+                    let add_name = if nth + 1 == num_adds {
+                        pre_activation_name(&name, activation)
+                    } else {
+                        format!("{}/add_{}", name, nth)
+                    };
+                    self.op(
+                        &add_name,
+                        "Add",
+                        vec![("auto_broadcast", "numpy".into())],
+                        &[&sum, &input],
+                        output_dims.clone(),
+                    )
+                });
+                self.push_activation(&name, sum, activation)
+            }
+            Layer::MaxPool(layer) => {
+                let MaxPoolLayer {
+                    base:
+                        MaxPoolLayerBase {
+                            config:
+                                MaxPoolConfig {
+                                    stride_x,
+                                    stride_y,
+                                    size,
+                                    padding,
+                                    ..
+                                },
+                            from_indexes,
+                            ..
+                        },
+                } = *layer;
+
+                let [pad_begin, pad_end] = maxpool_padding(padding);
+                let input = get_port(from_indexes);
+                self.op(
+                    &name,
+                    "MaxPool",
+                    vec![
+                        ("strides", format!("{},{}", stride_y, stride_x)),
+                        ("pads_begin", format!("{},{}", pad_begin, pad_begin)),
+                        ("pads_end", format!("{},{}", pad_end, pad_end)),
+                        ("kernel", format!("{},{}", size, size)),
+                        ("rounding_type", "floor".into()),
+                        ("auto_pad", "explicit".into()),
+                    ],
+                    &[&input],
+                    output_dims,
+                )
+            }
+            Layer::Route(layer) => {
+                let RouteLayer {
+                    base:
+                        RouteLayerBase {
+                            ref config,
+                            ref from_indexes,
+                            ..
+                        },
+                } = *layer;
+                let group_id = config.group.group_id() as usize;
+                let num_groups = config.group.num_groups();
+
+                // take the channel group of each input, then concatenate them
+                let inputs: Vec<_> = from_indexes
+                    .iter()
+                    .enumerate()
+                    .map(|(nth, &position)| {
+                        let input = get_port(position);
+                        if num_groups == 1 {
+                            return input;
+                        }
+
+                        let axis = self.const_i64(&format!("{}/axis_{}", name, nth), &[1], vec![]);
+                        let split_dims = {
+                            let mut dims = input.dims.clone();
+                            dims[1] /= num_groups;
+                            dims
+                        };
+                        self.layer(
+                            &format!("{}/split_{}", name, nth),
+                            "Split",
+                            vec![("num_splits", num_groups.to_string())],
+                            &[&input, &axis],
+                            "FP32",
+                            vec![split_dims; num_groups as usize],
+                        )
+                        .remove(group_id)
+                    })
+                    .collect();
+
+                if inputs.len() == 1 {
+                    inputs.into_iter().next().unwrap()
+                } else {
+                    let inputs: Vec<_> = inputs.iter().collect();
+                    self.op(
+                        &name,
+                        "Concat",
+                        vec![("axis", "1".into())],
+                        &inputs,
+                        output_dims,
+                    )
+                }
+            }
+            Layer::UpSample(layer) => {
+                let UpSampleLayer {
+                    base:
+                        UpSampleLayerBase {
+                            config: UpSampleConfig { .. },
+                            from_indexes,
+                            output_shape: [out_h, out_w, _c],
+                            ..
+                        },
+                } = *layer;
+
+                let input = get_port(from_indexes);
+                let target_shape = self.const_i64(
+                    &format!("{}/target_shape", name),
+                    &[out_h as i64, out_w as i64],
+                    vec![2],
+                );
+                self.op(
+                    &name,
+                    "Interpolate",
+                    vec![
+                        ("axes", "2,3".into()),
+                        ("mode", "nearest".into()),
+                        ("align_corners", "0".into()),
+                        ("antialias", "0".into()),
+                        ("pads_begin", "0".into()),
+                        ("pads_end", "0".into()),
+                    ],
+                    &[&input, &target_shape],
+                    output_dims,
+                )
+            }
+            Layer::Yolo(YoloLayer { base }) => {
+                let input = get_port(yolo_output(base));
+                self.layer(&name, "Result", vec![], &[&input], "FP32", vec![]);
+                input
+            }
+            Layer::Implicit(_)
+            | Layer::AvgPool(_)
+            | Layer::ScaleChannels(_)
+            | Layer::Dropout(_)
+            | Layer::Softmax(_)
+            | Layer::Region(_)
+            | Layer::ConvLstm(_)
+            | Layer::Crnn(_)
+            | Layer::Lstm(_)
+            | Layer::Sam(_)
+            | Layer::LocalAvgPool(_)
+            | Layer::Local(_)
+            | Layer::Reorg(_)
+            | Layer::GaussianYolo(_)
+            | Layer::Custom(_) => unreachable!("please report bug"),
+        };
+
+        Ok(port)
+    }
+
+    fn push_activation(&mut self, name: &str, input: Port, activation: Activation) -> Port {
+        let dims = input.dims.clone();
+        match activation {
+            Activation::Linear => input,
+            Activation::Relu => self.op(name, "Relu", vec![], &[&input], dims),
+            Activation::Logistic => self.op(name, "Sigmoid", vec![], &[&input], dims),
+            Activation::Tanh => self.op(name, "Tanh", vec![], &[&input], dims),
+            Activation::Mish | Activation::HardMish | Activation::Swish => {
+                self.push_lowered_activation(name, input, activation)
+            }
+            Activation::Elu => self.op(name, "Elu", vec![("alpha", "1".into())], &[&input], dims),
+            Activation::Leaky => {
+                let slope = self.const_f32(&format!("{}/slope", name), &[0.1], vec![1]);
+                self.op(name, "PRelu", vec![], &[&input, &slope], dims)
+            }
+            _ => unreachable!("please report bug"),
+        }
+    }
+
+    fn push_lowered_activation(&mut self, name: &str, input: Port, activation: Activation) -> Port {
+        let dims = input.dims.clone();
+        let multiply = |builder: &mut Self, rhs: Port| {
+            builder.op(
+                name,
+                "Multiply",
+                vec![("auto_broadcast", "numpy".into())],
+                &[&input, &rhs],
+                dims.clone(),
+            )
+        };
+
+        match (self.options.activation_lowering, activation) {
+            (ActivationLowering::Custom, _) => self
+                .layer_with_version(
+                    name,
+                    custom_op_type(activation).unwrap(),
+                    CUSTOM_OP_DOMAIN,
+                    vec![],
+                    &[&input],
+                    "FP32",
+                    vec![dims.clone()],
+                )
+                .remove(0),
+            (ActivationLowering::Native, Activation::Mish) => {
+                self.op(name, "Mish", vec![], &[&input], dims.clone())
+            }
+            (ActivationLowering::Native, Activation::Swish) => {
+                self.op(name, "Swish", vec![], &[&input], dims.clone())
+            }
+            // mish(x) = x * tanh(softplus(x))
+            (_, Activation::Mish) => {
+                let softplus = self.op(
+                    &format!("{}/softplus", name),
+                    "SoftPlus",
+                    vec![],
+                    &[&input],
+                    dims.clone(),
+                );
+                let tanh = self.op(
+                    &format!("{}/tanh", name),
+                    "Tanh",
+                    vec![],
+                    &[&softplus],
+                    dims.clone(),
+                );
+                multiply(self, tanh)
+            }
+            // swish(x) = x * sigmoid(x)
+            (_, Activation::Swish) => {
+                let sigmoid = self.op(
+                    &format!("{}/sigmoid", name),
+                    "Sigmoid",
+                    vec![],
+                    &[&input],
+                    dims.clone(),
+                );
+                multiply(self, sigmoid)
+            }
+            // hard_mish(x) = x * min(max(x / 2 + 1, 0), 1), see activate() in darknet
+            (_, Activation::HardMish) => {
+                let alpha = self.const_f32(&format!("{}/alpha", name), &[0.5], vec![]);
+                let beta = self.const_f32(&format!("{}/beta", name), &[1.0], vec![]);
+                let hard_sigmoid = self.op(
+                    &format!("{}/hard_sigmoid", name),
+                    "HardSigmoid",
+                    vec![],
+                    &[&input, &alpha, &beta],
+                    dims.clone(),
+                );
+                multiply(self, hard_sigmoid)
+            }
+            _ => unreachable!("please report bug"),
+        }
+    }
+}
+
+fn pre_activation_name(name: &str, activation: Activation) -> String {
+    match activation {
+        Activation::Linear => name.into(),
+        _ => format!("{}/pre", name),
+    }
+}
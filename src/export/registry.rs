@@ -0,0 +1,181 @@
+use super::{safetensors::to_safetensors, ExportOptions, UnsupportedLayer};
+use crate::{common::*, darknet::DarknetModel, model::ModelBase};
+use std::sync::OnceLock;
+
+// a target format, implemented by this crate or plugged in by others through
+// register_exporter()
+pub trait Exporter: Send + Sync {
+    // the unique name, such as "safetensors"
+    fn name(&self) -> &str;
+
+    // the file extension without the dot
+    fn extension(&self) -> &str;
+
+    // the layers that the format cannot express
+    fn unsupported_layers(&self, _model: &ModelBase) -> Vec<UnsupportedLayer> {
+        vec![]
+    }
+
+    fn export(
+        &self,
+        model: &DarknetModel,
+        options: &ExportOptions,
+        sink: &mut dyn Write,
+    ) -> Result<()>;
+}
+
+#[derive(Default)]
+pub struct ExporterRegistry {
+    exporters: IndexMap<String, Arc<dyn Exporter>>,
+}
+
+impl ExporterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the single-file formats of this crate. OpenVINO IR is left out as it
+    // consists of two files.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(SafetensorsExporter)).unwrap();
+        #[cfg(feature = "coreml")]
+        registry.register(Arc::new(CoremlExporter)).unwrap();
+        registry
+    }
+
+    pub fn register(&mut self, exporter: Arc<dyn Exporter>) -> Result<()> {
+        let name = exporter.name().to_owned();
+        ensure!(
+            !self.exporters.contains_key(&name),
+            "the exporter '{}' is already registered",
+            name
+        );
+        self.exporters.insert(name, exporter);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Exporter>> {
+        self.exporters.get(name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.exporters.keys().cloned().collect()
+    }
+
+    // check the layers and export the model with the named exporter
+    pub fn export(
+        &self,
+        name: &str,
+        model: &DarknetModel,
+        options: &ExportOptions,
+        sink: &mut dyn Write,
+    ) -> Result<()> {
+        let exporter = self
+            .get(name)
+            .ok_or_else(|| format_err!("no exporter named '{}' is registered", name))?;
+        checked_export(&*exporter, model, options, sink)
+    }
+}
+
+impl Debug for ExporterRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExporterRegistry")
+            .field("exporters", &self.names())
+            .finish()
+    }
+}
+
+// the process-wide registry, starting with the built-in exporters
+fn global_registry() -> &'static Mutex<ExporterRegistry> {
+    static REGISTRY: OnceLock<Mutex<ExporterRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(ExporterRegistry::with_builtins()))
+}
+
+pub fn register_exporter(exporter: Arc<dyn Exporter>) -> Result<()> {
+    global_registry().lock().unwrap().register(exporter)
+}
+
+pub fn exporter(name: &str) -> Option<Arc<dyn Exporter>> {
+    global_registry().lock().unwrap().get(name)
+}
+
+pub fn exporter_names() -> Vec<String> {
+    global_registry().lock().unwrap().names()
+}
+
+pub fn export(
+    name: &str,
+    model: &DarknetModel,
+    options: &ExportOptions,
+    sink: &mut dyn Write,
+) -> Result<()> {
+    // the lock is released before exporting, which may take a while
+    let exporter =
+        exporter(name).ok_or_else(|| format_err!("no exporter named '{}' is registered", name))?;
+    checked_export(&*exporter, model, options, sink)
+}
+
+fn checked_export(
+    exporter: &dyn Exporter,
+    model: &DarknetModel,
+    options: &ExportOptions,
+    sink: &mut dyn Write,
+) -> Result<()> {
+    let unsupported = exporter.unsupported_layers(&model.base);
+    ensure!(
+        unsupported.is_empty(),
+        "the model cannot be exported by '{}':\n{}",
+        exporter.name(),
+        unsupported.iter().join("\n")
+    );
+    exporter.export(model, options, sink)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SafetensorsExporter;
+
+impl Exporter for SafetensorsExporter {
+    fn name(&self) -> &str {
+        "safetensors"
+    }
+
+    fn extension(&self) -> &str {
+        "safetensors"
+    }
+
+    fn export(
+        &self,
+        model: &DarknetModel,
+        _options: &ExportOptions,
+        sink: &mut dyn Write,
+    ) -> Result<()> {
+        sink.write_all(&to_safetensors(model)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "coreml")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CoremlExporter;
+
+#[cfg(feature = "coreml")]
+impl Exporter for CoremlExporter {
+    fn name(&self) -> &str {
+        "coreml"
+    }
+
+    fn extension(&self) -> &str {
+        "mlmodel"
+    }
+
+    fn export(
+        &self,
+        model: &DarknetModel,
+        options: &ExportOptions,
+        sink: &mut dyn Write,
+    ) -> Result<()> {
+        sink.write_all(&super::coreml::to_coreml_with_options(model, options)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use super::layer_name;
+use crate::{
+    common::*,
+    darknet::{
+        BatchNormLayer, BatchNormWeights, ConnectedLayer, ConnectedWeights, ConvLstmLayer,
+        ConvLstmWeights, ConvolutionalLayer, ConvolutionalWeights, CrnnLayer, CrnnWeights,
+        DarknetModel, ImplicitLayer, ImplicitWeights, Layer, LocalLayer, LocalWeights, LstmLayer,
+        LstmWeights, RecurrentConvWeights, ScaleWeights, ShortcutLayer, ShortcutWeights,
+    },
+    model::LSTM_GATES,
+    progress::{ProgressObserver, Stage},
+};
+use serde_json::json;
+
+// tensors are named after PyTorch modules, e.g. conv_0.conv.weight and conv_0.bn.running_mean
+pub fn named_tensors(model: &DarknetModel) -> Vec<(String, Vec<u64>, &[f32])> {
+    model
+        .layers
+        .iter()
+        .flat_map(|(&layer_index, layer)| layer_named_tensors(model, layer_index, layer))
+        .collect()
+}
+
+fn layer_named_tensors<'a>(
+    model: &DarknetModel,
+    layer_index: usize,
+    layer: &'a Layer,
+) -> Vec<(String, Vec<u64>, &'a [f32])> {
+    let name = layer_name(layer_index, &model.base.layers[&layer_index]);
+    match layer {
+        Layer::Convolutional(ConvolutionalLayer { base, weights }) => match weights {
+            ConvolutionalWeights::Owned {
+                biases,
+                weights,
+                scales,
+            } => {
+                let [in_c, filters, size, _size] = base.weights_shape();
+                let weights = param(
+                    &name,
+                    "conv.weight",
+                    vec![filters, in_c, size, size],
+                    weights.as_slice().unwrap(),
+                );
+                match scales {
+                    Some(scales) => iter::once(weights)
+                        .chain(batch_norm(&name, biases, scales))
+                        .collect(),
+                    None => vec![weights, vector(&name, "conv.bias", biases)],
+                }
+            }
+            ConvolutionalWeights::Ref { .. } => vec![],
+        },
+        Layer::Connected(ConnectedLayer {
+            base,
+            weights:
+                ConnectedWeights {
+                    biases,
+                    weights,
+                    scales,
+                },
+        }) => {
+            let weights = param(
+                &name,
+                "fc.weight",
+                vec![base.output_shape, base.input_shape],
+                weights.as_slice().unwrap(),
+            );
+            match scales {
+                Some(scales) => iter::once(weights)
+                    .chain(batch_norm(&name, biases, scales))
+                    .collect(),
+                None => vec![weights, vector(&name, "fc.bias", biases)],
+            }
+        }
+        Layer::BatchNorm(BatchNormLayer {
+            weights:
+                BatchNormWeights {
+                    biases,
+                    scales,
+                    rolling_mean,
+                    rolling_variance,
+                },
+            ..
+        }) => vec![
+            vector(&name, "bn.weight", scales),
+            vector(&name, "bn.bias", biases),
+            vector(&name, "bn.running_mean", rolling_mean),
+            vector(&name, "bn.running_var", rolling_variance),
+        ],
+        Layer::Shortcut(ShortcutLayer { weights, .. }) => match weights {
+            ShortcutWeights::None => vec![],
+            ShortcutWeights::PerFeature(weights) => vec![vector(&name, "weight", weights)],
+            ShortcutWeights::PerChannel(weights) => {
+                let (num_inputs, channels) = weights.dim();
+                vec![param(
+                    &name,
+                    "weight",
+                    vec![num_inputs as u64, channels as u64],
+                    weights.as_slice().unwrap(),
+                )]
+            }
+        },
+        Layer::Implicit(ImplicitLayer {
+            weights: ImplicitWeights { weights },
+            ..
+        }) => vec![param(
+            &name,
+            "implicit",
+            vec![1, weights.len() as u64, 1, 1],
+            weights.as_slice().unwrap(),
+        )],
+        Layer::Local(LocalLayer {
+            base,
+            weights: LocalWeights { biases, weights },
+        }) => {
+            let [out_h, out_w, filters] = base.output_shape;
+            vec![
+                param(
+                    &name,
+                    "weight",
+                    base.weights_shape().to_vec(),
+                    weights.as_slice().unwrap(),
+                ),
+                param(
+                    &name,
+                    "bias",
+                    vec![filters, out_h, out_w],
+                    biases.as_slice().unwrap(),
+                ),
+            ]
+        }
+        // one fc module per gate, e.g. lstm_1.wf.fc.weight
+        Layer::Lstm(LstmLayer {
+            base,
+            weights: LstmWeights { gates },
+        }) => izip!(LSTM_GATES.iter(), base.gate_shapes().iter(), gates)
+            .flat_map(|(gate, &(inputs, outputs), weights)| {
+                let name = format!("{}.{}", name, gate);
+                let ConnectedWeights {
+                    biases,
+                    weights,
+                    scales,
+                } = weights;
+                let weights = param(
+                    &name,
+                    "fc.weight",
+                    vec![outputs, inputs],
+                    weights.as_slice().unwrap(),
+                );
+                match scales {
+                    Some(scales) => iter::once(weights)
+                        .chain(batch_norm(&name, biases, scales))
+                        .collect(),
+                    None => vec![weights, vector(&name, "fc.bias", biases)],
+                }
+            })
+            .collect(),
+        // one conv module per convolution, e.g. crnn_1.self.conv.weight
+        Layer::Crnn(CrnnLayer {
+            base,
+            weights: CrnnWeights { convs },
+        }) => recurrent_conv_params(
+            &name,
+            base.conv_shapes().iter().map(|(conv, ..)| *conv),
+            convs,
+        ),
+        Layer::ConvLstm(ConvLstmLayer {
+            base,
+            weights: ConvLstmWeights { gates },
+        }) => recurrent_conv_params(
+            &name,
+            base.gate_shapes().iter().map(|(gate, ..)| *gate),
+            gates,
+        ),
+        Layer::Route(_)
+        | Layer::MaxPool(_)
+        | Layer::UpSample(_)
+        | Layer::Yolo(_)
+        | Layer::AvgPool(_)
+        | Layer::ScaleChannels(_)
+        | Layer::Dropout(_)
+        | Layer::Softmax(_)
+        | Layer::Region(_)
+        | Layer::Sam(_)
+        | Layer::LocalAvgPool(_)
+        | Layer::Reorg(_)
+        | Layer::GaussianYolo(_)
+        | Layer::Custom(_) => vec![],
+    }
+}
+
+fn recurrent_conv_params<'a>(
+    name: &str,
+    convs: impl Iterator<Item = &'static str>,
+    weights: &'a [RecurrentConvWeights],
+) -> Vec<(String, Vec<u64>, &'a [f32])> {
+    convs
+        .zip(weights)
+        .flat_map(|(conv, weights)| {
+            let name = format!("{}.{}", name, conv);
+            let RecurrentConvWeights {
+                biases,
+                weights,
+                scales,
+            } = weights;
+            let (in_c, filters, size, _size) = weights.dim();
+            let weights = param(
+                &name,
+                "conv.weight",
+                vec![filters as u64, in_c as u64, size as u64, size as u64],
+                weights.as_slice().unwrap(),
+            );
+            match scales {
+                Some(scales) => iter::once(weights)
+                    .chain(batch_norm(&name, biases, scales))
+                    .collect(),
+                None => vec![weights, vector(&name, "conv.bias", biases)],
+            }
+        })
+        .collect()
+}
+
+fn param<'a>(
+    name: &str,
+    suffix: &str,
+    shape: Vec<u64>,
+    values: &'a [f32],
+) -> (String, Vec<u64>, &'a [f32]) {
+    (format!("{}.{}", name, suffix), shape, values)
+}
+
+fn vector<'a>(name: &str, suffix: &str, values: &'a Array1<f32>) -> (String, Vec<u64>, &'a [f32]) {
+    param(
+        name,
+        suffix,
+        vec![values.len() as u64],
+        values.as_slice().unwrap(),
+    )
+}
+
+fn batch_norm<'a>(
+    name: &str,
+    biases: &'a Array1<f32>,
+    scales: &'a ScaleWeights,
+) -> Vec<(String, Vec<u64>, &'a [f32])> {
+    let ScaleWeights {
+        scales,
+        rolling_mean,
+        rolling_variance,
+    } = scales;
+    vec![
+        vector(name, "bn.weight", scales),
+        vector(name, "bn.bias", biases),
+        vector(name, "bn.running_mean", rolling_mean),
+        vector(name, "bn.running_var", rolling_variance),
+    ]
+}
+
+pub fn to_safetensors(model: &DarknetModel) -> Result<Vec<u8>> {
+    to_safetensors_with_progress(model, &mut ())
+}
+
+pub fn to_safetensors_with_progress(
+    model: &DarknetModel,
+    observer: &mut dyn ProgressObserver,
+) -> Result<Vec<u8>> {
+    observer.stage_started(Stage::Export, Some(model.layers.len()));
+    let tensors: Vec<_> = model
+        .layers
+        .iter()
+        .map(|(&layer_index, layer)| (layer_index, layer_named_tensors(model, layer_index, layer)))
+        .collect();
+
+    let mut offset = 0;
+    let mut header = serde_json::Map::new();
+    header.insert(
+        "__metadata__".into(),
+        json!({
+            "format": "pt",
+            "seen": model.base.seen.to_string(),
+        }),
+    );
+    for (name, shape, values) in tensors.iter().flat_map(|(_, tensors)| tensors) {
+        let size = mem::size_of_val(*values);
+        header.insert(
+            name.clone(),
+            json!({
+                "dtype": "F32",
+                "shape": shape,
+                "data_offsets": [offset, offset + size],
+            }),
+        );
+        offset += size;
+    }
+
+    // the header is padded with spaces to keep the data 8-byte aligned
+    let mut header = serde_json::to_vec(&header)?;
+    header.resize(header.len().div_ceil(8) * 8, b' ');
+
+    let mut bytes = Vec::with_capacity(8 + header.len() + offset);
+    bytes.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    bytes.extend(header);
+    tensors.iter().for_each(|(layer_index, tensors)| {
+        observer.layer_started(Stage::Export, *layer_index);
+        let begin = bytes.len();
+        tensors.iter().for_each(|(_, _, values)| {
+            values
+                .iter()
+                .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()))
+        });
+        observer.layer_finished(Stage::Export, *layer_index, (bytes.len() - begin) as u64);
+    });
+
+    observer.stage_finished(Stage::Export, bytes.len() as u64);
+    Ok(bytes)
+}
+
+impl DarknetModel {
+    pub fn save_safetensors<P>(&self, safetensors_file: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(safetensors_file, to_safetensors(self)?)?;
+        Ok(())
+    }
+}
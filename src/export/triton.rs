@@ -0,0 +1,151 @@
+use super::layer_name;
+use crate::{
+    calibration::{fp16_overflow_layers, ActivationStatsProvider},
+    common::*,
+    config::Activation,
+    model::{ConvolutionalLayerBase, LayerBase, LayerPosition, ModelBase},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TritonPlatform {
+    #[serde(rename = "onnxruntime_onnx")]
+    Onnx,
+    #[serde(rename = "tensorrt_plan")]
+    TensorRt,
+}
+
+impl TritonPlatform {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Onnx => "onnxruntime_onnx",
+            Self::TensorRt => "tensorrt_plan",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TritonOptions {
+    pub name: String,
+    pub platform: TritonPlatform,
+    pub max_batch_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Precision {
+    #[serde(rename = "fp32")]
+    Fp32,
+    #[serde(rename = "fp16")]
+    Fp16,
+    #[serde(rename = "int8")]
+    Int8,
+}
+
+impl Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Self::Fp32 => "fp32",
+            Self::Fp16 => "fp16",
+            Self::Int8 => "int8",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+fn tensor_entry(name: &str, dims: &[u64]) -> String {
+    format!(
+        "  {{\n    name: \"{}\"\n    data_type: TYPE_FP32\n    dims: [ {} ]\n  }}",
+        name,
+        dims.iter().join(", ")
+    )
+}
+
+pub fn triton_config(model: &ModelBase, options: &TritonOptions) -> Result<String> {
+    let TritonOptions {
+        ref name,
+        platform,
+        max_batch_size,
+    } = *options;
+    let max_batch_size = max_batch_size.unwrap_or(model.net.batch / model.net.subdivisions.max(1));
+
+    let outputs: Vec<_> = model
+        .outputs()
+        .iter()
+        .map(|output| tensor_entry(&output.name, &output.dims))
+        .collect();
+    ensure!(!outputs.is_empty(), "the model has no output layers");
+
+    let input = model
+        .inputs()
+        .iter()
+        .map(|input| tensor_entry(&input.name, &input.dims))
+        .join(",\n");
+
+    Ok(format!(
+        "name: \"{}\"\nplatform: \"{}\"\nmax_batch_size: {}\ninput [\n{}\n]\noutput [\n{}\n]\n",
+        name,
+        platform.as_str(),
+        max_batch_size,
+        input,
+        outputs.join(",\n")
+    ))
+}
+
+pub fn tensorrt_precision_hints(model: &ModelBase, default: Precision) -> Vec<(String, Precision)> {
+    tensorrt_precision_hints_with_stats(model, default, &())
+}
+
+// like tensorrt_precision_hints(), but layers whose calibrated activations overflow
+// fp16 are kept in fp32
+pub fn tensorrt_precision_hints_with_stats(
+    model: &ModelBase,
+    default: Precision,
+    stats: &dyn ActivationStatsProvider,
+) -> Vec<(String, Precision)> {
+    let overflow_layers: HashSet<usize> = if default == Precision::Fp32 {
+        HashSet::new()
+    } else {
+        fp16_overflow_layers(model, stats).into_iter().collect()
+    };
+
+    // convolutions feeding detection heads are kept in fp32 to preserve box precision
+    let head_inputs: HashSet<usize> = model
+        .layers
+        .values()
+        .filter_map(|layer| match layer {
+            LayerBase::Yolo(yolo) => match yolo.from_indexes {
+                LayerPosition::Absolute(index) => Some(index),
+                LayerPosition::Input => None,
+            },
+            _ => None,
+        })
+        .collect();
+
+    let num_layers = model.layers.len();
+    (0..num_layers)
+        .map(|layer_index| {
+            let layer = &model.layers[&layer_index];
+            let precision = match layer {
+                LayerBase::Yolo(_) => Precision::Fp32,
+                _ if overflow_layers.contains(&layer_index) => Precision::Fp32,
+                LayerBase::Convolutional(_) if head_inputs.contains(&layer_index) => {
+                    Precision::Fp32
+                }
+                // softplus in mish overflows easily in reduced precision
+                LayerBase::Convolutional(ConvolutionalLayerBase { config, .. })
+                    if config.activation == Activation::Mish && default == Precision::Int8 =>
+                {
+                    Precision::Fp16
+                }
+                _ => default,
+            };
+            (layer_name(layer_index, layer), precision)
+        })
+        .collect()
+}
+
+pub fn tensorrt_precision_hints_text(model: &ModelBase, default: Precision) -> String {
+    tensorrt_precision_hints(model, default)
+        .into_iter()
+        .map(|(name, precision)| format!("{}: {}\n", name, precision))
+        .collect()
+}
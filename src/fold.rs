@@ -0,0 +1,157 @@
+use crate::{
+    common::*,
+    config::{Activation, DarknetConfig, LayerConfig, LayerConfigEx},
+    darknet::{ConvolutionalLayer, ConvolutionalWeights, DarknetModel, Layer},
+    model::{ConvolutionalLayerBase, LayerBase, LayerPosition},
+    prune::remove_layers,
+};
+
+// 1x1 convolutions with this extension key set to 1 are asserted to pass their
+// input through, e.g. placeholders kept for weights compatibility
+pub const PASSTHROUGH_KEY: &str = "x_passthrough";
+
+#[derive(Debug, Clone)]
+pub struct FoldedModel {
+    pub config: DarknetConfig,
+    pub model: DarknetModel,
+    // indexes of the removed layers in the original model
+    pub removed: Vec<usize>,
+}
+
+impl DarknetModel {
+    // remove the identity layers, that is, upsample layers with stride 1, routes
+    // of the previous layer alone without grouping and passthrough convolutions.
+    // the remaining layers keep their weights.
+    pub fn fold_noops(&self) -> Result<FoldedModel> {
+        let config = self.base.to_config();
+
+        // shared weights must stay with their owner
+        let weight_owners: HashSet<usize> = config
+            .layers
+            .iter()
+            .enumerate()
+            .filter_map(|(layer_index, layer)| match layer {
+                LayerConfig::Convolutional(conf) => conf.share_index?.to_absolute(layer_index),
+                _ => None,
+            })
+            .collect();
+
+        // the first layer reads the network input, which cannot be referred to
+        let mut removed = vec![];
+        for layer_index in 1..config.layers.len() {
+            if !weight_owners.contains(&layer_index) && self.is_noop_layer(layer_index)? {
+                removed.push(layer_index);
+            }
+        }
+
+        // removed layers pass the previous layer through
+        let redirect = |layer_index: usize| {
+            (0..layer_index)
+                .rev()
+                .find(|index| removed.binary_search(index).is_err())
+        };
+        let folded_config = remove_layers(&config, &removed, redirect)?;
+
+        let mut model = DarknetModel::from_config(&folded_config)?;
+        model.base.seen = self.base.seen;
+        model.base.cur_iteration = self.base.cur_iteration;
+
+        let kept = (0..config.layers.len()).filter(|index| removed.binary_search(index).is_err());
+        for (new_index, old_index) in kept.enumerate() {
+            let source = self.layers[&old_index].buffers();
+            let mut target = model.layers.get_mut(&new_index).unwrap().buffers_mut();
+            ensure!(
+                source.len() == target.len(),
+                "please report bug: the buffers of layer {} do not match",
+                old_index
+            );
+            source
+                .into_iter()
+                .zip(target.iter_mut())
+                .for_each(|((_, source), (_, target))| target.copy_from_slice(source));
+        }
+
+        Ok(FoldedModel {
+            config: folded_config,
+            model,
+            removed,
+        })
+    }
+
+    fn is_noop_layer(&self, layer_index: usize) -> Result<bool> {
+        let previous = LayerPosition::Absolute(layer_index - 1);
+
+        let is_noop = match &self.base.layers[&layer_index] {
+            LayerBase::UpSample(layer) => layer.config.stride == 1,
+            LayerBase::Route(layer) => {
+                layer.config.group.num_groups() == 1
+                    && layer.from_indexes.len() == 1
+                    && layer.from_indexes.contains(&previous)
+            }
+            LayerBase::Convolutional(base) => {
+                let marked = base
+                    .config
+                    .common()
+                    .extensions
+                    .get(PASSTHROUGH_KEY)
+                    .is_some_and(|value| value == "1");
+                if !marked {
+                    return Ok(false);
+                }
+                ensure!(
+                    is_identity_conv(base, &self.layers[&layer_index]),
+                    "layer {} is marked as passthrough, but it does not compute the identity",
+                    layer_index
+                );
+                true
+            }
+            _ => false,
+        };
+        Ok(is_noop)
+    }
+}
+
+fn is_identity_conv(base: &ConvolutionalLayerBase, layer: &Layer) -> bool {
+    let conf = &base.config;
+    let [_h, _w, in_c] = base.input_shape;
+    let is_plain = conf.size == 1
+        && conf.stride_x == 1
+        && conf.stride_y == 1
+        && conf.activation == Activation::Linear
+        && !conf.batch_normalize
+        && !conf.antialiasing
+        && !conf.assisted_excitation
+        && !conf.binary
+        && !conf.xnor
+        && !conf.coordconv
+        && conf.share_index.is_none()
+        && conf.filters == in_c
+        && (conf.groups == 1 || conf.groups == in_c);
+    if !is_plain {
+        return false;
+    }
+
+    let (biases, weights) = match layer {
+        Layer::Convolutional(ConvolutionalLayer {
+            weights: ConvolutionalWeights::Owned {
+                biases, weights, ..
+            },
+            ..
+        }) => (biases, weights),
+        _ => return false,
+    };
+
+    // weights are stored in [filters, channels / groups, 1, 1] order
+    let in_per_group = (in_c / conf.groups) as usize;
+    let values = weights.as_slice().unwrap();
+    biases.iter().all(|&bias| bias == 0.0)
+        && values.iter().enumerate().all(|(index, &value)| {
+            let [filter, channel] = [index / in_per_group, index % in_per_group];
+            let expect = if in_per_group == 1 || filter == channel {
+                1.0
+            } else {
+                0.0
+            };
+            value == expect
+        })
+}
@@ -0,0 +1,143 @@
+use crate::{
+    common::*,
+    config::{Activation, WeightsType},
+    model::{LayerBase, LayerPosition, ModelBase},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum FusedOp {
+    #[serde(rename = "conv")]
+    Conv,
+    #[serde(rename = "batch_norm")]
+    BatchNorm,
+    #[serde(rename = "activation")]
+    Activation { activation: Activation },
+    // element-wise sum with the other inputs of a shortcut layer
+    #[serde(rename = "add")]
+    Add,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FusionGroup {
+    // the fused layers in execution order, the first one is the convolution
+    pub layers: Vec<usize>,
+    pub ops: Vec<FusedOp>,
+}
+
+impl FusionGroup {
+    // e.g. "conv+batch_norm+activation+add"
+    pub fn pattern(&self) -> String {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                FusedOp::Conv => "conv",
+                FusedOp::BatchNorm => "batch_norm",
+                FusedOp::Activation { .. } => "activation",
+                FusedOp::Add => "add",
+            })
+            .join("+")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FusionPlan {
+    // every layer belongs to at most one group
+    pub groups: Vec<FusionGroup>,
+}
+
+impl FusionPlan {
+    pub fn group_of(&self, layer_index: usize) -> Option<&FusionGroup> {
+        self.groups
+            .iter()
+            .find(|group| group.layers.contains(&layer_index))
+    }
+}
+
+impl ModelBase {
+    // fusible operator chains starting at convolutions. a layer is only fused into
+    // its producer if it is the sole consumer of the producer's output, so that
+    // no intermediate tensor has to be materialized.
+    pub fn fusion_plan(&self) -> FusionPlan {
+        let mut consumers: HashMap<usize, Vec<usize>> = HashMap::new();
+        self.layers.iter().for_each(|(&layer_index, layer)| {
+            layer.from_indexes().iter().for_each(|position| {
+                if let LayerPosition::Absolute(from_index) = position {
+                    consumers.entry(from_index).or_default().push(layer_index);
+                }
+            });
+        });
+        let sole_consumer = |layer_index: usize| match consumers.get(&layer_index) {
+            Some(consumers) if consumers.len() == 1 => Some(consumers[0]),
+            _ => None,
+        };
+
+        let groups = (0..self.layers.len())
+            .filter_map(|layer_index| {
+                let conv = match &self.layers[&layer_index] {
+                    LayerBase::Convolutional(conv) => conv,
+                    _ => return None,
+                };
+                let mut layers = vec![layer_index];
+                let mut ops = vec![FusedOp::Conv];
+                let activation = conv.config.activation;
+
+                if conv.config.batch_normalize {
+                    ops.push(FusedOp::BatchNorm);
+                }
+
+                // a separate batch normalization folds only if nothing is applied in between
+                let next = sole_consumer(layer_index);
+                let mut last = layer_index;
+                match next.map(|index| (index, &self.layers[&index])) {
+                    Some((next_index, LayerBase::BatchNorm(_)))
+                        if activation == Activation::Linear =>
+                    {
+                        layers.push(next_index);
+                        ops.push(FusedOp::BatchNorm);
+                        last = next_index;
+                    }
+                    _ => {
+                        if is_pointwise(activation) {
+                            ops.push(FusedOp::Activation { activation });
+                        }
+                    }
+                }
+
+                // the other inputs of the sum must be computed before the convolution,
+                // which also keeps shortcuts from being claimed by two groups
+                if let Some(next_index) = sole_consumer(last) {
+                    if let LayerBase::Shortcut(shortcut) = &self.layers[&next_index] {
+                        let inputs_ready = shortcut.from_indexes.iter().all(|&position| {
+                            position == LayerPosition::Absolute(last)
+                                || position < LayerPosition::Absolute(layer_index)
+                        });
+                        if inputs_ready && shortcut.config.weights_type == WeightsType::None {
+                            layers.push(next_index);
+                            ops.push(FusedOp::Add);
+                            let activation = shortcut.config.activation;
+                            if is_pointwise(activation) {
+                                ops.push(FusedOp::Activation { activation });
+                            }
+                        }
+                    }
+                }
+
+                (ops.len() > 1).then_some(FusionGroup { layers, ops })
+            })
+            .collect();
+
+        FusionPlan { groups }
+    }
+}
+
+// linear needs no op, and the channel normalizations read across channels
+fn is_pointwise(activation: Activation) -> bool {
+    !matches!(
+        activation,
+        Activation::Linear
+            | Activation::NormalizeChannels
+            | Activation::NormalizeChannelsSoftmax
+            | Activation::NormalizeChannelsSoftmaxMaxval
+    )
+}
@@ -0,0 +1,150 @@
+use crate::{
+    common::*,
+    darknet::{ConvolutionalLayer, ConvolutionalWeights, DarknetModel, Layer},
+    weights_layout::named_buffers,
+};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+// the minimum offset alignment of storage buffer bindings guaranteed by WebGPU
+pub const STORAGE_OFFSET_ALIGNMENT: u64 = 256;
+
+// the place of a layer buffer in the packed GPU buffer
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GpuTensor {
+    pub layer_index: usize,
+    // the names of WeightsLayout records
+    pub name: String,
+    // byte offset, a multiple of STORAGE_OFFSET_ALIGNMENT so that each tensor
+    // can be bound on its own
+    pub offset: u64,
+    // number of f32 values
+    pub length: u64,
+}
+
+impl GpuTensor {
+    pub fn num_bytes(&self) -> u64 {
+        self.length * 4
+    }
+
+    pub fn binding<'a>(&self, buffer: &'a wgpu::Buffer) -> wgpu::BufferBinding<'a> {
+        wgpu::BufferBinding {
+            buffer,
+            offset: self.offset,
+            size: wgpu::BufferSize::new(self.num_bytes()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GpuWeights {
+    pub buffer: wgpu::Buffer,
+    pub tensors: Vec<GpuTensor>,
+}
+
+impl GpuWeights {
+    pub fn tensor(&self, layer_index: usize, name: &str) -> Option<&GpuTensor> {
+        self.tensors
+            .iter()
+            .find(|tensor| tensor.layer_index == layer_index && tensor.name == name)
+    }
+}
+
+impl DarknetModel {
+    // the buffers of all layers in file order packed into one byte array in host
+    // order, along with their places
+    pub fn pack_gpu_weights(&self) -> (Vec<u8>, Vec<GpuTensor>) {
+        let mut bytes = vec![];
+        let mut tensors = vec![];
+
+        for (&layer_index, layer) in self.layers.iter().sorted_by_key(|(index, _)| **index) {
+            for (name, values) in named_buffers(layer) {
+                let offset = bytes.len() as u64;
+                bytes.extend(values.iter().flat_map(|value| value.to_ne_bytes().to_vec()));
+                let padded_len = (bytes.len() as u64).div_ceil(STORAGE_OFFSET_ALIGNMENT)
+                    * STORAGE_OFFSET_ALIGNMENT;
+                bytes.resize(padded_len as usize, 0);
+
+                tensors.push(GpuTensor {
+                    layer_index,
+                    name: name.to_owned(),
+                    offset,
+                    length: values.len() as u64,
+                });
+            }
+        }
+
+        (bytes, tensors)
+    }
+
+    // upload all weights into one storage buffer
+    pub fn upload_weights(&self, device: &wgpu::Device) -> GpuWeights {
+        let (bytes, tensors) = self.pack_gpu_weights();
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("darknet weights"),
+            contents: &bytes,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+        });
+        GpuWeights { buffer, tensors }
+    }
+
+    // upload the weights of a convolution as an R32Float texture with one row per
+    // filter and one column per input value of the filter, that is darknet's
+    // [filters, in_c / groups * size * size] kernel order
+    pub fn upload_conv_weights_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layer_index: usize,
+    ) -> Result<wgpu::Texture> {
+        let (filters, weights) = match self.layers.get(&layer_index) {
+            Some(Layer::Convolutional(ConvolutionalLayer {
+                base,
+                weights: ConvolutionalWeights::Owned { weights, .. },
+            })) => (base.config.filters, weights),
+            Some(Layer::Convolutional(_)) => {
+                bail!("layer {} shares the weights of another layer", layer_index)
+            }
+            _ => bail!("layer {} is not a convolutional layer", layer_index),
+        };
+        let height = filters as u32;
+        let width = (weights.len() as u64 / filters.max(1)) as u32;
+
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        ensure!(
+            width <= max_dimension && height <= max_dimension,
+            "the {}x{} weights of layer {} exceed the texture size limit {}",
+            width,
+            height,
+            layer_index,
+            max_dimension
+        );
+
+        let bytes: Vec<u8> = weights
+            .iter()
+            .flat_map(|value| value.to_ne_bytes().to_vec())
+            .collect();
+        let label = format!("darknet layer {} weights", layer_index);
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some(&label),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::COPY_DST,
+            },
+            &bytes,
+        );
+        Ok(texture)
+    }
+}
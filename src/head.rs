@@ -0,0 +1,132 @@
+use crate::{
+    common::*,
+    config::{
+        Activation, CompoundYoloConfig, ConvolutionalConfig, DarknetConfig, LayerConfig,
+        LayerIndex, RouteConfig, RouteGroup, Shape,
+    },
+    model::{LayerBase, LayerPosition, LayerPositionSet, ModelBase},
+};
+
+// an additional detection head, that is a route from a chosen layer, an
+// optional 3x3 convolution, a 1x1 convolution and a yolo layer
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HeadTemplate {
+    // the layer whose output feeds the head
+    pub from_layer: usize,
+    // the filters of the 3x3 convolution before the output convolution
+    pub hidden_filters: Option<u64>,
+    pub anchors: Vec<(u64, u64)>,
+    // the config format shares the classes among yolo layers, so this must
+    // match the classes of the net
+    pub classes: u64,
+}
+
+impl DarknetConfig {
+    // append the head after the last layer. the loss options of the new yolo
+    // layer and the common options of the new layers are copied from the last
+    // head of the config.
+    pub fn append_head(&self, template: &HeadTemplate) -> Result<DarknetConfig> {
+        let HeadTemplate {
+            from_layer,
+            hidden_filters,
+            ref anchors,
+            classes,
+        } = *template;
+        ensure!(
+            !anchors.is_empty(),
+            "the head must have at least one anchor"
+        );
+        ensure!(
+            classes == self.net.classes,
+            "the yolo layers of a config share the classes, expect {} classes but get {}",
+            self.net.classes,
+            classes
+        );
+
+        let model = ModelBase::from_config(self)?;
+        match model
+            .layers
+            .get(&from_layer)
+            .map(|layer| layer.output_shape())
+        {
+            Some(Shape::Hwc(_)) => (),
+            Some(Shape::Flat(_)) => {
+                bail!("the output of layer {} is not 3-dimensional", from_layer)
+            }
+            None => bail!("the layer {} does not exist", from_layer),
+        }
+
+        // the last yolo layer and the convolution feeding it
+        let (head_conv, head_yolo) = model
+            .layers
+            .iter()
+            .filter_map(
+                |(&layer_index, layer)| match (layer, layer.from_indexes()) {
+                    (
+                        LayerBase::Yolo(_),
+                        LayerPositionSet::Single(LayerPosition::Absolute(from_index)),
+                    ) => Some((layer_index, from_index)),
+                    _ => None,
+                },
+            )
+            .max()
+            .and_then(|(yolo_index, conv_index)| {
+                match (&self.layers[conv_index], &self.layers[yolo_index]) {
+                    (LayerConfig::Convolutional(conv), LayerConfig::Yolo(yolo)) => {
+                        Some((conv, yolo))
+                    }
+                    _ => None,
+                }
+            })
+            .ok_or_else(|| {
+                format_err!("the config has no yolo layer fed by a convolution to copy from")
+            })?;
+
+        let route = RouteConfig {
+            layers: iter::once(LayerIndex::Absolute(from_layer)).collect(),
+            group: RouteGroup::new(0, 1).unwrap(),
+            common: head_conv.common.clone(),
+        };
+        let hidden = hidden_filters.map(|filters| ConvolutionalConfig {
+            filters,
+            groups: 1,
+            size: 3,
+            stride_x: 1,
+            stride_y: 1,
+            dilation: 1,
+            padding: 1,
+            batch_normalize: true,
+            activation: Activation::Leaky,
+            share_index: None,
+            ..head_conv.clone()
+        });
+        let output = ConvolutionalConfig {
+            filters: anchors.len() as u64 * (classes + 5),
+            share_index: None,
+            ..head_conv.clone()
+        };
+        let yolo = CompoundYoloConfig {
+            anchors: anchors.clone(),
+            counters_per_class: None,
+            embedding_layer: None,
+            ..head_yolo.clone()
+        };
+
+        let layers: Vec<_> = self
+            .layers
+            .iter()
+            .cloned()
+            .chain(iter::once(LayerConfig::Route(route)))
+            .chain(hidden.map(LayerConfig::Convolutional))
+            .chain([LayerConfig::Convolutional(output), LayerConfig::Yolo(yolo)])
+            .collect();
+        let config = DarknetConfig {
+            net: self.net.clone(),
+            layers,
+        };
+
+        // infer the shapes of the new layers
+        ModelBase::from_config(&config)?;
+        Ok(config)
+    }
+}
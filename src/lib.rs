@@ -1,10 +1,73 @@
+pub mod advise;
+pub mod approx;
+#[cfg(all(feature = "image", feature = "random"))]
+pub mod augment;
+pub mod average;
+pub mod binding;
+pub mod buffer;
+#[cfg(feature = "encryption")]
+pub mod bundle;
+pub mod calibration;
+pub mod checkpoint;
+pub mod codegen;
 mod common;
+mod compress;
 pub mod config;
 pub mod darknet;
+pub mod dataset;
+pub mod distill;
+pub mod export;
+pub mod fold;
+pub mod fusion;
+#[cfg(feature = "wgpu")]
+pub mod gpu;
+pub mod head;
+pub mod loss;
+#[cfg(feature = "manifest")]
+pub mod manifest;
+pub mod memory;
+pub mod migrate;
+#[cfg(feature = "random")]
+pub mod mix;
 pub mod model;
+pub mod model_ref;
+pub mod parity;
+#[cfg(feature = "random")]
+pub mod perturb;
+#[cfg(feature = "image")]
+pub mod preprocess;
+pub mod progress;
+#[cfg(feature = "manifest")]
+pub mod provenance;
+pub mod prune;
+pub mod query;
+pub mod reid;
+pub mod reinit;
+pub mod repack;
+pub mod salvage;
+pub mod section;
+pub mod separable;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod stability;
+pub mod stages;
+#[cfg(feature = "manifest")]
+pub mod store;
+pub mod summary;
 #[cfg(feature = "with-tch")]
 pub mod torch;
+pub mod trainable;
+pub mod transfer;
+pub mod tta;
 pub mod utils;
+pub mod validate;
+#[cfg(feature = "image")]
+pub mod visualize;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod weights_cache;
+pub mod weights_layout;
+pub mod winograd;
 
 pub use config::DarknetConfig;
 pub use darknet::DarknetModel;
@@ -0,0 +1,88 @@
+use crate::{
+    common::*,
+    config::{CompoundYoloConfig, IouLoss, IouThreshold},
+    model::{LayerBase, ModelBase},
+};
+
+// the yolo options that affect the training loss. the comments describe how
+// forward_yolo_layer() and its helpers in darknet use each of them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct YoloLossConfig {
+    // the box delta, mse on the raw coordinates or the gradient of 1 - (c/d/g)iou,
+    // see delta_yolo_box()
+    pub iou_loss: IouLoss,
+    // the scale of the box delta. mse is also scaled by 2 - w * h of the truth.
+    pub iou_normalizer: R64,
+    // the scale of the objectness delta of all predictions
+    pub obj_normalizer: R64,
+    // the scale of the class delta, see delta_yolo_class()
+    pub cls_normalizer: R64,
+    // the scale of the deltas when they are summed across the anchors of a truth
+    pub delta_normalizer: R64,
+    // predictions whose best iou with any truth exceeds this get no
+    // objectness penalty, otherwise their objectness is pushed to 0
+    pub ignore_thresh: R64,
+    // predictions whose best iou exceeds this are trained as positives for that
+    // truth, 1 disables it as the iou cannot exceed it
+    pub truth_thresh: R64,
+    // besides the best anchor, the anchors whose iou_thresh_kind with the truth
+    // exceeds this are also trained as positives, 1 disables it
+    pub iou_thresh: R64,
+    pub iou_thresh_kind: IouThreshold,
+    // the objectness target of ignored predictions becomes their iou instead of
+    // no penalty
+    pub objectness_smooth: bool,
+    // the class delta is weighted by (1 - p)^2 with alpha 0.5
+    pub focal_loss: bool,
+    // the class targets become 1 - eps / 2 and eps / 2, see class_targets()
+    pub label_smooth_eps: R64,
+    // the box deltas are clipped to [-max_delta, max_delta] if set
+    pub max_delta: Option<R64>,
+    // the decoding of the box the deltas are taken against
+    pub scale_x_y: R64,
+    pub new_coords: bool,
+}
+
+impl YoloLossConfig {
+    // [positive, negative] class targets after label smoothing
+    pub fn class_targets(&self) -> [f64; 2] {
+        let eps = self.label_smooth_eps.raw();
+        [1.0 - eps + 0.5 * eps, 0.5 * eps]
+    }
+}
+
+impl CompoundYoloConfig {
+    pub fn loss_config(&self) -> YoloLossConfig {
+        YoloLossConfig {
+            iou_loss: self.iou_loss,
+            iou_normalizer: self.iou_normalizer,
+            obj_normalizer: self.obj_normalizer,
+            cls_normalizer: self.cls_normalizer,
+            delta_normalizer: self.delta_normalizer,
+            ignore_thresh: self.ignore_thresh,
+            truth_thresh: self.truth_thresh,
+            iou_thresh: self.iou_thresh,
+            iou_thresh_kind: self.iou_thresh_kind,
+            objectness_smooth: self.objectness_smooth,
+            focal_loss: self.focal_loss,
+            label_smooth_eps: self.label_smooth_eps,
+            max_delta: self.max_delta,
+            scale_x_y: self.scale_x_y,
+            new_coords: self.new_coords,
+        }
+    }
+}
+
+impl ModelBase {
+    // the loss options of the yolo layers by layer index
+    pub fn yolo_loss_configs(&self) -> IndexMap<usize, YoloLossConfig> {
+        self.layers
+            .iter()
+            .filter_map(|(&layer_index, layer)| match layer {
+                LayerBase::Yolo(yolo) => Some((layer_index, yolo.config.loss_config())),
+                _ => None,
+            })
+            .sorted_by_key(|(layer_index, _)| *layer_index)
+            .collect()
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    common::*,
+    config::DarknetConfig,
+    dataset,
+    utils::{sha256_digest, sha256_file},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub crate_version: String,
+    pub config_fingerprint: String,
+    pub weights_checksum: Option<String>,
+    pub names_hash: Option<String>,
+    #[serde(default)]
+    pub options: IndexMap<String, String>,
+}
+
+impl Manifest {
+    pub fn new(config: &DarknetConfig) -> Result<Self> {
+        Ok(Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+            config_fingerprint: config.fingerprint()?,
+            weights_checksum: None,
+            names_hash: None,
+            options: IndexMap::new(),
+        })
+    }
+
+    pub fn with_weights_file<P>(mut self, weights_file: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        self.weights_checksum = Some(sha256_file(weights_file)?);
+        Ok(self)
+    }
+
+    pub fn with_names_file<P>(mut self, names_file: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        self.names_hash = Some(names_hash(&dataset::load_names(names_file)?));
+        Ok(self)
+    }
+
+    pub fn with_option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn load<P>(manifest_file: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let manifest_file = manifest_file.as_ref();
+        let text = fs::read_to_string(manifest_file)?;
+        match manifest_file.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&text),
+            Some("toml") => Self::from_toml(&text),
+            _ => bail!(
+                "unsupported manifest file extension '{}'",
+                manifest_file.display()
+            ),
+        }
+    }
+
+    pub fn save<P>(&self, manifest_file: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let manifest_file = manifest_file.as_ref();
+        let text = match manifest_file.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => self.to_json()?,
+            Some("toml") => self.to_toml()?,
+            _ => bail!(
+                "unsupported manifest file extension '{}'",
+                manifest_file.display()
+            ),
+        };
+        fs::write(manifest_file, text)?;
+        Ok(())
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn verify<P1, P2>(
+        &self,
+        config: &DarknetConfig,
+        weights_file: Option<P1>,
+        names_file: Option<P2>,
+    ) -> Result<()>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        let mut mismatches = vec![];
+
+        if config.fingerprint()? != self.config_fingerprint {
+            mismatches.push("config fingerprint");
+        }
+
+        match (&self.weights_checksum, weights_file) {
+            (Some(expect), Some(weights_file)) => {
+                if &sha256_file(weights_file)? != expect {
+                    mismatches.push("weights checksum");
+                }
+            }
+            (Some(_), None) => bail!("the manifest requires a weights file to verify"),
+            (None, _) => (),
+        }
+
+        match (&self.names_hash, names_file) {
+            (Some(expect), Some(names_file)) => {
+                if &names_hash(&dataset::load_names(names_file)?) != expect {
+                    mismatches.push("names hash");
+                }
+            }
+            (Some(_), None) => bail!("the manifest requires a names file to verify"),
+            (None, _) => (),
+        }
+
+        ensure!(
+            mismatches.is_empty(),
+            "manifest verification failed: {} mismatch",
+            mismatches.join(", ")
+        );
+
+        Ok(())
+    }
+}
+
+impl DarknetConfig {
+    // the hash of re-serialized text, which is invariant to comments,
+    // key order and formatting of the original file
+    pub fn fingerprint(&self) -> Result<String> {
+        Ok(sha256_digest(self.to_cfg_string()?))
+    }
+}
+
+fn names_hash(names: &[String]) -> String {
+    sha256_digest(names.join("\n"))
+}
@@ -0,0 +1,104 @@
+use crate::{
+    common::*,
+    config::Shape,
+    model::{LayerBase, ModelBase},
+    trainable::ParameterCounts,
+};
+
+const FLOAT_SIZE: u64 = 4;
+
+// a rough estimate of the device memory darknet allocates for training
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MemoryEstimate {
+    // weights and their updates, plus the moments if adam is enabled
+    pub parameter_bytes: u64,
+    // outputs and deltas of all layers for one image, including the extra
+    // buffers of batch normalization
+    pub activation_bytes_per_sample: u64,
+    // the largest im2col buffer, shared by all convolutions
+    pub workspace_bytes: u64,
+}
+
+impl MemoryEstimate {
+    // the memory used by one forward and backward pass over a mini-batch
+    pub fn mini_batch_bytes(&self, mini_batch: u64) -> u64 {
+        self.parameter_bytes + self.activation_bytes_per_sample * mini_batch + self.workspace_bytes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BatchSetting {
+    pub batch: u64,
+    pub subdivisions: u64,
+    pub estimated_bytes: u64,
+}
+
+impl BatchSetting {
+    // the number of images processed at once
+    pub fn mini_batch(&self) -> u64 {
+        self.batch / self.subdivisions
+    }
+}
+
+impl ModelBase {
+    pub fn estimate_training_memory(&self, input_size: Shape) -> Result<MemoryEstimate> {
+        let shapes = self.infer_shapes_multi(&[input_size])?.pop().unwrap();
+
+        let num_parameters: u64 = self
+            .layers
+            .values()
+            .map(|layer| ParameterCounts::new(layer).total())
+            .sum();
+        let parameter_copies = if self.net.adam.is_some() { 4 } else { 2 };
+        let parameter_bytes = num_parameters * parameter_copies * FLOAT_SIZE;
+
+        let mut activation_bytes_per_sample = 0;
+        let mut workspace_bytes = 0;
+        for (layer_index, layer) in &self.layers {
+            let (_, output_shape) = &shapes[layer_index];
+            let output_size = match *output_shape {
+                Shape::Hwc([h, w, c]) => h * w * c,
+                Shape::Flat(size) => size,
+            };
+            let buffers = match layer {
+                LayerBase::Convolutional(conv) if conv.config.batch_normalize => 4,
+                LayerBase::BatchNorm(_) => 4,
+                _ => 2,
+            };
+            activation_bytes_per_sample += output_size * buffers * FLOAT_SIZE;
+
+            if let (LayerBase::Convolutional(conv), Shape::Hwc([out_h, out_w, _])) =
+                (layer, output_shape)
+            {
+                let [in_c, _filters, size_h, size_w] = conv.weights_shape();
+                workspace_bytes =
+                    workspace_bytes.max(out_h * out_w * in_c * size_h * size_w * FLOAT_SIZE);
+            }
+        }
+
+        Ok(MemoryEstimate {
+            parameter_bytes,
+            activation_bytes_per_sample,
+            workspace_bytes,
+        })
+    }
+
+    // the subdivisions of the configured batch whose mini-batches fit in the
+    // given device memory at the input size, fastest first
+    pub fn advise_batch(&self, input_size: Shape, memory_bytes: u64) -> Result<Vec<BatchSetting>> {
+        let batch = self.net.batch;
+        ensure!(batch > 0, "batch must be positive");
+        let estimate = self.estimate_training_memory(input_size)?;
+
+        let settings = (1..=batch)
+            .filter(|subdivisions| batch.is_multiple_of(*subdivisions))
+            .map(|subdivisions| BatchSetting {
+                batch,
+                subdivisions,
+                estimated_bytes: estimate.mini_batch_bytes(batch / subdivisions),
+            })
+            .filter(|setting| setting.estimated_bytes <= memory_bytes)
+            .collect();
+        Ok(settings)
+    }
+}
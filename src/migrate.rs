@@ -0,0 +1,273 @@
+use crate::{common::*, config::DarknetConfig};
+
+// historical cfg dialects, in chronological order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Dialect {
+    // darknet up to yolov2, where maxpool padding is counted per side and
+    // defaults to (size - 1) / 2
+    #[serde(rename = "yolov2")]
+    YoloV2,
+    // darknet since yolov3, the dialect parsed by this crate
+    #[serde(rename = "yolov3")]
+    YoloV3,
+}
+
+impl Dialect {
+    pub const LATEST: Self = Self::YoloV3;
+
+    // best effort guess, the maxpool padding semantics cannot be told from the text
+    pub fn detect(text: &str) -> Self {
+        let document = CfgDocument::parse(text);
+        let has_section = |names: &[&str]| {
+            document
+                .sections
+                .iter()
+                .any(|section| names.contains(&section.name.as_str()))
+        };
+
+        if has_section(&["yolo", "Gaussian_yolo"]) {
+            Self::YoloV3
+        } else if has_section(&["region", "reorg", "reorg_old", "reorg3d", "detection"]) {
+            Self::YoloV2
+        } else {
+            Self::LATEST
+        }
+    }
+
+    fn next(&self) -> Option<Self> {
+        match self {
+            Self::YoloV2 => Some(Self::YoloV3),
+            Self::YoloV3 => None,
+        }
+    }
+}
+
+impl Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Self::YoloV2 => "yolov2",
+            Self::YoloV3 => "yolov3",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Change {
+    // index of the section in the file, [net] is 0
+    pub section_index: usize,
+    pub section: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from: Dialect,
+    pub to: Dialect,
+    pub changes: Vec<Change>,
+}
+
+// section names accepted by old parsers, mapped to the canonical names
+const SECTION_ALIASES: &[(&str, &str)] = &[
+    ("network", "net"),
+    ("conv", "convolutional"),
+    ("conn", "connected"),
+    ("max", "maxpool"),
+    ("avg", "avgpool"),
+    ("soft", "softmax"),
+    ("lrn", "normalization"),
+];
+
+// rewrite the cfg text from one dialect to a later one. comments and untouched
+// lines are kept as is.
+pub fn migrate_str(text: &str, from: Dialect, to: Dialect) -> Result<(String, MigrationReport)> {
+    ensure!(
+        from <= to,
+        "cannot migrate from {} back to the older dialect {}",
+        from,
+        to
+    );
+
+    let mut document = CfgDocument::parse(text);
+    let mut changes = vec![];
+    let mut dialect = from;
+
+    while dialect < to {
+        match dialect {
+            Dialect::YoloV2 => yolov2_to_yolov3(&mut document, &mut changes)?,
+            Dialect::YoloV3 => unreachable!("{} is the latest dialect", dialect),
+        }
+        dialect = dialect.next().unwrap();
+    }
+
+    let report = MigrationReport { from, to, changes };
+    Ok((document.to_string(), report))
+}
+
+// detect the dialect and migrate to the latest one
+pub fn upgrade_str(text: &str) -> Result<(String, MigrationReport)> {
+    migrate_str(text, Dialect::detect(text), Dialect::LATEST)
+}
+
+impl DarknetConfig {
+    pub fn load_migrated<P>(config_file: P, from: Dialect) -> Result<(Self, MigrationReport)>
+    where
+        P: AsRef<Path>,
+    {
+        let text = fs::read_to_string(config_file)?;
+        let (text, report) = migrate_str(&text, from, Dialect::LATEST)?;
+        Ok((text.parse()?, report))
+    }
+}
+
+fn yolov2_to_yolov3(document: &mut CfgDocument, changes: &mut Vec<Change>) -> Result<()> {
+    for (section_index, section) in document.sections.iter_mut().enumerate() {
+        if let Some(&(_, name)) = SECTION_ALIASES
+            .iter()
+            .find(|&&(alias, _)| alias == section.name)
+        {
+            changes.push(Change {
+                section_index,
+                section: name.to_owned(),
+                description: format!("rename section [{}] to [{}]", section.name, name),
+            });
+            section.name = name.to_owned();
+        }
+
+        // the old maxpool pads (size - 1) / 2 on each side, while the new one
+        // counts the padding of both sides together and defaults to size - 1
+        if section.name == "maxpool" {
+            let parse = |key: &str| -> Result<Option<u64>> {
+                section
+                    .get(key)
+                    .map(|value| {
+                        value.parse().map_err(|_| {
+                            format_err!("invalid {} '{}' in section {}", key, value, section_index)
+                        })
+                    })
+                    .transpose()
+            };
+            let stride = parse("stride")?.unwrap_or(1);
+            let size = parse("size")?.unwrap_or(stride);
+            let old_padding = parse("padding")?;
+            let padding = old_padding.unwrap_or_else(|| size.saturating_sub(1) / 2) * 2;
+
+            let unchanged = match old_padding {
+                Some(old_padding) => old_padding == padding,
+                None => padding == size.saturating_sub(1),
+            };
+            if !unchanged {
+                section.set("padding", &padding.to_string());
+                changes.push(Change {
+                    section_index,
+                    section: section.name.clone(),
+                    description: match old_padding {
+                        Some(old_padding) => format!(
+                            "double the per-side maxpool padding {} to {}",
+                            old_padding, padding
+                        ),
+                        None => format!(
+                            "set the implicit per-side maxpool padding of size {} to {}",
+                            size, padding
+                        ),
+                    },
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// a loosely parsed cfg file that preserves the lines it does not touch
+#[derive(Debug, Clone)]
+struct CfgDocument {
+    preamble: Vec<String>,
+    sections: Vec<CfgSection>,
+}
+
+#[derive(Debug, Clone)]
+struct CfgSection {
+    name: String,
+    lines: Vec<String>,
+}
+
+impl CfgDocument {
+    fn parse(text: &str) -> Self {
+        let mut preamble = vec![];
+        let mut sections: Vec<CfgSection> = vec![];
+
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                sections.push(CfgSection {
+                    name: trimmed[1..(trimmed.len() - 1)].trim().to_owned(),
+                    lines: vec![],
+                });
+            } else {
+                match sections.last_mut() {
+                    Some(section) => section.lines.push(line.to_owned()),
+                    None => preamble.push(line.to_owned()),
+                }
+            }
+        }
+
+        Self { preamble, sections }
+    }
+}
+
+impl Display for CfgDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.preamble {
+            writeln!(f, "{}", line)?;
+        }
+        for section in &self.sections {
+            writeln!(f, "[{}]", section.name)?;
+            for line in &section.lines {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CfgSection {
+    fn key_value(line: &str) -> Option<(&str, &str)> {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') || trimmed.starts_with(';') {
+            return None;
+        }
+        let pos = trimmed.find('=')?;
+        Some((trimmed[..pos].trim(), trimmed[(pos + 1)..].trim()))
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| Self::key_value(line))
+            .find(|&(other, _)| other == key)
+            .map(|(_, value)| value)
+    }
+
+    // replace the existing option or add it after the last option of the section
+    fn set(&mut self, key: &str, value: &str) {
+        let line = format!("{}={}", key, value);
+        let existing = self
+            .lines
+            .iter()
+            .position(|line| Self::key_value(line).is_some_and(|(other, _)| other == key));
+
+        match existing {
+            Some(pos) => self.lines[pos] = line,
+            None => {
+                let pos = self
+                    .lines
+                    .iter()
+                    .rposition(|line| Self::key_value(line).is_some())
+                    .map(|pos| pos + 1)
+                    .unwrap_or(0);
+                self.lines.insert(pos, line);
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    common::*,
+    config::{CompoundNetConfig, Shape},
+    dataset::LabelBox,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// a rectangle in input pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PixelRect {
+    pub x: u64,
+    pub y: u64,
+    pub w: u64,
+    pub h: u64,
+}
+
+// four images resized to the input size are cut at the same point, and each
+// contributes one quadrant in the order top-left, top-right, bottom-left and
+// bottom-right, see load_data_detection() in darknet. jitter cropping is
+// applied to the images before and is not modeled here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MosaicGeometry {
+    // [width, height] of the input
+    pub input_size: [u64; 2],
+    // [x, y] of the cut point
+    pub cut: [u64; 2],
+    // clip the boxes to their quadrants instead of the whole image
+    pub mosaic_bound: bool,
+}
+
+impl MosaicGeometry {
+    // the quadrants in the output, each copied from the same region of its image
+    pub fn tiles(&self) -> [PixelRect; 4] {
+        let [w, h] = self.input_size;
+        let [cut_x, cut_y] = self.cut;
+        [
+            PixelRect {
+                x: 0,
+                y: 0,
+                w: cut_x,
+                h: cut_y,
+            },
+            PixelRect {
+                x: cut_x,
+                y: 0,
+                w: w - cut_x,
+                h: cut_y,
+            },
+            PixelRect {
+                x: 0,
+                y: cut_y,
+                w: cut_x,
+                h: h - cut_y,
+            },
+            PixelRect {
+                x: cut_x,
+                y: cut_y,
+                w: w - cut_x,
+                h: h - cut_y,
+            },
+        ]
+    }
+
+    // the labels of the image placed at the tile, in coordinates relative to the
+    // output. boxes lying beyond the cut on the far side are dropped like
+    // blend_truth_mosaic() does, and boxes narrower than a pixel are removed.
+    pub fn transform_labels(&self, tile_index: usize, labels: &[LabelBox]) -> Vec<LabelBox> {
+        assert!(tile_index < 4, "the tile index must be less than 4");
+        let [w, h] = [self.input_size[0] as f64, self.input_size[1] as f64];
+        let [cut_x, cut_y] = [self.cut[0] as f64, self.cut[1] as f64];
+        let right_tile = tile_index % 2 == 1;
+        let bottom_tile = tile_index / 2 == 1;
+
+        labels
+            .iter()
+            .filter_map(|label| {
+                let mut left = (label.x - label.w / 2.0) * w;
+                let mut right = (label.x + label.w / 2.0) * w;
+                let mut top = (label.y - label.h / 2.0) * h;
+                let mut bot = (label.y + label.h / 2.0) * h;
+
+                let outside_x = if right_tile {
+                    right < cut_x
+                } else {
+                    left > cut_x
+                };
+                let outside_y = if bottom_tile {
+                    bot < cut_y
+                } else {
+                    top > cut_y
+                };
+                if outside_x || outside_y {
+                    return None;
+                }
+
+                let (min_x, max_x) = match (self.mosaic_bound, right_tile) {
+                    (true, false) => (0.0, cut_x),
+                    (true, true) => (cut_x, w),
+                    (false, _) => (0.0, w),
+                };
+                let (min_y, max_y) = match (self.mosaic_bound, bottom_tile) {
+                    (true, false) => (0.0, cut_y),
+                    (true, true) => (cut_y, h),
+                    (false, _) => (0.0, h),
+                };
+                left = left.max(min_x);
+                right = right.min(max_x);
+                top = top.max(min_y);
+                bot = bot.min(max_y);
+
+                if right - left < 1.0 || bot - top < 1.0 {
+                    return None;
+                }
+                Some(LabelBox {
+                    class: label.class,
+                    x: (left + right) / 2.0 / w,
+                    y: (top + bot) / 2.0 / h,
+                    w: (right - left) / w,
+                    h: (bot - top) / h,
+                })
+            })
+            .collect()
+    }
+}
+
+// a rectangle of the second image pasted onto the first one, see the cutmix
+// branch of load_data_augment() in darknet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CutMixGeometry {
+    // [width, height] of the input
+    pub input_size: [u64; 2],
+    pub rect: PixelRect,
+}
+
+impl CutMixGeometry {
+    // the weights of the class labels of the first and second image, in
+    // proportion to their visible areas
+    pub fn label_weights(&self) -> [f64; 2] {
+        let [w, h] = self.input_size;
+        let alpha = (self.rect.w * self.rect.h) as f64 / (w * h) as f64;
+        [1.0 - alpha, alpha]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MixGenerator {
+    input_size: [u64; 2],
+    mosaic_bound: bool,
+    rng: ChaCha8Rng,
+}
+
+impl MixGenerator {
+    // the same seed always yields the same sequence of geometries
+    pub fn new(input_size: [u64; 2], mosaic_bound: bool, seed: u64) -> Result<Self> {
+        let [w, h] = input_size;
+        ensure!(w >= 4 && h >= 4, "the input size {}x{} is too small", w, h);
+        Ok(Self {
+            input_size,
+            mosaic_bound,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        })
+    }
+
+    pub fn mosaic(&mut self) -> MosaicGeometry {
+        // the cut keeps at least 20% of the input on each side
+        const MIN_OFFSET: f64 = 0.2;
+        let [w, h] = self.input_size;
+        let cut_x = self.rand_int(
+            (w as f64 * MIN_OFFSET) as u64,
+            (w as f64 * (1.0 - MIN_OFFSET)) as u64,
+        );
+        let cut_y = self.rand_int(
+            (h as f64 * MIN_OFFSET) as u64,
+            (h as f64 * (1.0 - MIN_OFFSET)) as u64,
+        );
+
+        MosaicGeometry {
+            input_size: self.input_size,
+            cut: [cut_x, cut_y],
+            mosaic_bound: self.mosaic_bound,
+        }
+    }
+
+    pub fn cutmix(&mut self) -> CutMixGeometry {
+        // the pasted side is between 30% and 80% of the input
+        const MIN: f64 = 0.3;
+        const MAX: f64 = 0.8;
+        let [w, h] = self.input_size;
+        let cut_w = self.rand_int((w as f64 * MIN) as u64, (w as f64 * MAX) as u64);
+        let cut_h = self.rand_int((h as f64 * MIN) as u64, (h as f64 * MAX) as u64);
+        let cut_x = self.rand_int(0, w - cut_w - 1);
+        let cut_y = self.rand_int(0, h - cut_h - 1);
+
+        CutMixGeometry {
+            input_size: self.input_size,
+            rect: PixelRect {
+                x: cut_x,
+                y: cut_y,
+                w: cut_w,
+                h: cut_h,
+            },
+        }
+    }
+
+    // inclusive on both ends like rand_int() in darknet
+    fn rand_int(&mut self, min: u64, max: u64) -> u64 {
+        let (min, max) = if max < min { (max, min) } else { (min, max) };
+        self.rng.gen_range(min..=max)
+    }
+}
+
+impl CompoundNetConfig {
+    pub fn mix_generator(&self, seed: u64) -> Result<MixGenerator> {
+        let [h, w] = match self.input_size {
+            Shape::Hwc([h, w, _c]) => [h, w],
+            Shape::Flat(_) => bail!("mosaic and cutmix require an image input"),
+        };
+        MixGenerator::new([w, h], self.mosaic_bound, seed)
+    }
+}
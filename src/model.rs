@@ -1,12 +1,18 @@
 use crate::{
     common::*,
     config::{
-        BatchNormConfig, CompoundNetConfig, CompoundYoloConfig, ConnectedConfig,
-        ConvolutionalConfig, DarknetConfig, LayerConfig, LayerIndex, MaxPoolConfig, RouteConfig,
-        Shape, ShortcutConfig, UpSampleConfig,
+        AvgPoolConfig, BatchNormConfig, CompoundGaussianYoloConfig, CompoundNetConfig,
+        CompoundYoloConfig, ConnectedConfig, ConvLstmConfig, ConvolutionalConfig, CrnnConfig,
+        CustomConfig, DarknetConfig, DropoutConfig, ImplicitConfig, LayerConfig, LayerConfigRef,
+        LayerIndex, LocalAvgPoolConfig, LocalConfig, LstmConfig, MaxPoolConfig, RegionConfig,
+        ReorgConfig, RouteConfig, SamConfig, ScaleChannelsConfig, Shape, ShortcutConfig,
+        SoftmaxConfig, UpSampleConfig,
     },
+    section::expect_section_handler,
     utils::DisplayAsDebug,
 };
+use petgraph::Direction;
+use std::{cmp::Reverse, collections::BinaryHeap};
 
 #[derive(Debug, Clone)]
 pub struct ModelBase {
@@ -26,6 +32,45 @@ impl ModelBase {
         Ok(model)
     }
 
+    // the config the model is built from, with the layers in index order
+    pub fn to_config(&self) -> DarknetConfig {
+        DarknetConfig {
+            net: self.net.clone(),
+            layers: (0..self.layers.len())
+                .map(|layer_index| self.layers[&layer_index].config().cloned())
+                .collect(),
+        }
+    }
+
+    // the input and output shapes of every layer for each of the input sizes. the
+    // layer graph is resolved once and shared by all sizes.
+    pub fn infer_shapes_multi(
+        &self,
+        input_sizes: &[Shape],
+    ) -> Result<Vec<IndexMap<usize, (ShapeList, Shape)>>> {
+        let sorted_layer_indexes: Vec<usize> = self.layers.keys().cloned().collect();
+        let from_indexes_map: IndexMap<usize, LayerPositionSet> = self
+            .layers
+            .iter()
+            .map(|(&layer_index, layer)| (layer_index, layer.from_indexes()))
+            .collect();
+        let layers = self.to_config().layers;
+
+        input_sizes
+            .iter()
+            .map(|&input_size| {
+                compute_shapes(
+                    &sorted_layer_indexes,
+                    &from_indexes_map,
+                    &layers,
+                    input_size,
+                    self.net.classes,
+                )
+                .map_err(|err| format_err!("input size {}: {}", input_size, err))
+            })
+            .collect()
+    }
+
     pub fn from_config(config: &DarknetConfig) -> Result<Self> {
         // load config file
         let DarknetConfig {
@@ -49,6 +94,17 @@ impl ModelBase {
                     | LayerConfig::BatchNorm(_)
                     | LayerConfig::MaxPool(_)
                     | LayerConfig::UpSample(_)
+                    | LayerConfig::AvgPool(_)
+                    | LayerConfig::Dropout(_)
+                    | LayerConfig::Softmax(_)
+                    | LayerConfig::Region(_)
+                    | LayerConfig::GaussianYolo(_)
+                    | LayerConfig::ConvLstm(_)
+                    | LayerConfig::Crnn(_)
+                    | LayerConfig::Lstm(_)
+                    | LayerConfig::LocalAvgPool(_)
+                    | LayerConfig::Local(_)
+                    | LayerConfig::Reorg(_)
                     | LayerConfig::Yolo(_) => {
                         if layer_index == 0 {
                             LayerPositionSet::Single(LayerPosition::Input)
@@ -80,6 +136,44 @@ impl ModelBase {
 
                         LayerPositionSet::Multiple(from_indexes)
                     }
+                    // the previous layer provides the scales and the from layer provides the features
+                    LayerConfig::ScaleChannels(conf) => {
+                        ensure!(layer_index > 0, "scale_channels cannot be the first layer");
+                        let from_index = conf
+                            .from
+                            .to_absolute(layer_index)
+                            .ok_or_else(|| format_err!("invalid layer index"))?;
+                        let from_indexes: IndexSet<_> = vec![
+                            LayerPosition::Absolute(layer_index - 1),
+                            LayerPosition::Absolute(from_index),
+                        ]
+                        .into_iter()
+                        .collect();
+                        ensure!(
+                            from_indexes.len() == 2,
+                            "from must not point to the previous layer"
+                        );
+                        LayerPositionSet::Multiple(from_indexes)
+                    }
+                    // the previous layer provides the attention and the from layer provides the features
+                    LayerConfig::Sam(conf) => {
+                        ensure!(layer_index > 0, "sam cannot be the first layer");
+                        let from_index = conf
+                            .from
+                            .to_absolute(layer_index)
+                            .ok_or_else(|| format_err!("invalid layer index"))?;
+                        let from_indexes: IndexSet<_> = vec![
+                            LayerPosition::Absolute(layer_index - 1),
+                            LayerPosition::Absolute(from_index),
+                        ]
+                        .into_iter()
+                        .collect();
+                        ensure!(
+                            from_indexes.len() == 2,
+                            "from must not point to the previous layer"
+                        );
+                        LayerPositionSet::Multiple(from_indexes)
+                    }
                     LayerConfig::Route(conf) => {
                         let from_indexes: IndexSet<_> = conf
                             .layers
@@ -98,7 +192,43 @@ impl ModelBase {
                             .try_collect()?;
                         LayerPositionSet::Multiple(from_indexes)
                     }
+                    // implicit layers do not read from any layer
+                    LayerConfig::Implicit(_) => LayerPositionSet::Empty,
+                    LayerConfig::Custom(conf) => {
+                        let from_indexes: Vec<_> = conf
+                            .from
+                            .iter()
+                            .map(|&index| -> Result<_> {
+                                // the first layer reads the input in place of the previous layer
+                                if layer_index == 0 && index.relative() == Some(1) {
+                                    return Ok(LayerPosition::Input);
+                                }
+                                let index = index.to_absolute(layer_index).ok_or_else(|| {
+                                    format_err!("invalid layer index {}", isize::from(index))
+                                })?;
+                                Ok(LayerPosition::Absolute(index))
+                            })
+                            .try_collect()?;
+                        match *from_indexes.as_slice() {
+                            [] => LayerPositionSet::Empty,
+                            [from_index] => LayerPositionSet::Single(from_index),
+                            _ => LayerPositionSet::Multiple(from_indexes.into_iter().collect()),
+                        }
+                    }
                 };
+
+                // absolute indexes are not bounded by the parser
+                if let Some(index) = from_indexes.iter().find_map(|position| match position {
+                    LayerPosition::Absolute(index) if index >= layers.len() => Some(index),
+                    _ => None,
+                }) {
+                    bail!(
+                        "layer {} refers to non-existent layer {}",
+                        layer_index,
+                        index
+                    );
+                }
+
                 Ok((layer_index, from_indexes))
             })
             .try_collect()?;
@@ -119,23 +249,47 @@ impl ModelBase {
                 graph
             };
 
+            // Kahn's algorithm that picks the smallest ready index first, so that
+            // the layers stay in index order unless forward references force otherwise
             let sorted_layer_indexes: Vec<_> = {
-                let mut sorted_iter = petgraph::algo::toposort(&graph, None)
-                    .map_err(|cycle| {
-                        format_err!("cycle detected at layer index {:?}", cycle.node_id())
-                    })?
-                    .into_iter();
-
-                let first = sorted_iter.next();
-                debug_assert!(
-                    matches!(first, Some(LayerPosition::Input)),
-                    "please report bug"
-                );
+                let mut in_degrees: HashMap<LayerPosition, usize> = graph
+                    .nodes()
+                    .map(|node| {
+                        (
+                            node,
+                            graph.neighbors_directed(node, Direction::Incoming).count(),
+                        )
+                    })
+                    .collect();
+                let mut ready: BinaryHeap<_> = in_degrees
+                    .iter()
+                    .filter(|(_, &in_degree)| in_degree == 0)
+                    .map(|(&node, _)| Reverse(node))
+                    .collect();
+                let mut sorted = vec![];
+
+                while let Some(Reverse(node)) = ready.pop() {
+                    sorted.push(node);
+                    graph
+                        .neighbors_directed(node, Direction::Outgoing)
+                        .for_each(|next| {
+                            let in_degree = in_degrees.get_mut(&next).unwrap();
+                            *in_degree -= 1;
+                            if *in_degree == 0 {
+                                ready.push(Reverse(next));
+                            }
+                        });
+                }
+
+                if sorted.len() != graph.node_count() {
+                    bail!("{}", describe_cycle(&graph));
+                }
 
-                sorted_iter
-                    .map(|layer_index| match layer_index {
-                        LayerPosition::Input => unreachable!("please report bug"),
-                        LayerPosition::Absolute(index) => index,
+                sorted
+                    .into_iter()
+                    .filter_map(|position| match position {
+                        LayerPosition::Input => None,
+                        LayerPosition::Absolute(index) => Some(index),
                     })
                     .collect()
             };
@@ -150,8 +304,264 @@ impl ModelBase {
             .collect();
 
         // compute shapes
-        let shapes_map: IndexMap<usize, (ShapeList, Shape)> =
-            sorted_layer_indexes.iter().try_fold(
+        let shapes_map = compute_shapes(
+            &sorted_layer_indexes,
+            &from_indexes_map,
+            layers,
+            model_input_shape,
+            num_classes,
+        )?;
+
+        // aggregate all computed features
+        let layers: IndexMap<_, _> = {
+            let mut from_indexes_map = from_indexes_map;
+            let mut layer_configs_map = layer_configs_map;
+            let mut shapes_map = shapes_map;
+
+            sorted_layer_indexes
+                .into_iter()
+                .map(|layer_index| -> Result<_> {
+                    let from_indexes = from_indexes_map.remove(&layer_index).unwrap();
+                    let (input_shape, output_shape) = shapes_map.remove(&layer_index).unwrap();
+                    let layer_config = layer_configs_map.remove(&layer_index).unwrap().clone();
+
+                    let layer = match layer_config {
+                        LayerConfig::Connected(conf) => {
+                            let input_shape = input_shape.single_flat().unwrap();
+                            let output_shape = output_shape.flat().unwrap();
+
+                            LayerBase::Connected(ConnectedLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.single().unwrap(),
+                                input_shape,
+                                output_shape,
+                            })
+                        }
+                        LayerConfig::Convolutional(conf) => {
+                            let input_shape = input_shape.single_hwc().unwrap();
+                            let output_shape = output_shape.hwc().unwrap();
+
+                            LayerBase::Convolutional(ConvolutionalLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.single().unwrap(),
+                                input_shape,
+                                output_shape,
+                            })
+                        }
+                        LayerConfig::Route(conf) => {
+                            let input_shape = input_shape.multiple_hwc().unwrap();
+                            let output_shape = output_shape.hwc().unwrap();
+
+                            LayerBase::Route(RouteLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.multiple().unwrap(),
+                                input_shape,
+                                output_shape,
+                            })
+                        }
+                        LayerConfig::Shortcut(conf) => {
+                            let input_shape = input_shape.multiple_hwc().unwrap();
+                            let output_shape = output_shape.hwc().unwrap();
+
+                            LayerBase::Shortcut(ShortcutLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.multiple().unwrap(),
+                                input_shape,
+                                output_shape,
+                            })
+                        }
+                        LayerConfig::MaxPool(conf) => {
+                            let input_shape = input_shape.single_hwc().unwrap();
+                            let output_shape = output_shape.hwc().unwrap();
+                            LayerBase::MaxPool(MaxPoolLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.single().unwrap(),
+                                input_shape,
+                                output_shape,
+                            })
+                        }
+                        LayerConfig::UpSample(conf) => {
+                            let input_shape = input_shape.single_hwc().unwrap();
+                            let output_shape = output_shape.hwc().unwrap();
+
+                            LayerBase::UpSample(UpSampleLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.single().unwrap(),
+                                input_shape,
+                                output_shape,
+                            })
+                        }
+                        LayerConfig::BatchNorm(conf) => {
+                            let input_shape = input_shape.single_hwc().unwrap();
+                            let output_shape = output_shape.hwc().unwrap();
+                            debug_assert_eq!(input_shape, output_shape);
+
+                            LayerBase::BatchNorm(BatchNormLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.single().unwrap(),
+                                inout_shape: input_shape,
+                            })
+                        }
+                        LayerConfig::Yolo(conf) => {
+                            let input_shape = input_shape.single_hwc().unwrap();
+                            let output_shape = output_shape.hwc().unwrap();
+                            debug_assert_eq!(input_shape, output_shape);
+
+                            LayerBase::Yolo(YoloLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.single().unwrap(),
+                                inout_shape: input_shape,
+                            })
+                        }
+                        LayerConfig::Implicit(conf) => {
+                            let output_shape = output_shape.hwc().unwrap();
+
+                            LayerBase::Implicit(ImplicitLayerBase {
+                                config: conf,
+                                output_shape,
+                            })
+                        }
+                        LayerConfig::AvgPool(conf) => {
+                            let input_shape = input_shape.single_hwc().unwrap();
+                            let output_shape = output_shape.hwc().unwrap();
+
+                            LayerBase::AvgPool(AvgPoolLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.single().unwrap(),
+                                input_shape,
+                                output_shape,
+                            })
+                        }
+                        LayerConfig::ScaleChannels(conf) => {
+                            let input_shape = input_shape.multiple_hwc().unwrap();
+                            let output_shape = output_shape.hwc().unwrap();
+
+                            LayerBase::ScaleChannels(ScaleChannelsLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.multiple().unwrap(),
+                                input_shape,
+                                output_shape,
+                            })
+                        }
+                        LayerConfig::Dropout(conf) => LayerBase::Dropout(DropoutLayerBase {
+                            config: conf,
+                            from_indexes: from_indexes.single().unwrap(),
+                            inout_shape: output_shape,
+                        }),
+                        LayerConfig::Softmax(conf) => LayerBase::Softmax(SoftmaxLayerBase {
+                            config: conf,
+                            from_indexes: from_indexes.single().unwrap(),
+                            inout_shape: output_shape,
+                        }),
+                        LayerConfig::Region(conf) => LayerBase::Region(RegionLayerBase {
+                            config: conf,
+                            from_indexes: from_indexes.single().unwrap(),
+                            inout_shape: output_shape.hwc().unwrap(),
+                        }),
+                        LayerConfig::Reorg(conf) => LayerBase::Reorg(ReorgLayerBase {
+                            config: conf,
+                            from_indexes: from_indexes.single().unwrap(),
+                            input_shape: input_shape.single_hwc().unwrap(),
+                            output_shape: output_shape.hwc().unwrap(),
+                        }),
+                        LayerConfig::Local(conf) => LayerBase::Local(LocalLayerBase {
+                            config: conf,
+                            from_indexes: from_indexes.single().unwrap(),
+                            input_shape: input_shape.single_hwc().unwrap(),
+                            output_shape: output_shape.hwc().unwrap(),
+                        }),
+                        LayerConfig::LocalAvgPool(conf) => {
+                            LayerBase::LocalAvgPool(LocalAvgPoolLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.single().unwrap(),
+                                input_shape: input_shape.single_hwc().unwrap(),
+                                output_shape: output_shape.hwc().unwrap(),
+                            })
+                        }
+                        LayerConfig::Sam(conf) => LayerBase::Sam(SamLayerBase {
+                            config: conf,
+                            from_indexes: from_indexes.multiple().unwrap(),
+                            input_shape: input_shape.multiple_hwc().unwrap(),
+                            output_shape: output_shape.hwc().unwrap(),
+                        }),
+                        LayerConfig::Lstm(conf) => LayerBase::Lstm(LstmLayerBase {
+                            config: conf,
+                            from_indexes: from_indexes.single().unwrap(),
+                            input_shape: input_shape.single_flat().unwrap(),
+                            output_shape: output_shape.flat().unwrap(),
+                        }),
+                        LayerConfig::Crnn(conf) => LayerBase::Crnn(CrnnLayerBase {
+                            config: conf,
+                            from_indexes: from_indexes.single().unwrap(),
+                            input_shape: input_shape.single_hwc().unwrap(),
+                            output_shape: output_shape.hwc().unwrap(),
+                        }),
+                        LayerConfig::ConvLstm(conf) => LayerBase::ConvLstm(ConvLstmLayerBase {
+                            config: conf,
+                            from_indexes: from_indexes.single().unwrap(),
+                            input_shape: input_shape.single_hwc().unwrap(),
+                            output_shape: output_shape.hwc().unwrap(),
+                        }),
+                        LayerConfig::GaussianYolo(conf) => {
+                            LayerBase::GaussianYolo(GaussianYoloLayerBase {
+                                config: conf,
+                                from_indexes: from_indexes.single().unwrap(),
+                                inout_shape: output_shape.hwc().unwrap(),
+                            })
+                        }
+                        LayerConfig::Custom(conf) => LayerBase::Custom(CustomLayerBase {
+                            config: conf,
+                            from_indexes,
+                            input_shape,
+                            output_shape,
+                        }),
+                    };
+
+                    Ok((layer_index, layer))
+                })
+                .try_collect()?
+        };
+
+        // network parameters
+        let net = config.net.clone();
+        let seen = 0;
+        let cur_iteration = 0;
+
+        // print layer params for debugging
+        #[cfg(debug_assertions)]
+        {
+            let num_layers = layers.len();
+            (0..num_layers).for_each(|layer_index| {
+                let layer = &layers[&layer_index];
+
+                debug!(
+                    "{}\t{}\t{}\t{}",
+                    layer_index,
+                    layer.config(),
+                    layer.input_shape(),
+                    layer.output_shape()
+                );
+            });
+        }
+
+        Ok(Self {
+            seen,
+            cur_iteration,
+            net,
+            layers,
+        })
+    }
+}
+
+// compute the input and output shapes of the layers in topological order
+fn compute_shapes(
+    sorted_layer_indexes: &[usize],
+    from_indexes_map: &IndexMap<usize, LayerPositionSet>,
+    layers: &[LayerConfig],
+    model_input_shape: Shape,
+    num_classes: u64,
+) -> Result<IndexMap<usize, (ShapeList, Shape)>> {
+    sorted_layer_indexes.iter().try_fold(
                 IndexMap::new(),
                 |mut collected, layer_index| -> Result<_> {
                     // closures
@@ -223,13 +633,15 @@ impl ModelBase {
                     };
 
                     let from_index = from_indexes_map.get(layer_index).expect("please report bug");
-                    let layer_config = layer_configs_map.get(layer_index).expect("please report bug");
+                    let layer_config = &layers[*layer_index];
 
                     let (input_shape, output_shape) = match layer_config {
                         LayerConfig::Convolutional(conf) => {
                             let input_shape = hwc_input_shape(from_index)
                                 .ok_or_else(|| format_err!("invalid shape"))?;
-                            let output_shape = conf.output_shape(input_shape);
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
                             (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
                         }
                         LayerConfig::Connected(conf) => {
@@ -262,7 +674,9 @@ impl ModelBase {
                         LayerConfig::MaxPool(conf) => {
                             let input_shape = hwc_input_shape(from_index)
                                 .ok_or_else(|| format_err!("invalid shape"))?;
-                            let output_shape = conf.output_shape(input_shape);
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
                             (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
                         }
                         LayerConfig::Route(conf) => {
@@ -288,7 +702,9 @@ impl ModelBase {
                         LayerConfig::UpSample(conf) => {
                             let input_shape = hwc_input_shape(from_index)
                                 .ok_or_else(|| format_err!("invalid shape"))?;
-                            let output_shape = conf.output_shape(input_shape);
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
                             (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
                         }
                         LayerConfig::Yolo(conf) => {
@@ -306,160 +722,194 @@ impl ModelBase {
                             let output_shape = input_shape;
                             (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
                         }
-                    };
-
-                    collected.insert(*layer_index, (input_shape, output_shape));
-
-                    Ok(collected)
-                },
-            )?;
-
-        // aggregate all computed features
-        let layers: IndexMap<_, _> = {
-            let mut from_indexes_map = from_indexes_map;
-            let mut layer_configs_map = layer_configs_map;
-            let mut shapes_map = shapes_map;
-
-            sorted_layer_indexes
-                .into_iter()
-                .map(|layer_index| -> Result<_> {
-                    let from_indexes = from_indexes_map.remove(&layer_index).unwrap();
-                    let (input_shape, output_shape) = shapes_map.remove(&layer_index).unwrap();
-                    let layer_config = layer_configs_map.remove(&layer_index).unwrap().clone();
-
-                    let layer = match layer_config {
-                        LayerConfig::Connected(conf) => {
-                            let input_shape = input_shape.single_flat().unwrap();
-                            let output_shape = output_shape.flat().unwrap();
+                        LayerConfig::Implicit(conf) => {
+                            (ShapeList::MultipleHwc(vec![]), Shape::Hwc(conf.output_shape()))
+                        }
+                        LayerConfig::AvgPool(conf) => {
+                            let input_shape = hwc_input_shape(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let output_shape = conf.output_shape(input_shape);
+                            (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
+                        }
+                        LayerConfig::ScaleChannels(conf) => {
+                            let input_shapes = multiple_hwc_input_shapes(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let [scale_h, scale_w, scale_c] = input_shapes[0];
+                            let [in_h, in_w, in_c] = input_shapes[1];
+
+                            if conf.scale_wh {
+                                ensure!(
+                                    [scale_h, scale_w, scale_c] == [in_h, in_w, 1],
+                                    "the scale shape {:?} does not match the feature shape {:?}",
+                                    input_shapes[0],
+                                    input_shapes[1]
+                                );
+                            } else {
+                                ensure!(
+                                    [scale_h, scale_w, scale_c] == [1, 1, in_c],
+                                    "the scale shape {:?} does not match the feature shape {:?}",
+                                    input_shapes[0],
+                                    input_shapes[1]
+                                );
+                            }
 
-                            LayerBase::Connected(ConnectedLayerBase {
-                                config: conf,
-                                from_indexes: from_indexes.single().unwrap(),
-                                input_shape,
-                                output_shape,
-                            })
+                            let output_shape = input_shapes[1];
+                            (ShapeList::MultipleHwc(input_shapes), Shape::Hwc(output_shape))
+                        }
+                        LayerConfig::Dropout(_conf) => {
+                            let (input_shape, output_shape) = match hwc_input_shape(from_index) {
+                                Some(hwc) => (ShapeList::SingleHwc(hwc), Shape::Hwc(hwc)),
+                                None => {
+                                    let flat = flat_input_shape(from_index)
+                                        .ok_or_else(|| format_err!("invalid shape"))?;
+                                    (ShapeList::SingleFlat(flat), Shape::Flat(flat))
+                                }
+                            };
+                            (input_shape, output_shape)
+                        }
+                        LayerConfig::Softmax(conf) => {
+                            let input_shape = match hwc_input_shape(from_index) {
+                                Some(hwc) => Shape::Hwc(hwc),
+                                None => Shape::Flat(
+                                    flat_input_shape(from_index)
+                                        .ok_or_else(|| format_err!("invalid shape"))?,
+                                ),
+                            };
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
+                            let input_shape = match input_shape {
+                                Shape::Hwc(hwc) => ShapeList::SingleHwc(hwc),
+                                Shape::Flat(flat) => ShapeList::SingleFlat(flat),
+                            };
+                            (input_shape, output_shape)
+                        }
+                        LayerConfig::Reorg(conf) => {
+                            let input_shape = hwc_input_shape(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
+                            (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
+                        }
+                        LayerConfig::Local(conf) => {
+                            let input_shape = hwc_input_shape(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
+                            (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
+                        }
+                        LayerConfig::LocalAvgPool(conf) => {
+                            let input_shape = hwc_input_shape(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
+                            (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
                         }
-                        LayerConfig::Convolutional(conf) => {
-                            let input_shape = input_shape.single_hwc().unwrap();
-                            let output_shape = output_shape.hwc().unwrap();
-
-                            LayerBase::Convolutional(ConvolutionalLayerBase {
-                                config: conf,
-                                from_indexes: from_indexes.single().unwrap(),
-                                input_shape,
-                                output_shape,
-                            })
+                        LayerConfig::Sam(_conf) => {
+                            let input_shapes = multiple_hwc_input_shapes(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            ensure!(
+                                input_shapes[0] == input_shapes[1],
+                                "the attention shape {:?} does not match the feature shape {:?}",
+                                input_shapes[0],
+                                input_shapes[1]
+                            );
+                            let output_shape = input_shapes[1];
+                            (ShapeList::MultipleHwc(input_shapes), Shape::Hwc(output_shape))
                         }
-                        LayerConfig::Route(conf) => {
-                            let input_shape = input_shape.multiple_hwc().unwrap();
-                            let output_shape = output_shape.hwc().unwrap();
-
-                            LayerBase::Route(RouteLayerBase {
-                                config: conf,
-                                from_indexes: from_indexes.multiple().unwrap(),
-                                input_shape,
-                                output_shape,
-                            })
+                        LayerConfig::Lstm(conf) => {
+                            let input_shape = flat_input_shape(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let output_shape = conf.output;
+                            (ShapeList::SingleFlat(input_shape), Shape::Flat(output_shape))
                         }
-                        LayerConfig::Shortcut(conf) => {
-                            let input_shape = input_shape.multiple_hwc().unwrap();
-                            let output_shape = output_shape.hwc().unwrap();
-
-                            LayerBase::Shortcut(ShortcutLayerBase {
-                                config: conf,
-                                from_indexes: from_indexes.multiple().unwrap(),
-                                input_shape,
-                                output_shape,
-                            })
+                        LayerConfig::Crnn(conf) => {
+                            let input_shape = hwc_input_shape(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
+                            (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
                         }
-                        LayerConfig::MaxPool(conf) => {
-                            let input_shape = input_shape.single_hwc().unwrap();
-                            let output_shape = output_shape.hwc().unwrap();
-                            LayerBase::MaxPool(MaxPoolLayerBase {
-                                config: conf,
-                                from_indexes: from_indexes.single().unwrap(),
-                                input_shape,
-                                output_shape,
-                            })
+                        LayerConfig::ConvLstm(conf) => {
+                            let input_shape = hwc_input_shape(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
+                            (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
                         }
-                        LayerConfig::UpSample(conf) => {
-                            let input_shape = input_shape.single_hwc().unwrap();
-                            let output_shape = output_shape.hwc().unwrap();
-
-                            LayerBase::UpSample(UpSampleLayerBase {
-                                config: conf,
-                                from_indexes: from_indexes.single().unwrap(),
-                                input_shape,
-                                output_shape,
-                            })
+                        LayerConfig::GaussianYolo(conf) => {
+                            let input_shape = hwc_input_shape(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let num_anchors = conf.yolo.anchors.len() as u64;
+                            let expect = num_anchors
+                                * CompoundGaussianYoloConfig::num_entries(num_classes);
+                            ensure!(
+                                input_shape[2] == expect,
+                                "layer {} ({}): the Gaussian_yolo layer expects {} input channels, but {} are given",
+                                layer_index,
+                                layer_config,
+                                expect,
+                                input_shape[2]
+                            );
+                            (ShapeList::SingleHwc(input_shape), Shape::Hwc(input_shape))
                         }
-                        LayerConfig::BatchNorm(conf) => {
-                            let input_shape = input_shape.single_hwc().unwrap();
-                            let output_shape = output_shape.hwc().unwrap();
-                            debug_assert_eq!(input_shape, output_shape);
-
-                            LayerBase::BatchNorm(BatchNormLayerBase {
-                                config: conf,
-                                from_indexes: from_indexes.single().unwrap(),
-                                inout_shape: input_shape,
-                            })
+                        LayerConfig::Region(conf) => {
+                            let input_shape = hwc_input_shape(from_index)
+                                .ok_or_else(|| format_err!("invalid shape"))?;
+                            let output_shape = conf.output_shape(input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
+                            (ShapeList::SingleHwc(input_shape), Shape::Hwc(output_shape))
                         }
-                        LayerConfig::Yolo(conf) => {
-                            let input_shape = input_shape.single_hwc().unwrap();
-                            let output_shape = output_shape.hwc().unwrap();
-                            debug_assert_eq!(input_shape, output_shape);
-
-                            LayerBase::Yolo(YoloLayerBase {
-                                config: conf,
-                                from_indexes: from_indexes.single().unwrap(),
-                                inout_shape: input_shape,
-                            })
+                        LayerConfig::Custom(conf) => {
+                            let handler = expect_section_handler(&conf.section)?;
+                            let input_shape = match from_index {
+                                LayerPositionSet::Empty => ShapeList::MultipleHwc(vec![]),
+                                LayerPositionSet::Single(_) => match hwc_input_shape(from_index) {
+                                    Some(hwc) => ShapeList::SingleHwc(hwc),
+                                    None => ShapeList::SingleFlat(
+                                        flat_input_shape(from_index)
+                                            .ok_or_else(|| format_err!("invalid shape"))?,
+                                    ),
+                                },
+                                LayerPositionSet::Multiple(_) => ShapeList::MultipleHwc(
+                                    multiple_hwc_input_shapes(from_index)
+                                        .ok_or_else(|| format_err!("invalid shape"))?,
+                                ),
+                            };
+                            let output_shape = handler.output_shape(conf, &input_shape).map_err(|err| {
+                                format_err!("layer {} ({}): {}", layer_index, layer_config, err)
+                            })?;
+                            (input_shape, output_shape)
                         }
                     };
 
-                    Ok((layer_index, layer))
-                })
-                .try_collect()?
-        };
-
-        // network parameters
-        let net = config.net.clone();
-        let seen = 0;
-        let cur_iteration = 0;
+                    collected.insert(*layer_index, (input_shape, output_shape));
 
-        // print layer params for debugging
-        #[cfg(debug_assertions)]
-        {
-            let num_layers = layers.len();
-            (0..num_layers).for_each(|layer_index| {
-                let layer = &layers[&layer_index];
-                let kind = match layer {
-                    LayerBase::Convolutional(_) => "conv",
-                    LayerBase::Connected(_) => "connected",
-                    LayerBase::BatchNorm(_) => "batch_norm",
-                    LayerBase::Shortcut(_) => "shortcut",
-                    LayerBase::MaxPool(_) => "max_pool",
-                    LayerBase::Route(_) => "route",
-                    LayerBase::UpSample(_) => "up_sample",
-                    LayerBase::Yolo(_) => "yolo",
-                };
+                    Ok(collected)
+                },
+    )
+}
 
-                debug!(
-                    "{}\t{}\t{:?}\t{:?}",
-                    layer_index,
-                    kind,
-                    layer.input_shape(),
-                    layer.output_shape()
-                );
-            });
-        }
+// name the layers of one dependency cycle in the graph
+fn describe_cycle(graph: &DiGraphMap<LayerPosition, ()>) -> String {
+    let self_loop = graph.nodes().find(|&node| graph.contains_edge(node, node));
+    if let Some(node) = self_loop {
+        return format!("layer {} refers to itself", node);
+    }
 
-        Ok(Self {
-            seen,
-            cur_iteration,
-            net,
-            layers,
-        })
+    let cycle = petgraph::algo::tarjan_scc(graph)
+        .into_iter()
+        .find(|component| component.len() > 1)
+        .map(|component| component.into_iter().sorted().join(", "));
+    match cycle {
+        Some(layers) => format!("layers {} form a dependency cycle", layers),
+        None => "the layers form a dependency cycle".into(),
     }
 }
 
@@ -601,9 +1051,80 @@ pub enum LayerBase {
     UpSample(UpSampleLayerBase),
     Yolo(YoloLayerBase),
     BatchNorm(BatchNormLayerBase),
+    Implicit(ImplicitLayerBase),
+    AvgPool(AvgPoolLayerBase),
+    ScaleChannels(ScaleChannelsLayerBase),
+    Dropout(DropoutLayerBase),
+    Softmax(SoftmaxLayerBase),
+    Region(RegionLayerBase),
+    GaussianYolo(GaussianYoloLayerBase),
+    ConvLstm(ConvLstmLayerBase),
+    Crnn(CrnnLayerBase),
+    Lstm(LstmLayerBase),
+    Sam(SamLayerBase),
+    LocalAvgPool(LocalAvgPoolLayerBase),
+    Local(LocalLayerBase),
+    Reorg(ReorgLayerBase),
+    Custom(CustomLayerBase),
 }
 
 impl LayerBase {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Convolutional(_) => "conv",
+            Self::Connected(_) => "connected",
+            Self::BatchNorm(_) => "batch_norm",
+            Self::Shortcut(_) => "shortcut",
+            Self::MaxPool(_) => "max_pool",
+            Self::Route(_) => "route",
+            Self::UpSample(_) => "up_sample",
+            Self::Yolo(_) => "yolo",
+            Self::Implicit(_) => "implicit",
+            Self::AvgPool(_) => "avg_pool",
+            Self::ScaleChannels(_) => "scale_channels",
+            Self::Dropout(_) => "dropout",
+            Self::Softmax(_) => "softmax",
+            Self::Region(_) => "region",
+            Self::GaussianYolo(_) => "gaussian_yolo",
+            Self::ConvLstm(_) => "conv_lstm",
+            Self::Crnn(_) => "crnn",
+            Self::Lstm(_) => "lstm",
+            Self::Sam(_) => "sam",
+            Self::LocalAvgPool(_) => "local_avgpool",
+            Self::Local(_) => "local",
+            Self::Reorg(_) => "reorg",
+            Self::Custom(_) => "custom",
+        }
+    }
+
+    pub fn config(&self) -> LayerConfigRef<'_> {
+        match self {
+            Self::Connected(layer) => LayerConfigRef::Connected(&layer.config),
+            Self::Convolutional(layer) => LayerConfigRef::Convolutional(&layer.config),
+            Self::Route(layer) => LayerConfigRef::Route(&layer.config),
+            Self::Shortcut(layer) => LayerConfigRef::Shortcut(&layer.config),
+            Self::MaxPool(layer) => LayerConfigRef::MaxPool(&layer.config),
+            Self::UpSample(layer) => LayerConfigRef::UpSample(&layer.config),
+            Self::Yolo(layer) => LayerConfigRef::Yolo(&layer.config),
+            Self::BatchNorm(layer) => LayerConfigRef::BatchNorm(&layer.config),
+            Self::Implicit(layer) => LayerConfigRef::Implicit(&layer.config),
+            Self::AvgPool(layer) => LayerConfigRef::AvgPool(&layer.config),
+            Self::ScaleChannels(layer) => LayerConfigRef::ScaleChannels(&layer.config),
+            Self::Dropout(layer) => LayerConfigRef::Dropout(&layer.config),
+            Self::Softmax(layer) => LayerConfigRef::Softmax(&layer.config),
+            Self::Region(layer) => LayerConfigRef::Region(&layer.config),
+            Self::GaussianYolo(layer) => LayerConfigRef::GaussianYolo(&layer.config),
+            Self::ConvLstm(layer) => LayerConfigRef::ConvLstm(&layer.config),
+            Self::Crnn(layer) => LayerConfigRef::Crnn(&layer.config),
+            Self::Lstm(layer) => LayerConfigRef::Lstm(&layer.config),
+            Self::Sam(layer) => LayerConfigRef::Sam(&layer.config),
+            Self::LocalAvgPool(layer) => LayerConfigRef::LocalAvgPool(&layer.config),
+            Self::Local(layer) => LayerConfigRef::Local(&layer.config),
+            Self::Reorg(layer) => LayerConfigRef::Reorg(&layer.config),
+            Self::Custom(layer) => LayerConfigRef::Custom(&layer.config),
+        }
+    }
+
     pub fn input_shape(&self) -> ShapeList {
         match self {
             Self::Connected(layer) => ShapeList::SingleFlat(layer.input_shape),
@@ -614,6 +1135,27 @@ impl LayerBase {
             Self::UpSample(layer) => ShapeList::SingleHwc(layer.input_shape),
             Self::Yolo(layer) => ShapeList::SingleHwc(layer.inout_shape),
             Self::BatchNorm(layer) => ShapeList::SingleHwc(layer.inout_shape),
+            Self::Implicit(_) => ShapeList::MultipleHwc(vec![]),
+            Self::AvgPool(layer) => ShapeList::SingleHwc(layer.input_shape),
+            Self::ScaleChannels(layer) => ShapeList::MultipleHwc(layer.input_shape.clone()),
+            Self::Dropout(layer) => match layer.inout_shape {
+                Shape::Hwc(hwc) => ShapeList::SingleHwc(hwc),
+                Shape::Flat(flat) => ShapeList::SingleFlat(flat),
+            },
+            Self::Softmax(layer) => match layer.inout_shape {
+                Shape::Hwc(hwc) => ShapeList::SingleHwc(hwc),
+                Shape::Flat(flat) => ShapeList::SingleFlat(flat),
+            },
+            Self::Region(layer) => ShapeList::SingleHwc(layer.inout_shape),
+            Self::GaussianYolo(layer) => ShapeList::SingleHwc(layer.inout_shape),
+            Self::ConvLstm(layer) => ShapeList::SingleHwc(layer.input_shape),
+            Self::Crnn(layer) => ShapeList::SingleHwc(layer.input_shape),
+            Self::Lstm(layer) => ShapeList::SingleFlat(layer.input_shape),
+            Self::Sam(layer) => ShapeList::MultipleHwc(layer.input_shape.clone()),
+            Self::LocalAvgPool(layer) => ShapeList::SingleHwc(layer.input_shape),
+            Self::Local(layer) => ShapeList::SingleHwc(layer.input_shape),
+            Self::Reorg(layer) => ShapeList::SingleHwc(layer.input_shape),
+            Self::Custom(layer) => layer.input_shape.clone(),
         }
     }
 
@@ -627,6 +1169,21 @@ impl LayerBase {
             Self::UpSample(layer) => Shape::Hwc(layer.output_shape),
             Self::Yolo(layer) => Shape::Hwc(layer.inout_shape),
             Self::BatchNorm(layer) => Shape::Hwc(layer.inout_shape),
+            Self::Implicit(layer) => Shape::Hwc(layer.output_shape),
+            Self::AvgPool(layer) => Shape::Hwc(layer.output_shape),
+            Self::ScaleChannels(layer) => Shape::Hwc(layer.output_shape),
+            Self::Dropout(layer) => layer.inout_shape,
+            Self::Softmax(layer) => layer.inout_shape,
+            Self::Region(layer) => Shape::Hwc(layer.inout_shape),
+            Self::GaussianYolo(layer) => Shape::Hwc(layer.inout_shape),
+            Self::ConvLstm(layer) => Shape::Hwc(layer.output_shape),
+            Self::Crnn(layer) => Shape::Hwc(layer.output_shape),
+            Self::Lstm(layer) => Shape::Flat(layer.output_shape),
+            Self::Sam(layer) => Shape::Hwc(layer.output_shape),
+            Self::LocalAvgPool(layer) => Shape::Hwc(layer.output_shape),
+            Self::Local(layer) => Shape::Hwc(layer.output_shape),
+            Self::Reorg(layer) => Shape::Hwc(layer.output_shape),
+            Self::Custom(layer) => layer.output_shape,
         }
     }
 
@@ -640,6 +1197,21 @@ impl LayerBase {
             Self::UpSample(layer) => LayerPositionSet::Single(layer.from_indexes),
             Self::Yolo(layer) => LayerPositionSet::Single(layer.from_indexes),
             Self::BatchNorm(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::Implicit(_) => LayerPositionSet::Empty,
+            Self::AvgPool(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::ScaleChannels(layer) => LayerPositionSet::Multiple(layer.from_indexes.clone()),
+            Self::Dropout(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::Softmax(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::Region(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::GaussianYolo(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::ConvLstm(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::Crnn(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::Lstm(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::Sam(layer) => LayerPositionSet::Multiple(layer.from_indexes.clone()),
+            Self::LocalAvgPool(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::Local(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::Reorg(layer) => LayerPositionSet::Single(layer.from_indexes),
+            Self::Custom(layer) => layer.from_indexes.clone(),
         }
     }
 }
@@ -703,8 +1275,82 @@ declare_layer_base_inout_shape!(
     [u64; 3],
     [u64; 3]
 );
+declare_layer_base_inout_shape!(
+    AvgPoolLayerBase,
+    AvgPoolConfig,
+    LayerPosition,
+    [u64; 3],
+    [u64; 3]
+);
+declare_layer_base_inout_shape!(
+    ScaleChannelsLayerBase,
+    ScaleChannelsConfig,
+    IndexSet<LayerPosition>,
+    Vec<[u64; 3]>,
+    [u64; 3]
+);
 declare_layer_base_single_shape!(YoloLayerBase, CompoundYoloConfig, LayerPosition, [u64; 3]);
 declare_layer_base_single_shape!(BatchNormLayerBase, BatchNormConfig, LayerPosition, [u64; 3]);
+declare_layer_base_single_shape!(DropoutLayerBase, DropoutConfig, LayerPosition, Shape);
+declare_layer_base_single_shape!(SoftmaxLayerBase, SoftmaxConfig, LayerPosition, Shape);
+declare_layer_base_single_shape!(RegionLayerBase, RegionConfig, LayerPosition, [u64; 3]);
+declare_layer_base_single_shape!(
+    GaussianYoloLayerBase,
+    CompoundGaussianYoloConfig,
+    LayerPosition,
+    [u64; 3]
+);
+declare_layer_base_inout_shape!(
+    ConvLstmLayerBase,
+    ConvLstmConfig,
+    LayerPosition,
+    [u64; 3],
+    [u64; 3]
+);
+declare_layer_base_inout_shape!(CrnnLayerBase, CrnnConfig, LayerPosition, [u64; 3], [u64; 3]);
+declare_layer_base_inout_shape!(LstmLayerBase, LstmConfig, LayerPosition, u64, u64);
+declare_layer_base_inout_shape!(
+    SamLayerBase,
+    SamConfig,
+    IndexSet<LayerPosition>,
+    Vec<[u64; 3]>,
+    [u64; 3]
+);
+declare_layer_base_inout_shape!(
+    LocalAvgPoolLayerBase,
+    LocalAvgPoolConfig,
+    LayerPosition,
+    [u64; 3],
+    [u64; 3]
+);
+declare_layer_base_inout_shape!(
+    LocalLayerBase,
+    LocalConfig,
+    LayerPosition,
+    [u64; 3],
+    [u64; 3]
+);
+declare_layer_base_inout_shape!(
+    ReorgLayerBase,
+    ReorgConfig,
+    LayerPosition,
+    [u64; 3],
+    [u64; 3]
+);
+
+#[derive(Debug, Clone)]
+pub struct ImplicitLayerBase {
+    pub config: ImplicitConfig,
+    pub output_shape: [u64; 3],
+}
+
+#[derive(Debug, Clone)]
+pub struct CustomLayerBase {
+    pub config: CustomConfig,
+    pub from_indexes: LayerPositionSet,
+    pub input_shape: ShapeList,
+    pub output_shape: Shape,
+}
 
 impl From<ConnectedLayerBase> for LayerBase {
     fn from(from: ConnectedLayerBase) -> Self {
@@ -754,6 +1400,96 @@ impl From<BatchNormLayerBase> for LayerBase {
     }
 }
 
+impl From<ImplicitLayerBase> for LayerBase {
+    fn from(from: ImplicitLayerBase) -> Self {
+        Self::Implicit(from)
+    }
+}
+
+impl From<AvgPoolLayerBase> for LayerBase {
+    fn from(from: AvgPoolLayerBase) -> Self {
+        Self::AvgPool(from)
+    }
+}
+
+impl From<ScaleChannelsLayerBase> for LayerBase {
+    fn from(from: ScaleChannelsLayerBase) -> Self {
+        Self::ScaleChannels(from)
+    }
+}
+
+impl From<DropoutLayerBase> for LayerBase {
+    fn from(from: DropoutLayerBase) -> Self {
+        Self::Dropout(from)
+    }
+}
+
+impl From<SoftmaxLayerBase> for LayerBase {
+    fn from(from: SoftmaxLayerBase) -> Self {
+        Self::Softmax(from)
+    }
+}
+
+impl From<RegionLayerBase> for LayerBase {
+    fn from(from: RegionLayerBase) -> Self {
+        Self::Region(from)
+    }
+}
+
+impl From<GaussianYoloLayerBase> for LayerBase {
+    fn from(from: GaussianYoloLayerBase) -> Self {
+        Self::GaussianYolo(from)
+    }
+}
+
+impl From<ConvLstmLayerBase> for LayerBase {
+    fn from(from: ConvLstmLayerBase) -> Self {
+        Self::ConvLstm(from)
+    }
+}
+
+impl From<CrnnLayerBase> for LayerBase {
+    fn from(from: CrnnLayerBase) -> Self {
+        Self::Crnn(from)
+    }
+}
+
+impl From<LstmLayerBase> for LayerBase {
+    fn from(from: LstmLayerBase) -> Self {
+        Self::Lstm(from)
+    }
+}
+
+impl From<SamLayerBase> for LayerBase {
+    fn from(from: SamLayerBase) -> Self {
+        Self::Sam(from)
+    }
+}
+
+impl From<LocalAvgPoolLayerBase> for LayerBase {
+    fn from(from: LocalAvgPoolLayerBase) -> Self {
+        Self::LocalAvgPool(from)
+    }
+}
+
+impl From<LocalLayerBase> for LayerBase {
+    fn from(from: LocalLayerBase) -> Self {
+        Self::Local(from)
+    }
+}
+
+impl From<ReorgLayerBase> for LayerBase {
+    fn from(from: ReorgLayerBase) -> Self {
+        Self::Reorg(from)
+    }
+}
+
+impl From<CustomLayerBase> for LayerBase {
+    fn from(from: CustomLayerBase) -> Self {
+        Self::Custom(from)
+    }
+}
+
 impl ConvolutionalLayerBase {
     pub fn weights_shape(&self) -> [u64; 4] {
         let Self {
@@ -772,3 +1508,100 @@ impl ConvolutionalLayerBase {
         [in_c / groups, filters, size, size]
     }
 }
+
+impl LocalLayerBase {
+    // the kernels are stored per output location, each in the layout of a
+    // convolution kernel
+    pub fn weights_shape(&self) -> [u64; 3] {
+        let Self {
+            config: LocalConfig { size, .. },
+            input_shape: [_in_h, _in_w, in_c],
+            output_shape: [out_h, out_w, filters],
+            ..
+        } = *self;
+        [out_h * out_w, filters, in_c * size * size]
+    }
+
+    pub fn num_biases(&self) -> u64 {
+        let [out_h, out_w, filters] = self.output_shape;
+        out_h * out_w * filters
+    }
+}
+
+// the connected gates of a lstm layer in file order. the w gates take the
+// previous output and the u gates take the input.
+pub const LSTM_GATES: [&str; 8] = ["wf", "wi", "wg", "wo", "uf", "ui", "ug", "uo"];
+
+impl LstmLayerBase {
+    // the (inputs, outputs) of each gate in the order of LSTM_GATES
+    pub fn gate_shapes(&self) -> [(u64, u64); 8] {
+        let Self {
+            input_shape,
+            output_shape,
+            ..
+        } = *self;
+        let recurrent = (output_shape, output_shape);
+        let input = (input_shape, output_shape);
+        [
+            recurrent, recurrent, recurrent, recurrent, input, input, input, input,
+        ]
+    }
+}
+
+// the convolutions of a crnn layer in file order. the self convolution maps the
+// hidden state of the previous time step.
+pub const CRNN_CONVS: [&str; 3] = ["input", "self", "output"];
+
+// the convolutions of a conv_lstm layer in file order. the v gates are the
+// peepholes over the cell state, the w gates take the previous output and the u
+// gates take the input. bottleneck layers have wf as the only w gate.
+pub const CONV_LSTM_GATES: [&str; 11] = [
+    "vf", "vi", "vo", "wf", "wi", "wg", "wo", "uf", "ui", "ug", "uo",
+];
+
+impl ConvLstmLayerBase {
+    // the (gate, input channels, filters) of the convolutions present in the layer,
+    // in the order of CONV_LSTM_GATES
+    pub fn gate_shapes(&self) -> Vec<(&'static str, u64, u64)> {
+        let Self {
+            config:
+                ConvLstmConfig {
+                    output,
+                    peephole,
+                    bottleneck,
+                    ..
+                },
+            input_shape: [_, _, in_c],
+            ..
+        } = *self;
+        CONV_LSTM_GATES
+            .iter()
+            .filter_map(|&gate| {
+                let inputs = match gate {
+                    "vf" | "vi" | "vo" if !peephole => return None,
+                    "wi" | "wg" | "wo" if bottleneck => return None,
+                    "wf" if bottleneck => output * 2,
+                    "uf" | "ui" | "ug" | "uo" => in_c,
+                    _ => output,
+                };
+                Some((gate, inputs, output))
+            })
+            .collect()
+    }
+}
+
+impl CrnnLayerBase {
+    // the (convolution, input channels, filters) in the order of CRNN_CONVS
+    pub fn conv_shapes(&self) -> [(&'static str, u64, u64); 3] {
+        let Self {
+            config: CrnnConfig { hidden, output, .. },
+            input_shape: [_, _, in_c],
+            ..
+        } = *self;
+        [
+            (CRNN_CONVS[0], in_c, hidden),
+            (CRNN_CONVS[1], hidden, hidden),
+            (CRNN_CONVS[2], hidden, output),
+        ]
+    }
+}
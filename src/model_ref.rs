@@ -0,0 +1,164 @@
+use crate::{
+    common::*,
+    config::{DarknetConfig, Shape},
+    darknet::DarknetModel,
+    model::{ModelBase, ShapeList},
+    summary::ModelSummary,
+    weights_cache::WeightsStats,
+};
+use std::sync::OnceLock;
+
+type ShapeTable = IndexMap<usize, (ShapeList, Shape)>;
+
+// a cheaply cloneable, immutable view of a model that can be shared across
+// threads. the weights are loaded on first use, and derived data is computed
+// once and cached for all clones.
+#[derive(Debug, Clone)]
+pub struct ModelRef {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: DarknetConfig,
+    base: ModelBase,
+    weights_file: Option<PathBuf>,
+    // the lock is held while loading, so that concurrent callers load only once
+    weights: Mutex<Option<Arc<DarknetModel>>>,
+    summary: OnceLock<Arc<ModelSummary>>,
+    shapes: Mutex<HashMap<Shape, Arc<ShapeTable>>>,
+    weights_stats: OnceLock<Arc<IndexMap<usize, Option<WeightsStats>>>>,
+}
+
+impl ModelRef {
+    pub fn new(config: DarknetConfig) -> Result<Self> {
+        Self::build(config, None)
+    }
+
+    // the weights file is not read until the weights are requested
+    pub fn with_weights<P>(config: DarknetConfig, weights_file: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::build(config, Some(weights_file.as_ref().to_owned()))
+    }
+
+    pub fn from_files<P1, P2>(config_file: P1, weights_file: P2) -> Result<Self>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        Self::with_weights(DarknetConfig::load(config_file)?, weights_file)
+    }
+
+    fn build(config: DarknetConfig, weights_file: Option<PathBuf>) -> Result<Self> {
+        let base = ModelBase::from_config(&config)?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                config,
+                base,
+                weights_file,
+                weights: Mutex::new(None),
+                summary: OnceLock::new(),
+                shapes: Mutex::new(HashMap::new()),
+                weights_stats: OnceLock::new(),
+            }),
+        })
+    }
+
+    pub fn config(&self) -> &DarknetConfig {
+        &self.inner.config
+    }
+
+    pub fn base(&self) -> &ModelBase {
+        &self.inner.base
+    }
+
+    pub fn weights_file(&self) -> Option<&Path> {
+        self.inner.weights_file.as_deref()
+    }
+
+    // true if the weights are already in memory
+    pub fn weights_loaded(&self) -> bool {
+        self.inner.weights.lock().unwrap().is_some()
+    }
+
+    // load the weights on the first call. a failed load is not cached and is
+    // retried by the next call.
+    pub fn weights(&self) -> Result<Arc<DarknetModel>> {
+        let mut weights = self.inner.weights.lock().unwrap();
+        if let Some(model) = &*weights {
+            return Ok(model.clone());
+        }
+
+        let weights_file = self
+            .inner
+            .weights_file
+            .as_ref()
+            .ok_or_else(|| format_err!("the model has no weights file"))?;
+        let mut model = DarknetModel::new(&self.inner.base)?;
+        model.load_weights(weights_file)?;
+        let model = Arc::new(model);
+        *weights = Some(model.clone());
+        Ok(model)
+    }
+
+    pub fn summary(&self) -> Arc<ModelSummary> {
+        self.inner
+            .summary
+            .get_or_init(|| Arc::new(ModelSummary::new(&self.inner.base)))
+            .clone()
+    }
+
+    // the layer shapes for the input size, see ModelBase::infer_shapes_multi()
+    pub fn shapes(&self, input_size: Shape) -> Result<Arc<ShapeTable>> {
+        if let Some(shapes) = self.inner.shapes.lock().unwrap().get(&input_size) {
+            return Ok(shapes.clone());
+        }
+
+        // computed outside the lock, a concurrent caller may do the same work
+        let shapes = self
+            .inner
+            .base
+            .infer_shapes_multi(&[input_size])?
+            .pop()
+            .unwrap();
+        let shapes = self
+            .inner
+            .shapes
+            .lock()
+            .unwrap()
+            .entry(input_size)
+            .or_insert_with(|| Arc::new(shapes))
+            .clone();
+        Ok(shapes)
+    }
+
+    // statistics of the parameters per layer, None for layers without finite values
+    pub fn weights_stats(&self) -> Result<Arc<IndexMap<usize, Option<WeightsStats>>>> {
+        if let Some(stats) = self.inner.weights_stats.get() {
+            return Ok(stats.clone());
+        }
+
+        let model = self.weights()?;
+        let stats: IndexMap<_, _> = model
+            .layers
+            .iter()
+            .map(|(&layer_index, layer)| {
+                let buffers = layer.buffers();
+                let stats = WeightsStats::new(buffers.iter().flat_map(|(_, values)| values.iter()));
+                (layer_index, stats)
+            })
+            .collect();
+        Ok(self
+            .inner
+            .weights_stats
+            .get_or_init(|| Arc::new(stats))
+            .clone())
+    }
+
+    // true if both handles share the same model
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
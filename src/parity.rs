@@ -0,0 +1,396 @@
+use crate::{common::*, config::DarknetConfig, darknet::DarknetModel};
+
+// a tiny network with the expected output of each layer for a fixed input, for
+// backends to check their inference against darknet. tensors are flattened in
+// darknet's CHW order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParityFixture {
+    pub name: String,
+    // the .cfg text
+    pub config: String,
+    // the parameters in the order of the weights file, without the header
+    pub weights: Vec<f32>,
+    pub input: Vec<f32>,
+    // layers without an entry are not checked
+    pub outputs: IndexMap<usize, Vec<f32>>,
+}
+
+// a value passes if |actual - expected| <= abs + rel * |expected|
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Tolerance {
+    pub abs: f32,
+    pub rel: f32,
+}
+
+impl Tolerance {
+    // NaN never passes
+    pub fn accepts(&self, expected: f32, actual: f32) -> bool {
+        (actual - expected).abs() <= self.abs + self.rel * expected.abs()
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            abs: 1e-4,
+            rel: 1e-4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParityReport {
+    pub fixture: String,
+    pub layers: Vec<LayerParity>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerParity {
+    pub layer_index: usize,
+    #[serde(flatten)]
+    pub status: ParityStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status")]
+pub enum ParityStatus {
+    #[serde(rename = "passed")]
+    Passed { max_error: f32 },
+    // the first value out of tolerance and the number of such values
+    #[serde(rename = "mismatch")]
+    Mismatch {
+        position: usize,
+        expected: f32,
+        actual: f32,
+        count: usize,
+        max_error: f32,
+    },
+    #[serde(rename = "wrong_length")]
+    WrongLength { expected: usize, actual: usize },
+    #[serde(rename = "missing")]
+    Missing,
+}
+
+impl Display for ParityStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Passed { max_error } => write!(f, "passed, max error {:e}", max_error),
+            Self::Mismatch {
+                position,
+                expected,
+                actual,
+                count,
+                max_error,
+            } => write!(
+                f,
+                "{} values out of tolerance, first at {} (expect {}, got {}), max error {:e}",
+                count, position, expected, actual, max_error
+            ),
+            Self::WrongLength { expected, actual } => {
+                write!(f, "expect {} values, got {}", expected, actual)
+            }
+            Self::Missing => write!(f, "no output"),
+        }
+    }
+}
+
+impl ParityReport {
+    pub fn passed(&self) -> bool {
+        self.layers
+            .iter()
+            .all(|layer| matches!(layer.status, ParityStatus::Passed { .. }))
+    }
+
+    // errors propagate to later layers, so the first failing layer is reported
+    pub fn ensure_passed(&self) -> Result<()> {
+        if let Some(layer) = self
+            .layers
+            .iter()
+            .find(|layer| !matches!(layer.status, ParityStatus::Passed { .. }))
+        {
+            bail!(
+                "fixture {}: layer {}: {}",
+                self.fixture,
+                layer.layer_index,
+                layer.status
+            );
+        }
+        Ok(())
+    }
+}
+
+impl ParityFixture {
+    // the expected outputs are worked out by hand after the forward pass of
+    // darknet, not dumped from a darknet run. tests/parity.rs checks them
+    // against the tch backend.
+    pub fn builtin() -> Vec<Self> {
+        vec![
+            Self::new(
+                "conv_maxpool_avgpool",
+                CONV_MAXPOOL_AVGPOOL_CONFIG,
+                CONV_MAXPOOL_AVGPOOL_WEIGHTS,
+                CONV_MAXPOOL_AVGPOOL_INPUT,
+                CONV_MAXPOOL_AVGPOOL_OUTPUTS,
+            ),
+            Self::new(
+                "batchnorm_shortcut_route",
+                BATCHNORM_SHORTCUT_ROUTE_CONFIG,
+                BATCHNORM_SHORTCUT_ROUTE_WEIGHTS,
+                BATCHNORM_SHORTCUT_ROUTE_INPUT,
+                BATCHNORM_SHORTCUT_ROUTE_OUTPUTS,
+            ),
+            Self::new(
+                "yolo_head",
+                YOLO_HEAD_CONFIG,
+                YOLO_HEAD_WEIGHTS,
+                YOLO_HEAD_INPUT,
+                YOLO_HEAD_OUTPUTS,
+            ),
+        ]
+    }
+
+    fn new(name: &str, config: &str, weights: &[f32], input: &[f32], outputs: &[&[f32]]) -> Self {
+        Self {
+            name: name.to_owned(),
+            config: config.to_owned(),
+            weights: weights.to_vec(),
+            input: input.to_vec(),
+            outputs: outputs
+                .iter()
+                .enumerate()
+                .map(|(layer_index, output)| (layer_index, output.to_vec()))
+                .collect(),
+        }
+    }
+
+    pub fn load<P>(fixture_file: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_json(&fs::read_to_string(fixture_file)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn parse_config(&self) -> Result<DarknetConfig> {
+        self.config.parse()
+    }
+
+    // the model with the fixture weights loaded
+    pub fn model(&self) -> Result<DarknetModel> {
+        let mut model = DarknetModel::from_config(&self.parse_config()?)?;
+        let mut bytes = vec![];
+        [0u32, 2, 0]
+            .iter()
+            .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        self.weights
+            .iter()
+            .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+        model
+            .read_weights(bytes.as_slice())
+            .map_err(|err| format_err!("fixture {}: {:#}", self.name, err))?;
+        Ok(model)
+    }
+
+    // compare the layer outputs of a backend run on the fixture input
+    pub fn verify(
+        &self,
+        outputs: &IndexMap<usize, Vec<f32>>,
+        tolerance: Tolerance,
+    ) -> ParityReport {
+        let layers = self
+            .outputs
+            .iter()
+            .map(|(&layer_index, expected)| {
+                let status = match outputs.get(&layer_index) {
+                    None => ParityStatus::Missing,
+                    Some(actual) if actual.len() != expected.len() => ParityStatus::WrongLength {
+                        expected: expected.len(),
+                        actual: actual.len(),
+                    },
+                    Some(actual) => compare(expected, actual, tolerance),
+                };
+                LayerParity {
+                    layer_index,
+                    status,
+                }
+            })
+            .collect();
+
+        ParityReport {
+            fixture: self.name.clone(),
+            layers,
+        }
+    }
+}
+
+fn compare(expected: &[f32], actual: &[f32], tolerance: Tolerance) -> ParityStatus {
+    let mut max_error = 0f32;
+    let mut first = None;
+    let mut count = 0;
+
+    for (position, (&expected, &actual)) in izip!(expected, actual).enumerate() {
+        let error = (actual - expected).abs();
+        let error = if error.is_nan() { f32::INFINITY } else { error };
+        max_error = max_error.max(error);
+        if !tolerance.accepts(expected, actual) {
+            count += 1;
+            first.get_or_insert((position, expected, actual));
+        }
+    }
+
+    match first {
+        None => ParityStatus::Passed { max_error },
+        Some((position, expected, actual)) => ParityStatus::Mismatch {
+            position,
+            expected,
+            actual,
+            count,
+            max_error,
+        },
+    }
+}
+
+const CONV_MAXPOOL_AVGPOOL_CONFIG: &str = "\
+[net]
+width=4
+height=4
+channels=1
+
+[convolutional]
+filters=2
+size=1
+stride=1
+pad=0
+activation=leaky
+
+[maxpool]
+size=2
+stride=2
+
+[avgpool]
+";
+
+// biases, then weights
+const CONV_MAXPOOL_AVGPOOL_WEIGHTS: &[f32] = &[0.0, 1.0, 1.0, -0.5];
+
+const CONV_MAXPOOL_AVGPOOL_INPUT: &[f32] = &[
+    -2.0, -1.75, -1.5, -1.25, -1.0, -0.75, -0.5, -0.25, 0.0, 0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 1.75,
+];
+
+const CONV_MAXPOOL_AVGPOOL_OUTPUTS: &[&[f32]] = &[
+    &[
+        -0.2, -0.175, -0.15, -0.125, -0.1, -0.075, -0.05, -0.025, 0.0, 0.25, 0.5, 0.75, 1.0, 1.25,
+        1.5, 1.75, 2.0, 1.875, 1.75, 1.625, 1.5, 1.375, 1.25, 1.125, 1.0, 0.875, 0.75, 0.625, 0.5,
+        0.375, 0.25, 0.125,
+    ],
+    &[-0.075, -0.025, 1.25, 1.75, 2.0, 1.75, 1.0, 0.75],
+    &[0.725, 1.375],
+];
+
+const BATCHNORM_SHORTCUT_ROUTE_CONFIG: &str = "\
+[net]
+width=2
+height=2
+channels=2
+
+[convolutional]
+filters=2
+size=1
+stride=1
+pad=1
+activation=linear
+
+[convolutional]
+batch_normalize=1
+filters=2
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[shortcut]
+from=-2
+activation=linear
+
+[route]
+layers=-1,-3
+";
+
+// biases and weights of layer 0, then biases, scales, rolling mean, rolling
+// variance and weights of layer 1
+const BATCHNORM_SHORTCUT_ROUTE_WEIGHTS: &[f32] = &[
+    0.5, -0.5, 1.0, 2.0, -1.0, 0.5, 0.1, -0.2, 1.5, 0.5, 0.25, -1.0, 4.0, 0.25, 1.0, 0.0, 0.5, 0.5,
+];
+
+const BATCHNORM_SHORTCUT_ROUTE_INPUT: &[f32] = &[1.0, -2.0, 0.5, 3.0, -1.0, 0.25, 2.0, -0.5];
+
+const BATCHNORM_SHORTCUT_ROUTE_OUTPUTS: &[&[f32]] = &[
+    &[-0.5, -1.0, 5.0, 2.5, -2.0, 1.625, 0.0, -3.75],
+    &[
+        0.3863929, 0.3020616, 0.9749741, 0.8566205, 0.3893609, 0.7525944, 0.9644286, 0.5436385,
+    ],
+    &[
+        -0.1136071, -0.6979384, 5.974974, 3.35662, -1.610639, 2.377594, 0.9644286, -3.206361,
+    ],
+    &[
+        -0.1136071, -0.6979384, 5.974974, 3.35662, -1.610639, 2.377594, 0.9644286, -3.206361, -0.5,
+        -1.0, 5.0, 2.5, -2.0, 1.625, 0.0, -3.75,
+    ],
+];
+
+const YOLO_HEAD_CONFIG: &str = "\
+[net]
+width=2
+height=2
+channels=1
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=1,1, 2,2, 3,3
+classes=1
+num=3
+";
+
+// zero biases, then the weights
+const YOLO_HEAD_WEIGHTS: &[f32] = &[
+    0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -0.9,
+    -0.8, -0.7, -0.6, -0.5, -0.4, -0.3, -0.2, -0.1, 0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8,
+];
+
+const YOLO_HEAD_INPUT: &[f32] = &[-1.0, -0.5, 0.5, 1.0];
+
+// the yolo layer applies the logistic function to x, y, objectness and classes
+const YOLO_HEAD_OUTPUTS: &[&[f32]] = &[
+    &[
+        0.9, 0.45, -0.45, -0.9, 0.8, 0.4, -0.4, -0.8, 0.7, 0.35, -0.35, -0.7, 0.6, 0.3, -0.3, -0.6,
+        0.5, 0.25, -0.25, -0.5, 0.4, 0.2, -0.2, -0.4, 0.3, 0.15, -0.15, -0.3, 0.2, 0.1, -0.1, -0.2,
+        0.1, 0.05, -0.05, -0.1, 0.0, 0.0, 0.0, 0.0, -0.1, -0.05, 0.05, 0.1, -0.2, -0.1, 0.1, 0.2,
+        -0.3, -0.15, 0.15, 0.3, -0.4, -0.2, 0.2, 0.4, -0.5, -0.25, 0.25, 0.5, -0.6, -0.3, 0.3, 0.6,
+        -0.7, -0.35, 0.35, 0.7, -0.8, -0.4, 0.4, 0.8,
+    ],
+    &[
+        0.7109495, 0.6106392, 0.3893608, 0.2890505, 0.6899745, 0.5986877, 0.4013123, 0.3100255,
+        0.7, 0.35, -0.35, -0.7, 0.6, 0.3, -0.3, -0.6, 0.6224593, 0.5621765, 0.4378235, 0.3775407,
+        0.5986877, 0.549834, 0.450166, 0.4013123, 0.5744425, 0.5374298, 0.4625702, 0.4255575,
+        0.549834, 0.5249792, 0.4750208, 0.450166, 0.1, 0.05, -0.05, -0.1, 0.0, 0.0, 0.0, 0.0,
+        0.4750208, 0.4875026, 0.5124974, 0.5249792, 0.450166, 0.4750208, 0.5249792, 0.549834,
+        0.4255575, 0.4625702, 0.5374298, 0.5744425, 0.4013123, 0.450166, 0.549834, 0.5986877, -0.5,
+        -0.25, 0.25, 0.5, -0.6, -0.3, 0.3, 0.6, 0.3318122, 0.4133824, 0.5866176, 0.6681878,
+        0.3100255, 0.4013123, 0.5986877, 0.6899745,
+    ],
+];
@@ -0,0 +1,176 @@
+use crate::{
+    common::*,
+    darknet::{
+        BatchNormLayer, BatchNormWeights, ConnectedLayer, ConnectedWeights, ConvLstmLayer,
+        ConvLstmWeights, ConvolutionalLayer, ConvolutionalWeights, CrnnLayer, CrnnWeights,
+        DarknetModel, Layer, LstmLayer, LstmWeights,
+    },
+};
+use rand::{distributions::Distribution, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Normal, Uniform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Noise {
+    #[serde(rename = "gaussian")]
+    Gaussian { std: f32 },
+    #[serde(rename = "uniform")]
+    Uniform { low: f32, high: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoiseSpec {
+    pub noise: Noise,
+    // scale the noise by the standard deviation of each buffer, so that layers
+    // of different magnitudes are perturbed alike
+    #[serde(default)]
+    pub relative: bool,
+    // layer kinds to perturb, e.g. "conv", or all layers if empty
+    #[serde(default)]
+    pub kinds: Vec<String>,
+    // rolling mean and variance of batch normalization are kept unless enabled
+    #[serde(default)]
+    pub rolling_stats: bool,
+}
+
+impl NoiseSpec {
+    pub fn new(noise: Noise) -> Self {
+        Self {
+            noise,
+            relative: false,
+            kinds: vec![],
+            rolling_stats: false,
+        }
+    }
+}
+
+impl DarknetModel {
+    // add random noise to the weights, the same seed always yields the same weights
+    pub fn perturb(&self, spec: &NoiseSpec, seed: u64) -> Result<Self> {
+        let NoiseSpec {
+            noise,
+            relative,
+            ref kinds,
+            rolling_stats,
+        } = *spec;
+
+        match noise {
+            Noise::Gaussian { std } => {
+                ensure!(
+                    std.is_finite() && std >= 0.0,
+                    "the standard deviation must be non-negative"
+                );
+            }
+            Noise::Uniform { low, high } => {
+                ensure!(
+                    low.is_finite() && high.is_finite() && low <= high,
+                    "the uniform noise range must satisfy low <= high"
+                );
+            }
+        }
+
+        let mut model = self.clone();
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        for (&layer_index, layer) in model.layers.iter_mut() {
+            let kind = model.base.layers[&layer_index].kind();
+            if !kinds.is_empty() && !kinds.iter().any(|selected| selected == kind) {
+                continue;
+            }
+
+            for (is_rolling_stat, values) in layer.buffers_mut() {
+                if is_rolling_stat && !rolling_stats {
+                    continue;
+                }
+
+                let scale = if relative { buffer_std(values) } else { 1.0 };
+                match noise {
+                    Noise::Gaussian { std } => {
+                        let dist = Normal::new(0.0, std * scale).unwrap();
+                        values
+                            .iter_mut()
+                            .for_each(|value| *value += dist.sample(&mut rng));
+                    }
+                    Noise::Uniform { low, high } => {
+                        let dist = Uniform::new_inclusive(low * scale, high * scale);
+                        values
+                            .iter_mut()
+                            .for_each(|value| *value += dist.sample(&mut rng));
+                    }
+                }
+            }
+
+            // the variance must stay non-negative after perturbation
+            if rolling_stats {
+                rolling_variances_mut(layer)
+                    .into_iter()
+                    .flat_map(|values| values.iter_mut())
+                    .for_each(|value| *value = value.max(0.0));
+            }
+        }
+
+        Ok(model)
+    }
+}
+
+fn buffer_std(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let len = values.len() as f64;
+    let mean = values.iter().map(|&value| value as f64).sum::<f64>() / len;
+    let var = values
+        .iter()
+        .map(|&value| (value as f64 - mean).powi(2))
+        .sum::<f64>()
+        / len;
+    var.sqrt() as f32
+}
+
+fn rolling_variances_mut(layer: &mut Layer) -> Vec<&mut Array1<f32>> {
+    match layer {
+        Layer::Convolutional(ConvolutionalLayer {
+            weights:
+                ConvolutionalWeights::Owned {
+                    scales: Some(scales),
+                    ..
+                },
+            ..
+        })
+        | Layer::Connected(ConnectedLayer {
+            weights:
+                ConnectedWeights {
+                    scales: Some(scales),
+                    ..
+                },
+            ..
+        }) => vec![&mut scales.rolling_variance],
+        Layer::BatchNorm(BatchNormLayer {
+            weights: BatchNormWeights {
+                rolling_variance, ..
+            },
+            ..
+        }) => vec![rolling_variance],
+        Layer::Lstm(LstmLayer {
+            weights: LstmWeights { gates },
+            ..
+        }) => gates
+            .iter_mut()
+            .filter_map(|gate| gate.scales.as_mut())
+            .map(|scales| &mut scales.rolling_variance)
+            .collect(),
+        Layer::Crnn(CrnnLayer {
+            weights: CrnnWeights { convs },
+            ..
+        })
+        | Layer::ConvLstm(ConvLstmLayer {
+            weights: ConvLstmWeights { gates: convs },
+            ..
+        }) => convs
+            .iter_mut()
+            .filter_map(|conv| conv.scales.as_mut())
+            .map(|scales| &mut scales.rolling_variance)
+            .collect(),
+        _ => vec![],
+    }
+}
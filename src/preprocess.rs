@@ -0,0 +1,186 @@
+use crate::{
+    common::*,
+    config::{CompoundNetConfig, NetConfig, Shape},
+    tta::letterbox_size,
+};
+use image::DynamicImage;
+
+impl NetConfig {
+    pub fn preprocess(&self, image: &DynamicImage) -> Result<Vec<f32>> {
+        preprocess(image, self.input_size, self.letter_box)
+    }
+}
+
+impl CompoundNetConfig {
+    pub fn preprocess(&self, image: &DynamicImage) -> Result<Vec<f32>> {
+        preprocess(image, self.input_size, self.letter_box)
+    }
+}
+
+pub fn preprocess(image: &DynamicImage, input_size: Shape, letter_box: bool) -> Result<Vec<f32>> {
+    // follow darknet's load_image(), resize_image() and letterbox_image()
+    let [height, width, channels] = input_size
+        .hwc()
+        .ok_or_else(|| format_err!("the input size must be in [height, width, channels] shape"))?;
+    let image = ChwImage::from_dynamic_image(image, channels as usize)?;
+    let (width, height) = (width as usize, height as usize);
+
+    let sized = if letter_box {
+        image.letterbox(width, height)
+    } else {
+        image.resize(width, height)
+    };
+
+    Ok(sized.data)
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ChwImage {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub data: Vec<f32>,
+}
+
+impl ChwImage {
+    pub fn new(width: usize, height: usize, channels: usize) -> Self {
+        Self {
+            width,
+            height,
+            channels,
+            data: vec![0.0; width * height * channels],
+        }
+    }
+
+    pub fn from_dynamic_image(image: &DynamicImage, channels: usize) -> Result<Self> {
+        let (width, height, samples) = match channels {
+            1 => {
+                let image = image.to_luma8();
+                let (width, height) = image.dimensions();
+                (width, height, image.into_raw())
+            }
+            3 => {
+                let image = image.to_rgb8();
+                let (width, height) = image.dimensions();
+                (width, height, image.into_raw())
+            }
+            _ => bail!(
+                "only 1 or 3 input channels are supported, but get {}",
+                channels
+            ),
+        };
+        let (width, height) = (width as usize, height as usize);
+
+        // convert interleaved HWC bytes to planar CHW floats
+        let mut output = Self::new(width, height, channels);
+        samples.iter().enumerate().for_each(|(index, &sample)| {
+            let channel = index % channels;
+            let pixel = index / channels;
+            output.data[channel * width * height + pixel] = sample as f32 / 255.0;
+        });
+
+        Ok(output)
+    }
+
+    pub fn get(&self, x: usize, y: usize, c: usize) -> f32 {
+        self.data[c * self.width * self.height + y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, c: usize, value: f32) {
+        self.data[c * self.width * self.height + y * self.width + x] = value;
+    }
+
+    pub fn add(&mut self, x: usize, y: usize, c: usize, value: f32) {
+        self.data[c * self.width * self.height + y * self.width + x] += value;
+    }
+
+    pub fn resize(&self, width: usize, height: usize) -> Self {
+        if self.width == width && self.height == height {
+            return self.clone();
+        }
+
+        let channels = self.channels;
+        let mut resized = Self::new(width, height, channels);
+        if width == 0 || height == 0 {
+            return resized;
+        }
+        let mut part = Self::new(width, self.height, channels);
+
+        // a single destination pixel samples the first source pixel instead of 0/0
+        let scale = |src: usize, dst: usize| {
+            if dst == 1 {
+                0.0
+            } else {
+                (src - 1) as f32 / (dst - 1) as f32
+            }
+        };
+        let w_scale = scale(self.width, width);
+        let h_scale = scale(self.height, height);
+
+        // interpolate along columns
+        for k in 0..channels {
+            for r in 0..self.height {
+                for c in 0..width {
+                    let value = if c == width - 1 || self.width == 1 {
+                        self.get(self.width - 1, r, k)
+                    } else {
+                        let sx = c as f32 * w_scale;
+                        let ix = sx as usize;
+                        let dx = sx - ix as f32;
+                        (1.0 - dx) * self.get(ix, r, k) + dx * self.get(ix + 1, r, k)
+                    };
+                    part.set(c, r, k, value);
+                }
+            }
+        }
+
+        // interpolate along rows
+        for k in 0..channels {
+            for r in 0..height {
+                let sy = r as f32 * h_scale;
+                let iy = sy as usize;
+                let dy = sy - iy as f32;
+
+                for c in 0..width {
+                    let value = (1.0 - dy) * part.get(c, iy, k);
+                    resized.set(c, r, k, value);
+                }
+
+                if r == height - 1 || self.height == 1 {
+                    continue;
+                }
+
+                for c in 0..width {
+                    let value = dy * part.get(c, iy + 1, k);
+                    resized.add(c, r, k, value);
+                }
+            }
+        }
+
+        resized
+    }
+
+    pub fn letterbox(&self, width: usize, height: usize) -> Self {
+        let mut boxed = Self::new(width, height, self.channels);
+        if width == 0 || height == 0 {
+            return boxed;
+        }
+        let (new_w, new_h) = letterbox_size(self.width, self.height, width, height);
+        let resized = self.resize(new_w, new_h);
+
+        boxed.data.iter_mut().for_each(|value| *value = 0.5);
+        boxed.embed(&resized, (width - new_w) / 2, (height - new_h) / 2);
+        boxed
+    }
+
+    fn embed(&mut self, source: &Self, dx: usize, dy: usize) {
+        for k in 0..source.channels {
+            for y in 0..source.height {
+                for x in 0..source.width {
+                    let value = source.get(x, y, k);
+                    self.set(dx + x, dy + y, k, value);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use crate::common::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Stage {
+    #[serde(rename = "parse")]
+    Parse,
+    #[serde(rename = "validate")]
+    Validate,
+    #[serde(rename = "load_weights")]
+    LoadWeights,
+    #[serde(rename = "export")]
+    Export,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Parse => "parse",
+            Self::Validate => "validate",
+            Self::LoadWeights => "load_weights",
+            Self::Export => "export",
+        };
+        f.write_str(name)
+    }
+}
+
+// receives progress events from long running operations, the crate only reports
+// the events and leaves logging and timing to the implementor
+//
+// num_bytes is the number of bytes read or written, it is zero for stages that
+// do not touch bytes. parsing is not reported per layer.
+pub trait ProgressObserver {
+    fn stage_started(&mut self, _stage: Stage, _num_layers: Option<usize>) {}
+
+    fn stage_finished(&mut self, _stage: Stage, _num_bytes: u64) {}
+
+    fn layer_started(&mut self, _stage: Stage, _layer_index: usize) {}
+
+    fn layer_finished(&mut self, _stage: Stage, _layer_index: usize, _num_bytes: u64) {}
+}
+
+// ignores all events
+impl ProgressObserver for () {}
@@ -0,0 +1,186 @@
+use crate::{
+    common::*, compress, config::DarknetConfig, darknet::DarknetModel, utils::sha256_file,
+};
+
+// the sidecar is named after the weights file, e.g. yolov4.weights.provenance.json
+pub const SIDECAR_SUFFIX: &str = ".provenance.json";
+
+pub fn sidecar_path<P>(weights_file: P) -> PathBuf
+where
+    P: AsRef<Path>,
+{
+    let mut path = weights_file.as_ref().as_os_str().to_owned();
+    path.push(SIDECAR_SUFFIX);
+    PathBuf::from(path)
+}
+
+// how a weights file was produced, saved next to it by
+// DarknetModel::save_weights_with_provenance()
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeightsProvenance {
+    pub crate_version: String,
+    pub config_fingerprint: String,
+    // filled on save
+    pub weights_checksum: Option<String>,
+    // in the order they were applied
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+    // the checksum of the weights file this one is derived from
+    pub parent_checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Transform {
+    // batch normalization folded into the preceding convolutions
+    #[serde(rename = "fused_batch_norm")]
+    FusedBatchNorm { layers: Vec<usize> },
+    // layer indexes in the config before pruning, see PrunedConfig
+    #[serde(rename = "pruned_layers")]
+    PrunedLayers { layers: Vec<usize> },
+    // the output channels removed from a layer
+    #[serde(rename = "pruned_channels")]
+    PrunedChannels {
+        layer_index: usize,
+        channels: Vec<usize>,
+    },
+    #[serde(rename = "other")]
+    Other { description: String },
+}
+
+impl Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FusedBatchNorm { layers } => {
+                write!(f, "fused batch norm of layers {}", layers.iter().join(","))
+            }
+            Self::PrunedLayers { layers } => {
+                write!(f, "pruned layers {}", layers.iter().join(","))
+            }
+            Self::PrunedChannels {
+                layer_index,
+                channels,
+            } => write!(
+                f,
+                "pruned {} channels of layer {}",
+                channels.len(),
+                layer_index
+            ),
+            Self::Other { description } => write!(f, "{}", description),
+        }
+    }
+}
+
+impl WeightsProvenance {
+    pub fn new(config: &DarknetConfig) -> Result<Self> {
+        Ok(Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+            config_fingerprint: config.fingerprint()?,
+            weights_checksum: None,
+            transforms: vec![],
+            parent_checksum: None,
+        })
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    pub fn with_parent<P>(mut self, parent_weights_file: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        self.parent_checksum = Some(sha256_file(parent_weights_file)?);
+        Ok(self)
+    }
+
+    pub fn load<P>(sidecar_file: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_json(&fs::read_to_string(sidecar_file)?)
+    }
+
+    // the sidecar of the weights file if it exists
+    pub fn find<P>(weights_file: P) -> Result<Option<Self>>
+    where
+        P: AsRef<Path>,
+    {
+        let sidecar_file = sidecar_path(weights_file);
+        if !sidecar_file.is_file() {
+            return Ok(None);
+        }
+        let provenance = Self::load(&sidecar_file).map_err(|err| {
+            format_err!(
+                "failed to load the weights provenance '{}': {:#}",
+                sidecar_file.display(),
+                err
+            )
+        })?;
+        Ok(Some(provenance))
+    }
+
+    pub fn save<P>(&self, sidecar_file: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(sidecar_file, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn verify<P>(&self, config: &DarknetConfig, weights_file: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let mut mismatches = vec![];
+
+        if config.fingerprint()? != self.config_fingerprint {
+            mismatches.push("config fingerprint");
+        }
+        if let Some(expect) = &self.weights_checksum {
+            if &sha256_file(weights_file)? != expect {
+                mismatches.push("weights checksum");
+            }
+        }
+
+        ensure!(
+            mismatches.is_empty(),
+            "weights provenance verification failed: {} mismatch",
+            mismatches.join(", ")
+        );
+
+        Ok(())
+    }
+}
+
+impl DarknetModel {
+    // save the weights and the provenance sidecar with the checksum of the written file
+    pub fn save_weights_with_provenance<P>(
+        &self,
+        weights_file: P,
+        mut provenance: WeightsProvenance,
+    ) -> Result<WeightsProvenance>
+    where
+        P: AsRef<Path>,
+    {
+        let weights_file = weights_file.as_ref();
+        ensure!(
+            provenance.config_fingerprint == self.base.to_config().fingerprint()?,
+            "the provenance is not created from the config of the model"
+        );
+
+        compress::write_file(weights_file, |writer| self.write_weights(writer))?;
+        provenance.weights_checksum = Some(sha256_file(weights_file)?);
+        provenance.save(sidecar_path(weights_file))?;
+        Ok(provenance)
+    }
+}
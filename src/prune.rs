@@ -0,0 +1,185 @@
+use crate::{
+    common::*,
+    config::{DarknetConfig, LayerConfig, LayerIndex},
+    model::{LayerBase, LayerPosition, ModelBase},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrunedConfig {
+    pub config: DarknetConfig,
+    // indexes of the removed layers in the original config
+    pub removed: Vec<usize>,
+}
+
+impl ModelBase {
+    // layers that no output layer depends on, in ascending order. the last layer
+    // counts as an output, as darknet returns its output from network_predict().
+    pub fn unreachable_layers(&self) -> Vec<usize> {
+        let mut stack: Vec<usize> = self
+            .outputs()
+            .iter()
+            .map(|output| output.layer_index)
+            .chain(self.layers.keys().max().cloned())
+            .collect();
+        let mut reachable = HashSet::new();
+
+        while let Some(layer_index) = stack.pop() {
+            if !reachable.insert(layer_index) {
+                continue;
+            }
+            let layer = &self.layers[&layer_index];
+
+            stack.extend(
+                layer
+                    .from_indexes()
+                    .iter()
+                    .filter_map(|position| match position {
+                        LayerPosition::Absolute(index) => Some(index),
+                        LayerPosition::Input => None,
+                    }),
+            );
+
+            // the owner of shared weights and the embedding source are needed
+            // although they do not feed the layer
+            let reference = match layer {
+                LayerBase::Convolutional(conv) => conv.config.share_index,
+                LayerBase::Yolo(yolo) => yolo.config.embedding_layer,
+                LayerBase::GaussianYolo(yolo) => yolo.config.yolo.embedding_layer,
+                _ => None,
+            };
+            stack.extend(reference.and_then(|index| index.to_absolute(layer_index)));
+        }
+
+        self.layers
+            .keys()
+            .cloned()
+            .filter(|layer_index| !reachable.contains(layer_index))
+            .sorted()
+            .collect()
+    }
+}
+
+impl DarknetConfig {
+    // remove the layers that no output layer depends on and rewrite the layer
+    // references, keeping relative references relative
+    pub fn prune_unreachable(&self) -> Result<PrunedConfig> {
+        let removed = ModelBase::from_config(self)?.unreachable_layers();
+        let config = remove_layers(self, &removed, |_| None)?;
+        Ok(PrunedConfig { config, removed })
+    }
+}
+
+// remove the sorted layers and rewrite the references of the remaining layers.
+// references to removed layers are redirected to the layer given by redirect(),
+// which must not be removed, or rejected if it returns None.
+pub(crate) fn remove_layers(
+    config: &DarknetConfig,
+    removed: &[usize],
+    redirect: impl Fn(usize) -> Option<usize>,
+) -> Result<DarknetConfig> {
+    let new_indexes: HashMap<usize, usize> = (0..config.layers.len())
+        .filter(|layer_index| removed.binary_search(layer_index).is_err())
+        .enumerate()
+        .map(|(new_index, old_index)| (old_index, new_index))
+        .collect();
+
+    let remap = |index: LayerIndex, old_curr: usize, new_curr: usize| -> Result<LayerIndex> {
+        let old_target = index
+            .to_absolute(old_curr)
+            .ok_or_else(|| format_err!("layer {} has an invalid layer index", old_curr))?;
+        let new_target = new_indexes
+            .get(&old_target)
+            .or_else(|| new_indexes.get(&redirect(old_target)?))
+            .cloned()
+            .ok_or_else(|| {
+                format_err!(
+                    "layer {} refers to the removed layer {}",
+                    old_curr,
+                    old_target
+                )
+            })?;
+
+        Ok(match index {
+            LayerIndex::Relative(_) => {
+                let distance = new_curr.checked_sub(new_target).and_then(NonZeroUsize::new);
+                LayerIndex::Relative(distance.ok_or_else(|| {
+                    format_err!(
+                        "layer {} cannot refer to layer {} relatively after the removal",
+                        old_curr,
+                        old_target
+                    )
+                })?)
+            }
+            LayerIndex::Absolute(_) => LayerIndex::Absolute(new_target),
+        })
+    };
+    let mut layers = vec![];
+    for (old_curr, layer) in config.layers.iter().enumerate() {
+        let new_curr = match new_indexes.get(&old_curr) {
+            Some(&new_curr) => new_curr,
+            None => continue,
+        };
+        let mut layer = layer.clone();
+
+        remap_references(&mut layer, |index| remap(index, old_curr, new_curr))?;
+        layers.push(layer);
+    }
+
+    Ok(DarknetConfig {
+        net: config.net.clone(),
+        layers,
+    })
+}
+
+// rewrite the layer references of a layer in place
+pub(crate) fn remap_references(
+    layer: &mut LayerConfig,
+    remap: impl Fn(LayerIndex) -> Result<LayerIndex>,
+) -> Result<()> {
+    let remap_set = |indexes: &IndexSet<LayerIndex>| -> Result<IndexSet<LayerIndex>> {
+        indexes.iter().map(|&index| remap(index)).try_collect()
+    };
+
+    match layer {
+        LayerConfig::Convolutional(conf) => {
+            conf.share_index = conf.share_index.map(&remap).transpose()?;
+        }
+        LayerConfig::Route(conf) => {
+            conf.layers = remap_set(&conf.layers)?;
+        }
+        LayerConfig::Shortcut(conf) => {
+            conf.from = remap_set(&conf.from)?;
+        }
+        LayerConfig::ScaleChannels(conf) => {
+            conf.from = remap(conf.from)?;
+        }
+        LayerConfig::Sam(conf) => {
+            conf.from = remap(conf.from)?;
+        }
+        LayerConfig::Custom(conf) => {
+            conf.from = remap_set(&conf.from)?;
+        }
+        LayerConfig::Yolo(conf) => {
+            conf.embedding_layer = conf.embedding_layer.map(&remap).transpose()?;
+        }
+        LayerConfig::GaussianYolo(conf) => {
+            conf.yolo.embedding_layer = conf.yolo.embedding_layer.map(&remap).transpose()?;
+        }
+        LayerConfig::Connected(_)
+        | LayerConfig::MaxPool(_)
+        | LayerConfig::UpSample(_)
+        | LayerConfig::BatchNorm(_)
+        | LayerConfig::Implicit(_)
+        | LayerConfig::AvgPool(_)
+        | LayerConfig::Dropout(_)
+        | LayerConfig::Softmax(_)
+        | LayerConfig::Region(_)
+        | LayerConfig::ConvLstm(_)
+        | LayerConfig::Crnn(_)
+        | LayerConfig::Lstm(_)
+        | LayerConfig::LocalAvgPool(_)
+        | LayerConfig::Local(_)
+        | LayerConfig::Reorg(_) => (),
+    }
+    Ok(())
+}
@@ -0,0 +1,298 @@
+use crate::{
+    common::*,
+    config::{scan_entries, DarknetConfig, FloatFormat, ParseOptions},
+};
+
+// a bulk edit of the layers, e.g.
+// layer[type=convolutional][filters>512].set(activation, mish)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Query {
+    pub predicates: Vec<Predicate>,
+    pub actions: Vec<Action>,
+}
+
+// compares a key of the layer section with a value. the keys are matched against
+// the serialized config, so the default values are seen as well. "type" stands
+// for the section name and "index" for the layer index.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Predicate {
+    pub key: String,
+    pub op: CompareOp,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CompareOp {
+    #[serde(rename = "=")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "action")]
+pub enum Action {
+    #[serde(rename = "set")]
+    Set { key: String, value: String },
+    // falls back to the default value
+    #[serde(rename = "unset")]
+    Unset { key: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LayerChange {
+    pub layer_index: usize,
+    pub section: String,
+    pub key: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct QueryReport {
+    // the layers selected by the predicates
+    pub matched: Vec<usize>,
+    // the values that differ after the edit
+    pub changes: Vec<LayerChange>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filter(mut self, key: impl ToString, op: CompareOp, value: impl ToString) -> Self {
+        self.predicates.push(Predicate {
+            key: key.to_string(),
+            op,
+            value: value.to_string(),
+        });
+        self
+    }
+
+    pub fn set(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.actions.push(Action::Set {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+        self
+    }
+
+    pub fn unset(mut self, key: impl ToString) -> Self {
+        self.actions.push(Action::Unset {
+            key: key.to_string(),
+        });
+        self
+    }
+
+    // reports the edit without changing the config. the edited config is still
+    // parsed, so invalid values are rejected as in apply().
+    pub fn dry_run(&self, config: &DarknetConfig) -> Result<QueryReport> {
+        let (_, report) = self.execute(config)?;
+        Ok(report)
+    }
+
+    pub fn apply(&self, config: &mut DarknetConfig) -> Result<QueryReport> {
+        let (edited, report) = self.execute(config)?;
+        *config = edited;
+        Ok(report)
+    }
+
+    fn execute(&self, config: &DarknetConfig) -> Result<(DarknetConfig, QueryReport)> {
+        // the untouched values must come back unchanged
+        let mut sections = scan_entries(&config.to_cfg_string_with(FloatFormat::Exact)?);
+        ensure!(
+            sections.len() == config.layers.len() + 1,
+            "unexpected section count, please report bug"
+        );
+
+        let mut report = QueryReport::default();
+        // the first section is [net]
+        for (layer_index, (section, entries)) in sections.iter_mut().skip(1).enumerate() {
+            if !self
+                .predicates
+                .iter()
+                .all(|predicate| predicate.matches(layer_index, section, entries))
+            {
+                continue;
+            }
+            report.matched.push(layer_index);
+
+            for action in &self.actions {
+                let (key, new_value) = match action {
+                    Action::Set { key, value } => (key, Some(value.clone())),
+                    Action::Unset { key } => (key, None),
+                };
+                let position = entries.iter().position(|(other, _)| other == key);
+                let old_value = position.map(|position| entries[position].1.clone());
+                match (position, &new_value) {
+                    (Some(position), Some(value)) => entries[position].1 = value.clone(),
+                    (None, Some(value)) => entries.push((key.clone(), value.clone())),
+                    (Some(position), None) => {
+                        entries.remove(position);
+                    }
+                    (None, None) => (),
+                }
+                if old_value != new_value {
+                    report.changes.push(LayerChange {
+                        layer_index,
+                        section: section.clone(),
+                        key: key.clone(),
+                        old_value,
+                        new_value,
+                    });
+                }
+            }
+        }
+
+        let text = sections
+            .iter()
+            .map(|(section, entries)| {
+                iter::once(format!("[{}]", section))
+                    .chain(
+                        entries
+                            .iter()
+                            .map(|(key, value)| format!("{}={}", key, value)),
+                    )
+                    .join("\n")
+            })
+            .join("\n\n");
+        // keys that do not belong to the section are rejected
+        let mut edited = DarknetConfig::parse_with_options(&text, &ParseOptions::strict())
+            .map_err(|err| format_err!("the edited config is invalid: {:#}", err))?;
+        // only the layers are edited, which keeps the provenance of [net]
+        edited.net = config.net.clone();
+
+        Ok((edited, report))
+    }
+}
+
+impl Predicate {
+    fn matches(&self, layer_index: usize, section: &str, entries: &[(String, String)]) -> bool {
+        let index = layer_index.to_string();
+        let value = match self.key.as_str() {
+            "type" => section,
+            "index" => index.as_str(),
+            key => match entries.iter().find(|(other, _)| other == key) {
+                Some((_, value)) => value.as_str(),
+                None => return false,
+            },
+        };
+
+        // numbers are compared by value, other values only by equality
+        let ordering = match (value.parse::<f64>(), self.value.parse::<f64>()) {
+            (Ok(lhs), Ok(rhs)) => lhs.partial_cmp(&rhs),
+            _ if value == self.value => Some(Ordering::Equal),
+            _ => None,
+        };
+        match self.op {
+            CompareOp::Eq => ordering == Some(Ordering::Equal),
+            CompareOp::Ne => ordering != Some(Ordering::Equal),
+            CompareOp::Lt => ordering == Some(Ordering::Less),
+            CompareOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            CompareOp::Gt => ordering == Some(Ordering::Greater),
+            CompareOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut rest = text
+            .trim()
+            .strip_prefix("layer")
+            .ok_or_else(|| format_err!("the query '{}' does not start with 'layer'", text))?
+            .trim_start();
+        let mut query = Self::new();
+
+        while let Some(tail) = rest.strip_prefix('[') {
+            let end = tail
+                .find(']')
+                .ok_or_else(|| format_err!("unclosed '[' in '{}'", text))?;
+            query.predicates.push(tail[..end].parse()?);
+            rest = tail[(end + 1)..].trim_start();
+        }
+
+        while let Some(tail) = rest.strip_prefix('.') {
+            let open = tail
+                .find('(')
+                .ok_or_else(|| format_err!("expect '(' after '.' in '{}'", text))?;
+            let close = tail
+                .find(')')
+                .ok_or_else(|| format_err!("unclosed '(' in '{}'", text))?;
+            ensure!(open < close, "expect '(' before ')' in '{}'", text);
+            // the value may be a list like anchors or layers, so only the
+            // first comma separates the arguments
+            let args: Vec<_> = tail[(open + 1)..close]
+                .splitn(2, ',')
+                .map(|arg| arg.trim())
+                .collect();
+            let action = match (tail[..open].trim(), args.as_slice()) {
+                ("set", [key, value]) if !key.is_empty() && !value.is_empty() => Action::Set {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                },
+                ("unset", [key]) if !key.is_empty() => Action::Unset {
+                    key: key.to_string(),
+                },
+                _ => bail!(
+                    "invalid action '{}', expect set(key, value) or unset(key)",
+                    tail[..=close].trim()
+                ),
+            };
+            query.actions.push(action);
+            rest = tail[(close + 1)..].trim_start();
+        }
+
+        ensure!(rest.is_empty(), "unexpected '{}' in '{}'", rest, text);
+        ensure!(
+            !query.actions.is_empty(),
+            "the query '{}' has no action",
+            text
+        );
+        Ok(query)
+    }
+}
+
+impl FromStr for Predicate {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        // two-character operators are tried first
+        const OPS: &[(&str, CompareOp)] = &[
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("=", CompareOp::Eq),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ];
+        let (pos, symbol, op) = OPS
+            .iter()
+            .filter_map(|&(symbol, op)| Some((text.find(symbol)?, symbol, op)))
+            .min_by_key(|&(pos, symbol, _)| (pos, usize::MAX - symbol.len()))
+            .ok_or_else(|| format_err!("expect a comparison in '[{}]'", text))?;
+        let key = text[..pos].trim();
+        let value = text[(pos + symbol.len())..].trim();
+        ensure!(
+            !key.is_empty() && !value.is_empty(),
+            "invalid comparison '[{}]'",
+            text
+        );
+        Ok(Self {
+            key: key.to_owned(),
+            op,
+            value: value.to_owned(),
+        })
+    }
+}
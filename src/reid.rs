@@ -0,0 +1,128 @@
+use crate::{
+    common::*,
+    config::{Activation, LayerIndex},
+    model::{LayerBase, ModelBase},
+};
+
+// how embeddings are compared, darknet uses the cosine similarity, that is the
+// dot product of L2 normalized vectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EmbeddingNormalization {
+    L2,
+}
+
+// the embedding feature extraction of a yolo layer for tracking
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReidConfig {
+    pub yolo_index: usize,
+    pub embedding_index: usize,
+    // [height, width, channels] of the embedding layer output
+    pub output_shape: [u64; 3],
+    // the channels taken for each anchor, that is the channels divided by the
+    // number of anchors of the yolo layer, see parse_network_cfg() in darknet
+    pub embedding_size: u64,
+    // the activation of the embedding layer if it is a convolution
+    pub activation: Option<Activation>,
+    pub normalization: EmbeddingNormalization,
+    // the minimum similarity to match a detection to a track
+    pub sim_thresh: f64,
+    // the weight of the ciou between boxes in the track matching
+    pub track_ciou_norm: f64,
+    pub track_history_size: u64,
+    pub dets_for_track: u64,
+    pub dets_for_show: u64,
+}
+
+impl ModelBase {
+    // the embedding configurations of the yolo layers with embedding_layer set
+    pub fn reid_configs(&self) -> Result<Vec<ReidConfig>> {
+        self.layers
+            .iter()
+            .filter_map(|(&layer_index, layer)| match layer {
+                LayerBase::Yolo(yolo) => yolo
+                    .config
+                    .embedding_layer
+                    .map(|embedding_layer| (layer_index, yolo, embedding_layer)),
+                _ => None,
+            })
+            .sorted_by_key(|(layer_index, _, _)| *layer_index)
+            .map(|(yolo_index, yolo, embedding_layer)| {
+                let embedding_index = self.embedding_index(yolo_index, embedding_layer)?;
+                let embedding = &self.layers[&embedding_index];
+
+                let output_shape = embedding.output_shape().hwc().ok_or_else(|| {
+                    format_err!(
+                        "layer {}: the embedding layer {} must output an image",
+                        yolo_index,
+                        embedding_index
+                    )
+                })?;
+                let [out_h, out_w, out_c] = output_shape;
+                let [yolo_h, yolo_w, _] = yolo.inout_shape;
+                ensure!(
+                    [out_h, out_w] == [yolo_h, yolo_w],
+                    "layer {}: the embedding layer {} outputs {}x{}, but the yolo layer is {}x{}",
+                    yolo_index,
+                    embedding_index,
+                    out_h,
+                    out_w,
+                    yolo_h,
+                    yolo_w
+                );
+
+                let num_anchors = yolo.config.anchors.len() as u64;
+                ensure!(
+                    num_anchors > 0 && out_c.is_multiple_of(num_anchors),
+                    "layer {}: the {} channels of the embedding layer {} cannot be split among {} anchors",
+                    yolo_index,
+                    out_c,
+                    embedding_index,
+                    num_anchors
+                );
+
+                let activation = match embedding {
+                    LayerBase::Convolutional(conv) => Some(conv.config.activation),
+                    _ => None,
+                };
+
+                Ok(ReidConfig {
+                    yolo_index,
+                    embedding_index,
+                    output_shape,
+                    embedding_size: out_c / num_anchors,
+                    activation,
+                    normalization: EmbeddingNormalization::L2,
+                    sim_thresh: yolo.config.sim_thresh.raw(),
+                    track_ciou_norm: yolo.config.track_ciou_norm.raw(),
+                    track_history_size: yolo.config.track_history_size,
+                    dets_for_track: yolo.config.dets_for_track,
+                    dets_for_show: yolo.config.dets_for_show,
+                })
+            })
+            .collect()
+    }
+
+    // the embedding is read after the forward pass reaches the yolo layer, so
+    // the embedding layer must come earlier
+    fn embedding_index(&self, yolo_index: usize, embedding_layer: LayerIndex) -> Result<usize> {
+        let embedding_index = embedding_layer.to_absolute(yolo_index).ok_or_else(|| {
+            format_err!(
+                "layer {}: embedding_layer points before the first layer",
+                yolo_index
+            )
+        })?;
+        ensure!(
+            embedding_index < yolo_index,
+            "layer {}: the embedding layer {} must precede the yolo layer",
+            yolo_index,
+            embedding_index
+        );
+        ensure!(
+            self.layers.contains_key(&embedding_index),
+            "layer {}: the embedding layer {} does not exist",
+            yolo_index,
+            embedding_index
+        );
+        Ok(embedding_index)
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{
+    common::*,
+    model::{LayerBase, LayerPosition, LayerPositionSet, ModelBase},
+};
+
+#[cfg(feature = "random")]
+pub use heads::*;
+
+impl ModelBase {
+    // the convolutions feeding yolo layers, whose filters depend on the classes
+    pub fn head_convolutions(&self) -> Vec<usize> {
+        self.layers
+            .values()
+            .filter_map(|layer| match (layer, layer.from_indexes()) {
+                (
+                    LayerBase::Yolo(_),
+                    LayerPositionSet::Single(LayerPosition::Absolute(from_index)),
+                ) => match &self.layers[&from_index] {
+                    LayerBase::Convolutional(_) => Some(from_index),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .sorted()
+            .dedup()
+            .collect()
+    }
+}
+
+#[cfg(feature = "random")]
+mod heads {
+    use super::*;
+    use crate::{
+        config::LayerConfig,
+        darknet::{ConvolutionalLayer, ConvolutionalWeights, DarknetModel, Layer},
+        weights_layout::WeightsLayout,
+    };
+    use rand::{distributions::Distribution, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use rand_distr::Uniform;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub struct HeadReinitReport {
+        // the number of classes the checkpoint was trained for
+        pub source_classes: u64,
+        // the head convolutions that are freshly initialized, empty if the
+        // checkpoint matches the model
+        pub reinitialized: Vec<usize>,
+    }
+
+    impl DarknetModel {
+        // load a checkpoint trained for a different number of classes, as in
+        // fine-tuning. the class count of the checkpoint is inferred from the file
+        // size, the head convolutions are initialized like darknet does and the
+        // other layers are loaded as usual.
+        pub fn load_weights_reinit_heads<P>(
+            &mut self,
+            weights_file: P,
+            seed: u64,
+        ) -> Result<HeadReinitReport>
+        where
+            P: AsRef<Path>,
+        {
+            let weights_file = weights_file.as_ref();
+            let classes = self.base.net.classes;
+            let config = self.base.to_config();
+            let heads = self.base.head_convolutions();
+
+            // the number of values that one more class adds to the file
+            let values_per_class: u64 = heads
+                .iter()
+                .map(|&layer_index| match &config.layers[layer_index] {
+                    LayerConfig::Convolutional(conf) if !conf.common.dont_load => {
+                        let in_c = match &self.base.layers[&layer_index] {
+                            LayerBase::Convolutional(layer) => layer.input_shape[2],
+                            _ => unreachable!(),
+                        };
+                        let num_anchors = num_anchors(&self.base, layer_index);
+                        let scales = if conf.batch_normalize && !conf.common.dont_load_scales {
+                            3
+                        } else {
+                            0
+                        };
+                        num_anchors * (1 + scales + in_c / conf.groups * conf.size.pow(2))
+                    }
+                    _ => 0,
+                })
+                .sum();
+
+            let source_classes = {
+                let expected = WeightsLayout::describe(&config)?.file_size() - header_size(2);
+                let actual = {
+                    let mut file = File::open(weights_file)?;
+                    let mut version = [0u8; 8];
+                    file.read_exact(&mut version)?;
+                    let major =
+                        u32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+                    let minor =
+                        u32::from_le_bytes([version[4], version[5], version[6], version[7]]);
+                    let header_size = header_size(major * 10 + minor);
+                    let file_size = fs::metadata(weights_file)?.len();
+                    ensure!(file_size >= header_size, "the weights file is truncated");
+                    file_size - header_size
+                };
+                let diff = (actual as i64 - expected as i64) / 4;
+
+                if diff == 0 {
+                    classes
+                } else {
+                    ensure!(
+                        values_per_class > 0 && diff % values_per_class as i64 == 0,
+                        "the weights file does not fit the model with any number of classes"
+                    );
+                    let source_classes = classes as i64 + diff / values_per_class as i64;
+                    ensure!(
+                        source_classes > 0,
+                        "the weights file does not fit the model with any number of classes"
+                    );
+                    source_classes as u64
+                }
+            };
+
+            if source_classes == classes {
+                self.load_weights(weights_file)?;
+                return Ok(HeadReinitReport {
+                    source_classes,
+                    reinitialized: vec![],
+                });
+            }
+
+            // load into the model for the source classes, then take the non-head layers
+            let source_config = {
+                let mut source_config = config;
+                source_config.net.classes = source_classes;
+                for &layer_index in &heads {
+                    let num_anchors = num_anchors(&self.base, layer_index);
+                    if let LayerConfig::Convolutional(conf) = &mut source_config.layers[layer_index]
+                    {
+                        conf.filters = num_anchors * (source_classes + 4 + 1);
+                    }
+                }
+                // the per-class counters are only used in training
+                for layer in &mut source_config.layers {
+                    if let LayerConfig::Yolo(conf) = layer {
+                        conf.counters_per_class = None;
+                    }
+                }
+                source_config
+            };
+            let mut source = DarknetModel::from_config(&source_config)?;
+            source.load_weights(weights_file)?;
+
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            for (&layer_index, layer) in self.layers.iter_mut() {
+                if heads.contains(&layer_index) {
+                    match layer {
+                        Layer::Convolutional(layer) => init_convolutional(layer, &mut rng),
+                        _ => unreachable!(),
+                    }
+                    continue;
+                }
+
+                let source = source.layers[&layer_index].buffers();
+                let mut target = layer.buffers_mut();
+                ensure!(
+                    source.len() == target.len(),
+                    "please report bug: the buffers of layer {} do not match",
+                    layer_index
+                );
+                source
+                    .into_iter()
+                    .zip(target.iter_mut())
+                    .for_each(|((_, source), (_, target))| target.copy_from_slice(source));
+            }
+            self.base.seen = source.base.seen;
+            self.base.cur_iteration = source.base.cur_iteration;
+
+            Ok(HeadReinitReport {
+                source_classes,
+                reinitialized: heads,
+            })
+        }
+    }
+
+    // the anchors of the yolo layer following the head convolution
+    fn num_anchors(model: &ModelBase, layer_index: usize) -> u64 {
+        match &model.layers[&(layer_index + 1)] {
+            LayerBase::Yolo(layer) => layer.config.anchors.len() as u64,
+            _ => unreachable!(),
+        }
+    }
+
+    // the version and seen fields, seen is 64-bit since 0.2
+    fn header_size(version: u32) -> u64 {
+        if version >= 2 {
+            20
+        } else {
+            16
+        }
+    }
+
+    // uniform weights scaled by sqrt(2 / fan_in), zero biases and identity batch
+    // normalization, see make_convolutional_layer() in darknet
+    pub(crate) fn init_convolutional(layer: &mut ConvolutionalLayer, rng: &mut ChaCha8Rng) {
+        let [_h, _w, in_c] = layer.base.input_shape;
+        let fan_in = in_c / layer.base.config.groups * layer.base.config.size.pow(2);
+        let scale = (2.0 / fan_in as f32).sqrt();
+        let dist = Uniform::new_inclusive(-scale, scale);
+
+        if let ConvolutionalWeights::Owned {
+            biases,
+            weights,
+            scales,
+        } = &mut layer.weights
+        {
+            biases.fill(0.0);
+            weights
+                .iter_mut()
+                .for_each(|value| *value = dist.sample(rng));
+            if let Some(scales) = scales {
+                scales.scales.fill(1.0);
+                scales.rolling_mean.fill(0.0);
+                scales.rolling_variance.fill(1.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{
+    common::*,
+    darknet::{ConvolutionalLayer, ConvolutionalWeights},
+};
+
+// the memory order of a 4-d tensor. activations use the N, C, H, W names and
+// kernels use O, I, H, W for the same axes, so they convert to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LayoutTag {
+    #[serde(rename = "nchw")]
+    Nchw,
+    #[serde(rename = "nhwc")]
+    Nhwc,
+    // the kernel order of darknet
+    #[serde(rename = "oihw")]
+    Oihw,
+    #[serde(rename = "ohwi")]
+    Ohwi,
+    #[serde(rename = "hwio")]
+    Hwio,
+    // the kernel as an [O, I * H * W] matrix as in darknet's im2col gemm, cut into
+    // panels of `tile` output rows that are stored column by column. the last
+    // panel is padded with zeros.
+    #[serde(rename = "tiled")]
+    Tiled { tile: usize },
+}
+
+impl LayoutTag {
+    // the logical axes in memory order, None for tiled layouts
+    fn axes(&self) -> Option<[usize; 4]> {
+        match self {
+            Self::Nchw | Self::Oihw => Some([0, 1, 2, 3]),
+            Self::Nhwc | Self::Ohwi => Some([0, 2, 3, 1]),
+            Self::Hwio => Some([2, 3, 1, 0]),
+            Self::Tiled { .. } => None,
+        }
+    }
+}
+
+// a 4-d tensor with its memory order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Weights {
+    pub layout: LayoutTag,
+    // the sizes of the logical axes, that is [N, C, H, W] or [O, I, H, W]
+    // regardless of the layout
+    pub dims: [usize; 4],
+    pub data: Vec<f32>,
+}
+
+impl Weights {
+    pub fn new(layout: LayoutTag, dims: [usize; 4], data: Vec<f32>) -> Result<Self> {
+        let weights = Self { layout, dims, data };
+        ensure!(
+            weights.data.len() == weights.storage_len()?,
+            "expect {} values for {:?} in {:?} layout, but get {}",
+            weights.storage_len()?,
+            dims,
+            layout,
+            weights.data.len()
+        );
+        Ok(weights)
+    }
+
+    // the number of stored values including padding
+    pub fn storage_len(&self) -> Result<usize> {
+        let [o, i, h, w] = self.dims;
+        let len = match self.layout {
+            LayoutTag::Tiled { tile } => {
+                ensure!(tile > 0, "the tile size must be positive");
+                o.div_ceil(tile) * tile * i * h * w
+            }
+            _ => o * i * h * w,
+        };
+        Ok(len)
+    }
+
+    // the values in another layout
+    pub fn repack(&self, layout: LayoutTag) -> Result<Self> {
+        if layout == self.layout {
+            return Ok(self.clone());
+        }
+        let canonical = self.to_canonical()?;
+        let data = Self::from_canonical(&canonical, self.dims, layout)?;
+        Self::new(layout, self.dims, data)
+    }
+
+    // the values in [A0, A1, A2, A3] order of the logical axes
+    fn to_canonical(&self) -> Result<Vec<f32>> {
+        let [o, i, h, w] = self.dims;
+        let len = o * i * h * w;
+
+        let data = match self.layout {
+            LayoutTag::Tiled { tile } => {
+                ensure!(tile > 0, "the tile size must be positive");
+                let cols = i * h * w;
+                (0..len)
+                    .map(|index| {
+                        let (row, col) = (index / cols, index % cols);
+                        let (panel, offset) = (row / tile, row % tile);
+                        self.data[(panel * cols + col) * tile + offset]
+                    })
+                    .collect()
+            }
+            layout => {
+                let axes = layout.axes().unwrap();
+                let strides = strides(self.dims, axes);
+                (0..len)
+                    .map(|index| {
+                        let coord = unravel(index, self.dims);
+                        let offset: usize = (0..4).map(|axis| coord[axis] * strides[axis]).sum();
+                        self.data[offset]
+                    })
+                    .collect()
+            }
+        };
+        Ok(data)
+    }
+
+    fn from_canonical(canonical: &[f32], dims: [usize; 4], layout: LayoutTag) -> Result<Vec<f32>> {
+        let [o, i, h, w] = dims;
+
+        let data = match layout {
+            LayoutTag::Tiled { tile } => {
+                ensure!(tile > 0, "the tile size must be positive");
+                let cols = i * h * w;
+                let num_panels = o.div_ceil(tile);
+                let mut data = vec![0.0; num_panels * tile * cols];
+                canonical.iter().enumerate().for_each(|(index, &value)| {
+                    let (row, col) = (index / cols, index % cols);
+                    let (panel, offset) = (row / tile, row % tile);
+                    data[(panel * cols + col) * tile + offset] = value;
+                });
+                data
+            }
+            layout => {
+                let axes = layout.axes().unwrap();
+                let strides = strides(dims, axes);
+                let mut data = vec![0.0; canonical.len()];
+                canonical.iter().enumerate().for_each(|(index, &value)| {
+                    let coord = unravel(index, dims);
+                    let offset: usize = (0..4).map(|axis| coord[axis] * strides[axis]).sum();
+                    data[offset] = value;
+                });
+                data
+            }
+        };
+        Ok(data)
+    }
+}
+
+impl ConvolutionalLayer {
+    // the kernel in darknet's OIHW order, None if the weights are shared
+    pub fn weights_tensor(&self) -> Option<Weights> {
+        let config = &self.base.config;
+        let [_h, _w, in_c] = self.base.input_shape;
+        let dims = [
+            config.filters as usize,
+            (in_c / config.groups) as usize,
+            config.size as usize,
+            config.size as usize,
+        ];
+
+        match &self.weights {
+            ConvolutionalWeights::Owned { weights, .. } => Some(
+                Weights::new(LayoutTag::Oihw, dims, weights.iter().cloned().collect()).unwrap(),
+            ),
+            ConvolutionalWeights::Ref { .. } => None,
+        }
+    }
+}
+
+// the strides of the logical axes when they are stored in the given order
+fn strides(dims: [usize; 4], axes: [usize; 4]) -> [usize; 4] {
+    let mut strides = [0; 4];
+    let mut stride = 1;
+    axes.iter().rev().for_each(|&axis| {
+        strides[axis] = stride;
+        stride *= dims[axis];
+    });
+    strides
+}
+
+fn unravel(index: usize, dims: [usize; 4]) -> [usize; 4] {
+    let [_, d1, d2, d3] = dims;
+    [
+        index / (d1 * d2 * d3),
+        index / (d2 * d3) % d1,
+        index / d3 % d2,
+        index % d3,
+    ]
+}
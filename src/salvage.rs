@@ -0,0 +1,139 @@
+use crate::{
+    common::*,
+    compress,
+    config::LayerConfigEx,
+    darknet::DarknetModel,
+    weights_layout::{stored_buffers, WeightsDataType},
+};
+use std::convert::TryInto;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LayerLoadStatus {
+    #[serde(rename = "loaded")]
+    Loaded,
+    // the layer stores nothing in the file
+    #[serde(rename = "no_weights")]
+    NoWeights,
+    // the file ends within the layer
+    #[serde(rename = "truncated")]
+    Truncated,
+    // the file ends before the layer
+    #[serde(rename = "missing")]
+    Missing,
+    // the values are complete but contain NaN or infinity
+    #[serde(rename = "corrupted")]
+    Corrupted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SalvageReport {
+    pub seen: u64,
+    pub status: IndexMap<usize, LayerLoadStatus>,
+    // the byte offset of the first layer that failed to load, or the end of the
+    // expected data if the file has trailing bytes
+    pub first_bad_offset: Option<u64>,
+}
+
+impl SalvageReport {
+    pub fn is_complete(&self) -> bool {
+        self.first_bad_offset.is_none()
+    }
+
+    // the layers whose buffers are left as initialized
+    pub fn failed_layers(&self) -> Vec<usize> {
+        self.status
+            .iter()
+            .filter(|(_, status)| {
+                !matches!(status, LayerLoadStatus::Loaded | LayerLoadStatus::NoWeights)
+            })
+            .map(|(&layer_index, _)| layer_index)
+            .collect()
+    }
+}
+
+impl DarknetModel {
+    // load as many layers as possible from a truncated or damaged weights file,
+    // such as a checkpoint written while the training was killed. layers that
+    // fail keep their current values. only the header must be intact.
+    pub fn load_weights_salvage<P>(&mut self, weights_file: P) -> Result<SalvageReport>
+    where
+        P: AsRef<Path>,
+    {
+        let bytes = compress::read_file(weights_file.as_ref())?;
+        let read_u32 = |offset: usize| -> Result<u32> {
+            let field = bytes
+                .get(offset..(offset + 4))
+                .ok_or_else(|| format_err!("the header of the weights file is truncated"))?;
+            Ok(u32::from_le_bytes(field.try_into().unwrap()))
+        };
+
+        let major = read_u32(0)?;
+        let minor = read_u32(4)?;
+        let _revision = read_u32(8)?;
+        let (seen, header_size) = if major * 10 + minor >= 2 {
+            let field = bytes
+                .get(12..20)
+                .ok_or_else(|| format_err!("the header of the weights file is truncated"))?;
+            (u64::from_le_bytes(field.try_into().unwrap()), 20)
+        } else {
+            (read_u32(12)? as u64, 16)
+        };
+        let transpose = (major > 1000) || (minor > 1000);
+
+        self.base.seen = seen;
+        self.base.cur_iteration = self.base.net.iteration(seen);
+
+        let value_size = WeightsDataType::Float32.size() as usize;
+        let mut offset = header_size;
+        let mut first_bad_offset = None;
+        let mut status = IndexMap::new();
+
+        for layer_index in 0..self.layers.len() {
+            let config = self.base.layers[&layer_index].config();
+            let num_values: usize = stored_buffers(&self.layers[&layer_index], config.common())
+                .iter()
+                .map(|(_, values)| values.len())
+                .sum();
+            let num_bytes = num_values * value_size;
+
+            let layer_status = if num_bytes == 0 {
+                LayerLoadStatus::NoWeights
+            } else if offset >= bytes.len() {
+                LayerLoadStatus::Missing
+            } else if offset + num_bytes > bytes.len() {
+                LayerLoadStatus::Truncated
+            } else {
+                let data = &bytes[offset..(offset + num_bytes)];
+                let is_finite = data
+                    .chunks_exact(value_size)
+                    .all(|value| f32::from_le_bytes(value.try_into().unwrap()).is_finite());
+                if is_finite {
+                    self.layers[&layer_index].load_weights(data, transpose)?;
+                    LayerLoadStatus::Loaded
+                } else {
+                    LayerLoadStatus::Corrupted
+                }
+            };
+
+            if !matches!(
+                layer_status,
+                LayerLoadStatus::Loaded | LayerLoadStatus::NoWeights
+            ) && first_bad_offset.is_none()
+            {
+                first_bad_offset = Some(offset as u64);
+            }
+            status.insert(layer_index, layer_status);
+            offset += num_bytes;
+        }
+
+        if first_bad_offset.is_none() && offset < bytes.len() {
+            first_bad_offset = Some(offset as u64);
+        }
+
+        Ok(SalvageReport {
+            seen,
+            status,
+            first_bad_offset,
+        })
+    }
+}
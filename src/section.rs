@@ -0,0 +1,71 @@
+use crate::{
+    common::*,
+    config::{CustomConfig, Shape, BUILTIN_SECTIONS},
+    model::ShapeList,
+};
+use std::sync::OnceLock;
+
+// the handler of a layer section this crate does not know, e.g. a layer of a
+// darknet fork. once registered, sections with the name parse into
+// LayerConfig::Custom and take part in validation and the model graph. custom
+// layers store no weights in .weights files.
+pub trait SectionHandler: Send + Sync {
+    // the section name without brackets
+    fn section_name(&self) -> &'static str;
+
+    // the option listing the input layers in the format of the "from" option of
+    // [shortcut]. the layer reads the previous layer if the handler has no such
+    // option or the section omits it.
+    fn input_key(&self) -> Option<&'static str> {
+        None
+    }
+
+    // check the options of a parsed section, returning them normalized
+    fn parse(&self, options: IndexMap<String, String>) -> Result<IndexMap<String, String>> {
+        Ok(options)
+    }
+
+    // the options written back to the section
+    fn serialize(&self, options: &IndexMap<String, String>) -> Result<IndexMap<String, String>> {
+        Ok(options.clone())
+    }
+
+    // the input shape is a single shape unless the layer reads several layers,
+    // which must have HWC outputs
+    fn output_shape(&self, config: &CustomConfig, input_shape: &ShapeList) -> Result<Shape>;
+}
+
+fn global_handlers() -> &'static Mutex<IndexMap<&'static str, Arc<dyn SectionHandler>>> {
+    static HANDLERS: OnceLock<Mutex<IndexMap<&'static str, Arc<dyn SectionHandler>>>> =
+        OnceLock::new();
+    HANDLERS.get_or_init(|| Mutex::new(IndexMap::new()))
+}
+
+pub fn register_section_handler(handler: Arc<dyn SectionHandler>) -> Result<()> {
+    let name = handler.section_name();
+    ensure!(
+        !BUILTIN_SECTIONS.contains(&name),
+        "[{}] is a built-in section",
+        name
+    );
+    let mut handlers = global_handlers().lock().unwrap();
+    ensure!(
+        !handlers.contains_key(name),
+        "a handler of [{}] is already registered",
+        name
+    );
+    handlers.insert(name, handler);
+    Ok(())
+}
+
+pub fn section_handler(name: &str) -> Option<Arc<dyn SectionHandler>> {
+    global_handlers().lock().unwrap().get(name).cloned()
+}
+
+pub fn section_names() -> Vec<&'static str> {
+    global_handlers().lock().unwrap().keys().cloned().collect()
+}
+
+pub(crate) fn expect_section_handler(name: &str) -> Result<Arc<dyn SectionHandler>> {
+    section_handler(name).ok_or_else(|| format_err!("no handler of [{}] is registered", name))
+}
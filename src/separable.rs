@@ -0,0 +1,290 @@
+use crate::{
+    common::*,
+    config::{ConvolutionalConfig, DarknetConfig, Deform, LayerConfig, LayerIndex},
+    model::{LayerBase, ModelBase},
+    prune::remap_references,
+};
+#[cfg(feature = "random")]
+use crate::{
+    darknet::{DarknetModel, Layer},
+    reinit::init_convolutional,
+};
+#[cfg(feature = "random")]
+use rand::SeedableRng;
+#[cfg(feature = "random")]
+use rand_chacha::ChaCha8Rng;
+use std::cmp::Reverse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SeparableOptions {
+    // the separable form keeps at most this fraction of the kernel parameters
+    pub max_param_ratio: f64,
+    // depthwise filters over few channels, such as the stem, lose too much
+    pub min_in_channels: u64,
+    // the replaced convolutions account for at most this fraction of the
+    // convolution FLOPs of the model. a larger budget saves more at the cost of
+    // more accuracy.
+    pub flops_budget: f64,
+}
+
+impl Default for SeparableOptions {
+    fn default() -> Self {
+        Self {
+            max_param_ratio: 0.5,
+            min_in_channels: 16,
+            flops_budget: 0.5,
+        }
+    }
+}
+
+// a convolution and the cost of its depthwise-separable replacement. the
+// parameters count the kernel values and the FLOPs count multiply-adds.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SeparableCandidate {
+    pub layer_index: usize,
+    pub params: u64,
+    pub separable_params: u64,
+    pub flops: u64,
+    pub separable_flops: u64,
+}
+
+impl SeparableCandidate {
+    pub fn param_ratio(&self) -> f64 {
+        self.separable_params as f64 / self.params as f64
+    }
+
+    pub fn flops_saved(&self) -> u64 {
+        self.flops - self.separable_flops
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeparatedConfig {
+    pub config: DarknetConfig,
+    // the new index of each original layer. a replaced convolution maps to its
+    // pointwise convolution, which is preceded by the depthwise one.
+    pub new_indexes: IndexMap<usize, usize>,
+}
+
+impl ModelBase {
+    // the convolutions worth replacing by a depthwise convolution followed by a
+    // pointwise one, in ascending order. the eligible convolutions saving the
+    // most FLOPs are taken first until the FLOPs budget is spent.
+    pub fn separable_candidates(&self, options: &SeparableOptions) -> Vec<SeparableCandidate> {
+        let heads = self.head_convolutions();
+        let total_flops: u64 = self
+            .layers
+            .values()
+            .filter_map(|layer| match layer {
+                LayerBase::Convolutional(conv) => {
+                    let [out_h, out_w, _] = conv.output_shape;
+                    let [in_c, filters, size, _] = conv.weights_shape();
+                    Some(out_h * out_w * in_c * filters * size * size)
+                }
+                _ => None,
+            })
+            .sum();
+        let budget = total_flops as f64 * options.flops_budget;
+
+        let candidates = self
+            .layers
+            .iter()
+            .filter_map(|(&layer_index, layer)| {
+                let conv = match layer {
+                    LayerBase::Convolutional(conv) => conv,
+                    _ => return None,
+                };
+                let config = &conv.config;
+                let [_h, _w, in_c] = conv.input_shape;
+                let [out_h, out_w, _] = conv.output_shape;
+                let eligible = is_separable(config)
+                    && in_c >= options.min_in_channels
+                    && !heads.contains(&layer_index);
+                if !eligible {
+                    return None;
+                }
+
+                let params = config.size.pow(2) * in_c * config.filters;
+                let separable_params = config.size.pow(2) * in_c + in_c * config.filters;
+                let candidate = SeparableCandidate {
+                    layer_index,
+                    params,
+                    separable_params,
+                    flops: out_h * out_w * params,
+                    separable_flops: out_h * out_w * separable_params,
+                };
+                (candidate.param_ratio() <= options.max_param_ratio).then_some(candidate)
+            })
+            .sorted_by_key(|candidate| (Reverse(candidate.flops_saved()), candidate.layer_index));
+
+        let mut spent = 0;
+        candidates
+            .filter(|candidate| {
+                let fits = (spent + candidate.flops) as f64 <= budget;
+                if fits {
+                    spent += candidate.flops;
+                }
+                fits
+            })
+            .sorted_by_key(|candidate| candidate.layer_index)
+            .collect()
+    }
+}
+
+impl DarknetConfig {
+    // replace each given convolution by a depthwise convolution with the same
+    // window followed by a pointwise convolution with the same filters, and
+    // rewrite the layer references
+    pub fn separate_convolutions(&self, layer_indexes: &[usize]) -> Result<SeparatedConfig> {
+        let in_channels = {
+            let model = ModelBase::from_config(self)?;
+            layer_indexes
+                .iter()
+                .map(|&layer_index| {
+                    let in_c = match (model.layers.get(&layer_index), self.layers.get(layer_index))
+                    {
+                        (
+                            Some(LayerBase::Convolutional(conv)),
+                            Some(LayerConfig::Convolutional(config)),
+                        ) if is_separable(config) => conv.input_shape[2],
+                        (Some(LayerBase::Convolutional(_)), _) => bail!(
+                            "the convolutional layer {} cannot be separated",
+                            layer_index
+                        ),
+                        _ => bail!("layer {} is not a convolutional layer", layer_index),
+                    };
+                    Ok((layer_index, in_c))
+                })
+                .collect::<Result<HashMap<_, _>>>()?
+        };
+
+        let new_indexes: IndexMap<usize, usize> = {
+            let mut num_inserted = 0;
+            (0..self.layers.len())
+                .map(|old_index| {
+                    if in_channels.contains_key(&old_index) {
+                        num_inserted += 1;
+                    }
+                    (old_index, old_index + num_inserted)
+                })
+                .collect()
+        };
+
+        let remap = |index: LayerIndex, old_curr: usize, new_curr: usize| -> Result<LayerIndex> {
+            let old_target = index
+                .to_absolute(old_curr)
+                .ok_or_else(|| format_err!("layer {} has an invalid layer index", old_curr))?;
+            let new_target = *new_indexes.get(&old_target).ok_or_else(|| {
+                format_err!(
+                    "layer {} refers to the missing layer {}",
+                    old_curr,
+                    old_target
+                )
+            })?;
+            Ok(match index {
+                LayerIndex::Relative(_) => {
+                    LayerIndex::Relative(NonZeroUsize::new(new_curr - new_target).unwrap())
+                }
+                LayerIndex::Absolute(_) => LayerIndex::Absolute(new_target),
+            })
+        };
+
+        let mut layers = vec![];
+        for (old_curr, layer) in self.layers.iter().enumerate() {
+            let new_curr = new_indexes[&old_curr];
+            let mut layer = layer.clone();
+            if let LayerConfig::Convolutional(conf) = &layer {
+                let owner = conf
+                    .share_index
+                    .and_then(|index| index.to_absolute(old_curr));
+                if let Some(owner) = owner.filter(|owner| in_channels.contains_key(owner)) {
+                    bail!(
+                        "layer {} shares the weights of the separated layer {}",
+                        old_curr,
+                        owner
+                    );
+                }
+            }
+            remap_references(&mut layer, |index| remap(index, old_curr, new_curr))?;
+
+            match (layer, in_channels.get(&old_curr)) {
+                (LayerConfig::Convolutional(conf), Some(&in_c)) => {
+                    let depthwise = ConvolutionalConfig {
+                        filters: in_c,
+                        groups: in_c,
+                        batch_normalize: true,
+                        assisted_excitation: false,
+                        ..conf.clone()
+                    };
+                    let pointwise = ConvolutionalConfig {
+                        groups: 1,
+                        size: 1,
+                        stride_x: 1,
+                        stride_y: 1,
+                        dilation: 1,
+                        antialiasing: false,
+                        padding: 0,
+                        deform: Deform::None,
+                        coordconv: false,
+                        ..conf
+                    };
+                    layers.push(LayerConfig::Convolutional(depthwise));
+                    layers.push(LayerConfig::Convolutional(pointwise));
+                }
+                (layer, _) => layers.push(layer),
+            }
+        }
+
+        Ok(SeparatedConfig {
+            config: DarknetConfig {
+                net: self.net.clone(),
+                layers,
+            },
+            new_indexes,
+        })
+    }
+}
+
+#[cfg(feature = "random")]
+impl DarknetModel {
+    // the model with the given convolutions separated. the other layers keep
+    // their weights and the new convolutions are initialized like darknet does.
+    pub fn separate_convolutions(&self, layer_indexes: &[usize], seed: u64) -> Result<Self> {
+        let SeparatedConfig {
+            config,
+            new_indexes,
+        } = self.base.to_config().separate_convolutions(layer_indexes)?;
+        let mut model = DarknetModel::from_config(&config)?;
+        model.base.seen = self.base.seen;
+        model.base.cur_iteration = self.base.cur_iteration;
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        for (&old_index, &new_index) in &new_indexes {
+            if layer_indexes.contains(&old_index) {
+                for layer_index in [new_index - 1, new_index] {
+                    match &mut model.layers[&layer_index] {
+                        Layer::Convolutional(conv) => init_convolutional(conv, &mut rng),
+                        _ => unreachable!(),
+                    }
+                }
+            } else {
+                let source = self.layers[&old_index].buffers();
+                let target = model.layers[&new_index].buffers_mut();
+                for ((_, source), (_, target)) in source.into_iter().zip(target) {
+                    target.copy_from_slice(source);
+                }
+            }
+        }
+
+        Ok(model)
+    }
+}
+
+// plain convolutions with their own weights
+fn is_separable(config: &ConvolutionalConfig) -> bool {
+    config.size > 1
+        && config.groups == 1
+        && config.share_index.is_none()
+        && !config.binary
+        && !config.xnor
+}
@@ -0,0 +1,82 @@
+use crate::{
+    binding::HeadInfo,
+    common::*,
+    config::Shape,
+    model::ModelBase,
+    summary::{LayerSummary, ModelSummary},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ModelMetadata {
+    pub input_shape: Shape,
+    pub classes: u64,
+    pub class_names: Option<Vec<String>>,
+    pub seen: u64,
+    pub heads: Vec<HeadMetadata>,
+    pub layers: Vec<LayerSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HeadMetadata {
+    pub layer_index: usize,
+    pub shape: [u64; 3],
+    pub stride: [u64; 2],
+    pub anchors: Vec<(u64, u64)>,
+}
+
+impl ModelMetadata {
+    pub fn new(model: &ModelBase, class_names: Option<Vec<String>>) -> Result<Self> {
+        if let Some(class_names) = &class_names {
+            ensure!(
+                class_names.len() as u64 == model.net.classes,
+                "the number of class names ({}) does not match the number of classes ({})",
+                class_names.len(),
+                model.net.classes
+            );
+        }
+
+        let ModelSummary {
+            input_shape,
+            classes,
+            layers,
+        } = model.summary();
+
+        let heads = model
+            .heads()
+            .into_iter()
+            .map(|head| {
+                let HeadInfo {
+                    layer_index,
+                    stride,
+                    grid_size: [head_h, head_w],
+                    anchors,
+                    ..
+                } = head;
+                let head_c = match model.layers[&layer_index].output_shape() {
+                    Shape::Hwc([_h, _w, c]) => c,
+                    Shape::Flat(size) => size,
+                };
+
+                HeadMetadata {
+                    layer_index,
+                    shape: [head_h, head_w, head_c],
+                    stride,
+                    anchors,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            input_shape,
+            classes,
+            class_names,
+            seen: model.seen,
+            heads,
+            layers,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
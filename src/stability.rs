@@ -0,0 +1,126 @@
+use crate::{
+    common::*,
+    config::{DarknetConfig, LayerConfig, LayerConfigEx, WeightsType},
+    validate::{Diagnostic, DiagnosticCode},
+};
+
+// the options guarding the training against diverging values, gathered from
+// the net section and the layers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StabilityOptions {
+    // reset NaN and infinite values in the outputs and deltas instead of
+    // aborting, see fix_nan_and_inf() in darknet
+    pub try_fix_nan: bool,
+    // the loss is scaled by this in mixed precision training
+    pub loss_scale: R64,
+    // the weights of the layer are constrained to [-clip, clip] after updates
+    pub clips: IndexMap<usize, R64>,
+    // the box deltas of the yolo layer are constrained to [-max_delta, max_delta]
+    pub max_deltas: IndexMap<usize, R64>,
+}
+
+impl StabilityOptions {
+    pub fn from_config(config: &DarknetConfig) -> Self {
+        let clips = config
+            .layers
+            .iter()
+            .enumerate()
+            .filter_map(|(layer_index, layer)| Some((layer_index, layer.common().clip?)))
+            .collect();
+        let max_deltas = config
+            .layers
+            .iter()
+            .enumerate()
+            .filter_map(|(layer_index, layer)| match layer {
+                LayerConfig::Yolo(conf) => Some((layer_index, conf.max_delta?)),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            try_fix_nan: config.net.try_fix_nan,
+            loss_scale: config.net.loss_scale,
+            clips,
+            max_deltas,
+        }
+    }
+
+    pub fn diagnostics(&self, config: &DarknetConfig) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+
+        if self.loss_scale <= 0.0 {
+            diagnostics.push(Diagnostic::error(
+                DiagnosticCode::NonPositiveLossScale,
+                None,
+                format!("loss_scale={} must be positive", self.loss_scale),
+            ));
+        }
+
+        self.clips.iter().for_each(|(&layer_index, &clip)| {
+            if clip <= 0.0 {
+                diagnostics.push(Diagnostic::error(
+                    DiagnosticCode::NonPositiveClip,
+                    Some(layer_index),
+                    format!("clip={} must be positive", clip),
+                ));
+            } else if !has_weights(&config.layers[layer_index]) {
+                diagnostics.push(Diagnostic::warning(
+                    DiagnosticCode::IneffectiveClip,
+                    Some(layer_index),
+                    format!("clip={} has no effect on a layer without weights", clip),
+                ));
+            }
+        });
+
+        self.max_deltas
+            .iter()
+            .for_each(|(&layer_index, &max_delta)| {
+                if max_delta <= 0.0 {
+                    diagnostics.push(Diagnostic::error(
+                        DiagnosticCode::NonPositiveMaxDelta,
+                        Some(layer_index),
+                        format!(
+                            "max_delta={} must be positive, otherwise no box is trained",
+                            max_delta
+                        ),
+                    ));
+                }
+            });
+
+        diagnostics
+    }
+}
+
+impl DarknetConfig {
+    pub fn stability_options(&self) -> StabilityOptions {
+        StabilityOptions::from_config(self)
+    }
+}
+
+fn has_weights(layer: &LayerConfig) -> bool {
+    match layer {
+        LayerConfig::Connected(_)
+        | LayerConfig::Convolutional(_)
+        | LayerConfig::BatchNorm(_)
+        | LayerConfig::Implicit(_)
+        | LayerConfig::Local(_)
+        | LayerConfig::Lstm(_)
+        | LayerConfig::Crnn(_)
+        | LayerConfig::ConvLstm(_) => true,
+        LayerConfig::Shortcut(conf) => conf.weights_type != WeightsType::None,
+        LayerConfig::Route(_)
+        | LayerConfig::MaxPool(_)
+        | LayerConfig::UpSample(_)
+        | LayerConfig::Yolo(_)
+        | LayerConfig::AvgPool(_)
+        | LayerConfig::ScaleChannels(_)
+        | LayerConfig::Dropout(_)
+        | LayerConfig::Softmax(_)
+        | LayerConfig::Region(_)
+        | LayerConfig::Sam(_)
+        | LayerConfig::LocalAvgPool(_)
+        | LayerConfig::Reorg(_)
+        | LayerConfig::GaussianYolo(_)
+        | LayerConfig::Custom(_) => false,
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{
+    common::*,
+    compress,
+    config::{DarknetConfig, LayerConfigEx},
+    darknet::DarknetModel,
+    prune::{remove_layers, PrunedConfig},
+    query::{CompareOp, Query, QueryReport},
+};
+
+// the annotation of the stage a layer belongs to, e.g. x_stage=backbone. the
+// extension keys are kept by the parser and written back on save.
+pub const STAGE_KEY: &str = "x_stage";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LayerStage {
+    pub name: String,
+    pub layers: Range<usize>,
+}
+
+// named ranges of layers, such as backbone, neck and head. the ranges are
+// disjoint and sorted, and layers between them belong to no stage.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct StageMap {
+    stages: Vec<LayerStage>,
+}
+
+impl StageMap {
+    pub fn new(stages: impl IntoIterator<Item = LayerStage>) -> Result<Self> {
+        let stages: Vec<_> = stages
+            .into_iter()
+            .sorted_by_key(|stage| stage.layers.start)
+            .collect();
+
+        for stage in &stages {
+            ensure!(
+                !stage.name.is_empty(),
+                "the stage at layers {:?} has no name",
+                stage.layers
+            );
+            ensure!(
+                !stage.layers.is_empty(),
+                "the stage '{}' has no layers",
+                stage.name
+            );
+        }
+        let mut names = HashSet::new();
+        if let Some(stage) = stages.iter().find(|stage| !names.insert(&stage.name)) {
+            bail!("the stage '{}' is defined more than once", stage.name);
+        }
+        if let Some((prev, next)) = stages
+            .iter()
+            .tuple_windows()
+            .find(|(prev, next)| prev.layers.end > next.layers.start)
+        {
+            bail!(
+                "the stage '{}' at layers {:?} overlaps the stage '{}' at layers {:?}",
+                prev.name,
+                prev.layers,
+                next.name,
+                next.layers
+            );
+        }
+
+        Ok(Self { stages })
+    }
+
+    // collect the x_stage annotations, where the layers of a stage must be
+    // consecutive
+    pub fn from_config(config: &DarknetConfig) -> Result<Self> {
+        let mut stages: Vec<LayerStage> = vec![];
+        for (layer_index, layer) in config.layers.iter().enumerate() {
+            let name = match layer.common().extensions.get(STAGE_KEY) {
+                Some(name) => name,
+                None => continue,
+            };
+            match stages.last_mut() {
+                Some(stage) if stage.name == *name && stage.layers.end == layer_index => {
+                    stage.layers.end = layer_index + 1;
+                }
+                _ => {
+                    if let Some(stage) = stages.iter().find(|stage| stage.name == *name) {
+                        bail!(
+                            "layer {} belongs to the stage '{}', which ends at layer {}",
+                            layer_index,
+                            name,
+                            stage.layers.end - 1
+                        );
+                    }
+                    stages.push(LayerStage {
+                        name: name.clone(),
+                        layers: layer_index..(layer_index + 1),
+                    });
+                }
+            }
+        }
+        Self::new(stages)
+    }
+
+    pub fn get(&self, name: &str) -> Result<&LayerStage> {
+        self.stages
+            .iter()
+            .find(|stage| stage.name == name)
+            .ok_or_else(|| {
+                format_err!(
+                    "no stage is named '{}', the stages are {}",
+                    name,
+                    self.stages.iter().map(|stage| &stage.name).join(", ")
+                )
+            })
+    }
+
+    pub fn stage_of(&self, layer_index: usize) -> Option<&LayerStage> {
+        self.stages
+            .iter()
+            .find(|stage| stage.layers.contains(&layer_index))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LayerStage> {
+        self.stages.iter()
+    }
+}
+
+// selects the layers of a stage
+fn stage_query(stage: &LayerStage) -> Query {
+    Query::new()
+        .filter("index", CompareOp::Ge, stage.layers.start)
+        .filter("index", CompareOp::Lt, stage.layers.end)
+}
+
+impl DarknetConfig {
+    pub fn stages(&self) -> Result<StageMap> {
+        StageMap::from_config(self)
+    }
+
+    // replace the x_stage annotations by the stages
+    pub fn annotate_stages(&mut self, stages: &StageMap) -> Result<()> {
+        if let Some(stage) = stages
+            .iter()
+            .find(|stage| stage.layers.end > self.layers.len())
+        {
+            bail!(
+                "the stage '{}' at layers {:?} exceeds the {} layers",
+                stage.name,
+                stage.layers,
+                self.layers.len()
+            );
+        }
+
+        Query::new().unset(STAGE_KEY).apply(self)?;
+        for stage in stages.iter() {
+            stage_query(stage).set(STAGE_KEY, &stage.name).apply(self)?;
+        }
+        Ok(())
+    }
+
+    // stop the updates of the layers in the stage with dont_update=1. unlike
+    // stopbackward, the earlier layers are still trained.
+    pub fn freeze_stage(&mut self, stages: &StageMap, name: &str) -> Result<QueryReport> {
+        stage_query(stages.get(name)?)
+            .set("dont_update", 1)
+            .apply(self)
+    }
+
+    // remove the layers of the stage. the remaining layers must not refer to them.
+    pub fn remove_stage(&self, stages: &StageMap, name: &str) -> Result<PrunedConfig> {
+        let removed: Vec<_> = stages.get(name)?.layers.clone().collect();
+        let config = remove_layers(self, &removed, |_| None)?;
+        Ok(PrunedConfig { config, removed })
+    }
+}
+
+impl DarknetModel {
+    // write the layers up to the end of the stage like `darknet partial`, e.g. the
+    // .conv.N file of the backbone
+    pub fn write_stage_weights<W>(&self, writer: W, stages: &StageMap, name: &str) -> Result<()>
+    where
+        W: Write,
+    {
+        self.write_partial_weights(writer, stages.get(name)?.layers.end)
+    }
+
+    pub fn save_stage_weights<P>(
+        &self,
+        weights_file: P,
+        stages: &StageMap,
+        name: &str,
+    ) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        compress::write_file(weights_file.as_ref(), |writer| {
+            self.write_stage_weights(writer, stages, name)
+        })
+    }
+}
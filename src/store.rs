@@ -0,0 +1,288 @@
+use crate::{
+    common::*,
+    config::{DarknetConfig, LayerConfigEx},
+    darknet::DarknetModel,
+    utils::sha256_digest,
+    weights_layout::stored_buffers,
+};
+
+// a content-addressed store of checkpoints. each layer is stored as a blob
+// named by the SHA-256 of its bytes, and a checkpoint is a manifest listing the
+// blobs, so layers that are equal across checkpoints are stored once, such as
+// the backbone shared by the variants fine-tuned from it. the directory looks
+// like
+//
+//   {root}/blobs/{digest[..2]}/{digest}
+//   {root}/checkpoints/{name}.json
+//
+// the blob of a layer holds its stored buffers in the order and the byte order
+// of a .weights file. the config text is stored as a blob as well.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlobStore {
+    pub root: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointManifest {
+    pub config_digest: String,
+    pub seen: u64,
+    pub layers: Vec<LayerBlob>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerBlob {
+    pub layer_index: usize,
+    pub kind: String,
+    // None if the layer stores no weights
+    pub digest: Option<String>,
+    pub num_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct PutStats {
+    // the blobs written by this checkpoint
+    pub num_new_blobs: usize,
+    pub num_new_bytes: u64,
+    // the blobs already in the store
+    pub num_reused_blobs: usize,
+    pub num_reused_bytes: u64,
+}
+
+impl DarknetModel {
+    // the SHA-256 of the stored bytes of each layer, None for layers without
+    // weights
+    pub fn layer_digests(&self) -> IndexMap<usize, Option<String>> {
+        (0..self.layers.len())
+            .map(|layer_index| {
+                let digest = self
+                    .layer_bytes(layer_index)
+                    .map(|bytes| sha256_digest(&bytes));
+                (layer_index, digest)
+            })
+            .collect()
+    }
+
+    fn layer_bytes(&self, layer_index: usize) -> Option<Vec<u8>> {
+        let layer = &self.layers[&layer_index];
+        let config = self.base.layers[&layer_index].config();
+        let bytes: Vec<u8> = stored_buffers(layer, config.common())
+            .into_iter()
+            .flat_map(|(_, values)| values.iter().flat_map(|value| value.to_le_bytes()))
+            .collect();
+        (!bytes.is_empty()).then_some(bytes)
+    }
+}
+
+impl BlobStore {
+    pub fn open<P>(root: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref().to_owned();
+        fs::create_dir_all(root.join("blobs"))?;
+        fs::create_dir_all(root.join("checkpoints"))?;
+        Ok(Self { root })
+    }
+
+    pub fn blob_path(&self, digest: &str) -> PathBuf {
+        self.root
+            .join("blobs")
+            .join(&digest[..2.min(digest.len())])
+            .join(digest)
+    }
+
+    pub fn manifest_path(&self, name: &str) -> PathBuf {
+        self.root.join("checkpoints").join(format!("{}.json", name))
+    }
+
+    pub fn contains_blob(&self, digest: &str) -> bool {
+        self.blob_path(digest).is_file()
+    }
+
+    // store the bytes unless the store has them, returning the digest and
+    // whether the blob is new
+    pub fn put_blob(&self, bytes: &[u8]) -> Result<(String, bool)> {
+        let digest = sha256_digest(bytes);
+        let path = self.blob_path(&digest);
+        if path.is_file() {
+            return Ok((digest, false));
+        }
+
+        // written to a temporary file first so that a blob is never partial
+        fs::create_dir_all(path.parent().unwrap())?;
+        let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp_path, bytes)
+            .and_then(|()| fs::rename(&tmp_path, &path))
+            .map_err(|err| {
+                let _ = fs::remove_file(&tmp_path);
+                format_err!("failed to write blob {}: {}", digest, err)
+            })?;
+        Ok((digest, true))
+    }
+
+    pub fn get_blob(&self, digest: &str) -> Result<Vec<u8>> {
+        let bytes = fs::read(self.blob_path(digest))
+            .map_err(|err| format_err!("failed to read blob {}: {}", digest, err))?;
+        ensure!(
+            sha256_digest(&bytes) == digest,
+            "the blob {} is corrupted",
+            digest
+        );
+        Ok(bytes)
+    }
+
+    pub fn put_checkpoint(&self, name: &str, model: &DarknetModel) -> Result<PutStats> {
+        ensure!(
+            !name.is_empty() && !name.contains(['/', '\\']),
+            "invalid checkpoint name '{}'",
+            name
+        );
+        let mut stats = PutStats::default();
+        let mut put = |bytes: &[u8]| -> Result<String> {
+            let (digest, is_new) = self.put_blob(bytes)?;
+            if is_new {
+                stats.num_new_blobs += 1;
+                stats.num_new_bytes += bytes.len() as u64;
+            } else {
+                stats.num_reused_blobs += 1;
+                stats.num_reused_bytes += bytes.len() as u64;
+            }
+            Ok(digest)
+        };
+
+        let config_digest = put(model.base.to_config().to_cfg_string()?.as_bytes())?;
+        let layers: Vec<_> = (0..model.layers.len())
+            .map(|layer_index| -> Result<_> {
+                let bytes = model.layer_bytes(layer_index);
+                let num_bytes = bytes.as_ref().map_or(0, |bytes| bytes.len() as u64);
+                let digest = bytes.map(|bytes| put(&bytes)).transpose()?;
+                Ok(LayerBlob {
+                    layer_index,
+                    kind: model.base.layers[&layer_index].kind().to_owned(),
+                    digest,
+                    num_bytes,
+                })
+            })
+            .try_collect()?;
+
+        let manifest = CheckpointManifest {
+            config_digest,
+            seen: model.base.seen,
+            layers,
+        };
+        fs::write(
+            self.manifest_path(name),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        Ok(stats)
+    }
+
+    pub fn manifest(&self, name: &str) -> Result<CheckpointManifest> {
+        let text = fs::read_to_string(self.manifest_path(name))
+            .map_err(|err| format_err!("failed to read checkpoint '{}': {}", name, err))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub fn get_checkpoint(&self, name: &str) -> Result<DarknetModel> {
+        let manifest = self.manifest(name)?;
+        let config: DarknetConfig =
+            String::from_utf8(self.get_blob(&manifest.config_digest)?)?.parse()?;
+        let mut model = DarknetModel::from_config(&config)?;
+        ensure!(
+            manifest.layers.len() == model.layers.len(),
+            "the checkpoint '{}' lists {} layers but the config has {}",
+            name,
+            manifest.layers.len(),
+            model.layers.len()
+        );
+
+        for entry in &manifest.layers {
+            let digest = match &entry.digest {
+                Some(digest) => digest,
+                None => continue,
+            };
+            let bytes = self.get_blob(digest)?;
+            let expect = model
+                .layer_bytes(entry.layer_index)
+                .map_or(0, |bytes| bytes.len());
+            ensure!(
+                bytes.len() == expect,
+                "the blob of layer {} has {} bytes, but {} bytes are expected",
+                entry.layer_index,
+                bytes.len(),
+                expect
+            );
+            model
+                .layers
+                .get_mut(&entry.layer_index)
+                .ok_or_else(|| format_err!("the layer {} does not exist", entry.layer_index))?
+                .load_weights(bytes.as_slice(), false)?;
+        }
+
+        model.base.seen = manifest.seen;
+        model.base.cur_iteration = model.base.net.iteration(manifest.seen);
+        Ok(model)
+    }
+
+    pub fn checkpoint_names(&self) -> Result<Vec<String>> {
+        let names = fs::read_dir(self.root.join("checkpoints"))?
+            .map(|entry| -> Result<_> {
+                let path = entry?.path();
+                let name = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.strip_suffix(".json"))
+                    .map(|name| name.to_owned());
+                Ok(name)
+            })
+            .filter_map(|result| result.transpose())
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .sorted()
+            .collect();
+        Ok(names)
+    }
+
+    // remove the manifest. the blobs stay until collect_garbage().
+    pub fn remove_checkpoint(&self, name: &str) -> Result<()> {
+        fs::remove_file(self.manifest_path(name))
+            .map_err(|err| format_err!("failed to remove checkpoint '{}': {}", name, err))
+    }
+
+    // remove the blobs that no checkpoint refers to, returning their digests
+    pub fn collect_garbage(&self) -> Result<Vec<String>> {
+        let manifests: Vec<_> = self
+            .checkpoint_names()?
+            .iter()
+            .map(|name| self.manifest(name))
+            .try_collect()?;
+        let referenced: HashSet<String> = manifests
+            .into_iter()
+            .flat_map(|manifest| {
+                iter::once(manifest.config_digest)
+                    .chain(manifest.layers.into_iter().filter_map(|entry| entry.digest))
+            })
+            .collect();
+
+        let mut removed = vec![];
+        for dir in fs::read_dir(self.root.join("blobs"))? {
+            let dir = dir?.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let digest = match path.file_name().and_then(|name| name.to_str()) {
+                    Some(digest) => digest.to_owned(),
+                    None => continue,
+                };
+                if !referenced.contains(&digest) {
+                    fs::remove_file(&path)?;
+                    removed.push(digest);
+                }
+            }
+        }
+        removed.sort();
+        Ok(removed)
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{
+    common::*,
+    config::Shape,
+    model::{ModelBase, ShapeList},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ModelSummary {
+    pub input_shape: Shape,
+    pub classes: u64,
+    pub layers: Vec<LayerSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LayerSummary {
+    pub index: usize,
+    pub kind: String,
+    pub description: String,
+    pub from_indexes: Vec<String>,
+    pub input_shape: Vec<Shape>,
+    pub output_shape: Shape,
+}
+
+impl ModelSummary {
+    pub fn new(model: &ModelBase) -> Self {
+        let num_layers = model.layers.len();
+        let layers: Vec<_> = (0..num_layers)
+            .map(|index| {
+                let layer = &model.layers[&index];
+                let input_shape = match layer.input_shape() {
+                    ShapeList::SingleFlat(size) => vec![Shape::Flat(size)],
+                    ShapeList::SingleHwc(hwc) => vec![Shape::Hwc(hwc)],
+                    ShapeList::MultipleHwc(shapes) => shapes.into_iter().map(Shape::Hwc).collect(),
+                };
+
+                LayerSummary {
+                    index,
+                    kind: layer.kind().to_string(),
+                    description: layer.config().to_string(),
+                    from_indexes: layer
+                        .from_indexes()
+                        .iter()
+                        .map(|position| position.to_string())
+                        .collect(),
+                    input_shape,
+                    output_shape: layer.output_shape(),
+                }
+            })
+            .collect();
+
+        Self {
+            input_shape: model.net.input_size,
+            classes: model.net.classes,
+            layers,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl ModelBase {
+    pub fn summary(&self) -> ModelSummary {
+        ModelSummary::new(self)
+    }
+}
@@ -2,14 +2,15 @@ use crate::{
     common::*,
     config::{
         Activation, CompoundNetConfig, CompoundYoloConfig, ConnectedConfig, ConvolutionalConfig,
-        DarknetConfig, MaxPoolConfig, RouteConfig, Shape, ShortcutConfig, UpSampleConfig,
-        WeightsNormalization, WeightsType,
+        DarknetConfig, DropoutConfig, MaxPoolConfig, RouteConfig, ScaleChannelsConfig, Shape,
+        ShortcutConfig, SoftmaxConfig, UpSampleConfig, WeightsNormalization, WeightsType,
     },
     darknet::{self, DarknetModel},
     model::{
-        BatchNormLayerBase, ConnectedLayerBase, ConvolutionalLayerBase, LayerBase, LayerPosition,
-        LayerPositionSet, MaxPoolLayerBase, ModelBase, RouteLayerBase, ShapeList,
-        ShortcutLayerBase, UpSampleLayerBase, YoloLayerBase,
+        AvgPoolLayerBase, BatchNormLayerBase, ConnectedLayerBase, ConvolutionalLayerBase,
+        DropoutLayerBase, ImplicitLayerBase, LayerBase, LayerPosition, LayerPositionSet,
+        MaxPoolLayerBase, ModelBase, RouteLayerBase, ScaleChannelsLayerBase, ShapeList,
+        ShortcutLayerBase, SoftmaxLayerBase, UpSampleLayerBase, YoloLayerBase,
     },
 };
 use tch::{nn, Kind, Tensor};
@@ -67,6 +68,22 @@ impl TensorList {
     }
 }
 
+// None if the activation is not supported, layers check it on construction
+fn activation_fn(activation: Activation) -> Option<fn(&Tensor) -> Tensor> {
+    let activate: fn(&Tensor) -> Tensor = match activation {
+        Activation::Linear => |xs: &Tensor| xs.shallow_clone(),
+        Activation::Relu => |xs: &Tensor| xs.relu(),
+        Activation::Leaky => |xs: &Tensor| xs.clamp_min(0.0) + xs.clamp_max(0.0) * 0.1,
+        Activation::Logistic => |xs: &Tensor| xs.sigmoid(),
+        Activation::Tanh => |xs: &Tensor| xs.tanh(),
+        Activation::Swish => |xs: &Tensor| xs * xs.sigmoid(),
+        Activation::Mish => |xs: &Tensor| xs * (xs.exp() + 1.0).log().tanh(),
+        Activation::HardMish => |xs: &Tensor| xs.hardswish(),
+        _ => return None,
+    };
+    Some(activate)
+}
+
 mod tch_model {
     use super::*;
 
@@ -130,6 +147,51 @@ mod tch_model {
                         darknet::Layer::Yolo(conf) => {
                             YoloLayer::new(path, conf, num_classes)?.into()
                         }
+                        darknet::Layer::Implicit(conf) => ImplicitLayer::new(path, conf)?.into(),
+                        darknet::Layer::AvgPool(conf) => AvgPoolLayer::new(path, conf)?.into(),
+                        darknet::Layer::ScaleChannels(conf) => {
+                            ScaleChannelsLayer::new(path, conf)?.into()
+                        }
+                        darknet::Layer::Dropout(conf) => DropoutLayer::new(path, conf)?.into(),
+                        darknet::Layer::Softmax(conf) => SoftmaxLayer::new(path, conf)?.into(),
+                        darknet::Layer::Region(_) => {
+                            bail!("layer {}: region layers are not supported", layer_index)
+                        }
+                        darknet::Layer::GaussianYolo(_) => {
+                            bail!(
+                                "layer {}: Gaussian_yolo layers are not supported",
+                                layer_index
+                            )
+                        }
+                        darknet::Layer::Reorg(_) => {
+                            bail!("layer {}: reorg layers are not supported", layer_index)
+                        }
+                        darknet::Layer::Local(_) => {
+                            bail!("layer {}: local layers are not supported", layer_index)
+                        }
+                        darknet::Layer::LocalAvgPool(_) => {
+                            bail!(
+                                "layer {}: local_avgpool layers are not supported",
+                                layer_index
+                            )
+                        }
+                        darknet::Layer::Sam(_) => {
+                            bail!("layer {}: sam layers are not supported", layer_index)
+                        }
+                        darknet::Layer::Lstm(_) => {
+                            bail!("layer {}: lstm layers are not supported", layer_index)
+                        }
+                        darknet::Layer::Crnn(_) => {
+                            bail!("layer {}: crnn layers are not supported", layer_index)
+                        }
+                        darknet::Layer::ConvLstm(_) => {
+                            bail!("layer {}: conv_lstm layers are not supported", layer_index)
+                        }
+                        darknet::Layer::Custom(layer) => bail!(
+                            "layer {}: custom [{}] layers are not supported",
+                            layer_index,
+                            layer.base.config.section
+                        ),
                     };
 
                     collected.insert(layer_index, layer);
@@ -165,7 +227,7 @@ mod tch_model {
                             .collect();
                         TensorList::Multiple(tensors)
                     }
-                    LayerPositionSet::Empty => unreachable!(),
+                    LayerPositionSet::Empty => TensorList::Multiple(vec![]),
                 };
 
                 // save output
@@ -292,6 +354,11 @@ mod layer {
         UpSample(UpSampleLayer),
         Yolo(YoloLayer),
         BatchNorm(BatchNormLayer),
+        Implicit(ImplicitLayer),
+        AvgPool(AvgPoolLayer),
+        ScaleChannels(ScaleChannelsLayer),
+        Dropout(DropoutLayer),
+        Softmax(SoftmaxLayer),
     }
 
     impl Layer {
@@ -305,6 +372,19 @@ mod layer {
                 Self::UpSample(layer) => ShapeList::SingleHwc(layer.base.input_shape),
                 Self::Yolo(layer) => ShapeList::SingleHwc(layer.base.inout_shape),
                 Self::BatchNorm(layer) => ShapeList::SingleHwc(layer.base.inout_shape),
+                Self::Implicit(_) => ShapeList::MultipleHwc(vec![]),
+                Self::AvgPool(layer) => ShapeList::SingleHwc(layer.base.input_shape),
+                Self::ScaleChannels(layer) => {
+                    ShapeList::MultipleHwc(layer.base.input_shape.clone())
+                }
+                Self::Dropout(layer) => match layer.base.inout_shape {
+                    Shape::Hwc(hwc) => ShapeList::SingleHwc(hwc),
+                    Shape::Flat(flat) => ShapeList::SingleFlat(flat),
+                },
+                Self::Softmax(layer) => match layer.base.inout_shape {
+                    Shape::Hwc(hwc) => ShapeList::SingleHwc(hwc),
+                    Shape::Flat(flat) => ShapeList::SingleFlat(flat),
+                },
             }
         }
 
@@ -318,6 +398,11 @@ mod layer {
                 Self::UpSample(layer) => Shape::Hwc(layer.base.output_shape),
                 Self::Yolo(layer) => Shape::Hwc(layer.base.inout_shape),
                 Self::BatchNorm(layer) => Shape::Hwc(layer.base.inout_shape),
+                Self::Implicit(layer) => Shape::Hwc(layer.base.output_shape),
+                Self::AvgPool(layer) => Shape::Hwc(layer.base.output_shape),
+                Self::ScaleChannels(layer) => Shape::Hwc(layer.base.output_shape),
+                Self::Dropout(layer) => layer.base.inout_shape,
+                Self::Softmax(layer) => layer.base.inout_shape,
             }
         }
 
@@ -333,6 +418,13 @@ mod layer {
                 Self::UpSample(layer) => LayerPositionSet::Single(layer.base.from_indexes),
                 Self::Yolo(layer) => LayerPositionSet::Single(layer.base.from_indexes),
                 Self::BatchNorm(layer) => LayerPositionSet::Single(layer.base.from_indexes),
+                Self::Implicit(_) => LayerPositionSet::Empty,
+                Self::AvgPool(layer) => LayerPositionSet::Single(layer.base.from_indexes),
+                Self::ScaleChannels(layer) => {
+                    LayerPositionSet::Multiple(layer.base.from_indexes.clone())
+                }
+                Self::Dropout(layer) => LayerPositionSet::Single(layer.base.from_indexes),
+                Self::Softmax(layer) => LayerPositionSet::Single(layer.base.from_indexes),
             }
         }
 
@@ -346,6 +438,11 @@ mod layer {
                 Layer::UpSample(layer) => layer.forward(xs.single().unwrap()).into(),
                 Layer::Yolo(layer) => layer.forward(xs.single().unwrap()).into(),
                 Layer::BatchNorm(layer) => layer.forward_t(xs.single().unwrap(), train).into(),
+                Layer::Implicit(layer) => layer.forward().into(),
+                Layer::AvgPool(layer) => layer.forward(xs.single().unwrap()).into(),
+                Layer::ScaleChannels(layer) => layer.forward(xs.multiple().unwrap()).into(),
+                Layer::Dropout(layer) => layer.forward_t(xs.single().unwrap(), train).into(),
+                Layer::Softmax(layer) => layer.forward(xs.single().unwrap()).into(),
             }
         }
     }
@@ -362,6 +459,15 @@ mod layer {
     declare_tch_layer!(MaxPoolLayer, MaxPoolLayerBase, MaxPoolWeights);
     declare_tch_layer!(UpSampleLayer, UpSampleLayerBase, UpSampleWeights);
     declare_tch_layer!(YoloLayer, YoloLayerBase, YoloWeights);
+    declare_tch_layer!(ImplicitLayer, ImplicitLayerBase, ImplicitWeights);
+    declare_tch_layer!(AvgPoolLayer, AvgPoolLayerBase, AvgPoolWeights);
+    declare_tch_layer!(
+        ScaleChannelsLayer,
+        ScaleChannelsLayerBase,
+        ScaleChannelsWeights
+    );
+    declare_tch_layer!(DropoutLayer, DropoutLayerBase, DropoutWeights);
+    declare_tch_layer!(SoftmaxLayer, SoftmaxLayerBase, SoftmaxWeights);
 
     impl From<ConnectedLayer> for Layer {
         fn from(from: ConnectedLayer) -> Self {
@@ -411,6 +517,36 @@ mod layer {
         }
     }
 
+    impl From<ImplicitLayer> for Layer {
+        fn from(from: ImplicitLayer) -> Self {
+            Self::Implicit(from)
+        }
+    }
+
+    impl From<AvgPoolLayer> for Layer {
+        fn from(from: AvgPoolLayer) -> Self {
+            Self::AvgPool(from)
+        }
+    }
+
+    impl From<ScaleChannelsLayer> for Layer {
+        fn from(from: ScaleChannelsLayer) -> Self {
+            Self::ScaleChannels(from)
+        }
+    }
+
+    impl From<DropoutLayer> for Layer {
+        fn from(from: DropoutLayer) -> Self {
+            Self::Dropout(from)
+        }
+    }
+
+    impl From<SoftmaxLayer> for Layer {
+        fn from(from: SoftmaxLayer) -> Self {
+            Self::Softmax(from)
+        }
+    }
+
     impl ConnectedLayer {
         pub fn new<'p>(
             path: impl Borrow<nn::Path<'p>>,
@@ -528,9 +664,16 @@ mod layer {
                 stride_x,
                 padding,
                 groups,
+                activation,
                 ..
             } = *config;
 
+            ensure!(
+                activation_fn(activation).is_some(),
+                "the {} activation is not supported",
+                activation
+            );
+
             let stride = if stride_y == stride_x {
                 stride_y as i64
             } else {
@@ -648,17 +791,9 @@ mod layer {
                 None => xs,
             };
 
-            let xs = match activation {
-                Activation::Swish => todo!(),
-                Activation::Mish => todo!(),
-                Activation::HardMish => xs.hardswish(),
-                Activation::NormalizeChannels => todo!(),
-                Activation::NormalizeChannelsSoftmax => todo!(),
-                Activation::NormalizeChannelsSoftmaxMaxval => todo!(),
-                _ => unimplemented!(),
-            };
-
-            xs
+            // checked in new()
+            let activate = activation_fn(activation).unwrap();
+            activate(&xs)
         }
     }
 
@@ -720,6 +855,38 @@ mod layer {
         }
     }
 
+    impl ImplicitLayer {
+        pub fn new<'p>(
+            path: impl Borrow<nn::Path<'p>>,
+            from: &darknet::ImplicitLayer,
+        ) -> Result<Self> {
+            let path = path.borrow();
+            let darknet::ImplicitLayer {
+                base:
+                    ImplicitLayerBase {
+                        output_shape: [_h, _w, out_c],
+                        ..
+                    },
+                weights: darknet::ImplicitWeights { ref weights },
+            } = *from;
+
+            let out_c = out_c as i64;
+
+            // stored as [1, c, 1, 1] to broadcast over [batch, c, h, w] inputs
+            let mut implicit = path.zeros("implicit", &[1, out_c, 1, 1]);
+            implicit.replace(weights.as_slice().unwrap(), &[1, out_c, 1, 1]);
+
+            Ok(ImplicitLayer {
+                base: from.base.clone(),
+                weights: ImplicitWeights { implicit },
+            })
+        }
+
+        pub fn forward(&self) -> Tensor {
+            self.weights.implicit.shallow_clone()
+        }
+    }
+
     impl ShortcutLayer {
         pub fn new<'p>(
             path: impl Borrow<nn::Path<'p>>,
@@ -1022,6 +1189,132 @@ mod layer {
         }
     }
 
+    impl AvgPoolLayer {
+        pub fn new<'p>(
+            _path: impl Borrow<nn::Path<'p>>,
+            from: &darknet::AvgPoolLayer,
+        ) -> Result<Self> {
+            Ok(AvgPoolLayer {
+                base: from.base.clone(),
+                weights: AvgPoolWeights {},
+            })
+        }
+
+        pub fn forward(&self, xs: &Tensor) -> Tensor {
+            xs.adaptive_avg_pool2d(&[1, 1])
+        }
+    }
+
+    impl ScaleChannelsLayer {
+        pub fn new<'p>(
+            _path: impl Borrow<nn::Path<'p>>,
+            from: &darknet::ScaleChannelsLayer,
+        ) -> Result<Self> {
+            let activation = from.base.config.activation;
+            ensure!(
+                activation_fn(activation).is_some(),
+                "the {} activation is not supported",
+                activation
+            );
+
+            Ok(ScaleChannelsLayer {
+                base: from.base.clone(),
+                weights: ScaleChannelsWeights {},
+            })
+        }
+
+        pub fn forward<T>(&self, tensors: &[T]) -> Tensor
+        where
+            T: Borrow<Tensor>,
+        {
+            let Self {
+                base:
+                    ScaleChannelsLayerBase {
+                        config: ScaleChannelsConfig { activation, .. },
+                        ..
+                    },
+                ..
+            } = *self;
+
+            // the scales broadcast over the spatial or the channel dimension
+            let scales = tensors[0].borrow();
+            let features = tensors[1].borrow();
+            let xs = features * scales;
+
+            // checked in new()
+            let activate = activation_fn(activation).unwrap();
+            activate(&xs)
+        }
+    }
+
+    impl DropoutLayer {
+        pub fn new<'p>(
+            _path: impl Borrow<nn::Path<'p>>,
+            from: &darknet::DropoutLayer,
+        ) -> Result<Self> {
+            Ok(DropoutLayer {
+                base: from.base.clone(),
+                weights: DropoutWeights {},
+            })
+        }
+
+        pub fn forward_t(&self, xs: &Tensor, train: bool) -> Tensor {
+            let Self {
+                base:
+                    DropoutLayerBase {
+                        config: DropoutConfig { probability, .. },
+                        ..
+                    },
+                ..
+            } = *self;
+            xs.dropout(probability.raw(), train)
+        }
+    }
+
+    impl SoftmaxLayer {
+        pub fn new<'p>(
+            _path: impl Borrow<nn::Path<'p>>,
+            from: &darknet::SoftmaxLayer,
+        ) -> Result<Self> {
+            ensure!(
+                from.base.config.tree.is_none(),
+                "softmax over a class tree is not supported"
+            );
+            Ok(SoftmaxLayer {
+                base: from.base.clone(),
+                weights: SoftmaxWeights {},
+            })
+        }
+
+        pub fn forward(&self, xs: &Tensor) -> Tensor {
+            let Self {
+                base:
+                    SoftmaxLayerBase {
+                        config:
+                            SoftmaxConfig {
+                                groups,
+                                temperature,
+                                spatial,
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } = *self;
+            let xs = xs / temperature.raw();
+
+            if spatial {
+                return xs.softmax(1, xs.kind());
+            }
+
+            // darknet normalizes each group of the flattened input
+            let size = xs.size();
+            xs.view([size[0], groups as i64, -1])
+                .softmax(2, xs.kind())
+                .view(size.as_slice())
+        }
+    }
+
     impl YoloLayer {
         pub fn new<'p>(
             _path: impl Borrow<nn::Path<'p>>,
@@ -1043,7 +1336,12 @@ mod layer {
             let Self {
                 base:
                     YoloLayerBase {
-                        config: CompoundYoloConfig { ref anchors, .. },
+                        config:
+                            CompoundYoloConfig {
+                                ref anchors,
+                                new_coords,
+                                ..
+                            },
                         ..
                     },
                 weights: YoloWeights { num_classes, .. },
@@ -1071,8 +1369,16 @@ mod layer {
             } = self.cache(input);
             let x = (&raw_x + x_grids.expand_as(&raw_x)) / width as f64;
             let y = (&raw_y + y_grids.expand_as(&raw_y)) / height as f64;
-            let w = (raw_w.exp() + 0.5) / width as f64;
-            let h = (raw_h.exp() + 0.5) / height as f64;
+            // new_coords expects the previous layer to apply the logistic activation
+            let (w, h) = if new_coords {
+                let w = (&raw_w * 2.0).pow(2.0) / width as f64;
+                let h = (&raw_h * 2.0).pow(2.0) / height as f64;
+                (w, h)
+            } else {
+                let w = (raw_w.exp() + 0.5) / width as f64;
+                let h = (raw_h.exp() + 0.5) / height as f64;
+                (w, h)
+            };
 
             YoloLayerOutput {
                 y,
@@ -1187,6 +1493,23 @@ mod weights {
         pub group_ranges: Vec<(i64, i64)>,
     }
 
+    #[derive(Debug)]
+    pub struct ImplicitWeights {
+        pub implicit: Tensor,
+    }
+
+    #[derive(Debug)]
+    pub struct AvgPoolWeights {}
+
+    #[derive(Debug)]
+    pub struct ScaleChannelsWeights {}
+
+    #[derive(Debug)]
+    pub struct DropoutWeights {}
+
+    #[derive(Debug)]
+    pub struct SoftmaxWeights {}
+
     #[derive(Debug)]
     pub struct YoloWeights {
         pub num_classes: i64,
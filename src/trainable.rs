@@ -0,0 +1,312 @@
+use crate::{
+    common::*,
+    config::{CommonLayerOptions, ConvLstmConfig, LayerConfigEx, ShortcutConfig, WeightsType},
+    model::{LayerBase, ModelBase},
+};
+
+// the parameters of a layer split by how darknet updates them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct ParameterCounts {
+    // weights, and biases of layers without batch normalization
+    pub weights: u64,
+    // the scales and biases of batch normalization
+    pub batch_norm: u64,
+    // the rolling mean and variance, which are not learned by the optimizer
+    pub statistics: u64,
+}
+
+impl ParameterCounts {
+    pub fn new(layer: &LayerBase) -> Self {
+        match layer {
+            LayerBase::Convolutional(conv) => {
+                if conv.config.share_index.is_some() {
+                    return Self::default();
+                }
+                let [s1, s2, s3, s4] = conv.weights_shape();
+                let filters = conv.config.filters;
+                Self::with_biases(s1 * s2 * s3 * s4, filters, conv.config.batch_normalize)
+            }
+            LayerBase::Connected(connected) => {
+                let inputs = connected.input_shape;
+                let outputs = connected.output_shape;
+                Self::with_biases(inputs * outputs, outputs, connected.config.batch_normalize)
+            }
+            LayerBase::BatchNorm(batch_norm) => {
+                Self::with_biases(0, batch_norm.inout_shape[2], true)
+            }
+            LayerBase::Shortcut(shortcut) => {
+                let ShortcutConfig {
+                    weights_type,
+                    ref from,
+                    ..
+                } = shortcut.config;
+                let num_inputs = from.len() as u64 + 1;
+                let weights = match weights_type {
+                    WeightsType::None => 0,
+                    WeightsType::PerFeature => num_inputs,
+                    WeightsType::PerChannel => num_inputs * shortcut.output_shape[2],
+                };
+                Self {
+                    weights,
+                    ..Self::default()
+                }
+            }
+            LayerBase::Implicit(implicit) => Self {
+                weights: implicit.output_shape[2],
+                ..Self::default()
+            },
+            LayerBase::Local(local) => {
+                let [s1, s2, s3] = local.weights_shape();
+                Self {
+                    weights: s1 * s2 * s3 + local.num_biases(),
+                    ..Self::default()
+                }
+            }
+            LayerBase::Lstm(lstm) => lstm
+                .gate_shapes()
+                .iter()
+                .map(|&(inputs, outputs)| {
+                    Self::with_biases(inputs * outputs, outputs, lstm.config.batch_normalize)
+                })
+                .fold(Self::default(), |lhs, rhs| Self {
+                    weights: lhs.weights + rhs.weights,
+                    batch_norm: lhs.batch_norm + rhs.batch_norm,
+                    statistics: lhs.statistics + rhs.statistics,
+                }),
+            LayerBase::Crnn(crnn) => {
+                let kernel_len = crnn.config.size.pow(2);
+                crnn.conv_shapes()
+                    .iter()
+                    .map(|&(_, in_c, filters)| {
+                        Self::with_biases(
+                            in_c * filters * kernel_len,
+                            filters,
+                            crnn.config.batch_normalize,
+                        )
+                    })
+                    .fold(Self::default(), |lhs, rhs| Self {
+                        weights: lhs.weights + rhs.weights,
+                        batch_norm: lhs.batch_norm + rhs.batch_norm,
+                        statistics: lhs.statistics + rhs.statistics,
+                    })
+            }
+            LayerBase::ConvLstm(conv_lstm) => {
+                let ConvLstmConfig {
+                    size,
+                    groups,
+                    batch_normalize,
+                    ..
+                } = conv_lstm.config;
+                conv_lstm
+                    .gate_shapes()
+                    .iter()
+                    .map(|&(_, in_c, filters)| {
+                        Self::with_biases(
+                            in_c / groups * filters * size.pow(2),
+                            filters,
+                            batch_normalize,
+                        )
+                    })
+                    .fold(Self::default(), |lhs, rhs| Self {
+                        weights: lhs.weights + rhs.weights,
+                        batch_norm: lhs.batch_norm + rhs.batch_norm,
+                        statistics: lhs.statistics + rhs.statistics,
+                    })
+            }
+            LayerBase::Route(_)
+            | LayerBase::MaxPool(_)
+            | LayerBase::UpSample(_)
+            | LayerBase::Yolo(_)
+            | LayerBase::AvgPool(_)
+            | LayerBase::ScaleChannels(_)
+            | LayerBase::Dropout(_)
+            | LayerBase::Softmax(_)
+            | LayerBase::Region(_)
+            | LayerBase::Sam(_)
+            | LayerBase::LocalAvgPool(_)
+            | LayerBase::Reorg(_)
+            | LayerBase::GaussianYolo(_)
+            | LayerBase::Custom(_) => Self::default(),
+        }
+    }
+
+    // the biases act as the shift of batch normalization if it is enabled
+    fn with_biases(weights: u64, channels: u64, batch_normalize: bool) -> Self {
+        if batch_normalize {
+            Self {
+                weights,
+                batch_norm: channels * 2,
+                statistics: channels * 2,
+            }
+        } else {
+            Self {
+                weights: weights + channels,
+                batch_norm: 0,
+                statistics: 0,
+            }
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.weights + self.batch_norm + self.statistics
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "status")]
+pub enum TrainStatus {
+    #[serde(rename = "trainable")]
+    Trainable,
+    #[serde(rename = "no_parameters")]
+    NoParameters,
+    // the layer or a later one has stopbackward=1, so no gradient reaches it
+    #[serde(rename = "stop_backward")]
+    StopBackward { layer_index: usize },
+    #[serde(rename = "dont_update")]
+    DontUpdate,
+    #[serde(rename = "only_forward")]
+    OnlyForward,
+    // the layer or a later one has train_only_bn=1, so only the batch
+    // normalization is learned
+    #[serde(rename = "train_only_bn")]
+    TrainOnlyBn { layer_index: usize },
+}
+
+impl Display for TrainStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trainable => write!(f, "trainable"),
+            Self::NoParameters => write!(f, "-"),
+            Self::StopBackward { layer_index } => write!(f, "stopbackward@{}", layer_index),
+            Self::DontUpdate => write!(f, "dont_update"),
+            Self::OnlyForward => write!(f, "onlyforward"),
+            Self::TrainOnlyBn { layer_index } => write!(f, "train_only_bn@{}", layer_index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LayerTrainable {
+    pub layer_index: usize,
+    pub kind: String,
+    pub status: TrainStatus,
+    pub trainable: u64,
+    pub frozen: u64,
+    // rolling statistics of batch normalization
+    pub non_trainable: u64,
+}
+
+// the parameter counts split by the freezing flags of the layers
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TrainableReport {
+    pub layers: Vec<LayerTrainable>,
+    pub trainable: u64,
+    pub frozen: u64,
+    pub non_trainable: u64,
+}
+
+impl TrainableReport {
+    pub fn new(model: &ModelBase) -> Self {
+        let num_layers = model.layers.len();
+        let config = |layer_index: usize| model.layers[&layer_index].config();
+
+        // the last layer with the flag covers itself and all layers before it
+        let last_flagged = |flag: fn(&CommonLayerOptions) -> bool| {
+            (0..num_layers)
+                .rev()
+                .find(|&layer_index| flag(config(layer_index).common()))
+        };
+        let stop_backward = last_flagged(|common| common.stop_backward);
+        let train_only_bn = last_flagged(|common| common.train_only_bn);
+
+        let layers: Vec<_> = (0..num_layers)
+            .map(|layer_index| {
+                let layer = &model.layers[&layer_index];
+                let counts = ParameterCounts::new(layer);
+                let config = layer.config();
+                let common = config.common();
+                let covers = |flagged: Option<usize>| {
+                    flagged.filter(|&flagged_index| layer_index <= flagged_index)
+                };
+
+                let learned = counts.weights + counts.batch_norm;
+                let (status, trainable) = if learned == 0 {
+                    (TrainStatus::NoParameters, 0)
+                } else if let Some(flagged_index) = covers(stop_backward) {
+                    (
+                        TrainStatus::StopBackward {
+                            layer_index: flagged_index,
+                        },
+                        0,
+                    )
+                } else if common.only_forward {
+                    (TrainStatus::OnlyForward, 0)
+                } else if common.dont_update {
+                    (TrainStatus::DontUpdate, 0)
+                } else if let Some(flagged_index) = covers(train_only_bn) {
+                    (
+                        TrainStatus::TrainOnlyBn {
+                            layer_index: flagged_index,
+                        },
+                        counts.batch_norm,
+                    )
+                } else {
+                    (TrainStatus::Trainable, learned)
+                };
+
+                LayerTrainable {
+                    layer_index,
+                    kind: layer.kind().to_owned(),
+                    status,
+                    trainable,
+                    frozen: learned - trainable,
+                    non_trainable: counts.statistics,
+                }
+            })
+            .collect();
+
+        Self {
+            trainable: layers.iter().map(|layer| layer.trainable).sum(),
+            frozen: layers.iter().map(|layer| layer.frozen).sum(),
+            non_trainable: layers.iter().map(|layer| layer.non_trainable).sum(),
+            layers,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl Display for TrainableReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>5} {:<15} {:<18} {:>12} {:>12} {:>12}",
+            "layer", "kind", "status", "trainable", "frozen", "non-trainable"
+        )?;
+        for layer in &self.layers {
+            writeln!(
+                f,
+                "{:>5} {:<15} {:<18} {:>12} {:>12} {:>12}",
+                layer.layer_index,
+                layer.kind,
+                layer.status.to_string(),
+                layer.trainable,
+                layer.frozen,
+                layer.non_trainable
+            )?;
+        }
+        write!(
+            f,
+            "total: {} trainable, {} frozen, {} non-trainable",
+            self.trainable, self.frozen, self.non_trainable
+        )
+    }
+}
+
+impl ModelBase {
+    pub fn trainable_report(&self) -> TrainableReport {
+        TrainableReport::new(self)
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    common::*,
+    compress,
+    config::{DarknetConfig, LayerConfig},
+    darknet::DarknetModel,
+    model::ModelBase,
+    salvage::LayerLoadStatus,
+    weights_layout::WeightsLayout,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectorConfig {
+    pub config: DarknetConfig,
+    // the number of leading layers taken from the backbone, which is the N of
+    // the .conv.N weights file
+    pub backbone_layers: usize,
+}
+
+impl DarknetConfig {
+    // assemble a detector from the first `cutoff` layers of a classifier and a
+    // head template, as in darknet's transfer learning recipe. the template is a
+    // config whose [net] section is used for the detector and whose layers are
+    // appended to the backbone, so absolute layer indexes in the template count
+    // the backbone layers. the backbone layers must store the same weights in
+    // both configs so that the .conv.N file lines up.
+    pub fn classifier_to_detector(
+        &self,
+        cutoff: usize,
+        head: &DarknetConfig,
+    ) -> Result<DetectorConfig> {
+        ensure!(
+            cutoff > 0 && cutoff <= self.layers.len(),
+            "the cutoff {} is out of the range of the {} backbone layers",
+            cutoff,
+            self.layers.len()
+        );
+        if let Some(layer_index) = self.layers[..cutoff]
+            .iter()
+            .position(|layer| matches!(layer, LayerConfig::Yolo(_)))
+        {
+            bail!("the backbone has a yolo layer at {}", layer_index);
+        }
+        ensure!(
+            head.layers
+                .iter()
+                .any(|layer| matches!(layer, LayerConfig::Yolo(_))),
+            "the head template has no yolo layer"
+        );
+
+        let config = DarknetConfig {
+            net: head.net.clone(),
+            layers: self.layers[..cutoff]
+                .iter()
+                .chain(&head.layers)
+                .cloned()
+                .collect(),
+        };
+        ModelBase::from_config(&config)?;
+
+        // the input size of the detector may differ, but the stored weights of
+        // the backbone layers must not
+        let backbone_layout = WeightsLayout::describe(self)?;
+        let detector_layout = WeightsLayout::describe(&config)?;
+        for layer_index in 0..cutoff {
+            let lengths = |layout: &WeightsLayout| -> Vec<u64> {
+                layout
+                    .layer_records(layer_index)
+                    .map(|record| record.length)
+                    .collect()
+            };
+            let backbone = lengths(&backbone_layout);
+            let detector = lengths(&detector_layout);
+            ensure!(
+                backbone == detector,
+                "layer {} stores {:?} values in the backbone but {:?} in the detector",
+                layer_index,
+                backbone,
+                detector
+            );
+        }
+
+        Ok(DetectorConfig {
+            config,
+            backbone_layers: cutoff,
+        })
+    }
+}
+
+impl DarknetModel {
+    // write the first num_layers layers with seen reset to zero, like
+    // `darknet partial`
+    pub fn write_partial_weights<W>(&self, writer: W, num_layers: usize) -> Result<()>
+    where
+        W: Write,
+    {
+        ensure!(
+            num_layers <= self.layers.len(),
+            "the model has only {} layers",
+            self.layers.len()
+        );
+        self.write_weights_upto(writer, 0, num_layers)
+    }
+
+    pub fn save_partial_weights<P>(&self, weights_file: P, num_layers: usize) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        compress::write_file(weights_file.as_ref(), |writer| {
+            self.write_partial_weights(writer, num_layers)
+        })
+    }
+
+    // load a .conv.N file into the first num_layers layers. the file must end
+    // exactly after these layers, otherwise the backbone does not line up with
+    // the model.
+    pub fn load_backbone_weights<P>(&mut self, weights_file: P, num_layers: usize) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        ensure!(
+            num_layers <= self.layers.len(),
+            "the model has only {} layers",
+            self.layers.len()
+        );
+        let report = self.load_weights_salvage(weights_file)?;
+
+        for (&layer_index, &status) in &report.status {
+            if layer_index < num_layers {
+                ensure!(
+                    matches!(status, LayerLoadStatus::Loaded | LayerLoadStatus::NoWeights),
+                    "the backbone layer {} is {:?} in the weights file",
+                    layer_index,
+                    status
+                );
+            } else {
+                ensure!(
+                    matches!(
+                        status,
+                        LayerLoadStatus::Missing | LayerLoadStatus::NoWeights
+                    ),
+                    "the weights file has weights beyond the first {} layers",
+                    num_layers
+                );
+            }
+        }
+        ensure!(
+            num_layers < self.layers.len() || report.is_complete(),
+            "the weights file has trailing bytes"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{
+    common::*,
+    config::{CompoundNetConfig, Shape},
+    dataset::LabelBox,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TtaOptions {
+    // add a horizontally flipped copy of every scale
+    pub flip: bool,
+    // multipliers of the configured input size
+    pub scales: Vec<f64>,
+    // scaled input sizes are rounded to multiples of this step
+    pub size_step: u64,
+}
+
+impl Default for TtaOptions {
+    fn default() -> Self {
+        Self {
+            flip: true,
+            scales: vec![1.0],
+            size_step: 32,
+        }
+    }
+}
+
+// one augmented inference pass. the image is flipped first if enabled, then
+// resized or letterboxed to the input size like the network input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TtaTransform {
+    pub flip: bool,
+    pub scale: f64,
+    // [width, height]
+    pub input_size: [u64; 2],
+    pub letter_box: bool,
+}
+
+impl CompoundNetConfig {
+    pub fn tta_transforms(&self, options: &TtaOptions) -> Result<Vec<TtaTransform>> {
+        let TtaOptions {
+            flip,
+            ref scales,
+            size_step,
+        } = *options;
+        ensure!(size_step > 0, "the size step must be positive");
+        let [height, width] = match self.input_size {
+            Shape::Hwc([h, w, _c]) => [h, w],
+            Shape::Flat(_) => bail!("test-time augmentation requires an image input"),
+        };
+
+        let round_size = |size: u64, scale: f64| {
+            let steps = (size as f64 * scale / size_step as f64).round() as u64;
+            steps.max(1) * size_step
+        };
+        let flips: &[bool] = if flip { &[false, true] } else { &[false] };
+
+        let mut transforms = vec![];
+        for &scale in scales {
+            ensure!(
+                scale.is_finite() && scale > 0.0,
+                "the scale {} must be positive",
+                scale
+            );
+            let input_size = if scale == 1.0 {
+                [width, height]
+            } else {
+                [round_size(width, scale), round_size(height, scale)]
+            };
+            transforms.extend(flips.iter().map(|&flip| TtaTransform {
+                flip,
+                scale,
+                input_size,
+                letter_box: self.letter_box,
+            }));
+        }
+        Ok(transforms)
+    }
+}
+
+impl TtaTransform {
+    // map a box relative to the original image to the network input
+    pub fn to_network(&self, image_size: [u64; 2], bbox: &LabelBox) -> LabelBox {
+        let mut bbox = bbox.clone();
+        if self.flip {
+            bbox.x = 1.0 - bbox.x;
+        }
+        if self.letter_box {
+            let ([offset_x, offset_y], [ratio_w, ratio_h]) = self.letterbox_geometry(image_size);
+            bbox.x = bbox.x * ratio_w + offset_x;
+            bbox.y = bbox.y * ratio_h + offset_y;
+            bbox.w *= ratio_w;
+            bbox.h *= ratio_h;
+        }
+        bbox
+    }
+
+    // map a box detected on the network input back to the original image, the
+    // same as correct_yolo_boxes() in darknet followed by undoing the flip
+    pub fn to_image(&self, image_size: [u64; 2], bbox: &LabelBox) -> LabelBox {
+        let mut bbox = bbox.clone();
+        if self.letter_box {
+            let ([offset_x, offset_y], [ratio_w, ratio_h]) = self.letterbox_geometry(image_size);
+            bbox.x = (bbox.x - offset_x) / ratio_w;
+            bbox.y = (bbox.y - offset_y) / ratio_h;
+            bbox.w /= ratio_w;
+            bbox.h /= ratio_h;
+        }
+        if self.flip {
+            bbox.x = 1.0 - bbox.x;
+        }
+        bbox
+    }
+
+    // the relative offset and size of the image within the letterboxed input
+    fn letterbox_geometry(&self, [image_w, image_h]: [u64; 2]) -> ([f64; 2], [f64; 2]) {
+        let [net_w, net_h] = self.input_size;
+        let (new_w, new_h) = letterbox_size(
+            image_w as usize,
+            image_h as usize,
+            net_w as usize,
+            net_h as usize,
+        );
+        let [net_w, net_h] = [net_w as f64, net_h as f64];
+        let [new_w, new_h] = [new_w as f64, new_h as f64];
+        (
+            [(net_w - new_w) / 2.0 / net_w, (net_h - new_h) / 2.0 / net_h],
+            [new_w / net_w, new_h / net_h],
+        )
+    }
+}
+
+// the size of the resized image within a letterboxed input, see letterbox_image().
+// the shorter side keeps at least one pixel for extreme aspect ratios.
+pub fn letterbox_size(
+    image_w: usize,
+    image_h: usize,
+    width: usize,
+    height: usize,
+) -> (usize, usize) {
+    if (width as f32 / image_w as f32) < (height as f32 / image_h as f32) {
+        (width, (image_h * width / image_w).max(1))
+    } else {
+        ((image_w * height / image_h).max(1), height)
+    }
+}
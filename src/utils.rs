@@ -1,4 +1,6 @@
 use crate::common::*;
+#[cfg(feature = "manifest")]
+use sha2::{Digest, Sha256};
 
 pub fn transpose_matrix<T>(buf: &mut [T], nrows: usize, ncols: usize) -> Result<()>
 where
@@ -32,3 +34,30 @@ where
 
 unzip_n!(pub 2);
 unzip_n!(pub 3);
+
+#[cfg(feature = "manifest")]
+pub fn sha256_digest(data: impl AsRef<[u8]>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data.as_ref());
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(feature = "manifest")]
+pub fn sha256_file<P>(path: P) -> Result<String>
+where
+    P: AsRef<Path>,
+{
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 1 << 16];
+
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buf[..len]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -0,0 +1,574 @@
+use crate::{
+    common::*,
+    config::{
+        CompoundNetConfig, ConvolutionalConfig, DarknetConfig, LayerConfig, LayerIndex, Shape,
+    },
+    model::{LayerBase, ModelBase, YoloLayerBase},
+    progress::{ProgressObserver, Stage},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    #[serde(rename = "warning")]
+    Warning,
+    #[serde(rename = "error")]
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+// the rule that raised a diagnostic. the codes are stable across releases, so
+// removed rules leave their codes unused rather than renumbering the rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DiagnosticCode {
+    #[serde(rename = "DKC0001")]
+    ParseError,
+    #[serde(rename = "DKC0002")]
+    ModelError,
+    #[serde(rename = "DKC0003")]
+    SelfReference,
+    #[serde(rename = "DKC0004")]
+    ForwardReference,
+    #[serde(rename = "DKC0005")]
+    UnreachableLayer,
+    #[serde(rename = "DKC0006")]
+    TruncatedGrid,
+    #[serde(rename = "DKC0007")]
+    RandomResizeStride,
+    #[serde(rename = "DKC0008")]
+    InvertedAnchors,
+    #[serde(rename = "DKC0009")]
+    DeprecatedKey,
+    #[serde(rename = "DKC0010")]
+    NonPositiveLossScale,
+    #[serde(rename = "DKC0011")]
+    NonPositiveClip,
+    #[serde(rename = "DKC0012")]
+    IneffectiveClip,
+    #[serde(rename = "DKC0013")]
+    NonPositiveMaxDelta,
+    #[serde(rename = "DKC0014")]
+    SingleTimeStep,
+    #[serde(rename = "DKC0015")]
+    SharedAnchors,
+}
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ParseError => "DKC0001",
+            Self::ModelError => "DKC0002",
+            Self::SelfReference => "DKC0003",
+            Self::ForwardReference => "DKC0004",
+            Self::UnreachableLayer => "DKC0005",
+            Self::TruncatedGrid => "DKC0006",
+            Self::RandomResizeStride => "DKC0007",
+            Self::InvertedAnchors => "DKC0008",
+            Self::DeprecatedKey => "DKC0009",
+            Self::NonPositiveLossScale => "DKC0010",
+            Self::NonPositiveClip => "DKC0011",
+            Self::IneffectiveClip => "DKC0012",
+            Self::NonPositiveMaxDelta => "DKC0013",
+            Self::SingleTimeStep => "DKC0014",
+            Self::SharedAnchors => "DKC0015",
+        }
+    }
+}
+
+impl Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub code: DiagnosticCode,
+    pub severity: Severity,
+    pub layer_index: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(code: DiagnosticCode, layer_index: Option<usize>, message: impl Display) -> Self {
+        Self {
+            code,
+            severity: Severity::Error,
+            layer_index,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn warning(
+        code: DiagnosticCode,
+        layer_index: Option<usize>,
+        message: impl Display,
+    ) -> Self {
+        Self {
+            code,
+            severity: Severity::Warning,
+            layer_index,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.layer_index {
+            Some(index) => write!(
+                f,
+                "{}[{}]: layer {}: {}",
+                self.severity, self.code, index, self.message
+            ),
+            None => write!(f, "{}[{}]: {}", self.severity, self.code, self.message),
+        }
+    }
+}
+
+pub fn validate(config: &DarknetConfig) -> Vec<Diagnostic> {
+    validate_with_progress(config, &mut ())
+}
+
+pub fn validate_with_progress(
+    config: &DarknetConfig,
+    observer: &mut dyn ProgressObserver,
+) -> Vec<Diagnostic> {
+    observer.stage_started(Stage::Validate, Some(config.layers.len()));
+    let mut diagnostics = vec![];
+
+    // darknet builds and runs the layers in index order
+    diagnostics.extend(forward_reference_diagnostics(config));
+    diagnostics.extend(time_steps_diagnostics(config));
+
+    // the model graph and shapes must be buildable
+    match ModelBase::from_config(config) {
+        Ok(model) => {
+            diagnostics.extend(grid_diagnostics(&model));
+            diagnostics.extend(anchor_diagnostics(&model));
+            diagnostics.extend(shared_anchor_diagnostics(&model));
+
+            // leftovers of manual cfg edits, they still cost computation and weights
+            diagnostics.extend(model.unreachable_layers().into_iter().map(|layer_index| {
+                Diagnostic::warning(
+                    DiagnosticCode::UnreachableLayer,
+                    Some(layer_index),
+                    "the output is not used by any output layer, see prune_unreachable()",
+                )
+            }));
+        }
+        Err(err) => diagnostics.push(Diagnostic::error(
+            DiagnosticCode::ModelError,
+            None,
+            format!("{:#}", err),
+        )),
+    }
+
+    diagnostics.extend(config.stability_options().diagnostics(config));
+
+    // obsolete keys are accepted by the parser, so they only raise warnings
+    let deprecation_warning = |layer_index, deprecation: &Deprecation| {
+        Diagnostic::warning(
+            DiagnosticCode::DeprecatedKey,
+            layer_index,
+            format!(
+                "{} in [{}] is deprecated: {}",
+                deprecation.key, deprecation.section, deprecation.rationale
+            ),
+        )
+    };
+    diagnostics.extend(
+        net_deprecations(config)
+            .into_iter()
+            .map(|deprecation| deprecation_warning(None, deprecation)),
+    );
+    config
+        .layers
+        .iter()
+        .enumerate()
+        .for_each(|(layer_index, layer)| {
+            observer.layer_started(Stage::Validate, layer_index);
+            diagnostics.extend(
+                layer_deprecations(layer)
+                    .into_iter()
+                    .map(|deprecation| deprecation_warning(Some(layer_index), deprecation)),
+            );
+            observer.layer_finished(Stage::Validate, layer_index, 0);
+        });
+
+    observer.stage_finished(Stage::Validate, 0);
+    diagnostics
+}
+
+// recurrent layers split the batch into sequences of time_steps
+fn time_steps_diagnostics(config: &DarknetConfig) -> Vec<Diagnostic> {
+    if config.net.time_steps > 1 {
+        return vec![];
+    }
+    config
+        .layers
+        .iter()
+        .enumerate()
+        .filter(|(_, layer)| {
+            matches!(
+                layer,
+                LayerConfig::Lstm(_) | LayerConfig::Crnn(_) | LayerConfig::ConvLstm(_)
+            )
+        })
+        .map(|(layer_index, _)| {
+            Diagnostic::warning(
+                DiagnosticCode::SingleTimeStep,
+                Some(layer_index),
+                "time_steps in [net] is 1, so the recurrent layer sees sequences of one step",
+            )
+        })
+        .collect()
+}
+
+fn forward_reference_diagnostics(config: &DarknetConfig) -> Vec<Diagnostic> {
+    config
+        .layers
+        .iter()
+        .enumerate()
+        .flat_map(|(layer_index, layer)| {
+            let references: Vec<(&str, LayerIndex)> = match layer {
+                LayerConfig::Convolutional(conf) => conf
+                    .share_index
+                    .iter()
+                    .map(|&index| ("share_index", index))
+                    .collect(),
+                LayerConfig::Route(conf) => conf
+                    .layers
+                    .iter()
+                    .map(|&index| ("layers", index))
+                    .collect(),
+                LayerConfig::Shortcut(conf) => {
+                    conf.from.iter().map(|&index| ("from", index)).collect()
+                }
+                LayerConfig::ScaleChannels(conf) => vec![("from", conf.from)],
+                LayerConfig::Sam(conf) => vec![("from", conf.from)],
+                LayerConfig::Custom(conf) => {
+                    conf.from.iter().map(|&index| ("from", index)).collect()
+                }
+                LayerConfig::Yolo(conf) => conf
+                    .embedding_layer
+                    .iter()
+                    .map(|&index| ("embedding_layer", index))
+                    .collect(),
+                LayerConfig::GaussianYolo(conf) => conf
+                    .yolo
+                    .embedding_layer
+                    .iter()
+                    .map(|&index| ("embedding_layer", index))
+                    .collect(),
+                LayerConfig::Connected(_)
+                | LayerConfig::MaxPool(_)
+                | LayerConfig::UpSample(_)
+                | LayerConfig::BatchNorm(_)
+                | LayerConfig::Implicit(_)
+                | LayerConfig::AvgPool(_)
+                | LayerConfig::Dropout(_)
+                | LayerConfig::Softmax(_)
+                | LayerConfig::Region(_)
+                | LayerConfig::ConvLstm(_)
+                | LayerConfig::Crnn(_)
+                | LayerConfig::Lstm(_)
+                | LayerConfig::LocalAvgPool(_)
+                | LayerConfig::Local(_)
+                | LayerConfig::Reorg(_) => vec![],
+            };
+
+            // relative indexes always point backwards, out of range indexes are
+            // reported by the model builder
+            references
+                .into_iter()
+                .filter_map(move |(key, index)| {
+                    let target = index.absolute()?;
+                    let (code, message) = if target == layer_index {
+                        (
+                            DiagnosticCode::SelfReference,
+                            format!("{}={} refers to the layer itself", key, target),
+                        )
+                    } else if target > layer_index {
+                        (
+                            DiagnosticCode::ForwardReference,
+                            format!(
+                                "{}={} refers to a later layer, darknet only allows references to earlier layers",
+                                key, target
+                            ),
+                        )
+                    } else {
+                        return None;
+                    };
+                    Some(Diagnostic::error(code, Some(layer_index), message))
+                })
+        })
+        .collect()
+}
+
+// the input size must be a multiple of the cumulative stride of every yolo head,
+// at the configured size and at every size random resizing can pick
+fn grid_diagnostics(model: &ModelBase) -> Vec<Diagnostic> {
+    let [in_h, in_w] = match model.net.input_size {
+        Shape::Hwc([h, w, _c]) => [h, w],
+        Shape::Flat(_) => return vec![],
+    };
+    let heads: Vec<_> = model
+        .layers
+        .iter()
+        .filter_map(|(&layer_index, layer)| match layer {
+            LayerBase::Yolo(YoloLayerBase {
+                inout_shape: [out_h, out_w, _c],
+                ..
+            }) if *out_h > 0 && *out_w > 0 => {
+                let stride_h = (in_h as f64 / *out_h as f64).round() as u64;
+                let stride_w = (in_w as f64 / *out_w as f64).round() as u64;
+                Some((layer_index, [stride_h.max(1), stride_w.max(1)]))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut diagnostics = vec![];
+
+    heads.iter().for_each(|&(layer_index, [stride_h, stride_w])| {
+        if in_h % stride_h != 0 || in_w % stride_w != 0 {
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticCode::TruncatedGrid,
+                Some(layer_index),
+                format!(
+                    "the input size {}x{} is not a multiple of the cumulative stride {}x{}, the grid is truncated",
+                    in_w, in_h, stride_w, stride_h
+                ),
+            ));
+        }
+    });
+
+    // darknet decides random resizing by the last layer, see train_detector()
+    let random = match model.layers.values().last() {
+        Some(LayerBase::Yolo(yolo)) if yolo.config.random > 0.0 => yolo.config.random.raw(),
+        _ => return diagnostics,
+    };
+    let coef = if random == 1.0 { 1.4 } else { random };
+    let step = model.net.resize_step.max(1);
+    let resize_range = |init: u64| -> Vec<u64> {
+        let round_dim =
+            |scale: f64| ((scale * init as f64 / step as f64 + 1.0).round() as u64) * step;
+        let min = round_dim(1.0 / coef).max(step);
+        let max = round_dim(coef);
+        (min..=max).step_by(step as usize).collect()
+    };
+    let heights = resize_range(in_h);
+    let widths = resize_range(in_w);
+
+    heads.iter().for_each(|&(layer_index, [stride_h, stride_w])| {
+        let bad_sizes: Vec<_> = widths
+            .iter()
+            .filter(|&&w| w % stride_w != 0)
+            .map(|w| format!("width {}", w))
+            .chain(
+                heights
+                    .iter()
+                    .filter(|&&h| h % stride_h != 0)
+                    .map(|h| format!("height {}", h)),
+            )
+            .collect();
+
+        if !bad_sizes.is_empty() {
+            diagnostics.push(Diagnostic::error(
+                DiagnosticCode::RandomResizeStride,
+                Some(layer_index),
+                format!(
+                    "random resizing with resize_step={} picks sizes that are not multiples of the cumulative stride {}x{}: {}",
+                    step,
+                    stride_w,
+                    stride_h,
+                    bad_sizes.join(", ")
+                ),
+            ));
+        }
+    });
+
+    diagnostics
+}
+
+// heads with finer grids detect smaller objects, so their anchors are expected
+// to be smaller. the opposite usually means the masks were swapped after the
+// anchors were edited by hand.
+fn anchor_diagnostics(model: &ModelBase) -> Vec<Diagnostic> {
+    let in_h = match model.net.input_size {
+        Shape::Hwc([h, _w, _c]) => h,
+        Shape::Flat(_) => return vec![],
+    };
+    let heads: Vec<_> = model
+        .layers
+        .iter()
+        .filter_map(|(&layer_index, layer)| match layer {
+            LayerBase::Yolo(yolo) if yolo.inout_shape[0] > 0 && !yolo.config.anchors.is_empty() => {
+                let stride = in_h as f64 / yolo.inout_shape[0] as f64;
+                let anchors = &yolo.config.anchors;
+                let mean_area = anchors.iter().map(|&(w, h)| (w * h) as f64).sum::<f64>()
+                    / anchors.len() as f64;
+                Some((layer_index, stride, mean_area))
+            }
+            _ => None,
+        })
+        .sorted_by(|(_, lhs, _), (_, rhs, _)| lhs.partial_cmp(rhs).unwrap())
+        .collect();
+
+    heads
+        .iter()
+        .tuple_windows()
+        .filter(|((_, fine_stride, fine_area), (_, coarse_stride, coarse_area))| {
+            fine_stride < coarse_stride && fine_area > coarse_area
+        })
+        .map(|(&(fine_index, fine_stride, _), &(coarse_index, coarse_stride, _))| {
+            Diagnostic::warning(
+                DiagnosticCode::InvertedAnchors,
+                Some(fine_index),
+                format!(
+                    "the anchors at stride {} are larger than the anchors of layer {} at stride {}, the masks may be inverted",
+                    fine_stride, coarse_index, coarse_stride
+                ),
+            )
+        })
+        .collect()
+}
+
+// a yolo layer without mask takes all anchors, which is meant for single head
+// models. heads predicting the same anchors usually lack their masks.
+fn shared_anchor_diagnostics(model: &ModelBase) -> Vec<Diagnostic> {
+    let heads: Vec<_> = model
+        .layers
+        .iter()
+        .filter_map(|(&layer_index, layer)| match layer {
+            LayerBase::Yolo(yolo) if !yolo.config.anchors.is_empty() => {
+                Some((layer_index, &yolo.config.anchors))
+            }
+            _ => None,
+        })
+        .sorted_by_key(|(layer_index, _)| *layer_index)
+        .collect();
+
+    heads
+        .iter()
+        .enumerate()
+        .filter_map(|(nth, &(layer_index, anchors))| {
+            let (first_index, _) = heads[..nth]
+                .iter()
+                .find(|(_, other_anchors)| *other_anchors == anchors)?;
+            Some(Diagnostic::warning(
+                DiagnosticCode::SharedAnchors,
+                Some(layer_index),
+                format!(
+                    "the layer predicts the same anchors as layer {}, the mask may be missing",
+                    first_index
+                ),
+            ))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DeprecationCheck {
+    Net(fn(&CompoundNetConfig) -> bool),
+    Convolutional(fn(&ConvolutionalConfig) -> bool),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecation {
+    pub section: &'static str,
+    pub key: &'static str,
+    pub rationale: &'static str,
+    check: DeprecationCheck,
+}
+
+// keys that darknet still parses but no longer acts on, the check tells if the
+// key is set to a non-default value
+pub const DEPRECATIONS: &[Deprecation] = &[
+    Deprecation {
+        section: "convolutional",
+        key: "dot",
+        rationale: "the dot product penalty is no longer applied during training",
+        check: DeprecationCheck::Convolutional(|conv| conv.dot),
+    },
+    Deprecation {
+        section: "net",
+        key: "max_crop",
+        rationale: "random crops only apply to classifier training, detectors use jitter instead",
+        check: DeprecationCheck::Net(|net| match net.input_size {
+            Shape::Hwc([_h, w, _c]) => net.max_crop != w * 2,
+            Shape::Flat(_) => net.max_crop != 0,
+        }),
+    },
+    Deprecation {
+        section: "net",
+        key: "min_crop",
+        rationale: "random crops only apply to classifier training, detectors use jitter instead",
+        check: DeprecationCheck::Net(|net| match net.input_size {
+            Shape::Hwc([_h, w, _c]) => net.min_crop != w,
+            Shape::Flat(_) => net.min_crop != 0,
+        }),
+    },
+    Deprecation {
+        section: "net",
+        key: "aspect",
+        rationale:
+            "aspect jittering only applies to classifier crops, detectors use jitter instead",
+        check: DeprecationCheck::Net(|net| net.aspect != 1.0),
+    },
+];
+
+pub fn find_deprecated_keys(config: &DarknetConfig) -> Vec<(Option<usize>, &'static Deprecation)> {
+    net_deprecations(config)
+        .into_iter()
+        .map(|deprecation| (None, deprecation))
+        .chain(
+            config
+                .layers
+                .iter()
+                .enumerate()
+                .flat_map(|(layer_index, layer)| {
+                    layer_deprecations(layer)
+                        .into_iter()
+                        .map(move |deprecation| (Some(layer_index), deprecation))
+                }),
+        )
+        .collect()
+}
+
+fn net_deprecations(config: &DarknetConfig) -> Vec<&'static Deprecation> {
+    DEPRECATIONS
+        .iter()
+        .filter(|deprecation| match deprecation.check {
+            DeprecationCheck::Net(check) => check(&config.net),
+            _ => false,
+        })
+        .collect()
+}
+
+fn layer_deprecations(layer: &LayerConfig) -> Vec<&'static Deprecation> {
+    DEPRECATIONS
+        .iter()
+        .filter(|deprecation| match (deprecation.check, layer) {
+            (DeprecationCheck::Convolutional(check), LayerConfig::Convolutional(conv)) => {
+                check(conv)
+            }
+            _ => false,
+        })
+        .collect()
+}
+
+impl DarknetConfig {
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        validate(self)
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    common::*,
+    darknet::{ConvolutionalLayer, DarknetModel, Layer},
+    export::{conv_weights, layer_name},
+};
+use image::{GrayImage, Luma, Rgb, RgbImage};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FilterImageOptions {
+    // each kernel element is drawn as a square of this size
+    pub scale: u32,
+    // normalize each filter on its own instead of the whole layer
+    pub per_filter: bool,
+    // kernels of other than 1 or 3 input channels are drawn as a grid of kernel
+    // norms, with one row per filter and one column per input channel
+    pub kernel_norms: bool,
+}
+
+impl Default for FilterImageOptions {
+    fn default() -> Self {
+        Self {
+            scale: 8,
+            per_filter: false,
+            kernel_norms: true,
+        }
+    }
+}
+
+impl DarknetModel {
+    pub fn export_filter_images<P>(&self, layer_index: usize, dir: P) -> Result<Vec<PathBuf>>
+    where
+        P: AsRef<Path>,
+    {
+        self.export_filter_images_with(layer_index, dir, &FilterImageOptions::default())
+    }
+
+    // render the kernels of a convolutional layer as PNG images, returns the saved files
+    pub fn export_filter_images_with<P>(
+        &self,
+        layer_index: usize,
+        dir: P,
+        options: &FilterImageOptions,
+    ) -> Result<Vec<PathBuf>>
+    where
+        P: AsRef<Path>,
+    {
+        let FilterImageOptions {
+            scale,
+            per_filter,
+            kernel_norms,
+        } = *options;
+        ensure!(scale > 0, "the scale must be positive");
+
+        let layer = self
+            .layers
+            .get(&layer_index)
+            .ok_or_else(|| format_err!("layer {} does not exist", layer_index))?;
+        let (base, weights) = match layer {
+            Layer::Convolutional(ConvolutionalLayer { base, weights }) => (base, weights),
+            _ => bail!("layer {} is not a convolutional layer", layer_index),
+        };
+        let (_biases, weights, _scales) = conv_weights(self, weights).ok_or_else(|| {
+            format_err!("the shared weights of layer {} are missing", layer_index)
+        })?;
+
+        // weights are stored in [filters, channels, size, size] order in darknet
+        let [in_c, filters, size, _size] = base.weights_shape();
+        let [in_c, filters, size] = [in_c as usize, filters as usize, size as usize];
+        let values = weights.as_slice().unwrap();
+        let kernel_len = in_c * size * size;
+
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let name = layer_name(layer_index, &self.base.layers[&layer_index]);
+
+        match in_c {
+            1 | 3 => {
+                let layer_range = value_range(values);
+                let pixels = size as u32 * scale;
+
+                (0..filters)
+                    .map(|filter_index| -> Result<_> {
+                        let kernel = &values[filter_index * kernel_len..][..kernel_len];
+                        let (min, max) = if per_filter {
+                            value_range(kernel)
+                        } else {
+                            layer_range
+                        };
+                        let pixel = |channel: usize, x: u32, y: u32| {
+                            let [x, y] = [(x / scale) as usize, (y / scale) as usize];
+                            to_u8(kernel[(channel * size + y) * size + x], min, max)
+                        };
+
+                        let path = dir.join(format!("{}_filter_{:03}.png", name, filter_index));
+                        if in_c == 3 {
+                            RgbImage::from_fn(pixels, pixels, |x, y| {
+                                Rgb([pixel(0, x, y), pixel(1, x, y), pixel(2, x, y)])
+                            })
+                            .save(&path)?;
+                        } else {
+                            GrayImage::from_fn(pixels, pixels, |x, y| Luma([pixel(0, x, y)]))
+                                .save(&path)?;
+                        }
+                        Ok(path)
+                    })
+                    .try_collect()
+            }
+            _ => {
+                ensure!(
+                    kernel_norms,
+                    "layer {} has {} input channels, only 1 or 3 channel kernels can be drawn directly",
+                    layer_index,
+                    in_c
+                );
+
+                let norms: Vec<f32> = values
+                    .chunks(size * size)
+                    .map(|kernel| kernel.iter().map(|value| value.powi(2)).sum::<f32>().sqrt())
+                    .collect();
+                let (min, max) = value_range(&norms);
+
+                let path = dir.join(format!("{}_kernel_norms.png", name));
+                GrayImage::from_fn(in_c as u32 * scale, filters as u32 * scale, |x, y| {
+                    let [channel, filter] = [(x / scale) as usize, (y / scale) as usize];
+                    Luma([to_u8(norms[filter * in_c + channel], min, max)])
+                })
+                .save(&path)?;
+                Ok(vec![path])
+            }
+        }
+    }
+}
+
+fn value_range(values: &[f32]) -> (f32, f32) {
+    values
+        .iter()
+        .filter(|value| value.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        })
+}
+
+// map [min, max] to [0, 255], constant kernels are drawn in gray
+fn to_u8(value: f32, min: f32, max: f32) -> u8 {
+    if max <= min || !value.is_finite() {
+        return 128;
+    }
+    ((value - min) / (max - min) * 255.0)
+        .round()
+        .clamp(0.0, 255.0) as u8
+}
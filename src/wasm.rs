@@ -0,0 +1,37 @@
+use crate::{
+    common::*,
+    config::DarknetConfig,
+    model::ModelBase,
+    validate::{self, Diagnostic, DiagnosticCode},
+};
+use wasm_bindgen::prelude::*;
+
+fn to_js_error(err: impl Display) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
+
+#[wasm_bindgen]
+pub fn parse(text: &str) -> Result<String, JsValue> {
+    let config = DarknetConfig::from_str(text).map_err(|err| to_js_error(format!("{:#}", err)))?;
+    serde_json::to_string(&config).map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub fn validate(text: &str) -> Result<String, JsValue> {
+    let diagnostics = match DarknetConfig::from_str(text) {
+        Ok(config) => validate::validate(&config),
+        Err(err) => vec![Diagnostic::error(
+            DiagnosticCode::ParseError,
+            None,
+            format!("{:#}", err),
+        )],
+    };
+    serde_json::to_string(&diagnostics).map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub fn summary(text: &str) -> Result<String, JsValue> {
+    let config = DarknetConfig::from_str(text).map_err(|err| to_js_error(format!("{:#}", err)))?;
+    let model = ModelBase::from_config(&config).map_err(|err| to_js_error(format!("{:#}", err)))?;
+    model.summary().to_json().map_err(to_js_error)
+}
@@ -0,0 +1,330 @@
+use crate::common::*;
+
+#[cfg(feature = "manifest")]
+pub use index::*;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightsStats {
+    pub num_values: u64,
+    pub num_non_finite: u64,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f64,
+    pub std: f64,
+}
+
+impl WeightsStats {
+    pub fn new<'a>(values: impl IntoIterator<Item = &'a f32>) -> Option<Self> {
+        let mut num_values = 0;
+        let mut num_non_finite = 0;
+        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
+        let mut sum = 0.0;
+        let mut sum_squares = 0.0;
+
+        values.into_iter().for_each(|&value| {
+            num_values += 1;
+            if !value.is_finite() {
+                num_non_finite += 1;
+                return;
+            }
+            min = min.min(value);
+            max = max.max(value);
+            sum += value as f64;
+            sum_squares += (value as f64).powi(2);
+        });
+
+        let num_finite = num_values - num_non_finite;
+        if num_finite == 0 {
+            return None;
+        }
+        let mean = sum / num_finite as f64;
+        let std = (sum_squares / num_finite as f64 - mean.powi(2))
+            .max(0.0)
+            .sqrt();
+
+        Some(Self {
+            num_values,
+            num_non_finite,
+            min,
+            max,
+            mean,
+            std,
+        })
+    }
+}
+
+#[cfg(feature = "manifest")]
+mod index {
+    use super::*;
+    use crate::{
+        config::DarknetConfig,
+        darknet::{
+            BatchNormLayer, BatchNormWeights, ConnectedLayer, ConnectedWeights, ConvLstmLayer,
+            ConvLstmWeights, ConvolutionalLayer, ConvolutionalWeights, CrnnLayer, CrnnWeights,
+            DarknetModel, ImplicitLayer, ImplicitWeights, Layer, LocalLayer, LocalWeights,
+            LstmLayer, LstmWeights, ScaleWeights, ShortcutLayer, ShortcutWeights,
+        },
+        utils::sha256_file,
+    };
+
+    // bump the version whenever the index format changes, stale entries are rebuilt
+    const INDEX_VERSION: u64 = 1;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct WeightsIndex {
+        pub version: u64,
+        pub weights_checksum: String,
+        pub config_fingerprint: String,
+        pub seen: u64,
+        pub layers: Vec<LayerWeightsEntry>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct LayerWeightsEntry {
+        pub layer_index: usize,
+        pub kind: String,
+        pub offset: u64,
+        pub num_bytes: u64,
+        pub stats: Option<WeightsStats>,
+    }
+
+    impl WeightsIndex {
+        // scans the whole weights file, use WeightsCache to avoid repeated scans
+        pub fn build<P>(config: &DarknetConfig, weights_file: P) -> Result<Self>
+        where
+            P: AsRef<Path>,
+        {
+            let weights_file = weights_file.as_ref();
+            let weights_checksum = sha256_file(weights_file)?;
+            Self::build_with_checksum(config, weights_file, weights_checksum)
+        }
+
+        fn build_with_checksum(
+            config: &DarknetConfig,
+            weights_file: &Path,
+            weights_checksum: String,
+        ) -> Result<Self> {
+            let mut model = DarknetModel::from_config(config)?;
+            let offsets = model.load_weights_with_offsets(weights_file)?;
+
+            let layers: Vec<_> = offsets
+                .into_iter()
+                .map(|(layer_index, range)| {
+                    let layer = &model.layers[&layer_index];
+                    let num_bytes = range.end - range.start;
+                    // layers with dont_load keep their initial values, which say nothing about the file
+                    let stats = if num_bytes > 0 {
+                        layer_stats(layer)
+                    } else {
+                        None
+                    };
+
+                    LayerWeightsEntry {
+                        layer_index,
+                        kind: model.base.layers[&layer_index].kind().to_owned(),
+                        offset: range.start,
+                        num_bytes,
+                        stats,
+                    }
+                })
+                .collect();
+
+            Ok(Self {
+                version: INDEX_VERSION,
+                weights_checksum,
+                config_fingerprint: config.fingerprint()?,
+                seen: model.base.seen,
+                layers,
+            })
+        }
+    }
+
+    fn scale_values(scales: &ScaleWeights) -> impl Iterator<Item = &f32> {
+        let ScaleWeights {
+            scales,
+            rolling_mean,
+            rolling_variance,
+        } = scales;
+        scales.iter().chain(rolling_mean).chain(rolling_variance)
+    }
+
+    fn layer_stats(layer: &Layer) -> Option<WeightsStats> {
+        match layer {
+            Layer::Convolutional(ConvolutionalLayer { weights, .. }) => match weights {
+                ConvolutionalWeights::Owned {
+                    biases,
+                    weights,
+                    scales,
+                } => WeightsStats::new(
+                    biases
+                        .iter()
+                        .chain(weights.iter())
+                        .chain(scales.iter().flat_map(scale_values)),
+                ),
+                ConvolutionalWeights::Ref { .. } => None,
+            },
+            Layer::Connected(ConnectedLayer {
+                weights:
+                    ConnectedWeights {
+                        biases,
+                        weights,
+                        scales,
+                    },
+                ..
+            }) => WeightsStats::new(
+                biases
+                    .iter()
+                    .chain(weights.iter())
+                    .chain(scales.iter().flat_map(scale_values)),
+            ),
+            Layer::BatchNorm(BatchNormLayer {
+                weights:
+                    BatchNormWeights {
+                        biases,
+                        scales,
+                        rolling_mean,
+                        rolling_variance,
+                    },
+                ..
+            }) => WeightsStats::new(
+                biases
+                    .iter()
+                    .chain(scales)
+                    .chain(rolling_mean)
+                    .chain(rolling_variance),
+            ),
+            Layer::Shortcut(ShortcutLayer { weights, .. }) => match weights {
+                ShortcutWeights::None => None,
+                ShortcutWeights::PerFeature(weights) => WeightsStats::new(weights),
+                ShortcutWeights::PerChannel(weights) => WeightsStats::new(weights),
+            },
+            Layer::Implicit(ImplicitLayer {
+                weights: ImplicitWeights { weights },
+                ..
+            }) => WeightsStats::new(weights),
+            Layer::Local(LocalLayer {
+                weights: LocalWeights { biases, weights },
+                ..
+            }) => WeightsStats::new(biases.iter().chain(weights)),
+            Layer::Lstm(LstmLayer {
+                weights: LstmWeights { gates },
+                ..
+            }) => WeightsStats::new(gates.iter().flat_map(|gate| {
+                gate.biases
+                    .iter()
+                    .chain(gate.weights.iter())
+                    .chain(gate.scales.iter().flat_map(scale_values))
+            })),
+            Layer::Crnn(CrnnLayer {
+                weights: CrnnWeights { convs },
+                ..
+            })
+            | Layer::ConvLstm(ConvLstmLayer {
+                weights: ConvLstmWeights { gates: convs },
+                ..
+            }) => WeightsStats::new(convs.iter().flat_map(|conv| {
+                conv.biases
+                    .iter()
+                    .chain(conv.scales.iter().flat_map(scale_values))
+                    .chain(conv.weights.iter())
+            })),
+            Layer::Route(_)
+            | Layer::MaxPool(_)
+            | Layer::UpSample(_)
+            | Layer::Yolo(_)
+            | Layer::AvgPool(_)
+            | Layer::ScaleChannels(_)
+            | Layer::Dropout(_)
+            | Layer::Softmax(_)
+            | Layer::Region(_)
+            | Layer::Sam(_)
+            | Layer::LocalAvgPool(_)
+            | Layer::Reorg(_)
+            | Layer::GaussianYolo(_)
+            | Layer::Custom(_) => None,
+        }
+    }
+
+    // on-disk cache of weights indexes, one JSON file per weights checksum
+    #[derive(Debug, Clone)]
+    pub struct WeightsCache {
+        dir: PathBuf,
+    }
+
+    impl WeightsCache {
+        pub fn new<P>(dir: P) -> Self
+        where
+            P: AsRef<Path>,
+        {
+            Self {
+                dir: dir.as_ref().to_owned(),
+            }
+        }
+
+        pub fn dir(&self) -> &Path {
+            &self.dir
+        }
+
+        fn entry_path(&self, weights_checksum: &str) -> PathBuf {
+            self.dir.join(format!("{}.json", weights_checksum))
+        }
+
+        // returns the cached index if it was built from the same weights and config
+        pub fn get(
+            &self,
+            config: &DarknetConfig,
+            weights_checksum: &str,
+        ) -> Result<Option<WeightsIndex>> {
+            let path = self.entry_path(weights_checksum);
+            if !path.is_file() {
+                return Ok(None);
+            }
+
+            // corrupted or outdated entries are treated as misses
+            let index: WeightsIndex = match serde_json::from_str(&fs::read_to_string(&path)?) {
+                Ok(index) => index,
+                Err(err) => {
+                    warn!("ignore invalid cache entry {}: {}", path.display(), err);
+                    return Ok(None);
+                }
+            };
+            let is_valid = index.version == INDEX_VERSION
+                && index.weights_checksum == weights_checksum
+                && index.config_fingerprint == config.fingerprint()?;
+
+            Ok(if is_valid { Some(index) } else { None })
+        }
+
+        pub fn insert(&self, index: &WeightsIndex) -> Result<()> {
+            fs::create_dir_all(&self.dir)?;
+
+            // write to a temporary file first so that readers never see partial entries
+            let path = self.entry_path(&index.weights_checksum);
+            let tmp_path = path.with_extension("json.tmp");
+            fs::write(&tmp_path, serde_json::to_string(index)?)?;
+            fs::rename(&tmp_path, &path)?;
+            Ok(())
+        }
+
+        pub fn load_or_build<P>(
+            &self,
+            config: &DarknetConfig,
+            weights_file: P,
+        ) -> Result<WeightsIndex>
+        where
+            P: AsRef<Path>,
+        {
+            let weights_file = weights_file.as_ref();
+            let weights_checksum = sha256_file(weights_file)?;
+
+            if let Some(index) = self.get(config, &weights_checksum)? {
+                return Ok(index);
+            }
+
+            let index = WeightsIndex::build_with_checksum(config, weights_file, weights_checksum)?;
+            self.insert(&index)?;
+            Ok(index)
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{
+    common::*,
+    config::{CommonLayerOptions, DarknetConfig, LayerConfigEx},
+    darknet::{DarknetModel, Layer},
+    model::{CONV_LSTM_GATES, LSTM_GATES},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WeightsDataType {
+    #[serde(rename = "uint32")]
+    UInt32,
+    #[serde(rename = "uint64")]
+    UInt64,
+    #[serde(rename = "float32")]
+    Float32,
+}
+
+impl WeightsDataType {
+    pub fn size(&self) -> u64 {
+        match self {
+            Self::UInt32 | Self::Float32 => 4,
+            Self::UInt64 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WeightsRecord {
+    // None for the header fields
+    pub layer_index: Option<usize>,
+    pub name: String,
+    // byte offset from the start of the file
+    pub offset: u64,
+    // number of values
+    pub length: u64,
+    pub dtype: WeightsDataType,
+}
+
+impl WeightsRecord {
+    pub fn num_bytes(&self) -> u64 {
+        self.length * self.dtype.size()
+    }
+}
+
+// the byte layout of a .weights file as written by darknet 0.2.0 and read by
+// DarknetModel::load_weights(). all values are little endian, see
+// WeightsByteOrder for files dumped on big-endian hosts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WeightsLayout {
+    pub records: Vec<WeightsRecord>,
+}
+
+impl WeightsLayout {
+    pub fn describe(config: &DarknetConfig) -> Result<Self> {
+        let model = DarknetModel::from_config(config)?;
+        let mut records = vec![];
+        let mut offset = 0;
+        let mut push = |layer_index, name: &str, length, dtype| {
+            let record = WeightsRecord {
+                layer_index,
+                name: name.to_owned(),
+                offset,
+                length,
+                dtype,
+            };
+            offset += record.num_bytes();
+            records.push(record);
+        };
+
+        // files older than 0.2.0 store seen as uint32, which is not described here
+        push(None, "major", 1, WeightsDataType::UInt32);
+        push(None, "minor", 1, WeightsDataType::UInt32);
+        push(None, "revision", 1, WeightsDataType::UInt32);
+        push(None, "seen", 1, WeightsDataType::UInt64);
+
+        for (&layer_index, layer) in &model.layers {
+            let common = config.layers[layer_index].common();
+            for (name, values) in stored_buffers(layer, common) {
+                push(
+                    Some(layer_index),
+                    name,
+                    values.len() as u64,
+                    WeightsDataType::Float32,
+                );
+            }
+        }
+
+        Ok(Self { records })
+    }
+
+    // the expected size of the file in bytes
+    pub fn file_size(&self) -> u64 {
+        self.records
+            .last()
+            .map(|record| record.offset + record.num_bytes())
+            .unwrap_or(0)
+    }
+
+    pub fn layer_records(&self, layer_index: usize) -> impl Iterator<Item = &WeightsRecord> {
+        self.records
+            .iter()
+            .filter(move |record| record.layer_index == Some(layer_index))
+    }
+}
+
+// the named buffers of the layer that are stored in the file, in file order
+pub(crate) fn stored_buffers<'a>(
+    layer: &'a Layer,
+    common: &CommonLayerOptions,
+) -> Vec<(&'static str, &'a [f32])> {
+    if common.dont_load {
+        return vec![];
+    }
+
+    named_buffers(layer)
+        .into_iter()
+        .filter(|&(name, _)| {
+            // only the scales of batch normalization inside conv and connected layers can be skipped
+            let is_scale = matches!(name, "scales" | "rolling_mean" | "rolling_variance");
+            !(common.dont_load_scales && is_scale && !matches!(layer, Layer::BatchNorm(_)))
+        })
+        .collect()
+}
+
+// all buffers of the layer with their names, in the order of Layer::buffers()
+pub(crate) fn named_buffers(layer: &Layer) -> Vec<(&'static str, &[f32])> {
+    let buffers = layer.buffers();
+    let names = buffer_names(layer);
+    debug_assert_eq!(buffers.len(), names.len());
+
+    names
+        .into_iter()
+        .zip(buffers)
+        .map(|(name, (_, values))| (name, values))
+        .collect()
+}
+
+macro_rules! lstm_gate_buffers {
+    ($gate:literal) => {
+        [
+            concat!($gate, ".biases"),
+            concat!($gate, ".weights"),
+            concat!($gate, ".scales"),
+            concat!($gate, ".rolling_mean"),
+            concat!($gate, ".rolling_variance"),
+        ]
+    };
+}
+
+// the buffers of each gate of a lstm layer in the order of LSTM_GATES
+const LSTM_BUFFER_NAMES: [[&str; 5]; 8] = [
+    lstm_gate_buffers!("wf"),
+    lstm_gate_buffers!("wi"),
+    lstm_gate_buffers!("wg"),
+    lstm_gate_buffers!("wo"),
+    lstm_gate_buffers!("uf"),
+    lstm_gate_buffers!("ui"),
+    lstm_gate_buffers!("ug"),
+    lstm_gate_buffers!("uo"),
+];
+
+macro_rules! conv_buffers {
+    ($conv:literal) => {
+        [
+            concat!($conv, ".biases"),
+            concat!($conv, ".scales"),
+            concat!($conv, ".rolling_mean"),
+            concat!($conv, ".rolling_variance"),
+            concat!($conv, ".weights"),
+        ]
+    };
+}
+
+// the buffers of each convolution of a crnn layer in the order of CRNN_CONVS
+const CRNN_BUFFER_NAMES: [[&str; 5]; 3] = [
+    conv_buffers!("input"),
+    conv_buffers!("self"),
+    conv_buffers!("output"),
+];
+
+// the buffers of each gate of a conv_lstm layer in the order of CONV_LSTM_GATES
+const CONV_LSTM_BUFFER_NAMES: [[&str; 5]; 11] = [
+    conv_buffers!("vf"),
+    conv_buffers!("vi"),
+    conv_buffers!("vo"),
+    conv_buffers!("wf"),
+    conv_buffers!("wi"),
+    conv_buffers!("wg"),
+    conv_buffers!("wo"),
+    conv_buffers!("uf"),
+    conv_buffers!("ui"),
+    conv_buffers!("ug"),
+    conv_buffers!("uo"),
+];
+
+// the biases and kernels of a recurrent convolution, with the batch norm buffers
+// in between if present
+fn recurrent_conv_names(names: &[&'static str; 5], batch_normalize: bool) -> Vec<&'static str> {
+    if batch_normalize {
+        names.to_vec()
+    } else {
+        vec![names[0], names[4]]
+    }
+}
+
+// the names of the buffers in the order of Layer::buffers()
+fn buffer_names(layer: &Layer) -> Vec<&'static str> {
+    const SCALES: [&str; 3] = ["scales", "rolling_mean", "rolling_variance"];
+
+    let num_buffers = layer.buffers().len();
+    match layer {
+        Layer::Connected(_) => ["biases", "weights"]
+            .iter()
+            .chain(&SCALES)
+            .take(num_buffers)
+            .cloned()
+            .collect(),
+        Layer::Convolutional(_) if num_buffers == 0 => vec![],
+        Layer::Convolutional(_) if num_buffers == 2 => vec!["biases", "weights"],
+        Layer::Convolutional(_) => iter::once("biases")
+            .chain(SCALES.iter().cloned())
+            .chain(iter::once("weights"))
+            .collect(),
+        Layer::BatchNorm(_) => vec!["biases", "scales", "rolling_mean", "rolling_variance"],
+        Layer::Local(_) => vec!["biases", "weights"],
+        Layer::Lstm(_) => LSTM_BUFFER_NAMES
+            .iter()
+            .flat_map(|names| names.iter().take(num_buffers / LSTM_GATES.len()))
+            .cloned()
+            .collect(),
+        Layer::Crnn(layer) => CRNN_BUFFER_NAMES
+            .iter()
+            .flat_map(|names| recurrent_conv_names(names, layer.base.config.batch_normalize))
+            .collect(),
+        Layer::ConvLstm(layer) => layer
+            .base
+            .gate_shapes()
+            .iter()
+            .flat_map(|&(gate, ..)| {
+                let index = CONV_LSTM_GATES
+                    .iter()
+                    .position(|&name| name == gate)
+                    .unwrap();
+                recurrent_conv_names(
+                    &CONV_LSTM_BUFFER_NAMES[index],
+                    layer.base.config.batch_normalize,
+                )
+            })
+            .collect(),
+        Layer::Shortcut(_) | Layer::Implicit(_) => vec!["weights"; num_buffers],
+        Layer::Route(_)
+        | Layer::MaxPool(_)
+        | Layer::UpSample(_)
+        | Layer::Yolo(_)
+        | Layer::AvgPool(_)
+        | Layer::ScaleChannels(_)
+        | Layer::Dropout(_)
+        | Layer::Softmax(_)
+        | Layer::Region(_)
+        | Layer::Sam(_)
+        | Layer::LocalAvgPool(_)
+        | Layer::Reorg(_)
+        | Layer::GaussianYolo(_)
+        | Layer::Custom(_) => vec![],
+    }
+}
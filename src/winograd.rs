@@ -0,0 +1,175 @@
+use crate::{
+    common::*,
+    darknet::{ConvolutionalLayer, ConvolutionalWeights, DarknetModel, Layer},
+    repack::{LayoutTag, Weights},
+};
+
+// the minimal filtering algorithms F(m x m, 3 x 3) from Lavin and Gray, "Fast
+// Algorithms for Convolutional Neural Networks". an m x m output tile is
+// computed as A^T [(G g G^T) * (B^T d B)] A from an (m + 2) x (m + 2) input tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WinogradTile {
+    #[serde(rename = "f2x3")]
+    F2x3,
+    #[serde(rename = "f4x3")]
+    F4x3,
+}
+
+impl WinogradTile {
+    // m, the side of the output tile
+    pub fn output_size(&self) -> usize {
+        match self {
+            Self::F2x3 => 2,
+            Self::F4x3 => 4,
+        }
+    }
+
+    // m + 2, the side of the input tile and the transformed kernel
+    pub fn input_size(&self) -> usize {
+        self.output_size() + 2
+    }
+
+    // G, applied to the kernel
+    pub fn kernel_transform(&self) -> &'static [[f32; 3]] {
+        match self {
+            Self::F2x3 => &[
+                [1.0, 0.0, 0.0],
+                [0.5, 0.5, 0.5],
+                [0.5, -0.5, 0.5],
+                [0.0, 0.0, 1.0],
+            ],
+            Self::F4x3 => &[
+                [1.0 / 4.0, 0.0, 0.0],
+                [-1.0 / 6.0, -1.0 / 6.0, -1.0 / 6.0],
+                [-1.0 / 6.0, 1.0 / 6.0, -1.0 / 6.0],
+                [1.0 / 24.0, 1.0 / 12.0, 1.0 / 6.0],
+                [1.0 / 24.0, -1.0 / 12.0, 1.0 / 6.0],
+                [0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    // B^T, applied to the input tile
+    pub fn input_transform(&self) -> &'static [&'static [f32]] {
+        match self {
+            Self::F2x3 => &[
+                &[1.0, 0.0, -1.0, 0.0],
+                &[0.0, 1.0, 1.0, 0.0],
+                &[0.0, -1.0, 1.0, 0.0],
+                &[0.0, 1.0, 0.0, -1.0],
+            ],
+            Self::F4x3 => &[
+                &[4.0, 0.0, -5.0, 0.0, 1.0, 0.0],
+                &[0.0, -4.0, -4.0, 1.0, 1.0, 0.0],
+                &[0.0, 4.0, -4.0, -1.0, 1.0, 0.0],
+                &[0.0, -2.0, -1.0, 2.0, 1.0, 0.0],
+                &[0.0, 2.0, -1.0, -2.0, 1.0, 0.0],
+                &[0.0, 4.0, 0.0, -5.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    // A^T, applied to the elementwise product
+    pub fn output_transform(&self) -> &'static [&'static [f32]] {
+        match self {
+            Self::F2x3 => &[&[1.0, 1.0, 1.0, 0.0], &[0.0, 1.0, -1.0, -1.0]],
+            Self::F4x3 => &[
+                &[1.0, 1.0, 1.0, 1.0, 1.0, 0.0],
+                &[0.0, 1.0, -1.0, 2.0, -2.0, 0.0],
+                &[0.0, 1.0, 1.0, 4.0, 4.0, 0.0],
+                &[0.0, 1.0, -1.0, 8.0, -8.0, 1.0],
+            ],
+        }
+    }
+
+    // G g G^T of one 3 x 3 kernel in row-major order
+    pub fn transform_kernel(&self, kernel: &[f32]) -> Vec<f32> {
+        assert_eq!(kernel.len(), 9, "the kernel must be 3x3");
+        let g = self.kernel_transform();
+        let n = self.input_size();
+
+        // G g, n x 3
+        let gg: Vec<[f32; 3]> = g
+            .iter()
+            .map(|row| {
+                let mut out = [0.0; 3];
+                (0..3).for_each(|col| {
+                    out[col] = (0..3).map(|k| row[k] * kernel[k * 3 + col]).sum();
+                });
+                out
+            })
+            .collect();
+
+        // (G g) G^T, n x n
+        (0..n)
+            .flat_map(|row| {
+                let gg = &gg;
+                (0..n).map(move |col| (0..3).map(|k| gg[row][k] * g[col][k]).sum())
+            })
+            .collect()
+    }
+}
+
+// the transformed kernels of the convolutions that support them, kept apart
+// from the original weights
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WinogradKernels {
+    pub tile: WinogradTile,
+    // [filters, in_c / groups, m + 2, m + 2] in OIHW order by layer index
+    pub kernels: IndexMap<usize, Weights>,
+}
+
+impl WinogradKernels {
+    pub fn is_transformed(&self, layer_index: usize) -> bool {
+        self.kernels.contains_key(&layer_index)
+    }
+
+    pub fn get(&self, layer_index: usize) -> Option<&Weights> {
+        self.kernels.get(&layer_index)
+    }
+}
+
+impl ConvolutionalLayer {
+    // plain 3x3 stride-1 convolutions with their own weights
+    pub fn supports_winograd(&self) -> bool {
+        let config = &self.base.config;
+        config.size == 3
+            && config.stride_x == 1
+            && config.stride_y == 1
+            && config.dilation == 1
+            && !config.antialiasing
+            && !config.binary
+            && !config.xnor
+            && matches!(self.weights, ConvolutionalWeights::Owned { .. })
+    }
+
+    pub fn winograd_kernel(&self, tile: WinogradTile) -> Option<Weights> {
+        if !self.supports_winograd() {
+            return None;
+        }
+        let weights = self.weights_tensor()?;
+        let [filters, in_c, _, _] = weights.dims;
+        let n = tile.input_size();
+        let data = weights
+            .data
+            .chunks_exact(9)
+            .flat_map(|kernel| tile.transform_kernel(kernel))
+            .collect();
+        Some(Weights::new(LayoutTag::Oihw, [filters, in_c, n, n], data).unwrap())
+    }
+}
+
+impl DarknetModel {
+    pub fn precompute_winograd(&self, tile: WinogradTile) -> WinogradKernels {
+        let kernels = self
+            .layers
+            .iter()
+            .filter_map(|(&layer_index, layer)| match layer {
+                Layer::Convolutional(conv) => Some((layer_index, conv.winograd_kernel(tile)?)),
+                _ => None,
+            })
+            .sorted_by_key(|(layer_index, _)| *layer_index)
+            .collect();
+        WinogradKernels { tile, kernels }
+    }
+}
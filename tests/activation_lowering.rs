@@ -0,0 +1,101 @@
+use anyhow::Result;
+use darknet_config::{
+    export::{self, openvino::to_openvino_with_options, ActivationLowering, ExportOptions},
+    DarknetConfig, DarknetModel,
+};
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=mish
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=hard_mish
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=swish
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+fn options(activation_lowering: ActivationLowering) -> ExportOptions {
+    ExportOptions {
+        activation_lowering,
+    }
+}
+
+#[test]
+fn onnx_activation_lowering() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    let model = DarknetModel::from_config(&config)?;
+    let op_types = |lowering| -> Vec<Vec<String>> {
+        export::coverage_with_options(&model.base, &options(lowering))
+            .layers
+            .iter()
+            .take(3)
+            .map(|layer| layer.ops.iter().map(|op| op.to_string()).collect())
+            .collect()
+    };
+
+    let native = export::coverage(&model.base);
+    assert!(native.is_supported());
+    assert_eq!(native.min_opset(), 18);
+
+    let primitives = op_types(ActivationLowering::Primitives);
+    assert_eq!(primitives[0], ["Conv-1", "Softplus-1", "Tanh-6", "Mul-7"]);
+    assert_eq!(primitives[1], ["Conv-1", "HardSigmoid-6", "Mul-7"]);
+    assert_eq!(
+        export::coverage_with_options(&model.base, &options(ActivationLowering::Primitives))
+            .min_opset(),
+        7
+    );
+
+    let custom = op_types(ActivationLowering::Custom);
+    assert_eq!(custom[0], ["Conv-1", "darknet.Mish-1"]);
+    assert_eq!(custom[1], ["Conv-1", "darknet.HardMish-1"]);
+    assert_eq!(custom[2], ["Conv-1", "darknet.Swish-1"]);
+    Ok(())
+}
+
+#[test]
+fn openvino_activation_lowering() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    let model = DarknetModel::from_config(&config)?;
+
+    let native = to_openvino_with_options(&model, "test", &ExportOptions::default())?;
+    assert!(native.xml.contains("type=\"Mish\" version=\"opset4\""));
+    assert!(native.xml.contains("type=\"HardSigmoid\""));
+
+    let primitives =
+        to_openvino_with_options(&model, "test", &options(ActivationLowering::Primitives))?;
+    assert!(!primitives.xml.contains("type=\"Mish\""));
+    assert!(primitives
+        .xml
+        .contains("type=\"SoftPlus\" version=\"opset4\""));
+
+    let custom = to_openvino_with_options(&model, "test", &options(ActivationLowering::Custom))?;
+    assert!(custom.xml.contains("type=\"Mish\" version=\"darknet\""));
+    assert!(custom.xml.contains("type=\"HardMish\" version=\"darknet\""));
+    assert!(custom.xml.contains("type=\"Swish\" version=\"darknet\""));
+    Ok(())
+}
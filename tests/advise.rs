@@ -0,0 +1,116 @@
+use anyhow::Result;
+use darknet_config::{
+    advise::{advise, Advice},
+    dataset::{save_labels, DatasetStats, LabelBox},
+    validate::Severity,
+    DarknetConfig,
+};
+use std::fs;
+
+const CONFIG: &str = "\
+[net]
+width=64
+height=64
+channels=3
+batch=64
+subdivisions=16
+max_batches=10000
+burn_in=1000
+policy=steps
+steps=8000,9000
+scales=.1,.1
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+fn find<'a>(advices: &'a [Advice], prefix: &str) -> Option<&'a Advice> {
+    advices
+        .iter()
+        .find(|advice| advice.message.starts_with(prefix))
+}
+
+#[test]
+fn advise_schedule() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    assert!(advise(&config, None).is_empty());
+
+    let text = CONFIG
+        .replace("subdivisions=16", "subdivisions=3")
+        .replace("burn_in=1000", "burn_in=20000")
+        .replace("steps=8000,9000", "steps=12000,9000");
+    let config: DarknetConfig = text.parse()?;
+    let advices = advise(&config, None);
+
+    let advice = find(&advices, "batch (64) is not divisible").unwrap();
+    assert_eq!(advice.severity, Severity::Error);
+    assert_eq!(advice.suggestion.as_deref(), Some("subdivisions=2"));
+
+    let advice = find(&advices, "burn_in (20000) is longer").unwrap();
+    assert_eq!(advice.severity, Severity::Warning);
+    assert_eq!(advice.suggestion.as_deref(), Some("burn_in=1000"));
+
+    let advice = find(&advices, "some steps [12000, 9000] exceed").unwrap();
+    assert_eq!(advice.suggestion.as_deref(), Some("steps=8000,9000"));
+    assert!(find(&advices, "steps [12000, 9000] are not in increasing order").is_some());
+    Ok(())
+}
+
+#[test]
+fn advise_random_resize() -> Result<()> {
+    let text = CONFIG.replace("height=64", "height=32") + "random=1\n";
+    let config: DarknetConfig = text.parse()?;
+    let advices = advise(&config, None);
+
+    let advice = find(&advices, "random resizing").unwrap();
+    assert_eq!(advice.layer_index, Some(1));
+    assert_eq!(
+        advice.to_string(),
+        "warning: layer 1: random resizing is enabled with non-square network input \
+         (suggestion: random=0)"
+    );
+    Ok(())
+}
+
+#[test]
+fn advise_dataset() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("darknet-config-advise-{}", std::process::id()));
+    fs::create_dir_all(dir.join("labels"))?;
+    let label = |class| LabelBox {
+        class,
+        x: 0.5,
+        y: 0.5,
+        w: 0.1,
+        h: 0.1,
+    };
+    let images = [dir.join("images/0001.jpg"), dir.join("images/0002.jpg")];
+    save_labels(dir.join("labels/0001.txt"), &[label(0), label(0)])?;
+    let stats = DatasetStats::from_image_list(2, &images)?;
+
+    let config: DarknetConfig = CONFIG.parse()?;
+    let advices = advise(&config, Some(&stats));
+
+    let advice = find(&advices, "the dataset has 2 classes").unwrap();
+    assert_eq!(advice.severity, Severity::Error);
+    assert_eq!(advice.suggestion.as_deref(), Some("classes=2"));
+    assert!(find(&advices, "1 training images have no label files").is_some());
+    assert!(find(&advices, "max_batches (10000)").is_none());
+
+    // the second class has no objects at all
+    let advice = find(&advices, "the dataset is imbalanced").unwrap();
+    assert_eq!(advice.layer_index, Some(1));
+    assert_eq!(advice.suggestion.as_deref(), Some("counters_per_class=2,0"));
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use darknet_config::{parity::Tolerance, DarknetConfig, DarknetModel};
+use half::f16;
+
+const CONFIG: &str = "\
+[net]
+width=16
+height=16
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=8
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[maxpool]
+size=2
+stride=2
+
+[convolutional]
+filters=4
+size=1
+stride=1
+pad=1
+activation=linear
+";
+
+#[test]
+fn weights_approx_eq() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+    model
+        .layers
+        .values_mut()
+        .flat_map(|layer| layer.buffers_mut())
+        .flat_map(|(_, values)| values.iter_mut())
+        .enumerate()
+        .for_each(|(index, value)| *value = (index as f32 * 0.37).sin());
+
+    // a fp16 round trip is within the half precision
+    let mut rounded = model.clone();
+    rounded
+        .layers
+        .values_mut()
+        .flat_map(|layer| layer.buffers_mut())
+        .flat_map(|(_, values)| values.iter_mut())
+        .for_each(|value| *value = f16::from_f32(*value).to_f32());
+
+    let report = model.approx_eq(&rounded, Tolerance::default())?;
+    assert!(!report.passed());
+    let half_precision = Tolerance {
+        abs: 1e-3,
+        rel: 0.0,
+    };
+    let report = model.approx_eq(&rounded, half_precision)?;
+    assert!(report.passed());
+    report.ensure_passed()?;
+    assert!(report.max_error() > 0.0);
+    // the maxpool layer has no buffers
+    let layers: Vec<_> = report
+        .layers
+        .iter()
+        .map(|layer| layer.layer_index)
+        .collect();
+    assert_eq!(layers, [0, 2]);
+
+    // a changed bias is reported with its layer and buffer
+    rounded.layers[&2].buffers_mut()[0].1[1] += 0.5;
+    let report = model.approx_eq(&rounded, half_precision)?;
+    let failed: Vec<_> = report
+        .failed_layers()
+        .map(|layer| layer.layer_index)
+        .collect();
+    assert_eq!(failed, [2]);
+    let err = report.ensure_passed().unwrap_err().to_string();
+    assert!(err.contains("layer 2 biases: 1 of 4 values"), "{}", err);
+
+    // the layouts must match
+    let other: DarknetConfig = CONFIG.replace("filters=4", "filters=5").parse()?;
+    assert!(model
+        .approx_eq(&DarknetModel::from_config(&other)?, half_precision)
+        .is_err());
+    Ok(())
+}
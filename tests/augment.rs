@@ -0,0 +1,45 @@
+#![cfg(all(feature = "image", feature = "random"))]
+
+use anyhow::Result;
+use darknet_config::{augment::Photometric, DarknetConfig};
+use image::{DynamicImage, Rgb, RgbImage};
+
+#[test]
+fn photometric_augmentation() -> Result<()> {
+    let image = DynamicImage::ImageRgb8(RgbImage::from_fn(8, 8, |x, y| {
+        Rgb([(x * 32) as u8, (y * 32) as u8, 128])
+    }));
+
+    // the identity keeps the pixels up to the byte truncation
+    let output = Photometric::IDENTITY.apply(&image).to_rgb8();
+    let input = image.to_rgb8();
+    assert!(input
+        .pixels()
+        .zip(output.pixels())
+        .all(|(lhs, rhs)| (0..3).all(|c| (lhs[c] as i32 - rhs[c] as i32).abs() <= 1)));
+
+    // without saturation every pixel turns gray regardless of the hue
+    let gray = Photometric {
+        hue: 0.5,
+        saturation: 0.0,
+        exposure: 1.0,
+    }
+    .apply(&image)
+    .to_rgb8();
+    assert!(gray
+        .pixels()
+        .all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]));
+
+    let config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let lhs = config.net.augment_preview(&image, 4, 7);
+    let rhs = config.net.augment_preview(&image, 4, 7);
+    assert_eq!(lhs.len(), 4);
+    lhs.iter().zip(&rhs).for_each(|((lhs, _), (rhs, _))| {
+        assert_eq!(lhs, rhs);
+        assert!(lhs.hue.abs() <= config.net.hue.raw() as f32);
+    });
+    Ok(())
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "random")]
+
+use anyhow::Result;
+use darknet_config::{
+    perturb::{Noise, NoiseSpec},
+    DarknetConfig, DarknetModel,
+};
+
+#[test]
+fn average_checkpoints() -> Result<()> {
+    let config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let model = DarknetModel::from_config(&config)?;
+    let spec = NoiseSpec::new(Noise::Uniform {
+        low: -1.0,
+        high: 1.0,
+    });
+    let lhs = model.perturb(&spec, 1)?;
+    let rhs = model.perturb(&spec, 2)?;
+
+    let averaged = DarknetModel::average(&[lhs.clone(), rhs.clone()])?;
+    let layer_index = 0;
+    let buffers = averaged.layers[&layer_index].buffers();
+    let lhs_buffers = lhs.layers[&layer_index].buffers();
+    let rhs_buffers = rhs.layers[&layer_index].buffers();
+    buffers.iter().zip(&lhs_buffers).zip(&rhs_buffers).for_each(
+        |(((_, values), (_, lhs)), (_, rhs))| {
+            values
+                .iter()
+                .zip(lhs.iter())
+                .zip(rhs.iter())
+                .for_each(|((&value, &lhs), &rhs)| {
+                    assert!((value - (lhs + rhs) / 2.0).abs() <= 1e-6)
+                });
+        },
+    );
+
+    // checkpoints of different architectures are rejected
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/yolov7-tiny.cfg");
+    let other_config: DarknetConfig = std::fs::read_to_string(path)?
+        .replacen("filters=32", "filters=16", 1)
+        .parse()?;
+    let other = DarknetModel::from_config(&other_config)?;
+    assert!(DarknetModel::average(&[model, other]).is_err());
+    assert!(DarknetModel::average(&[]).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use darknet_config::{
+    binding::{ColorFormat, DecodeParams, HeadInfo, ResizeMode, TensorLayout},
+    model::ModelBase,
+};
+
+#[test]
+fn yolov7_tiny_outputs() -> Result<()> {
+    let model = ModelBase::from_config_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+
+    let outputs = model.outputs();
+    assert_eq!(outputs.len(), 3);
+    assert!(outputs
+        .iter()
+        .all(|output| output.name == format!("yolo_{}", output.layer_index)));
+
+    let strides: Vec<_> = outputs
+        .iter()
+        .map(|output| {
+            let (classes, anchors, stride) = match output.decode {
+                DecodeParams::Yolo {
+                    classes,
+                    ref anchors,
+                    stride,
+                    ..
+                } => (classes, anchors, stride),
+                _ => unreachable!(),
+            };
+            assert_eq!(classes, 80);
+            assert_eq!(anchors.len(), 3);
+            assert_eq!(output.dims, [255, 416 / stride[0], 416 / stride[1]]);
+            stride
+        })
+        .collect();
+    assert_eq!(strides, [[8, 8], [16, 16], [32, 32]]);
+
+    Ok(())
+}
+
+#[test]
+fn yolov7_tiny_inputs() -> Result<()> {
+    let model = ModelBase::from_config_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+
+    let inputs = model.inputs();
+    assert_eq!(inputs.len(), 1);
+    let input = &inputs[0];
+    assert_eq!(input.name, "input");
+    assert_eq!(input.layout, TensorLayout::Nchw);
+    assert_eq!(input.dims, [3, 416, 416]);
+    assert_eq!(input.value_range, [0.0, 1.0]);
+    assert_eq!(input.color_format, Some(ColorFormat::Rgb));
+    assert_eq!(input.resize, ResizeMode::Stretch);
+
+    Ok(())
+}
+
+#[test]
+fn yolov7_tiny_head_table() -> Result<()> {
+    let model = ModelBase::from_config_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+
+    let heads: Vec<HeadInfo> = serde_json::from_str(&model.head_table_json()?)?;
+    assert_eq!(heads, model.heads());
+    assert_eq!(heads.len(), 3);
+    assert_eq!(heads[0].stride, [8, 8]);
+    assert_eq!(heads[0].grid_size, [52, 52]);
+    assert_eq!(heads[0].anchors, [(10, 13), (16, 30), (33, 23)]);
+    assert_eq!(heads[2].grid_size, [13, 13]);
+    assert!(heads
+        .iter()
+        .all(|head| head.classes == 80 && head.scale_x_y == 2.0));
+
+    Ok(())
+}
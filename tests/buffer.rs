@@ -0,0 +1,68 @@
+use anyhow::Result;
+use darknet_config::{buffer::BufferOptions, DarknetConfig, DarknetModel};
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+#[test]
+fn aligned_buffers() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+    model.layers[0]
+        .buffers_mut()
+        .into_iter()
+        .for_each(|(_, values)| values.fill(0.5));
+
+    let buffers = model.aligned_buffers(BufferOptions::cache_line())?;
+    let names: Vec<_> = buffers.iter().map(|buffer| buffer.name).collect();
+    assert_eq!(
+        names,
+        [
+            "biases",
+            "scales",
+            "rolling_mean",
+            "rolling_variance",
+            "weights"
+        ]
+    );
+
+    for buffer in buffers.iter().chain(&buffers.clone()) {
+        assert_eq!(buffer.alignment(), 64);
+        assert_eq!(buffer.as_slice().as_ptr() as usize % 64, 0);
+        assert_eq!(buffer.padded_len() % 16, 0);
+        assert!(buffer.as_slice().iter().all(|&value| value == 0.5));
+        assert!(buffer.padded_slice()[buffer.len()..]
+            .iter()
+            .all(|&value| value == 0.0));
+    }
+    // 18 biases are padded to 32 values, 3 * 18 weights to 64
+    assert_eq!(buffers[0].len(), 18);
+    assert_eq!(buffers[0].padded_len(), 32);
+    assert_eq!(buffers[4].padded_len(), 64);
+
+    assert!(model
+        .aligned_buffers(BufferOptions {
+            alignment: 48,
+            pad_to: 1
+        })
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "encryption")]
+
+use anyhow::Result;
+use darknet_config::{
+    bundle::{DecryptReader, EncryptWriter},
+    DarknetConfig, DarknetModel,
+};
+use std::io::{Read, Write};
+
+#[test]
+fn encrypted_bundle() -> Result<()> {
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+"
+    .parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+    model.base.seen = 1234;
+    let key = [7u8; 32];
+
+    let mut bundle = vec![];
+    model.write_bundle(&mut bundle, &key)?;
+    let text = config.to_cfg_string()?;
+    assert!(!bundle
+        .windows(text.len())
+        .any(|window| window == text.as_bytes()));
+
+    let loaded = DarknetModel::read_bundle(bundle.as_slice(), &key)?;
+    assert_eq!(loaded.base.to_config(), config);
+    assert_eq!(loaded.base.seen, 1234);
+    let mut weights = vec![];
+    let mut loaded_weights = vec![];
+    model.write_weights(&mut weights)?;
+    loaded.write_weights(&mut loaded_weights)?;
+    assert_eq!(weights, loaded_weights);
+
+    // wrong keys, altered bytes and truncation are detected
+    assert!(DarknetModel::read_bundle(bundle.as_slice(), &[8u8; 32]).is_err());
+    let mut altered = bundle.clone();
+    *altered.last_mut().unwrap() ^= 1;
+    assert!(DarknetModel::read_bundle(altered.as_slice(), &key).is_err());
+    assert!(DarknetModel::read_bundle(&bundle[..bundle.len() - 1], &key).is_err());
+
+    // a chunk-aligned stream ends with an empty chunk
+    let mut writer = EncryptWriter::with_chunk_size(vec![], &key, 4)?;
+    writer.write_all(b"abcdefgh")?;
+    let stream = writer.finish()?;
+    let mut plaintext = vec![];
+    DecryptReader::new(stream.as_slice(), &key)?.read_to_end(&mut plaintext)?;
+    assert_eq!(plaintext, b"abcdefgh");
+    let mut reader = DecryptReader::new(&stream[..stream.len() - 16], &key)?;
+    assert!(reader.read_to_end(&mut vec![]).is_err());
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use darknet_config::{
+    darknet::WeightsByteOrder, weights_layout::WeightsLayout, DarknetConfig, DarknetModel,
+};
+use std::fs;
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+#[test]
+fn big_endian_weights() -> Result<()> {
+    let dir =
+        std::env::temp_dir().join(format!("darknet-config-byte-order-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let config: DarknetConfig = CONFIG.parse()?;
+    let num_values = (WeightsLayout::describe(&config)?.file_size() - 20) / 4;
+    let values: Vec<f32> = (0..num_values).map(|index| index as f32 * 0.25).collect();
+
+    // the same file as dumped by darknet on little and big endian hosts
+    let mut le_bytes = vec![];
+    let mut be_bytes = vec![];
+    [0u32, 2, 0].iter().for_each(|value| {
+        le_bytes.extend_from_slice(&value.to_le_bytes());
+        be_bytes.extend_from_slice(&value.to_be_bytes());
+    });
+    le_bytes.extend_from_slice(&64u64.to_le_bytes());
+    be_bytes.extend_from_slice(&64u64.to_be_bytes());
+    values.iter().for_each(|value| {
+        le_bytes.extend_from_slice(&value.to_le_bytes());
+        be_bytes.extend_from_slice(&value.to_be_bytes());
+    });
+    let le_file = dir.join("le.weights");
+    let be_file = dir.join("be.weights");
+    fs::write(&le_file, &le_bytes)?;
+    fs::write(&be_file, &be_bytes)?;
+
+    let mut le_model = DarknetModel::from_config(&config)?;
+    le_model.load_weights(&le_file)?;
+    let mut be_model = DarknetModel::from_config(&config)?;
+    be_model.load_weights_with_byte_order(&be_file, WeightsByteOrder::Big)?;
+
+    assert_eq!(be_model.base.seen, 64);
+    let loaded: Vec<f32> = be_model.layers[&0]
+        .buffers()
+        .into_iter()
+        .flat_map(|(_, values)| values.to_vec())
+        .collect();
+    assert_eq!(loaded, values);
+    assert_eq!(be_model.layers[&0].buffers(), le_model.layers[&0].buffers());
+
+    // the host order only matters for files dumped on this host
+    let native_file = match WeightsByteOrder::native() {
+        WeightsByteOrder::Little => &le_file,
+        WeightsByteOrder::Big => &be_file,
+    };
+    let mut native_model = DarknetModel::from_config(&config)?;
+    native_model.load_weights_with_byte_order(native_file, WeightsByteOrder::native())?;
+    assert_eq!(
+        native_model.layers[&0].buffers(),
+        le_model.layers[&0].buffers()
+    );
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use anyhow::Result;
+use darknet_config::{
+    calibration::{
+        fp16_overflow_layers, int8_scales, ActivationRange, ActivationStatsProvider,
+        CalibrationSink, CalibrationTable,
+    },
+    export::triton::{tensorrt_precision_hints_with_stats, Precision},
+    ModelBase,
+};
+
+#[test]
+fn calibration_table() -> Result<()> {
+    let model = ModelBase::from_config_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+
+    let mut table = CalibrationTable::new();
+    table.record(0, ActivationRange::new(-1.0, 6.0));
+    table.record(0, ActivationRange::new(-2.0, 12.7));
+    table.record(1, ActivationRange::new(-1e6, 3.0));
+
+    let range = table.activation_range(0).unwrap();
+    assert_eq!((range.min, range.max, range.num_batches), (-2.0, 12.7, 2));
+    assert_eq!(fp16_overflow_layers(&model, &table), vec![1]);
+
+    let scales = int8_scales(&model, &table);
+    assert_eq!(scales.len(), 2);
+    assert!((scales["conv_0"] - 0.1).abs() < 1e-6);
+
+    let hints = tensorrt_precision_hints_with_stats(&model, Precision::Fp16, &table);
+    assert_eq!(hints[0], ("conv_0".to_string(), Precision::Fp16));
+    assert_eq!(hints[1], ("conv_1".to_string(), Precision::Fp32));
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use darknet_config::{
+    checkpoint::{CheckpointManager, RetentionPolicy},
+    darknet::{ConvolutionalLayer, ConvolutionalWeights, Layer},
+    DarknetConfig, DarknetModel,
+};
+use std::fs;
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+fn fill_biases(model: &mut DarknetModel, value: f32) {
+    match model.layers.get_mut(&0) {
+        Some(Layer::Convolutional(ConvolutionalLayer {
+            weights: ConvolutionalWeights::Owned { biases, .. },
+            ..
+        })) => biases.fill(value),
+        _ => unreachable!(),
+    }
+}
+
+fn first_bias(model: &DarknetModel) -> f32 {
+    match &model.layers[&0] {
+        Layer::Convolutional(ConvolutionalLayer {
+            weights: ConvolutionalWeights::Owned { biases, .. },
+            ..
+        }) => biases[0],
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn checkpoint_rotation() -> Result<()> {
+    let dir =
+        std::env::temp_dir().join(format!("darknet-config-checkpoint-{}", std::process::id()));
+    let config: DarknetConfig = CONFIG.parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+    model.base.seen = 640;
+
+    let manager = CheckpointManager::new(
+        &dir,
+        "yolo",
+        RetentionPolicy {
+            keep_last: Some(2),
+            keep_every: Some(2000),
+        },
+    )?;
+    assert_eq!(manager.latest()?, None);
+
+    let mut removed = vec![];
+    for iteration in (1..=4).map(|step| step * 1000) {
+        fill_biases(&mut model, iteration as f32);
+        removed.extend(manager.save_iteration(&model, iteration)?);
+    }
+    assert_eq!(removed, [manager.iteration_path(1000)]);
+
+    let iterations: Vec<_> = manager
+        .iterations()?
+        .into_iter()
+        .map(|(iteration, _)| iteration)
+        .collect();
+    assert_eq!(iterations, [2000, 3000, 4000]);
+    assert_eq!(manager.latest()?, Some(manager.last_path()));
+    assert!(!dir.join("yolo_last.weights.tmp").exists());
+
+    // the last checkpoint loads back into the same model
+    let mut loaded = DarknetModel::from_config(&config)?;
+    loaded.load_weights(manager.last_path())?;
+    assert_eq!(loaded.base.seen, 640);
+    assert_eq!(first_bias(&loaded), 4000.0);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use anyhow::Result;
+use darknet_config::dataset::{
+    load_labels, load_names, merge_names, save_labels, DatasetStats, LabelBox, LabeledDataset,
+};
+use std::fs;
+
+#[test]
+fn merge_dataset_names() -> Result<()> {
+    let names = |names: &[&str]| -> Vec<String> { names.iter().map(|&name| name.into()).collect() };
+    let merged = merge_names(&[names(&["person", "car"]), names(&["dog", "person", "bus"])]);
+    assert_eq!(merged.names, names(&["person", "car", "dog", "bus"]));
+    assert_eq!(merged.translations, vec![vec![0, 1], vec![2, 0, 3]]);
+
+    let dir = std::env::temp_dir().join(format!("darknet-config-merge-{}", std::process::id()));
+    fs::create_dir_all(dir.join("labels"))?;
+    let image = dir.join("images/0001.jpg");
+    let label_file = dir.join("labels/0001.txt");
+    let label = |class| LabelBox {
+        class,
+        x: 0.5,
+        y: 0.5,
+        w: 0.25,
+        h: 0.25,
+    };
+    save_labels(&label_file, &[label(0), label(2)])?;
+
+    assert_eq!(merged.remap_image_list(1, &[&image])?, 1);
+    let classes: Vec<_> = load_labels(&label_file)?
+        .into_iter()
+        .map(|label| label.class)
+        .collect();
+    assert_eq!(classes, vec![2, 3]);
+
+    // out of range classes are rejected without touching the files
+    save_labels(&label_file, &[label(0), label(5)])?;
+    assert!(merged.remap_image_list(1, &[&image]).is_err());
+    assert_eq!(load_labels(&label_file)?[0].class, 0);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn import_coco() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("darknet-config-coco-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let annotation_file = dir.join("instances.json");
+    fs::write(
+        &annotation_file,
+        r#"{
+            "images": [
+                {"id": 1, "file_name": "a.jpg", "width": 200, "height": 100},
+                {"id": 2, "file_name": "b.jpg", "width": 100, "height": 100}
+            ],
+            "annotations": [
+                {"image_id": 1, "category_id": 18, "bbox": [50, 25, 100, 50], "iscrowd": 0},
+                {"image_id": 1, "category_id": 1, "bbox": [0, 0, 10, 10], "iscrowd": 1}
+            ],
+            "categories": [{"id": 18, "name": "dog"}, {"id": 1, "name": "person"}]
+        }"#,
+    )?;
+
+    let dataset = LabeledDataset::from_coco_json(&annotation_file, dir.join("images"))?;
+    assert_eq!(dataset.names, vec!["person".to_string(), "dog".to_string()]);
+    assert_eq!(
+        dataset.images[0].1,
+        vec![LabelBox {
+            class: 1,
+            x: 0.5,
+            y: 0.5,
+            w: 0.5,
+            h: 0.5
+        }]
+    );
+    assert!(dataset.images[1].1.is_empty());
+
+    let data = dataset.save(&dir, "coco")?;
+    assert_eq!(data.classes, 2);
+    assert_eq!(load_names(data.names.as_ref().unwrap())?, dataset.names);
+    assert_eq!(data.load_train_list()?.len(), 2);
+    assert_eq!(load_labels(dir.join("labels/a.txt"))?.len(), 1);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(feature = "voc")]
+#[test]
+fn import_voc() -> Result<()> {
+    use darknet_config::dataset::load_image_list;
+
+    let dir = std::env::temp_dir().join(format!("darknet-config-voc-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let xml_file = dir.join("000001.xml");
+    fs::write(
+        &xml_file,
+        "<annotation>
+            <filename>000001.jpg</filename>
+            <size><width>100</width><height>200</height><depth>3</depth></size>
+            <object>
+                <name>dog</name>
+                <difficult>0</difficult>
+                <bndbox><xmin>11</xmin><ymin>21</ymin><xmax>61</xmax><ymax>121</ymax></bndbox>
+            </object>
+            <object>
+                <name>cat</name>
+                <difficult>1</difficult>
+                <bndbox><xmin>1</xmin><ymin>1</ymin><xmax>10</xmax><ymax>10</ymax></bndbox>
+            </object>
+        </annotation>",
+    )?;
+
+    let dataset = LabeledDataset::from_voc_xml(&[&xml_file], dir.join("JPEGImages"), None)?;
+    assert_eq!(dataset.names, vec!["dog".to_string()]);
+    let (image_path, boxes) = &dataset.images[0];
+    assert_eq!(*image_path, dir.join("JPEGImages/000001.jpg"));
+    assert_eq!(boxes.len(), 1);
+    assert!((boxes[0].x - 0.35).abs() < 1e-9 && (boxes[0].h - 0.5).abs() < 1e-9);
+
+    let data = dataset.save(&dir, "voc")?;
+    assert_eq!(load_image_list(data.train.as_ref().unwrap())?.len(), 1);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn export_coco() -> Result<()> {
+    let label = |class, x, y| LabelBox {
+        class,
+        x,
+        y,
+        w: 0.5,
+        h: 0.5,
+    };
+    let dataset = LabeledDataset {
+        names: vec!["person".into(), "dog".into()],
+        images: vec![
+            ("val2017/000000000139.jpg".into(), vec![label(1, 0.5, 0.5)]),
+            (
+                "val2017/000000000285.jpg".into(),
+                vec![label(0, 0.25, 0.75)],
+            ),
+        ],
+    };
+
+    let text = dataset.to_coco_json(|_| Ok((200, 100)))?;
+    let coco: serde_json::Value = serde_json::from_str(&text)?;
+    assert_eq!(coco["images"][0]["id"], 139);
+    assert_eq!(coco["images"][1]["file_name"], "000000000285.jpg");
+    assert_eq!(coco["categories"][1]["id"], 2);
+    assert_eq!(coco["annotations"][0]["category_id"], 2);
+    assert_eq!(
+        coco["annotations"][1]["bbox"],
+        serde_json::json!([0.0, 50.0, 100.0, 50.0])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn suggest_anchors() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("darknet-config-anchors-{}", std::process::id()));
+    fs::create_dir_all(dir.join("labels"))?;
+    let label = |w, h| LabelBox {
+        class: 0,
+        x: 0.5,
+        y: 0.5,
+        w,
+        h,
+    };
+    let images = [dir.join("images/0001.jpg"), dir.join("images/0002.jpg")];
+    save_labels(
+        dir.join("labels/0001.txt"),
+        &[label(0.05, 0.05), label(0.5, 0.5), label(0.0, 0.1)],
+    )?;
+    save_labels(
+        dir.join("labels/0002.txt"),
+        &[label(0.05, 0.05), label(0.5, 0.5)],
+    )?;
+
+    // the zero-width box is counted but takes no part in the clustering
+    let stats = DatasetStats::from_image_list(1, &images)?;
+    assert_eq!(stats.num_boxes, 5);
+    assert_eq!(stats.suggest_anchors(2, 416, 416)?, [(21, 21), (208, 208)]);
+    assert!(stats.suggest_anchors(5, 416, 416).is_err());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use darknet_config::{
+    distill::{pair_feature_layers, Alignment},
+    ModelBase,
+};
+
+#[test]
+fn distillation_pairs() -> Result<()> {
+    let teacher =
+        ModelBase::from_config_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/yolov4.cfg"))?;
+    let student = ModelBase::from_config_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+
+    // a model pairs with itself exactly
+    let map = pair_feature_layers(&student, &student);
+    assert!(map.is_aligned());
+    assert!(map.unpaired_teacher.is_empty() && map.unpaired_student.is_empty());
+    assert!(map
+        .pairs
+        .iter()
+        .all(|pair| pair.teacher_index == pair.student_index));
+
+    let map = teacher.pair_feature_layers(&student);
+    for &stride in &[8, 16, 32] {
+        assert!(map.pairs.iter().any(|pair| pair.stride == stride));
+    }
+    assert!(map.pairs.iter().all(|pair| {
+        let [teacher_h, teacher_w, _] = pair.teacher_shape;
+        let [student_h, student_w, _] = pair.student_shape;
+        let spatial_eq = [teacher_h, teacher_w] == [student_h, student_w];
+        spatial_eq == (pair.alignment != Alignment::Spatial)
+    }));
+
+    Ok(())
+}
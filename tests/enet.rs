@@ -0,0 +1,88 @@
+use anyhow::Result;
+use darknet_config::{
+    config::DarknetConfig,
+    model::{LayerBase, ModelBase},
+};
+
+#[test]
+fn enet_coco() -> Result<()> {
+    let config = DarknetConfig::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/enet-coco.cfg"))?;
+    let model = ModelBase::from_config(&config)?;
+
+    // every squeeze-excitation block rescales the depthwise conv output
+    let scale_layers: Vec<_> = model
+        .layers
+        .values()
+        .filter_map(|layer| match layer {
+            LayerBase::ScaleChannels(layer) => Some(layer),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(scale_layers.len(), 16);
+    scale_layers.iter().for_each(|layer| {
+        let [h, w, c] = layer.output_shape;
+        assert_eq!(layer.input_shape, vec![[1, 1, c], [h, w, c]]);
+    });
+
+    let output_shapes: Vec<_> = model
+        .layers
+        .values()
+        .filter_map(|layer| match layer {
+            LayerBase::Yolo(yolo) => Some(yolo.inout_shape),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(output_shapes, vec![[13, 13, 255], [26, 26, 255]]);
+
+    // the config survives a round trip
+    let reloaded: DarknetConfig = config.to_cfg_string()?.parse()?;
+    assert_eq!(config, reloaded);
+
+    Ok(())
+}
+
+#[test]
+fn scale_channels_mismatch() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=swish
+
+[avgpool]
+
+[convolutional]
+filters=8
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[scale_channels]
+from=-3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+    assert!(ModelBase::from_config(&config).is_err());
+
+    Ok(())
+}
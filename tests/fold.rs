@@ -0,0 +1,81 @@
+use anyhow::Result;
+use darknet_config::{config::DarknetConfig, darknet::DarknetModel};
+
+#[test]
+fn fold_noop_layers() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[upsample]
+stride=1
+
+[route]
+layers=-1
+
+[convolutional]
+filters=16
+size=1
+stride=1
+pad=1
+activation=linear
+x_passthrough=1
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+
+    // identity weights for the passthrough convolution
+    for (index, (_, values)) in model.layers[&3].buffers_mut().into_iter().enumerate() {
+        values.iter_mut().enumerate().for_each(|(pos, value)| {
+            *value = if index == 1 && pos % 17 == 0 {
+                1.0
+            } else {
+                0.0
+            };
+        });
+    }
+    for (_, values) in model.layers[&4].buffers_mut() {
+        values
+            .iter_mut()
+            .enumerate()
+            .for_each(|(pos, value)| *value = pos as f32);
+    }
+
+    let folded = model.fold_noops()?;
+    assert_eq!(folded.removed, [1, 2, 3]);
+    assert_eq!(folded.config.layers.len(), 3);
+    assert_eq!(
+        folded.model.layers[&1].buffers(),
+        model.layers[&4].buffers()
+    );
+
+    // a passthrough mark on a non-identity convolution is rejected
+    for (_, values) in model.layers[&3].buffers_mut() {
+        values.iter_mut().for_each(|value| *value = 0.5);
+    }
+    assert!(model.fold_noops().is_err());
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use anyhow::Result;
+use darknet_config::{config::Activation, fusion::FusedOp, model::ModelBase};
+
+#[test]
+fn yolov4_fusion_plan() -> Result<()> {
+    let model =
+        ModelBase::from_config_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/yolov4.cfg"))?;
+    let plan = model.fusion_plan();
+
+    // the convolution right before the first shortcut absorbs the residual sum
+    let group = plan.group_of(7).unwrap();
+    assert_eq!(group.layers, [6, 7]);
+    assert_eq!(
+        group.ops,
+        [
+            FusedOp::Conv,
+            FusedOp::BatchNorm,
+            FusedOp::Activation {
+                activation: Activation::Mish
+            },
+            FusedOp::Add
+        ]
+    );
+    assert_eq!(group.pattern(), "conv+batch_norm+activation+add");
+
+    // every layer is fused at most once
+    let mut fused: Vec<_> = plan
+        .groups
+        .iter()
+        .flat_map(|group| group.layers.iter().cloned())
+        .collect();
+    let num_fused = fused.len();
+    fused.sort_unstable();
+    fused.dedup();
+    assert_eq!(fused.len(), num_fused);
+
+    Ok(())
+}
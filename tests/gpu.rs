@@ -0,0 +1,64 @@
+#![cfg(feature = "wgpu")]
+
+use anyhow::Result;
+use darknet_config::{gpu::STORAGE_OFFSET_ALIGNMENT, DarknetConfig, DarknetModel};
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+#[test]
+fn pack_gpu_weights() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+    model.layers[0]
+        .buffers_mut()
+        .into_iter()
+        .for_each(|(_, values)| values.fill(0.5));
+
+    let (bytes, tensors) = model.pack_gpu_weights();
+    let names: Vec<_> = tensors.iter().map(|tensor| tensor.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "biases",
+            "scales",
+            "rolling_mean",
+            "rolling_variance",
+            "weights"
+        ]
+    );
+    assert!(tensors
+        .iter()
+        .all(|tensor| tensor.offset % STORAGE_OFFSET_ALIGNMENT == 0));
+    assert_eq!(tensors[4].length, 54);
+    assert_eq!(bytes.len() as u64, 5 * STORAGE_OFFSET_ALIGNMENT);
+
+    let weights = &tensors[4];
+    let begin = weights.offset as usize;
+    let value = f32::from_ne_bytes([
+        bytes[begin],
+        bytes[begin + 1],
+        bytes[begin + 2],
+        bytes[begin + 3],
+    ]);
+    assert_eq!(value, 0.5);
+    Ok(())
+}
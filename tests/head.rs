@@ -0,0 +1,78 @@
+use anyhow::Result;
+use darknet_config::{
+    config::{LayerConfig, Shape},
+    head::HeadTemplate,
+    model::ModelBase,
+    DarknetConfig,
+};
+
+#[test]
+fn append_head() -> Result<()> {
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=2
+pad=1
+activation=leaky
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+"
+    .parse()?;
+
+    let template = HeadTemplate {
+        from_layer: 0,
+        hidden_filters: Some(32),
+        anchors: vec![(5, 6), (8, 9)],
+        classes: 1,
+    };
+    let extended = config.append_head(&template)?;
+    assert_eq!(extended.layers.len(), 7);
+    assert_eq!(
+        extended.layers[3..]
+            .iter()
+            .map(|layer| layer.kind())
+            .collect::<Vec<_>>(),
+        ["route", "conv", "conv", "yolo"]
+    );
+    match &extended.layers[6] {
+        LayerConfig::Yolo(yolo) => assert_eq!(yolo.anchors, [(5, 6), (8, 9)]),
+        _ => unreachable!(),
+    }
+
+    let model = ModelBase::from_config(&extended)?;
+    assert_eq!(model.layers[&5].output_shape(), Shape::Hwc([16, 16, 12]));
+    assert_eq!(model.heads().len(), 2);
+    extended.roundtrip_check()?;
+
+    // the classes are shared by the yolo layers
+    assert!(config
+        .append_head(&HeadTemplate {
+            classes: 2,
+            ..template.clone()
+        })
+        .is_err());
+    assert!(config
+        .append_head(&HeadTemplate {
+            from_layer: 9,
+            ..template
+        })
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use anyhow::Result;
+use darknet_config::DarknetConfig;
+
+#[test]
+fn load_dir() -> Result<()> {
+    let results: Vec<_> =
+        DarknetConfig::load_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests"))?.collect();
+
+    let names: Vec<_> = results
+        .iter()
+        .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(names.contains(&"enet-coco.cfg"));
+    assert!(names.contains(&"yolov7-tiny.cfg"));
+    assert!(results
+        .iter()
+        .filter(|(path, _)| path.ends_with("yolov7-tiny.cfg"))
+        .all(|(_, result)| result.is_ok()));
+
+    Ok(())
+}
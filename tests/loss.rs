@@ -0,0 +1,23 @@
+use anyhow::Result;
+use darknet_config::{config::IouLoss, model::ModelBase};
+
+#[test]
+fn yolo_loss_config() -> Result<()> {
+    let model = ModelBase::from_config_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let configs = model.yolo_loss_configs();
+    assert_eq!(configs.len(), 3);
+
+    let (_, loss) = configs.first().unwrap();
+    assert_eq!(loss.iou_loss, IouLoss::CIoU);
+    assert_eq!(loss.iou_normalizer.raw(), 0.05);
+    assert_eq!(loss.cls_normalizer.raw(), 0.5);
+    assert_eq!(loss.ignore_thresh.raw(), 0.7);
+    assert_eq!(loss.iou_thresh.raw(), 0.2);
+    assert_eq!(loss.max_delta.map(|max_delta| max_delta.raw()), Some(20.0));
+    assert!(loss.objectness_smooth && loss.new_coords);
+    assert_eq!(loss.class_targets(), [1.0, 0.0]);
+    Ok(())
+}
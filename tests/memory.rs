@@ -0,0 +1,27 @@
+use anyhow::Result;
+use darknet_config::{config::Shape, model::ModelBase};
+
+#[test]
+fn advise_batch() -> Result<()> {
+    let model = ModelBase::from_config_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let small = model.estimate_training_memory(Shape::Hwc([416, 416, 3]))?;
+    let large = model.estimate_training_memory(Shape::Hwc([608, 608, 3]))?;
+    assert_eq!(small.parameter_bytes, large.parameter_bytes);
+    assert!(small.activation_bytes_per_sample < large.activation_bytes_per_sample);
+
+    // enough memory for mini-batches of 16 images
+    let input_size = Shape::Hwc([416, 416, 3]);
+    let settings = model.advise_batch(input_size, small.mini_batch_bytes(16))?;
+    let subdivisions: Vec<_> = settings
+        .iter()
+        .map(|setting| setting.subdivisions)
+        .collect();
+    assert_eq!(subdivisions, [4, 8, 16, 32, 64]);
+    assert_eq!(settings[0].mini_batch(), 16);
+
+    assert!(model.advise_batch(input_size, 1 << 20)?.is_empty());
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use darknet_config::{
+    config::{DarknetConfig, LayerConfig},
+    migrate::{migrate_str, Dialect},
+};
+
+const YOLOV2_TINY_LIKE: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[conv]
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+# keep the resolution
+[max]
+size=2
+stride=1
+
+[maxpool]
+size=3
+stride=2
+padding=1
+
+[conv]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+#[test]
+fn yolov2_maxpool_padding() -> Result<()> {
+    let (text, report) = migrate_str(YOLOV2_TINY_LIKE, Dialect::YoloV2, Dialect::LATEST)?;
+    assert!(text.contains("# keep the resolution"));
+    assert_eq!(report.changes.len(), 5);
+
+    let config: DarknetConfig = text.parse()?;
+    let paddings: Vec<_> = config
+        .layers
+        .iter()
+        .filter_map(|layer| match layer {
+            LayerConfig::MaxPool(maxpool) => Some(maxpool.padding),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(paddings, [0, 2]);
+
+    // the latest dialect is left untouched
+    let (same, report) = migrate_str(&text, Dialect::LATEST, Dialect::LATEST)?;
+    assert_eq!(same, text);
+    assert!(report.changes.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn detect_dialect() {
+    assert_eq!(Dialect::detect(YOLOV2_TINY_LIKE), Dialect::YoloV3);
+    assert_eq!(
+        Dialect::detect("[net]\n\n[conv]\nfilters=125\n\n[region]\nclasses=20\n"),
+        Dialect::YoloV2
+    );
+}
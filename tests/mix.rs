@@ -0,0 +1,55 @@
+#![cfg(feature = "random")]
+
+use anyhow::Result;
+use darknet_config::{
+    dataset::LabelBox,
+    mix::{MixGenerator, MosaicGeometry},
+};
+use std::slice;
+
+#[test]
+fn mosaic_geometry() -> Result<()> {
+    let mut lhs = MixGenerator::new([416, 416], false, 3)?;
+    let mut rhs = MixGenerator::new([416, 416], false, 3)?;
+    for _ in 0..16 {
+        let mosaic = lhs.mosaic();
+        assert_eq!(mosaic, rhs.mosaic());
+        assert!(mosaic.cut.iter().all(|&cut| (83..=332).contains(&cut)));
+        let area: u64 = mosaic.tiles().iter().map(|tile| tile.w * tile.h).sum();
+        assert_eq!(area, 416 * 416);
+
+        let cutmix = lhs.cutmix();
+        assert_eq!(cutmix, rhs.cutmix());
+        assert!(cutmix.rect.x + cutmix.rect.w < 416);
+        let [beta, alpha] = cutmix.label_weights();
+        assert!((beta + alpha - 1.0).abs() < 1e-9);
+    }
+
+    // a box across the cut is kept by the top-left tile and clipped if bounded
+    let label = LabelBox {
+        class: 1,
+        x: 0.5,
+        y: 0.25,
+        w: 0.5,
+        h: 0.25,
+    };
+    let mut mosaic = MosaicGeometry {
+        input_size: [400, 400],
+        cut: [200, 200],
+        mosaic_bound: false,
+    };
+    assert_eq!(
+        mosaic.transform_labels(0, slice::from_ref(&label)),
+        slice::from_ref(&label)
+    );
+    assert!(mosaic
+        .transform_labels(2, slice::from_ref(&label))
+        .is_empty());
+
+    mosaic.mosaic_bound = true;
+    let clipped = mosaic.transform_labels(0, &[label]);
+    assert_eq!(clipped.len(), 1);
+    assert!((clipped[0].x - 0.375).abs() < 1e-9);
+    assert!((clipped[0].w - 0.25).abs() < 1e-9);
+    Ok(())
+}
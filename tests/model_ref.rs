@@ -0,0 +1,77 @@
+use anyhow::Result;
+use darknet_config::{
+    config::{DarknetConfig, Shape},
+    model_ref::ModelRef,
+};
+use std::{fs, thread};
+
+#[test]
+fn model_ref() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+
+    let dir = std::env::temp_dir().join(format!("darknet-config-model-ref-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+
+    // version 0.2.0, seen = 64, then 18 biases and 18x3 weights
+    let weights_file = dir.join("model.weights");
+    let mut bytes = vec![];
+    [0u32, 2, 0]
+        .iter()
+        .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    bytes.extend_from_slice(&64u64.to_le_bytes());
+    (0..72).for_each(|value| bytes.extend_from_slice(&(value as f32).to_le_bytes()));
+    fs::write(&weights_file, &bytes)?;
+
+    let model = ModelRef::with_weights(config, &weights_file)?;
+    assert!(!model.weights_loaded());
+
+    // all threads observe the same lazily loaded weights
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let model = model.clone();
+            thread::spawn(move || model.weights().map(|weights| weights.base.seen))
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap()?, 64);
+    }
+    assert!(model.weights_loaded());
+    assert!(std::sync::Arc::ptr_eq(
+        &model.weights()?,
+        &model.clone().weights()?
+    ));
+
+    let stats = model.weights_stats()?;
+    assert_eq!(stats[0].as_ref().unwrap().num_values, 72);
+    assert!(stats[1].is_none());
+
+    let shapes = model.shapes(Shape::Hwc([64, 64, 3]))?;
+    assert_eq!(shapes[&1].1, Shape::Hwc([64, 64, 18]));
+    assert!(std::sync::Arc::ptr_eq(
+        &shapes,
+        &model.shapes(Shape::Hwc([64, 64, 3]))?
+    ));
+    assert_eq!(model.summary().layers.len(), 2);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use darknet_config::{export, model::ModelBase, DarknetConfig};
+
+#[test]
+fn onnx_coverage() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=16
+size=3
+stride=1
+pad=1
+activation=mish
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=stair
+
+[shortcut]
+from=-2
+activation=linear
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+    let model = ModelBase::from_config(&config)?;
+    let coverage = export::coverage(&model);
+
+    assert_eq!(coverage.layers.len(), 5);
+    let ops: Vec<_> = coverage.layers[0]
+        .ops
+        .iter()
+        .map(|op| op.op_type.as_str())
+        .collect();
+    assert_eq!(ops, ["Conv", "BatchNormalization", "Mish"]);
+    assert_eq!(coverage.min_opset(), 18);
+
+    assert!(!coverage.is_supported());
+    let unsupported = coverage.unsupported_layers();
+    assert_eq!(unsupported.len(), 1);
+    assert_eq!(unsupported[0].layer_index, 1);
+    assert!(coverage.to_string().contains("stair"));
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use anyhow::Result;
+use darknet_config::{
+    config::Shape,
+    parity::{ParityFixture, ParityStatus, Tolerance},
+};
+
+fn num_values(shape: Shape) -> u64 {
+    match shape {
+        Shape::Hwc([h, w, c]) => h * w * c,
+        Shape::Flat(len) => len,
+    }
+}
+
+#[test]
+fn builtin_fixtures() -> Result<()> {
+    for fixture in ParityFixture::builtin() {
+        // the weights fill the model exactly and the outputs match the shapes
+        let model = fixture.model()?;
+        assert_eq!(
+            fixture.input.len() as u64,
+            num_values(fixture.parse_config()?.net.input_size),
+            "{}",
+            fixture.name
+        );
+        for (layer_index, output) in &fixture.outputs {
+            let shape = model.base.layers[layer_index].output_shape();
+            assert_eq!(output.len() as u64, num_values(shape), "{}", fixture.name);
+        }
+
+        assert_eq!(ParityFixture::from_json(&fixture.to_json()?)?, fixture);
+    }
+    Ok(())
+}
+
+// the tch backend runs every builtin fixture, yolo layers are skipped since
+// they return decoded boxes instead of the activated head
+#[cfg(feature = "with-tch")]
+#[test]
+fn tch_forward() -> Result<()> {
+    use darknet_config::{
+        model::{LayerPosition, LayerPositionSet},
+        torch::{LayerOutputKind, TchModel, TensorList},
+    };
+    use indexmap::IndexMap;
+    use std::collections::HashMap;
+    use tch::{nn, Device, Tensor};
+
+    for fixture in ParityFixture::builtin() {
+        let vs = nn::VarStore::new(Device::Cpu);
+        let mut model = TchModel::from_darknet_model(&vs.root(), &fixture.model()?)?;
+        let [h, w, c] = match model.base.net.input_size {
+            Shape::Hwc(hwc) => hwc,
+            Shape::Flat(_) => unreachable!(),
+        };
+        let input = Tensor::of_slice(&fixture.input).view([1, c as i64, h as i64, w as i64]);
+
+        let mut tensors: HashMap<_, _> = vec![(LayerPosition::Input, input)].into_iter().collect();
+        let mut outputs = IndexMap::new();
+        for (&layer_index, layer) in model.layers.iter_mut() {
+            let input = match layer.from_indexes() {
+                LayerPositionSet::Single(index) => {
+                    TensorList::Single(tensors[&index].shallow_clone())
+                }
+                LayerPositionSet::Multiple(indexes) => TensorList::Multiple(
+                    indexes
+                        .iter()
+                        .map(|index| tensors[index].shallow_clone())
+                        .collect(),
+                ),
+                LayerPositionSet::Empty => TensorList::Multiple(vec![]),
+            };
+            if let LayerOutputKind::Tensor(output) = layer.forward_t(input, false) {
+                outputs.insert(layer_index, Vec::<f32>::from(&output.flatten(0, -1)));
+                tensors.insert(LayerPosition::Absolute(layer_index), output);
+            }
+        }
+
+        let mut expected = fixture.clone();
+        expected
+            .outputs
+            .retain(|layer_index, _| outputs.contains_key(layer_index));
+        expected
+            .verify(&outputs, Tolerance::default())
+            .ensure_passed()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn parity_mismatch() -> Result<()> {
+    let fixture = &ParityFixture::builtin()[1];
+    let mut outputs = fixture.outputs.clone();
+    outputs[&1][3] += 1e-3;
+    outputs[&2].pop();
+    outputs.remove(&3);
+
+    let report = fixture.verify(&outputs, Tolerance::default());
+    let statuses: Vec<_> = report.layers.iter().map(|layer| &layer.status).collect();
+    assert!(matches!(statuses[0], ParityStatus::Passed { .. }));
+    assert!(matches!(
+        statuses[1],
+        ParityStatus::Mismatch {
+            position: 3,
+            count: 1,
+            ..
+        }
+    ));
+    assert_eq!(
+        *statuses[2],
+        ParityStatus::WrongLength {
+            expected: 8,
+            actual: 7
+        }
+    );
+    assert_eq!(*statuses[3], ParityStatus::Missing);
+
+    let err = report.ensure_passed().unwrap_err().to_string();
+    assert!(err.starts_with("fixture batchnorm_shortcut_route: layer 1:"));
+
+    // a looser tolerance accepts the drift
+    let tolerance = Tolerance {
+        abs: 1e-2,
+        rel: 0.0,
+    };
+    assert!(matches!(
+        fixture.verify(&outputs, tolerance).layers[1].status,
+        ParityStatus::Passed { .. }
+    ));
+    Ok(())
+}
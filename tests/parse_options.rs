@@ -0,0 +1,253 @@
+use anyhow::Result;
+use darknet_config::config::{
+    AugmentationMix, DarknetConfig, KeySource, LayerConfig, MixUp, NormalizationKind, ParseOptions,
+    UnknownKeys,
+};
+
+#[test]
+fn unknown_keys() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+ema_alpha=0.9995
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+bogus=1
+x_note=kept
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    // unknown keys are ignored by default
+    let config = DarknetConfig::parse_with_options(text, &ParseOptions::default())?;
+    assert_eq!(config, text.parse()?);
+
+    // the fork key in [net] is allowed, the bogus key is not
+    let err = DarknetConfig::parse_with_options(text, &ParseOptions::strict())
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "section 1 [convolutional]: unknown keys bogus");
+
+    let mut options = ParseOptions::strict();
+    options
+        .section_unknown_keys
+        .insert("convolutional".into(), UnknownKeys::Warn);
+    DarknetConfig::parse_with_options(text, &options)?;
+
+    let mut options = ParseOptions::strict();
+    options.allowed_keys.insert("convolutional.bogus".into());
+    DarknetConfig::parse_with_options(text, &options)?;
+
+    let mut options = ParseOptions::strict();
+    options.allowed_keys.clear();
+    let err = DarknetConfig::parse_with_options(text, &options)
+        .unwrap_err()
+        .to_string();
+    assert!(err.starts_with("section 0 [net]: unknown keys ema_alpha;"));
+    Ok(())
+}
+
+#[test]
+fn net_provenance() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+burn_in=0
+ema_alpha=0.9995
+
+[convolutional]
+filters=8
+size=1
+stride=1
+pad=1
+activation=linear
+";
+    let config: DarknetConfig = text.parse()?;
+    let provenance = config.net.provenance();
+    assert_eq!(config.net.burn_in, 0);
+    assert_eq!(provenance.source("burn_in"), KeySource::File);
+    assert_eq!(provenance.source("max_batches"), KeySource::Default);
+    // unknown keys are not options
+    assert_eq!(
+        provenance.specified_keys().collect::<Vec<_>>(),
+        ["width", "height", "channels", "burn_in"]
+    );
+    assert!(provenance.defaulted_keys().contains(&"learning_rate"));
+
+    // saved configs write every key, and the provenance is ignored by comparisons
+    let saved: DarknetConfig = config.to_cfg_string()?.parse()?;
+    assert_eq!(saved, config);
+    assert!(saved.net.provenance().is_specified("max_batches"));
+    Ok(())
+}
+
+#[test]
+fn misspelled_group_id() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=8
+size=1
+stride=1
+pad=1
+activation=linear
+
+[route]
+layers=-1
+groups=2
+groupd_id=1
+";
+    let config = DarknetConfig::parse_with_options(text, &ParseOptions::strict())?;
+    match &config.layers[1] {
+        LayerConfig::Route(route) => assert_eq!(route.group.group_id(), 1),
+        _ => unreachable!(),
+    }
+
+    // the correct spelling is written back
+    let saved = config.to_cfg_string()?;
+    assert!(saved.contains("group_id=1") && !saved.contains("groupd_id"));
+    assert_eq!(saved.parse::<DarknetConfig>()?, config);
+    Ok(())
+}
+
+#[test]
+fn augmentation_mix() -> Result<()> {
+    let config_with = |net_keys: &str| -> Result<DarknetConfig> {
+        let text = format!(
+            "\
+[net]
+width=32
+height=32
+channels=3
+{}
+
+[convolutional]
+filters=8
+size=1
+stride=1
+pad=1
+activation=linear
+",
+            net_keys
+        );
+        DarknetConfig::parse_with_options(&text, &ParseOptions::strict())
+    };
+
+    assert_eq!(config_with("")?.net.augmentation_mix, AugmentationMix::None);
+    assert_eq!(
+        config_with("mixup=1")?.net.augmentation_mix,
+        AugmentationMix::MixUp
+    );
+    assert_eq!(
+        config_with("mixup=4")?.net.augmentation_mix,
+        AugmentationMix::CutMixMosaic
+    );
+    assert_eq!(
+        config_with("mixup=1\nmosaic=1")?.net.augmentation_mix,
+        AugmentationMix::Mosaic
+    );
+
+    // the misspelled key is accepted and written back as cutmix
+    let config = config_with("cutmux=1\nmosaic=1")?;
+    assert_eq!(config.net.augmentation_mix, AugmentationMix::CutMixMosaic);
+    let saved = config.to_cfg_string()?;
+    assert!(saved.contains("cutmix=1") && !saved.contains("cutmux"));
+    assert_eq!(saved.parse::<DarknetConfig>()?, config);
+
+    assert_eq!(
+        AugmentationMix::CutMix.to_keys(),
+        (MixUp::None, true, false)
+    );
+    Ok(())
+}
+
+#[test]
+fn parse_report() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+cutmux=1
+
+[convolutional]
+filters=18
+size=3
+stride=1
+pad=1
+padding=0
+activation=linear
+
+[route]
+layers=-1
+groups=2
+groupd_id=1
+
+[yolo]
+mask=0
+anchors=10,13
+num=1
+";
+    let (config, report) = DarknetConfig::parse_with_report(text, &ParseOptions::strict())?;
+    assert_eq!(config, text.parse::<DarknetConfig>()?);
+
+    let changes: Vec<_> = report
+        .normalizations
+        .iter()
+        .map(|change| (change.section_index, change.kind.clone()))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (
+                0,
+                NormalizationKind::KeyAlias {
+                    key: "cutmux".into(),
+                    canonical: "cutmix".into()
+                }
+            ),
+            (
+                1,
+                NormalizationKind::IgnoredKey {
+                    key: "padding".into(),
+                    reason: "pad=1 sets the padding to size / 2".into()
+                }
+            ),
+            (
+                2,
+                NormalizationKind::KeyAlias {
+                    key: "groupd_id".into(),
+                    canonical: "group_id".into()
+                }
+            ),
+            (
+                3,
+                NormalizationKind::DefaultedKey {
+                    key: "classes".into(),
+                    value: "20".into()
+                }
+            ),
+        ]
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()?)?;
+    assert_eq!(json["normalizations"][0]["kind"], "key_alias");
+    assert_eq!(json["normalizations"][0]["section"], "net");
+    Ok(())
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "random")]
+
+use anyhow::Result;
+use darknet_config::{
+    darknet::{ConvolutionalLayer, ConvolutionalWeights, Layer},
+    perturb::{Noise, NoiseSpec},
+    DarknetConfig, DarknetModel,
+};
+
+fn conv_weights(model: &DarknetModel, layer_index: usize) -> Vec<f32> {
+    match &model.layers[&layer_index] {
+        Layer::Convolutional(ConvolutionalLayer {
+            weights: ConvolutionalWeights::Owned { weights, .. },
+            ..
+        }) => weights.iter().cloned().collect(),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn perturb_weights() -> Result<()> {
+    let config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let model = DarknetModel::from_config(&config)?;
+
+    let mut spec = NoiseSpec::new(Noise::Gaussian { std: 0.1 });
+    spec.kinds = vec!["conv".into()];
+
+    let lhs = model.perturb(&spec, 7)?;
+    let rhs = model.perturb(&spec, 7)?;
+    let other = model.perturb(&spec, 8)?;
+    assert_eq!(conv_weights(&lhs, 0), conv_weights(&rhs, 0));
+    assert_ne!(conv_weights(&lhs, 0), conv_weights(&other, 0));
+    assert!(conv_weights(&lhs, 0).iter().any(|&value| value != 0.0));
+
+    // other kinds are left intact
+    spec.kinds = vec!["connected".into()];
+    let untouched = model.perturb(&spec, 7)?;
+    assert_eq!(conv_weights(&untouched, 0), conv_weights(&model, 0));
+
+    assert!(model
+        .perturb(
+            &NoiseSpec::new(Noise::Uniform {
+                low: 1.0,
+                high: 0.0
+            }),
+            7
+        )
+        .is_err());
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "image")]
+
+use anyhow::Result;
+use darknet_config::{config::Shape, preprocess::preprocess, tta::letterbox_size};
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+
+fn gradient(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        Rgb([(x * 16) as u8, (y * 16) as u8, 255])
+    }))
+}
+
+#[test]
+fn resize() -> Result<()> {
+    let image = gradient(8, 6);
+
+    // the corners are kept by the bilinear interpolation
+    let data = preprocess(&image, Shape::Hwc([3, 4, 3]), false)?;
+    assert_eq!(data.len(), 3 * 4 * 3);
+    assert_eq!(data[0], 0.0);
+    assert_eq!(data[3], 7.0 * 16.0 / 255.0);
+    assert_eq!(data[12 + 11], 5.0 * 16.0 / 255.0);
+    assert!(data[24..].iter().all(|&value| (value - 1.0).abs() < 1e-6));
+
+    // a destination dimension of one does not divide by zero
+    let data = preprocess(&image, Shape::Hwc([1, 4, 3]), false)?;
+    assert_eq!(data.len(), 4 * 3);
+    assert!(data.iter().all(|value| value.is_finite()));
+    let data = preprocess(&image, Shape::Hwc([6, 1, 3]), false)?;
+    assert_eq!(data.len(), 6 * 3);
+    assert!(data.iter().all(|value| value.is_finite()));
+    let data = preprocess(&image, Shape::Hwc([1, 1, 3]), false)?;
+    assert_eq!(data.len(), 3);
+    assert!(data.iter().all(|value| value.is_finite()));
+
+    // and an empty destination yields no values
+    assert!(preprocess(&image, Shape::Hwc([0, 4, 3]), false)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn letterbox() -> Result<()> {
+    assert_eq!(letterbox_size(640, 480, 416, 416), (416, 312));
+    assert_eq!(letterbox_size(480, 640, 416, 416), (312, 416));
+
+    // the shorter side is at least one pixel for extreme aspect ratios
+    assert_eq!(letterbox_size(10000, 1, 416, 416), (416, 1));
+    assert_eq!(letterbox_size(1, 10000, 416, 416), (1, 416));
+
+    let image = DynamicImage::ImageLuma8(GrayImage::from_pixel(10000, 1, Luma([255])));
+    let data = preprocess(&image, Shape::Hwc([32, 32, 1]), true)?;
+    assert_eq!(data.len(), 32 * 32);
+    assert!(data.iter().all(|value| value.is_finite()));
+    // the image fills one row and the rest is padded with gray
+    assert_eq!(
+        data.iter()
+            .filter(|&&value| (value - 1.0).abs() < 1e-6)
+            .count(),
+        32
+    );
+    assert_eq!(data.iter().filter(|&&value| value == 0.5).count(), 31 * 32);
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use darknet_config::{
+    export::safetensors::to_safetensors_with_progress,
+    progress::{ProgressObserver, Stage},
+    validate::validate_with_progress,
+    DarknetConfig, DarknetModel,
+};
+use std::fs;
+
+#[derive(Debug, Default)]
+struct Recorder {
+    events: Vec<(Stage, Option<usize>, u64)>,
+}
+
+impl ProgressObserver for Recorder {
+    fn stage_finished(&mut self, stage: Stage, num_bytes: u64) {
+        self.events.push((stage, None, num_bytes));
+    }
+
+    fn layer_finished(&mut self, stage: Stage, layer_index: usize, num_bytes: u64) {
+        self.events.push((stage, Some(layer_index), num_bytes));
+    }
+}
+
+#[test]
+fn progress_events() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let mut recorder = Recorder::default();
+    let config = DarknetConfig::parse_with_progress(text, &mut recorder)?;
+    assert!(validate_with_progress(&config, &mut recorder).is_empty());
+
+    // version 0.2.0, seen = 0, then 18 biases and 18x3 weights
+    let weights_file = std::env::temp_dir().join(format!(
+        "darknet-config-progress-{}.weights",
+        std::process::id()
+    ));
+    let mut bytes = vec![];
+    [0u32, 2, 0]
+        .iter()
+        .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend(vec![0u8; 72 * 4]);
+    fs::write(&weights_file, &bytes)?;
+
+    let mut model = DarknetModel::from_config(&config)?;
+    let result = model.load_weights_with_progress(&weights_file, &mut recorder);
+    fs::remove_file(&weights_file)?;
+    result?;
+
+    let exported = to_safetensors_with_progress(&model, &mut recorder)?;
+
+    assert_eq!(recorder.events[0], (Stage::Parse, None, text.len() as u64));
+    assert_eq!(
+        &recorder.events[1..],
+        &[
+            (Stage::Validate, Some(0), 0),
+            (Stage::Validate, Some(1), 0),
+            (Stage::Validate, None, 0),
+            (Stage::LoadWeights, Some(0), 72 * 4),
+            (Stage::LoadWeights, Some(1), 0),
+            (Stage::LoadWeights, None, bytes.len() as u64),
+            (Stage::Export, Some(0), 72 * 4),
+            (Stage::Export, Some(1), 0),
+            (Stage::Export, None, exported.len() as u64),
+        ][..]
+    );
+
+    Ok(())
+}
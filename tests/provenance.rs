@@ -0,0 +1,93 @@
+#![cfg(feature = "manifest")]
+
+use anyhow::Result;
+use darknet_config::{
+    provenance::{sidecar_path, Transform, WeightsProvenance},
+    DarknetConfig, DarknetModel,
+};
+use std::fs;
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+#[test]
+fn weights_provenance() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+    model
+        .layers
+        .values_mut()
+        .flat_map(|layer| layer.buffers_mut())
+        .for_each(|(_, values)| values.fill(0.5));
+
+    let dir =
+        std::env::temp_dir().join(format!("darknet-config-provenance-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let parent_file = dir.join("parent.weights");
+    let weights_file = dir.join("child.weights");
+    model.save_weights(&parent_file)?;
+
+    let provenance = WeightsProvenance::new(&config)?
+        .with_transform(Transform::FusedBatchNorm { layers: vec![0] })
+        .with_parent(&parent_file)?;
+    let saved = model.save_weights_with_provenance(&weights_file, provenance)?;
+    assert_eq!(
+        sidecar_path(&weights_file),
+        dir.join("child.weights.provenance.json")
+    );
+    assert_eq!(WeightsProvenance::find(&weights_file)?, Some(saved.clone()));
+    assert!(WeightsProvenance::find(&parent_file)?.is_none());
+    assert!(saved.weights_checksum.is_some());
+    assert_eq!(saved.parent_checksum, saved.weights_checksum);
+
+    // the sidecar is verified on load
+    let mut loaded = DarknetModel::from_config(&config)?;
+    loaded.load_weights(&weights_file)?;
+    assert_eq!(loaded.layers[&0].buffers(), model.layers[&0].buffers());
+
+    // a different config
+    let other: DarknetConfig = CONFIG
+        .replace("activation=leaky", "activation=mish")
+        .parse()?;
+    let mut loaded = DarknetModel::from_config(&other)?;
+    let err = loaded.load_weights(&weights_file).unwrap_err().to_string();
+    assert!(err.contains("config fingerprint mismatch"), "{}", err);
+
+    // the weights file is overwritten without updating the sidecar
+    model.layers[&1]
+        .buffers_mut()
+        .into_iter()
+        .for_each(|(_, values)| values.fill(0.0));
+    model.save_weights(&weights_file)?;
+    let mut loaded = DarknetModel::from_config(&config)?;
+    let err = loaded.load_weights(&weights_file).unwrap_err().to_string();
+    assert!(err.contains("weights checksum mismatch"), "{}", err);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
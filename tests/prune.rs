@@ -0,0 +1,69 @@
+use anyhow::Result;
+use darknet_config::{
+    config::{DarknetConfig, LayerConfig, LayerIndex},
+    model::ModelBase,
+};
+
+#[test]
+fn prune_dead_branch() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+# left over from an edit
+[convolutional]
+filters=8
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[route]
+layers=-2
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.unreachable_layers(), [1]);
+    assert!(config
+        .validate()
+        .iter()
+        .any(|diagnostic| diagnostic.layer_index == Some(1)));
+
+    let pruned = config.prune_unreachable()?;
+    assert_eq!(pruned.removed, [1]);
+    assert_eq!(pruned.config.layers.len(), 4);
+    match &pruned.config.layers[1] {
+        LayerConfig::Route(route) => {
+            let layers: Vec<_> = route.layers.iter().cloned().collect();
+            assert_eq!(layers, [LayerIndex::from(-1)]);
+        }
+        _ => panic!("the route layer is expected at index 1"),
+    }
+
+    let model = ModelBase::from_config(&pruned.config)?;
+    assert!(model.unreachable_layers().is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use anyhow::Result;
+use darknet_config::{
+    config::{Activation, DarknetConfig, LayerConfig},
+    query::{CompareOp, Query},
+};
+
+const CONFIG: &str = "\
+[net]
+width=64
+height=64
+channels=3
+
+[convolutional]
+filters=32
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+filters=1024
+size=3
+stride=2
+pad=1
+activation=leaky
+
+[maxpool]
+size=2
+stride=2
+
+[convolutional]
+batch_normalize=1
+filters=512
+size=1
+stride=1
+pad=1
+activation=leaky
+";
+
+#[test]
+fn query_set() -> Result<()> {
+    let mut config: DarknetConfig = CONFIG.parse()?;
+    let query: Query = "layer[type=convolutional][filters>=512].set(activation, mish)".parse()?;
+
+    // the dry run reports the edit only
+    let report = query.dry_run(&config)?;
+    assert_eq!(report.matched, vec![1, 3]);
+    assert_eq!(report.changes.len(), 2);
+    assert_eq!(report.changes[0].old_value.as_deref(), Some("leaky"));
+    assert_eq!(report.changes[0].new_value.as_deref(), Some("mish"));
+    assert_eq!(config, CONFIG.parse()?);
+
+    assert_eq!(query.apply(&mut config)?, report);
+    let activations: Vec<_> = config
+        .layers
+        .iter()
+        .filter_map(|layer| match layer {
+            LayerConfig::Convolutional(conv) => Some(conv.activation),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        activations,
+        vec![Activation::Leaky, Activation::Mish, Activation::Mish]
+    );
+
+    // the builder produces the same query
+    let built = Query::new()
+        .filter("type", CompareOp::Eq, "convolutional")
+        .filter("filters", CompareOp::Ge, 512)
+        .set("activation", "mish");
+    assert_eq!(built, query);
+    Ok(())
+}
+
+#[test]
+fn query_errors() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+
+    // keys unknown to the section are rejected
+    let query: Query = "layer[type=maxpool].set(filters, 3)".parse()?;
+    assert!(query.dry_run(&config).is_err());
+
+    // no layer matches
+    let query: Query = "layer[index>10].set(activation, mish)".parse()?;
+    assert!(query.dry_run(&config)?.matched.is_empty());
+
+    assert!("layer[filters].set(activation, mish)"
+        .parse::<Query>()
+        .is_err());
+    assert!("layer[type=convolutional]".parse::<Query>().is_err());
+    assert!("net.set(width, 32)".parse::<Query>().is_err());
+    Ok(())
+}
+
+#[test]
+fn query_set_list() -> Result<()> {
+    let mut config: DarknetConfig = "\
+[net]
+width=64
+height=64
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+"
+    .parse()?;
+
+    let query: Query = "layer[type=yolo].set(anchors, 12,16, 19,36, 40,28)".parse()?;
+    assert_eq!(
+        query,
+        Query::new()
+            .filter("type", CompareOp::Eq, "yolo")
+            .set("anchors", "12,16, 19,36, 40,28")
+    );
+    query.apply(&mut config)?;
+    match &config.layers[1] {
+        LayerConfig::Yolo(yolo) => assert_eq!(yolo.anchors, [(12, 16), (19, 36), (40, 28)]),
+        _ => unreachable!(),
+    }
+
+    assert!("layer[type=yolo].set(anchors)".parse::<Query>().is_err());
+    assert!("layer[type=yolo].unset(mask, anchors)"
+        .parse::<Query>()
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use darknet_config::{
+    darknet::DarknetModel,
+    export::{
+        registry::{self, Exporter, ExporterRegistry},
+        ExportOptions, UnsupportedLayer,
+    },
+    model::{LayerBase, ModelBase},
+    DarknetConfig,
+};
+use std::{io::Write, sync::Arc};
+
+// writes the layer kinds, one per line
+struct KindsExporter;
+
+impl Exporter for KindsExporter {
+    fn name(&self) -> &str {
+        "kinds"
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
+    fn unsupported_layers(&self, model: &ModelBase) -> Vec<UnsupportedLayer> {
+        model
+            .layers
+            .iter()
+            .filter(|(_, layer)| matches!(layer, LayerBase::MaxPool(_)))
+            .map(|(&layer_index, layer)| UnsupportedLayer {
+                layer_index,
+                kind: layer.kind().into(),
+                reason: "maxpool is not supported".into(),
+            })
+            .collect()
+    }
+
+    fn export(
+        &self,
+        model: &DarknetModel,
+        _options: &ExportOptions,
+        sink: &mut dyn Write,
+    ) -> Result<()> {
+        for layer in model.base.layers.values() {
+            writeln!(sink, "{}", layer.kind())?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn exporter_registry() -> Result<()> {
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+"
+    .parse()?;
+    let model = DarknetModel::from_config(&config)?;
+
+    assert!(registry::exporter_names().contains(&"safetensors".to_owned()));
+    registry::register_exporter(Arc::new(KindsExporter))?;
+    assert!(registry::register_exporter(Arc::new(KindsExporter)).is_err());
+    assert_eq!(registry::exporter("kinds").unwrap().extension(), "txt");
+
+    let mut output = vec![];
+    registry::export("kinds", &model, &ExportOptions::default(), &mut output)?;
+    assert_eq!(String::from_utf8(output)?, "conv\nyolo\n");
+
+    let mut output = vec![];
+    registry::export(
+        "safetensors",
+        &model,
+        &ExportOptions::default(),
+        &mut output,
+    )?;
+    assert!(!output.is_empty());
+    assert!(registry::export("onnx", &model, &ExportOptions::default(), &mut vec![]).is_err());
+
+    // a local registry only has what is registered into it
+    let mut local = ExporterRegistry::new();
+    assert!(local.names().is_empty());
+    local.register(Arc::new(KindsExporter))?;
+    assert_eq!(local.names(), ["kinds"]);
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use darknet_config::{
+    config::{Activation, DarknetConfig},
+    model::ModelBase,
+    reid::EmbeddingNormalization,
+};
+
+fn config_text(embedding_layer: isize) -> String {
+    format!(
+        "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=24
+size=1
+stride=1
+pad=1
+activation=linear
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+embedding_layer={}
+sim_thresh=0.8
+track_ciou_norm=0.3
+",
+        embedding_layer
+    )
+}
+
+#[test]
+fn reid_config() -> Result<()> {
+    let config: DarknetConfig = config_text(-2).parse()?;
+    let model = ModelBase::from_config(&config)?;
+    let reid = model.reid_configs()?;
+    assert_eq!(reid.len(), 1);
+
+    let reid = &reid[0];
+    assert_eq!(reid.yolo_index, 2);
+    assert_eq!(reid.embedding_index, 0);
+    assert_eq!(reid.output_shape, [32, 32, 24]);
+    assert_eq!(reid.embedding_size, 8);
+    assert_eq!(reid.activation, Some(Activation::Linear));
+    assert_eq!(reid.normalization, EmbeddingNormalization::L2);
+    assert!((reid.sim_thresh - 0.8).abs() < 1e-9);
+    assert!((reid.track_ciou_norm - 0.3).abs() < 1e-9);
+
+    // the embedding layer must come before the yolo layer
+    let config: DarknetConfig = config_text(2).parse()?;
+    let model = ModelBase::from_config(&config)?;
+    assert!(model.reid_configs().is_err());
+    Ok(())
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "random")]
+
+use anyhow::Result;
+use darknet_config::{
+    darknet::{ConvolutionalLayer, ConvolutionalWeights, Layer},
+    weights_layout::WeightsLayout,
+    DarknetConfig, DarknetModel,
+};
+use std::fs;
+
+fn config_text(classes: u64) -> String {
+    format!(
+        "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=8
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+filters={}
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes={}
+num=3
+",
+        3 * (classes + 5),
+        classes
+    )
+}
+
+fn conv_biases(model: &DarknetModel, layer_index: usize) -> Vec<f32> {
+    match &model.layers[&layer_index] {
+        Layer::Convolutional(ConvolutionalLayer {
+            weights: ConvolutionalWeights::Owned { biases, .. },
+            ..
+        }) => biases.iter().cloned().collect(),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn reinit_heads() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("darknet-config-reinit-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+
+    // a checkpoint for 5 classes filled with 0.5
+    let source_config: DarknetConfig = config_text(5).parse()?;
+    let num_values = (WeightsLayout::describe(&source_config)?.file_size() - 20) / 4;
+    let mut bytes = vec![];
+    [0u32, 2, 0]
+        .iter()
+        .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    bytes.extend_from_slice(&64u64.to_le_bytes());
+    (0..num_values).for_each(|_| bytes.extend_from_slice(&0.5f32.to_le_bytes()));
+    let weights_file = dir.join("source.weights");
+    fs::write(&weights_file, &bytes)?;
+
+    let config: DarknetConfig = config_text(2).parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+    let report = model.load_weights_reinit_heads(&weights_file, 1)?;
+    assert_eq!(report.source_classes, 5);
+    assert_eq!(report.reinitialized, [1]);
+    assert_eq!(model.base.seen, 64);
+    assert!(conv_biases(&model, 0).iter().all(|&value| value == 0.5));
+    assert!(conv_biases(&model, 1).iter().all(|&value| value == 0.0));
+
+    // a matching checkpoint is loaded as is
+    let mut model = DarknetModel::from_config(&source_config)?;
+    let report = model.load_weights_reinit_heads(&weights_file, 1)?;
+    assert!(report.reinitialized.is_empty());
+    assert!(conv_biases(&model, 1).iter().all(|&value| value == 0.5));
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use darknet_config::{
+    darknet::Layer,
+    repack::{LayoutTag, Weights},
+    DarknetConfig, DarknetModel,
+};
+
+#[test]
+fn repack_weights() -> Result<()> {
+    // [O, I, H, W] = [3, 2, 2, 1], the value encodes the coordinates
+    let dims = [3, 2, 2, 1];
+    let data: Vec<f32> = (0..3)
+        .flat_map(|o| (0..2).flat_map(move |i| (0..2).map(move |h| (o * 100 + i * 10 + h) as f32)))
+        .collect();
+    let oihw = Weights::new(LayoutTag::Oihw, dims, data)?;
+
+    // HWIO puts the output channels innermost
+    let hwio = oihw.repack(LayoutTag::Hwio)?;
+    assert_eq!(&hwio.data[..6], &[0.0, 100.0, 200.0, 10.0, 110.0, 210.0]);
+    assert_eq!(hwio.repack(LayoutTag::Oihw)?, oihw);
+
+    // NCHW to NHWC puts the channels innermost
+    let nhwc = Weights::new(LayoutTag::Nchw, dims, oihw.data.clone())?.repack(LayoutTag::Nhwc)?;
+    assert_eq!(&nhwc.data[..4], &[0.0, 10.0, 1.0, 11.0]);
+
+    // panels of 2 rows, the second one padded
+    let tiled = oihw.repack(LayoutTag::Tiled { tile: 2 })?;
+    assert_eq!(tiled.data.len(), 16);
+    assert_eq!(&tiled.data[..4], &[0.0, 100.0, 1.0, 101.0]);
+    assert_eq!(&tiled.data[8..12], &[200.0, 0.0, 201.0, 0.0]);
+    assert_eq!(tiled.repack(LayoutTag::Oihw)?, oihw);
+
+    assert!(Weights::new(LayoutTag::Oihw, dims, vec![0.0; 5]).is_err());
+    Ok(())
+}
+
+#[test]
+fn conv_weights_tensor() -> Result<()> {
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=3
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+"
+    .parse()?;
+    let model = DarknetModel::from_config(&config)?;
+    let weights = match &model.layers[&0] {
+        Layer::Convolutional(conv) => conv.weights_tensor().unwrap(),
+        _ => unreachable!(),
+    };
+    assert_eq!(weights.layout, LayoutTag::Oihw);
+    assert_eq!(weights.dims, [18, 3, 3, 3]);
+    Ok(())
+}
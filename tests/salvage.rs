@@ -0,0 +1,84 @@
+use anyhow::Result;
+use darknet_config::{
+    salvage::LayerLoadStatus, weights_layout::WeightsLayout, DarknetConfig, DarknetModel,
+};
+use std::fs;
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=8
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+#[test]
+fn salvage_weights() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("darknet-config-salvage-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let config: DarknetConfig = CONFIG.parse()?;
+    let layout = WeightsLayout::describe(&config)?;
+    let layer1_offset = layout.layer_records(1).next().unwrap().offset;
+
+    let mut bytes = vec![];
+    [0u32, 2, 0]
+        .iter()
+        .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    bytes.extend_from_slice(&64u64.to_le_bytes());
+    (0..((layout.file_size() - 20) / 4))
+        .for_each(|_| bytes.extend_from_slice(&0.5f32.to_le_bytes()));
+
+    // the file ends in the middle of the second convolution
+    let weights_file = dir.join("truncated.weights");
+    fs::write(&weights_file, &bytes[..(layer1_offset as usize + 10)])?;
+    let mut model = DarknetModel::from_config(&config)?;
+    let report = model.load_weights_salvage(&weights_file)?;
+    assert_eq!(report.seen, 64);
+    assert_eq!(report.first_bad_offset, Some(layer1_offset));
+    assert_eq!(
+        report.status.values().cloned().collect::<Vec<_>>(),
+        [
+            LayerLoadStatus::Loaded,
+            LayerLoadStatus::Truncated,
+            LayerLoadStatus::NoWeights
+        ]
+    );
+    assert!(model.layers[&0]
+        .buffers()
+        .iter()
+        .all(|(_, values)| values.iter().all(|&value| value == 0.5)));
+
+    // a NaN in the first layer does not prevent loading the second one
+    let mut corrupted = bytes.clone();
+    corrupted[20..24].copy_from_slice(&f32::NAN.to_le_bytes());
+    let weights_file = dir.join("corrupted.weights");
+    fs::write(&weights_file, &corrupted)?;
+    let mut model = DarknetModel::from_config(&config)?;
+    let report = model.load_weights_salvage(&weights_file)?;
+    assert_eq!(report.first_bad_offset, Some(20));
+    assert_eq!(report.failed_layers(), [0]);
+    assert_eq!(report.status[&1], LayerLoadStatus::Loaded);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
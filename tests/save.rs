@@ -0,0 +1,142 @@
+use anyhow::Result;
+use darknet_config::config::{DarknetConfig, FloatFormat, LayerConfig, LayerConfigEx};
+use noisy_float::prelude::r64;
+
+#[test]
+fn float_format() -> Result<()> {
+    let mut config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    config.net.momentum = r64(0.1 + 0.2);
+    if let LayerConfig::Convolutional(conv) = &mut config.layers[0] {
+        conv.common
+            .extensions
+            .insert("x_note".into(), "0.30000000000000004".into());
+    }
+
+    // floats keep their exact values by default, while text values are left
+    // untouched
+    let text = config.to_cfg_string()?;
+    assert!(text
+        .lines()
+        .any(|line| line == "momentum=0.30000000000000004"));
+    assert!(text.lines().any(|line| line == "scales=0.1,0.1"));
+    assert!(text
+        .lines()
+        .any(|line| line == "x_note=0.30000000000000004"));
+    assert_eq!(text.parse::<DarknetConfig>()?, config);
+
+    // the f32 precision darknet reads the values in
+    let text = config.to_cfg_string_with(FloatFormat::Shortest)?;
+    assert!(text.lines().any(|line| line == "momentum=0.3"));
+    assert!(text
+        .lines()
+        .any(|line| line == "x_note=0.30000000000000004"));
+    assert_eq!(text.parse::<DarknetConfig>()?.net.momentum, r64(0.3));
+
+    let text = config.to_cfg_string_with(FloatFormat::Fixed(1))?;
+    assert!(text.lines().any(|line| line == "momentum=0.3"));
+    assert!(text.lines().any(|line| line == "decay=0.0"));
+    assert!(text
+        .lines()
+        .any(|line| line == "x_note=0.30000000000000004"));
+
+    assert_eq!(FloatFormat::Shortest.format(0.1 + 0.2), "0.3");
+    assert_eq!(FloatFormat::Shortest.format(2.0), "2.0");
+    assert_eq!(FloatFormat::Fixed(3).format(0.25), "0.25");
+    Ok(())
+}
+
+#[test]
+fn extension_keys() -> Result<()> {
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+x_quant_bits=4
+x_note=keep
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+"
+    .parse()?;
+
+    let extensions = &config.layers[0].common().extensions;
+    assert_eq!(extensions.len(), 2);
+    assert_eq!(extensions["x_quant_bits"], "4");
+    assert_eq!(extensions["x_note"], "keep");
+    assert!(config.layers[1].common().extensions.is_empty());
+
+    let text = config.to_cfg_string()?;
+    assert!(text.lines().any(|line| line == "x_quant_bits=4"));
+    assert_eq!(text.parse::<DarknetConfig>()?, config);
+
+    Ok(())
+}
+
+#[test]
+fn roundtrip_check() -> Result<()> {
+    for file in &[
+        "yolov4.cfg",
+        "yolov4-p5.cfg",
+        "yolov4-p6.cfg",
+        "yolov4-p7.cfg",
+        "yolov7-tiny.cfg",
+        "enet-coco.cfg",
+    ] {
+        let path = format!("{}/tests/{}", env!("CARGO_MANIFEST_DIR"), file);
+        DarknetConfig::load(&path)?.roundtrip_check()?;
+    }
+
+    // the kinds the bundled configs do not cover
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[batchnorm]
+
+[implicit]
+filters=18
+
+[shortcut]
+from=-1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+
+[route]
+layers=-2
+
+[connected]
+output=10
+activation=linear
+"
+    .parse()?;
+    config.roundtrip_check()?;
+    Ok(())
+}
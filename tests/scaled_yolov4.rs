@@ -0,0 +1,92 @@
+use anyhow::Result;
+use darknet_config::{
+    config::DarknetConfig,
+    model::{LayerBase, ModelBase},
+};
+
+fn yolo_output_shapes(file: &str) -> Result<Vec<[u64; 3]>> {
+    let config = DarknetConfig::load(file)?;
+
+    // the config survives a round trip
+    let reloaded: DarknetConfig = config.to_cfg_string()?.parse()?;
+    assert_eq!(config, reloaded);
+
+    let model = ModelBase::from_config(&config)?;
+    let output_shapes = model
+        .layers
+        .values()
+        .filter_map(|layer| match layer {
+            LayerBase::Yolo(yolo) => Some(yolo.inout_shape),
+            _ => None,
+        })
+        .collect();
+    Ok(output_shapes)
+}
+
+#[test]
+fn yolov4_p5() -> Result<()> {
+    let output_shapes =
+        yolo_output_shapes(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/yolov4-p5.cfg"))?;
+    assert_eq!(
+        output_shapes,
+        vec![[112, 112, 340], [56, 56, 340], [28, 28, 340]]
+    );
+    Ok(())
+}
+
+#[test]
+fn yolov4_p6() -> Result<()> {
+    let output_shapes =
+        yolo_output_shapes(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/yolov4-p6.cfg"))?;
+    assert_eq!(
+        output_shapes,
+        vec![[160, 160, 340], [80, 80, 340], [40, 40, 340], [20, 20, 340]]
+    );
+    Ok(())
+}
+
+#[test]
+fn yolov4_p7() -> Result<()> {
+    let output_shapes =
+        yolo_output_shapes(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/yolov4-p7.cfg"))?;
+    assert_eq!(
+        output_shapes,
+        vec![
+            [192, 192, 340],
+            [96, 96, 340],
+            [48, 48, 340],
+            [24, 24, 340],
+            [12, 12, 340]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn route_to_missing_layer() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[route]
+layers=0, 5
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+    assert!(ModelBase::from_config(&config).is_err());
+    Ok(())
+}
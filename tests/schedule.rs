@@ -0,0 +1,65 @@
+use anyhow::Result;
+use darknet_config::config::{DarknetConfig, LayerConfigEx, Policy};
+use noisy_float::prelude::r64;
+
+#[test]
+fn step_schedule() -> Result<()> {
+    let mut config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let net = &mut config.net;
+
+    // custom steps keep their relative positions
+    net.set_max_batches(6000);
+    match &net.policy {
+        Policy::Steps { steps, .. } => assert_eq!(steps, &[4795, 5395]),
+        _ => unreachable!(),
+    }
+
+    // the default schedule follows max_batches
+    net.policy = net.default_step_schedule();
+    match &net.policy {
+        Policy::Steps { steps, scales, .. } => {
+            assert_eq!(steps, &[4800, 5400]);
+            assert!(scales.iter().all(|scale| scale.raw() == 0.1));
+        }
+        _ => unreachable!(),
+    }
+    net.set_max_batches(10000);
+    assert_eq!(net.policy, Policy::default_steps(10000));
+    Ok(())
+}
+
+#[test]
+fn effective_learning_rate() -> Result<()> {
+    let mut config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let net = &config.net;
+    let rate = |iteration| net.learning_rate_at(iteration).unwrap().raw();
+
+    // burn-in ramps up with power 4, then the steps decay by 10x
+    assert!((rate(500) - 0.001 * 0.5f64.powi(4)).abs() < 1e-12);
+    assert!((rate(1000) - 0.001).abs() < 1e-12);
+    assert!((rate(400000) - 0.0001).abs() < 1e-12);
+    assert!((rate(450000) - 0.00001).abs() < 1e-12);
+
+    let common = config.layers[0].common().clone();
+    assert_eq!(
+        common.effective_learning_rate(net, 2000)?,
+        net.learning_rate_at(2000)?
+    );
+
+    // layers with burnin_update stay frozen during the burn-in
+    let mut common = common;
+    common.burnin_update = true;
+    common.learning_scale_scale = r64(0.5);
+    assert_eq!(common.effective_learning_rate(net, 500)?.raw(), 0.0);
+    assert!((common.effective_learning_rate(net, 2000)?.raw() - 0.0005).abs() < 1e-12);
+
+    config.net.policy = Policy::Random;
+    assert!(config.net.learning_rate_at(2000).is_err());
+    Ok(())
+}
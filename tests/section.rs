@@ -0,0 +1,118 @@
+use anyhow::{bail, ensure, format_err, Result};
+use darknet_config::{
+    config::{CustomConfig, LayerConfig, Shape},
+    model::{LayerBase, ShapeList},
+    section::{self, SectionHandler},
+    validate::{validate, Severity},
+    DarknetConfig, ModelBase,
+};
+use indexmap::IndexMap;
+use std::sync::Arc;
+
+// moves stride x stride blocks of pixels into the channels
+struct SpaceToDepth;
+
+impl SpaceToDepth {
+    fn stride(options: &IndexMap<String, String>) -> Result<u64> {
+        let stride: u64 = options
+            .get("stride")
+            .ok_or_else(|| format_err!("stride is missing"))?
+            .parse()?;
+        ensure!(stride > 0, "stride must be positive");
+        Ok(stride)
+    }
+}
+
+impl SectionHandler for SpaceToDepth {
+    fn section_name(&self) -> &'static str {
+        "space_to_depth"
+    }
+
+    fn parse(&self, options: IndexMap<String, String>) -> Result<IndexMap<String, String>> {
+        Self::stride(&options)?;
+        Ok(options)
+    }
+
+    fn output_shape(&self, config: &CustomConfig, input_shape: &ShapeList) -> Result<Shape> {
+        let stride = Self::stride(&config.options)?;
+        let [h, w, c] = match *input_shape {
+            ShapeList::SingleHwc(hwc) => hwc,
+            _ => bail!("expect a single HWC input"),
+        };
+        ensure!(
+            h % stride == 0 && w % stride == 0,
+            "the input size must be a multiple of the stride"
+        );
+        Ok(Shape::Hwc([h / stride, w / stride, c * stride * stride]))
+    }
+}
+
+#[test]
+fn custom_section() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=8
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[space_to_depth]
+stride=2
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    // unknown sections are rejected until a handler is registered
+    assert!(text.parse::<DarknetConfig>().is_err());
+    section::register_section_handler(Arc::new(SpaceToDepth))?;
+    assert!(section::register_section_handler(Arc::new(SpaceToDepth)).is_err());
+    assert!(section::section_names().contains(&"space_to_depth"));
+
+    let config: DarknetConfig = text.parse()?;
+    match &config.layers[1] {
+        LayerConfig::Custom(conf) => {
+            assert_eq!(conf.section, "space_to_depth");
+            assert_eq!(conf.options["stride"], "2");
+        }
+        _ => unreachable!(),
+    }
+    config.roundtrip_check()?;
+    let has_errors = |config: &DarknetConfig| {
+        validate(config)
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    };
+    assert!(!has_errors(&config));
+
+    let model = ModelBase::from_config(&config)?;
+    assert!(matches!(model.layers[&1], LayerBase::Custom(_)));
+    assert_eq!(model.layers[&1].output_shape(), Shape::Hwc([16, 16, 32]));
+    assert_eq!(model.layers[&2].output_shape(), Shape::Hwc([16, 16, 18]));
+
+    // the handler checks the options and the shapes
+    assert!(text
+        .replace(
+            "stride=2\n\n[convolutional]\nfilters=18",
+            "stride=0\n\n[convolutional]\nfilters=18"
+        )
+        .parse::<DarknetConfig>()
+        .is_err());
+    assert!(has_errors(&text.replace("width=32", "width=30").parse()?));
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use anyhow::Result;
+use darknet_config::config::{semantic_eq, DarknetConfig};
+
+const RELATIVE: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[shortcut]
+from=-2
+activation=linear
+
+[route]
+layers=-1,-3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+// the same model with absolute indexes, reordered keys and explicit defaults
+const ABSOLUTE: &str = "\
+[net]
+channels=3
+height=32
+width=32
+
+[convolutional]
+size=3
+filters=16
+pad=1
+stride=1
+groups=1
+activation=leaky
+
+[convolutional]
+activation=leaky
+filters=16
+size=3
+stride=1
+pad=1
+
+[shortcut]
+activation=linear
+from=0
+
+[route]
+layers=3,1
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+#[test]
+fn semantic_equality() -> Result<()> {
+    let relative: DarknetConfig = RELATIVE.parse()?;
+    let absolute: DarknetConfig = ABSOLUTE.parse()?;
+    assert_ne!(relative, absolute);
+    assert!(semantic_eq(&relative, &absolute));
+    assert_eq!(relative.canonicalize(), absolute.canonicalize());
+
+    // swapping the route inputs changes the channel order
+    let swapped: DarknetConfig = ABSOLUTE.replace("layers=3,1", "layers=1,3").parse()?;
+    assert!(!semantic_eq(&relative, &swapped));
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use darknet_config::{
+    config::{LayerConfig, LayerIndex},
+    model::ModelBase,
+    separable::SeparableOptions,
+    DarknetConfig,
+};
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+batch_normalize=1
+filters=32
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+filters=16
+size=1
+stride=1
+pad=1
+activation=leaky
+
+[shortcut]
+from=-3
+activation=linear
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+#[test]
+fn separable_candidates() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    let model = ModelBase::from_config(&config)?;
+
+    // the stem has too few input channels and the rest are pointwise
+    let candidates = model.separable_candidates(&SeparableOptions {
+        flops_budget: 1.0,
+        ..Default::default()
+    });
+    assert_eq!(candidates.len(), 1);
+    let candidate = &candidates[0];
+    assert_eq!(candidate.layer_index, 1);
+    assert_eq!(candidate.params, 9 * 16 * 32);
+    assert_eq!(candidate.separable_params, 9 * 16 + 16 * 32);
+    assert_eq!(candidate.flops, 32 * 32 * 9 * 16 * 32);
+
+    // the layer takes most of the compute and exceeds the default budget
+    assert!(model
+        .separable_candidates(&SeparableOptions::default())
+        .is_empty());
+    Ok(())
+}
+
+#[test]
+fn separate_convolutions() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    let separated = config.separate_convolutions(&[1])?;
+    assert_eq!(separated.config.layers.len(), 7);
+    assert_eq!(separated.new_indexes[&1], 2);
+    assert_eq!(separated.new_indexes[&3], 4);
+    match &separated.config.layers[1] {
+        LayerConfig::Convolutional(conv) => {
+            assert_eq!((conv.filters, conv.groups, conv.size), (16, 16, 3));
+        }
+        _ => unreachable!(),
+    }
+    match &separated.config.layers[2] {
+        LayerConfig::Convolutional(conv) => {
+            assert_eq!((conv.filters, conv.groups, conv.size), (32, 1, 1));
+        }
+        _ => unreachable!(),
+    }
+    match &separated.config.layers[4] {
+        LayerConfig::Shortcut(shortcut) => {
+            let from: Vec<_> = shortcut.from.iter().cloned().collect();
+            assert_eq!(from, [LayerIndex::from(-4)]);
+        }
+        _ => unreachable!(),
+    }
+
+    // the head convolution is not a plain spatial convolution
+    assert!(config.separate_convolutions(&[4]).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "random")]
+#[test]
+fn separate_model_convolutions() -> Result<()> {
+    use darknet_config::{
+        darknet::{ConvolutionalLayer, ConvolutionalWeights, Layer},
+        DarknetModel,
+    };
+
+    let config: DarknetConfig = CONFIG.parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+    model
+        .layers
+        .values_mut()
+        .flat_map(|layer| layer.buffers_mut())
+        .for_each(|(_, values)| values.fill(0.5));
+
+    let separated_model = model.separate_convolutions(&[1], 7)?;
+    assert_eq!(separated_model.layers.len(), 7);
+    assert_eq!(
+        separated_model.layers[&0].buffers(),
+        model.layers[&0].buffers()
+    );
+    assert_eq!(
+        separated_model.layers[&5].buffers(),
+        model.layers[&4].buffers()
+    );
+    match &separated_model.layers[&1] {
+        Layer::Convolutional(ConvolutionalLayer {
+            weights: ConvolutionalWeights::Owned {
+                biases, weights, ..
+            },
+            ..
+        }) => {
+            assert!(biases.iter().all(|&value| value == 0.0));
+            assert!(weights.iter().any(|&value| value != 0.5));
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
@@ -0,0 +1,814 @@
+use anyhow::Result;
+use darknet_config::{
+    binding::{DecodeParams, HeadKind},
+    config::{DarknetConfig, LayerConfig, LayerConfigRef, ReorgMode, Shape},
+    model::ModelBase,
+    trainable::ParameterCounts,
+    validate::DiagnosticCode,
+    weights_layout::WeightsLayout,
+};
+use noisy_float::prelude::r64;
+use std::path::Path;
+
+#[test]
+fn kernel_larger_than_input() -> Result<()> {
+    let text = "\
+[net]
+width=4
+height=4
+channels=3
+
+[convolutional]
+filters=18
+size=7
+stride=1
+pad=0
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+    let err = ModelBase::from_config(&config).unwrap_err().to_string();
+    assert!(
+        err.starts_with("layer 0 (conv 7x7/1 18):"),
+        "unexpected error: {}",
+        err
+    );
+    assert!(err.contains("height"), "unexpected error: {}", err);
+    Ok(())
+}
+
+#[test]
+fn layer_display() -> Result<()> {
+    let config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    assert_eq!(config.layers[0].to_string(), "conv 3x3/2 32");
+
+    let text = format!("{}", config);
+    let mut lines = text.lines();
+    let header = lines.next().unwrap();
+    assert!(header.starts_with("net 416x416x3, 80 classes, 99 layers (conv "));
+    assert_eq!(lines.next(), Some(" 0 conv 3x3/2 32"));
+
+    assert!(header.contains(", maxpool "));
+
+    // the model layers borrow the configs they were built from
+    let model = ModelBase::from_config(&config)?;
+    assert!(model
+        .layers
+        .iter()
+        .all(|(&index, layer)| layer.config() == LayerConfigRef::from(&config.layers[index])));
+    Ok(())
+}
+
+#[test]
+fn infer_shapes_multi() -> Result<()> {
+    let model = ModelBase::from_config_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let sizes = [
+        Shape::Hwc([320, 320, 3]),
+        Shape::Hwc([416, 416, 3]),
+        Shape::Hwc([512, 640, 3]),
+    ];
+    let tables = model.infer_shapes_multi(&sizes)?;
+    assert_eq!(tables.len(), 3);
+
+    // the configured size reproduces the shapes of the model
+    for (layer_index, layer) in &model.layers {
+        assert_eq!(tables[1][layer_index].1, layer.output_shape());
+    }
+
+    let last = model.layers.len() - 1;
+    assert_eq!(tables[0][&last].1, Shape::Hwc([40, 40, 255]));
+    assert_eq!(tables[2][&last].1, Shape::Hwc([64, 80, 255]));
+
+    assert!(model.infer_shapes_multi(&[Shape::Flat(100)]).is_err());
+    Ok(())
+}
+
+#[test]
+fn classifier_avgpool() -> Result<()> {
+    // a darknet19-style tail, classifiers have no yolo layers
+    let text = "\
+[net]
+width=64
+height=64
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=32
+size=3
+stride=2
+pad=1
+activation=leaky
+
+[convolutional]
+filters=1000
+size=1
+stride=1
+pad=1
+activation=linear
+
+[avgpool]
+";
+    let config: DarknetConfig = text.parse()?;
+    assert_eq!(config.net.classes, 0);
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&2].output_shape(), Shape::Hwc([1, 1, 1000]));
+    Ok(())
+}
+
+#[test]
+fn classifier_softmax() -> Result<()> {
+    let text = "\
+[net]
+width=64
+height=64
+channels=3
+
+[convolutional]
+filters=1000
+size=1
+stride=1
+pad=1
+activation=linear
+
+[avgpool]
+
+[softmax]
+groups=1
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&2].output_shape(), Shape::Hwc([1, 1, 1000]));
+
+    let outputs = model.outputs();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].layer_index, 2);
+    assert_eq!(outputs[0].dims, [1000, 1, 1]);
+
+    // the groups must divide the inputs
+    let config: DarknetConfig = text.replace("groups=1", "groups=3").parse()?;
+    assert!(ModelBase::from_config(&config).is_err());
+
+    // YOLO9000 names the tree file hierarchy in some configs
+    let config: DarknetConfig = text.replace("groups=1", "hierarchy=data/9k.tree").parse()?;
+    match &config.layers[2] {
+        LayerConfig::Softmax(softmax) => {
+            assert_eq!(softmax.tree.as_deref(), Some(Path::new("data/9k.tree")))
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+#[test]
+fn yolov2_region() -> Result<()> {
+    let text = "\
+[net]
+width=416
+height=416
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=32
+pad=1
+activation=leaky
+
+[convolutional]
+filters=125
+size=1
+stride=1
+pad=1
+activation=linear
+
+[region]
+anchors = 1.08,1.19,  3.42,4.41,  6.63,11.38,  9.42,5.11,  16.62,10.52
+bias_match=1
+classes=20
+coords=4
+num=5
+softmax=1
+jitter=.2
+rescore=1
+thresh = .6
+random=1
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    assert_eq!(config.net.classes, 20);
+    match &config.layers[2] {
+        LayerConfig::Region(region) => {
+            let anchors = region.anchors.as_ref().unwrap();
+            assert_eq!(anchors.len(), 5);
+            assert_eq!(anchors[1], (r64(3.42), r64(4.41)));
+            assert!(region.bias_match && region.softmax && region.rescore);
+            assert_eq!(region.thresh, r64(0.6));
+        }
+        _ => unreachable!(),
+    }
+
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&2].output_shape(), Shape::Hwc([13, 13, 125]));
+    let outputs = model.outputs();
+    assert_eq!(outputs.len(), 1);
+    assert_eq!(outputs[0].dims, [125, 13, 13]);
+    assert!(matches!(
+        outputs[0].decode,
+        DecodeParams::Region {
+            classes: 20,
+            coords: 4,
+            softmax: true,
+            ..
+        }
+    ));
+
+    // the anchors in grid cells are scaled to input pixels
+    let heads = model.heads();
+    assert_eq!(heads.len(), 1);
+    assert_eq!(heads[0].kind, HeadKind::Region);
+    assert_eq!(heads[0].stride, [32, 32]);
+    assert_eq!(heads[0].anchors[1], (109, 141));
+    assert_eq!(heads[0].classes, 20);
+
+    // the channels must hold num * (coords + 1 + classes) values
+    let config: DarknetConfig = text.replace("filters=125", "filters=120").parse()?;
+    assert!(ModelBase::from_config(&config).is_err());
+    Ok(())
+}
+
+#[test]
+fn gaussian_yolov3() -> Result<()> {
+    let text = "\
+[net]
+width=416
+height=416
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=32
+pad=1
+activation=leaky
+
+[convolutional]
+filters=48
+size=1
+stride=1
+pad=1
+activation=linear
+
+[Gaussian_yolo]
+mask = 3,4,5
+anchors = 7,10, 14,24, 27,43, 32,97, 57,64, 92,109
+classes=7
+num=6
+jitter=.3
+ignore_thresh = .5
+truth_thresh = 1
+iou_thresh=0.213
+uc_normalizer=1.0
+iou_normalizer=0.5
+iou_loss=giou
+random=1
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    assert_eq!(config.net.classes, 7);
+    match &config.layers[2] {
+        LayerConfig::GaussianYolo(conf) => {
+            assert_eq!(conf.yolo.anchors, [(32, 97), (57, 64), (92, 109)]);
+            assert_eq!(conf.yolo.iou_normalizer, r64(0.5));
+            assert_eq!(conf.uc_normalizer, r64(1.0));
+        }
+        _ => unreachable!(),
+    }
+
+    // every anchor predicts 4 coordinates with their sigmas, objectness and classes
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&2].output_shape(), Shape::Hwc([13, 13, 48]));
+    let outputs = model.outputs();
+    assert_eq!(outputs.len(), 1);
+    assert!(matches!(
+        outputs[0].decode,
+        DecodeParams::GaussianYolo {
+            classes: 7,
+            stride: [32, 32],
+            ..
+        }
+    ));
+    let heads = model.heads();
+    assert_eq!(heads.len(), 1);
+    assert_eq!(heads[0].kind, HeadKind::GaussianYolo);
+    assert_eq!(heads[0].anchors, [(32, 97), (57, 64), (92, 109)]);
+
+    let config: DarknetConfig = text.replace("filters=48", "filters=36").parse()?;
+    assert!(ModelBase::from_config(&config).is_err());
+
+    // the uncertainty keys are rejected in plain yolo sections
+    assert!(text
+        .replace("[Gaussian_yolo]", "[yolo]")
+        .parse::<DarknetConfig>()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn yolov2_reorg() -> Result<()> {
+    let text = "\
+[net]
+width=416
+height=416
+channels=3
+
+[convolutional]
+filters=64
+size=3
+stride=16
+pad=1
+activation=leaky
+
+[reorg]
+stride=2
+
+[reorg]
+stride=2
+reverse=1
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&1].output_shape(), Shape::Hwc([13, 13, 256]));
+    assert_eq!(model.layers[&2].output_shape(), Shape::Hwc([26, 26, 64]));
+
+    // 13x13 blocks cannot be split by 2
+    let config: DarknetConfig = text.replace("stride=16", "stride=32").parse()?;
+    assert!(ModelBase::from_config(&config).is_err());
+    Ok(())
+}
+
+#[test]
+fn yolov1_local() -> Result<()> {
+    let text = "\
+[net]
+width=14
+height=14
+channels=3
+
+[local]
+filters=4
+size=3
+stride=2
+pad=1
+activation=leaky
+
+[local]
+size=3
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&0].output_shape(), Shape::Hwc([7, 7, 4]));
+    // defaults to one filter without padding
+    assert_eq!(model.layers[&1].output_shape(), Shape::Hwc([5, 5, 1]));
+
+    // a bias per output value and a 3x3 kernel per output location
+    let num_params = 7 * 7 * 4 * (3 * 3 * 3 + 1) + 5 * 5 * (3 * 3 * 4 + 1);
+    assert_eq!(model.trainable_report().trainable, num_params);
+    assert_eq!(
+        WeightsLayout::describe(&config)?.file_size(),
+        20 + num_params * 4
+    );
+    Ok(())
+}
+
+#[test]
+fn local_avgpool() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=8
+
+[local_avgpool]
+size=2
+stride=2
+
+[local_avgpool]
+size=3
+stride=1
+padding=2
+
+[maxpool]
+size=3
+stride=1
+padding=2
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let model = ModelBase::from_config(&config)?;
+    // the padding defaults to size - 1 as in [maxpool]
+    assert_eq!(model.layers[&0].output_shape(), Shape::Hwc([16, 16, 8]));
+    assert_eq!(model.layers[&1].output_shape(), Shape::Hwc([16, 16, 8]));
+    assert_eq!(
+        model.layers[&1].output_shape(),
+        model.layers[&2].output_shape()
+    );
+    Ok(())
+}
+
+#[test]
+fn sam() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=mish
+
+[convolutional]
+filters=16
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[sam]
+from=-2
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&2].output_shape(), Shape::Hwc([32, 32, 16]));
+    assert!(config.validate().is_empty());
+
+    // the attention must match the features
+    let config: DarknetConfig = text.replacen("filters=16", "filters=8", 1).parse()?;
+    assert!(ModelBase::from_config(&config).is_err());
+
+    // only earlier layers can be referenced
+    let config: DarknetConfig = text.replace("from=-2", "from=3").parse()?;
+    assert!(config
+        .validate()
+        .iter()
+        .any(|diagnostic| diagnostic.code == DiagnosticCode::ForwardReference));
+    Ok(())
+}
+
+#[test]
+fn legacy_reorg() -> Result<()> {
+    let text = "\
+[net]
+width=64
+height=64
+channels=3
+
+[reorg_old]
+stride=2
+
+[reorg3d]
+stride=2
+
+[reorg]
+stride=2
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let modes: Vec<_> = config
+        .layers
+        .iter()
+        .map(|layer| match layer {
+            LayerConfig::Reorg(reorg) => reorg.mode,
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(
+        modes,
+        [ReorgMode::Old, ReorgMode::Reorg3d, ReorgMode::Standard]
+    );
+
+    // the section names are kept on saving
+    let saved = config.to_cfg_string()?;
+    assert!(saved.contains("[reorg_old]") && saved.contains("[reorg3d]"));
+    assert_eq!(saved.parse::<DarknetConfig>()?, config);
+
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&2].output_shape(), Shape::Hwc([8, 8, 192]));
+    Ok(())
+}
+
+#[test]
+fn lstm() -> Result<()> {
+    // cfg/lstm.train.cfg without the [cost] section
+    let text = "\
+[net]
+subdivisions=8
+inputs=256
+batch=128
+momentum=0.9
+decay=0.001
+max_batches=2000
+time_steps=576
+learning_rate=0.5
+policy=steps
+burn_in=10
+steps=1000,1500
+scales=.1,.1
+
+[lstm]
+batch_normalize=1
+output=1024
+
+[lstm]
+batch_normalize=1
+output=1024
+
+[lstm]
+batch_normalize=1
+output=1024
+
+[connected]
+output=256
+activation=leaky
+
+[softmax]
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&0].output_shape(), Shape::Flat(1024));
+    assert_eq!(model.layers[&3].output_shape(), Shape::Flat(256));
+    assert!(config.validate().is_empty());
+
+    // four recurrent and four input gates, each a connected layer with batch norm
+    let gate = |inputs: u64, outputs: u64| inputs * outputs + 4 * outputs;
+    let layout = WeightsLayout::describe(&config)?;
+    let records: Vec<_> = layout.layer_records(0).collect();
+    assert_eq!(records.len(), 8 * 5);
+    assert_eq!(records[0].name, "wf.biases");
+    assert_eq!(records[39].name, "uo.rolling_variance");
+    assert_eq!(
+        records.iter().map(|record| record.length).sum::<u64>(),
+        4 * gate(1024, 1024) + 4 * gate(256, 1024)
+    );
+    assert_eq!(
+        layout
+            .layer_records(1)
+            .map(|record| record.length)
+            .sum::<u64>(),
+        8 * gate(1024, 1024)
+    );
+
+    // a single time step is legal but defeats the recurrence
+    let text = text.replace("time_steps=576\n", "");
+    let config: DarknetConfig = text.parse()?;
+    let layers: Vec<_> = config
+        .validate()
+        .iter()
+        .filter(|diagnostic| diagnostic.code == DiagnosticCode::SingleTimeStep)
+        .map(|diagnostic| diagnostic.layer_index)
+        .collect();
+    assert_eq!(layers, [Some(0), Some(1), Some(2)]);
+    Ok(())
+}
+
+#[test]
+fn crnn() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+batch=64
+time_steps=16
+
+[crnn]
+batch_normalize=1
+size=3
+pad=1
+output=32
+hidden=16
+activation=leaky
+
+[crnn]
+batch_normalize=1
+size=3
+pad=1
+output=8
+hidden=16
+activation=leaky
+
+[maxpool]
+size=2
+stride=2
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&0].output_shape(), Shape::Hwc([32, 32, 32]));
+    assert_eq!(model.layers[&1].output_shape(), Shape::Hwc([32, 32, 8]));
+    assert_eq!(model.layers[&2].output_shape(), Shape::Hwc([16, 16, 8]));
+    assert!(config.validate().is_empty());
+
+    // the input, self and output convolutions, each with batch norm
+    let conv = |in_c: u64, filters: u64| in_c * filters * 9 + 4 * filters;
+    let layout = WeightsLayout::describe(&config)?;
+    let records: Vec<_> = layout.layer_records(0).collect();
+    assert_eq!(records.len(), 3 * 5);
+    assert_eq!(records[0].name, "input.biases");
+    assert_eq!(records[14].name, "output.weights");
+    assert_eq!(
+        records.iter().map(|record| record.length).sum::<u64>(),
+        conv(3, 16) + conv(16, 16) + conv(16, 32)
+    );
+
+    // the hidden state must keep the input size to be fed to the next step
+    let unpadded = text.replacen("pad=1\noutput=32", "output=32", 1);
+    let strided = text.replacen("pad=1\noutput=32", "pad=1\nstride=2\noutput=32", 1);
+    for text in &[unpadded, strided] {
+        let config: DarknetConfig = text.parse()?;
+        let err = ModelBase::from_config(&config).unwrap_err().to_string();
+        assert!(err.contains("cannot be carried over time steps"), "{}", err);
+    }
+    Ok(())
+}
+
+#[test]
+fn conv_lstm() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+batch=64
+time_steps=16
+
+[convolutional]
+batch_normalize=1
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[conv_lstm]
+batch_normalize=1
+size=3
+pad=1
+output=16
+peephole=1
+state_constrain=16
+activation=leaky
+
+[conv_lstm]
+batch_normalize=1
+size=3
+pad=1
+output=16
+bottleneck=1
+shortcut=1
+activation=leaky
+
+[conv_lstm]
+size=1
+output=8
+groups=2
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let model = ModelBase::from_config(&config)?;
+    assert_eq!(model.layers[&1].output_shape(), Shape::Hwc([32, 32, 16]));
+    assert_eq!(model.layers[&3].output_shape(), Shape::Hwc([32, 32, 8]));
+    assert!(config.validate().is_empty());
+
+    // the peephole adds vf, vi and vo, and the bottleneck keeps wf alone
+    let conv = |in_c: u64, filters: u64, size: u64, batch_normalize: bool| {
+        in_c * filters * size * size + if batch_normalize { 4 } else { 1 } * filters
+    };
+    let layout = WeightsLayout::describe(&config)?;
+    let names = |layer_index| -> Vec<_> {
+        layout
+            .layer_records(layer_index)
+            .map(|record| record.name.clone())
+            .collect()
+    };
+    let num_values = |layer_index| -> u64 {
+        layout
+            .layer_records(layer_index)
+            .map(|record| record.length)
+            .sum()
+    };
+    assert_eq!(names(1).len(), 11 * 5);
+    assert_eq!(names(1)[0], "vf.biases");
+    assert_eq!(num_values(1), 11 * conv(16, 16, 3, true));
+    assert_eq!(names(2).len(), 5 * 5);
+    assert_eq!(names(2)[0], "wf.biases");
+    assert_eq!(
+        num_values(2),
+        conv(32, 16, 3, true) + 4 * conv(16, 16, 3, true)
+    );
+    assert_eq!(names(3)[..3], ["wf.biases", "wf.weights", "wi.biases"]);
+    assert_eq!(
+        num_values(3),
+        4 * conv(8 / 2, 8, 1, false) + 4 * conv(16 / 2, 8, 1, false)
+    );
+    for layer_index in 1..=3 {
+        assert_eq!(
+            ParameterCounts::new(&model.layers[&layer_index]).total(),
+            num_values(layer_index)
+        );
+    }
+
+    // the bottleneck copies the input next to the output
+    let text = text.replace("size=1\noutput=8", "size=1\noutput=8\nbottleneck=1");
+    let config: DarknetConfig = text.parse()?;
+    let err = ModelBase::from_config(&config).unwrap_err().to_string();
+    assert!(
+        err.contains("the bottleneck expects 8 input channels"),
+        "{}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn yolo_without_mask() -> Result<()> {
+    // darknet takes all anchors if the mask is omitted
+    let text = "\
+[net]
+width=64
+height=64
+channels=3
+
+[convolutional]
+filters=30
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+anchors=10,14, 23,27, 37,58, 81,82, 135,169
+classes=1
+num=5
+";
+    let config: DarknetConfig = text.parse()?;
+    config.roundtrip_check()?;
+    let model = ModelBase::from_config(&config)?;
+    let heads = model.heads();
+    assert_eq!(heads.len(), 1);
+    assert_eq!(
+        heads[0].anchors,
+        [(10, 14), (23, 27), (37, 58), (81, 82), (135, 169)]
+    );
+    assert!(config.validate().is_empty());
+
+    // a second head without mask predicts the same anchors
+    let head = "
+[route]
+layers=0
+
+[maxpool]
+size=2
+stride=2
+
+[yolo]
+anchors=10,14, 23,27, 37,58, 81,82, 135,169
+classes=1
+num=5
+";
+    let config: DarknetConfig = format!("{}{}", text, head).parse()?;
+    let diagnostics: Vec<_> = config
+        .validate()
+        .into_iter()
+        .filter(|diagnostic| diagnostic.code == DiagnosticCode::SharedAnchors)
+        .map(|diagnostic| (diagnostic.layer_index, diagnostic.message))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [(
+            Some(4),
+            "the layer predicts the same anchors as layer 1, the mask may be missing".to_owned()
+        )]
+    );
+    Ok(())
+}
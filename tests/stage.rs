@@ -0,0 +1,118 @@
+use anyhow::Result;
+use darknet_config::{
+    config::{DarknetConfig, LayerConfig, LayerConfigEx},
+    stages::{LayerStage, StageMap, STAGE_KEY},
+};
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+x_stage=backbone
+
+[convolutional]
+filters=32
+size=3
+stride=2
+pad=1
+activation=leaky
+x_stage=backbone
+
+[convolutional]
+filters=16
+size=1
+stride=1
+pad=1
+activation=leaky
+x_stage=neck
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+x_stage=head
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+x_stage=head
+";
+
+#[test]
+fn stage_annotations() -> Result<()> {
+    let mut config: DarknetConfig = CONFIG.parse()?;
+    let stages = config.stages()?;
+    let ranges: Vec<_> = stages
+        .iter()
+        .map(|stage| (stage.name.as_str(), stage.layers.clone()))
+        .collect();
+    assert_eq!(ranges, [("backbone", 0..2), ("neck", 2..3), ("head", 3..5)]);
+    assert_eq!(
+        stages.stage_of(4).map(|stage| stage.name.as_str()),
+        Some("head")
+    );
+    assert!(stages.get("tail").is_err());
+
+    // freeze the backbone only
+    let report = config.freeze_stage(&stages, "backbone")?;
+    assert_eq!(report.matched, [0, 1]);
+    let frozen: Vec<_> = config
+        .layers
+        .iter()
+        .map(|layer| layer.common().dont_update)
+        .collect();
+    assert_eq!(frozen, [true, true, false, false, false]);
+
+    // drop the head
+    let pruned = config.remove_stage(&stages, "head")?;
+    assert_eq!(pruned.removed, [3, 4]);
+    assert_eq!(pruned.config.layers.len(), 3);
+    assert!(matches!(
+        pruned.config.layers[2],
+        LayerConfig::Convolutional(_)
+    ));
+
+    // stages defined by ranges are written back as annotations
+    let stages = StageMap::new(vec![LayerStage {
+        name: "features".into(),
+        layers: 0..3,
+    }])?;
+    config.annotate_stages(&stages)?;
+    let reparsed: DarknetConfig = config.to_cfg_string()?.parse()?;
+    assert_eq!(reparsed.stages()?, stages);
+    assert!(!reparsed.layers[4]
+        .common()
+        .extensions
+        .contains_key(STAGE_KEY));
+    Ok(())
+}
+
+#[test]
+fn stage_errors() -> Result<()> {
+    let stage = |name: &str, start, end| LayerStage {
+        name: name.into(),
+        layers: start..end,
+    };
+    assert!(StageMap::new(vec![stage("a", 0, 2), stage("b", 1, 3)]).is_err());
+    assert!(StageMap::new(vec![stage("a", 0, 1), stage("a", 1, 2)]).is_err());
+    assert!(StageMap::new(vec![stage("a", 1, 1)]).is_err());
+
+    // the layers of a stage are consecutive
+    let text = CONFIG.replacen("x_stage=head", "x_stage=backbone", 1);
+    assert!(text.parse::<DarknetConfig>()?.stages().is_err());
+    let text = CONFIG.replacen("x_stage=neck", "x_stage=backbone", 1);
+    assert!(text.parse::<DarknetConfig>()?.stages().is_ok());
+    Ok(())
+}
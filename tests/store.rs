@@ -0,0 +1,91 @@
+#![cfg(feature = "manifest")]
+
+use anyhow::Result;
+use darknet_config::{store::BlobStore, DarknetConfig, DarknetModel};
+use std::fs;
+
+#[test]
+fn layer_dedup() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("darknet-config-store-{}", std::process::id()));
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=8
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+"
+    .parse()?;
+    let mut base = DarknetModel::from_config(&config)?;
+    let fill = |model: &mut DarknetModel, layer_index: usize, value: f32| {
+        for (_, values) in model.layers.get_mut(&layer_index).unwrap().buffers_mut() {
+            values.fill(value);
+        }
+    };
+    fill(&mut base, 0, 0.5);
+    fill(&mut base, 1, 0.25);
+    let mut variant = base.clone();
+    fill(&mut variant, 1, -1.0);
+    variant.base.seen = 100;
+
+    let store = BlobStore::open(&dir)?;
+    let stats = store.put_checkpoint("base", &base)?;
+    assert_eq!(stats.num_new_blobs, 3);
+    assert_eq!(stats.num_reused_blobs, 0);
+
+    // only the changed head is added
+    let stats = store.put_checkpoint("variant", &variant)?;
+    assert_eq!(stats.num_new_blobs, 1);
+    assert_eq!(stats.num_reused_blobs, 2);
+    let base_manifest = store.manifest("base")?;
+    let variant_manifest = store.manifest("variant")?;
+    assert_eq!(
+        base_manifest.layers[0].digest,
+        variant_manifest.layers[0].digest
+    );
+    assert_ne!(
+        base_manifest.layers[1].digest,
+        variant_manifest.layers[1].digest
+    );
+    assert_eq!(base_manifest.layers[2].digest, None);
+    assert_eq!(
+        variant.layer_digests()[&1],
+        variant_manifest.layers[1].digest
+    );
+
+    let loaded = store.get_checkpoint("variant")?;
+    assert_eq!(loaded.base.seen, 100);
+    let mut expect = vec![];
+    let mut actual = vec![];
+    variant.write_weights(&mut expect)?;
+    loaded.write_weights(&mut actual)?;
+    assert_eq!(actual, expect);
+
+    // blobs of removed checkpoints are collected unless still referenced
+    assert_eq!(store.checkpoint_names()?, ["base", "variant"]);
+    store.remove_checkpoint("variant")?;
+    let removed = store.collect_garbage()?;
+    assert_eq!(removed.len(), 1);
+    assert!(store.get_checkpoint("base").is_ok());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
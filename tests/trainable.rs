@@ -0,0 +1,110 @@
+use anyhow::Result;
+use darknet_config::{model::ModelBase, trainable::TrainStatus, DarknetConfig};
+
+#[test]
+fn frozen_backbone() -> Result<()> {
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=8
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+batch_normalize=1
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+stopbackward=1
+
+[convolutional]
+batch_normalize=1
+filters=8
+size=1
+stride=1
+pad=1
+activation=leaky
+dont_update=1
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+"
+    .parse()?;
+    let model = ModelBase::from_config(&config)?;
+    let report = model.trainable_report();
+
+    let statuses: Vec<_> = report.layers.iter().map(|layer| layer.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            TrainStatus::StopBackward { layer_index: 1 },
+            TrainStatus::StopBackward { layer_index: 1 },
+            TrainStatus::DontUpdate,
+            TrainStatus::Trainable,
+            TrainStatus::NoParameters,
+        ]
+    );
+    assert_eq!(report.trainable, 8 * 18 + 18);
+    assert_eq!(
+        report.frozen,
+        (3 * 9 * 8 + 16) + (8 * 9 * 16 + 32) + (16 * 8 + 16)
+    );
+    assert_eq!(report.non_trainable, 16 + 32 + 16);
+    assert!(report.to_string().contains("stopbackward@1"));
+    Ok(())
+}
+
+#[test]
+fn train_only_bn() -> Result<()> {
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=8
+size=3
+stride=1
+pad=1
+activation=leaky
+train_only_bn=1
+
+[convolutional]
+filters=8
+size=1
+stride=1
+pad=1
+activation=linear
+"
+    .parse()?;
+    let report = ModelBase::from_config(&config)?.trainable_report();
+    assert_eq!(
+        report.layers[0].status,
+        TrainStatus::TrainOnlyBn { layer_index: 0 }
+    );
+    assert_eq!(report.layers[0].trainable, 16);
+    assert_eq!(report.layers[0].frozen, 3 * 9 * 8);
+    assert_eq!(report.layers[1].status, TrainStatus::Trainable);
+    Ok(())
+}
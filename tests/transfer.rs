@@ -0,0 +1,103 @@
+use anyhow::Result;
+use darknet_config::{DarknetConfig, DarknetModel};
+use std::fs;
+
+const BACKBONE: &str = "\
+[net]
+width=64
+height=64
+channels=3
+
+[convolutional]
+batch_normalize=1
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[maxpool]
+size=2
+stride=2
+
+[convolutional]
+batch_normalize=1
+filters=32
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+filters=10
+size=1
+stride=1
+pad=1
+activation=linear
+
+[avgpool]
+";
+
+const HEAD: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+#[test]
+fn classifier_to_detector() -> Result<()> {
+    let backbone: DarknetConfig = BACKBONE.parse()?;
+    let head: DarknetConfig = HEAD.parse()?;
+    assert_eq!(backbone.net.classes, 0);
+
+    let detector = backbone.classifier_to_detector(3, &head)?;
+    assert_eq!(detector.backbone_layers, 3);
+    assert_eq!(detector.config.layers.len(), 5);
+    assert_eq!(detector.config.net.classes, 1);
+    assert!(backbone.classifier_to_detector(6, &head).is_err());
+
+    // extract the .conv.3 file and load it into the detector
+    let mut classifier = DarknetModel::from_config(&backbone)?;
+    classifier
+        .layers
+        .values_mut()
+        .flat_map(|layer| layer.buffers_mut())
+        .for_each(|(_, values)| values.fill(0.25));
+    classifier.base.seen = 640;
+
+    let dir = std::env::temp_dir().join(format!("darknet-config-transfer-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let conv_file = dir.join("classifier.conv.3");
+    classifier.save_partial_weights(&conv_file, 3)?;
+
+    let mut model = DarknetModel::from_config(&detector.config)?;
+    model.load_backbone_weights(&conv_file, 3)?;
+    assert_eq!(model.base.seen, 0);
+    (0..3).for_each(|layer_index| {
+        assert_eq!(
+            model.layers[&layer_index].buffers(),
+            classifier.layers[&layer_index].buffers()
+        );
+    });
+
+    // the file does not line up with a shorter backbone
+    let mut model = DarknetModel::from_config(&detector.config)?;
+    assert!(model.load_backbone_weights(&conv_file, 2).is_err());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use darknet_config::{config::DarknetConfig, dataset::LabelBox, tta::TtaOptions};
+
+#[test]
+fn tta_transforms() -> Result<()> {
+    let mut config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    config.net.letter_box = true;
+
+    let options = TtaOptions {
+        scales: vec![0.8, 1.0, 1.2],
+        ..TtaOptions::default()
+    };
+    let transforms = config.net.tta_transforms(&options)?;
+    let sizes: Vec<_> = transforms
+        .iter()
+        .map(|transform| (transform.input_size, transform.flip))
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            ([320, 320], false),
+            ([320, 320], true),
+            ([416, 416], false),
+            ([416, 416], true),
+            ([512, 512], false),
+            ([512, 512], true),
+        ]
+    );
+
+    // a 2:1 image fills the upper and lower quarters with padding
+    let image_size = [832, 416];
+    let bbox = LabelBox {
+        class: 0,
+        x: 0.2,
+        y: 0.0,
+        w: 0.1,
+        h: 0.2,
+    };
+    let flipped = &transforms[3];
+    let on_network = flipped.to_network(image_size, &bbox);
+    assert!((on_network.x - 0.8).abs() < 1e-9);
+    assert!((on_network.y - 0.25).abs() < 1e-9);
+    assert!((on_network.h - 0.1).abs() < 1e-9);
+
+    for transform in &transforms {
+        let restored = transform.to_image(image_size, &transform.to_network(image_size, &bbox));
+        assert!((restored.x - bbox.x).abs() < 1e-9);
+        assert!((restored.y - bbox.y).abs() < 1e-9);
+        assert!((restored.w - bbox.w).abs() < 1e-9);
+        assert!((restored.h - bbox.h).abs() < 1e-9);
+    }
+
+    Ok(())
+}
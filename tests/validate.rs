@@ -0,0 +1,297 @@
+use anyhow::Result;
+use darknet_config::{
+    config::DarknetConfig,
+    validate::{DiagnosticCode, Severity},
+};
+
+#[test]
+fn deprecated_keys() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+aspect=1.5
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+flipped=1
+dot=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+    let diagnostics = config.validate();
+
+    // deprecations never fail the validation
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity == Severity::Warning));
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.layer_index == Some(0)
+            && diagnostic.message.starts_with("dot in [convolutional]")));
+    // flipped still transposes the loaded weights
+    assert!(!diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.message.starts_with("flipped")));
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.layer_index.is_none()
+            && diagnostic.message.starts_with("aspect in [net]")));
+
+    Ok(())
+}
+
+#[test]
+fn random_resize_grid() -> Result<()> {
+    let text = "\
+[net]
+width=128
+height=128
+channels=3
+
+[maxpool]
+size=2
+stride=64
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+random=1
+";
+    let config: DarknetConfig = text.parse()?;
+    let diagnostics = config.validate();
+
+    // random resizing picks multiples of 32 from 128 to 224
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].layer_index, Some(2));
+    assert!(errors[0]
+        .message
+        .ends_with("width 160, width 224, height 160, height 224"));
+
+    Ok(())
+}
+
+#[test]
+fn forward_references() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=16
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[route]
+layers=2
+
+[route]
+layers=1
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+    let diagnostics = config.validate();
+
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.layer_index == Some(1)
+            && diagnostic
+                .message
+                .starts_with("layers=2 refers to a later layer")));
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.layer_index.is_none()
+            && diagnostic.message == "layers 1, 2 form a dependency cycle"));
+
+    Ok(())
+}
+
+#[test]
+fn stability_options() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+try_fix_nan=1
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+clip=0.5
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+max_delta=-1
+clip=1
+";
+    let config: DarknetConfig = text.parse()?;
+    let options = config.stability_options();
+    assert!(options.try_fix_nan);
+    assert_eq!(options.clips.len(), 2);
+    assert_eq!(options.clips[&0].raw(), 0.5);
+    assert_eq!(options.max_deltas[&1].raw(), -1.0);
+
+    let diagnostics = config.validate();
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error
+            && diagnostic.layer_index == Some(1)
+            && diagnostic
+                .message
+                .starts_with("max_delta=-1 must be positive")));
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Warning
+            && diagnostic.layer_index == Some(1)
+            && diagnostic.message.starts_with("clip=1 has no effect")));
+
+    Ok(())
+}
+
+#[test]
+fn inverted_anchor_masks() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=6,7,8
+anchors=10,13, 16,30, 33,23, 30,61, 62,45, 59,119, 116,90, 156,198, 373,326
+classes=1
+num=9
+
+[route]
+layers=0
+
+[maxpool]
+size=2
+stride=2
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23, 30,61, 62,45, 59,119, 116,90, 156,198, 373,326
+classes=1
+num=9
+";
+    let config: DarknetConfig = text.parse()?;
+    let diagnostics = config.validate();
+
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Warning
+            && diagnostic.layer_index == Some(1)
+            && diagnostic.message
+                == "the anchors at stride 1 are larger than the anchors of layer 4 at stride 2, the masks may be inverted"));
+
+    Ok(())
+}
+
+#[test]
+fn diagnostic_codes() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+aspect=1.5
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+max_delta=-1
+";
+    let config: DarknetConfig = text.parse()?;
+    let diagnostics = config.validate();
+    let codes: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.code, diagnostic.severity))
+        .collect();
+    assert!(codes.contains(&(DiagnosticCode::DeprecatedKey, Severity::Warning)));
+    assert!(codes.contains(&(DiagnosticCode::NonPositiveMaxDelta, Severity::Error)));
+
+    // the codes are serialized as strings for external tools
+    let json: serde_json::Value = serde_json::to_value(&diagnostics)?;
+    let max_delta = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|diagnostic| diagnostic["code"] == "DKC0013")
+        .unwrap();
+    assert_eq!(max_delta["severity"], "error");
+    assert_eq!(max_delta["layer_index"], 1);
+
+    let error = diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.code == DiagnosticCode::NonPositiveMaxDelta)
+        .unwrap();
+    assert!(error.to_string().starts_with("error[DKC0013]: layer 1: "));
+    assert_eq!(
+        serde_json::from_value::<DiagnosticCode>("DKC0013".into())?,
+        DiagnosticCode::NonPositiveMaxDelta
+    );
+    Ok(())
+}
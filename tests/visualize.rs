@@ -0,0 +1,27 @@
+#![cfg(feature = "image")]
+
+use anyhow::Result;
+use darknet_config::{DarknetConfig, DarknetModel};
+use std::fs;
+
+#[test]
+fn export_filter_images() -> Result<()> {
+    let config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let model = DarknetModel::from_config(&config)?;
+    let dir = std::env::temp_dir().join(format!("darknet-config-filters-{}", std::process::id()));
+
+    // the first layer has 32 filters on rgb inputs
+    let files = model.export_filter_images(0, &dir)?;
+    assert_eq!(files.len(), 32);
+    assert!(files.iter().all(|file| file.is_file()));
+
+    // deeper layers are drawn as a single grid of kernel norms
+    let files = model.export_filter_images(1, &dir)?;
+    assert_eq!(files.len(), 1);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "manifest")]
+
+use anyhow::Result;
+use darknet_config::{config::DarknetConfig, weights_cache::WeightsCache};
+use std::fs;
+
+#[test]
+fn weights_cache() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+
+    let dir = std::env::temp_dir().join(format!(
+        "darknet-config-weights-cache-{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir)?;
+
+    // version 0.2.0, seen = 64, then 18 biases and 18x3 weights
+    let weights_file = dir.join("model.weights");
+    let mut bytes = vec![];
+    [0u32, 2, 0]
+        .iter()
+        .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    bytes.extend_from_slice(&64u64.to_le_bytes());
+    (0..72).for_each(|value| bytes.extend_from_slice(&(value as f32).to_le_bytes()));
+    fs::write(&weights_file, &bytes)?;
+
+    let cache = WeightsCache::new(dir.join("cache"));
+    let index = cache.load_or_build(&config, &weights_file)?;
+    assert_eq!(index.seen, 64);
+    assert_eq!(index.layers.len(), 2);
+    assert_eq!(index.layers[0].offset, 20);
+    assert_eq!(index.layers[0].num_bytes, 72 * 4);
+    let stats = index.layers[0].stats.as_ref().unwrap();
+    assert_eq!((stats.min, stats.max), (0.0, 71.0));
+    assert_eq!(index.layers[1].num_bytes, 0);
+
+    // the second lookup is served from the cache
+    assert_eq!(
+        cache.get(&config, &index.weights_checksum)?,
+        Some(index.clone())
+    );
+    assert_eq!(cache.load_or_build(&config, &weights_file)?, index);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use darknet_config::{
+    config::DarknetConfig,
+    weights_layout::{WeightsDataType, WeightsLayout},
+};
+
+#[test]
+fn weights_layout() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+    let config: DarknetConfig = text.parse()?;
+    let layout = WeightsLayout::describe(&config)?;
+
+    let fields: Vec<_> = layout
+        .records
+        .iter()
+        .map(|record| {
+            (
+                record.layer_index,
+                record.name.as_str(),
+                record.offset,
+                record.length,
+            )
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            (None, "major", 0, 1),
+            (None, "minor", 4, 1),
+            (None, "revision", 8, 1),
+            (None, "seen", 12, 1),
+            (Some(0), "biases", 20, 18),
+            (Some(0), "weights", 92, 54),
+        ]
+    );
+    assert_eq!(layout.records[3].dtype, WeightsDataType::UInt64);
+    assert_eq!(layout.file_size(), 20 + 72 * 4);
+
+    // batch normalized convolutions store the scales between biases and weights
+    let config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+    let layout = WeightsLayout::describe(&config)?;
+    let names: Vec<_> = layout
+        .layer_records(0)
+        .map(|record| record.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "biases",
+            "scales",
+            "rolling_mean",
+            "rolling_variance",
+            "weights"
+        ]
+    );
+    Ok(())
+}
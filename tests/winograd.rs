@@ -0,0 +1,102 @@
+use anyhow::Result;
+use darknet_config::{winograd::WinogradTile, DarknetConfig, DarknetModel};
+
+const CONFIG: &str = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=8
+size=3
+stride=1
+pad=1
+activation=leaky
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+";
+
+fn matmul(lhs: &[Vec<f32>], rhs: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    lhs.iter()
+        .map(|row| {
+            (0..rhs[0].len())
+                .map(|col| (0..rhs.len()).map(|k| row[k] * rhs[k][col]).sum())
+                .collect()
+        })
+        .collect()
+}
+
+fn transpose(matrix: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    (0..matrix[0].len())
+        .map(|col| matrix.iter().map(|row| row[col]).collect())
+        .collect()
+}
+
+#[test]
+fn winograd_matches_direct_convolution() {
+    let kernel: Vec<f32> = (0..9).map(|index| (index as f32 * 0.37).sin()).collect();
+
+    for &tile in &[WinogradTile::F2x3, WinogradTile::F4x3] {
+        let m = tile.output_size();
+        let n = tile.input_size();
+        let input: Vec<Vec<f32>> = (0..n)
+            .map(|row| {
+                (0..n)
+                    .map(|col| ((row * n + col) as f32 * 0.11).cos())
+                    .collect()
+            })
+            .collect();
+
+        let u: Vec<f32> = tile.transform_kernel(&kernel);
+        let bt: Vec<Vec<f32>> = tile
+            .input_transform()
+            .iter()
+            .map(|row| row.to_vec())
+            .collect();
+        let at: Vec<Vec<f32>> = tile
+            .output_transform()
+            .iter()
+            .map(|row| row.to_vec())
+            .collect();
+        let v = matmul(&matmul(&bt, &input), &transpose(&bt));
+        let product: Vec<Vec<f32>> = (0..n)
+            .map(|row| (0..n).map(|col| u[row * n + col] * v[row][col]).collect())
+            .collect();
+        let output = matmul(&matmul(&at, &product), &transpose(&at));
+
+        for row in 0..m {
+            for col in 0..m {
+                let direct: f32 = (0..3)
+                    .flat_map(|dy| (0..3).map(move |dx| (dy, dx)))
+                    .map(|(dy, dx)| input[row + dy][col + dx] * kernel[dy * 3 + dx])
+                    .sum();
+                assert!((output[row][col] - direct).abs() < 1e-4);
+            }
+        }
+    }
+}
+
+#[test]
+fn precompute_winograd() -> Result<()> {
+    let config: DarknetConfig = CONFIG.parse()?;
+    let model = DarknetModel::from_config(&config)?;
+    let kernels = model.precompute_winograd(WinogradTile::F2x3);
+
+    // only the 3x3 convolution is transformed
+    assert!(kernels.is_transformed(0));
+    assert!(!kernels.is_transformed(1));
+    assert_eq!(kernels.get(0).unwrap().dims, [8, 3, 4, 4]);
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use darknet_config::{
+    config::{DarknetConfig, ImplicitMode, LayerConfig},
+    model::{LayerBase, ModelBase},
+};
+
+#[test]
+fn yolov7_tiny() -> Result<()> {
+    let config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-tiny.cfg"
+    ))?;
+
+    let yolo_configs: Vec<_> = config
+        .layers
+        .iter()
+        .filter_map(|layer| match layer {
+            LayerConfig::Yolo(yolo) => Some(yolo),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(yolo_configs.len(), 3);
+    assert!(yolo_configs.iter().all(|yolo| yolo.new_coords));
+
+    let model = ModelBase::from_config(&config)?;
+    let output_shapes: Vec<_> = model
+        .layers
+        .values()
+        .filter_map(|layer| match layer {
+            LayerBase::Yolo(yolo) => Some(yolo.inout_shape),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        output_shapes,
+        vec![[52, 52, 255], [26, 26, 255], [13, 13, 255]]
+    );
+
+    // the config survives a round trip
+    let reloaded: DarknetConfig = config.to_cfg_string()?.parse()?;
+    assert_eq!(config, reloaded);
+
+    Ok(())
+}
+
+#[test]
+fn implicit_layer() -> Result<()> {
+    let text = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=logistic
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+new_coords=1
+
+[implicit]
+filters=16
+mean=1.0
+std=0.02
+";
+    let config: DarknetConfig = text.parse()?;
+    let model = ModelBase::from_config(&config)?;
+
+    match &model.layers[&2] {
+        LayerBase::Implicit(implicit) => {
+            assert_eq!(implicit.output_shape, [1, 1, 16]);
+            assert_eq!(implicit.config.mean.raw(), 1.0);
+        }
+        _ => panic!("expect an implicit layer"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn implicit_sections() -> Result<()> {
+    let config = DarknetConfig::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/yolov7-implicit.cfg"
+    ))?;
+
+    let implicit_modes: Vec<_> = config
+        .layers
+        .iter()
+        .filter_map(|layer| match layer {
+            LayerConfig::Implicit(implicit) => Some((implicit.mode, implicit.filters)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        implicit_modes,
+        [(ImplicitMode::Mul, 18), (ImplicitMode::Add, 32)]
+    );
+
+    // the implicit_mul tensor scales the channels of the head convolution
+    let model = ModelBase::from_config(&config)?;
+    match &model.layers[&3] {
+        LayerBase::Implicit(implicit) => {
+            assert_eq!(implicit.output_shape, [1, 1, 18]);
+            assert_eq!(implicit.config.mean.raw(), 1.0);
+        }
+        _ => panic!("expect an implicit layer"),
+    }
+    match &model.layers[&5] {
+        LayerBase::Yolo(yolo) => assert_eq!(yolo.inout_shape, [16, 16, 18]),
+        _ => panic!("expect a yolo layer"),
+    }
+
+    // the sections keep their spelling when saved
+    let saved = config.to_cfg_string()?;
+    assert!(saved.contains("[implicit_mul]") && saved.contains("[implicit_add]"));
+    assert!(!saved.contains("[implicit]"));
+    let reloaded: DarknetConfig = saved.parse()?;
+    assert_eq!(config, reloaded);
+
+    Ok(())
+}
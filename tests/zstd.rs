@@ -0,0 +1,59 @@
+#![cfg(feature = "zstd")]
+
+use anyhow::Result;
+use darknet_config::{DarknetConfig, DarknetModel};
+use std::fs::{self, File};
+
+#[test]
+fn zstd_weights() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("darknet-config-zstd-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let config: DarknetConfig = "\
+[net]
+width=32
+height=32
+channels=3
+
+[convolutional]
+filters=18
+size=1
+stride=1
+pad=1
+activation=linear
+
+[yolo]
+mask=0,1,2
+anchors=10,13, 16,30, 33,23
+classes=1
+num=3
+"
+    .parse()?;
+    let mut model = DarknetModel::from_config(&config)?;
+    model.base.seen = 64;
+
+    let raw_file = dir.join("model.weights");
+    let zst_file = dir.join("model.weights.zst");
+    model.save_weights(&raw_file)?;
+    model.save_weights(&zst_file)?;
+    let raw = fs::read(&raw_file)?;
+    let compressed = fs::read(&zst_file)?;
+    assert_eq!(compressed[..4], [0x28, 0xb5, 0x2f, 0xfd]);
+    assert!(compressed.len() < raw.len());
+
+    // compressed files are detected by content on loading, from a path or a stream
+    let mut loaded = DarknetModel::from_config(&config)?;
+    loaded.load_weights(&zst_file)?;
+    assert_eq!(loaded.base.seen, 64);
+    let mut written = vec![];
+    loaded.write_weights(&mut written)?;
+    assert_eq!(written, raw);
+
+    let mut streamed = DarknetModel::from_config(&config)?;
+    streamed.read_weights(File::open(&zst_file)?)?;
+    let mut written = vec![];
+    streamed.write_weights_zstd(&mut written, 19)?;
+    assert_eq!(zstd::decode_all(written.as_slice())?, raw);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}